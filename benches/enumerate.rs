@@ -0,0 +1,117 @@
+//! Enumerate, parse and render bundled `lspci -xxxx` dumps
+//!
+//! `cargo bench --bench enumerate`
+
+use std::{fmt::Write, fs};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pcitool::{
+    access::{dump::Dump, Access, AccessMethod},
+    names::{index, Names},
+    view::lspci::basic::{View, ViewArgs},
+};
+
+const MACHINE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/machine");
+const PCI_IDS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids");
+
+/// Machine name and `out.xxxx.txt` content
+fn dumps() -> Vec<(String, String)> {
+    let mut dumps = fs::read_dir(MACHINE_PATH)
+        .unwrap()
+        .flatten()
+        .filter_map(|entry| {
+            let content = fs::read_to_string(entry.path().join("out.xxxx.txt")).ok()?;
+            Some((entry.file_name().to_string_lossy().into_owned(), content))
+        })
+        .collect::<Vec<_>>();
+    dumps.sort();
+    dumps
+}
+
+/// `lspci -nnvvv` output of all dump devices
+fn render(access: &Access, names: &Names) -> String {
+    let Access::Dump(dump) = access else {
+        unreachable!()
+    };
+    let vds = &names.vendor_device_subsystem();
+    let cc = &names.class_code();
+    let args = &ViewArgs {
+        verbose: 3,
+        kernel: false,
+        always_domain_number: false,
+        as_numbers: 2,
+        bus_centric: false,
+        show_power: false,
+        name_width: Default::default(),
+        vds,
+        cc,
+        access,
+    };
+    let mut devices = dump.iter().flatten().collect::<Vec<_>>();
+    devices.sort();
+    let mut out = String::new();
+    for data in devices {
+        write!(out, "{}", View { data, args }).unwrap();
+    }
+    out
+}
+
+fn enumerate(c: &mut Criterion) {
+    let names = Names::init_pciids(PCI_IDS_PATH).unwrap();
+    let mut group = c.benchmark_group("enumerate");
+    for (machine, content) in dumps() {
+        let access = Access::Dump(Dump::new(&content));
+        group.bench_with_input(BenchmarkId::new("parse", &machine), &access, |b, access| {
+            let Access::Dump(dump) = access else {
+                unreachable!()
+            };
+            b.iter(|| dump.iter().count())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("parse_render", &machine),
+            &access,
+            |b, access| b.iter(|| render(access, &names)),
+        );
+    }
+    group.finish();
+}
+
+fn names(c: &mut Criterion) {
+    c.bench_function("names/init_pciids", |b| {
+        b.iter(|| Names::init_pciids(PCI_IDS_PATH).unwrap())
+    });
+    let names = Names::init_pciids(PCI_IDS_PATH).unwrap();
+    // Every view takes its own copy of the tables
+    c.bench_function("names/tables", |b| {
+        b.iter(|| (names.vendor_device_subsystem(), names.class_code()))
+    });
+    let vds = names.vendor_device_subsystem();
+    c.bench_function("names/lookup", |b| {
+        b.iter(|| {
+            (
+                vds.lookup(0x8086, None, None),
+                vds.lookup(0x8086, 0x9dc8, None),
+                vds.lookup(0x8086, 0x9dc8, (0x1043, 0x16a1)),
+            )
+        })
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pci.ids.idx");
+    index::compile(PCI_IDS_PATH, &path).unwrap();
+    c.bench_function("names/init_index", |b| {
+        b.iter(|| Names::init_index(&path).unwrap())
+    });
+    let vds = Names::init_index(&path).unwrap().vendor_device_subsystem();
+    c.bench_function("names/lookup_index", |b| {
+        b.iter(|| {
+            (
+                vds.lookup(0x8086, None, None),
+                vds.lookup(0x8086, 0x9dc8, None),
+                vds.lookup(0x8086, 0x9dc8, (0x1043, 0x16a1)),
+            )
+        })
+    });
+}
+
+criterion_group!(benches, enumerate, names);
+criterion_main!(benches);
@@ -0,0 +1,63 @@
+/*
+ * pcitool C interface
+ *
+ * Build shared library (target/release/libpcitool.so):
+ *   cargo build --lib --release --features ffi
+ */
+
+#ifndef PCITOOL_H
+#define PCITOOL_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque handles, pci_access may be shared between threads */
+typedef struct PciAccess pci_access;
+typedef struct PciIter pci_iter;
+typedef struct PciDevice pci_device;
+
+/* pci_device_field() fields */
+#define PCI_FIELD_DOMAIN		0
+#define PCI_FIELD_BUS			1
+#define PCI_FIELD_DEVICE		2
+#define PCI_FIELD_FUNCTION		3
+#define PCI_FIELD_VENDOR_ID		4
+#define PCI_FIELD_DEVICE_ID		5
+#define PCI_FIELD_CLASS			6	/* base << 16 | sub << 8 | prog-if */
+#define PCI_FIELD_REVISION_ID		7
+#define PCI_FIELD_HEADER_TYPE		8
+#define PCI_FIELD_SUBSYS_VENDOR_ID	9
+#define PCI_FIELD_SUBSYS_ID		10
+#define PCI_FIELD_IRQ			11
+#define PCI_FIELD_NUMA_NODE		12
+
+/* Return codes */
+#define PCI_OK		0
+#define PCI_EINVAL	-1
+#define PCI_ENODATA	-2
+
+/* Access */
+pci_access *pci_access_init(void);
+pci_access *pci_access_init_dump(const char *path);
+void pci_access_free(pci_access *access);
+
+/* Enumeration (sorted by address, unparsable devices are skipped) */
+pci_iter *pci_iter_new(const pci_access *access);
+pci_device *pci_iter_next(pci_iter *iter);
+void pci_iter_free(pci_iter *iter);
+
+/* Device */
+void pci_device_free(pci_device *device);
+int pci_device_field(const pci_device *device, uint32_t field, uint32_t *value);
+int pci_device_address(const pci_device *device, char *buf, size_t len);
+int pci_device_capabilities(const pci_device *device, uint8_t *ids, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PCITOOL_H */
@@ -1,18 +1,52 @@
-use std::{collections::HashMap, io, iter, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use thiserror::Error;
 
-use crate::device::{address::ParseAddressError, Address, Device};
+use pcics::header::{Bridge, Cardbus, HeaderType};
+
+#[cfg(feature = "margining")]
+use crate::device::margining::{
+    self, LaneEye, LaneRegisters, MarginSettings, MarginingError, MarginingPort,
+};
+use crate::device::{
+    address::ParseAddressError,
+    reset::{ResetError, ResetKind, ResetStep},
+    sizing, Address, Device, Resource, ResourceEntry,
+};
+use crate::topology::{
+    vga::{BootVga, BootVgaSource},
+    Topology,
+};
 
 pub mod dump;
 use dump::{Dump, DumpError};
 
+pub mod ecam;
+use ecam::Ecam;
+
+pub mod fill;
+use fill::FillFlags;
+
 pub mod linux_procfs;
 use linux_procfs::LinuxProcfs;
 
 pub mod linux_sysfs;
 use linux_sysfs::LinuxSysfs;
 
+pub mod monitor;
+
+pub mod retry;
+
+pub mod snapshot;
+
 #[derive(Debug, Error)]
 pub enum AccessError {
     #[error("No addressed device {0}")]
@@ -28,10 +62,29 @@ pub enum AccessError {
     File { path: PathBuf, source: io::Error },
     #[error("unable to parse configuration space data")]
     ConfigurationSpace,
+    #[error("configuration space write is not supported by this access method")]
+    ReadOnly,
     #[error(transparent)]
     Dump(#[from] DumpError),
+    #[error("ecam access: {0}")]
+    Ecam(#[from] ecam::EcamError),
     #[error("linux-sysfs access {0}")]
     LinuxSysfs(#[from] linux_sysfs::LinuxSysfsError),
+    #[error(transparent)]
+    Reset(#[from] ResetError),
+    #[cfg(feature = "margining")]
+    #[error(transparent)]
+    Margining(#[from] MarginingError),
+    #[error("unknown access method '{0}'")]
+    Method(String),
+    #[error("{0} is not a configured bridge")]
+    NotBridge(Address),
+    /// Device files vanished while reading, e.g. device was hot-removed during enumeration
+    #[error("device {0} is gone")]
+    DeviceGone(Address),
+    /// Configuration space read as all ones after every retry
+    #[error("device {address} reads all ones after {attempts} attempts")]
+    Unstable { address: Address, attempts: u32 },
 }
 
 impl PartialEq for AccessError {
@@ -57,20 +110,135 @@ impl Eq for AccessError {}
 pub type Result<T> = core::result::Result<T, AccessError>;
 pub type Slots = HashMap<Address, String>;
 
+/// Configuration space access method.
+///
+/// Access is `Send + Sync` and may be shared between threads, e.g. behind [Arc](std::sync::Arc)
+/// in a service: backends keep no mutable state, files and mappings are opened per read.
+/// Iterators are `Send` too, so enumeration may run on a worker thread.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Access {
     Void(Void),
     Dump(Dump),
     LinuxSysfs(LinuxSysfs),
     LinuxProcfs(LinuxProcfs),
+    Ecam(Ecam),
+}
+
+/// Access method tried while probing, with default parameters
+///
+/// Intel configuration mechanisms and dumps are not probed: the former are not
+/// implemented and the latter require a file name. ECAM requires root, so it is
+/// not in the default order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    LinuxSysfs,
+    LinuxProcfs,
+    Ecam,
+    Void,
+}
+
+impl Method {
+    /// Every method compiled in
+    pub const ALL: [Self; 4] = [Self::LinuxSysfs, Self::LinuxProcfs, Self::Ecam, Self::Void];
+    /// Order used by [Access::init]
+    pub const DEFAULT_ORDER: [Self; 3] = [Self::LinuxSysfs, Self::LinuxProcfs, Self::Void];
+    pub fn init(&self) -> Result<Access> {
+        match self {
+            Self::LinuxSysfs => LinuxSysfs::default().access(),
+            Self::LinuxProcfs => LinuxProcfs::init(LinuxProcfs::PATH).map(Into::into),
+            Self::Ecam => Ecam::init(Ecam::MCFG_PATH).map(Into::into),
+            Self::Void => Void::init().map(Into::into),
+        }
+    }
+    /// Initialize and count readable devices
+    pub fn probe(&self) -> Probe {
+        let result = self
+            .init()
+            .map(|access| access.iter().filter(|result| result.is_ok()).count());
+        Probe {
+            method: *self,
+            result,
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = AccessError;
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "linux-sysfs" => Ok(Self::LinuxSysfs),
+            "linux-proc" => Ok(Self::LinuxProcfs),
+            "ecam" => Ok(Self::Ecam),
+            "void" => Ok(Self::Void),
+            _ => Err(AccessError::Method(s.into())),
+        }
+    }
+}
+
+/// Same names as `-A` option of lspci
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LinuxSysfs => write!(f, "linux-sysfs"),
+            Self::LinuxProcfs => write!(f, "linux-proc"),
+            Self::Ecam => write!(f, "ecam"),
+            Self::Void => write!(f, "void"),
+        }
+    }
+}
+
+/// Access method diagnostics: number of devices found or initialization error
+#[derive(Debug, PartialEq, Eq)]
+pub struct Probe {
+    pub method: Method,
+    pub result: Result<usize>,
+}
+
+impl fmt::Display for Probe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(1) => write!(f, "{}: found 1 device", self.method),
+            Ok(n) => write!(f, "{}: found {} devices", self.method, n),
+            Err(err) => write!(f, "{}: {}", self.method, err),
+        }
+    }
 }
 
 impl Access {
+    /// Method name as accepted by `-A`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Void(_) => "void",
+            Self::Dump(_) => "dump",
+            Self::LinuxSysfs(_) => "linux-sysfs",
+            Self::LinuxProcfs(_) => "linux-proc",
+            Self::Ecam(_) => "ecam",
+        }
+    }
+    /// First available method of [Method::DEFAULT_ORDER]
     pub fn init() -> Result<Self> {
-        LinuxSysfs::default()
-            .access()
-            .or_else(|_| LinuxProcfs::init(LinuxProcfs::PATH).map(Into::into))
-            .or_else(|_| Void::init().map(Into::into))
+        Self::probe(&Method::DEFAULT_ORDER)
+    }
+    /// First method of `order` initialized successfully, the last error otherwise
+    pub fn probe(order: &[Method]) -> Result<Self> {
+        let mut result = Err(AccessError::Platform);
+        for method in order {
+            result = method.init();
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+    /// Diagnostics of every method in default order
+    pub fn probe_all() -> Vec<Probe> {
+        Method::DEFAULT_ORDER.iter().map(Method::probe).collect()
+    }
+    /// Primary display device with the way it was found, see [BootVga::find]
+    pub fn boot_vga(&self) -> Option<(Device, BootVgaSource)> {
+        let devices = self.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let boot_vga = BootVga::find(&Topology::new(&devices))?;
+        Some((boot_vga.device.clone(), boot_vga.source))
     }
     pub fn device(&self, addr: Address) -> Result<Device> {
         match self {
@@ -78,34 +246,113 @@ impl Access {
             Self::Dump(a) => a.device(addr),
             Self::LinuxSysfs(a) => a.device(addr),
             Self::LinuxProcfs(a) => a.device(addr),
+            Self::Ecam(a) => a.device(addr),
         }
     }
-    pub fn scan(&self) -> Box<dyn Iterator<Item = Result<Address>> + '_> {
+    pub fn scan(&self) -> Box<dyn Iterator<Item = Result<Address>> + Send + '_> {
         match self {
             Self::Void(a) => Box::new(a.scan()),
             Self::Dump(a) => Box::new(a.scan()),
             Self::LinuxSysfs(a) => Box::new(a.scan()),
             Self::LinuxProcfs(a) => Box::new(a.scan()),
+            Self::Ecam(a) => Box::new(a.scan()),
         }
     }
-    pub fn iter(&self) -> Box<dyn Iterator<Item = Result<Device>> + '_> {
+    pub fn iter(&self) -> Box<dyn Iterator<Item = Result<Device>> + Send + '_> {
         match self {
             Self::Void(a) => Box::new(a.iter()),
             Self::Dump(a) => Box::new(a.iter()),
             Self::LinuxSysfs(a) => Box::new(a.iter()),
             Self::LinuxProcfs(a) => Box::new(a.iter()),
+            Self::Ecam(a) => Box::new(a.iter()),
         }
     }
+    /// Functions on buses from secondary to subordinate bus of `bridge` in the same domain.
+    /// Addresses are scanned first, so configuration space of other functions is not read
+    pub fn iter_under(
+        &self,
+        bridge: Address,
+    ) -> Result<Box<dyn Iterator<Item = Result<Device>> + Send + '_>> {
+        let buses = match self.device(bridge.clone())?.header.header_type {
+            HeaderType::Bridge(Bridge {
+                secondary_bus_number,
+                subordinate_bus_number,
+                ..
+            }) => secondary_bus_number..=subordinate_bus_number,
+            HeaderType::Cardbus(Cardbus {
+                cardbus_bus_number,
+                subordinate_bus_number,
+                ..
+            }) => cardbus_bus_number..=subordinate_bus_number,
+            _ => return Err(AccessError::NotBridge(bridge)),
+        };
+        if buses.is_empty() || *buses.start() == 0 {
+            return Err(AccessError::NotBridge(bridge));
+        }
+        let domain = bridge.domain;
+        let under =
+            move |address: &Address| address.domain == domain && buses.contains(&address.bus);
+        let iter: Box<dyn Iterator<Item = _> + Send> = match self {
+            // Dump is parsed as a whole anyway
+            Self::Dump(a) => Box::new(
+                a.iter()
+                    .filter(move |result| result.as_ref().map_or(true, |d| under(&d.address))),
+            ),
+            _ => Box::new(
+                self.scan()
+                    .filter(move |result| result.as_ref().map_or(true, &under))
+                    .map(|result| result.and_then(|address| self.device(address))),
+            ),
+        };
+        Ok(iter)
+    }
     pub fn vital_product_data(&self, addr: Address) -> io::Result<Vec<u8>> {
         match self {
             Self::Void(a) => a.vital_product_data(addr),
             Self::Dump(a) => a.vital_product_data(addr),
             Self::LinuxSysfs(a) => a.vital_product_data(addr),
             Self::LinuxProcfs(a) => a.vital_product_data(addr),
+            Self::Ecam(a) => a.vital_product_data(addr),
+        }
+    }
+    /// Expansion ROM image
+    pub fn expansion_rom(&self, addr: Address) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Void(a) => a.expansion_rom(addr),
+            Self::Dump(a) => a.expansion_rom(addr),
+            Self::LinuxSysfs(a) => a.expansion_rom(addr),
+            Self::LinuxProcfs(a) => a.expansion_rom(addr),
+            Self::Ecam(a) => a.expansion_rom(addr),
+        }
+    }
+    /// Read `len` bytes of configuration space at `offset`
+    pub fn read(&self, addr: Address, offset: usize, len: usize) -> Result<Vec<u8>> {
+        match self {
+            Self::Void(a) => a.read(addr, offset, len),
+            Self::Dump(a) => a.read(addr, offset, len),
+            Self::LinuxSysfs(a) => a.read(addr, offset, len),
+            Self::LinuxProcfs(a) => a.read(addr, offset, len),
+            Self::Ecam(a) => a.read(addr, offset, len),
+        }
+    }
+    /// Write `data` to configuration space at `offset`
+    pub fn write(&self, addr: Address, offset: usize, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Void(a) => a.write(addr, offset, data),
+            Self::Dump(a) => a.write(addr, offset, data),
+            Self::LinuxSysfs(a) => a.write(addr, offset, data),
+            Self::LinuxProcfs(a) => a.write(addr, offset, data),
+            Self::Ecam(a) => a.write(addr, offset, data),
         }
     }
 }
 
+// Keep backends shareable: a cache added to one of them needs synchronization
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Access>();
+};
+
 impl Default for Access {
     fn default() -> Self {
         Self::Void(Void)
@@ -136,6 +383,12 @@ impl From<LinuxProcfs> for Access {
     }
 }
 
+impl From<Ecam> for Access {
+    fn from(a: Ecam) -> Self {
+        Self::Ecam(a)
+    }
+}
+
 pub trait AccessMethod<'a> {
     type Scan: Iterator<Item = Result<Address>>;
     type Iter: Iterator<Item = Result<Device>>;
@@ -153,6 +406,209 @@ pub trait AccessMethod<'a> {
     fn vital_product_data(&'a self, _: Address) -> io::Result<Vec<u8>> {
         Err(io::ErrorKind::Other.into())
     }
+    fn expansion_rom(&'a self, _: Address) -> io::Result<Vec<u8>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+    fn read(&'a self, _: Address, _offset: usize, _len: usize) -> Result<Vec<u8>> {
+        Err(AccessError::Platform)
+    }
+    fn write(&'a self, _: Address, _offset: usize, _data: &[u8]) -> Result<()> {
+        Err(AccessError::ReadOnly)
+    }
+}
+
+impl Device {
+    /// Run [reset sequence](Device::reset_steps), every update reads current register value first
+    pub fn reset(&self, access: &Access, kind: ResetKind) -> Result<()> {
+        for step in self.reset_steps(kind)? {
+            match step {
+                ResetStep::Update {
+                    offset,
+                    mask,
+                    value,
+                } => {
+                    let bytes = access.read(self.address.clone(), offset, 2)?;
+                    let current = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    let new = (current & !mask) | (value & mask);
+                    access.write(self.address.clone(), offset, &new.to_le_bytes())?;
+                }
+                ResetStep::Delay(duration) => std::thread::sleep(duration),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Margining Lane Control and Status through access method
+#[cfg(feature = "margining")]
+struct AccessLaneRegisters<'a> {
+    access: &'a Access,
+    address: Address,
+    port: MarginingPort,
+}
+
+#[cfg(feature = "margining")]
+impl LaneRegisters for AccessLaneRegisters<'_> {
+    type Error = AccessError;
+    fn write_control(&mut self, lane: usize, control: u16) -> Result<()> {
+        let offset = self.port.lane_control_offset(lane);
+        self.access
+            .write(self.address.clone(), offset, &control.to_le_bytes())
+    }
+    fn read_status(&mut self, lane: usize) -> Result<u16> {
+        let offset = self.port.lane_status_offset(lane);
+        let bytes = self.access.read(self.address.clone(), offset, 2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    fn wait(&mut self, duration: std::time::Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+#[cfg(feature = "margining")]
+impl Device {
+    /// [Margin](margining::margin) `lane` of downstream port link, Margining Lane Control is
+    /// left with No Command
+    pub fn margin_lane(
+        &self,
+        access: &Access,
+        lane: usize,
+        settings: &MarginSettings,
+    ) -> Result<LaneEye> {
+        let mut registers = AccessLaneRegisters {
+            access,
+            address: self.address.clone(),
+            port: self.margining_lane_port(lane)?,
+        };
+        margining::margin(&mut registers, lane, settings)
+    }
+}
+
+impl Device {
+    /// Read OS provided information selected by `flags`, like libpci `pci_fill_info`. Fields
+    /// are read again on every call. Returns flags supported by access method, fields of
+    /// other flags are kept as is
+    pub fn fill(&mut self, flags: FillFlags, access: &Access) -> FillFlags {
+        match access {
+            Access::LinuxSysfs(a) => a.fill(self, flags),
+            Access::LinuxProcfs(a) => a.fill(self, flags),
+            _ => FillFlags::NONE,
+        }
+    }
+}
+
+impl Device {
+    /// Size BARs and expansion ROM by writing all-ones to registers. Memory and I/O decoding
+    /// is disabled while sizing, Command register and BARs are restored even on error. VF BARs
+    /// of SR-IOV capability are sized the same way with VF MSE cleared.
+    pub fn size_bars(&self, access: &Access) -> Result<Resource> {
+        let bytes = access.read(self.address.clone(), sizing::COMMAND, 2)?;
+        let command = u16::from_le_bytes([bytes[0], bytes[1]]);
+        let disabled = command & !sizing::COMMAND_DECODE;
+        access.write(
+            self.address.clone(),
+            sizing::COMMAND,
+            &disabled.to_le_bytes(),
+        )?;
+        let result = self.size_registers(access);
+        let restored = access.write(
+            self.address.clone(),
+            sizing::COMMAND,
+            &command.to_le_bytes(),
+        );
+        let mut resource = result?;
+        restored?;
+        if let Some((offset, sr_iov)) = self.sr_iov_offset() {
+            resource.iov_entries = self.size_vf_bars(access, offset, sr_iov.total_vfs)?;
+        }
+        Ok(resource)
+    }
+    fn size_registers(&self, access: &Access) -> Result<Resource> {
+        let (bars, rom) = self.sizing_registers();
+        let (original, sized): (Vec<_>, Vec<_>) = bars
+            .step_by(4)
+            .map(|offset| self.size_register(access, offset, sizing::BAR_SIZING_VALUE))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .unzip();
+        let rom = rom
+            .map(|offset| self.size_register(access, offset, sizing::ROM_SIZING_VALUE))
+            .transpose()?;
+        Ok(sizing::resource(&original, &sized, rom))
+    }
+    fn size_vf_bars(
+        &self,
+        access: &Access,
+        offset: usize,
+        total_vfs: u16,
+    ) -> Result<[ResourceEntry; 6]> {
+        let control = offset + sizing::SRIOV_CONTROL;
+        let bytes = access.read(self.address.clone(), control, 2)?;
+        let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+        let disabled = value & !sizing::SRIOV_CONTROL_VF_MSE;
+        access.write(self.address.clone(), control, &disabled.to_le_bytes())?;
+        let bars = offset + sizing::SRIOV_VF_BARS;
+        let result = (bars..bars + 6 * 4)
+            .step_by(4)
+            .map(|offset| self.size_register(access, offset, sizing::BAR_SIZING_VALUE))
+            .collect::<Result<Vec<_>>>();
+        let restored = access.write(self.address.clone(), control, &value.to_le_bytes());
+        let (original, sized): (Vec<_>, Vec<_>) = result?.into_iter().unzip();
+        restored.map(|_| sizing::iov_entries(&original, &sized, total_vfs))
+    }
+    /// Register value before and after writing `value`, the original value is written back
+    fn size_register(&self, access: &Access, offset: usize, value: u32) -> Result<(u32, u32)> {
+        let read = || {
+            access
+                .read(self.address.clone(), offset, 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+        let original = read()?;
+        access.write(self.address.clone(), offset, &value.to_le_bytes())?;
+        let sized = read();
+        access.write(self.address.clone(), offset, &original.to_le_bytes())?;
+        Ok((original, sized?))
+    }
+}
+
+/// Device file read error, [AccessError::DeviceGone] on ENOENT and ENODEV
+fn device_file_error(address: &Address, path: PathBuf, source: io::Error) -> AccessError {
+    let is_gone =
+        source.kind() == io::ErrorKind::NotFound || source.raw_os_error() == Some(libc::ENODEV);
+    if is_gone {
+        AccessError::DeviceGone(address.clone())
+    } else {
+        AccessError::File { path, source }
+    }
+}
+
+/// Read from configuration space file exported by OS
+fn read_config(path: &Path, offset: usize, len: usize) -> Result<Vec<u8>> {
+    let file_error = |source| AccessError::File {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut file = File::open(path).map_err(file_error)?;
+    let mut buf = vec![0; len];
+    file.seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| file.read_exact(&mut buf))
+        .map_err(file_error)?;
+    Ok(buf)
+}
+
+/// Write to configuration space file exported by OS
+fn write_config(path: &Path, offset: usize, data: &[u8]) -> Result<()> {
+    let file_error = |source| AccessError::File {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(file_error)?;
+    file.seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| file.write_all(data))
+        .map_err(file_error)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -174,3 +630,126 @@ impl<'a> AccessMethod<'a> for Void {
         iter::empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+
+    #[test]
+    fn iter_under() {
+        let dump = Dump::fixture();
+        let access = Access::from(dump);
+        // Root port with NVMe SSD on bus 04
+        let result = access
+            .iter_under("00:1d.4".parse().unwrap())
+            .unwrap()
+            .map(|device| device.unwrap().address.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["0000:04:00.0"], result);
+        assert!(matches!(
+            access.iter_under("00:1f.3".parse().unwrap()),
+            Err(AccessError::NotBridge(_))
+        ));
+    }
+
+    #[test]
+    fn boot_vga() {
+        let dump = Dump::init(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/machine/ec8a5fc/out.xxxx.txt"
+        ))
+        .unwrap();
+        let (device, source) = Access::from(dump).boot_vga().unwrap();
+        assert_eq!("0000:03:00.0", device.address.to_string());
+        assert_eq!(BootVgaSource::Routed, source);
+        assert_eq!(None, Access::default().boot_vga());
+    }
+
+    #[test]
+    fn probe_order() {
+        let order = "linux-proc,void"
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Method>>>()
+            .unwrap();
+        assert_eq!(vec![Method::LinuxProcfs, Method::Void], order);
+        assert!(matches!(
+            "intel-conf1".parse::<Method>(),
+            Err(AccessError::Method(name)) if name == "intel-conf1"
+        ));
+        assert_eq!(Access::Void(Void), Access::probe(&[Method::Void]).unwrap());
+        assert_eq!("void", Access::probe(&[Method::Void]).unwrap().name());
+        assert!(matches!(Access::probe(&[]), Err(AccessError::Platform)));
+        assert_eq!("void: found 0 devices", Method::Void.probe().to_string());
+    }
+
+    #[test]
+    fn size_bars_restores_registers() {
+        let dir = tempfile::tempdir().unwrap();
+        let dev_dir = dir.path().join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(&dev_dir).unwrap();
+        let mut cs = [0u8; 4096];
+        cs[0x04] = 0x06;
+        cs[0x10..0x14].copy_from_slice(&0xb4418000u32.to_le_bytes());
+        // SR-IOV with VF Enable and VF MSE, 64-bit VF BAR 0
+        cs[0x100..0x10a].copy_from_slice(&[0x10, 0x00, 0x01, 0x00, 0, 0, 0, 0, 0x09, 0x00]);
+        cs[0x10e] = 0x08;
+        cs[0x124..0x12c].copy_from_slice(&0x6000_0000_0000_000cu64.to_le_bytes());
+        fs::write(dev_dir.join("config"), cs).unwrap();
+
+        let access = LinuxSysfs::new(dir.path()).access().unwrap();
+        let device = access.device("00:1f.3".parse().unwrap()).unwrap();
+        // Plain file keeps written values: BARs look not implemented
+        let resource = device.size_bars(&access).unwrap();
+        assert_eq!(Resource::default().entries, resource.entries);
+        assert_eq!(Resource::default().iov_entries, resource.iov_entries);
+        assert_eq!(cs.to_vec(), fs::read(dev_dir.join("config")).unwrap());
+        assert!(matches!(
+            device.size_bars(&Access::default()),
+            Err(AccessError::Platform)
+        ));
+    }
+
+    #[test]
+    fn shared_between_threads() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/machine/23c7a39/vfs/sys/bus/pci"
+        );
+        let access = std::sync::Arc::new(LinuxSysfs::new(path).access().unwrap());
+        let sample = access.iter().collect::<Result<Vec<_>>>().unwrap();
+        assert!(!sample.is_empty());
+        std::thread::scope(|scope| {
+            let workers = (0..4)
+                .map(|_| {
+                    let access = access.clone();
+                    scope.spawn(move || access.iter().collect::<Result<Vec<_>>>().unwrap())
+                })
+                .collect::<Vec<_>>();
+            // Iterator started here and finished on another thread
+            let mut iter = access.iter();
+            let first = iter.next().unwrap().unwrap();
+            let rest = scope.spawn(move || iter.map(Result::unwrap).collect::<Vec<_>>());
+            for device in &sample {
+                let access = &access;
+                scope.spawn(move || {
+                    let address = device.address.clone();
+                    let bytes = access.read(address.clone(), 0, 2).unwrap();
+                    assert_eq!(device, &access.device(address).unwrap());
+                    assert_eq!(
+                        device.header.vendor_id,
+                        u16::from_le_bytes([bytes[0], bytes[1]])
+                    );
+                });
+            }
+            for worker in workers {
+                assert_eq!(sample, worker.join().unwrap());
+            }
+            let mut devices = vec![first];
+            devices.extend(rest.join().unwrap());
+            assert_eq!(sample, devices);
+        });
+    }
+}
@@ -50,6 +50,15 @@ impl Dump {
             .map(|s| Self { content: s })
             .map_err(|source| AccessError::File { path, source })
     }
+    /// Whole machine `-vvvxxxx` dump of the 8086:9dc8 laptop shared by unit tests
+    #[cfg(test)]
+    pub(crate) fn fixture() -> Self {
+        Self::init(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+        ))
+        .unwrap()
+    }
 }
 
 impl<'a> AccessMethod<'a> for Dump {
@@ -63,6 +72,17 @@ impl<'a> AccessMethod<'a> for Dump {
     fn iter(&'a self) -> Self::Iter {
         Iter::new(self.content.lines())
     }
+    fn read(&'a self, addr: Address, offset: usize, len: usize) -> super::Result<Vec<u8>> {
+        let mut iter = Iter::new(self.content.lines());
+        let (_, bytes) = std::iter::from_fn(|| iter.next_raw())
+            .flatten()
+            .find(|(address, _)| address == &addr)
+            .ok_or(AccessError::NoAddress(addr))?;
+        bytes
+            .get(offset..offset + len)
+            .map(<[u8]>::to_vec)
+            .ok_or(AccessError::ConfigurationSpace)
+    }
 }
 
 #[derive(Error, Clone, Debug, PartialEq, Eq)]
@@ -157,9 +177,9 @@ impl<'a> Iter<'a> {
     }
 }
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = super::Result<Device>;
-    fn next(&mut self) -> Option<Self::Item> {
+impl<'a> Iter<'a> {
+    /// Address and raw configuration space of the next device
+    fn next_raw(&mut self) -> Option<super::Result<(Address, Vec<u8>)>> {
         let AddressLine(address) = self.lines.find_map(|(_, line)| line.parse().ok())?;
 
         let mut buf = [0u8; 4096];
@@ -195,10 +215,20 @@ impl<'a> Iterator for Iter<'a> {
             64..=255 => ExtendedConfigurationSpace::OFFSET,
             _ => ConfigurationSpace::SIZE,
         };
-        let result = buf[..end]
-            .try_into()
-            .map(|cs| Device::new(address, cs))
-            .map_err(|_| AccessError::ConfigurationSpace);
+        Some(Ok((address, buf[..end].to_vec())))
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = super::Result<Device>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_raw()?.and_then(|(address, bytes)| {
+            bytes
+                .as_slice()
+                .try_into()
+                .map(|cs| Device::new(address, cs))
+                .map_err(|_| AccessError::ConfigurationSpace)
+        });
         Some(result)
     }
 }
@@ -398,4 +428,24 @@ mod tests {
         let result = scan.next();
         assert_eq!(Some(Ok(addr)), result);
     }
+
+    #[test]
+    fn read() {
+        let data = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:2030/out.vxxxx.txt"
+        ));
+        let dump = Dump::new(data);
+        let sample = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:2030/config"
+        ));
+        let addr: Address = "ae:00.0".parse().unwrap();
+        assert_eq!(
+            sample[0x100..0x108].to_vec(),
+            dump.read(addr, 0x100, 8).unwrap()
+        );
+        let result = dump.read("ae:00.1".parse().unwrap(), 0, 2);
+        assert!(matches!(result, Err(AccessError::NoAddress(_))));
+    }
 }
@@ -0,0 +1,525 @@
+//! PCI Express Enhanced Configuration Access Mechanism: configuration spaces of every function
+//! mapped to physical memory, 4096 bytes each. Regions are read from ACPI MCFG table or given
+//! explicitly, memory is mapped from `/dev/mem`. Requires root and a kernel allowing access to
+//! the region (`iomem=relaxed` with `CONFIG_STRICT_DEVMEM`). Useful on bare-metal and bring-up
+//! systems where OS interfaces are missing or restricted.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::io::AsRawFd,
+    path::PathBuf,
+    ptr,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+use super::{AccessError, AccessMethod};
+use crate::device::{Address, ConfigurationSpace, Device};
+
+/// Configuration space size of a single function
+const FUNCTION_SIZE: usize = 4096;
+/// Device and function number bits of a single bus
+const BUS_SIZE: usize = 32 * 8 * FUNCTION_SIZE;
+/// ACPI table header and reserved field
+const MCFG_ENTRIES_OFFSET: usize = 44;
+const MCFG_ENTRY_SIZE: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum EcamError {
+    #[error("MCFG table has wrong signature or length")]
+    Mcfg,
+    #[error("no ECAM region for {0}")]
+    NoRegion(Address),
+    #[error("unable to map {address:#x}: {source}")]
+    Map { address: u64, source: io::Error },
+    #[error("region format is [<domain>[:<bus>[-<bus>]]:]<address>")]
+    ParseRegion,
+}
+
+/// Memory mapped configuration space of a segment bus range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// Physical address of the configuration space of `start_bus`
+    pub base: u64,
+    pub segment: u16,
+    pub start_bus: u8,
+    pub end_bus: u8,
+}
+
+impl Region {
+    /// Configuration space allocation entries of ACPI MCFG table
+    pub fn parse_mcfg(bytes: &[u8]) -> Result<Vec<Self>, EcamError> {
+        let length = bytes
+            .get(4..8)
+            .filter(|_| bytes.starts_with(b"MCFG"))
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .filter(|&length| length >= MCFG_ENTRIES_OFFSET && length <= bytes.len())
+            .ok_or(EcamError::Mcfg)?;
+        let regions = bytes[MCFG_ENTRIES_OFFSET..length]
+            .chunks_exact(MCFG_ENTRY_SIZE)
+            .map(|entry| {
+                let mut base = [0; 8];
+                base.copy_from_slice(&entry[..8]);
+                Self {
+                    base: u64::from_le_bytes(base),
+                    segment: u16::from_le_bytes([entry[8], entry[9]]),
+                    start_bus: entry[10],
+                    end_bus: entry[11],
+                }
+            })
+            .collect();
+        Ok(regions)
+    }
+    /// Physical address of the function configuration space
+    pub fn function_address(&self, address: &Address) -> Option<u64> {
+        let in_region = address.domain == u32::from(self.segment)
+            && (self.start_bus..=self.end_bus).contains(&address.bus);
+        in_region.then(|| {
+            self.base
+                + ((address.bus - self.start_bus) as u64) * BUS_SIZE as u64
+                + ((address.device as u64) << 15)
+                + ((address.function as u64) << 12)
+        })
+    }
+}
+
+/// Same format as pciutils `ecam.addrs`, all numbers are hexadecimal:
+/// `[<domain>[:<bus>[-<bus>]]:]<address>`
+impl FromStr for Region {
+    type Err = EcamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = |s: &str| {
+            u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(|_| EcamError::ParseRegion)
+        };
+        let fields = s.split(':').collect::<Vec<_>>();
+        let (segment, buses, base) = match fields[..] {
+            [base] => (None, None, base),
+            [segment, base] => (Some(segment), None, base),
+            [segment, buses, base] => (Some(segment), Some(buses), base),
+            _ => return Err(EcamError::ParseRegion),
+        };
+        let base = hex(base)?;
+        let segment = match segment {
+            Some(segment) => hex(segment)?
+                .try_into()
+                .map_err(|_| EcamError::ParseRegion)?,
+            None => 0,
+        };
+        let (start_bus, end_bus) = match buses {
+            Some(buses) => {
+                let (start, end) = buses.split_once('-').unwrap_or((buses, buses));
+                let bus = |s| hex(s)?.try_into().map_err(|_| EcamError::ParseRegion);
+                (bus(start)?, bus(end)?)
+            }
+            None => (0, 0xff),
+        };
+        Ok(Self {
+            base,
+            segment,
+            start_bus,
+            end_bus,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ecam {
+    mem_path: PathBuf,
+    regions: Vec<Region>,
+}
+
+impl Ecam {
+    pub const MEM_PATH: &'static str = "/dev/mem";
+    pub const MCFG_PATH: &'static str = "/sys/firmware/acpi/tables/MCFG";
+    /// Regions from ACPI MCFG table
+    pub fn init(mcfg_path: impl Into<PathBuf>) -> super::Result<Self> {
+        let path = mcfg_path.into();
+        let bytes = fs::read(&path).map_err(|source| AccessError::File { path, source })?;
+        let regions = Region::parse_mcfg(&bytes)?;
+        Self::new(regions, Self::MEM_PATH)
+    }
+    /// Explicitly given regions, physical memory device should be readable
+    pub fn new(regions: Vec<Region>, mem_path: impl Into<PathBuf>) -> super::Result<Self> {
+        let ecam = Self {
+            mem_path: mem_path.into(),
+            regions,
+        };
+        ecam.open(false)?;
+        Ok(ecam)
+    }
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+    fn open(&self, write: bool) -> super::Result<File> {
+        OpenOptions::new()
+            .read(true)
+            .write(write)
+            .open(&self.mem_path)
+            .map_err(|source| AccessError::File {
+                path: self.mem_path.clone(),
+                source,
+            })
+    }
+    fn map_function(&self, address: &Address, write: bool) -> super::Result<Mapping> {
+        let physical = self
+            .regions
+            .iter()
+            .find_map(|region| region.function_address(address))
+            .ok_or_else(|| EcamError::NoRegion(address.clone()))?;
+        Mapping::new(&self.open(write)?, physical, FUNCTION_SIZE, write)
+    }
+    /// Present functions of every region bus
+    fn addresses(&self) -> Vec<super::Result<Address>> {
+        let file = match self.open(false) {
+            Ok(file) => file,
+            Err(err) => return vec![Err(err)],
+        };
+        let mut result = Vec::new();
+        for region in &self.regions {
+            for bus in region.start_bus..=region.end_bus {
+                let physical = region.base + ((bus - region.start_bus) as u64) * BUS_SIZE as u64;
+                let mapping = match Mapping::new(&file, physical, BUS_SIZE, false) {
+                    Ok(mapping) => mapping,
+                    Err(err) => {
+                        result.push(Err(err));
+                        continue;
+                    }
+                };
+                let present = |devfn: u8| {
+                    let id = mapping.read_u32((devfn as usize) << 12);
+                    !matches!(id & 0xffff, 0 | 0xffff)
+                };
+                let mut devfns = Vec::new();
+                for device in 0..32u8 {
+                    for function in 0..8u8 {
+                        let devfn = device << 3 | function;
+                        if !present(devfn) {
+                            if function == 0 {
+                                break;
+                            }
+                            continue;
+                        }
+                        devfns.push(devfn);
+                        // Header Type multi-function bit
+                        let header_type = mapping.read_u32(((devfn as usize) << 12) + 0x0c);
+                        if function == 0 && header_type & 0x0080_0000 == 0 {
+                            break;
+                        }
+                    }
+                }
+                // ARI device functions are chained by Next Function Number and may have no
+                // function 0 of legacy device numbers 1-31 to be found by multi-function bit
+                if present(0) {
+                    let mut next = ari_next_function(&mapping, 0);
+                    while let Some(devfn) = next.filter(|&n| n != 0 && !devfns.contains(&n)) {
+                        if !present(devfn) {
+                            break;
+                        }
+                        devfns.push(devfn);
+                        next = ari_next_function(&mapping, devfn);
+                    }
+                    devfns.sort_unstable();
+                }
+                let domain = region.segment.into();
+                result.extend(
+                    devfns
+                        .into_iter()
+                        .map(|devfn| Ok(Address::from_devfn(domain, bus, devfn))),
+                );
+            }
+        }
+        result
+    }
+}
+
+/// ARI capability Next Function Number found by extended capabilities walk
+fn ari_next_function(mapping: &Mapping, devfn: u8) -> Option<u8> {
+    let base = (devfn as usize) << 12;
+    let mut pointer = 0x100;
+    // Every extended capability takes at least one dword, so a loop ends here
+    for _ in 0..(FUNCTION_SIZE - 0x100) / 4 {
+        let header = mapping.read_u32(base + pointer);
+        match header & 0xffff {
+            0x000e => return Some((mapping.read_u32(base + pointer + 4) >> 8) as u8),
+            0xffff => return None,
+            _ => (),
+        }
+        pointer = (header >> 20) as usize & !3;
+        // ARI capability header and capability register must fit into function
+        if pointer < 0x100 || pointer + 8 > FUNCTION_SIZE {
+            return None;
+        }
+    }
+    None
+}
+
+impl<'a> AccessMethod<'a> for Ecam {
+    type Scan = std::vec::IntoIter<super::Result<Address>>;
+    type Iter = Iter<'a>;
+    fn device(&self, address: Address) -> super::Result<Device> {
+        let bytes = self.map_function(&address, false)?.read(0, FUNCTION_SIZE);
+        bytes
+            .as_slice()
+            .try_into()
+            .map(|cs: ConfigurationSpace| Device::new(address, cs))
+            .map_err(|_| AccessError::ConfigurationSpace)
+    }
+    fn scan(&'a self) -> Self::Scan {
+        self.addresses().into_iter()
+    }
+    fn iter(&'a self) -> Self::Iter {
+        Iter {
+            ecam: self,
+            scan: self.scan(),
+        }
+    }
+    fn read(&'a self, address: Address, offset: usize, len: usize) -> super::Result<Vec<u8>> {
+        if offset + len > FUNCTION_SIZE {
+            return Err(AccessError::ConfigurationSpace);
+        }
+        Ok(self.map_function(&address, false)?.read(offset, len))
+    }
+    fn write(&'a self, address: Address, offset: usize, data: &[u8]) -> super::Result<()> {
+        if offset + data.len() > FUNCTION_SIZE {
+            return Err(AccessError::ConfigurationSpace);
+        }
+        self.map_function(&address, true)?.write(offset, data);
+        Ok(())
+    }
+}
+
+pub struct Iter<'a> {
+    ecam: &'a Ecam,
+    scan: std::vec::IntoIter<super::Result<Address>>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = super::Result<Device>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let address = self.scan.next()?;
+        Some(address.and_then(|address| self.ecam.device(address)))
+    }
+}
+
+/// Physical memory window, unmapped on drop
+struct Mapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl Mapping {
+    /// `address` should be page aligned, ECAM regions are at least 1MB aligned
+    fn new(file: &File, address: u64, len: usize, write: bool) -> super::Result<Self> {
+        let prot = if write {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                prot,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                address as libc::off_t,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            let source = io::Error::last_os_error();
+            return Err(EcamError::Map { address, source }.into());
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            len,
+        })
+    }
+    /// Configuration space registers are read by aligned dwords
+    fn read_u32(&self, offset: usize) -> u32 {
+        assert!(offset & 3 == 0 && offset + 4 <= self.len);
+        unsafe { ptr::read_volatile(self.ptr.add(offset) as *const u32) }
+    }
+    fn read(&self, offset: usize, len: usize) -> Vec<u8> {
+        let start = offset & !3;
+        let end = (offset + len + 3) & !3;
+        let bytes = (start..end)
+            .step_by(4)
+            .flat_map(|offset| self.read_u32(offset).to_le_bytes())
+            .collect::<Vec<_>>();
+        bytes[offset - start..][..len].to_vec()
+    }
+    /// Naturally aligned dwords and words are written at once, registers wider than a byte
+    /// may not accept partial writes
+    fn write(&self, offset: usize, data: &[u8]) {
+        assert!(offset + data.len() <= self.len);
+        let (mut offset, mut data) = (offset, data);
+        while !data.is_empty() {
+            let ptr = unsafe { self.ptr.add(offset) };
+            let size = match data.len() {
+                len if len >= 4 && offset & 3 == 0 => {
+                    let value = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+                    unsafe { ptr::write_volatile(ptr as *mut u32, value) };
+                    4
+                }
+                len if len >= 2 && offset & 1 == 0 => {
+                    let value = u16::from_le_bytes([data[0], data[1]]);
+                    unsafe { ptr::write_volatile(ptr as *mut u16, value) };
+                    2
+                }
+                _ => {
+                    unsafe { ptr::write_volatile(ptr, data[0]) };
+                    1
+                }
+            };
+            offset += size;
+            data = &data[size..];
+        }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn mcfg() {
+        let mut bytes = vec![0u8; 60];
+        bytes[..4].copy_from_slice(b"MCFG");
+        bytes[4] = 60;
+        bytes[44..52].copy_from_slice(&0xe000_0000u64.to_le_bytes());
+        bytes[54] = 0x00;
+        bytes[55] = 0x3f;
+        let sample = vec![Region {
+            base: 0xe000_0000,
+            segment: 0,
+            start_bus: 0,
+            end_bus: 0x3f,
+        }];
+        assert_eq!(sample, Region::parse_mcfg(&bytes).unwrap());
+        assert!(Region::parse_mcfg(&bytes[..40]).is_err());
+        assert!(Region::parse_mcfg(b"APIC").is_err());
+    }
+
+    #[test]
+    fn region() {
+        let sample = Region {
+            base: 0xe000_0000,
+            segment: 1,
+            start_bus: 0x10,
+            end_bus: 0x1f,
+        };
+        assert_eq!(sample, "1:10-1f:0xe0000000".parse().unwrap());
+        let region: Region = "e0000000".parse().unwrap();
+        assert_eq!(
+            (0, 0, 0xff),
+            (region.segment, region.start_bus, region.end_bus)
+        );
+        let region: Region = "2:f0000000".parse().unwrap();
+        assert_eq!(
+            (2, 0, 0xff),
+            (region.segment, region.start_bus, region.end_bus)
+        );
+        assert!("1:100:e0000000".parse::<Region>().is_err());
+
+        let address = "0001:12:03.1".parse().unwrap();
+        assert_eq!(Some(0xe021_9000), sample.function_address(&address));
+        assert_eq!(None, region.function_address(&address));
+    }
+
+    #[test]
+    fn mapped_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path();
+        let mut mem = vec![0xffu8; BUS_SIZE];
+        let mut function = |offset: usize, id: u32, header_type: u8| {
+            mem[offset..offset + 4].copy_from_slice(&id.to_le_bytes());
+            mem[offset + 0x0e] = header_type;
+        };
+        function(0x00000, 0x1234_8086, 0x80);
+        function(0x01000, 0x1235_8086, 0x00);
+        function(0x18000, 0x1000_1af4, 0x00);
+        // Not a multi-function device, ignored
+        function(0x19000, 0x1001_1af4, 0x00);
+        fs::write(path, &mem).unwrap();
+        let region = Region {
+            base: 0,
+            segment: 0,
+            start_bus: 0,
+            end_bus: 0,
+        };
+        let ecam = Ecam::new(vec![region], path).unwrap();
+        let result = ecam.scan().map(|address| address.unwrap().to_string());
+        assert_eq!(
+            vec!["0000:00:00.0", "0000:00:00.1", "0000:00:03.0"],
+            result.collect::<Vec<_>>()
+        );
+        let device = ecam.device("00:03.0".parse().unwrap()).unwrap();
+        assert_eq!(
+            (0x1af4, 0x1000),
+            (device.header.vendor_id, device.header.device_id)
+        );
+        let address: Address = "00:00.1".parse().unwrap();
+        ecam.write(address.clone(), 0x42, &[0xaa, 0x55]).unwrap();
+        assert_eq!(
+            vec![0xff, 0xaa, 0x55, 0xff],
+            ecam.read(address.clone(), 0x41, 4).unwrap()
+        );
+        // Unaligned head, dword and word
+        let data = [1, 2, 3, 4, 5, 6, 7];
+        ecam.write(address.clone(), 0x47, &data).unwrap();
+        assert_eq!(
+            vec![0xff, 1, 2, 3, 4, 5, 6, 7, 0xff],
+            ecam.read(address, 0x46, 9).unwrap()
+        );
+    }
+
+    #[test]
+    fn ari_functions() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path();
+        let mut mem = vec![0xffu8; BUS_SIZE];
+        // Single function header, ARI capability at 0x100 with next function
+        let mut function = |devfn: usize, next: u8| {
+            let offset = devfn << 12;
+            mem[offset..offset + 4].copy_from_slice(&0x1000_15b3u32.to_le_bytes());
+            mem[offset + 0x0e] = 0x00;
+            mem[offset + 0x100..offset + 0x104].copy_from_slice(&0x0001_000eu32.to_le_bytes());
+            mem[offset + 0x104..offset + 0x108].copy_from_slice(&[0, next, 0, 0]);
+        };
+        function(0x00, 0x01);
+        function(0x01, 0x09);
+        function(0x09, 0x10);
+        function(0x10, 0x00);
+        fs::write(path, &mem).unwrap();
+        let region = Region {
+            base: 0,
+            segment: 0,
+            start_bus: 0,
+            end_bus: 0,
+        };
+        let ecam = Ecam::new(vec![region], path).unwrap();
+        let result = ecam.scan().map(|address| address.unwrap().to_string());
+        assert_eq!(
+            vec![
+                "0000:00:00.0",
+                "0000:00:00.1",
+                "0000:00:01.1",
+                "0000:00:02.0"
+            ],
+            result.collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,166 @@
+/*!
+# Staged device information
+
+Flags selecting which OS provided device information [Device::fill](crate::device::Device::fill)
+reads, like libpci `pci_fill_info`. Configuration space is always read, everything else can be
+fetched only when needed and refreshed later. Values of libpci flags are kept, pcitool specific
+flags use high bits.
+
+```rust
+# use pcitool::access::fill::FillFlags;
+let flags = FillFlags::IRQ | FillFlags::LABEL;
+assert!(flags.contains(FillFlags::LABEL));
+assert!(!flags.contains(FillFlags::RESOURCES));
+assert_eq!(FillFlags::IRQ, flags & FillFlags::IRQ);
+assert_eq!("IRQ|LABEL", flags.to_string());
+```
+*/
+
+use std::{fmt, ops};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FillFlags(pub u32);
+
+impl FillFlags {
+    pub const NONE: Self = Self(0);
+    /// [Device::irq](crate::device::Device::irq)
+    pub const IRQ: Self = Self(0x2);
+    /// Base addresses, ROM base address, sizes and flags of
+    /// [Device::resource](crate::device::Device::resource)
+    pub const RESOURCES: Self = Self(0x4 | 0x8 | 0x10 | 0x1000);
+    pub const PHYS_SLOT: Self = Self(0x100);
+    /// Kernel modules capable of handling device
+    pub const MODULE_ALIAS: Self = Self(0x200);
+    pub const LABEL: Self = Self(0x400);
+    pub const NUMA_NODE: Self = Self(0x800);
+    pub const DT_NODE: Self = Self(0x2000);
+    pub const IOMMU_GROUP: Self = Self(0x4000);
+    /// Kernel driver in use
+    pub const DRIVER: Self = Self(0x10_0000);
+    pub const SERIAL: Self = Self(0x0100_0000);
+    pub const VIRTUAL_FUNCTION: Self = Self(0x0200_0000);
+    /// Runtime power management state
+    pub const POWER: Self = Self(0x0400_0000);
+    pub const ACPI_PATH: Self = Self(0x0800_0000);
+    /// [Device::aer_firmware_first](crate::device::Device::aer_firmware_first)
+    pub const AER_FIRMWARE_FIRST: Self = Self(0x1000_0000);
+    /// [Device::slot_designation](crate::device::Device::slot_designation)
+    pub const SLOT_DESIGNATION: Self = Self(0x2000_0000);
+    /// [Device::boot_vga](crate::device::Device::boot_vga)
+    pub const BOOT_VGA: Self = Self(0x4000_0000);
+    pub const ALL: Self = Self(
+        Self::IRQ.0
+            | Self::RESOURCES.0
+            | Self::PHYS_SLOT.0
+            | Self::MODULE_ALIAS.0
+            | Self::LABEL.0
+            | Self::NUMA_NODE.0
+            | Self::DT_NODE.0
+            | Self::IOMMU_GROUP.0
+            | Self::DRIVER.0
+            | Self::SERIAL.0
+            | Self::VIRTUAL_FUNCTION.0
+            | Self::POWER.0
+            | Self::ACPI_PATH.0
+            | Self::AER_FIRMWARE_FIRST.0
+            | Self::SLOT_DESIGNATION.0
+            | Self::BOOT_VGA.0,
+    );
+
+    const NAMES: [(Self, &'static str); 16] = [
+        (Self::IRQ, "IRQ"),
+        (Self::RESOURCES, "RESOURCES"),
+        (Self::PHYS_SLOT, "PHYS_SLOT"),
+        (Self::MODULE_ALIAS, "MODULE_ALIAS"),
+        (Self::LABEL, "LABEL"),
+        (Self::NUMA_NODE, "NUMA_NODE"),
+        (Self::DT_NODE, "DT_NODE"),
+        (Self::IOMMU_GROUP, "IOMMU_GROUP"),
+        (Self::DRIVER, "DRIVER"),
+        (Self::SERIAL, "SERIAL"),
+        (Self::VIRTUAL_FUNCTION, "VIRTUAL_FUNCTION"),
+        (Self::POWER, "POWER"),
+        (Self::ACPI_PATH, "ACPI_PATH"),
+        (Self::AER_FIRMWARE_FIRST, "AER_FIRMWARE_FIRST"),
+        (Self::SLOT_DESIGNATION, "SLOT_DESIGNATION"),
+        (Self::BOOT_VGA, "BOOT_VGA"),
+    ];
+
+    /// All bits of `other` are set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// Any bit of `other` is set
+    pub fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl ops::BitOr for FillFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for FillFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for FillFlags {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl ops::Not for FillFlags {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0)
+    }
+}
+
+/// Flag names joined with `|`, unknown bits are printed in hex
+impl fmt::Display for FillFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = *self;
+        let mut names = Vec::new();
+        for (flag, name) in Self::NAMES {
+            if rest.contains(flag) {
+                names.push(name.to_string());
+                rest = rest & !flag;
+            }
+        }
+        if !rest.is_empty() || names.is_empty() {
+            names.push(format!("{:#x}", rest.0));
+        }
+        write!(f, "{}", names.join("|"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn flags() {
+        let mut flags = FillFlags::NONE;
+        assert!(flags.is_empty());
+        flags |= FillFlags::RESOURCES;
+        assert!(flags.intersects(FillFlags(0x4)));
+        assert!(!flags.contains(FillFlags::RESOURCES | FillFlags::IRQ));
+        assert_eq!("0x0", FillFlags::NONE.to_string());
+        assert_eq!("RESOURCES|0x1", (flags | FillFlags(0x1)).to_string());
+        assert_eq!(FillFlags::NONE, FillFlags::ALL & FillFlags(0x1));
+    }
+}
@@ -6,7 +6,7 @@
 use std::{
     collections::HashMap,
     fs,
-    io::{self, BufRead, BufReader},
+    io::{self, BufRead, BufReader, Read},
     num::ParseIntError,
     path::PathBuf,
     str::FromStr,
@@ -15,8 +15,8 @@ use std::{
 use thiserror::Error;
 use walkdir::WalkDir;
 
-use super::{AccessError, AccessMethod};
-use crate::device::{Address, ConfigurationSpace, Device, Resource, ResourceEntry};
+use super::{fill::FillFlags, AccessError, AccessMethod};
+use crate::device::{Address, ConfigurationSpace, Device, Resource, ResourceEntry, DDR_OFFSET};
 
 #[derive(Debug, Error)]
 pub enum LinuxProcfsError {
@@ -32,6 +32,8 @@ pub enum LinuxProcfsError {
 pub struct LinuxProcfs {
     path: PathBuf,
     info: InfoEntries,
+    strict: bool,
+    terse: bool,
 }
 
 type InfoEntries = HashMap<Address, InfoEntry>;
@@ -67,7 +69,26 @@ impl LinuxProcfs {
                 Some((entry.address(), entry))
             })
             .collect();
-        Ok(Self { path, info })
+        Ok(Self {
+            path,
+            info,
+            strict: false,
+            terse: false,
+        })
+    }
+    /// Report devices removed during [iter](AccessMethod::iter) as
+    /// [DeviceGone](AccessError::DeviceGone) instead of skipping them
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
+    /// Make [iter](AccessMethod::iter) list devices of the devices table instead of walking bus
+    /// directories and read only their standard header, enough for terse listing. Class code,
+    /// revision and header type are not in the table, so configuration space file of every
+    /// device is still opened. An empty table falls back to walking bus directories
+    pub fn terse(&mut self, terse: bool) -> &mut Self {
+        self.terse = terse;
+        self
     }
     fn address_from_path(path: impl Into<PathBuf>) -> super::Result<Address> {
         let path = path.into();
@@ -87,6 +108,20 @@ impl LinuxProcfs {
             .map_err(|source| AccessError::ParseAddress { address, source })
     }
     // Config spaces /proc/bus/pci/xx/xx.x iterator
+    fn device_path(&self, address: &Address) -> PathBuf {
+        let path = self
+            .path
+            .join(format!("{:02x}", address.bus))
+            .join(format!("{:02x}.{}", address.device, address.function));
+        if path.is_file() {
+            path
+        } else {
+            // Paths with domains (ex: /proc/bus/pci/0001:02/)
+            self.path
+                .join(format!("{:04x}:{:02x}", address.domain, address.bus))
+                .join(format!("{:02x}.{}", address.device, address.function))
+        }
+    }
     fn device_entries(&self) -> walkdir::IntoIter {
         WalkDir::new(&self.path)
             .min_depth(2)
@@ -94,76 +129,88 @@ impl LinuxProcfs {
             .follow_links(true)
             .into_iter()
     }
-    fn read_device(path: impl Into<PathBuf>, info: &InfoEntries) -> super::Result<Device> {
+    /// Whole configuration space file or its first `limit` bytes
+    fn read_device(
+        path: impl Into<PathBuf>,
+        info: &InfoEntries,
+        limit: Option<u64>,
+    ) -> super::Result<Device> {
         let path = path.into();
         let address = Self::address_from_path(&path)?;
-        let bytes = fs::read(&path).map_err(|source| AccessError::File { path, source })?;
+        let read = |path: &PathBuf| match limit {
+            Some(limit) => {
+                let mut bytes = Vec::new();
+                fs::File::open(path)?.take(limit).read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            None => fs::read(path),
+        };
+        let bytes =
+            read(&path).map_err(|source| super::device_file_error(&address, path, source))?;
         let mut device = bytes
             .as_slice()
             .try_into()
             .map(|cs: ConfigurationSpace| Device::new(address.clone(), cs))
             .map_err(|_| AccessError::ConfigurationSpace)?;
-        if let Some(&InfoEntry {
-            irq,
-            base_addr,
-            rom_addr,
-            base_size,
-            rom_size,
-            ..
-        }) = info.get(&address)
-        {
-            device.irq = Some(irq);
-            let mut entries = [ResourceEntry::default(); 6];
-            let base_size = base_size.unwrap_or_default();
-            for (i, entry) in entries.iter_mut().enumerate() {
-                let start = base_addr[i];
-                let end = (start + base_size[i]).saturating_sub(1);
-                *entry = ResourceEntry {
-                    start,
-                    end,
-                    flags: 0,
-                };
-            }
-            device.resource = Some(Resource {
-                entries,
-                rom_entry: {
-                    let start = rom_addr.unwrap_or(0);
-                    ResourceEntry {
-                        start,
-                        end: (start + rom_size.unwrap_or(0)).saturating_sub(1),
-                        flags: 0,
-                    }
-                },
-            });
+        if let Some(entry) = info.get(&address) {
+            device.irq = Some(entry.irq);
+            device.resource = Some(entry.resource());
         }
         Ok(device)
     }
+    /// IRQ, resources and driver from devices table parsed at init, returns flags filled
+    pub fn fill(&self, device: &mut Device, fill: FillFlags) -> FillFlags {
+        let fill = fill & (FillFlags::IRQ | FillFlags::RESOURCES | FillFlags::DRIVER);
+        if let Some(entry) = self.info.get(&device.address) {
+            if fill.contains(FillFlags::IRQ) {
+                device.irq = Some(entry.irq);
+            }
+            if fill.intersects(FillFlags::RESOURCES) {
+                device.resource = Some(entry.resource());
+            }
+            if fill.contains(FillFlags::DRIVER) {
+                device.driver_in_use = entry.drv_name.clone();
+            }
+        }
+        fill
+    }
+    /// Devices table `/proc/bus/pci/devices` parsed at init, sorted by address. Addresses,
+    /// IDs, IRQ, BARs and driver of every device without reading configuration space files.
+    /// Class code, revision and header type are not in the table, so [terse](Self::terse)
+    /// listing still reads the configuration space header of every device
+    pub fn entries(&self) -> Vec<&InfoEntry> {
+        let mut entries = self.info.values().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.address());
+        entries
+    }
 }
 
 impl<'a> AccessMethod<'a> for LinuxProcfs {
     type Scan = Scan;
     type Iter = Iter<'a>;
     fn device(&self, address: Address) -> super::Result<Device> {
-        let path = self
-            .path
-            .join(format!("{:02x}", address.bus))
-            .join(format!("{:02x}.{}", address.device, address.function));
-        if path.is_file() {
-            Self::read_device(path, &self.info)
-        } else {
-            // Paths with domains (ex: /proc/bus/pci/0001:02/)
-            let path = self
-                .path
-                .join(format!("{:04x}:{:02x}", address.domain, address.bus))
-                .join(format!("{:02x}.{}", address.device, address.function));
-            Self::read_device(path, &self.info)
-        }
+        Self::read_device(self.device_path(&address), &self.info, None)
     }
     fn scan(&'a self) -> Self::Scan {
         Scan::new(self.device_entries())
     }
     fn iter(&'a self) -> Self::Iter {
-        Iter::new(self.device_entries(), &self.info)
+        let iter = if self.terse && !self.info.is_empty() {
+            let entries = self.entries().into_iter();
+            Iter::table(
+                entries.map(|e| self.device_path(&e.address())).collect(),
+                &self.info,
+            )
+        } else {
+            Iter::new(self.device_entries(), &self.info)
+        };
+        iter.strict(self.strict)
+    }
+    fn read(&'a self, addr: Address, offset: usize, len: usize) -> super::Result<Vec<u8>> {
+        super::read_config(&self.device_path(&addr), offset, len)
+    }
+    fn write(&'a self, addr: Address, offset: usize, data: &[u8]) -> super::Result<()> {
+        super::write_config(&self.device_path(&addr), offset, data)
     }
 }
 
@@ -189,6 +236,7 @@ pub enum InfoEntryError {
     ParseIntError(#[from] ParseIntError),
 }
 
+/// Line of `/proc/bus/pci/devices`, sizes and driver name are missing on old kernels
 #[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct InfoEntry {
     bus_number: u8,
@@ -196,6 +244,7 @@ pub struct InfoEntry {
     vendor: u16,
     device: u16,
     irq: usize,
+    /// BAR bases with flags in low bits
     base_addr: [u64; 6],
     rom_addr: Option<u64>,
     base_size: Option<[u64; 6]>,
@@ -204,16 +253,42 @@ pub struct InfoEntry {
 }
 
 impl InfoEntry {
+    /// Resources as in sysfs `resource` file, flags are not known
+    pub fn resource(&self) -> Resource {
+        let base_size = self.base_size.unwrap_or_default();
+        let entry = |start: u64, size: u64| ResourceEntry {
+            start,
+            end: (start + size).saturating_sub(1),
+            flags: 0,
+        };
+        let mut entries = [ResourceEntry::default(); 6];
+        for (i, re) in entries.iter_mut().enumerate() {
+            *re = entry(self.base_addr[i], base_size[i]);
+        }
+        Resource {
+            entries,
+            rom_entry: entry(self.rom_addr.unwrap_or(0), self.rom_size.unwrap_or(0)),
+            iov_entries: Default::default(),
+        }
+    }
     pub fn address(&self) -> Address {
         let &Self {
             bus_number, devfn, ..
         } = self;
-        Address {
-            domain: 0,
-            bus: bus_number,
-            device: (devfn >> 3) & 0x1f,
-            function: devfn & 0x07,
-        }
+        Address::from_devfn(0, bus_number, devfn)
+    }
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor
+    }
+    pub fn device_id(&self) -> u16 {
+        self.device
+    }
+    pub fn irq(&self) -> usize {
+        self.irq
+    }
+    /// Driver name, missing on old kernels
+    pub fn driver(&self) -> Option<&str> {
+        self.drv_name.as_deref()
     }
 }
 
@@ -344,23 +419,63 @@ impl Iterator for Scan {
     }
 }
 
+/// Configuration space files to read
+enum Paths {
+    /// Walk of bus directories, whole files are read
+    Walk(walkdir::IntoIter),
+    /// Devices table entries, standard header only
+    Table(std::vec::IntoIter<PathBuf>),
+}
+
 pub struct Iter<'a> {
-    iter: walkdir::IntoIter,
+    paths: Paths,
     info: &'a HashMap<Address, InfoEntry>,
+    strict: bool,
 }
 
 impl<'a> Iter<'a> {
     pub fn new(iter: walkdir::IntoIter, info: &'a HashMap<Address, InfoEntry>) -> Self {
-        Self { iter, info }
+        Self {
+            paths: Paths::Walk(iter),
+            info,
+            strict: false,
+        }
+    }
+    /// Standard headers of the given device files, see [LinuxProcfs::terse]
+    pub fn table(paths: Vec<PathBuf>, info: &'a HashMap<Address, InfoEntry>) -> Self {
+        Self {
+            paths: Paths::Table(paths.into_iter()),
+            info,
+            strict: false,
+        }
+    }
+    /// Do not skip devices removed while iterating
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
     }
 }
 
 impl<'a> Iterator for Iter<'a> {
     type Item = super::Result<Device>;
 
+    /// Directory entries failed to read and removed devices (unless strict) are skipped
     fn next(&mut self) -> Option<Self::Item> {
-        let path = self.iter.next()?.ok()?.into_path();
-        Some(LinuxProcfs::read_device(path, self.info))
+        loop {
+            let result = match &mut self.paths {
+                Paths::Walk(iter) => {
+                    let Ok(entry) = iter.next()? else {
+                        continue;
+                    };
+                    LinuxProcfs::read_device(entry.into_path(), self.info, None)
+                }
+                Paths::Table(iter) => {
+                    LinuxProcfs::read_device(iter.next()?, self.info, Some(DDR_OFFSET as u64))
+                }
+            };
+            if self.strict || !matches!(result, Err(AccessError::DeviceGone(_))) {
+                return Some(result);
+            }
+        }
     }
 }
 
@@ -409,6 +524,41 @@ mod tests {
         assert_eq!(sample, result);
     }
 
+    #[cfg(not(feature = "lib_proc_baseaddr_parse"))]
+    #[test]
+    fn devices_table() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let data = "\
+            0200\t10de1d12\t91\tb3000000\ta000000c\t0\tb000000c\t0\t3001\tb4000000\t1000000\t10000000\t0\t2000000\t0\t80\t80000\tnouveau\n\
+            00f8\t80869d84\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\t0\n\
+        ";
+        fs::write(path.join("devices"), data).unwrap();
+        let access = LinuxProcfs::init(path).unwrap();
+        let result = access
+            .entries()
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {:04x}:{:04x}",
+                    entry.address(),
+                    entry.vendor_id(),
+                    entry.device_id()
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["0000:00:1f.0 8086:9d84", "0000:02:00.0 10de:1d12"],
+            result
+        );
+        let resource = access.entries()[1].resource();
+        assert_eq!(
+            (0xb3000000, 0xb3ffffff),
+            (resource.entries[0].start, resource.entries[0].end)
+        );
+        assert_eq!(0xb407ffff, resource.rom_entry.end);
+    }
+
     #[test]
     fn init_no_dir() {
         let path = "/7ecc5f6b4aadb8e641a07d3cea6e8c6fa43050c916e69eac7e300c3b25172cb6";
@@ -526,6 +676,51 @@ mod tests {
         assert_eq!(vec!["0000:00:1f.3", "0000:06:00.0"], result);
     }
 
+    #[cfg(not(feature = "lib_proc_baseaddr_parse"))]
+    #[test]
+    fn terse_iter() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let data = "\
+            00fb\t80868c22\t12\t0\t0\t0\t0\t0\t0\t0\n\
+            0600\t102b0534\t10\t0\t0\t0\t0\t0\t0\t0\n\
+        ";
+        fs::write(path.join("devices"), data).unwrap();
+        for (bus, devfn, header) in [
+            ("00", "1f.3", DEV00_1F_3),
+            ("06", "00.0", DEV06_00_0),
+            ("07", "00.0", DEV06_00_0),
+        ] {
+            let bus_dir = path.join(bus);
+            fs::create_dir_all(&bus_dir).unwrap();
+            let mut cs = header.to_vec();
+            cs.resize(256, 0xff);
+            fs::write(bus_dir.join(devfn), cs).unwrap();
+        }
+
+        let mut access = LinuxProcfs::init(path).unwrap();
+        access.terse(true);
+        let result = access
+            .iter()
+            .map(|result| result.unwrap())
+            .map(|dev| {
+                (
+                    dev.address.to_string(),
+                    dev.irq,
+                    dev.device_dependent_region,
+                )
+            })
+            .collect::<Vec<_>>();
+        // Devices missing in the table are not listed, device dependent region is not read
+        let sample = vec![
+            ("0000:00:1f.3".to_string(), Some(0x12), None),
+            ("0000:06:00.0".to_string(), Some(0x10), None),
+        ];
+        assert_eq!(sample, result);
+        access.terse(false);
+        assert_eq!(3, access.iter().filter(Result::is_ok).count());
+    }
+
     #[test]
     fn invalid_iter() {
         let dir = tempdir().unwrap();
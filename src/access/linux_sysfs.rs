@@ -5,19 +5,28 @@
 
 use std::{
     fs, io,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 use thiserror::Error;
 use walkdir::WalkDir;
 
-use super::{Access, AccessError, AccessMethod};
-use crate::device::{Address, ConfigurationSpace, Device};
+use super::{fill::FillFlags, Access, AccessError, AccessMethod};
+use crate::{
+    device::{Address, ConfigurationSpace, Device, PowerControl, PowerInfo, VirtualFunction},
+    misc::{
+        hest::Hest,
+        irq::{self, IrqVector},
+        smbios::{SystemSlot, SystemSlots},
+    },
+};
+
+pub mod driver_override;
 
-mod modules_alias;
+pub mod modules_alias;
 use modules_alias::ModulesAlias;
 
-mod slots;
+pub mod slots;
 use slots::Slots;
 
 #[derive(Debug, Error)]
@@ -31,26 +40,33 @@ pub struct LinuxSysfs {
     sysfs_path: PathBuf,
     modules_alias: Option<ModulesAlias>,
     slots: Option<Slots>,
+    hest: Option<Hest>,
+    system_slots: Option<SystemSlots>,
+    fill: FillFlags,
+    strict: bool,
 }
 
 impl LinuxSysfs {
     pub const PATH: &'static str = "/sys/bus/pci";
     pub fn new(sysfs_path: impl Into<PathBuf>) -> Self {
         let sysfs_path = sysfs_path.into();
-        let modules_alias = uname::uname()
-            .and_then(|info| {
-                let path = Path::new("/lib/modules")
-                    .join(info.release)
-                    .join("modules.alias");
-
-                ModulesAlias::init(path)
-            })
+        let modules_alias = modules_alias::modules_dir()
+            .and_then(|dir| ModulesAlias::init(dir.join("modules.alias")))
             .ok();
         let slots = Slots::init(sysfs_path.join("slots")).ok();
+        // Readable by root only
+        let hest = fs::read(Hest::PATH)
+            .ok()
+            .and_then(|bytes| Hest::parse(&bytes).ok());
+        let system_slots = read_system_slots(SystemSlots::PATH);
         Self {
             sysfs_path,
             modules_alias,
             slots,
+            hest,
+            system_slots,
+            fill: FillFlags::ALL,
+            strict: false,
         }
     }
     pub fn modules_alias(&mut self, modules_alias: impl Into<ModulesAlias>) -> &mut Self {
@@ -61,6 +77,30 @@ impl LinuxSysfs {
         self.slots = Some(slots.into());
         self
     }
+    /// ACPI HEST error sources for [Device::aer_firmware_first], read from
+    /// [Hest::PATH] by default
+    pub fn hest(&mut self, hest: impl Into<Hest>) -> &mut Self {
+        self.hest = Some(hest.into());
+        self
+    }
+    /// SMBIOS System Slots for [Device::slot_designation], read from
+    /// [SystemSlots::PATH] by default
+    pub fn system_slots(&mut self, system_slots: impl Into<SystemSlots>) -> &mut Self {
+        self.system_slots = Some(system_slots.into());
+        self
+    }
+    /// Information read with configuration space by [device](AccessMethod::device) and
+    /// [iter](AccessMethod::iter), all by default. The rest can be read by [Device::fill]
+    pub fn fill_flags(&mut self, fill: FillFlags) -> &mut Self {
+        self.fill = fill;
+        self
+    }
+    /// Report devices removed during [iter](AccessMethod::iter) as
+    /// [DeviceGone](AccessError::DeviceGone) instead of skipping them
+    pub fn strict(&mut self, strict: bool) -> &mut Self {
+        self.strict = strict;
+        self
+    }
     pub fn access(&self) -> super::Result<Access> {
         // Check directory
         let is_dir = fs::metadata(&self.sysfs_path)
@@ -88,6 +128,9 @@ impl LinuxSysfs {
         sysfs_path: impl Into<PathBuf>,
         modules_alias: &Option<ModulesAlias>,
         slots: &Option<Slots>,
+        hest: &Option<Hest>,
+        system_slots: &Option<SystemSlots>,
+        fill: FillFlags,
     ) -> super::Result<Device> {
         let path = sysfs_path.into();
         let address = path
@@ -102,59 +145,272 @@ impl LinuxSysfs {
             .parse()
             .map_err(|source| AccessError::ParseAddress { address, source })?;
         let config_path = path.join("config");
-        let bytes = fs::read(&config_path).map_err(|source| AccessError::File {
-            path: config_path,
-            source,
-        })?;
+        let bytes = fs::read(&config_path)
+            .map_err(|source| super::device_file_error(&address, config_path, source))?;
         let mut device = bytes
             .as_slice()
             .try_into()
             .map(|cs: ConfigurationSpace| Device::new(address.clone(), cs))
             .map_err(|_| AccessError::ConfigurationSpace)?;
-        let label_path = path.join("label");
-        device.label = fs::read_to_string(&label_path)
-            .map_err(|err| {
-                if err.kind() != io::ErrorKind::NotFound {
-                    eprintln!(
-                        "access::linux_sysfs: Error reading {}: {}",
-                        label_path.display(),
-                        err
-                    );
-                }
+        Self::fill_device(
+            &path,
+            &mut device,
+            fill,
+            modules_alias,
+            slots,
+            hest,
+            system_slots,
+        );
+        Ok(device)
+    }
+    /// Read information selected by `fill` from device directory, returns supported flags
+    fn fill_device(
+        path: &Path,
+        device: &mut Device,
+        fill: FillFlags,
+        modules_alias: &Option<ModulesAlias>,
+        slots: &Option<Slots>,
+        hest: &Option<Hest>,
+        system_slots: &Option<SystemSlots>,
+    ) -> FillFlags {
+        let address = device.address.clone();
+        if fill.contains(FillFlags::LABEL) {
+            let label_path = path.join("label");
+            device.label = fs::read_to_string(&label_path)
+                .map_err(|err| {
+                    if err.kind() != io::ErrorKind::NotFound {
+                        tracing::warn!("Error reading {}: {}", label_path.display(), err);
+                    }
+                })
+                .ok();
+        }
+        if fill.contains(FillFlags::PHYS_SLOT) {
+            device.phy_slot = slots.as_ref().and_then(|slots| {
+                slots.find(Address {
+                    function: 0,
+                    ..address.clone()
+                })
+            });
+        }
+        if fill.contains(FillFlags::NUMA_NODE) {
+            device.numa_node = fs::read_to_string(path.join("numa_node"))
+                .ok()
+                .and_then(|s| u16::from_str_radix(s.trim(), 16).ok());
+        }
+        if fill.contains(FillFlags::IOMMU_GROUP) {
+            device.iommu_group = fs::read_to_string(path.join("iommu_group")).ok();
+        }
+        if fill.contains(FillFlags::IRQ) {
+            device.irq = fs::read_to_string(path.join("irq"))
+                .ok()
+                .and_then(|s| s.trim().parse().ok());
+        }
+        if fill.intersects(FillFlags::RESOURCES) {
+            device.resource = fs::read_to_string(path.join("resource"))
+                .ok()
+                .and_then(|s| s.parse().ok());
+        }
+        if fill.contains(FillFlags::DRIVER) {
+            device.driver_in_use = fs::read_link(path.join("driver"))
+                .ok()
+                .and_then(|path| path.file_name()?.to_str().map(|s| s.to_string()));
+        }
+        if fill.contains(FillFlags::MODULE_ALIAS) {
+            let modalias =
+                fs::read_to_string(path.join("modalias")).unwrap_or_else(|_| device.modalias());
+            device.kernel_modules = modules_alias
+                .as_ref()
+                .map(|modules_alias| modules_alias.lookup(&modalias));
+        }
+        if fill.contains(FillFlags::SERIAL) {
+            device.serial = fs::read_to_string(path.join("serial"))
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+        }
+        if fill.contains(FillFlags::VIRTUAL_FUNCTION) {
+            device.virtual_function = Self::virtual_function(path, &address);
+        }
+        if fill.contains(FillFlags::POWER) {
+            device.power_info = Self::power_info(path);
+        }
+        if fill.contains(FillFlags::DT_NODE) {
+            device.dt_node = fs::canonicalize(path.join("of_node"))
+                .ok()
+                .map(|path| path.display().to_string());
+        }
+        if fill.contains(FillFlags::ACPI_PATH) {
+            device.acpi_path = fs::read_to_string(path.join("firmware_node/path"))
+                .ok()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+        }
+        if fill.contains(FillFlags::AER_FIRMWARE_FIRST) {
+            device.aer_firmware_first = hest
+                .as_ref()
+                .and_then(|hest| hest.device_firmware_first(device));
+        }
+        if fill.contains(FillFlags::SLOT_DESIGNATION) {
+            device.slot_designation = system_slots
+                .as_ref()
+                .and_then(|slots| slots.designation(&address))
+                .map(String::from);
+        }
+        if fill.contains(FillFlags::BOOT_VGA) {
+            // Present for VGA class devices only
+            device.boot_vga = fs::read_to_string(path.join("boot_vga"))
+                .ok()
+                .map(|s| s.trim() == "1");
+        }
+        fill & FillFlags::ALL
+    }
+    fn power_info(path: &Path) -> Option<PowerInfo> {
+        let read = |name: &str| {
+            fs::read_to_string(path.join(name))
+                .ok()
+                .map(|s| s.trim().to_string())
+        };
+        let info = PowerInfo {
+            runtime_status: read("power/runtime_status"),
+            control: read("power/control").and_then(|s| s.parse().ok()),
+            d3cold_allowed: read("d3cold_allowed").map(|s| s == "1"),
+        };
+        (!info.is_empty()).then_some(info)
+    }
+    /// Read information selected by `fill` to `device`, returns flags filled
+    pub fn fill(&self, device: &mut Device, fill: FillFlags) -> FillFlags {
+        let path = self.device_path(&device.address);
+        let (modules_alias, slots) = (&self.modules_alias, &self.slots);
+        let (hest, system_slots) = (&self.hest, &self.system_slots);
+        Self::fill_device(
+            &path,
+            device,
+            fill,
+            modules_alias,
+            slots,
+            hest,
+            system_slots,
+        )
+    }
+    /// Device directory, `<sysfs>/devices/<address>`
+    pub fn device_path(&self, address: &Address) -> PathBuf {
+        self.sysfs_path.join("devices").join(address.to_string())
+    }
+    /// Device attribute not modeled by [Device], e.g. `max_link_speed` or `revision`, with
+    /// trailing whitespace removed
+    pub fn attribute(&self, address: &Address, name: &str) -> io::Result<String> {
+        attribute(&self.device_path(address), name)
+    }
+    /// Port service driver such as `dpc` or `aer` is bound to a service device of the port.
+    /// Service devices are on `pci_express` bus next to `pci` one and named `<address>:pcieNNN`
+    pub fn port_service_bound(&self, address: &Address, driver: &str) -> bool {
+        let prefix = format!("{}:", address);
+        let path = self.sysfs_path.with_file_name("pci_express");
+        fs::read_dir(path.join("drivers").join(driver))
+            .map(|entries| {
+                entries.filter_map(Result::ok).any(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| name.starts_with(&prefix))
+                })
             })
-            .ok();
-        device.phy_slot = slots.as_ref().and_then(|slots| {
-            slots.find(Address {
-                function: 0,
-                ..address
+            .unwrap_or(false)
+    }
+    /// Toggle runtime PM by writing `power/control` attribute
+    pub fn set_power_control(&self, address: &Address, control: PowerControl) -> io::Result<()> {
+        let path = self.device_path(address).join("power/control");
+        fs::write(path, control.to_string())
+    }
+    /// MSI and MSI-X vectors listed in `msi_irqs` with counts and affinity from procfs mounted
+    /// at `proc_path`. Vectors are sorted by IRQ number
+    pub fn irq_vectors(&self, address: &Address, proc_path: &Path) -> io::Result<Vec<IrqVector>> {
+        let interrupts = fs::read_to_string(proc_path.join("interrupts"))
+            .map(|s| irq::parse_interrupts(&s))
+            .unwrap_or_default();
+        let mut vectors = fs::read_dir(self.device_path(address).join("msi_irqs"))?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let irq: u32 = entry.file_name().to_str()?.parse().ok()?;
+                let kind = fs::read_to_string(entry.path()).unwrap_or_default();
+                let affinity = |name: &str| {
+                    let path = proc_path.join("irq").join(irq.to_string()).join(name);
+                    fs::read_to_string(path).ok()?.parse().ok()
+                };
+                Some(IrqVector {
+                    irq,
+                    kind: kind.trim().to_string(),
+                    interrupts: interrupts.iter().find(|line| line.irq == irq).cloned(),
+                    affinity: affinity("smp_affinity_list"),
+                    effective_affinity: affinity("effective_affinity_list"),
+                })
             })
-        });
-        device.numa_node = fs::read_to_string(path.join("numa_node"))
-            .ok()
-            .and_then(|s| u16::from_str_radix(s.trim(), 16).ok());
-        device.iommu_group = fs::read_to_string(path.join("iommu_group")).ok();
-        device.irq = fs::read_to_string(path.join("irq"))
-            .ok()
-            .and_then(|s| s.trim().parse().ok());
-        device.resource = fs::read_to_string(path.join("resource"))
-            .ok()
-            .and_then(|s| s.parse().ok());
+            .collect::<Vec<_>>();
+        vectors.sort_by_key(|vector| vector.irq);
+        Ok(vectors)
+    }
+    // VF has `physfn` link to PF directory, which contains `virtfn<N>` links to each VF
+    fn virtual_function(path: &Path, address: &Address) -> Option<VirtualFunction> {
+        let physfn_path = path.join("physfn");
+        let physfn = fs::read_link(&physfn_path)
+            .ok()?
+            .file_name()?
+            .to_str()?
+            .parse()
+            .ok()?;
+        let index = fs::read_dir(&physfn_path).ok()?.find_map(|entry| {
+            let entry = entry.ok()?;
+            let index = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("virtfn")?
+                .parse()
+                .ok()?;
+            let link = fs::read_link(entry.path()).ok()?;
+            let vf: Address = link.file_name()?.to_str()?.parse().ok()?;
+            (&vf == address).then_some(index)
+        })?;
+        Some(VirtualFunction { physfn, index })
+    }
+}
 
-        device.driver_in_use = fs::read_link(path.join("driver"))
-            .ok()
-            .and_then(|path| path.file_name()?.to_str().map(|s| s.to_string()));
-        device.kernel_modules =
-            fs::read_to_string(path.join("modalias"))
-                .ok()
-                .and_then(|modalias| {
-                    let mut kernel_modules = modules_alias
-                        .as_ref()?
-                        .lookup(&modalias)
-                        .collect::<Vec<_>>();
-                    kernel_modules.dedup();
-                    Some(kernel_modules)
-                });
-        Ok(device)
+/// System Slots structures of `dir` (`9-<n>/raw` entries), `None` if none is readable
+fn read_system_slots(dir: impl AsRef<Path>) -> Option<SystemSlots> {
+    let slots = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("9-"))
+        .filter_map(|entry| fs::read(entry.path().join("raw")).ok())
+        .filter_map(|raw| SystemSlot::parse(&raw).ok())
+        .collect::<SystemSlots>();
+    (!slots.0.is_empty()).then_some(slots)
+}
+
+/// Attribute name is relative to device directory (`power/control`), `..` is not allowed
+fn attribute(path: &Path, name: &str) -> io::Result<String> {
+    let is_relative = Path::new(name)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if name.is_empty() || !is_relative {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid attribute name '{}'", name),
+        ));
+    }
+    fs::read_to_string(path.join(name)).map(|s| s.trim_end().to_string())
+}
+
+#[cfg(target_os = "linux")]
+impl Device {
+    /// Device directory under default sysfs mount point
+    pub fn sysfs_path(&self) -> PathBuf {
+        Path::new(LinuxSysfs::PATH)
+            .join("devices")
+            .join(self.address.to_string())
+    }
+    /// Read sysfs attribute, see [LinuxSysfs::attribute]
+    pub fn sysfs_attr(&self, name: &str) -> io::Result<String> {
+        attribute(&self.sysfs_path(), name)
     }
 }
 
@@ -168,23 +424,47 @@ impl<'a> AccessMethod<'a> for LinuxSysfs {
     type Scan = Scan;
     type Iter = Iter<'a>;
     fn device(&self, address: Address) -> super::Result<Device> {
-        let sysfs_path = self.sysfs_path.join("devices").join(address.to_string());
-        Self::read_device(sysfs_path, &self.modules_alias, &self.slots)
+        let sysfs_path = self.device_path(&address);
+        let (modules_alias, slots) = (&self.modules_alias, &self.slots);
+        let (hest, system_slots) = (&self.hest, &self.system_slots);
+        Self::read_device(
+            sysfs_path,
+            modules_alias,
+            slots,
+            hest,
+            system_slots,
+            self.fill,
+        )
     }
     fn scan(&'a self) -> Self::Scan {
         Scan::new(self.dev_dir_entries())
     }
     fn iter(&'a self) -> Self::Iter {
         Iter::new(self.dev_dir_entries(), &self.modules_alias, &self.slots)
+            .hest(&self.hest)
+            .system_slots(&self.system_slots)
+            .fill_flags(self.fill)
+            .strict(self.strict)
     }
     fn vital_product_data(&'a self, addr: Address) -> io::Result<Vec<u8>> {
-        let path = self
-            .sysfs_path
-            .join("devices")
-            .join(addr.to_string())
-            .join("vpd");
+        let path = self.device_path(&addr).join("vpd");
         fs::read(path)
     }
+    /// ROM is enabled for reading and disabled afterwards, like `echo 1 > rom`
+    fn expansion_rom(&'a self, addr: Address) -> io::Result<Vec<u8>> {
+        let path = self.device_path(&addr).join("rom");
+        fs::write(&path, "1")?;
+        let result = fs::read(&path);
+        fs::write(&path, "0").and(result)
+    }
+    fn read(&'a self, addr: Address, offset: usize, len: usize) -> super::Result<Vec<u8>> {
+        let path = self.device_path(&addr).join("config");
+        super::read_config(&path, offset, len)
+    }
+    fn write(&'a self, addr: Address, offset: usize, data: &[u8]) -> super::Result<()> {
+        let path = self.device_path(&addr).join("config");
+        super::write_config(&path, offset, data)
+    }
 }
 
 #[derive(Debug)]
@@ -229,6 +509,10 @@ pub struct Iter<'a> {
     iter: walkdir::IntoIter,
     modules_alias: &'a Option<ModulesAlias>,
     slots: &'a Option<Slots>,
+    hest: &'a Option<Hest>,
+    system_slots: &'a Option<SystemSlots>,
+    fill: FillFlags,
+    strict: bool,
 }
 
 impl<'a> Iter<'a> {
@@ -241,20 +525,48 @@ impl<'a> Iter<'a> {
             iter,
             modules_alias,
             slots,
+            hest: &None,
+            system_slots: &None,
+            fill: FillFlags::ALL,
+            strict: false,
+        }
+    }
+    pub fn hest(self, hest: &'a Option<Hest>) -> Self {
+        Self { hest, ..self }
+    }
+    pub fn system_slots(self, system_slots: &'a Option<SystemSlots>) -> Self {
+        Self {
+            system_slots,
+            ..self
         }
     }
+    pub fn fill_flags(self, fill: FillFlags) -> Self {
+        Self { fill, ..self }
+    }
+    /// Do not skip devices removed while iterating
+    pub fn strict(self, strict: bool) -> Self {
+        Self { strict, ..self }
+    }
 }
 
 impl<'a> Iterator for Iter<'a> {
     type Item = super::Result<Device>;
 
+    /// Directory entries failed to read and removed devices (unless strict) are skipped
     fn next(&mut self) -> Option<Self::Item> {
-        let path = self.iter.next()?.ok()?.into_path();
-        Some(LinuxSysfs::read_device(
-            path,
-            self.modules_alias,
-            self.slots,
-        ))
+        loop {
+            let Ok(entry) = self.iter.next()? else {
+                continue;
+            };
+            let path = entry.into_path();
+            let (modules_alias, slots) = (self.modules_alias, self.slots);
+            let (hest, system_slots) = (self.hest, self.system_slots);
+            let result =
+                LinuxSysfs::read_device(path, modules_alias, slots, hest, system_slots, self.fill);
+            if self.strict || !matches!(result, Err(AccessError::DeviceGone(_))) {
+                return Some(result);
+            }
+        }
     }
 }
 
@@ -293,6 +605,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn port_service_bound() {
+        let dir = tempdir().unwrap();
+        let sysfs = LinuxSysfs::new(dir.path().join("bus/pci"));
+        let dpc = dir.path().join("bus/pci_express/drivers/dpc");
+        fs::create_dir_all(dpc.join("0000:00:1c.0:pcie008")).unwrap();
+        let port: Address = "00:1c.0".parse().unwrap();
+        assert!(sysfs.port_service_bound(&port, "dpc"));
+        assert!(!sysfs.port_service_bound(&port, "aer"));
+        assert!(!sysfs.port_service_bound(&"00:1c.4".parse().unwrap(), "dpc"));
+    }
+
     #[test]
     fn init_empty_dir() {
         let dir = tempdir().unwrap();
@@ -366,6 +690,290 @@ mod tests {
         );
     }
 
+    #[test]
+    fn virtual_function_identifiers() {
+        use std::os::unix::fs::symlink;
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let pf_dir = path.join("devices").join("0000:3b:00.0");
+        fs::create_dir_all(&pf_dir).unwrap();
+        fs::write(pf_dir.join("config"), DEV06_00_0).unwrap();
+        for (n, vf) in ["0000:3b:02.0", "0000:3b:02.1"].iter().enumerate() {
+            let vf_dir = path.join("devices").join(vf);
+            fs::create_dir_all(&vf_dir).unwrap();
+            fs::write(vf_dir.join("config"), DEV06_00_0).unwrap();
+            fs::write(vf_dir.join("serial"), "SN-VF\n").unwrap();
+            symlink("../0000:3b:00.0", vf_dir.join("physfn")).unwrap();
+            symlink(format!("../{}", vf), pf_dir.join(format!("virtfn{}", n))).unwrap();
+        }
+
+        let access = LinuxSysfs::new(path).access().unwrap();
+        let pf = access.device("0000:3b:00.0".parse().unwrap()).unwrap();
+        assert_eq!(None, pf.virtual_function);
+        let vf = access.device("0000:3b:02.1".parse().unwrap()).unwrap();
+        let sample = VirtualFunction {
+            physfn: "0000:3b:00.0".parse().unwrap(),
+            index: 1,
+        };
+        assert_eq!(Some(sample), vf.identifiers().virtual_function);
+        assert_eq!(Some("SN-VF"), vf.serial.as_deref());
+    }
+
+    #[test]
+    fn write_config() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+
+        let access = LinuxSysfs::new(path).access().unwrap();
+        let address: Address = "00:1f.3".parse().unwrap();
+        // Command register
+        access.write(address.clone(), 0x04, &[0x06, 0x04]).unwrap();
+        assert_eq!(
+            vec![0x06, 0x04],
+            access.read(address.clone(), 0x04, 2).unwrap()
+        );
+        let device = access.device(address).unwrap();
+        assert_eq!(pcics::header::Command::from(0x0406), device.header.command);
+    }
+
+    #[test]
+    fn attributes() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(dev_dir.join("power")).unwrap();
+        fs::write(dev_dir.join("max_link_speed"), "8.0 GT/s PCIe\n").unwrap();
+        fs::write(dev_dir.join("power/control"), "auto\n").unwrap();
+
+        let sysfs = LinuxSysfs::new(path);
+        let address: Address = "00:1f.3".parse().unwrap();
+        assert_eq!(dev_dir, sysfs.device_path(&address));
+        assert_eq!(
+            "8.0 GT/s PCIe",
+            sysfs.attribute(&address, "max_link_speed").unwrap()
+        );
+        assert_eq!("auto", sysfs.attribute(&address, "power/control").unwrap());
+        for name in ["", "../0000:00:1f.4/config", "/etc/hostname"] {
+            let err = sysfs.attribute(&address, name).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        }
+
+        let device = Device::new(address, DEV00_1F_3.as_slice().try_into().unwrap());
+        assert_eq!(
+            Path::new("/sys/bus/pci/devices/0000:00:1f.3"),
+            device.sysfs_path()
+        );
+    }
+
+    #[test]
+    fn power_info() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(dev_dir.join("power")).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        fs::write(dev_dir.join("power/runtime_status"), "suspended\n").unwrap();
+        fs::write(dev_dir.join("power/control"), "auto\n").unwrap();
+        fs::write(dev_dir.join("d3cold_allowed"), "1\n").unwrap();
+
+        let sysfs = LinuxSysfs::new(path);
+        let address: Address = "00:1f.3".parse().unwrap();
+        let device = sysfs.device(address.clone()).unwrap();
+        let sample = PowerInfo {
+            runtime_status: Some("suspended".into()),
+            control: Some(PowerControl::Auto),
+            d3cold_allowed: Some(true),
+        };
+        assert_eq!(Some(sample), device.power_info);
+
+        sysfs.set_power_control(&address, PowerControl::On).unwrap();
+        let device = sysfs.device(address).unwrap();
+        assert_eq!(
+            Some(PowerControl::On),
+            device.power_info.and_then(|info| info.control)
+        );
+    }
+
+    #[test]
+    fn irq_vectors() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:02:00.0");
+        fs::create_dir_all(dev_dir.join("msi_irqs")).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        fs::write(dev_dir.join("msi_irqs/126"), "msix\n").unwrap();
+        fs::write(dev_dir.join("msi_irqs/125"), "msix\n").unwrap();
+        let proc_dir = path.join("proc");
+        fs::create_dir_all(proc_dir.join("irq/125")).unwrap();
+        fs::write(
+            proc_dir.join("interrupts"),
+            "CPU0 CPU1\n125: 3 1234 IR-PCI-MSIX-0000:02:00.0 0-edge nvme0q0\n",
+        )
+        .unwrap();
+        fs::write(proc_dir.join("irq/125/smp_affinity_list"), "0-1\n").unwrap();
+        fs::write(proc_dir.join("irq/125/effective_affinity_list"), "1\n").unwrap();
+
+        let sysfs = LinuxSysfs::new(path);
+        let address = "02:00.0".parse().unwrap();
+        let result = sysfs
+            .irq_vectors(&address, &proc_dir)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "IRQ 125 msix CPUs 1 (affinity 0-1) count 1237 nvme0q0",
+            "IRQ 126 msix CPUs ? (affinity ?)",
+        ];
+        assert_eq!(sample, result);
+        let address = "00:1f.3".parse().unwrap();
+        assert!(sysfs.irq_vectors(&address, &proc_dir).is_err());
+    }
+
+    #[test]
+    fn firmware_node() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        let dt_dir = path.join("firmware/devicetree/base/pcie@10000000");
+        fs::create_dir_all(dev_dir.join("firmware_node")).unwrap();
+        fs::create_dir_all(&dt_dir).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        fs::write(dev_dir.join("firmware_node/path"), "\\_SB_.PCI0.HDAS\n").unwrap();
+        std::os::unix::fs::symlink(&dt_dir, dev_dir.join("of_node")).unwrap();
+
+        let device = LinuxSysfs::new(path)
+            .device("00:1f.3".parse().unwrap())
+            .unwrap();
+        let dt_node = fs::canonicalize(dt_dir).unwrap();
+        assert_eq!(Some(dt_node.display().to_string()), device.dt_node);
+        assert_eq!(Some("\\_SB_.PCI0.HDAS".into()), device.acpi_path);
+    }
+
+    #[test]
+    fn boot_vga() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let devices = [
+            ("0000:00:02.0", Some("1\n")),
+            ("0000:01:00.0", Some("0\n")),
+            ("0000:00:1f.3", None),
+        ];
+        for (dev, boot_vga) in devices {
+            let dev_dir = path.join("devices").join(dev);
+            fs::create_dir_all(&dev_dir).unwrap();
+            fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+            if let Some(boot_vga) = boot_vga {
+                fs::write(dev_dir.join("boot_vga"), boot_vga).unwrap();
+            }
+        }
+        let sysfs = LinuxSysfs::new(path);
+        let boot_vga = |address: &str| sysfs.device(address.parse().unwrap()).unwrap().boot_vga;
+        assert_eq!(Some(true), boot_vga("00:02.0"));
+        assert_eq!(Some(false), boot_vga("01:00.0"));
+        assert_eq!(None, boot_vga("00:1f.3"));
+    }
+
+    #[test]
+    fn aer_firmware_first() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        for (dev, aer) in [("0000:00:1f.3", true), ("0000:06:00.0", false)] {
+            let dev_dir = path.join("devices").join(dev);
+            fs::create_dir_all(&dev_dir).unwrap();
+            let mut config = [0; 0x1000];
+            config[..64].copy_from_slice(&DEV00_1F_3);
+            if aer {
+                config[0x100..0x104].copy_from_slice(&0x0001_0001u32.to_le_bytes());
+            }
+            fs::write(dev_dir.join("config"), config).unwrap();
+        }
+        // AER Endpoint error source of 00:1f.3 with FIRMWARE_FIRST flag
+        let mut table = [b"HEST".as_slice(), &[0; 36]].concat();
+        table[36] = 1;
+        let mut source = [0; 44];
+        (source[0], source[6], source[20], source[22]) = (7, 0b01, 0x1f, 3);
+        table.extend_from_slice(&source);
+        table[4] = table.len() as u8;
+
+        let access = LinuxSysfs::new(path)
+            .hest(Hest::parse(&table).unwrap())
+            .access()
+            .unwrap();
+        let mut result = access
+            .iter()
+            .map(|result| result.unwrap())
+            .map(|device| (device.address.to_string(), device.aer_firmware_first))
+            .collect::<Vec<_>>();
+        result.sort();
+        let sample = vec![
+            ("0000:00:1f.3".to_string(), Some(true)),
+            ("0000:06:00.0".to_string(), None),
+        ];
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn slot_designation() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        // System Slots of 00:1f.0 and Onboard Devices Extended Information
+        let entries = path.join("dmi");
+        let mut raw = vec![0; 0x11];
+        (raw[0], raw[1], raw[4], raw[0x10]) = (9, 0x11, 1, 0x1f << 3);
+        raw.extend_from_slice(b"PCIe Slot 3\0\0");
+        for (entry, raw) in [("9-0", raw), ("41-0", vec![41, 0x0b])] {
+            fs::create_dir_all(entries.join(entry)).unwrap();
+            fs::write(entries.join(entry).join("raw"), raw).unwrap();
+        }
+        let system_slots = read_system_slots(&entries).unwrap();
+        assert_eq!(1, system_slots.0.len());
+
+        let access = LinuxSysfs::new(path)
+            .system_slots(system_slots)
+            .access()
+            .unwrap();
+        let device = access.device("00:1f.3".parse().unwrap()).unwrap();
+        assert_eq!(Some("PCIe Slot 3".into()), device.slot_designation);
+        assert_eq!(None, read_system_slots(path.join("devices")));
+    }
+
+    #[test]
+    fn multiple_domains() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        for dev in ["0000:00:1f.3", "0002:00:00.0", "10000:e1:00.0"] {
+            let dev_dir = path.join("devices").join(dev);
+            fs::create_dir_all(&dev_dir).unwrap();
+            fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        }
+        let slot_dir = path.join("slots").join("7");
+        fs::create_dir_all(&slot_dir).unwrap();
+        fs::write(slot_dir.join("address"), "10000:e1:00\n").unwrap();
+
+        let access = LinuxSysfs::new(path).access().unwrap();
+        let mut result = access
+            .iter()
+            .map(|result| result.unwrap())
+            .map(|device| (device.address.to_string(), device.phy_slot))
+            .collect::<Vec<_>>();
+        result.sort();
+        let sample = vec![
+            ("0000:00:1f.3".to_string(), None),
+            ("0002:00:00.0".to_string(), None),
+            ("10000:e1:00.0".to_string(), Some("7".to_string())),
+        ];
+        assert_eq!(sample, result);
+        let device = access.device(Address::new(0x10000, 0xe1, 0, 0)).unwrap();
+        assert_eq!(0x10000, device.address.domain);
+    }
+
     #[test]
     fn valid_iter() {
         let dir = tempdir().unwrap();
@@ -389,6 +997,65 @@ mod tests {
         assert_eq!(vec!["0000:00:1f.3", "0000:06:00.0"], result);
     }
 
+    #[test]
+    fn removed_device() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        // Directory left without attributes by hot-removal
+        fs::create_dir_all(path.join("devices").join("0000:06:00.0")).unwrap();
+
+        let mut sysfs = LinuxSysfs::new(path);
+        let result = sysfs
+            .iter()
+            .map(|result| result.unwrap().address.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["0000:00:1f.3"], result);
+
+        sysfs.strict(true);
+        let address: Address = "06:00.0".parse().unwrap();
+        let result = sysfs
+            .iter()
+            .filter_map(Result::err)
+            .map(|err| err.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![AccessError::DeviceGone(address).to_string()], result);
+    }
+
+    #[test]
+    fn staged_fill() {
+        let dir = tempdir().unwrap();
+        let path = dir.path();
+        let dev_dir = path.join("devices").join("0000:00:1f.3");
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::write(dev_dir.join("config"), DEV00_1F_3).unwrap();
+        fs::write(dev_dir.join("label"), "Onboard Audio").unwrap();
+        fs::write(dev_dir.join("numa_node"), "1\n").unwrap();
+        fs::write(dev_dir.join("irq"), "16\n").unwrap();
+
+        let access = LinuxSysfs::new(path)
+            .fill_flags(FillFlags::NONE)
+            .access()
+            .unwrap();
+        let mut device = access.iter().next().unwrap().unwrap();
+        assert_eq!(
+            (None, None, None),
+            (device.label.as_deref(), device.numa_node, device.irq)
+        );
+
+        let flags = FillFlags::LABEL | FillFlags::NUMA_NODE;
+        assert_eq!(flags, device.fill(flags, &access));
+        assert_eq!(Some("Onboard Audio"), device.label.as_deref());
+        assert_eq!((Some(1), None), (device.numa_node, device.irq));
+
+        fs::write(dev_dir.join("numa_node"), "0\n").unwrap();
+        device.fill(FillFlags::NUMA_NODE | FillFlags::IRQ, &access);
+        assert_eq!((Some(0), Some(16)), (device.numa_node, device.irq));
+        assert_eq!(Some("Onboard Audio"), device.label.as_deref());
+    }
+
     #[test]
     fn invalid_iter() {
         let dir = tempdir().unwrap();
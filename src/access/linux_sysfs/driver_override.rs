@@ -0,0 +1,195 @@
+/*!
+# Driver override
+
+`driver_override` attribute restricts binding of a device to the named driver, e.g. `vfio-pci`
+for passthrough. Kernel applies it at the next probe, so [LinuxSysfs::set_driver_override]
+unbinds the current driver and asks the bus to probe the device again. The attribute is lost on
+reboot or hot-plug, [SavedOverrides] keeps overrides in the driverctl format (`pci-<address>`
+files with driver name in `/etc/driverctl.d`), so they are applied by driverctl udev rules,
+[SavedOverrides::present] lists the ones to set without driverctl.
+
+```rust
+# use pcitool::access::linux_sysfs::driver_override::SavedOverrides;
+let dir = tempfile::tempdir().unwrap();
+let saved = SavedOverrides::new(dir.path());
+saved.save(&"01:00.0".parse().unwrap(), "vfio-pci").unwrap();
+let overrides = saved.list().unwrap();
+assert_eq!("0000:01:00.0", overrides[0].0.to_string());
+assert_eq!("vfio-pci", overrides[0].1);
+```
+*/
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::LinuxSysfs;
+use crate::device::Address;
+
+/// File name prefix of PCI devices, driverctl also manages other buses
+const PREFIX: &str = "pci-";
+
+/// Persistent overrides directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedOverrides {
+    dir: PathBuf,
+}
+
+impl LinuxSysfs {
+    /// `driver_override` attribute, `None` if not set
+    pub fn driver_override(&self, address: &Address) -> io::Result<Option<String>> {
+        let path = self.device_path(address).join("driver_override");
+        let value = fs::read_to_string(path)?;
+        let value = value.trim();
+        Ok((!value.is_empty() && value != "(null)").then(|| value.to_string()))
+    }
+    /// Set or clear (`None`) driver override and rebind the device unless it is already bound
+    /// to the requested driver
+    pub fn set_driver_override(&self, address: &Address, driver: Option<&str>) -> io::Result<()> {
+        let path = self.device_path(address);
+        fs::write(path.join("driver_override"), driver.unwrap_or("\n"))?;
+        let bound = fs::read_link(path.join("driver"))
+            .ok()
+            .and_then(|link| link.file_name()?.to_str().map(|s| s.to_string()));
+        if bound.is_some() && bound.as_deref() == driver {
+            return Ok(());
+        }
+        if bound.is_some() {
+            fs::write(path.join("driver/unbind"), address.to_string())?;
+        }
+        fs::write(self.sysfs_path.join("drivers_probe"), address.to_string())
+    }
+}
+
+impl SavedOverrides {
+    /// driverctl configuration directory
+    pub const PATH: &'static str = "/etc/driverctl.d";
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+    /// Saved PCI overrides sorted by address, missing directory is the same as empty one
+    pub fn list(&self) -> io::Result<Vec<(Address, String)>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut overrides = entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name();
+                let address = name.to_str()?.strip_prefix(PREFIX)?.parse().ok()?;
+                let driver = fs::read_to_string(entry.path()).ok()?.trim().to_string();
+                (!driver.is_empty()).then_some((address, driver))
+            })
+            .collect::<Vec<_>>();
+        overrides.sort();
+        Ok(overrides)
+    }
+    /// Saved override of the device
+    pub fn get(&self, address: &Address) -> io::Result<Option<String>> {
+        match fs::read_to_string(self.file(address)) {
+            Ok(s) => Ok(Some(s.trim().to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    pub fn save(&self, address: &Address, driver: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.file(address), format!("{}\n", driver))
+    }
+    /// Remove saved override, not saved one is not an error
+    pub fn remove(&self, address: &Address) -> io::Result<()> {
+        match fs::remove_file(self.file(address)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+    /// Saved overrides of present devices, `address` limits it to one device as udev `add`
+    /// rule does
+    pub fn present(
+        &self,
+        sysfs: &LinuxSysfs,
+        address: Option<&Address>,
+    ) -> io::Result<Vec<(Address, String)>> {
+        let present = self
+            .list()?
+            .into_iter()
+            .filter(|(saved, _)| address.is_none() || address == Some(saved))
+            .filter(|(address, _)| sysfs.device_path(address).exists())
+            .collect();
+        Ok(present)
+    }
+    fn file(&self, address: &Address) -> PathBuf {
+        self.dir.join(format!("{}{}", PREFIX, address))
+    }
+}
+
+impl Default for SavedOverrides {
+    fn default() -> Self {
+        Self::new(Self::PATH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::os::unix::fs::symlink;
+    use tempfile::tempdir;
+
+    #[test]
+    fn driver_override() {
+        let dir = tempdir().unwrap();
+        let sysfs_path = dir.path().join("sys");
+        let address: Address = "01:00.0".parse().unwrap();
+        let dev_dir = sysfs_path.join("devices").join(address.to_string());
+        let driver_dir = sysfs_path.join("drivers").join("nvme");
+        fs::create_dir_all(&dev_dir).unwrap();
+        fs::create_dir_all(&driver_dir).unwrap();
+        fs::write(dev_dir.join("driver_override"), "(null)\n").unwrap();
+        symlink(&driver_dir, dev_dir.join("driver")).unwrap();
+
+        let sysfs = LinuxSysfs::new(&sysfs_path);
+        assert_eq!(None, sysfs.driver_override(&address).unwrap());
+        let vfio = Some("vfio-pci");
+        sysfs.set_driver_override(&address, vfio).unwrap();
+        let result = (
+            sysfs.driver_override(&address).unwrap(),
+            fs::read_to_string(driver_dir.join("unbind")).unwrap(),
+            fs::read_to_string(sysfs_path.join("drivers_probe")).unwrap(),
+        );
+        let sample = (
+            Some("vfio-pci".to_string()),
+            "0000:01:00.0".to_string(),
+            "0000:01:00.0".to_string(),
+        );
+        assert_eq!(sample, result);
+
+        // Already bound to the requested driver
+        fs::remove_file(sysfs_path.join("drivers_probe")).unwrap();
+        sysfs.set_driver_override(&address, Some("nvme")).unwrap();
+        assert!(!sysfs_path.join("drivers_probe").exists());
+
+        let saved = SavedOverrides::new(dir.path().join("driverctl.d"));
+        assert_eq!(Vec::<(Address, String)>::new(), saved.list().unwrap());
+        let absent: Address = "02:00.0".parse().unwrap();
+        saved.save(&absent, "vfio-pci").unwrap();
+        saved.save(&address, "vfio-pci").unwrap();
+        fs::write(saved.path().join("usb-1-1"), "usbhid\n").unwrap();
+        assert_eq!(Some("vfio-pci".to_string()), saved.get(&address).unwrap());
+        let sample = vec![(address.clone(), "vfio-pci".to_string())];
+        assert_eq!(sample, saved.present(&sysfs, None).unwrap());
+        assert!(saved.present(&sysfs, Some(&absent)).unwrap().is_empty());
+
+        saved.remove(&address).unwrap();
+        saved.remove(&address).unwrap();
+        assert_eq!(None, saved.get(&address).unwrap());
+        sysfs.set_driver_override(&address, None).unwrap();
+        assert_eq!(None, sysfs.driver_override(&address).unwrap());
+    }
+}
@@ -1,15 +1,42 @@
+//! Kernel modules capable of handling device, resolved without libkmod: PCI entries of
+//! `/lib/modules/$(uname -r)/modules.alias` are matched against device `modalias` the same
+//! way `modprobe` does it, with shell wildcards. Built-in drivers aliases are in
+//! `modules.builtin.modinfo`, their names in `modules.builtin`.
+
 use std::{
     fs,
     io::{self, BufRead},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
+use glob::Pattern;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct ModulesAlias {
-    data: Vec<(String, String)>,
+    data: Vec<(Pattern, String)>,
+}
+
+/// Modules compiled into kernel
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ModulesBuiltin {
+    modules: Vec<String>,
+}
+
+/// Modules directory of running kernel, `/lib/modules/$(uname -r)`
+pub fn modules_dir() -> io::Result<PathBuf> {
+    uname::uname().map(|info| Path::new("/lib/modules").join(info.release))
+}
+
+/// Module file name to module name: `kernel/drivers/ata/ahci-pci.ko` is `ahci_pci`
+fn module_name(path: &str) -> Option<String> {
+    let name = path.rsplit('/').next()?;
+    let name = name.split_once(".ko").map_or(name, |(name, _)| name);
+    (!name.is_empty()).then(|| name.replace('-', "_"))
 }
 
 impl ModulesAlias {
+    /// Lines `alias pci:v...d...sv...sd...bc...sc...i... <module>`, other lines and invalid
+    /// patterns are skipped
     pub fn init(path: impl AsRef<Path>) -> io::Result<ModulesAlias> {
         let file = fs::File::open(path.as_ref())?;
         let buf_reader = io::BufReader::new(file);
@@ -17,18 +44,124 @@ impl ModulesAlias {
             .lines()
             .filter_map(|entry| {
                 let line = entry.ok()?;
-                let mut fields = line.split_ascii_whitespace().skip(1);
-                let pattern = fields.next().filter(|p| p.starts_with("pci:"))?.to_string();
+                let mut fields = line.split_ascii_whitespace();
+                fields.next().filter(|&kw| kw == "alias")?;
+                let pattern = fields.next().filter(|p| p.starts_with("pci:"))?;
+                let pattern = Pattern::new(pattern).ok()?;
                 let value = fields.next()?.to_string();
                 Some((pattern, value))
             })
             .collect();
         Ok(Self { data })
     }
-    pub fn lookup<'a>(&'a self, modalias: &'a str) -> impl Iterator<Item = String> + 'a {
-        self.data.iter().filter_map(|(pattern, value)| {
-            let pattern = glob::Pattern::new(pattern).ok()?;
-            pattern.matches(modalias).then(|| value.clone())
-        })
+    /// PCI aliases of built-in modules, `modules.builtin.modinfo` is a list of NUL terminated
+    /// `<module>.<key>=<value>` records
+    pub fn init_builtin(path: impl AsRef<Path>) -> io::Result<ModulesAlias> {
+        let modinfo = fs::read(path.as_ref())?;
+        let data = modinfo
+            .split(|&b| b == 0)
+            .filter_map(|record| {
+                let record = std::str::from_utf8(record).ok()?;
+                let (module, pattern) = record.split_once(".alias=")?;
+                let pattern = Pattern::new(pattern)
+                    .ok()
+                    .filter(|_| pattern.starts_with("pci:"))?;
+                Some((pattern, module.to_string()))
+            })
+            .collect();
+        Ok(Self { data })
+    }
+    /// Append aliases of `other`
+    pub fn merge(&mut self, other: ModulesAlias) -> &mut Self {
+        self.data.extend(other.data);
+        self
+    }
+    /// Matching modules in `modules.alias` order, duplicates are removed
+    pub fn lookup(&self, modalias: &str) -> Vec<String> {
+        let modalias = modalias.trim();
+        let mut modules: Vec<String> = Vec::new();
+        for (pattern, value) in &self.data {
+            if pattern.matches(modalias) && !modules.contains(value) {
+                modules.push(value.clone());
+            }
+        }
+        modules
+    }
+}
+
+impl ModulesBuiltin {
+    /// `modules.builtin` lines are module paths relative to modules directory
+    pub fn init(path: impl AsRef<Path>) -> io::Result<ModulesBuiltin> {
+        let modules = fs::read_to_string(path.as_ref())?
+            .lines()
+            .filter_map(module_name)
+            .collect();
+        Ok(Self { modules })
+    }
+    pub fn contains(&self, module: &str) -> bool {
+        let module = module.replace('-', "_");
+        self.modules.iter().any(|m| m == &module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    #[test]
+    fn lookup() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("modules.alias");
+        fs::write(
+            &path,
+            "\
+            # Aliases extracted from modules themselves.\n\
+            alias pci:v00008086d00009DC8sv*sd*bc*sc*i* snd_hda_intel\n\
+            alias pci:v*d*sv*sd*bc04sc03i00* snd_hda_intel\n\
+            alias pci:v*d*sv*sd*bc04sc03i00* snd_sof_pci_intel_cnl\n\
+            alias usb:v*p*d*dc*dsc*dp*ic09isc*ip*in* usbcore\n\
+            ",
+        )
+        .unwrap();
+        let modules_alias = ModulesAlias::init(&path).unwrap();
+        let modalias = "pci:v00008086d00009DC8sv00001028sd00000962bc04sc03i80\n";
+        assert_eq!(vec!["snd_hda_intel"], modules_alias.lookup(modalias));
+        let modalias = "pci:v00008086d00009DC9sv00001028sd00000962bc04sc03i00";
+        assert_eq!(
+            vec!["snd_hda_intel", "snd_sof_pci_intel_cnl"],
+            modules_alias.lookup(modalias)
+        );
+    }
+
+    #[test]
+    fn builtin() {
+        let dir = tempdir().unwrap();
+        let modinfo = dir.path().join("modules.builtin.modinfo");
+        fs::write(
+            &modinfo,
+            "\
+            ahci.file=drivers/ata/ahci\0\
+            ahci.alias=pci:v*d*sv*sd*bc01sc06i01*\0\
+            xhci_pci.alias=pci:v*d*sv*sd*bc0Csc03i30*\0\
+            usbcore.alias=usb:v*p*d*dc*dsc*dp*ic09isc*ip*in*\0\
+            ",
+        )
+        .unwrap();
+        let builtin = dir.path().join("modules.builtin");
+        fs::write(
+            &builtin,
+            "kernel/drivers/ata/ahci.ko\nkernel/drivers/usb/host/xhci-pci.ko\n",
+        )
+        .unwrap();
+        let mut modules_alias = ModulesAlias::default();
+        modules_alias.merge(ModulesAlias::init_builtin(&modinfo).unwrap());
+        let modalias = "pci:v00008086d00009DD3sv00001043sd000016A1bc01sc06i01";
+        assert_eq!(vec!["ahci"], modules_alias.lookup(modalias));
+        let builtin = ModulesBuiltin::init(&builtin).unwrap();
+        assert!(builtin.contains("ahci"));
+        assert!(builtin.contains("xhci-pci"));
+        assert!(!builtin.contains("nvme"));
     }
 }
@@ -0,0 +1,135 @@
+/*!
+# Device changes
+
+Long-lived device map kept in sync with an access method. [Access::refresh] re-enumerates
+devices into a map and reports differences, [Monitor] owns the map and refreshes it
+periodically, so hotplug and driver rebinds are seen without restart.
+
+```rust
+# use std::collections::BTreeMap;
+# use pcitool::access::{dump::Dump, Access};
+let dump = Dump::init(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+))
+.unwrap();
+let access = Access::from(dump);
+let mut devices = BTreeMap::new();
+let delta = access.refresh(&mut devices);
+assert_eq!(25, delta.added.len());
+assert!(access.refresh(&mut devices).is_empty());
+```
+*/
+
+use std::{collections::BTreeMap, thread, time::Duration};
+
+use super::Access;
+use crate::device::{Address, Device};
+
+/// Differences between two enumerations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Delta {
+    pub added: Vec<Address>,
+    pub removed: Vec<Address>,
+    /// Devices with different configuration space or OS provided information
+    pub changed: Vec<Address>,
+}
+
+/// Device map refreshed from access method
+#[derive(Debug)]
+pub struct Monitor {
+    access: Access,
+    devices: BTreeMap<Address, Device>,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Access {
+    /// Re-enumerate devices into `devices`, returns differences with previous content.
+    /// Devices failed to read are treated as removed
+    pub fn refresh(&self, devices: &mut BTreeMap<Address, Device>) -> Delta {
+        let new: BTreeMap<_, _> = self
+            .iter()
+            .filter_map(Result::ok)
+            .map(|device| (device.address.clone(), device))
+            .collect();
+        let mut delta = Delta::default();
+        for (address, device) in &new {
+            match devices.get(address) {
+                None => delta.added.push(address.clone()),
+                Some(old) if old != device => delta.changed.push(address.clone()),
+                _ => (),
+            }
+        }
+        delta.removed = devices
+            .keys()
+            .filter(|address| !new.contains_key(address))
+            .cloned()
+            .collect();
+        *devices = new;
+        delta
+    }
+}
+
+impl Monitor {
+    /// Enumerated devices are not reported as added
+    pub fn new(access: Access) -> Self {
+        let mut devices = BTreeMap::new();
+        access.refresh(&mut devices);
+        Self { access, devices }
+    }
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+    pub fn devices(&self) -> &BTreeMap<Address, Device> {
+        &self.devices
+    }
+    pub fn device(&self, address: &Address) -> Option<&Device> {
+        self.devices.get(address)
+    }
+    pub fn refresh(&mut self) -> Delta {
+        self.access.refresh(&mut self.devices)
+    }
+    /// Refresh every `interval`, yields non-empty deltas. Iterator never ends
+    pub fn watch(&mut self, interval: Duration) -> impl Iterator<Item = Delta> + '_ {
+        std::iter::repeat_with(move || {
+            thread::sleep(interval);
+            self.refresh()
+        })
+        .filter(|delta| !delta.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::dump::Dump;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn refresh() {
+        let dump = Dump::fixture();
+        let mut monitor = Monitor::new(dump.into());
+        assert_eq!(25, monitor.devices().len());
+        assert_eq!(Delta::default(), monitor.refresh());
+
+        let removed: Address = "00:1f.3".parse().unwrap();
+        let changed: Address = "00:1d.4".parse().unwrap();
+        monitor.devices.remove(&removed);
+        monitor.devices.get_mut(&changed).unwrap().driver_in_use = Some("pcieport".into());
+        monitor.devices.insert(
+            "01:00.0".parse().unwrap(),
+            monitor.device(&changed).unwrap().clone(),
+        );
+        let sample = Delta {
+            added: vec![removed],
+            removed: vec!["01:00.0".parse().unwrap()],
+            changed: vec![changed],
+        };
+        assert_eq!(sample, monitor.refresh());
+    }
+}
@@ -39,6 +39,6 @@ mod tests {
         //
         //}
         let is_found = unsafe { pci_lookup_method("linux-sysfs\x00") };
-        dbg!(is_found);
+        assert!(is_found >= 0);
     }
 }
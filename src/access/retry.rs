@@ -0,0 +1,153 @@
+/*!
+# Retries of unstable reads
+
+Some platforms transiently return all ones on configuration reads, e.g. while a device is
+changing power state. Devices read with Vendor ID and Device ID `ffff` are read again with
+doubling delay, devices still returning all ones are reported as
+[Unstable](super::AccessError::Unstable) instead of decoded garbage.
+
+```rust
+# use std::time::Duration;
+# use pcitool::access::retry::RetryPolicy;
+let policy: RetryPolicy = "3,10".parse().unwrap();
+assert_eq!(RetryPolicy::new(3, Duration::from_millis(10)), policy);
+assert_eq!(
+    vec![10, 20, 40],
+    policy.delays().map(|d| d.as_millis()).collect::<Vec<_>>()
+);
+```
+*/
+
+use std::{fmt, str::FromStr, thread, time::Duration};
+
+use thiserror::Error;
+
+use super::{Access, AccessError, Result};
+use crate::device::{Address, Device};
+
+/// Retries count and delay before the first retry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub delay: Duration,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("retry policy should be <count>[,<delay ms>]")]
+pub struct ParseRetryPolicyError;
+
+impl RetryPolicy {
+    /// Single read without retries
+    pub const NONE: Self = Self::new(0, Duration::ZERO);
+    pub const fn new(retries: u32, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+    /// Delay before each retry, doubled every time
+    pub fn delays(&self) -> impl Iterator<Item = Duration> {
+        let delay = self.delay;
+        (0..self.retries).map(move |n| delay.saturating_mul(1 << n.min(16)))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(10))
+    }
+}
+
+/// `<count>[,<delay ms>]`, default delay is 10ms
+impl FromStr for RetryPolicy {
+    type Err = ParseRetryPolicyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (retries, delay) = s.split_once(',').unwrap_or((s, "10"));
+        let retries = retries.trim().parse().map_err(|_| ParseRetryPolicyError)?;
+        let delay = delay.trim().parse().map_err(|_| ParseRetryPolicyError)?;
+        Ok(Self::new(retries, Duration::from_millis(delay)))
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.retries, self.delay.as_millis())
+    }
+}
+
+impl Access {
+    /// [Access::device] read again while configuration space is all ones
+    pub fn device_retry(&self, addr: Address, policy: &RetryPolicy) -> Result<Device> {
+        let device = self.device(addr)?;
+        self.stabilize(device, policy)
+    }
+    /// [Access::iter] with devices read as all ones read again
+    pub fn iter_retry<'a>(
+        &'a self,
+        policy: &'a RetryPolicy,
+    ) -> Box<dyn Iterator<Item = Result<Device>> + Send + 'a> {
+        Box::new(
+            self.iter()
+                .map(move |result| result.and_then(|device| self.stabilize(device, policy))),
+        )
+    }
+    fn stabilize(&self, device: Device, policy: &RetryPolicy) -> Result<Device> {
+        if !device.is_not_responding() {
+            return Ok(device);
+        }
+        let address = device.address.clone();
+        for delay in policy.delays() {
+            thread::sleep(delay);
+            let device = self.device(address.clone())?;
+            if !device.is_not_responding() {
+                return Ok(device);
+            }
+        }
+        Err(AccessError::Unstable {
+            address,
+            attempts: policy.retries + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::dump::Dump;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn unstable() {
+        let data = "\
+            00:1f.3 Audio device: Intel Corporation Device 9dc8 (rev 30)\n\
+            00: 86 80 c8 9d 06 04 10 00 30 80 03 04 10 20 00 00\n\
+            10: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+            20: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+            30: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+            \n\
+            00:1f.4 SMBus: Device ffff:ffff (rev ff)\n\
+            00: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            10: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            20: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            30: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            \n\
+        ";
+        let access = Access::from(Dump::new(data));
+        let policy = RetryPolicy::new(2, Duration::from_millis(1));
+        let result = access
+            .iter_retry(&policy)
+            .map(|result| {
+                result
+                    .map(|device| device.address.to_string())
+                    .map_err(|err| err.to_string())
+            })
+            .collect::<Vec<_>>();
+        let sample = vec![
+            Ok("0000:00:1f.3".to_string()),
+            Err("device 0000:00:1f.4 reads all ones after 3 attempts".to_string()),
+        ];
+        assert_eq!(sample, result);
+        assert!(access
+            .device_retry("00:1f.3".parse().unwrap(), &RetryPolicy::NONE)
+            .is_ok());
+        assert_eq!(Err(ParseRetryPolicyError), "x".parse::<RetryPolicy>());
+    }
+}
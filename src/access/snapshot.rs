@@ -0,0 +1,119 @@
+/*!
+# Consistent device records
+
+Device record is assembled from several reads: configuration space, then OS provided resources,
+IRQ, driver and so on. A function hot-removed and another one plugged at the same address in
+between yields a record mixing both. [Device::snapshot] reads Vendor ID and Device ID before
+and after reading the whole device again and compares them with the record, mismatching
+records are read again a few times and flagged if they never settle.
+
+```rust
+# use pcitool::access::{dump::Dump, Access, AccessMethod};
+let dump = Dump::init(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+))
+.unwrap();
+let access = Access::from(dump);
+let device = access.device("04:00.0".parse().unwrap()).unwrap();
+let snapshot = device.snapshot(&access).unwrap();
+assert!(snapshot.consistent);
+assert_eq!(device, snapshot.device);
+```
+*/
+
+use super::{Access, Result};
+use crate::device::Device;
+
+/// Reads of a device changing under them
+const ATTEMPTS: u32 = 3;
+
+/// Device record with its consistency check result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub device: Device,
+    /// Vendor ID and Device ID were the same before, during and after the last read
+    pub consistent: bool,
+    /// Reads made, more than one if the device changed while being read
+    pub attempts: u32,
+}
+
+impl Device {
+    /// Read the device at the same address again as a coherent record
+    pub fn snapshot(&self, access: &Access) -> Result<Snapshot> {
+        let address = &self.address;
+        snapshot(
+            || {
+                let bytes = access.read(address.clone(), 0, 4)?;
+                Ok([bytes[0], bytes[1], bytes[2], bytes[3]])
+            },
+            || access.device(address.clone()),
+        )
+    }
+}
+
+/// Vendor ID and Device ID bytes are read by `ids`, the whole device by `device`
+fn snapshot(
+    mut ids: impl FnMut() -> Result<[u8; 4]>,
+    mut device: impl FnMut() -> Result<Device>,
+) -> Result<Snapshot> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let before = ids()?;
+        let record = device()?;
+        let after = ids()?;
+        let header = record.header.vendor_id as u32 | (record.header.device_id as u32) << 16;
+        let consistent = before == after && u32::from_le_bytes(before) == header;
+        if consistent || attempts == ATTEMPTS {
+            return Ok(Snapshot {
+                device: record,
+                consistent,
+                attempts,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessError};
+    use pretty_assertions::assert_eq;
+
+    const DATA: &str = "\
+        00:1f.3 Audio device: Intel Corporation Device 9dc8 (rev 30)\n\
+        00: 86 80 c8 9d 06 04 10 00 30 80 03 04 10 20 00 00\n\
+        10: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+        20: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+        30: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n\
+        \n\
+    ";
+
+    #[test]
+    fn hotplug_while_reading() {
+        let access = Access::from(Dump::new(DATA));
+        let device = access.device("00:1f.3".parse().unwrap()).unwrap();
+        let audio = [0x86, 0x80, 0xc8, 0x9d];
+        let other = [0x86, 0x80, 0xa3, 0x9d];
+
+        // Replaced between IDs read and device read, settled on the second attempt
+        let mut reads = [other, audio, audio, audio].into_iter();
+        let result = snapshot(|| Ok(reads.next().unwrap()), || Ok(device.clone())).unwrap();
+        assert_eq!((true, 2), (result.consistent, result.attempts));
+
+        // Keeps changing
+        let mut reads = [audio, other].into_iter().cycle();
+        let result = snapshot(|| Ok(reads.next().unwrap()), || Ok(device.clone())).unwrap();
+        assert_eq!((false, ATTEMPTS), (result.consistent, result.attempts));
+        assert_eq!(device, result.device);
+
+        // Removed
+        let gone = Device {
+            address: "00:1f.4".parse().unwrap(),
+            ..device
+        };
+        let result = gone.snapshot(&access);
+        assert!(matches!(result, Err(AccessError::NoAddress(_))));
+    }
+}
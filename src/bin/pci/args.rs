@@ -1,8 +1,22 @@
 #[cfg(feature = "clap")]
 use clap::Parser;
-use clap::{builder::TypedValueParser, ErrorKind};
+use clap::{builder::TypedValueParser, ArgGroup, ErrorKind};
 
-use std::path::PathBuf;
+use std::{ops::Range, path::PathBuf};
+
+use pcitool::{
+    access::{
+        ecam::Region, linux_sysfs::driver_override::SavedOverrides, retry::RetryPolicy, Method,
+    },
+    device::{
+        address::parse_domain, hexdump::parse_range, rebar::BarSize, registers::RegisterAddress,
+        slot::Indicator, Address, PowerControl,
+    },
+    topology::select::Selector,
+    view::{table::Column, template::Template},
+};
+
+use crate::{config::Color, set::Assignment};
 
 #[derive(Parser, Debug)]
 #[clap(author, about, version)]
@@ -21,13 +35,124 @@ pub enum Command {
     /// Configure PCI devices
     #[clap(name = "set")]
     Set(Set),
+    /// Read configuration registers
+    #[clap(name = "get")]
+    Get(Get),
+    /// Show PCI Express links speed, width and bandwidth
+    #[clap(name = "link")]
+    Link(Link),
+    /// Poll links and log speed, width, retraining and error bits changes with timestamps
+    #[clap(name = "link-watch")]
+    LinkWatch(LinkWatch),
+    /// Report MaxPayload/MaxReadReq mismatches along root port to endpoint paths
+    #[clap(name = "mps-audit")]
+    MpsAudit(MpsAudit),
+    /// Check bridges bus numbers and windows, BARs placement and decoding along the topology,
+    /// duplicated capabilities
+    #[clap(name = "lint")]
+    Lint(Lint),
+    /// Compare devices with saved snapshot: added/removed devices, drivers, links and BARs
+    #[clap(name = "diff-inventory")]
+    DiffInventory(DiffInventory),
+    /// Show capabilities and extended capabilities offsets, IDs and versions without decoding
+    #[clap(name = "caps")]
+    Caps(Caps),
+    /// Hexdump configuration space range
+    #[clap(name = "hexdump")]
+    Hexdump(Hexdump),
+    /// Show or change Resizable BAR sizes
+    #[clap(name = "rebar")]
+    Rebar(Rebar),
+    /// Show or change device runtime power management
+    #[clap(name = "power")]
+    Power(Power),
+    /// Show or change driver override, optionally saved in driverctl format to survive reboots
+    #[clap(name = "driver-override")]
+    DriverOverride(DriverOverride),
+    /// Show Downstream Port Containment status or release containment
+    #[clap(name = "dpc")]
+    Dpc(Dpc),
+    /// Show Precision Time Measurement state along the path from PTM Root to a device
+    #[clap(name = "ptm")]
+    Ptm(Ptm),
+    /// Check PASID, ATS, PRI, IOMMU and End-End TLP Prefix support for Shared Virtual Memory
+    #[clap(name = "svm-check")]
+    SvmCheck(SvmCheck),
+    /// Print identifier stable across reboots and bus renumbering: path below the root device,
+    /// slot designation and Device Serial Number
+    #[clap(name = "id")]
+    Id(Id),
+    /// Show or change hot-plug slot power and indicators
+    #[clap(name = "slot")]
+    Slot(Slot),
+    /// Show or change MSI and MSI-X enable and mask bits
+    #[clap(name = "msi")]
+    Msi(Msi),
+    /// Dump and decode device expansion ROM
+    #[clap(name = "rom")]
+    Rom(Rom),
+    /// Show MSI and MSI-X vectors with interrupt counts and CPU affinity
+    #[clap(name = "irqs")]
+    Irqs(Irqs),
+    /// List kernel modules matching devices without a driver
+    #[clap(name = "suggest-driver")]
+    SuggestDriver(SuggestDriver),
+    /// Check whether ACS redirects peer-to-peer DMA between two devices
+    #[clap(name = "p2p-check")]
+    P2pCheck(P2pCheck),
+    /// Show allocated and free space of bridge windows
+    #[clap(name = "windows")]
+    Windows(Windows),
+    /// Show virtio devices type, configuration structures and MSI-X vectors
+    #[clap(name = "virtio")]
+    Virtio(Virtio),
+    /// Try access methods in fallback order and report devices found or errors
+    #[clap(name = "probe")]
+    Probe(Probe),
+    /// Count devices per class and vendor, endpoints, bridges and SR-IOV virtual functions
+    #[clap(name = "summary")]
+    Stats(Summary),
+    /// Show NVMe controllers link, NUMA node, MSI-X vectors, SR-IOV VFs and driver
+    #[clap(name = "nvme")]
+    Nvme(Summary),
+    /// Show display controllers memory BARs, Resizable BARs, link and driver
+    #[clap(name = "gpu")]
+    Gpu(Summary),
+    /// Show VGA compatible controllers with the boot VGA one and bridges routing legacy VGA
+    #[clap(name = "vga-route")]
+    VgaRoute(Summary),
+    /// Margin receiver lanes of a 16.0 GT/s or faster link and report eye width and height
+    #[cfg(feature = "margining")]
+    #[clap(name = "margin")]
+    Margin(Margin),
+    /// Publish devices inventory on D-Bus
+    #[cfg(feature = "dbus")]
+    #[clap(name = "daemon")]
+    Daemon(Daemon),
+    /// Interactive devices browser
+    #[cfg(feature = "tui")]
+    #[clap(name = "tui")]
+    Tui(Tui),
+    /// Manage the names database
+    #[clap(name = "ids")]
+    Ids(Ids),
+    /// Print shell completion script
+    #[clap(name = "completions")]
+    Completions(Completions),
+    /// Print man page in roff format
+    #[clap(name = "manpage")]
+    Manpage,
 }
 
 #[derive(Parser, Debug)]
 pub struct List {
-    // /// Produce machine-readable output (single -m for an obsolete format)
-    // #[clap(short = 'm', parse(from_occurrences))]
-    // pub machine: usize,
+    /// Produce machine-readable output (single -m for an obsolete format)
+    #[clap(
+        short = 'm',
+        parse(from_occurrences),
+        conflicts_with_all = &["table", "identifiers", "json", "yaml"]
+    )]
+    pub machine: usize,
     // /// Show bus tree
     // #[clap(short = 't')]
     // pub tree: bool,
@@ -35,19 +160,21 @@ pub struct List {
     #[clap(short = 'x', parse(from_occurrences))]
     pub hex: usize,
 
-    /// Instead of accessing real hardware, read the list of devices and values of their configuration registers from the given file
-    #[clap(short = 'F', value_name = "file")]
-    pub file: Option<PathBuf>,
-
     /// Be verbose (-vv or -vvv for higher verbosity)
     #[clap(short = 'v', parse(from_occurrences))]
     pub verbose: usize,
     /// Show kernel drivers handling each device
     #[clap(short = 'k')]
     pub kernel: bool,
-    // /// Bus-centric view (addresses and IRQ's as seen by the bus)
-    // #[cfg_attr(feature = "clap", clap(short = 'b'))]
-    // pub bus_centric: bool,
+    /// Bus-centric view (addresses and IRQ's as seen by the bus)
+    #[clap(short = 'b')]
+    pub bus_centric: bool,
+    /// Show current power state of devices not in D0, BARs of suspended devices read as ff
+    #[clap(long)]
+    pub show_power: bool,
+    /// Do not truncate class, vendor and device names to lspci buffer sizes
+    #[clap(long)]
+    pub full_names: bool,
     /// Always show domain numbers
     #[clap(short = 'D')]
     pub always_domain_number: bool,
@@ -57,26 +184,68 @@ pub struct List {
     /// Show numeric ID's
     #[clap(short = 'n', parse(from_occurrences))]
     pub as_numbers: usize,
+    /// Query the PCI ID database for unknown ID's via DNS (-qq to re-query locally cached entries)
+    #[cfg(feature = "network")]
+    #[clap(short = 'q', parse(from_occurrences))]
+    pub query_dns: usize,
+    /// Query the PCI ID database for all ID's via DNS
+    #[cfg(feature = "network")]
+    #[clap(short = 'Q')]
+    pub query_all: bool,
+    /// Colorize output
+    #[clap(
+        long,
+        value_enum,
+        value_name = "when",
+        min_values = 0,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    pub color: Option<Color>,
+    /// Show devices as aligned table
+    #[clap(long)]
+    pub table: bool,
+    /// Table columns: address, class, vendor, device, driver, irq, numa, link, msi-x, vfs,
+    /// max-link, bars, rebar, virt
+    #[clap(
+        long,
+        value_name = "column,...",
+        value_delimiter = ',',
+        requires = "table"
+    )]
+    pub columns: Vec<Column>,
+    /// Show device identifiers in columns: address, DSN, serial number, SR-IOV VF position
+    #[clap(long)]
+    pub identifiers: bool,
+    /// Show each device as a line from template with {field} placeholders, e.g.
+    /// '{address} {vendor_name} {device_name} {driver}'
+    #[clap(long, value_name = "template", conflicts_with_all = &["table", "identifiers"])]
+    pub format: Option<Template>,
+    /// Show devices as JSON array
+    #[clap(long, conflicts_with_all = &["table", "identifiers", "format"])]
+    pub json: bool,
+    /// Show devices as YAML sequence, same fields as JSON
+    #[clap(long, conflicts_with_all = &["table", "identifiers", "json", "format"])]
+    pub yaml: bool,
+    /// Sort devices by key, devices with equal keys are ordered by address
+    #[clap(long, value_enum, value_name = "key")]
+    pub sort: Option<SortKey>,
+    /// Show devices under headings by key: class, driver or IOMMU group
+    #[clap(long, value_enum, value_name = "key", conflicts_with = "identifiers")]
+    pub group_by: Option<GroupKey>,
 
-    /// The library supports a variety of methods to access the PCI hardware.
-    /// By default, it uses the first access method available, but you can use this
-    /// option to override this decision.
-    #[clap(short = 'A', value_enum, value_name = "method")]
-    pub method: Option<PreferredMethod>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+
+    /// Look up kernel modules in a given file instead of default
+    /// /lib/modules/$(uname -r)/modules.alias
+    #[clap(short = 'p', value_name = "file")]
+    pub(crate) modules_alias: Option<PathBuf>,
 
-    /// The behavior of the library is controlled by several named parameters.
-    /// This option allows one to set the value of any of the parameters.
-    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
-    pub(crate) parameter_value: Option<ParameterValue>,
-    
-    // This option actuallly does not work
-    // #[clap(short = 'p', value_name = "file")]
-    // pub(crate) modules_alias: Option<PathBuf>,
-    
     /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
     #[clap(short = 'i', value_name = "file")]
     pub(crate) pci_ids_path: Option<PathBuf>,
-    
+
     /// Show only devices in the specified domain (in case your machine has several
     /// host bridges, they can either share  a  common  bus  number space  or  each  of
     /// them can address a PCI domain of its own; domains are numbered from 0 to ffff),
@@ -85,10 +254,52 @@ pub struct List {
     /// numbers are  hexa‐ decimal.  E.g., "0:" means all devices on bus 0, "0" means
     /// all functions of device 0 on any bus, "0.3" selects third function of device 0
     /// on all buses and ".4" shows only the fourth function of each device.
-    #[clap(short = 's', value_name = "[[[[<domain>]:]<bus>]:][<device>][.[<func>]]")]
-    pub(crate) address: Option<PathBuf>,
-    
+    /// "/<device>.<func>" hops select devices below matched bridges regardless of bus numbers,
+    /// trailing "/" selects everything below, e.g. "00:1c.0/" or "00:1c.0/00.0/".
+    #[clap(
+        short = 's',
+        value_name = "[[[[<domain>]:]<bus>]:][<device>][.[<func>]][/<device>.<func>]...[/]"
+    )]
+    pub(crate) address: Option<Selector>,
 
+    /// Show only devices in the specified PCI domain (segment), hexadecimal: 0, 0002, 10000
+    #[clap(long, value_name = "domain", value_parser = parse_domain)]
+    pub domain: Option<u32>,
+    /// Print durations of access initialization, enumeration, names loading and rendering
+    /// to stderr
+    #[clap(long)]
+    pub timing: bool,
+    /// Size BARs, expansion ROM and SR-IOV VF BARs of devices without OS reported resources by
+    /// writing all-ones to the registers (root only). Decoding of the device is disabled while
+    /// sizing, so it needs `--force` and devices bound to a driver or missing in sysfs are
+    /// skipped unless `--allow-bound` is given
+    #[clap(long, requires = "force")]
+    pub size_bars: bool,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    /// Read devices returning all ones again <count> times with doubling delay starting at
+    /// <delay ms> (10 by default), report devices never stabilized as errors
+    #[clap(long, value_name = "count[,delay-ms]")]
+    pub retry: Option<RetryPolicy>,
+    /// Report to stderr what every access method of the probe order sees and which one was
+    /// selected
+    #[clap(long)]
+    pub debug_access: bool,
+}
+
+/// Options selecting how devices are read, shared by subcommands
+#[derive(clap::Args, Debug, Default)]
+pub struct AccessArgs {
+    /// Instead of accessing real hardware, read the list of devices and values of their
+    /// configuration registers from the given file (`-` reads stdin)
+    #[clap(short = 'F', long, value_name = "file")]
+    pub file: Option<PathBuf>,
+    /// Use the specified PCI access method, the first available one by default
+    #[clap(short = 'A', value_enum, value_name = "method")]
+    pub method: Option<PreferredMethod>,
+    /// Set PCI access parameter
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
 }
 
 #[derive(Debug, Clone)]
@@ -99,6 +310,7 @@ pub enum PreferredMethod {
     LinuxProcfs,
     IntelConf1,
     IntelConf2,
+    Ecam,
     #[cfg(target_os = "freebsd")]
     FbsdDevice,
     #[cfg(target_os = "netbsd")]
@@ -110,6 +322,45 @@ pub enum PreferredMethod {
     Dump,
 }
 
+/// Listing order, devices without the value go last
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum SortKey {
+    /// Class code
+    Class,
+    /// Vendor ID
+    Vendor,
+    /// Driver name
+    Driver,
+    /// NUMA node
+    Numa,
+    /// Current PCI Express link speed
+    LinkSpeed,
+}
+
+/// Listing grouping
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum GroupKey {
+    /// Base class and sub class
+    Class,
+    /// Driver in use
+    Driver,
+    /// IOMMU group
+    IommuGroup,
+}
+
+/// Configuration space write policy options shared by writing subcommands
+#[derive(clap::Args, Debug, Default, Clone, Copy)]
+pub struct WriteGuard {
+    /// Write bits outside Command, ASPM Control, slot indicators and Resizable BAR size
+    #[clap(long)]
+    pub force: bool,
+    /// Write to devices bound to a driver or missing in sysfs
+    #[clap(long)]
+    pub allow_bound: bool,
+}
+
 #[derive(Debug, Clone)]
 // #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
 pub(crate) enum ParameterValue {
@@ -119,6 +370,9 @@ pub(crate) enum ParameterValue {
     SysfsPath(PathBuf),
     NetCacheName(PathBuf),
     NetDomain(String),
+    ProbeOrder(Vec<Method>),
+    EcamAcpiMcfg(PathBuf),
+    EcamAddrs(Vec<Region>),
 }
 
 #[derive(Debug, Clone)]
@@ -144,13 +398,513 @@ impl TypedValueParser for ParameterValueParser {
             "sysfs.path" => Ok(ParameterValue::SysfsPath(PathBuf::from(value))),
             "net.cache_name" => Ok(ParameterValue::NetCacheName(PathBuf::from(value))),
             "net.domain" => Ok(ParameterValue::NetDomain(value.into())),
+            "probe.order" => value
+                .split(',')
+                .map(|name| name.trim().parse())
+                .collect::<Result<_, _>>()
+                .map(ParameterValue::ProbeOrder)
+                .map_err(|err| cmd.error(ErrorKind::InvalidValue, err)),
+            "ecam.acpimcfg" => Ok(ParameterValue::EcamAcpiMcfg(PathBuf::from(value))),
+            "ecam.addrs" => value
+                .split(',')
+                .map(|region| region.trim().parse())
+                .collect::<Result<_, _>>()
+                .map(ParameterValue::EcamAddrs)
+                .map_err(|err| cmd.error(ErrorKind::InvalidValue, err)),
             _ => Err(cmd.error(
                 ErrorKind::InvalidValue,
-                "available values: dump.name, proc.path, sysfs.path, net.cache_name, net.domain",
+                "available values: dump.name, proc.path, sysfs.path, net.cache_name, net.domain, probe.order, ecam.acpimcfg, ecam.addrs",
             )),
         }
     }
 }
 
 #[derive(Parser, Debug)]
-pub struct Set;
+#[clap(group(ArgGroup::new("dump").arg("file").requires("dry-run")))]
+pub struct Set {
+    /// Device address
+    #[clap(
+        short = 's',
+        value_name = "[[<domain>]:]<bus>:<device>.<func>",
+        required_unless_present = "from-file",
+        requires = "assignments"
+    )]
+    pub address: Option<Address>,
+    /// Register assignments, e.g. COMMAND=0406, CAP_PM+4.w=0:3 or ECAP_AER+0x10.l=0
+    #[clap(value_name = "register>=<value>[:<mask>", requires = "address")]
+    pub assignments: Vec<Assignment>,
+    /// Apply assignments from file, one `<address> <assignment>...` line per device
+    #[clap(long, value_name = "file", conflicts_with = "address")]
+    pub from_file: Option<PathBuf>,
+    /// Print changes with current and new values without writing
+    #[clap(long)]
+    pub dry_run: bool,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Get {
+    /// Device address
+    #[clap(short = 's', value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Registers, e.g. COMMAND, LNKSTA, CAP_PM+4.w or 3c.b
+    #[clap(value_name = "register", required = true)]
+    pub registers: Vec<RegisterAddress>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Link {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
+    #[clap(short = 'i', value_name = "file")]
+    pub pci_ids_path: Option<PathBuf>,
+    /// Show devices with links as JSON array
+    #[clap(long)]
+    pub json: bool,
+    /// Show devices with links as YAML sequence
+    #[clap(long, conflicts_with = "json")]
+    pub yaml: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LinkWatch {
+    /// Watch only selected devices, same syntax as `list -s`
+    #[clap(
+        short = 's',
+        value_name = "[[[[<domain>]:]<bus>]:][<device>][.[<func>]][/<device>.<func>]...[/]"
+    )]
+    pub address: Option<Selector>,
+    /// Polling interval in milliseconds
+    #[clap(long, value_name = "ms", default_value = "1000")]
+    pub interval: u64,
+    /// Stop after <count> polls
+    #[clap(long, value_name = "count")]
+    pub count: Option<usize>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Virtio {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Probe {
+    /// Set PCI access parameter, e.g. probe.order=linux-proc,linux-sysfs
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Completions {
+    /// Shell to complete commands of
+    #[clap(value_enum, value_name = "shell")]
+    pub shell: Shell,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Devices of a single class: `nvme`, `gpu`
+#[derive(Parser, Debug)]
+pub struct Summary {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
+    #[clap(short = 'i', value_name = "file")]
+    pub pci_ids_path: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct MpsAudit {
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Apply safe MaxPayload and raise MaxReadReq up to it (root only)
+    #[clap(long)]
+    pub fix: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct Lint {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct DiffInventory {
+    /// Saved snapshot (JSON)
+    #[clap(value_name = "old", required_unless_present = "save")]
+    pub old: Option<PathBuf>,
+    /// Snapshot to compare with instead of current devices
+    #[clap(value_name = "new")]
+    pub new: Option<PathBuf>,
+    /// Save current devices snapshot to the file
+    #[clap(long, value_name = "file", conflicts_with_all = &["old", "new"])]
+    pub save: Option<PathBuf>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Rebar {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// BAR index to resize
+    #[clap(long, value_name = "index", requires = "size")]
+    pub bar: Option<u8>,
+    /// New BAR size, power of two, e.g. 256M or 8G
+    #[clap(long, value_name = "size", requires = "bar")]
+    pub size: Option<BarSize>,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Hexdump {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Configuration space offsets, end is exclusive
+    #[clap(long, value_name = "start..end", value_parser = parse_range, default_value = "0..100")]
+    pub range: Range<usize>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Dpc {
+    /// Downstream port address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Clear Trigger Status to release triggered containment
+    #[clap(long, conflicts_with = "file")]
+    pub release: bool,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Ptm {
+    /// PTM requester address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SvmCheck {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Caps {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Print only one line per capability: offset, ID, version and setpci name separated by
+    /// tabs, without hex-dump of capability bytes
+    #[clap(long)]
+    pub list: bool,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Id {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Ids {
+    #[clap(subcommand)]
+    pub command: IdsCommand,
+}
+
+#[derive(Parser, Debug)]
+pub enum IdsCommand {
+    /// Convert pci.ids to a memory-mapped index used instead of parsing, the index is
+    /// regenerated when pci.ids changes
+    #[clap(name = "compile")]
+    Compile(IdsCompile),
+}
+
+#[derive(Parser, Debug)]
+pub struct IdsCompile {
+    /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
+    #[clap(short = 'i', value_name = "file")]
+    pub(crate) pci_ids_path: Option<PathBuf>,
+    /// Write index to <file> instead of ~/.cache/pcitool/pci.ids.idx, pass it with -i to other
+    /// commands
+    #[clap(short = 'o', long, value_name = "file")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+#[clap(group(
+    ArgGroup::new("changes")
+        .args(&["disable", "enable", "mask", "unmask", "function-mask"])
+        .multiple(true)
+        .conflicts_with("file")
+))]
+pub struct Msi {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Disable MSI and MSI-X
+    #[clap(long, conflicts_with = "enable")]
+    pub disable: bool,
+    /// Enable MSI or MSI-X
+    #[clap(long, value_name = "msi|msix", value_parser = ["msi", "msix"])]
+    pub enable: Option<String>,
+    /// Set MSI per-vector Mask Bit
+    #[clap(long, value_name = "vector")]
+    pub mask: Vec<u8>,
+    /// Clear MSI per-vector Mask Bit
+    #[clap(long, value_name = "vector")]
+    pub unmask: Vec<u8>,
+    /// Set or clear MSI-X Function Mask
+    #[clap(long, value_name = "on|off", value_parser = ["on", "off"])]
+    pub function_mask: Option<String>,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+#[clap(group(
+    ArgGroup::new("changes")
+        .args(&["power", "attention", "power-indicator"])
+        .multiple(true)
+        .conflicts_with("file")
+))]
+pub struct Slot {
+    /// Downstream port address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Turn slot power on or off
+    #[clap(long, value_name = "on|off", value_parser = ["on", "off"])]
+    pub power: Option<String>,
+    /// Set attention indicator
+    #[clap(long, value_name = "on|blink|off")]
+    pub attention: Option<Indicator>,
+    /// Set power indicator
+    #[clap(long, value_name = "on|blink|off")]
+    pub power_indicator: Option<Indicator>,
+    /// Do not ask for confirmation before powering slot off
+    #[clap(short = 'y', long)]
+    pub yes: bool,
+    #[clap(flatten)]
+    pub guard: WriteGuard,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Power {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Change setting, e.g. control=auto or control=on
+    #[clap(long, value_name = "setting>=<value")]
+    pub set: Option<PowerSetting>,
+    /// Set sysfs.path parameter
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
+}
+
+#[derive(Parser, Debug)]
+pub struct DriverOverride {
+    /// Device address
+    #[clap(
+        value_name = "[[<domain>]:]<bus>:<device>.<func>",
+        required_unless_present = "apply"
+    )]
+    pub address: Option<Address>,
+    /// Bind device only to this driver, e.g. vfio-pci
+    #[clap(value_name = "driver", conflicts_with_all = &["clear", "apply"])]
+    pub driver: Option<String>,
+    /// Remove the override and let the device bind to its default driver
+    #[clap(long, conflicts_with = "apply")]
+    pub clear: bool,
+    /// Also save or remove the override, so it is applied at boot and hot-plug
+    #[clap(long)]
+    pub persist: bool,
+    /// Apply saved overrides of present devices, or of the given one as udev rule does
+    #[clap(long)]
+    pub apply: bool,
+    /// Rebind devices bound to a driver other than the requested one, implied by `--apply`
+    #[clap(long)]
+    pub allow_bound: bool,
+    /// Saved overrides directory
+    #[clap(long, value_name = "dir", default_value = SavedOverrides::PATH)]
+    pub saved_dir: PathBuf,
+    /// Set sysfs.path parameter
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Rom {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Save ROM image to the given file
+    #[clap(short = 'o', value_name = "file")]
+    pub output: Option<PathBuf>,
+    /// Set sysfs.path parameter
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Irqs {
+    /// Device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Set sysfs.path parameter
+    #[clap(short = 'O', value_name = "param>=<value", value_parser = ParameterValueParser)]
+    pub(crate) parameter_value: Option<ParameterValue>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SuggestDriver {
+    /// Device address, every device without a driver if omitted
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Option<Address>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Kernel modules directory with modules.alias, modules.builtin and
+    /// modules.builtin.modinfo instead of /lib/modules/$(uname -r)
+    #[clap(long, value_name = "dir")]
+    pub modules_dir: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct P2pCheck {
+    /// First device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub a: Address,
+    /// Second device address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub b: Address,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct Windows {
+    /// Bridge address, every bridge if omitted
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Option<Address>,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+/// `pci power --set` value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PowerSetting {
+    Control(PowerControl),
+}
+
+impl std::str::FromStr for PowerSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some(("control", value)) => value
+                .parse()
+                .map(Self::Control)
+                .map_err(|err| format!("{}", err)),
+            _ => Err("available settings: control=auto|on".into()),
+        }
+    }
+}
+
+#[cfg(feature = "margining")]
+#[derive(Parser, Debug)]
+pub struct Margin {
+    /// Downstream port address
+    #[clap(value_name = "[[<domain>]:]<bus>:<device>.<func>")]
+    pub address: Address,
+    /// Lanes to margin, every lane of the link by default
+    #[clap(long, value_name = "lane,...", value_delimiter = ',')]
+    pub lanes: Vec<usize>,
+    /// Receiver number: 1 downstream port, 2-5 retimers, 6 upstream port
+    #[clap(
+        long,
+        value_name = "n",
+        default_value = "1",
+        value_parser = clap::value_parser!(u8).range(1..=6)
+    )]
+    pub receiver: u8,
+    /// Errors allowed at a passing step
+    #[clap(long, value_name = "count", default_value = "4")]
+    pub error_limit: u8,
+    /// Time spent at each step in milliseconds
+    #[clap(long, value_name = "ms", default_value = "100")]
+    pub dwell: u64,
+    /// Write to devices bound to a driver or missing in sysfs
+    #[clap(long)]
+    pub allow_bound: bool,
+    #[clap(flatten)]
+    pub access: AccessArgs,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Parser, Debug)]
+pub struct Tui {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
+    #[clap(short = 'i', value_name = "file")]
+    pub pci_ids_path: Option<PathBuf>,
+    /// Devices list refresh interval in seconds
+    #[clap(long, value_name = "seconds", default_value = "2")]
+    pub interval: u64,
+}
+
+#[cfg(feature = "dbus")]
+#[derive(Parser, Debug)]
+pub struct Daemon {
+    #[clap(flatten)]
+    pub access: AccessArgs,
+    /// Use <file> as the PCI ID list instead of /usr/share/hwdata/pci.ids.
+    #[clap(short = 'i', value_name = "file")]
+    pub pci_ids_path: Option<PathBuf>,
+    /// Connect to session bus instead of system bus
+    #[clap(long)]
+    pub session: bool,
+    /// Hotplug polling interval in seconds
+    #[clap(long, value_name = "seconds", default_value = "5")]
+    pub interval: u64,
+}
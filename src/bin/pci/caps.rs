@@ -0,0 +1,58 @@
+/*!
+Raw capabilities list of `pci caps`
+
+Capabilities and extended capabilities are listed by offset, ID and version with a hexdump of
+each structure. ARI capability is followed by the function chain of the bus.
+*/
+
+use pcitool::{
+    access::Access,
+    device::{self, Address, Device},
+};
+
+use crate::{args::Caps, init_access};
+
+/// Capabilities are walked by headers, so structures pcics fails to decode are listed too
+pub fn run(args: Caps) {
+    let Caps {
+        address,
+        list,
+        access,
+    } = args;
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}: {}", address, err);
+        std::process::exit(1)
+    });
+    for cap in device.raw_capabilities() {
+        println!("{}", cap);
+        if list {
+            continue;
+        }
+        if cap.name() == Some("ECAP_ARI") {
+            let chain = ari_chain(&access, &device);
+            let chain = chain.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+            println!("ARI chain: {}", chain.join(" -> "));
+        }
+        if let Some(bytes) = device.capability_bytes(cap.offset) {
+            print!("{}", device::hexdump::Hexdump::new(cap.offset, bytes));
+        }
+    }
+}
+
+/// ARI function numbers chained from function 0 of the device bus
+fn ari_chain(access: &Access, device: &Device) -> Vec<u8> {
+    let Address { domain, bus, .. } = device.address;
+    let first = Address::from_devfn(domain, bus, 0);
+    let first = if device.address == first {
+        Some(device.clone())
+    } else {
+        access.device(first).ok()
+    };
+    first
+        .map(|first| device::ari::chain(first, |address| access.device(address.clone()).ok()))
+        .unwrap_or_default()
+        .iter()
+        .map(|function| function.address.devfn())
+        .collect()
+}
@@ -0,0 +1,35 @@
+/*!
+Device class tables of `pci nvme` and `pci gpu`
+
+Devices of a class are shown as a [Table] with the columns relevant to the class.
+*/
+
+use pcitool::view::table::{Column, Table};
+
+use crate::{args::Summary, init_access, init_names};
+
+/// Devices of `base` class (and `sub` subclass) as a table
+pub fn run(args: Summary, base: u8, sub: Option<u8>, columns: &[Column]) {
+    let Summary {
+        access,
+        pci_ids_path,
+    } = args;
+    let access = init_access(&access);
+    let mut devices = access
+        .iter()
+        .filter_map(Result::ok)
+        .filter(|device| {
+            let cc = &device.header.class_code;
+            cc.base == base && sub.is_none_or(|sub| cc.sub == sub)
+        })
+        .collect::<Vec<_>>();
+    devices.sort();
+    let names = init_names(pci_ids_path);
+    let table = Table {
+        devices: &devices,
+        columns,
+        vds: &names.vendor_device_subsystem(),
+        cc: &names.class_code(),
+    };
+    print!("{}", table);
+}
@@ -0,0 +1,372 @@
+/*!
+Shell completion scripts
+
+Scripts are generated from the clap command definition, so every subcommand, flag and possible
+value of `pci` is completed without a separate list to keep in sync. Options taking a value
+without fixed possible values complete file names.
+*/
+
+use std::io::{self, Write};
+
+use clap::{Arg, Command};
+
+use crate::{
+    args::{Completions, Shell},
+    command,
+};
+
+/// Option of a subcommand reduced to what completions need
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opt {
+    pub short: Option<char>,
+    pub long: Option<String>,
+    /// First line of help
+    pub help: String,
+    pub takes_value: bool,
+    pub multiple: bool,
+    pub values: Vec<String>,
+}
+
+impl Opt {
+    fn new(arg: &Arg) -> Self {
+        Self {
+            short: arg.get_short(),
+            long: arg.get_long().map(String::from),
+            help: arg
+                .get_help()
+                .and_then(|s| s.lines().next())
+                .unwrap_or_default()
+                .into(),
+            takes_value: arg.is_takes_value_set(),
+            multiple: arg.is_multiple_occurrences_set(),
+            values: possible_values(arg),
+        }
+    }
+    /// `-s` and `--long` spellings
+    pub fn names(&self) -> Vec<String> {
+        let short = self.short.map(|c| format!("-{}", c));
+        let long = self.long.as_ref().map(|s| format!("--{}", s));
+        short.into_iter().chain(long).collect()
+    }
+}
+
+/// Visible possible values of an option taking a value
+pub fn possible_values(arg: &Arg) -> Vec<String> {
+    if !arg.is_takes_value_set() {
+        return Vec::new();
+    }
+    // `value_enum` of clap 3 derive sets possible values, not a value parser
+    let values = match arg.get_possible_values() {
+        Some(values) => values.to_vec(),
+        None => arg
+            .get_value_parser()
+            .possible_values()
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+    values
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+/// Visible options of `cmd`, positional arguments are skipped
+pub fn options(cmd: &Command) -> Vec<Opt> {
+    cmd.get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+        .map(Opt::new)
+        .collect()
+}
+
+/// Visible subcommands with their names and aliases
+pub fn subcommands<'a, 'help>(cmd: &'a Command<'help>) -> Vec<(Vec<&'a str>, &'a Command<'help>)> {
+    cmd.get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .map(|sub| {
+            let names = Some(sub.get_name())
+                .into_iter()
+                .chain(sub.get_all_aliases());
+            (names.collect(), sub)
+        })
+        .collect()
+}
+
+/// Write completion script for `shell`, `cmd` should be built
+pub fn generate(cmd: &Command, shell: Shell, out: &mut impl Write) -> io::Result<()> {
+    let name = cmd.get_bin_name().unwrap_or_else(|| cmd.get_name());
+    match shell {
+        Shell::Bash => bash(cmd, name, out),
+        Shell::Zsh => zsh(cmd, name, out),
+        Shell::Fish => fish(cmd, name, out),
+    }
+}
+
+fn bash(cmd: &Command, name: &str, out: &mut impl Write) -> io::Result<()> {
+    let function = format!("_{}", name.replace('-', "_"));
+    let subcommands = subcommands(cmd);
+    writeln!(out, "{}() {{", function)?;
+    writeln!(out, "    local cur prev word cmd=\"\"")?;
+    writeln!(out, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(out, "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    writeln!(
+        out,
+        "    for word in \"${{COMP_WORDS[@]:1:COMP_CWORD-1}}\"; do"
+    )?;
+    writeln!(out, "        case \"$word\" in")?;
+    for (names, sub) in &subcommands {
+        writeln!(
+            out,
+            "            {}) cmd={}; break ;;",
+            names.join("|"),
+            sub.get_name()
+        )?;
+    }
+    writeln!(out, "        esac")?;
+    writeln!(out, "    done")?;
+    writeln!(out, "    case \"$cmd\" in")?;
+    let top = options(cmd)
+        .iter()
+        .flat_map(Opt::names)
+        .chain(
+            subcommands
+                .iter()
+                .map(|(_, sub)| sub.get_name().to_string()),
+        )
+        .collect::<Vec<_>>();
+    writeln!(out, "        \"\")")?;
+    writeln!(
+        out,
+        "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+        top.join(" ")
+    )?;
+    for (_, sub) in &subcommands {
+        let opts = options(sub);
+        writeln!(out, "        {})", sub.get_name())?;
+        writeln!(out, "            case \"$prev\" in")?;
+        for opt in opts.iter().filter(|opt| opt.takes_value) {
+            let words = if opt.values.is_empty() {
+                "-f".to_string()
+            } else {
+                format!("-W \"{}\"", opt.values.join(" "))
+            };
+            writeln!(
+                out,
+                "                {}) COMPREPLY=($(compgen {} -- \"$cur\")); return ;;",
+                opt.names().join("|"),
+                words
+            )?;
+        }
+        writeln!(out, "            esac")?;
+        let words = opts
+            .iter()
+            .flat_map(Opt::names)
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            "            COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+            words
+        )?;
+    }
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "complete -F {} {}", function, name)
+}
+
+/// Escape help inside single quoted `_arguments` spec brackets
+fn zsh_help(help: &str) -> String {
+    help.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_arguments(opts: &[Opt], indent: &str, out: &mut impl Write) -> io::Result<()> {
+    for opt in opts {
+        let names = opt.names();
+        let exclusive = if names.len() > 1 && !opt.multiple {
+            format!("({})", names.join(" "))
+        } else {
+            String::new()
+        };
+        let action = if !opt.takes_value {
+            String::new()
+        } else if opt.values.is_empty() {
+            ":value:_files".to_string()
+        } else {
+            format!(":value:({})", opt.values.join(" "))
+        };
+        let repeat = if opt.multiple { "*" } else { "" };
+        let help = zsh_help(&opt.help);
+        let spec = names
+            .iter()
+            .map(|name| format!("{}{}{}[{}]{}", exclusive, repeat, name, help, action));
+        let spec = if names.len() > 1 {
+            format!("'{}'", spec.collect::<Vec<_>>().join("' '"))
+        } else {
+            format!("'{}'", spec.collect::<String>())
+        };
+        writeln!(out, "{}{} \\", indent, spec)?;
+    }
+    Ok(())
+}
+
+fn zsh(cmd: &Command, name: &str, out: &mut impl Write) -> io::Result<()> {
+    let function = format!("_{}", name.replace('-', "_"));
+    writeln!(out, "#compdef {}", name)?;
+    writeln!(out)?;
+    writeln!(out, "{}() {{", function)?;
+    writeln!(out, "    local context state state_descr line")?;
+    writeln!(out, "    typeset -A opt_args")?;
+    writeln!(out, "    _arguments -C \\")?;
+    zsh_arguments(&options(cmd), "        ", out)?;
+    writeln!(out, "        '1: :->command' \\")?;
+    writeln!(out, "        '*:: :->args'")?;
+    writeln!(out, "    case $state in")?;
+    writeln!(out, "        command)")?;
+    writeln!(out, "            local -a commands")?;
+    writeln!(out, "            commands=(")?;
+    let subcommands = subcommands(cmd);
+    for (_, sub) in &subcommands {
+        let about = sub
+            .get_about()
+            .and_then(|s| s.lines().next())
+            .unwrap_or_default();
+        let about = about.replace('\'', "'\\''").replace(':', "\\:");
+        writeln!(out, "                '{}:{}'", sub.get_name(), about)?;
+    }
+    writeln!(out, "            )")?;
+    writeln!(out, "            _describe command commands ;;")?;
+    writeln!(out, "        args)")?;
+    writeln!(out, "            case $line[1] in")?;
+    for (names, sub) in &subcommands {
+        writeln!(out, "                {})", names.join("|"))?;
+        writeln!(out, "                    _arguments \\")?;
+        zsh_arguments(&options(sub), "                        ", out)?;
+        writeln!(out, "                        ;;")?;
+    }
+    writeln!(out, "            esac ;;")?;
+    writeln!(out, "    esac")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "{} \"$@\"", function)
+}
+
+fn fish_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish_options(opts: &[Opt], prefix: &str, out: &mut impl Write) -> io::Result<()> {
+    for opt in opts {
+        let mut line = prefix.to_string();
+        if let Some(short) = opt.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        if let Some(long) = &opt.long {
+            line.push_str(&format!(" -l {}", long));
+        }
+        if opt.takes_value {
+            if opt.values.is_empty() {
+                line.push_str(" -r -F");
+            } else {
+                line.push_str(&format!(" -r -f -a {}", fish_quote(&opt.values.join(" "))));
+            }
+        }
+        if !opt.help.is_empty() {
+            line.push_str(&format!(" -d {}", fish_quote(&opt.help)));
+        }
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn fish(cmd: &Command, name: &str, out: &mut impl Write) -> io::Result<()> {
+    let prefix = format!("complete -c {} -n __fish_use_subcommand", name);
+    fish_options(&options(cmd), &prefix, out)?;
+    let subcommands = subcommands(cmd);
+    for (_, sub) in &subcommands {
+        let about = sub
+            .get_about()
+            .and_then(|s| s.lines().next())
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "{} -f -a {} -d {}",
+            prefix,
+            sub.get_name(),
+            fish_quote(about)
+        )?;
+    }
+    for (names, sub) in &subcommands {
+        let prefix = format!(
+            "complete -c {} -n '__fish_seen_subcommand_from {}'",
+            name,
+            names.join(" ")
+        );
+        fish_options(&options(sub), &prefix, out)?;
+    }
+    Ok(())
+}
+
+/// Script of the selected shell to stdout
+pub fn run(args: Completions) {
+    let mut stdout = io::stdout().lock();
+    if let Err(err) = generate(&command(), args.shell, &mut stdout) {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Args;
+    use clap::CommandFactory;
+    use pretty_assertions::assert_eq;
+
+    fn script(shell: Shell) -> String {
+        let mut cmd = Args::command().bin_name("pci");
+        cmd.build();
+        let mut out = Vec::new();
+        generate(&cmd, shell, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn list_options() {
+        let mut cmd = Args::command();
+        cmd.build();
+        let list = cmd.find_subcommand("list").unwrap();
+        let opts = options(list);
+        let method = opts.iter().find(|opt| opt.short == Some('A')).unwrap();
+        assert!(method.values.contains(&"linux-sysfs".to_string()));
+        let verbose = opts.iter().find(|opt| opt.short == Some('v')).unwrap();
+        assert_eq!((false, true), (verbose.takes_value, verbose.multiple));
+        let names = subcommands(&cmd);
+        let (aliases, _) = names
+            .iter()
+            .find(|(_, sub)| sub.get_name() == "list")
+            .unwrap();
+        assert_eq!(&vec!["list", "ls"], aliases);
+    }
+
+    #[test]
+    fn scripts() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("list|ls) cmd=list; break ;;"));
+        assert!(bash.contains("-F|--file) COMPREPLY=($(compgen -f -- \"$cur\")); return ;;"));
+        assert!(bash.ends_with("complete -F _pci pci\n"));
+
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef pci\n"));
+        assert!(zsh.contains("'list:List all PCI devices'"));
+        assert!(zsh.contains("'*-v[Be verbose"));
+
+        let fish = script(Shell::Fish);
+        let line = "complete -c pci -n __fish_use_subcommand -f -a list -d 'List all PCI devices'";
+        assert!(fish.lines().any(|l| l == line));
+        assert!(fish.contains("-n '__fish_seen_subcommand_from list ls' -s F -l file -r -F"));
+    }
+}
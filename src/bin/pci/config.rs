@@ -0,0 +1,233 @@
+/*!
+Defaults from environment and config file
+
+Resolution order (first wins): command line, environment, config file
+(`$PCITOOL_CONFIG`, `$XDG_CONFIG_HOME/pcitool.toml` or `~/.config/pcitool.toml`).
+
+Environment:
+- `PCIIDS_PATH` - pci.ids database path
+- `LSPCI` - default `list` options, e.g. `LSPCI="-nn -k"`
+
+Config file:
+```toml
+pci-ids-path = "/usr/share/misc/pci.ids"
+verbose = 1
+method = "linux-sysfs"
+color = "auto"
+# fallback order when no method is given
+probe-order = ["linux-proc", "linux-sysfs"]
+```
+*/
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use pcitool::access::Method;
+
+use crate::args::{List, PreferredMethod};
+
+pub const FILE_NAME: &str = "pcitool.toml";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    pub pci_ids_path: Option<PathBuf>,
+    pub verbose: Option<usize>,
+    /// Access method name as accepted by `-A`
+    pub method: Option<String>,
+    pub color: Option<Color>,
+    /// Access method names tried in order if no method is given
+    pub probe_order: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    /// Colorize if stdout is a terminal and `NO_COLOR` is not set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl Config {
+    /// Config file path or None if there is no way to locate home directory
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("PCITOOL_CONFIG") {
+            return Some(path.into());
+        }
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join(FILE_NAME))
+    }
+    /// Missing file is the same as empty config
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => {
+                toml::from_str(&s).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+    /// Load config file and apply environment. Broken config file is reported and ignored.
+    pub fn init() -> Self {
+        let mut config = Self::path()
+            .map(|path| {
+                Self::load(&path).unwrap_or_else(|err| {
+                    eprintln!("pci: ignoring {}: {}", path.display(), err);
+                    Self::default()
+                })
+            })
+            .unwrap_or_default();
+        if let Some(path) = env::var_os("PCIIDS_PATH") {
+            config.pci_ids_path = Some(path.into());
+        }
+        config
+    }
+    /// Preferred access method, invalid name is reported and ignored
+    pub fn method(&self) -> Option<PreferredMethod> {
+        self.method.as_ref().and_then(|name| {
+            PreferredMethod::from_str(name, true)
+                .map_err(|err| eprintln!("pci: ignoring method {:?}: {}", name, err))
+                .ok()
+        })
+    }
+    /// Fallback order of access methods, invalid names are reported and ignored. Built-in
+    /// order is used if no valid name is left
+    pub fn probe_order(&self) -> Option<Vec<Method>> {
+        let names = self.probe_order.as_ref()?;
+        let order = names
+            .iter()
+            .filter_map(|name| {
+                name.parse()
+                    .map_err(|err| eprintln!("pci: ignoring probe order entry: {}", err))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        if order.is_empty() {
+            eprintln!("pci: no valid probe order entries, using built-in order");
+            return None;
+        }
+        Some(order)
+    }
+    /// Fill `list` options not given on the command line
+    pub fn apply(&self, list: &mut List) {
+        if list.verbose == 0 {
+            list.verbose = self.verbose.unwrap_or_default();
+        }
+        if list.pci_ids_path.is_none() {
+            list.pci_ids_path = self.pci_ids_path.clone();
+        }
+        if list.color.is_none() {
+            list.color = self.color;
+        }
+    }
+}
+
+impl Color {
+    pub fn enabled(&self) -> bool {
+        match self {
+            Self::Auto => env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Insert options from `LSPCI` variable right after `list` subcommand
+pub fn args_with_env<I>(args: I, lspci: Option<OsString>) -> Vec<OsString>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut args: Vec<OsString> = args.into_iter().collect();
+    let Some(lspci) = lspci else {
+        return args;
+    };
+    let position = args
+        .iter()
+        .skip(1)
+        .position(|arg| arg == "list" || arg == "ls");
+    if let (Some(position), Some(lspci)) = (position, lspci.to_str()) {
+        let index = position + 2;
+        args.splice(index..index, lspci.split_whitespace().map(OsString::from));
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_config() {
+        let s = r#"
+            pci-ids-path = "/usr/share/misc/pci.ids"
+            verbose = 2
+            method = "linux-proc"
+            color = "never"
+            probe-order = ["linux-proc", "conf1", "void"]
+        "#;
+        let result: Config = toml::from_str(s).unwrap();
+        let sample = Config {
+            pci_ids_path: Some("/usr/share/misc/pci.ids".into()),
+            verbose: Some(2),
+            method: Some("linux-proc".into()),
+            color: Some(Color::Never),
+            probe_order: Some(vec!["linux-proc".into(), "conf1".into(), "void".into()]),
+        };
+        assert_eq!(sample, result);
+        assert_eq!(
+            Some(vec![Method::LinuxProcfs, Method::Void]),
+            result.probe_order()
+        );
+        assert!(toml::from_str::<Config>("verbosity = 1").is_err());
+        let result: Config = toml::from_str(r#"probe-order = ["conf1", "conf2"]"#).unwrap();
+        assert_eq!(None, result.probe_order());
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let config = Config {
+            pci_ids_path: Some("pci.ids".into()),
+            verbose: Some(2),
+            method: Some("linux-proc".into()),
+            color: None,
+            probe_order: None,
+        };
+        let mut list = List::parse_from(["list", "-v", "-A", "dump"]);
+        config.apply(&mut list);
+        assert_eq!(1, list.verbose);
+        assert!(matches!(list.access.method, Some(PreferredMethod::Dump)));
+        assert_eq!(Some(PathBuf::from("pci.ids")), list.pci_ids_path);
+
+        let mut list = List::parse_from(["list"]);
+        config.apply(&mut list);
+        assert_eq!(2, list.verbose);
+        assert!(list.access.method.is_none());
+        assert!(matches!(
+            config.method(),
+            Some(PreferredMethod::LinuxProcfs)
+        ));
+    }
+
+    #[test]
+    fn lspci_variable() {
+        let args = ["pci", "-G", "ls", "-s", "00:1f.3"].map(OsString::from);
+        let result = args_with_env(args.clone(), Some("-nn  -k".into()));
+        let sample = ["pci", "-G", "ls", "-nn", "-k", "-s", "00:1f.3"].map(OsString::from);
+        assert_eq!(sample.to_vec(), result);
+        assert_eq!(args.to_vec(), args_with_env(args.clone(), None));
+    }
+}
@@ -0,0 +1,173 @@
+/*!
+D-Bus service publishing PCI inventory
+
+Service `org.pcitool.Manager` at `/org/pcitool/Manager`:
+- `ListDevices() -> as` device addresses
+- `GetDevice(s address) -> a{ss}` device properties
+- `DeviceAdded(s address)`, `DeviceRemoved(s address)` signals
+
+Devices are re-enumerated every interval, differences are published as signals.
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    thread,
+    time::Duration,
+};
+
+use zbus::{blocking::connection, fdo, interface, object_server::SignalEmitter};
+
+use pcitool::{
+    access::Access,
+    device::{Address, Device},
+    names::{ClassCode, Names, VendorDeviceSubsystem},
+    view::DisplayMultiView,
+};
+
+pub const NAME: &str = "org.pcitool.Manager";
+pub const PATH: &str = "/org/pcitool/Manager";
+
+pub struct Manager {
+    access: Access,
+    vds: VendorDeviceSubsystem,
+    cc: ClassCode,
+    devices: BTreeMap<Address, Device>,
+}
+
+#[interface(name = "org.pcitool.Manager")]
+impl Manager {
+    /// Addresses of all devices
+    fn list_devices(&self) -> Vec<String> {
+        self.devices.keys().map(ToString::to_string).collect()
+    }
+
+    /// Device properties, missing values are omitted
+    fn get_device(&self, address: &str) -> fdo::Result<HashMap<String, String>> {
+        let address = address
+            .parse::<Address>()
+            .map_err(|err| fdo::Error::InvalidArgs(err.to_string()))?;
+        self.devices
+            .get(&address)
+            .map(|device| self.properties(device))
+            .ok_or_else(|| fdo::Error::UnknownObject(format!("no device {}", address)))
+    }
+
+    #[zbus(signal)]
+    async fn device_added(emitter: &SignalEmitter<'_>, address: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn device_removed(emitter: &SignalEmitter<'_>, address: &str) -> zbus::Result<()>;
+}
+
+impl Manager {
+    pub fn new(access: Access, names: Names) -> Self {
+        let mut manager = Self {
+            access,
+            vds: names.vendor_device_subsystem(),
+            cc: names.class_code(),
+            devices: BTreeMap::new(),
+        };
+        manager.rescan();
+        manager
+    }
+
+    /// Re-enumerate devices, returns added and removed addresses
+    pub fn rescan(&mut self) -> (Vec<Address>, Vec<Address>) {
+        let delta = self.access.refresh(&mut self.devices);
+        (delta.added, delta.removed)
+    }
+
+    fn properties(&self, device: &Device) -> HashMap<String, String> {
+        let header = &device.header;
+        let (vendor_id, device_id) = (header.vendor_id, header.device_id);
+        let cc = &header.class_code;
+        let vendor_name = self.vds.lookup(vendor_id, None, None);
+        let device_name = self.vds.lookup(vendor_id, device_id, None);
+        let class_name = self.cc.lookup(cc.base, cc.sub, None);
+        let properties = [
+            ("address", Some(device.address.to_string())),
+            ("vendor_id", Some(format!("{:04x}", vendor_id))),
+            ("device_id", Some(format!("{:04x}", device_id))),
+            (
+                "class",
+                Some(format!("{:02x}{:02x}{:02x}", cc.base, cc.sub, cc.interface)),
+            ),
+            ("revision", Some(format!("{:02x}", header.revision_id))),
+            ("vendor", vendor_name.map(String::from)),
+            ("device", device_name.map(String::from)),
+            ("class_name", class_name.map(String::from)),
+            ("driver", device.driver_in_use.clone()),
+            ("numa_node", device.numa_node.map(|n| n.to_string())),
+            ("iommu_group", device.iommu_group.clone()),
+            (
+                "link",
+                device.express_link().map(|link| {
+                    format!(
+                        "{} {}",
+                        link.status.current_link_speed.display(()),
+                        link.status.negotiated_link_width.display(())
+                    )
+                }),
+            ),
+        ];
+        properties
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect()
+    }
+}
+
+/// Serve on system (or session) bus until killed
+pub fn run(access: Access, names: Names, session: bool, interval: Duration) -> zbus::Result<()> {
+    let builder = if session {
+        connection::Builder::session()?
+    } else {
+        connection::Builder::system()?
+    };
+    let connection = builder
+        .name(NAME)?
+        .serve_at(PATH, Manager::new(access, names))?
+        .build()?;
+    let iface = connection.object_server().interface::<_, Manager>(PATH)?;
+    loop {
+        thread::sleep(interval);
+        let (added, removed) = iface.get_mut().rescan();
+        let emitter = iface.signal_emitter();
+        for address in added {
+            zbus::block_on(Manager::device_added(emitter, &address.to_string()))?;
+        }
+        for address in removed {
+            zbus::block_on(Manager::device_removed(emitter, &address.to_string()))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn inventory() {
+        let dump = crate::fixture();
+        let mut manager = Manager::new(dump.into(), Names::default());
+        let devices = manager.list_devices();
+        assert_eq!(25, devices.len());
+        assert_eq!("0000:00:00.0", devices[0]);
+
+        let properties = manager.get_device("00:1d.4").unwrap();
+        assert_eq!(
+            Some("9db4"),
+            properties.get("device_id").map(String::as_str)
+        );
+        assert_eq!(Some("8GT/s x4"), properties.get("link").map(String::as_str));
+        assert!(!properties.contains_key("driver"));
+        assert!(manager.get_device("01:00.0").is_err());
+        assert!(manager.get_device("bogus").is_err());
+
+        // Nothing changed
+        assert_eq!((vec![], vec![]), manager.rescan());
+        manager.devices.remove(&"00:1f.3".parse().unwrap());
+        assert_eq!((vec!["00:1f.3".parse().unwrap()], vec![]), manager.rescan());
+    }
+}
@@ -0,0 +1,58 @@
+/*!
+Inventory snapshots of `pci diff-inventory`
+
+Snapshots are JSON arrays of [DeviceSnapshot]. Without the second snapshot the saved one is
+compared with current devices, `--save` writes current devices instead.
+*/
+
+use std::path::PathBuf;
+
+use pcitool::inventory::{self, DeviceSnapshot};
+
+use crate::{args::DiffInventory, init_access};
+
+/// Exit status is 1 if anything changed, so it can be used as CI check
+pub fn run(args: DiffInventory) {
+    let DiffInventory {
+        old,
+        new,
+        save,
+        access,
+    } = args;
+    let read = |path: &PathBuf| {
+        std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|s| {
+                serde_json::from_str::<Vec<DeviceSnapshot>>(&s).map_err(|err| err.to_string())
+            })
+            .unwrap_or_else(|err| {
+                eprintln!("{}: {}", path.display(), err);
+                std::process::exit(1)
+            })
+    };
+    let current = || {
+        let access = init_access(&access);
+        let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices.iter().map(DeviceSnapshot::new).collect::<Vec<_>>()
+    };
+    if let Some(path) = save {
+        let result = serde_json::to_string_pretty(&current())
+            .map_err(|err| err.to_string())
+            .and_then(|s| std::fs::write(&path, s + "\n").map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1)
+        }
+        return;
+    }
+    let old = old.as_ref().map(read).unwrap_or_default();
+    let new = new.as_ref().map(read).unwrap_or_else(current);
+    let changes = inventory::diff(&old, &new);
+    for change in &changes {
+        println!("{}", change);
+    }
+    if !changes.is_empty() {
+        std::process::exit(1)
+    }
+}
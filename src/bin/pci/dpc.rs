@@ -0,0 +1,57 @@
+/*!
+Downstream Port Containment of `pci dpc`
+
+Status of the DPC capability, with sysfs also whether the kernel DPC port service is bound.
+Release clears DPC Trigger Status, so the link is brought back up.
+*/
+
+use pcitool::access::Access;
+
+use crate::{args::Dpc, guard::Guard, init_access, init_sysfs};
+
+/// Kernel DPC port service is reported only with sysfs, it owns containment when bound
+pub fn run(args: Dpc) {
+    let Dpc {
+        address,
+        release,
+        guard,
+        access,
+    } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    let Some(info) = device.dpc() else {
+        eprintln!("{}: no Downstream Port Containment capability", address);
+        std::process::exit(1)
+    };
+    println!("{}: DPC {}", address, info);
+    if let Access::LinuxSysfs(sysfs) = &access {
+        let state = if sysfs.port_service_bound(&address, "dpc") {
+            "bound"
+        } else {
+            "not bound"
+        };
+        println!("\tkernel DPC service: {}", state);
+    }
+    if !release {
+        return;
+    }
+    let result = device
+        .dpc_release()
+        .map_err(|err| err.to_string())
+        .and_then(|(offset, status)| {
+            let data = status.to_le_bytes();
+            Guard::from(guard).check_write(&access, &sysfs, &device, offset, &data)?;
+            access
+                .write(address.clone(), offset, &data)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        eprintln!("{}: {}", address, err);
+        std::process::exit(1)
+    }
+    println!("{}: containment released", address);
+}
@@ -0,0 +1,110 @@
+/*!
+Driver override of `pci driver-override`
+
+Override is written to sysfs `driver_override` and the device is rebound. Saved overrides are
+kept in driverctl format, `--apply` sets them for devices present at boot.
+*/
+
+use std::fmt;
+
+use pcitool::{
+    access::{linux_sysfs::driver_override::SavedOverrides, AccessMethod},
+    device::Address,
+};
+
+use crate::{args::DriverOverride, guard::Guard, init_sysfs};
+
+/// Driver override is a sysfs feature too, saved overrides use driverctl format
+pub fn run(args: DriverOverride) {
+    let DriverOverride {
+        address,
+        driver,
+        clear,
+        persist,
+        apply,
+        allow_bound,
+        saved_dir,
+        parameter_value,
+    } = args;
+    let linux_sysfs = init_sysfs(&parameter_value);
+    let saved = SavedOverrides::new(saved_dir);
+    // Device is rebound unless it is already bound to the requested driver. Saved overrides
+    // were confirmed when persisted, at boot devices are bound to native drivers already
+    let guard = Guard::new(true, allow_bound || apply);
+    let set = |address: &Address, driver: Option<&str>| -> Result<(), String> {
+        let device = linux_sysfs
+            .device(address.clone())
+            .map_err(|err| err.to_string())?;
+        if driver.is_none() || device.driver_in_use.as_deref() != driver {
+            guard
+                .check_bound(&device, &linux_sysfs)
+                .map_err(|refusal| refusal.to_string())?;
+        }
+        linux_sysfs
+            .set_driver_override(address, driver)
+            .map_err(|err| err.to_string())
+    };
+    if apply {
+        let present = saved
+            .present(&linux_sysfs, address.as_ref())
+            .unwrap_or_else(|err| {
+                eprintln!("{}: {}", saved.path().display(), err);
+                std::process::exit(1)
+            });
+        let mut failed = false;
+        for (address, driver) in present {
+            match set(&address, Some(&driver)) {
+                Ok(()) => println!("{}\t{}", address, driver),
+                Err(err) => {
+                    eprintln!("{}: unable to set override {}: {}", address, driver, err);
+                    failed = true;
+                }
+            }
+        }
+        if failed {
+            std::process::exit(1)
+        }
+        return;
+    }
+    // Required unless --apply is given
+    let Some(address) = address else {
+        return;
+    };
+    let exit = |action: &str, err: &dyn fmt::Display| -> ! {
+        eprintln!("{}: unable to {}: {}", address, action, err);
+        std::process::exit(1)
+    };
+    if driver.is_some() || clear {
+        if let Err(err) = set(&address, driver.as_deref()) {
+            exit("set driver override", &err)
+        }
+    }
+    if persist {
+        let result = match &driver {
+            Some(driver) => saved.save(&address, driver),
+            None if clear => saved.remove(&address),
+            None => Ok(()),
+        };
+        if let Err(err) = result {
+            exit("update saved override", &err)
+        }
+    }
+    let value = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+    let current = linux_sysfs
+        .driver_override(&address)
+        .unwrap_or_else(|err| exit("read driver override", &err));
+    let stored = saved
+        .get(&address)
+        .unwrap_or_else(|err| exit("read saved override", &err));
+    let in_use = linux_sysfs
+        .device(address.clone())
+        .ok()
+        .and_then(|device| device.driver_in_use);
+    println!(
+        "{}\toverride={} saved={} driver={}",
+        address,
+        value(current),
+        value(stored),
+        value(in_use)
+    );
+}
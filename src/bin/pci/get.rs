@@ -0,0 +1,37 @@
+/*!
+Register reads of `pci get`
+
+Registers are named as in `pci set` and read from the access method, values are printed in
+hex one per line for scripts.
+*/
+
+use crate::{args::Get, init_access, set};
+
+/// Register values in hex, one per line
+pub fn run(args: Get) {
+    let Get {
+        address,
+        registers,
+        access,
+    } = args;
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    for register in registers {
+        let result = device
+            .register_offset(&register)
+            .map_err(|err| err.to_string())
+            .and_then(|offset| {
+                set::read(&access, &address, offset, register.width).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok(value) => println!("{:0w$x}", value, w = register.width.size() * 2),
+            Err(err) => {
+                eprintln!("{}: {}: {}", address, register, err);
+                std::process::exit(1)
+            }
+        }
+    }
+}
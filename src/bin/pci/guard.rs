@@ -0,0 +1,261 @@
+/*!
+Configuration space write guard
+
+Writes may change only bits known to be safe on a running system: Command register, ASPM
+Control, slot Attention and Power Indicators and Resizable BAR sizes. Other bits of these
+registers must keep their values. Any write outside of them needs `--force`, even of the
+current value, as write-1-to-clear bits are cleared that way. Devices bound to a driver are
+refused unless `--allow-bound` is given. Access methods other than Linux sysfs do not report
+the driver, so it is resolved through sysfs, devices whose driver can not be found out are
+refused as well.
+*/
+
+use std::{fmt, fs};
+
+use pcitool::{
+    access::{linux_sysfs::LinuxSysfs, Access},
+    device::Device,
+};
+
+use crate::args::WriteGuard;
+
+/// Register bits changeable without `--force`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeBits {
+    pub register: &'static str,
+    pub offset: usize,
+    /// Register size in bytes
+    pub size: usize,
+    /// Little-endian bits starting at `offset`
+    pub mask: u32,
+}
+
+impl SafeBits {
+    const fn new(register: &'static str, offset: usize, size: usize, mask: u32) -> Self {
+        Self {
+            register,
+            offset,
+            size,
+            mask,
+        }
+    }
+    /// Safe bits of byte at `offset`, `None` outside register
+    fn byte_mask(&self, offset: usize) -> Option<u8> {
+        match offset.checked_sub(self.offset) {
+            Some(n) if n < self.size => Some((self.mask >> (n * 8)) as u8),
+            _ => None,
+        }
+    }
+}
+
+/// Safe registers of the device
+pub fn allowlist(device: &Device) -> Vec<SafeBits> {
+    let mut list = vec![SafeBits::new("COMMAND", 0x04, 2, 0x07ff)];
+    if let Some(cap) = device.capability_offset(0x10) {
+        list.push(SafeBits::new("LNKCTL ASPM", cap + 0x10, 2, 0x0003));
+        if device.slot_state().is_some() {
+            list.push(SafeBits::new("SLTCTL indicators", cap + 0x18, 2, 0x03c0));
+        }
+    }
+    list.extend(
+        device
+            .resizable_bars()
+            .into_iter()
+            .map(|info| SafeBits::new("ReBAR size", info.control_offset, 4, 0x3f00)),
+    );
+    list
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Refusal {
+    Bound(String),
+    /// Device is missing in sysfs, so it may be bound
+    UnknownDriver,
+    /// First written byte with unsafe bits
+    Unsafe(usize),
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bound(driver) => write!(
+                f,
+                "bound to driver {}, use --allow-bound to write anyway",
+                driver
+            ),
+            Self::UnknownDriver => write!(
+                f,
+                "driver is unknown without sysfs, use --allow-bound to write anyway"
+            ),
+            Self::Unsafe(offset) => write!(
+                f,
+                "write at {:03x} is outside safe register bits, use --force",
+                offset
+            ),
+        }
+    }
+}
+
+/// Write policy set by `--force` and `--allow-bound`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Guard {
+    pub force: bool,
+    pub allow_bound: bool,
+}
+
+impl Guard {
+    pub fn new(force: bool, allow_bound: bool) -> Self {
+        Self { force, allow_bound }
+    }
+    /// Check `data` written at `offset` over `current` bytes
+    pub fn check(
+        &self,
+        device: &Device,
+        sysfs: &LinuxSysfs,
+        offset: usize,
+        current: &[u8],
+        data: &[u8],
+    ) -> Result<(), Refusal> {
+        self.check_bound(device, sysfs)?;
+        if self.force {
+            return Ok(());
+        }
+        let allowlist = allowlist(device);
+        let unsafe_byte = current
+            .iter()
+            .zip(data)
+            .enumerate()
+            .find(|(n, (current, new))| {
+                let safe = allowlist.iter().find_map(|bits| bits.byte_mask(offset + n));
+                safe.is_none_or(|mask| (*current ^ *new) & !mask != 0)
+            });
+        match unsafe_byte {
+            Some((n, _)) => Err(Refusal::Unsafe(offset + n)),
+            None => Ok(()),
+        }
+    }
+    /// Device bound to a driver is refused unless allowed, the driver is looked up in `sysfs`
+    /// when the access method does not report it
+    pub fn check_bound(&self, device: &Device, sysfs: &LinuxSysfs) -> Result<(), Refusal> {
+        if self.allow_bound {
+            return Ok(());
+        }
+        if let Some(driver) = &device.driver_in_use {
+            return Err(Refusal::Bound(driver.clone()));
+        }
+        let path = sysfs.device_path(&device.address);
+        if !path.is_dir() {
+            return Err(Refusal::UnknownDriver);
+        }
+        let driver = fs::read_link(path.join("driver"))
+            .ok()
+            .and_then(|path| path.file_name()?.to_str().map(|s| s.to_string()));
+        match driver {
+            Some(driver) => Err(Refusal::Bound(driver)),
+            None => Ok(()),
+        }
+    }
+    /// Current bytes are read from `access` unless forced
+    pub fn check_write(
+        &self,
+        access: &Access,
+        sysfs: &LinuxSysfs,
+        device: &Device,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), String> {
+        let current = if self.force {
+            data.to_vec()
+        } else {
+            access
+                .read(device.address.clone(), offset, data.len())
+                .map_err(|err| err.to_string())?
+        };
+        self.check(device, sysfs, offset, &current, data)
+            .map_err(|refusal| refusal.to_string())
+    }
+}
+
+impl From<WriteGuard> for Guard {
+    fn from(WriteGuard { force, allow_bound }: WriteGuard) -> Self {
+        Self::new(force, allow_bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcitool::access::AccessMethod;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn write_guard() {
+        let dump = crate::fixture();
+        let mut device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let registers = allowlist(&device)
+            .iter()
+            .map(|bits| (bits.register, bits.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(vec![("COMMAND", 0x04), ("LNKCTL ASPM", 0x50)], registers);
+
+        // Device without driver
+        let dir = tempfile::tempdir().unwrap();
+        let sysfs = LinuxSysfs::new(dir.path());
+        fs::create_dir_all(sysfs.device_path(&device.address)).unwrap();
+        let guard = Guard::default();
+        // Bus Master off
+        assert_eq!(
+            Ok(()),
+            guard.check(&device, &sysfs, 0x04, &[0x06, 0x04], &[0x02, 0x04])
+        );
+        // ASPM L1 only, Common Clock untouched
+        assert_eq!(Ok(()), guard.check(&device, &sysfs, 0x50, &[0x43], &[0x42]));
+        // Common Clock Configuration
+        let refusal = Refusal::Unsafe(0x50);
+        assert_eq!(
+            Err(refusal),
+            guard.check(&device, &sysfs, 0x50, &[0x43], &[0x03])
+        );
+        // Write-1-to-clear Status register is changed by the same value
+        let command_status = [0x06, 0x04, 0x10, 0x00];
+        let result = guard.check(&device, &sysfs, 0x04, &command_status, &command_status);
+        assert_eq!(Err(Refusal::Unsafe(0x06)), result);
+        let force = Guard::new(true, false);
+        assert_eq!(Ok(()), force.check(&device, &sysfs, 0x50, &[0x43], &[0x03]));
+
+        device.driver_in_use = Some("nvme".into());
+        assert_eq!(
+            Err(Refusal::Bound("nvme".into())),
+            force.check(&device, &sysfs, 0x04, &[0x06], &[0x02])
+        );
+        let allow_bound = Guard::new(false, true);
+        assert_eq!(
+            Ok(()),
+            allow_bound.check(&device, &sysfs, 0x04, &[0x06], &[0x02])
+        );
+    }
+
+    #[test]
+    fn bound_guard() {
+        let dump = crate::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let sysfs = LinuxSysfs::new(dir.path());
+        let guard = Guard::new(true, false);
+        // Not in sysfs, as with a device of another machine
+        assert_eq!(
+            Err(Refusal::UnknownDriver),
+            guard.check_bound(&device, &sysfs)
+        );
+        let path = sysfs.device_path(&device.address);
+        fs::create_dir_all(&path).unwrap();
+        assert_eq!(Ok(()), guard.check_bound(&device, &sysfs));
+        std::os::unix::fs::symlink("../../../drivers/nvme", path.join("driver")).unwrap();
+        assert_eq!(
+            Err(Refusal::Bound("nvme".into())),
+            guard.check_bound(&device, &sysfs)
+        );
+        let allow_bound = Guard::new(true, true);
+        assert_eq!(Ok(()), allow_bound.check_bound(&device, &sysfs));
+    }
+}
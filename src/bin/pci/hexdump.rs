@@ -0,0 +1,24 @@
+/*!
+Configuration space hexdump of `pci hexdump`
+*/
+
+use pcitool::device;
+
+use crate::{args::Hexdump, init_access};
+
+/// Header is read from access method as [Device](device::Device) keeps it decoded only
+pub fn run(args: Hexdump) {
+    let Hexdump {
+        address,
+        range,
+        access,
+    } = args;
+    let access = init_access(&access);
+    let bytes = access
+        .read(address.clone(), range.start, range.len())
+        .unwrap_or_else(|err| {
+            eprintln!("{}: {}", address, err);
+            std::process::exit(1)
+        });
+    print!("{}", device::hexdump::Hexdump::new(range.start, &bytes));
+}
@@ -0,0 +1,19 @@
+/*!
+Persistent device identifier of `pci id`
+*/
+
+use pcitool::topology::{naming::PersistentId, Topology};
+
+use crate::{args::Id, init_access};
+
+/// Exit status is 1 if the device is not found
+pub fn run(args: Id) {
+    let Id { address, access } = args;
+    let access = init_access(&access);
+    let devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    let Some(device) = devices.iter().find(|device| device.address == address) else {
+        eprintln!("{}: device not found", address);
+        std::process::exit(1)
+    };
+    println!("{}", PersistentId::new(&Topology::new(&devices), device));
+}
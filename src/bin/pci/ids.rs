@@ -0,0 +1,37 @@
+/*!
+Names database management of `pci ids`
+
+`compile` builds the memory-mapped index of pci.ids used instead of parsing the text file.
+*/
+
+use pcitool::names::{
+    index::{self, Index},
+    Names,
+};
+
+use crate::args::IdsCompile;
+
+/// Index of `-i` or the default pci.ids, written to `-o` or the cache directory
+pub fn compile(args: IdsCompile) {
+    let IdsCompile {
+        pci_ids_path,
+        output,
+    } = args;
+    let source = pci_ids_path.unwrap_or_else(|| Names::PCI_IDS_PATH.into());
+    let Some(output) = output.or_else(Index::default_path) else {
+        eprintln!("no cache directory, pass index path with -o");
+        std::process::exit(1)
+    };
+    match index::compile(&source, &output) {
+        Ok(names) => println!(
+            "{}: {} vendor, device and subsystem names, {} class names",
+            output.display(),
+            names.vendor_device_subsystem().0.len(),
+            names.class_code().0.len()
+        ),
+        Err(err) => {
+            eprintln!("{}: {}", source.display(), err);
+            std::process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,26 @@
+/*!
+Interrupt vectors of `pci irqs`
+*/
+
+use std::path::Path;
+
+use crate::{args::Irqs, init_sysfs};
+
+/// Vectors are listed by sysfs only, counts and affinity are read from /proc
+pub fn run(args: Irqs) {
+    let Irqs {
+        address,
+        parameter_value,
+    } = args;
+    let linux_sysfs = init_sysfs(&parameter_value);
+    let vectors = linux_sysfs
+        .irq_vectors(&address, Path::new("/proc"))
+        .unwrap_or_else(|err| {
+            eprintln!("{}: no MSI or MSI-X vectors: {}", address, err);
+            std::process::exit(1)
+        });
+    println!("{}: {} vectors", address, vectors.len());
+    for vector in vectors {
+        println!("\t{}", vector);
+    }
+}
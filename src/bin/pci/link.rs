@@ -0,0 +1,54 @@
+/*!
+PCI Express links of `pci link`
+
+Current speed and width of every link with the maximum the link is capable of, downgraded
+links are marked. JSON and YAML outputs are the device records of `pci list --json`.
+*/
+
+use pcitool::{
+    device::{Device, LinkInfo},
+    view::json::Record,
+};
+
+use crate::{args::Link, init_access, init_names, print_structured};
+
+pub fn run(args: Link) {
+    let Link {
+        access,
+        pci_ids_path,
+        json,
+        yaml,
+    } = args;
+    let access = init_access(&access);
+    let mut devices = access
+        .iter()
+        .filter_map(Result::ok)
+        .filter(|device| device.express_link().is_some())
+        .collect::<Vec<Device>>();
+    devices.sort();
+    if json || yaml {
+        let names = init_names(pci_ids_path);
+        let (vds, cc) = (&names.vendor_device_subsystem(), &names.class_code());
+        let records = devices
+            .iter()
+            .map(|device| Record::new(device, vds, cc))
+            .collect::<Vec<_>>();
+        print_structured(&records, yaml);
+        return;
+    }
+    for device in &devices {
+        let Some(info) = device.link_info() else {
+            continue;
+        };
+        let max = LinkInfo {
+            speed: info.max_speed,
+            width: info.max_width.clone(),
+            ..info.clone()
+        };
+        print!("{} {}, max {}", device.address, info, max);
+        if info.is_downgraded() {
+            print!(" (downgraded)");
+        }
+        println!();
+    }
+}
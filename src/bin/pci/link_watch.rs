@@ -0,0 +1,109 @@
+/*!
+Link changes log of `pci link-watch`
+
+Selected devices are polled every interval, changes of link speed, width, training and error
+bits are printed with UTC timestamps. Devices disappearing and coming back are logged too.
+*/
+
+use std::io::{self, Write};
+
+use pcitool::topology::Topology;
+
+use crate::{args::LinkWatch, init_access};
+
+/// Log link changes of selected devices until `count` polls are done
+pub fn run(args: LinkWatch) {
+    let LinkWatch {
+        address: selector,
+        interval,
+        count,
+        access,
+    } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let topology = Topology::new(&devices);
+    let mut watched = devices
+        .iter()
+        .filter(|device| {
+            selector
+                .as_ref()
+                .is_none_or(|selector| selector.matches(&topology, device))
+        })
+        .filter_map(|device| Some((device.address.clone(), Some(device.link_sample()?))))
+        .collect::<Vec<_>>();
+    if watched.is_empty() {
+        eprintln!("no PCI Express links to watch");
+        std::process::exit(1);
+    }
+    for (address, sample) in &watched {
+        if let Some(sample) = sample {
+            println!("{} {} {}", timestamp(), address, sample);
+        }
+    }
+    let interval = std::time::Duration::from_millis(interval);
+    for _ in 0..count.unwrap_or(usize::MAX) {
+        std::thread::sleep(interval);
+        for (address, last) in watched.iter_mut() {
+            let sample = access
+                .device(address.clone())
+                .map(|device| device.link_sample());
+            *last = match (last.as_ref(), sample) {
+                (Some(last), Ok(Some(sample))) => {
+                    let changes = last.changes(&sample);
+                    if !changes.is_empty() {
+                        let changes = changes.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                        println!("{} {} {}", timestamp(), address, changes.join(", "));
+                    }
+                    Some(sample)
+                }
+                (None, Ok(Some(sample))) => {
+                    println!("{} {} back: {}", timestamp(), address, sample);
+                    Some(sample)
+                }
+                (Some(_), Ok(None)) => {
+                    println!(
+                        "{} {} lost: no PCI Express capability",
+                        timestamp(),
+                        address
+                    );
+                    None
+                }
+                (Some(_), Err(err)) => {
+                    println!("{} {} lost: {}", timestamp(), address, err);
+                    None
+                }
+                (None, _) => None,
+            };
+        }
+        let _ = io::stdout().flush();
+    }
+}
+
+/// Current UTC time as `YYYY-MM-DDThh:mm:ss.mmmZ`
+fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let (days, seconds) = (now.as_secs() / 86400, now.as_secs() % 86400);
+    // Civil from days, H. Hinnant's algorithm
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        now.subsec_millis()
+    )
+}
@@ -0,0 +1,25 @@
+/*!
+Topology checks of `pci lint`
+
+Warnings of [topology::lint] are printed one per line, so the command can be used as a check
+of firmware resource assignment.
+*/
+
+use pcitool::topology::{self, Topology};
+
+use crate::{args::Lint, init_access};
+
+/// Exit status is 1 if there are warnings
+pub fn run(args: Lint) {
+    let Lint { access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let warnings = topology::lint::check(&Topology::new(&devices));
+    for warning in &warnings {
+        println!("{}", warning);
+    }
+    if !warnings.is_empty() {
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,60 @@
+//! Library diagnostics printed to stderr. The level is taken from `PCI_LOG` environment
+//! variable (`off`, `error`, `warn`, `info`, `debug` or `trace`), warnings by default, like
+//! lspci prints pcilib warnings.
+
+use std::fmt;
+
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata, Subscriber,
+};
+
+/// Events up to `max_level` as bare messages
+struct Stderr {
+    max_level: Option<Level>,
+}
+
+/// `message` field with other fields appended as `name=value`
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
+
+impl Subscriber for Stderr {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.max_level
+            .is_some_and(|max_level| *metadata.level() <= max_level)
+    }
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut message = Message::default();
+        event.record(&mut message);
+        eprintln!("{}", message.0);
+    }
+    fn enter(&self, _: &span::Id) {}
+    fn exit(&self, _: &span::Id) {}
+}
+
+pub fn init() {
+    let max_level = match std::env::var("PCI_LOG").as_deref() {
+        Ok("off") => None,
+        Ok("error") => Some(Level::ERROR),
+        Ok("info") => Some(Level::INFO),
+        Ok("debug") => Some(Level::DEBUG),
+        Ok("trace") => Some(Level::TRACE),
+        _ => Some(Level::WARN),
+    };
+    let _ = tracing::subscriber::set_global_default(Stderr { max_level });
+}
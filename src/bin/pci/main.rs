@@ -1,99 +1,786 @@
-use std::path::PathBuf;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 
 use pcitool::{
-    access::{self, dump::Dump, linux_procfs::LinuxProcfs, linux_sysfs::LinuxSysfs, Access, Void},
-    names::Names,
-    view::lspci,
+    access::{
+        self,
+        dump::Dump,
+        ecam::Ecam,
+        linux_procfs::LinuxProcfs,
+        linux_sysfs::{modules_alias::ModulesAlias, LinuxSysfs},
+        Access, AccessError, Method,
+    },
+    device::Device,
+    names::{ClassCode, Names},
+    topology::Topology,
+    view::{
+        color::Colored,
+        json::{GroupRecord, Record},
+        lspci::{self, basic::NameWidth},
+        table::{Column, Table},
+        template::Formatted,
+    },
 };
 
 mod args;
-use args::{Args, Command, List, ParameterValue, PreferredMethod};
+use args::{
+    AccessArgs, Args, Command, GroupKey, Ids, IdsCommand, List, ParameterValue, PreferredMethod,
+    SortKey,
+};
+
+mod caps;
+
+mod class_summary;
+
+mod completions;
+
+mod config;
+use config::Config;
+
+mod diff_inventory;
+
+mod dpc;
+
+mod driver_override;
+
+mod get;
+
+mod guard;
+use guard::Guard;
+
+mod hexdump;
+
+mod id;
+
+mod ids;
+
+mod irqs;
+
+mod link;
+
+mod link_watch;
+
+mod lint;
+
+mod log;
+
+mod manpage;
+
+mod mps_audit;
+
+mod msi;
+
+mod p2p_check;
+
+mod power;
+
+mod probe;
+
+mod ptm;
+
+mod rebar;
+
+mod rom;
+
+mod set;
+
+mod slot;
+
+mod stats;
+
+mod suggest_driver;
+
+mod svm_check;
+
+mod timing;
+use timing::Timing;
+
+mod version;
+
+mod vga_route;
+
+mod virtio;
+
+mod windows;
+
+#[cfg(feature = "dbus")]
+mod daemon;
+
+#[cfg(feature = "margining")]
+mod margin;
+
+#[cfg(feature = "tui")]
+mod tui;
+
+/// Access methods fallback order from config file
+static PROBE_ORDER: OnceLock<Vec<Method>> = OnceLock::new();
+
+/// Access method from config file used when no `-A` is given
+static METHOD: OnceLock<PreferredMethod> = OnceLock::new();
 
 fn main() {
-    let args = Args::parse();
+    log::init();
+    let config = Config::init();
+    let long_version = version::long_version(config.pci_ids_path.as_deref());
+    let matches = Args::command()
+        .long_version(long_version.as_str())
+        .get_matches_from(config::args_with_env(
+            std::env::args_os(),
+            std::env::var_os("LSPCI"),
+        ));
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    if let Some(order) = config.probe_order() {
+        let _ = PROBE_ORDER.set(order);
+    }
+    if let Some(method) = config.method() {
+        let _ = METHOD.set(method);
+    }
     match args.command {
-        Command::List(args) => list(args),
-        _ => todo!(),
+        Command::List(mut args) => {
+            config.apply(&mut args);
+            list(args)
+        }
+        Command::Set(args) => set::run(args),
+        Command::Get(args) => get::run(args),
+        Command::Link(mut args) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            link::run(args)
+        }
+        Command::MpsAudit(args) => mps_audit::run(args),
+        Command::Lint(args) => lint::run(args),
+        Command::DiffInventory(args) => diff_inventory::run(args),
+        Command::Rebar(args) => rebar::run(args),
+        Command::Power(args) => power::run(args),
+        Command::DriverOverride(args) => driver_override::run(args),
+        Command::Slot(args) => slot::run(args),
+        Command::Msi(args) => msi::run(args),
+        Command::Hexdump(args) => hexdump::run(args),
+        Command::Caps(args) => caps::run(args),
+        Command::Dpc(args) => dpc::run(args),
+        Command::Ptm(args) => ptm::run(args),
+        Command::SvmCheck(args) => svm_check::run(args),
+        Command::Id(args) => id::run(args),
+        Command::Rom(args) => rom::run(args),
+        Command::Irqs(args) => irqs::run(args),
+        Command::SuggestDriver(args) => suggest_driver::run(args),
+        Command::P2pCheck(args) => p2p_check::run(args),
+        Command::Windows(args) => windows::run(args),
+        Command::LinkWatch(args) => link_watch::run(args),
+        Command::Stats(mut args) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            stats::run(args)
+        }
+        Command::Nvme(mut args) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            let columns = [
+                Column::Address,
+                Column::Device,
+                Column::Link,
+                Column::Numa,
+                Column::MsiX,
+                Column::Vfs,
+                Column::Driver,
+            ];
+            // Mass storage controller, Non-Volatile memory subclass
+            class_summary::run(args, 0x01, Some(0x08), &columns)
+        }
+        Command::Gpu(mut args) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            let columns = [
+                Column::Address,
+                Column::Device,
+                Column::Link,
+                Column::MaxLink,
+                Column::Bars,
+                Column::Rebar,
+                Column::Driver,
+            ];
+            // Display controllers of every subclass
+            class_summary::run(args, 0x03, None, &columns)
+        }
+        Command::VgaRoute(mut args) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            vga_route::run(args)
+        }
+        Command::Virtio(args) => virtio::run(args),
+        Command::Probe(args) => probe::run(args),
+        #[cfg(feature = "margining")]
+        Command::Margin(args) => margin::run(args),
+        #[cfg(feature = "dbus")]
+        Command::Daemon(args) => {
+            let access = init_access(&args.access);
+            let names = init_names(args.pci_ids_path.or(config.pci_ids_path));
+            let interval = std::time::Duration::from_secs(args.interval);
+            if let Err(err) = daemon::run(access, names, args.session, interval) {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui(args) => {
+            let access = init_access(&args.access);
+            let names = init_names(args.pci_ids_path.or(config.pci_ids_path));
+            let interval = std::time::Duration::from_secs(args.interval);
+            if let Err(err) = tui::run(access, names, interval) {
+                eprintln!("{}", err);
+                std::process::exit(1)
+            }
+        }
+        Command::Ids(Ids {
+            command: IdsCommand::Compile(mut args),
+        }) => {
+            args.pci_ids_path = args.pci_ids_path.or(config.pci_ids_path);
+            ids::compile(args)
+        }
+        Command::Completions(args) => completions::run(args),
+        Command::Manpage => manpage::run(),
     }
 }
 
 fn list(args: List) {
     let List {
-        method,
-        file,
+        access: access_args,
+        machine,
+        hex,
         verbose,
         as_numbers,
         kernel,
+        bus_centric,
+        show_power,
+        full_names,
         always_domain_number,
-        parameter_value,
         pci_ids_path,
+        identifiers,
+        format,
+        json,
+        yaml,
+        sort,
+        group_by,
+        table,
+        columns,
+        color,
+        domain,
+        timing,
+        size_bars,
+        guard,
+        #[cfg(feature = "network")]
+        query_dns,
+        #[cfg(feature = "network")]
+        query_all,
+        modules_alias,
+        retry,
+        debug_access,
+        address: selector,
         ..
     } = args;
 
-    let linux_sysfs = if let Some(ParameterValue::SysfsPath(ref path)) = parameter_value {
-        LinuxSysfs::new(path)
+    let mut timing = Timing::new(timing);
+    // Probe report goes first, init_access exits if no method is available
+    if debug_access {
+        for method in probe_order(&access_args.parameter_value) {
+            eprintln!("access: {}", method.probe());
+        }
+    }
+    let reason = match (&access_args.method, &access_args.file) {
+        (_, Some(_)) => "-F",
+        (Some(_), _) => "-A",
+        _ if METHOD.get().is_some() => "config file",
+        _ => "probe order",
+    };
+    let mut access = init_access(&access_args);
+    if debug_access {
+        let found = access.iter().filter(Result::is_ok).count();
+        eprintln!(
+            "access: selected {} ({}), {} devices",
+            access.name(),
+            reason,
+            found
+        );
+    }
+    let modules_alias = modules_alias.map(|path| {
+        ModulesAlias::init(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1);
+        })
+    });
+    if let (Access::LinuxSysfs(sysfs), Some(modules_alias)) = (&mut access, &modules_alias) {
+        sysfs.modules_alias(modules_alias.clone());
+    }
+    // Terse listing shows nothing past the standard header
+    let terse = verbose == 0 && hex == 0 && !show_power && !identifiers && !size_bars;
+    let terse = terse && !table && columns.is_empty() && format.is_none() && !json && !yaml;
+    if let Access::LinuxProcfs(procfs) = &mut access {
+        procfs.terse(terse && sort.is_none() && group_by.is_none());
+    }
+    timing.mark("access");
+
+    // Split successfully parse devices and errors
+    let iter = match &retry {
+        Some(retry) => access.iter_retry(retry),
+        None => access.iter(),
+    };
+    let (devices, errors): (Vec<_>, Vec<_>) = iter.partition(Result::is_ok);
+    let mut devices: Vec<_> = devices
+        .into_iter()
+        .map(Result::unwrap)
+        .filter(|device| domain.is_none_or(|domain| device.address.domain == domain))
+        .collect();
+    let errors: Vec<_> = errors.into_iter().map(Result::unwrap_err).collect();
+
+    devices.sort();
+    if let Some(selector) = &selector {
+        let topology = Topology::new(&devices);
+        let selected = devices
+            .iter()
+            .map(|device| selector.matches(&topology, device))
+            .collect::<Vec<_>>();
+        let mut selected = selected.into_iter();
+        devices.retain(|_| selected.next().unwrap_or_default());
+    }
+    if let Some(key) = sort {
+        sort_devices(&mut devices, key);
+    }
+    // Modules predicted from configuration space when OS does not report modalias
+    if let Some(modules_alias) = &modules_alias {
+        for device in devices
+            .iter_mut()
+            .filter(|device| device.kernel_modules.is_none())
+        {
+            device.kernel_modules = Some(modules_alias.lookup(&device.modalias()));
+        }
+    }
+    if size_bars {
+        let guard = Guard::from(guard);
+        let sysfs = init_sysfs(&access_args.parameter_value);
+        for device in devices
+            .iter_mut()
+            .filter(|device| device.resource.is_none())
+        {
+            let result = guard
+                .check_bound(device, &sysfs)
+                .map_err(|refusal| refusal.to_string())
+                .and_then(|()| device.size_bars(&access).map_err(|err| err.to_string()));
+            match result {
+                Ok(resource) => device.resource = Some(resource),
+                Err(err) => eprintln!("{}: BAR sizing failed: {}", device.address, err),
+            }
+        }
+    }
+    // Dumps are decoded as is
+    let live = !matches!(access, Access::Dump(_));
+    for device in devices
+        .iter()
+        .filter(|device| live && device.is_not_responding())
+    {
+        eprintln!(
+            "{}: device not responding, configuration space reads all ones",
+            device.address
+        );
+    }
+    timing.mark("enumerate");
+    if identifiers {
+        for device in &devices {
+            println!("{}\t{}", device.address, device.identifiers());
+        }
+        return;
+    }
+    // Show domain (slot) if any device domain != 0000
+    let always_domain_number =
+        always_domain_number || devices.iter().any(|d| d.address.domain != 0);
+    #[cfg_attr(not(feature = "network"), allow(unused_mut))]
+    let mut names = init_names(pci_ids_path);
+    #[cfg(feature = "network")]
+    if query_dns > 0 || query_all {
+        // -Q skips local database
+        if query_all {
+            names = Names::default();
+        }
+        query_network(
+            &mut names,
+            &devices,
+            query_dns > 1,
+            &access_args.parameter_value,
+        );
+    }
+    let vds = &names.vendor_device_subsystem();
+    let cc = &names.class_code();
+    timing.mark("names");
+    let groups = match group_by {
+        Some(key) => group_devices(devices, key, cc),
+        None => vec![DeviceGroup {
+            key: None,
+            heading: String::new(),
+            devices,
+        }],
+    };
+    if json || yaml {
+        let records = |devices: &[Device]| {
+            devices
+                .iter()
+                .map(|device| Record::new(device, vds, cc))
+                .collect::<Vec<_>>()
+        };
+        if group_by.is_some() {
+            let groups = groups
+                .iter()
+                .map(|group| GroupRecord {
+                    key: group.key.clone(),
+                    heading: group.heading.clone(),
+                    devices: records(&group.devices),
+                })
+                .collect::<Vec<_>>();
+            print_structured(&groups, yaml);
+        } else {
+            let devices = groups.first().map(|group| &group.devices[..]);
+            print_structured(&records(devices.unwrap_or_default()), yaml);
+        }
+        return;
+    }
+    let columns = if columns.is_empty() {
+        &Column::DEFAULT[..]
     } else {
-        LinuxSysfs::default()
+        &columns[..]
+    };
+    let args = &lspci::basic::ViewArgs {
+        verbose,
+        kernel,
+        always_domain_number,
+        as_numbers,
+        bus_centric,
+        show_power,
+        name_width: if full_names {
+            NameWidth::Full
+        } else {
+            NameWidth::Lspci
+        },
+        vds,
+        cc,
+        access: &access,
     };
-    // if let Some(path) = modules_alias {
-    //     linux_sysfs.modules_alias_path(path);
-    // }
+    let color = color.unwrap_or_default().enabled();
+    for (n, group) in groups.into_iter().enumerate() {
+        if group_by.is_some() {
+            if n > 0 {
+                println!();
+            }
+            println!("{}:", group.heading);
+        }
+        let devices = group.devices;
+        if let Some(template) = &format {
+            print!(
+                "{}",
+                Formatted {
+                    devices: &devices,
+                    template,
+                    vds,
+                    cc
+                }
+            );
+        } else if table {
+            print!(
+                "{}",
+                Table {
+                    devices: &devices,
+                    columns,
+                    vds,
+                    cc
+                }
+            );
+        } else if machine > 0 {
+            for data in &devices {
+                let view = lspci::machine::View {
+                    data,
+                    args,
+                    machine,
+                };
+                print!("{}", view);
+            }
+        } else if color {
+            for data in devices {
+                print!("{}", Colored(lspci::basic::View { data, args }));
+            }
+        } else {
+            let mut stdout = io::BufWriter::new(io::stdout().lock());
+            if let Err(err) =
+                lspci::render_to(&mut stdout, devices, args).and_then(|_| stdout.flush())
+            {
+                if err.kind() != io::ErrorKind::BrokenPipe {
+                    eprintln!("{}", err);
+                    std::process::exit(1)
+                }
+            }
+        }
+    }
+    if format.is_none() && !table {
+        for error in &errors {
+            print!("{}", error);
+        }
+    }
+}
+
+/// Devices sharing a [GroupKey] value
+struct DeviceGroup {
+    /// `None` for devices without the value
+    key: Option<String>,
+    heading: String,
+    devices: Vec<Device>,
+}
 
+/// Groups ordered by key, devices without the value go last, devices order is kept
+fn group_devices(devices: Vec<Device>, key: GroupKey, cc: &ClassCode) -> Vec<DeviceGroup> {
+    let mut groups: Vec<DeviceGroup> = Vec::new();
+    for device in devices {
+        let (key, heading) = match key {
+            GroupKey::Class => {
+                let class_code = &device.header.class_code;
+                let id = format!("{:02x}{:02x}", class_code.base, class_code.sub);
+                let heading = match cc.lookup(class_code.base, class_code.sub, None) {
+                    Some(name) => format!("{} [{}]", name, id),
+                    None => format!("Class {}", id),
+                };
+                (Some(id), heading)
+            }
+            GroupKey::Driver => match &device.driver_in_use {
+                Some(driver) => (Some(driver.clone()), driver.clone()),
+                None => (None, "No driver".into()),
+            },
+            GroupKey::IommuGroup => match &device.iommu_group {
+                Some(group) => {
+                    let group = group.trim().to_string();
+                    (Some(group.clone()), format!("IOMMU group {}", group))
+                }
+                None => (None, "No IOMMU group".into()),
+            },
+        };
+        match groups.iter_mut().find(|group| group.key == key) {
+            Some(group) => group.devices.push(device),
+            None => groups.push(DeviceGroup {
+                key,
+                heading,
+                devices: vec![device],
+            }),
+        }
+    }
+    // IOMMU groups are numbers
+    let numeric = matches!(key, GroupKey::IommuGroup);
+    groups.sort_by(|a, b| {
+        let order = |key: &Option<String>| {
+            let number = key
+                .as_deref()
+                .filter(|_| numeric)
+                .and_then(|key| key.parse::<u32>().ok());
+            (key.is_none(), number, key.clone())
+        };
+        order(&a.key).cmp(&order(&b.key))
+    });
+    groups
+}
+
+/// Print JSON or YAML document, exits on error
+fn print_structured(value: &impl serde::Serialize, yaml: bool) {
+    match structured(value, yaml) {
+        Ok(s) => println!("{}", s),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        }
+    }
+}
+
+/// Pretty JSON or YAML document
+fn structured(value: &impl serde::Serialize, yaml: bool) -> Result<String, String> {
+    let result = if yaml {
+        serde_yaml::to_string(value).map_err(|err| err.to_string())
+    } else {
+        serde_json::to_string_pretty(value).map_err(|err| err.to_string())
+    };
+    result.map(|s| s.trim_end().to_string())
+}
+
+/// Dump file given with `-F`, `-` is stdin like `lspci -F /dev/stdin`
+fn init_dump(path: PathBuf) -> access::Result<Dump> {
+    if path != Path::new("-") {
+        return Dump::init(path);
+    }
+    io::read_to_string(io::stdin())
+        .map(Dump::new)
+        .map_err(|source| AccessError::File { path, source })
+}
+
+/// Whole machine `-vvvxxxx` dump of the 8086:9dc8 laptop shared by unit tests
+#[cfg(test)]
+fn fixture() -> Dump {
+    Dump::init(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+    ))
+    .unwrap()
+}
+
+/// Access selected by `-F`, `-A`, config file method or probe order, exits on error
+fn init_access(access: &AccessArgs) -> Access {
+    let AccessArgs {
+        file,
+        method,
+        parameter_value,
+    } = access;
+    let linux_sysfs = init_sysfs(parameter_value);
+    let method = method.clone().or_else(|| METHOD.get().cloned());
     let result: access::Result<Access> = match (method, file) {
-        (_, Some(path)) => Dump::init(path).map(Into::into),
-        (Some(PreferredMethod::Dump), None) => Dump::init("/dev/stdin").map(Into::into),
+        (_, Some(path)) => init_dump(path.clone()).map(Into::into),
+        (Some(PreferredMethod::Dump), None) => init_dump("-".into()).map(Into::into),
         (Some(PreferredMethod::LinuxSysfs), _) => linux_sysfs.access(),
         (Some(PreferredMethod::LinuxProcfs), _) => {
-            let path = if let Some(ParameterValue::ProcPath(path)) = parameter_value {
-                path
+            let path = if let Some(ParameterValue::ProcPath(ref path)) = parameter_value {
+                path.clone()
             } else {
                 PathBuf::from(LinuxProcfs::PATH)
             };
             LinuxProcfs::init(path).map(Into::into)
         }
-        _ => linux_sysfs
-            .access()
-            .or_else(|_| LinuxProcfs::init(LinuxProcfs::PATH).map(Into::into))
-            .or_else(|_| Void::init().map(Into::into)),
+        (Some(PreferredMethod::Ecam), _) => match parameter_value {
+            Some(ParameterValue::EcamAddrs(regions)) => Ecam::new(regions.clone(), Ecam::MEM_PATH),
+            Some(ParameterValue::EcamAcpiMcfg(path)) => Ecam::init(path),
+            _ => Ecam::init(Ecam::MCFG_PATH),
+        }
+        .map(Into::into),
+        _ => {
+            let mut result = Err(AccessError::Platform);
+            for method in probe_order(parameter_value) {
+                result = match method {
+                    Method::LinuxSysfs => linux_sysfs.access(),
+                    _ => method.init(),
+                };
+                if result.is_ok() {
+                    break;
+                }
+            }
+            result
+        }
     };
 
     // Print errors to stderr
-    let access = result.unwrap_or_else(|err| {
+    result.unwrap_or_else(|err| {
         eprintln!("{}", err);
         std::process::exit(1)
-    });
+    })
+}
 
-    // Split successfully parse devices and errors
-    let (devices, errors): (Vec<_>, Vec<_>) = access.iter().partition(Result::is_ok);
-    let mut devices: Vec<_> = devices.into_iter().map(Result::unwrap).collect();
-    let errors: Vec<_> = errors.into_iter().map(Result::unwrap_err).collect();
+/// sysfs at `-O sysfs.path` or the default mount point
+fn init_sysfs(parameter_value: &Option<ParameterValue>) -> LinuxSysfs {
+    if let Some(ParameterValue::SysfsPath(ref path)) = parameter_value {
+        LinuxSysfs::new(path)
+    } else {
+        LinuxSysfs::default()
+    }
+}
 
-    devices.sort();
-    // Show domain (slot) if any device domain != 0000
-    let always_domain_number =
-        always_domain_number || devices.iter().any(|d| d.address.domain != 0);
-    let names = if let Some(pci_ids_path) = pci_ids_path {
+/// Command definition with help and version flags and the binary name set
+fn command() -> clap::Command<'static> {
+    let mut cmd = Args::command().bin_name("pci");
+    cmd.build();
+    cmd
+}
+
+/// `-O probe.order`, config file `probe-order` or default order
+fn probe_order(parameter_value: &Option<ParameterValue>) -> &[Method] {
+    match parameter_value {
+        Some(ParameterValue::ProbeOrder(order)) => order,
+        _ => PROBE_ORDER
+            .get()
+            .map(Vec::as_slice)
+            .unwrap_or(&Method::DEFAULT_ORDER),
+    }
+}
+
+/// Stable sort of devices sorted by address
+fn sort_devices(devices: &mut [Device], key: SortKey) {
+    match key {
+        SortKey::Class => devices.sort_by_key(|device| {
+            let cc = &device.header.class_code;
+            (cc.base, cc.sub, cc.interface)
+        }),
+        SortKey::Vendor => devices.sort_by_key(|device| device.header.vendor_id),
+        SortKey::Driver => devices.sort_by(|a, b| {
+            let (a, b) = (&a.driver_in_use, &b.driver_in_use);
+            (a.is_none(), a).cmp(&(b.is_none(), b))
+        }),
+        SortKey::Numa => {
+            devices.sort_by_key(|device| (device.numa_node.is_none(), device.numa_node))
+        }
+        SortKey::LinkSpeed => devices.sort_by_key(|device| {
+            let speed = device
+                .express_link()
+                .map(|link| u8::from(link.status.current_link_speed));
+            (speed.is_none(), speed)
+        }),
+    }
+}
+
+fn init_names(pci_ids_path: Option<PathBuf>) -> Names {
+    if let Some(pci_ids_path) = pci_ids_path {
         Names::init_pciids(pci_ids_path).unwrap_or_default()
     } else {
         Names::init().unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "network")]
+fn query_network(
+    names: &mut Names,
+    devices: &[pcitool::device::Device],
+    refresh: bool,
+    parameter_value: &Option<ParameterValue>,
+) {
+    use pcitool::names::net::{self, Cache, Key, NetQuery};
+
+    let domain = match parameter_value {
+        Some(ParameterValue::NetDomain(domain)) => domain.clone(),
+        _ => net::DOMAIN.into(),
     };
-    let vds = &names.vendor_device_subsystem();
-    let cc = &names.class_code();
-    let args = &lspci::basic::ViewArgs {
-        verbose,
-        kernel,
-        always_domain_number,
-        as_numbers,
-        vds,
-        cc,
-        access: &access,
+    let cache_path = match parameter_value {
+        Some(ParameterValue::NetCacheName(path)) => Some(path.clone()),
+        _ => Cache::default_path(),
     };
-    for data in devices {
-        print!("{}", lspci::basic::View { data, args });
+    let cache = cache_path
+        .as_ref()
+        .and_then(|path| Cache::load(path).ok())
+        .unwrap_or_default();
+    let mut query = NetQuery::new(domain, cache, refresh);
+    names.query_network(devices.iter().flat_map(Key::device_keys), &mut query);
+    if let Some(path) = cache_path {
+        if let Err(err) = query.cache.save(&path) {
+            eprintln!("Cannot write cache {}: {}", path.display(), err);
+        }
     }
-    for error in &errors {
-        print!("{}", error);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcitool::access::AccessMethod;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn yaml_records() {
+        let device = fixture().device("04:00.0".parse().unwrap()).unwrap();
+        let record = Record::new(&device, &Default::default(), &Default::default());
+        let records = [record];
+        let yaml = structured(&records, true).unwrap();
+        let sample = "\
+            - address: 0000:04:00.0\n  \
+              status: ok\n  \
+              class: '0108'\n";
+        assert_eq!(sample, &yaml[..sample.len()]);
+        // Same document as JSON output
+        let json = structured(&records, false).unwrap();
+        let from_yaml: serde_json::Value = serde_yaml::from_str(&yaml).unwrap();
+        let from_json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, from_yaml);
     }
 }
@@ -0,0 +1,157 @@
+/*!
+Man page in roff format
+
+Page is rendered from the clap command definition: global options, then a section per
+subcommand with its options, value names and possible values. lspci compatible flags of `list`
+are documented with the same help text as `pci list --help`.
+*/
+
+use std::io::{self, Write};
+
+use clap::{Arg, Command};
+
+use crate::{command, completions::possible_values};
+
+/// Escape text for roff, lines starting with a control character are protected
+fn roff(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    text.lines()
+        .map(|line| {
+            if line.starts_with('.') || line.starts_with('\'') {
+                format!("\\&{}", line)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Option spelling with value name, e.g. `-A method`
+fn synopsis(arg: &Arg) -> String {
+    let short = arg.get_short().map(|c| format!("\\fB\\-{}\\fR", c));
+    let long = arg.get_long().map(|s| format!("\\fB\\-\\-{}\\fR", roff(s)));
+    let mut spelling = short.into_iter().chain(long).collect::<Vec<_>>().join(", ");
+    if arg.is_takes_value_set() {
+        let names = arg.get_value_names().unwrap_or(&[]);
+        let value = match names {
+            [] => arg.get_id().to_string(),
+            names => names.join(" "),
+        };
+        let separator = if arg.is_require_equals_set() {
+            "[="
+        } else {
+            " "
+        };
+        let end = if arg.is_require_equals_set() { "]" } else { "" };
+        spelling.push_str(&format!("{}\\fI{}\\fR{}", separator, roff(&value), end));
+    }
+    spelling
+}
+
+fn options(cmd: &Command, out: &mut impl Write) -> io::Result<()> {
+    for arg in cmd.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        writeln!(out, ".TP")?;
+        if arg.is_positional() {
+            let name = arg
+                .get_value_names()
+                .and_then(|names| names.first().copied());
+            writeln!(
+                out,
+                "\\fI{}\\fR",
+                roff(name.unwrap_or_else(|| arg.get_id()))
+            )?;
+        } else {
+            writeln!(out, "{}", synopsis(arg))?;
+        }
+        if let Some(help) = arg.get_long_help().or_else(|| arg.get_help()) {
+            writeln!(out, "{}", roff(help))?;
+        }
+        let values = possible_values(arg);
+        if !values.is_empty() {
+            writeln!(out, ".br")?;
+            writeln!(out, "Possible values: {}", roff(&values.join(", ")))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write man page of `cmd`, it should be built
+pub fn render(cmd: &Command, out: &mut impl Write) -> io::Result<()> {
+    let name = cmd.get_bin_name().unwrap_or_else(|| cmd.get_name());
+    let version = cmd.get_version().unwrap_or_default();
+    writeln!(
+        out,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        roff(&name.to_uppercase()),
+        roff(name),
+        roff(version)
+    )?;
+    writeln!(out, ".SH NAME")?;
+    let about = cmd
+        .get_about()
+        .unwrap_or("list, inspect and configure PCI devices");
+    writeln!(out, "{} \\- {}", roff(name), roff(about))?;
+    writeln!(out, ".SH SYNOPSIS")?;
+    writeln!(
+        out,
+        "\\fB{}\\fR [\\fIoptions\\fR] \\fIcommand\\fR",
+        roff(name)
+    )?;
+    writeln!(out, ".SH OPTIONS")?;
+    options(cmd, out)?;
+    writeln!(out, ".SH COMMANDS")?;
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        writeln!(out, ".SS \"{} {}\"", roff(name), roff(sub.get_name()))?;
+        let aliases = sub.get_all_aliases().collect::<Vec<_>>();
+        if !aliases.is_empty() {
+            writeln!(out, "Aliases: {}", roff(&aliases.join(", ")))?;
+            writeln!(out, ".br")?;
+        }
+        if let Some(about) = sub.get_long_about().or_else(|| sub.get_about()) {
+            writeln!(out, "{}", roff(about))?;
+        }
+        options(sub, out)?;
+    }
+    if let Some(author) = cmd.get_author() {
+        writeln!(out, ".SH AUTHORS")?;
+        writeln!(out, "{}", roff(author))?;
+    }
+    Ok(())
+}
+
+/// Page of `pci` and every subcommand to stdout
+pub fn run() {
+    let mut stdout = io::stdout().lock();
+    if let Err(err) = render(&command(), &mut stdout) {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Args;
+    use clap::CommandFactory;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn escape() {
+        assert_eq!("\\-vv\n\\&.TP \\e", roff("-vv\n.TP \\"));
+    }
+
+    #[test]
+    fn page() {
+        let mut cmd = Args::command().bin_name("pci");
+        cmd.build();
+        let mut out = Vec::new();
+        render(&cmd, &mut out).unwrap();
+        let page = String::from_utf8(out).unwrap();
+        assert!(page.starts_with(".TH PCI 1 \"\" \"pci "));
+        assert!(page.contains(".SS \"pci list\"\nAliases: ls\n.br\nList all PCI devices\n"));
+        assert!(page.contains("\\fB\\-F\\fR, \\fB\\-\\-file\\fR \\fIfile\\fR\n"));
+        assert!(page.contains("\\fB\\-\\-color\\fR[=\\fIwhen\\fR]\n"));
+        assert!(page.contains("Possible values: linux\\-sysfs, linux\\-proc"));
+    }
+}
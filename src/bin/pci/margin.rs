@@ -0,0 +1,57 @@
+/*!
+Lane Margining at the Receiver of `pci margin`
+
+Lanes of the link are margined one by one, eye width and height are reported per lane.
+*/
+
+use pcitool::device::margining::MarginSettings;
+
+use crate::{args::Margin, guard::Guard, init_access, init_sysfs};
+
+/// Ports are usually bound to the kernel port driver, margining needs `--allow-bound` then
+pub fn run(args: Margin) {
+    let Margin {
+        address,
+        lanes,
+        receiver,
+        error_limit,
+        dwell,
+        allow_bound,
+        access,
+    } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    // Margining commands go through the Margining capability, only the driver matters
+    if let Err(refusal) = Guard::new(true, allow_bound).check_bound(&device, &sysfs) {
+        eprintln!("{}: {}", address, refusal);
+        std::process::exit(1)
+    }
+    let lanes = if lanes.is_empty() {
+        let width = device.link_info().map(|info| u8::from(info.width));
+        (0..width.unwrap_or_default() as usize).collect()
+    } else {
+        lanes
+    };
+    let settings = MarginSettings {
+        receiver,
+        error_limit,
+        dwell: std::time::Duration::from_millis(dwell),
+    };
+    let mut failed = false;
+    for lane in lanes {
+        match device.margin_lane(&access, lane, &settings) {
+            Ok(eye) => println!("{}: {}", address, eye),
+            Err(err) => {
+                eprintln!("{}: lane {}: {}", address, lane, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,367 @@
+/*!
+MaxPayload/MaxReadReq audit
+
+Every root-port-to-endpoint path should run with the same Max_Payload_Size. The safe value
+for a hierarchy is the smallest Max_Payload_Size Supported of all its functions, since
+bridges are shared between paths. Endpoints with Max_Read_Request_Size below the path
+Max_Payload_Size split reads into needlessly small requests.
+
+Fix writes Device Control register directly, kernel is not notified.
+*/
+
+use std::{collections::BTreeSet, fmt};
+
+use pcics::capabilities::{
+    pci_express::{DeviceType, MaxSize},
+    CapabilityKind,
+};
+
+use pcitool::{
+    access::{self, linux_sysfs::LinuxSysfs, Access},
+    device::{Address, Device, DeviceDependentRegion},
+    topology::Topology,
+    view::DisplayMultiView,
+};
+
+use crate::{args::MpsAudit, guard::Guard, init_access, init_sysfs};
+
+/// Device Control register offset in PCI Express capability
+const DEVICE_CONTROL: usize = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Register {
+    MaxPayload,
+    MaxReadReq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Problem {
+    pub register: Register,
+    pub current: MaxSize,
+    pub expected: MaxSize,
+}
+
+/// PCI Express function on a path
+#[derive(Debug, Clone)]
+pub struct Node<'a> {
+    pub device: &'a Device,
+    /// PCI Express capability pointer
+    pointer: u8,
+    pub mpss: MaxSize,
+    pub mps: MaxSize,
+    pub mrrs: MaxSize,
+    pub problems: Vec<Problem>,
+}
+
+impl<'a> Node<'a> {
+    fn new(device: &'a Device) -> Option<Self> {
+        device
+            .capabilities()?
+            .flatten()
+            .find_map(|cap| match cap.kind {
+                CapabilityKind::PciExpress(pcie) => Some(Self {
+                    device,
+                    pointer: cap.pointer,
+                    mpss: pcie.device.capabilities.max_payload_size_supported,
+                    mps: pcie.device.control.max_payload_size,
+                    mrrs: pcie.device.control.max_read_request_size,
+                    problems: Vec::new(),
+                }),
+                _ => None,
+            })
+    }
+    fn is_endpoint(&self) -> bool {
+        self.device
+            .capabilities()
+            .into_iter()
+            .flat_map(|caps| caps.flatten())
+            .any(|cap| {
+                matches!(
+                    cap.kind,
+                    CapabilityKind::PciExpress(pcie) if matches!(
+                        pcie.device_type,
+                        DeviceType::Endpoint { .. } | DeviceType::LegacyEndpoint { .. }
+                    )
+                )
+            })
+    }
+    fn device_control_offset(&self) -> usize {
+        self.pointer as usize + DEVICE_CONTROL
+    }
+    /// Raw Device Control register
+    fn device_control(&self) -> Option<u16> {
+        let offset = self.device_control_offset() - DeviceDependentRegion::OFFSET;
+        let ddr = self.device.device_dependent_region.as_ref()?;
+        let bytes = ddr.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    /// Device Control register with all problems fixed
+    fn fixed_device_control(&self) -> Option<u16> {
+        let control = self.device_control()?;
+        Some(self.problems.iter().fold(control, |control, problem| {
+            let value = problem.expected as u16;
+            match problem.register {
+                Register::MaxPayload => (control & !0x00e0) | (value << 5),
+                Register::MaxReadReq => (control & !0x7000) | (value << 12),
+            }
+        }))
+    }
+}
+
+/// Root port to endpoint path
+#[derive(Debug, Clone)]
+pub struct Path<'a> {
+    pub nodes: Vec<Node<'a>>,
+}
+
+impl Path<'_> {
+    pub fn is_ok(&self) -> bool {
+        self.nodes.iter().all(|node| node.problems.is_empty())
+    }
+}
+
+/// Smaller encoded value is smaller size
+fn min(a: MaxSize, b: MaxSize) -> MaxSize {
+    if (b as u8) < (a as u8) {
+        b
+    } else {
+        a
+    }
+}
+
+pub fn audit(devices: &[Device]) -> Vec<Path<'_>> {
+    let topology = Topology::new(devices);
+    let mut paths = devices
+        .iter()
+        .filter_map(|device| {
+            let endpoint = Node::new(device).filter(Node::is_endpoint)?;
+            let mut nodes = topology.path(device);
+            nodes.pop();
+            let mut nodes = nodes.into_iter().filter_map(Node::new).collect::<Vec<_>>();
+            // Root complex integrated endpoints have no link
+            if nodes.is_empty() {
+                return None;
+            }
+            nodes.push(endpoint);
+            Some(Path { nodes })
+        })
+        .collect::<Vec<_>>();
+    // Safe MPS for every hierarchy under the same root port
+    let roots = paths
+        .iter()
+        .map(|path| path.nodes[0].device.address.clone())
+        .collect::<BTreeSet<_>>();
+    for root in roots {
+        let under_root = |path: &&mut Path| path.nodes[0].device.address == root;
+        let expected = paths
+            .iter_mut()
+            .filter(under_root)
+            .flat_map(|path| path.nodes.iter().map(|node| node.mpss))
+            .reduce(min)
+            .unwrap_or(MaxSize::B128);
+        for path in paths.iter_mut().filter(under_root) {
+            let last = path.nodes.len() - 1;
+            for (n, node) in path.nodes.iter_mut().enumerate() {
+                if node.mps != expected {
+                    node.problems.push(Problem {
+                        register: Register::MaxPayload,
+                        current: node.mps,
+                        expected,
+                    });
+                }
+                if n == last && (node.mrrs as u8) < (expected as u8) {
+                    node.problems.push(Problem {
+                        register: Register::MaxReadReq,
+                        current: node.mrrs,
+                        expected,
+                    });
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// Write fixed Device Control registers, every device at most once
+pub fn fix(
+    access: &Access,
+    sysfs: &LinuxSysfs,
+    paths: &[Path],
+    guard: &Guard,
+) -> Vec<(Address, Result<(), String>)> {
+    let mut fixed = BTreeSet::new();
+    paths
+        .iter()
+        .flat_map(|path| &path.nodes)
+        .filter(|node| !node.problems.is_empty())
+        .filter(|node| fixed.insert(node.device.address.clone()))
+        .map(|node| {
+            let offset = node.device_control_offset();
+            let result = node
+                .fixed_device_control()
+                .ok_or(access::AccessError::ConfigurationSpace)
+                .map_err(|err| err.to_string())
+                .and_then(|control| {
+                    let data = control.to_le_bytes();
+                    guard.check_write(access, sysfs, node.device, offset, &data)?;
+                    access
+                        .write(node.device.address.clone(), offset, &data)
+                        .map_err(|err| err.to_string())
+                });
+            (node.device.address.clone(), result)
+        })
+        .collect()
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxPayload => write!(f, "MaxPayload"),
+            Self::MaxReadReq => write!(f, "MaxReadReq"),
+        }
+    }
+}
+
+/// `<root> -> ... -> <endpoint>: MaxPayload <sizes> MaxReadReq <size>` followed by problems
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addresses = self
+            .nodes
+            .iter()
+            .map(|node| node.device.address.to_string())
+            .collect::<Vec<_>>();
+        let mps = self
+            .nodes
+            .iter()
+            .map(|node| node.mps.display(()).to_string())
+            .collect::<Vec<_>>();
+        let mrrs = self
+            .nodes
+            .last()
+            .map(|node| node.mrrs.display(()).to_string());
+        write!(
+            f,
+            "{}: MaxPayload {}",
+            addresses.join(" -> "),
+            mps.join("/")
+        )?;
+        writeln!(f, " MaxReadReq {}", mrrs.unwrap_or_default())?;
+        for node in &self.nodes {
+            for problem in &node.problems {
+                writeln!(
+                    f,
+                    "\t{}: {} {} bytes, expected {} bytes",
+                    node.device.address,
+                    problem.register,
+                    problem.current.display(()),
+                    problem.expected.display(()),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exit status is 1 if a path is misconfigured and not fixed
+pub fn run(args: MpsAudit) {
+    let MpsAudit { access, fix, guard } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let paths = audit(&devices);
+    for path in &paths {
+        print!("{}", path);
+    }
+    if paths.iter().all(Path::is_ok) {
+        return;
+    }
+    if !fix {
+        std::process::exit(1)
+    }
+    let mut failed = false;
+    let guard = Guard::from(guard);
+    for (address, result) in self::fix(&access, &sysfs, &paths, &guard) {
+        match result {
+            Ok(()) => println!("{}: fixed", address),
+            Err(err) => {
+                eprintln!("{}: {}", address, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcitool::access::AccessMethod;
+    use pretty_assertions::assert_eq;
+
+    fn devices() -> Vec<Device> {
+        let dump = crate::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices
+    }
+
+    #[test]
+    fn consistent() {
+        let devices = devices();
+        let paths = audit(&devices);
+        let result = paths.iter().map(ToString::to_string).collect::<String>();
+        let sample = "\
+            0000:00:1c.4 -> 0000:02:00.0: MaxPayload 256/256 MaxReadReq 512\n\
+            0000:00:1d.0 -> 0000:03:00.0: MaxPayload 128/128 MaxReadReq 512\n\
+            0000:00:1d.4 -> 0000:04:00.0: MaxPayload 256/256 MaxReadReq 512\n\
+        ";
+        assert_eq!(sample, result);
+        assert!(paths.iter().all(Path::is_ok));
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut devices = devices();
+        let nvme = devices
+            .iter_mut()
+            .find(|d| d.address == "04:00.0".parse().unwrap())
+            .unwrap();
+        let node = Node::new(nvme).unwrap();
+        // MPS 512, MRRS 128
+        let offset = node.device_control_offset() - DeviceDependentRegion::OFFSET;
+        let control = (node.device_control().unwrap() & !0x70e0) | 0x0040;
+        let ddr = nvme.device_dependent_region.as_mut().unwrap();
+        ddr.0[offset..offset + 2].copy_from_slice(&control.to_le_bytes());
+
+        let paths = audit(&devices);
+        let path = &paths[2];
+        let sample = "\
+            0000:00:1d.4 -> 0000:04:00.0: MaxPayload 256/512 MaxReadReq 128\n\
+            \t0000:04:00.0: MaxPayload 512 bytes, expected 256 bytes\n\
+            \t0000:04:00.0: MaxReadReq 128 bytes, expected 256 bytes\n\
+        ";
+        assert_eq!(sample, path.to_string());
+        let node = &path.nodes[1];
+        assert_eq!(
+            Some(0x1020),
+            node.fixed_device_control().map(|c| c & 0x70e0)
+        );
+
+        // Dump is read-only
+        let access = Access::Void(pcitool::access::Void);
+        let result = fix(
+            &access,
+            &LinuxSysfs::default(),
+            &paths,
+            &Guard::new(true, true),
+        );
+        let read_only = access::AccessError::ReadOnly.to_string();
+        assert!(matches!(
+            result.as_slice(),
+            [(address, Err(err))] if address.to_string() == "0000:04:00.0" && *err == read_only
+        ));
+    }
+}
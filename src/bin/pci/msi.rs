@@ -0,0 +1,95 @@
+/*!
+MSI and MSI-X control of `pci msi`
+
+Enable bits, per-vector masks and MSI-X Function Mask are changed in the capabilities and
+MSI-X table, without changes the current state is printed.
+*/
+
+use pcitool::device::{msi::MsiSetting, Device};
+
+use crate::{args::Msi, guard::Guard, init_access, init_sysfs};
+
+/// Changes are resolved before anything is written
+pub fn run(args: Msi) {
+    let Msi {
+        address,
+        disable,
+        enable,
+        mask,
+        unmask,
+        function_mask,
+        guard,
+        access,
+    } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    let print_state = |device: &Device| {
+        let msi = device.msi_control();
+        let msi_x = device.msi_x_control();
+        if msi.is_none() && msi_x.is_none() {
+            println!("{}: no MSI or MSI-X capability", address);
+        }
+        for state in msi
+            .map(|msi| msi.to_string())
+            .into_iter()
+            .chain(msi_x.map(|msi_x| msi_x.to_string()))
+        {
+            println!("{}: {}", address, state);
+        }
+    };
+    let mut settings = Vec::new();
+    if disable {
+        if device.msi_control().is_some() {
+            settings.push(MsiSetting::Msi(false));
+        }
+        if device.msi_x_control().is_some() {
+            settings.push(MsiSetting::MsiX(false));
+        }
+    }
+    match enable.as_deref() {
+        Some("msi") => settings.push(MsiSetting::Msi(true)),
+        Some("msix") => settings.push(MsiSetting::MsiX(true)),
+        _ => (),
+    }
+    settings.extend(mask.into_iter().map(|vector| MsiSetting::Mask {
+        vector,
+        masked: true,
+    }));
+    settings.extend(unmask.into_iter().map(|vector| MsiSetting::Mask {
+        vector,
+        masked: false,
+    }));
+    settings.extend(function_mask.map(|value| MsiSetting::FunctionMask(value == "on")));
+    if settings.is_empty() {
+        print_state(&device);
+        return;
+    }
+    let writes = device.msi_writes(&settings).unwrap_or_else(|err| {
+        eprintln!("{}: {}", address, err);
+        std::process::exit(1)
+    });
+    // Nothing is written unless every write passes the guard
+    let guard = Guard::from(guard);
+    for &(offset, value, width) in &writes {
+        let data = &value.to_le_bytes()[..width.size()];
+        if let Err(err) = guard.check_write(&access, &sysfs, &device, offset, data) {
+            eprintln!("{}: {}", address, err);
+            std::process::exit(1)
+        }
+    }
+    for (offset, value, width) in writes {
+        let data = &value.to_le_bytes()[..width.size()];
+        if let Err(err) = access.write(address.clone(), offset, data) {
+            eprintln!("{}: {}", address, err);
+            std::process::exit(1)
+        }
+    }
+    match access.device(address.clone()) {
+        Ok(device) => print_state(&device),
+        Err(err) => eprintln!("{}: {}", address, err),
+    }
+}
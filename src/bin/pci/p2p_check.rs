@@ -0,0 +1,57 @@
+/*!
+Peer-to-peer DMA path of `pci p2p-check`
+
+Path between two devices through their common upstream bridge with ACS settings redirecting
+peer-to-peer requests to the root complex.
+*/
+
+use pcitool::{
+    device,
+    topology::{
+        p2p::{P2pPath, Verdict},
+        Topology,
+    },
+};
+
+use crate::{args::P2pCheck, init_access};
+
+/// Exit status is 1 if ACS redirects peer-to-peer transactions
+pub fn run(args: P2pCheck) {
+    let P2pCheck { a, b, access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let find = |address: &device::Address| {
+        devices
+            .iter()
+            .find(|device| device.address == *address)
+            .unwrap_or_else(|| {
+                eprintln!("{}: device not found", address);
+                std::process::exit(1)
+            })
+    };
+    let topology = Topology::new(&devices);
+    let path = P2pPath::new(&topology, find(&a), find(&b));
+    let up = path.a.iter().map(|d| d.address.to_string());
+    let down = path.b.iter().rev().map(|d| d.address.to_string());
+    let common = path
+        .common
+        .map_or_else(|| "[root complex]".to_string(), |d| d.address.to_string());
+    let hops = up.chain(Some(common)).chain(down).collect::<Vec<_>>();
+    println!("Path: {}", hops.join(" -> "));
+    match path.common {
+        Some(common) => println!("Common upstream bridge: {}", common.address),
+        None => println!("Common upstream bridge: none"),
+    }
+    for redirect in path.redirects() {
+        println!("ACS redirect: {} {}", redirect.device.address, redirect.acs);
+    }
+    let verdict = path.verdict();
+    println!("P2P DMA: {}", verdict);
+    if let Some(parameter) = path.override_parameter() {
+        println!("Override: {}", parameter);
+    }
+    if verdict == Verdict::Redirected {
+        std::process::exit(1)
+    }
+}
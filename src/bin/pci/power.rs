@@ -0,0 +1,38 @@
+/*!
+Runtime power management of `pci power`
+
+Runtime PM state is read from and `power/control` is written to sysfs.
+*/
+
+use pcitool::access::AccessMethod;
+
+use crate::{
+    args::{Power, PowerSetting},
+    init_sysfs,
+};
+
+/// Runtime PM is a sysfs feature, so other access methods are not used
+pub fn run(args: Power) {
+    let Power {
+        address,
+        set,
+        parameter_value,
+    } = args;
+    let linux_sysfs = init_sysfs(&parameter_value);
+    if let Some(PowerSetting::Control(control)) = set {
+        if let Err(err) = linux_sysfs.set_power_control(&address, control) {
+            eprintln!("{}: unable to set control={}: {}", address, control, err);
+            std::process::exit(1)
+        }
+    }
+    match linux_sysfs.device(address.clone()) {
+        Ok(device) => match device.power_info {
+            Some(info) => println!("{}\t{}", address, info),
+            None => println!("{}\tRuntime PM unavailable", address),
+        },
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        }
+    }
+}
@@ -0,0 +1,13 @@
+/*!
+Access methods probing of `pci probe`
+
+Every method of the probing order is tried and the devices found or the error is reported.
+*/
+
+use crate::{args::Probe, probe_order};
+
+pub fn run(args: Probe) {
+    for method in probe_order(&args.parameter_value) {
+        println!("{}", method.probe());
+    }
+}
@@ -0,0 +1,27 @@
+/*!
+Precision Time Measurement path of `pci ptm`
+
+PTM state of every device from the PTM Root down to the selected device.
+*/
+
+use pcitool::{device::ptm::PtmPath, topology::Topology};
+
+use crate::{args::Ptm, init_access};
+
+/// Exit status is 1 if the device is not found or has no PTM capability
+pub fn run(args: Ptm) {
+    let Ptm { address, access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let Some(device) = devices.iter().find(|device| device.address == address) else {
+        eprintln!("{}: device not found", address);
+        std::process::exit(1)
+    };
+    if device.ptm().is_none() {
+        eprintln!("{}: no Precision Time Measurement capability", address);
+        std::process::exit(1)
+    }
+    let topology = Topology::new(&devices);
+    print!("{}", PtmPath::new(topology.path(device)));
+}
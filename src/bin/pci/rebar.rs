@@ -0,0 +1,64 @@
+/*!
+Resizable BAR sizes of `pci rebar`
+
+Without a size every Resizable BAR is listed with its current and supported sizes. Resizing
+writes the BAR Control register, the device must not decode memory meanwhile.
+*/
+
+use crate::{args::Rebar, guard::Guard, init_access, init_sysfs};
+
+/// BAR must not be in use: driver unbound unless `--allow-bound` and memory decoding disabled
+pub fn run(args: Rebar) {
+    let Rebar {
+        address,
+        bar,
+        size,
+        guard,
+        access,
+    } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    if let (Some(bar), Some(size)) = (bar, size) {
+        let result = device
+            .resizable_bar_control(bar, size)
+            .map_err(|err| err.to_string())
+            .and_then(|(offset, control)| {
+                let data = control.to_le_bytes();
+                Guard::from(guard).check_write(&access, &sysfs, &device, offset, &data)?;
+                if device.header.command.memory_space {
+                    return Err("memory decoding is enabled, disable it before resizing".into());
+                }
+                access
+                    .write(address.clone(), offset, &data)
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            eprintln!("{}: {}", address, err);
+            std::process::exit(1)
+        }
+        println!("{}: BAR {} size set to {}", address, bar, size);
+        return;
+    }
+    let bars = device.resizable_bars();
+    if bars.is_empty() {
+        eprintln!("{}: no Resizable BAR capability", address);
+        std::process::exit(1)
+    }
+    for info in bars {
+        let supported = info
+            .supported
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        println!(
+            "BAR {}: current size: {}, supported: {}",
+            info.bar,
+            info.current,
+            supported.join(" ")
+        );
+    }
+}
@@ -0,0 +1,41 @@
+/*!
+Expansion ROM of `pci rom`
+
+ROM is read through sysfs, optionally saved to a file, and its images are decoded.
+*/
+
+use pcitool::{access::AccessMethod, misc::rom::RomImages};
+
+use crate::{args::Rom, init_sysfs};
+
+/// Expansion ROM is read through sysfs `rom` attribute, so other access methods are not used
+pub fn run(args: Rom) {
+    let Rom {
+        address,
+        output,
+        parameter_value,
+    } = args;
+    let linux_sysfs = init_sysfs(&parameter_value);
+    let data = linux_sysfs
+        .expansion_rom(address.clone())
+        .unwrap_or_else(|err| {
+            eprintln!("{}: unable to read expansion ROM: {}", address, err);
+            std::process::exit(1)
+        });
+    if let Some(path) = output {
+        if let Err(err) = std::fs::write(&path, &data) {
+            eprintln!("{}: {}", path.display(), err);
+            std::process::exit(1)
+        }
+    }
+    println!("{}: expansion ROM, {} bytes", address, data.len());
+    for (n, image) in RomImages::new(&data).enumerate() {
+        match image {
+            Ok(image) => println!("\tImage {}: {}", n, image),
+            Err(err) => {
+                eprintln!("\tImage {}: {}", n, err);
+                std::process::exit(1)
+            }
+        }
+    }
+}
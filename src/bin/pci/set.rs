@@ -0,0 +1,388 @@
+/*!
+Configuration register writes
+
+Assignments use `setpci` syntax `<register>=<value>[:<mask>]`, values and masks are hex. Only
+bits set in mask are changed, the rest of register is read from device first.
+
+Batch file has one device per line: `<address> <assignment>...`, `#` starts a comment. Every
+line is parsed and every register is resolved before the first write.
+*/
+
+use std::{fmt, str::FromStr};
+
+use pcitool::{
+    access::{self, linux_sysfs::LinuxSysfs, Access},
+    device::{
+        registers::{RegisterAddress, Width},
+        Address, Device,
+    },
+};
+
+use crate::{args::Set, guard::Guard, init_access, init_sysfs};
+
+/// Single register assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment {
+    pub register: RegisterAddress,
+    pub value: u32,
+    pub mask: u32,
+}
+
+fn parse_hex(s: &str) -> Result<u32, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u32::from_str_radix(digits, 16).map_err(|_| format!("invalid hex value '{}'", s))
+}
+
+impl FromStr for Assignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (register, value) = s
+            .split_once('=')
+            .ok_or_else(|| "assignment should be <register>=<value>[:<mask>]".to_string())?;
+        let register: RegisterAddress = register.parse().map_err(|err| format!("{}", err))?;
+        let width_mask = register.width.mask();
+        let (value, mask) = match value.split_once(':') {
+            Some((value, mask)) => (parse_hex(value)?, parse_hex(mask)?),
+            None => (parse_hex(value)?, width_mask),
+        };
+        if value & !width_mask != 0 || mask & !width_mask != 0 {
+            return Err(format!(
+                "value does not fit {}-byte register",
+                register.width.size()
+            ));
+        }
+        Ok(Self {
+            register,
+            value,
+            mask,
+        })
+    }
+}
+
+/// Assignments for a single device
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    pub address: Address,
+    pub assignments: Vec<Assignment>,
+}
+
+/// Parse batch file content
+pub fn parse_batch(s: &str) -> Result<Vec<Operation>, String> {
+    s.lines()
+        .enumerate()
+        .filter_map(|(n, line)| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            (!line.is_empty()).then_some((n + 1, line))
+        })
+        .map(|(n, line)| {
+            let mut fields = line.split_ascii_whitespace();
+            let address = fields
+                .next()
+                .unwrap_or_default()
+                .parse()
+                .map_err(|err| format!("line {}: {}", n, err))?;
+            let assignments = fields
+                .map(str::parse)
+                .collect::<Result<Vec<Assignment>, _>>()
+                .map_err(|err| format!("line {}: {}", n, err))?;
+            if assignments.is_empty() {
+                return Err(format!("line {}: no assignments", n));
+            }
+            Ok(Operation {
+                address,
+                assignments,
+            })
+        })
+        .collect()
+}
+
+/// Assignment resolved to configuration space offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub assignment: Assignment,
+    pub offset: usize,
+}
+
+impl Change {
+    fn width(&self) -> Width {
+        self.assignment.register.width
+    }
+    /// New register value based on current one
+    pub fn apply(&self, current: u32) -> u32 {
+        let Assignment { value, mask, .. } = self.assignment;
+        (current & !mask) | (value & mask)
+    }
+}
+
+/// Resolve every assignment before anything is written
+pub fn resolve(device: &Device, assignments: &[Assignment]) -> Result<Vec<Change>, String> {
+    assignments
+        .iter()
+        .map(|assignment| {
+            device
+                .register_offset(&assignment.register)
+                .map(|offset| Change {
+                    assignment: *assignment,
+                    offset,
+                })
+                .map_err(|err| format!("{}: {}", assignment.register, err))
+        })
+        .collect()
+}
+
+/// Read register value from device
+pub fn read(
+    access: &Access,
+    address: &Address,
+    offset: usize,
+    width: Width,
+) -> access::Result<u32> {
+    let bytes = access.read(address.clone(), offset, width.size())?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |value, byte| (value << 8) | *byte as u32))
+}
+
+/// Every change must pass the guard, current values are read from device
+pub fn check(
+    access: &Access,
+    sysfs: &LinuxSysfs,
+    device: &Device,
+    changes: &[Change],
+    guard: &Guard,
+) -> Result<(), String> {
+    for change in changes {
+        let width = change.width();
+        let size = width.size();
+        let current =
+            read(access, &device.address, change.offset, width).map_err(|err| err.to_string())?;
+        let new = change.apply(current);
+        guard
+            .check(
+                device,
+                sysfs,
+                change.offset,
+                &current.to_le_bytes()[..size],
+                &new.to_le_bytes()[..size],
+            )
+            .map_err(|refusal| format!("{}: {}", change.assignment.register, refusal))?;
+    }
+    Ok(())
+}
+
+/// Write changes in order, partially masked registers are read back first
+pub fn write(access: &Access, address: &Address, changes: &[Change]) -> access::Result<()> {
+    for change in changes {
+        let size = change.width().size();
+        let current = if change.assignment.mask == change.width().mask() {
+            0
+        } else {
+            read(access, address, change.offset, change.width())?
+        };
+        let value = change.apply(current).to_le_bytes();
+        access.write(address.clone(), change.offset, &value[..size])?;
+    }
+    Ok(())
+}
+
+/// `<address> <change>: <current> -> <new>` for every change, current value is read if possible
+pub fn dry_run(access: &Access, plans: &[(Address, Vec<Change>)]) -> Vec<String> {
+    plans
+        .iter()
+        .flat_map(|(address, changes)| {
+            changes.iter().map(move |change| {
+                let width = change.width();
+                let digits = width.size() * 2;
+                match read(access, address, change.offset, width) {
+                    Ok(current) => format!(
+                        "{} {}: {:0w$x} -> {:0w$x}",
+                        address,
+                        change,
+                        current,
+                        change.apply(current),
+                        w = digits
+                    ),
+                    Err(_) => format!("{} {}", address, change),
+                }
+            })
+        })
+        .collect()
+}
+
+/// `<register> @<offset> = <value>[:<mask>]`
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Assignment {
+            register,
+            value,
+            mask,
+        } = self.assignment;
+        let digits = self.width().size() * 2;
+        write!(
+            f,
+            "{} @{:03x} = {:0w$x}",
+            register,
+            self.offset,
+            value,
+            w = digits
+        )?;
+        if mask != self.width().mask() {
+            write!(f, ":{:0w$x}", mask, w = digits)?;
+        }
+        Ok(())
+    }
+}
+
+/// Assignments of a single device or a batch file
+pub fn run(args: Set) {
+    let Set {
+        address,
+        assignments,
+        from_file,
+        dry_run,
+        guard,
+        access,
+    } = args;
+    let operations = match (from_file, address) {
+        (Some(path), _) => std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|s| parse_batch(&s))
+            .unwrap_or_else(|err| {
+                eprintln!("{}: {}", path.display(), err);
+                std::process::exit(1)
+            }),
+        (None, Some(address)) => vec![Operation {
+            address,
+            assignments,
+        }],
+        (None, None) => unreachable!("address or --from-file is required"),
+    };
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let guard = Guard::from(guard);
+    // Nothing is written unless every device exists, every register resolves and every change
+    // passes the guard
+    let plans = operations
+        .into_iter()
+        .map(|op| {
+            access
+                .device(op.address.clone())
+                .map_err(|err| err.to_string())
+                .and_then(|device| {
+                    let changes = resolve(&device, &op.assignments)?;
+                    if !dry_run {
+                        check(&access, &sysfs, &device, &changes, &guard)?;
+                    }
+                    Ok(changes)
+                })
+                .map(|changes| (op.address.clone(), changes))
+                .map_err(|err| format!("{}: {}", op.address, err))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            std::process::exit(1)
+        });
+    if dry_run {
+        for line in self::dry_run(&access, &plans) {
+            println!("{}", line);
+        }
+        return;
+    }
+    for (address, changes) in &plans {
+        if let Err(err) = write(&access, address, changes) {
+            eprintln!("{}: {}", address, err);
+            std::process::exit(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcitool::access::AccessMethod;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_assignment() {
+        let a: Assignment = "CAP_PM+4.w=8:3".parse().unwrap();
+        assert_eq!((0x8, 0x3), (a.value, a.mask));
+        let a: Assignment = "latency_timer=40".parse().unwrap();
+        assert_eq!((0x40, 0xff), (a.value, a.mask));
+        assert!("COMMAND.b=100".parse::<Assignment>().is_err());
+        assert!("COMMAND".parse::<Assignment>().is_err());
+        assert!("COMMAND=xyz".parse::<Assignment>().is_err());
+    }
+
+    #[test]
+    fn batch() {
+        let content = "\
+            # Lab bring-up\n\
+            04:00.0 COMMAND=0406 CAP_EXP+8.w=1000:7000\n\
+            \n\
+            0000:03:00.0 LATENCY_TIMER=40 # comment\n\
+        ";
+        let operations = parse_batch(content).unwrap();
+        assert_eq!(2, operations.len());
+        assert_eq!(2, operations[0].assignments.len());
+        assert_eq!("0000:03:00.0", operations[1].address.to_string());
+
+        assert_eq!(
+            Err("line 2: no assignments".to_string()),
+            parse_batch("# header\n04:00.0\n")
+        );
+        assert!(parse_batch("04:00.0 COMMAND=0406\n04:00.0 NOSUCH=1\n")
+            .unwrap_err()
+            .starts_with("line 2: "));
+
+        let dump = crate::fixture();
+        let device = dump.device(operations[0].address.clone()).unwrap();
+        let changes = resolve(&device, &operations[0].assignments).unwrap();
+        let access = Access::Dump(dump);
+        let result = dry_run(&access, &[(device.address.clone(), changes)]);
+        let sample = vec![
+            "0000:04:00.0 COMMAND @004 = 0406: 0406 -> 0406",
+            "0000:04:00.0 CAP_EXP+8.w @048 = 1000:7000: 202f -> 102f",
+        ];
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn resolve_changes() {
+        let dump = crate::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let assignments = ["COMMAND=0406", "CAP_PM+4.w=0:3"].map(|s| s.parse().unwrap());
+        let changes = resolve(&device, &assignments).unwrap();
+        let result = changes.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            vec!["COMMAND @004 = 0406", "CAP_PM+4.w @084 = 0000:0003"],
+            result
+        );
+        assert_eq!(0x0008, changes[1].apply(0x000b));
+
+        let access = Access::Dump(dump);
+        let address = device.address.clone();
+        assert_eq!(0x0406, read(&access, &address, 0x04, Width::Word).unwrap());
+        assert_eq!(0x1043, read(&access, &address, 0x52, Width::Word).unwrap());
+        let guard = Guard::new(false, true);
+        let sysfs = LinuxSysfs::default();
+        assert_eq!(
+            Ok(()),
+            check(&access, &sysfs, &device, &changes[..1], &guard)
+        );
+        assert!(check(&access, &sysfs, &device, &changes[1..], &guard)
+            .unwrap_err()
+            .starts_with("CAP_PM+4.w: write at 084"));
+
+        let assignments = ["CAP_AGP+4.l=0".parse().unwrap()];
+        assert_eq!(
+            Err("CAP_AGP+4.l: device has no capability 0x02".to_string()),
+            resolve(&device, &assignments)
+        );
+    }
+}
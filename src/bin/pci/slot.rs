@@ -0,0 +1,78 @@
+/*!
+Hot-plug slot control of `pci slot`
+
+Slot power and indicators are set through the Slot Control register of the downstream port.
+*/
+
+use pcitool::device::slot::SlotSetting;
+
+use crate::{args::Slot, guard::Guard, init_access, init_sysfs};
+
+/// Powering slot off removes devices below without notifying their drivers, so it is confirmed
+pub fn run(args: Slot) {
+    let Slot {
+        address,
+        power,
+        attention,
+        power_indicator,
+        yes,
+        guard,
+        access,
+    } = args;
+    let sysfs = init_sysfs(&access.parameter_value);
+    let access = init_access(&access);
+    let device = access.device(address.clone()).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1)
+    });
+    let Some(state) = device.slot_state() else {
+        eprintln!("{}: no hot-plug slot", address);
+        std::process::exit(1)
+    };
+    let power = power.map(|value| value == "on");
+    let settings = power
+        .map(SlotSetting::Power)
+        .into_iter()
+        .chain(attention.map(SlotSetting::Attention))
+        .chain(power_indicator.map(SlotSetting::PowerIndicator))
+        .collect::<Vec<_>>();
+    if settings.is_empty() {
+        println!("{}: {}", address, state);
+        return;
+    }
+    let (offset, control) = device.slot_control(&settings).unwrap_or_else(|err| {
+        eprintln!("{}: {}", address, err);
+        std::process::exit(1)
+    });
+    if power == Some(false) && !yes {
+        eprint!(
+            "{}: power off slot? Devices below are removed without driver notice [y/N] ",
+            address
+        );
+        let mut answer = String::new();
+        let _ = std::io::stdin().read_line(&mut answer);
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            eprintln!("{}: cancelled", address);
+            std::process::exit(1)
+        }
+    }
+    let data = control.to_le_bytes();
+    let result = Guard::from(guard)
+        .check_write(&access, &sysfs, &device, offset, &data)
+        .and_then(|()| {
+            access
+                .write(address.clone(), offset, &data)
+                .map_err(|err| err.to_string())
+        });
+    if let Err(err) = result {
+        eprintln!("{}: {}", address, err);
+        std::process::exit(1)
+    }
+    match access
+        .device(address.clone())
+        .map(|device| device.slot_state())
+    {
+        Ok(Some(state)) => println!("{}: {}", address, state),
+        _ => println!("{}: Slot Control set to {:04x}", address, control),
+    }
+}
@@ -0,0 +1,43 @@
+/*!
+Devices statistics of `pci summary`
+
+Device counts by header type, SR-IOV, class and vendor with names from the names database.
+*/
+
+use pcitool::inventory::stats::Stats;
+
+use crate::{args::Summary, init_access, init_names};
+
+pub fn run(args: Summary) {
+    let Summary {
+        access,
+        pci_ids_path,
+    } = args;
+    let access = init_access(&access);
+    let devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    let stats = Stats::new(&devices);
+    let names = init_names(pci_ids_path);
+    let (vds, cc) = (names.vendor_device_subsystem(), names.class_code());
+    println!(
+        "Devices: {} ({} endpoints, {} bridges, {} CardBus bridges)",
+        stats.total, stats.endpoints, stats.bridges, stats.cardbus_bridges
+    );
+    println!(
+        "SR-IOV: {} capable, {} virtual functions enabled",
+        stats.sr_iov_capable, stats.virtual_functions
+    );
+    println!("Classes:");
+    for ((base, sub), count) in &stats.classes {
+        let name = cc
+            .lookup(*base, *sub, None)
+            .or_else(|| cc.lookup(*base, None, None));
+        let name = name.map(|name| format!("  {}", name)).unwrap_or_default();
+        println!("{:6}  {:02x}{:02x}{}", count, base, sub, name);
+    }
+    println!("Vendors:");
+    for (vendor_id, count) in &stats.vendors {
+        let name = vds.lookup(*vendor_id, None, None);
+        let name = name.map(|name| format!("  {}", name)).unwrap_or_default();
+        println!("{:6}  {:04x}{}", count, vendor_id, name);
+    }
+}
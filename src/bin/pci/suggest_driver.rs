@@ -0,0 +1,65 @@
+/*!
+Kernel modules for unbound devices of `pci suggest-driver`
+*/
+
+use pcitool::access::linux_sysfs::modules_alias::{self, ModulesAlias, ModulesBuiltin};
+
+use crate::{args::SuggestDriver, init_access};
+
+/// Modules of modules.alias and built-in modules aliases matching modalias built from
+/// configuration space, so dumps of other machines can be checked too
+pub fn run(args: SuggestDriver) {
+    let SuggestDriver {
+        address,
+        access,
+        modules_dir,
+    } = args;
+    let dir = modules_dir
+        .map(Ok)
+        .unwrap_or_else(modules_alias::modules_dir);
+    let dir = dir.unwrap_or_else(|err| {
+        eprintln!("kernel modules directory: {}", err);
+        std::process::exit(1)
+    });
+    let path = dir.join("modules.alias");
+    let mut modules_alias = ModulesAlias::init(&path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path.display(), err);
+        std::process::exit(1)
+    });
+    if let Ok(builtin) = ModulesAlias::init_builtin(dir.join("modules.builtin.modinfo")) {
+        modules_alias.merge(builtin);
+    }
+    let builtin = ModulesBuiltin::init(dir.join("modules.builtin")).unwrap_or_default();
+
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    if let Some(address) = address {
+        devices.retain(|device| device.address == address);
+        if devices.is_empty() {
+            eprintln!("{}: device not found", address);
+            std::process::exit(1)
+        }
+    } else {
+        devices.retain(|device| device.driver_in_use.is_none());
+    }
+    for device in devices {
+        if let Some(driver) = &device.driver_in_use {
+            println!("{}: driver {} in use", device.address, driver);
+            continue;
+        }
+        let modalias = device.modalias();
+        println!("{} {}", device.address, modalias);
+        let modules = modules_alias.lookup(&modalias);
+        if modules.is_empty() {
+            println!("\tno matching module");
+        }
+        for module in modules {
+            if builtin.contains(&module) {
+                println!("\t{} (built-in, not bound)", module);
+            } else {
+                println!("\t{}", module);
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+/*!
+Shared Virtual Memory readiness of `pci svm-check`
+
+Device capabilities, path to the root port and IOMMU are checked as [svm::SvmCheck] does.
+*/
+
+use pcitool::{device::svm, topology::Topology};
+
+use crate::{args::SvmCheck, init_access};
+
+/// Exit status is 1 if the device is not found or SVM can not work
+pub fn run(args: SvmCheck) {
+    let SvmCheck { address, access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let Some(device) = devices.iter().find(|device| device.address == address) else {
+        eprintln!("{}: device not found", address);
+        std::process::exit(1)
+    };
+    let topology = Topology::new(&devices);
+    let check = svm::SvmCheck::new(&topology, device);
+    print!("{}", check);
+    if !check.is_ready() {
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,72 @@
+/*!
+Per-phase durations of `pci list --timing`
+
+Printed to stderr when dropped, so early returns of output formats are covered.
+*/
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct Timing {
+    enabled: bool,
+    start: Instant,
+    last: Instant,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Timing {
+    pub fn new(enabled: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            enabled,
+            start: now,
+            last: now,
+            phases: Vec::new(),
+        }
+    }
+    /// End of phase started at the previous mark
+    pub fn mark(&mut self, phase: &'static str) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.last));
+        self.last = now;
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (phase, duration) in &self.phases {
+            writeln!(f, "{:<10}{:>12.3?}", phase, duration)?;
+        }
+        write!(f, "{:<10}{:>12.3?}", "total", self.last - self.start)
+    }
+}
+
+impl Drop for Timing {
+    fn drop(&mut self) {
+        if self.enabled {
+            self.mark("render");
+            eprintln!("{}", self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases() {
+        let mut timing = Timing::new(false);
+        timing.mark("access");
+        timing.mark("enumerate");
+        let result = timing.to_string();
+        let names = result
+            .lines()
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["access", "enumerate", "total"], names);
+    }
+}
@@ -0,0 +1,270 @@
+/*!
+Interactive devices browser
+
+Left pane lists devices in the terse lspci format, right pane shows the
+selected device as `lspci -vvv` would. Devices are re-enumerated every
+refresh interval so hotplug is visible without restart.
+
+Keys: `↑`/`↓`/`j`/`k` select, `PgUp`/`PgDn` scroll details, `/` filter,
+`Esc` clear filter, `r` refresh, `q` quit.
+*/
+
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use pcitool::{
+    access::Access,
+    device::{Address, Device},
+    names::{ClassCode, Names, VendorDeviceSubsystem},
+    view::lspci::basic::{View, ViewArgs},
+};
+
+/// Input poll timeout, keeps refresh timer running without key presses
+const TICK: Duration = Duration::from_millis(250);
+
+pub fn run(access: Access, names: Names, interval: Duration) -> io::Result<()> {
+    let mut app = App::new(access, names);
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal, interval);
+    ratatui::restore();
+    result
+}
+
+struct App {
+    access: Access,
+    vds: VendorDeviceSubsystem,
+    cc: ClassCode,
+    devices: Vec<Device>,
+    filter: String,
+    /// Filter input mode
+    filtering: bool,
+    selected: Option<Address>,
+    scroll: u16,
+}
+
+impl App {
+    fn new(access: Access, names: Names) -> Self {
+        let mut app = Self {
+            access,
+            vds: names.vendor_device_subsystem(),
+            cc: names.class_code(),
+            devices: Vec::new(),
+            filter: String::new(),
+            filtering: false,
+            selected: None,
+            scroll: 0,
+        };
+        app.refresh();
+        app
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal, interval: Duration) -> io::Result<()> {
+        let mut refreshed = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TICK)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !self.key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+            if refreshed.elapsed() >= interval {
+                self.refresh();
+                refreshed = Instant::now();
+            }
+        }
+    }
+
+    /// Returns false on quit
+    fn key(&mut self, code: KeyCode) -> bool {
+        if self.filtering {
+            match code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Char(c) => self.filter.push(c),
+                _ => (),
+            }
+            return true;
+        }
+        match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Down | KeyCode::Char('j') => self.select(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+            KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+            _ => (),
+        }
+        true
+    }
+
+    fn refresh(&mut self) {
+        self.devices = self.access.iter().filter_map(Result::ok).collect();
+        self.devices.sort();
+    }
+
+    fn view_args(&self, verbose: usize) -> ViewArgs<'_> {
+        ViewArgs {
+            verbose,
+            kernel: true,
+            always_domain_number: self.devices.iter().any(|d| d.address.domain != 0),
+            as_numbers: 0,
+            bus_centric: false,
+            show_power: false,
+            name_width: Default::default(),
+            vds: &self.vds,
+            cc: &self.cc,
+            access: &self.access,
+        }
+    }
+
+    /// Devices matching filter with their terse descriptions
+    fn visible(&self) -> Vec<(&Device, String)> {
+        let args = &self.view_args(0);
+        let filter = self.filter.to_lowercase();
+        self.devices
+            .iter()
+            .map(|device| {
+                let data = device.clone();
+                let line = View { data, args }.to_string();
+                (device, line.trim_end().to_string())
+            })
+            .filter(|(_, line)| line.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    /// Move selection by `delta` in visible list
+    fn select(&mut self, delta: isize) {
+        let visible = self.visible();
+        let current = visible
+            .iter()
+            .position(|(d, _)| Some(&d.address) == self.selected.as_ref());
+        let next = match current {
+            Some(n) => n
+                .saturating_add_signed(delta)
+                .min(visible.len().saturating_sub(1)),
+            None => 0,
+        };
+        let selected = visible.get(next).map(|(d, _)| d.address.clone());
+        if selected != self.selected {
+            self.selected = selected;
+            self.scroll = 0;
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, details_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main);
+
+        let visible = self.visible();
+        let position = visible
+            .iter()
+            .position(|(d, _)| Some(&d.address) == self.selected.as_ref())
+            .or(if visible.is_empty() { None } else { Some(0) });
+        let details = position
+            .and_then(|n| visible.get(n))
+            .map(|(device, _)| {
+                let args = &self.view_args(3);
+                View {
+                    data: (*device).clone(),
+                    args,
+                }
+                .to_string()
+            })
+            .unwrap_or_default();
+        let title = format!("Devices ({}/{})", visible.len(), self.devices.len());
+        let list = List::new(visible.into_iter().map(|(_, line)| line))
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(position);
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let details = Paragraph::new(details.replace('\t', "    "))
+            .block(Block::bordered().title("Details"))
+            .scroll((self.scroll, 0));
+        frame.render_widget(details, details_area);
+
+        let status_line = if self.filtering {
+            format!("/{}", self.filter)
+        } else if !self.filter.is_empty() {
+            format!("filter: {}  (Esc to clear)", self.filter)
+        } else {
+            "q quit  / filter  r refresh  PgUp/PgDn scroll".into()
+        };
+        frame.render_widget(Line::from(status_line), status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn app() -> App {
+        let dump = crate::fixture();
+        App::new(dump.into(), Names::default())
+    }
+
+    #[test]
+    fn filter_and_select() {
+        let mut app = app();
+        assert_eq!(25, app.visible().len());
+        app.key(KeyCode::Char('/'));
+        for c in "0604".chars() {
+            app.key(KeyCode::Char(c));
+        }
+        app.key(KeyCode::Enter);
+        assert_eq!(4, app.visible().len());
+        app.select(1);
+        app.select(1);
+        assert_eq!(Some("0000:00:1c.4".parse().unwrap()), app.selected);
+        app.key(KeyCode::Esc);
+        assert_eq!(25, app.visible().len());
+        assert!(!app.key(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn draw() {
+        use ratatui::{backend::TestBackend, Terminal};
+        let mut app = app();
+        app.select(1);
+        let mut terminal = Terminal::new(TestBackend::new(160, 40)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let screen = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect::<String>();
+        assert!(screen.contains("Devices (25/25)"));
+        assert!(
+            screen.contains("Capabilities: [e0] Vendor Specific"),
+            "{}",
+            screen
+        );
+    }
+}
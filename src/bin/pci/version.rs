@@ -0,0 +1,134 @@
+/*!
+Long version for `pci --version`
+
+Besides the crate version it tells what support requests usually ask about: cargo features the
+binary was built with, access methods compiled in and the names database `list` would use.
+*/
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use pcitool::{
+    access::Method,
+    names::{index::Index, Names, PciIdsHeader},
+};
+
+/// Features named as in Cargo.toml
+macro_rules! enabled_features {
+    ($($name:literal),* $(,)?) => {
+        [$(($name, cfg!(feature = $name))),*]
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect::<Vec<&str>>()
+    };
+}
+
+/// pci.ids header fits in the first few kilobytes
+const HEADER_SIZE: u64 = 4096;
+
+pub fn features() -> Vec<&'static str> {
+    enabled_features!(
+        "std",
+        "serde",
+        "clap",
+        "tui",
+        "dbus",
+        "ffi",
+        "network",
+        "margining",
+        "pciutils_3_7",
+        "ls_bridge_subsystem",
+    )
+}
+
+/// Access method names as accepted by `-A`, dumps are read with `-F`
+pub fn access_methods() -> Vec<String> {
+    Method::ALL
+        .iter()
+        .map(ToString::to_string)
+        .chain(Some("dump".to_string()))
+        .collect()
+}
+
+/// Names database source, same choice as [pcitool::names::Names::init]
+pub fn names(pci_ids_path: Option<&Path>) -> String {
+    let path = match pci_ids_path {
+        Some(path) => path.to_path_buf(),
+        None => match Index::default_path().filter(|path| Index::is_index(path)) {
+            Some(path) => path,
+            None if Path::new(Names::HWDB_PATH).exists() => {
+                return format!("udev hwdb {}", Names::HWDB_PATH)
+            }
+            None => PathBuf::from(Names::PCI_IDS_PATH),
+        },
+    };
+    if Index::is_index(&path) {
+        return match Index::open(&path) {
+            Ok(index) => format!("{} index of {}", path.display(), pci_ids(index.source())),
+            Err(err) => format!("{} ({})", path.display(), err),
+        };
+    }
+    pci_ids(&path)
+}
+
+/// pci.ids path with release from its header
+fn pci_ids(path: &Path) -> String {
+    let mut head = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(HEADER_SIZE).read_to_end(&mut head));
+    if let Err(err) = read {
+        return format!("{} ({})", path.display(), err);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let header = PciIdsHeader::parse(head.lines());
+    let release = header
+        .version
+        .map(|version| format!("version {}", version))
+        .into_iter()
+        .chain(header.date.map(|date| format!("date {}", date)))
+        .collect::<Vec<_>>();
+    if release.is_empty() {
+        path.display().to_string()
+    } else {
+        format!("{} ({})", path.display(), release.join(", "))
+    }
+}
+
+/// Version followed by build and environment details
+pub fn long_version(pci_ids_path: Option<&Path>) -> String {
+    format!(
+        "{}\nfeatures: {}\naccess methods: {}\nnames: {}",
+        env!("CARGO_PKG_VERSION"),
+        features().join(", "),
+        access_methods().join(", "),
+        names(pci_ids_path)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn long() {
+        let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids"));
+        let version = long_version(Some(path));
+        let mut lines = version.lines();
+        assert_eq!(Some(env!("CARGO_PKG_VERSION")), lines.next());
+        assert!(lines.next().unwrap().contains("clap"));
+        assert_eq!(
+            Some("access methods: linux-sysfs, linux-proc, ecam, void, dump"),
+            lines.next()
+        );
+        let expected = format!(
+            "names: {} (version 2021.05.16, date 2021-05-16 03:15:02)",
+            path.display()
+        );
+        assert_eq!(Some(expected.as_str()), lines.next());
+        let missing = names(Some(Path::new("/nonexistent/pci.ids")));
+        assert!(missing.starts_with("/nonexistent/pci.ids ("));
+    }
+}
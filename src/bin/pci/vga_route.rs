@@ -0,0 +1,60 @@
+/*!
+Legacy VGA routing of `pci vga-route`
+
+Every VGA compatible controller with the bridges above it and whether they forward legacy VGA
+ranges, followed by the boot VGA device and how it was found.
+*/
+
+use pcitool::topology::{
+    vga::{BootVga, VgaRoute},
+    Topology,
+};
+
+use crate::{args::Summary, init_access, init_names};
+
+pub fn run(args: Summary) {
+    let Summary {
+        access,
+        pci_ids_path,
+    } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let names = init_names(pci_ids_path);
+    let vds = names.vendor_device_subsystem();
+    let topology = Topology::new(&devices);
+    let routes = VgaRoute::all(&topology);
+    if routes.is_empty() {
+        println!("No VGA compatible controllers");
+        return;
+    }
+    for route in &routes {
+        let (vendor_id, device_id) = (route.device.header.vendor_id, route.device.header.device_id);
+        let vendor = vds.lookup(vendor_id, None, None);
+        let device = vds.lookup(vendor_id, device_id, None);
+        let name = match (vendor, device) {
+            (Some(vendor), Some(device)) => format!("{} {}", vendor, device),
+            _ => format!("Device {:04x}:{:04x}", vendor_id, device_id),
+        };
+        let boot = if route.is_boot() { " (boot VGA)" } else { "" };
+        println!("{} {}{}", route.device.address, name, boot);
+        let hops = route
+            .bridges
+            .iter()
+            .map(ToString::to_string)
+            .chain(Some(route.device.address.to_string()))
+            .collect::<Vec<_>>();
+        println!("\tRoute: {}", hops.join(" -> "));
+        match route.blocked_at() {
+            None => println!("\tLegacy VGA: routed"),
+            Some(bridge) => println!(
+                "\tLegacy VGA: not routed, VGA Enable clear on {}",
+                bridge.device.address
+            ),
+        }
+    }
+    match BootVga::find(&topology) {
+        Some(BootVga { device, source }) => println!("Boot VGA: {} ({})", device.address, source),
+        None => println!("Boot VGA: unknown"),
+    }
+}
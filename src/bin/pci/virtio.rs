@@ -0,0 +1,39 @@
+/*!
+Virtio devices of `pci virtio`
+*/
+
+use pcitool::misc::virtio::{self, VirtioInfo};
+
+use crate::{args::Virtio, init_access};
+
+/// Virtio devices with configuration structures locations
+pub fn run(args: Virtio) {
+    let Virtio { access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    for device in &devices {
+        let Some(info) = VirtioInfo::new(device) else {
+            continue;
+        };
+        print!("{} virtio ", device.address);
+        match virtio::device_type_name(info.device_type) {
+            Some(name) => println!("{}", name),
+            None => println!("device type {}", info.device_type),
+        }
+        if info.capabilities.is_empty() {
+            println!("\tlegacy I/O interface only");
+        }
+        for cap in &info.capabilities {
+            println!("\t{}: {}", cap.cfg_type, cap);
+        }
+        match info.msi_x {
+            Some((count, enabled)) => println!(
+                "\tMSI-X: {} vectors, {}",
+                count,
+                if enabled { "enabled" } else { "disabled" }
+            ),
+            None => println!("\tMSI-X: none, INTx"),
+        }
+    }
+}
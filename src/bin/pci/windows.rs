@@ -0,0 +1,77 @@
+/*!
+Bridge windows usage of `pci windows`
+
+Every window of a bridge with BARs and windows of devices below allocated in it and the free
+ranges between them.
+*/
+
+use pcitool::{
+    device::Size,
+    topology::{
+        usage::{self, Claim},
+        Topology,
+    },
+};
+
+use crate::{args::Windows, init_access};
+
+pub fn run(args: Windows) {
+    let Windows { address, access } = args;
+    let access = init_access(&access);
+    let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+    devices.sort();
+    let topology = Topology::new(&devices);
+    let bridges = devices
+        .iter()
+        .filter(|device| address.as_ref().is_none_or(|a| device.address == *a))
+        .collect::<Vec<_>>();
+    if let Some(address) = &address {
+        match bridges.first() {
+            None => eprintln!("{}: device not found", address),
+            Some(device) if device.bridge().is_none() => eprintln!("{}: not a bridge", address),
+            _ => (),
+        }
+        if bridges.iter().all(|device| device.bridge().is_none()) {
+            std::process::exit(1)
+        }
+    }
+    for bridge in bridges {
+        for usage in usage::usage(&topology, bridge) {
+            println!(
+                "{} {}: {}, {} allocated, {} free",
+                bridge.address,
+                usage.window,
+                Size(usage.window.span.size()),
+                Size(usage.allocated_size()),
+                Size(usage.free_size()),
+            );
+            let allocations = usage.allocations.iter().map(|allocation| {
+                let span = allocation.span;
+                let claim = match allocation.claim {
+                    // OS does not report resources
+                    Claim::Bar(index) if span.size() == 1 => {
+                        format!("BAR {} (size unknown)", index)
+                    }
+                    Claim::Bar(index) => format!("BAR {}", index),
+                    Claim::Window(space) => format!("{} window", space),
+                };
+                let span = if span.size() == 1 {
+                    format!("{:x}", span.start)
+                } else {
+                    span.to_string()
+                };
+                let line = format!("{} {} {}", span, allocation.device.address, claim);
+                (allocation.span.start, line)
+            });
+            let free = usage
+                .free()
+                .into_iter()
+                .map(|span| (span.start, format!("{} free", span)));
+            let mut lines = allocations.chain(free).collect::<Vec<_>>();
+            lines.sort();
+            for (_, line) in lines {
+                println!("\t{}", line);
+            }
+        }
+    }
+}
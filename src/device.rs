@@ -3,18 +3,74 @@
 
 */
 
-use std::{
-    array::TryFromSliceError, cmp::Ordering, num::ParseIntError, slice::SliceIndex, str::FromStr,
+use core::{
+    array::TryFromSliceError, cmp::Ordering, fmt, num::ParseIntError, ops::Range,
+    slice::SliceIndex, str::FromStr,
 };
 
+use alloc::{string::String, vec::Vec};
+
 use heterob::Seq;
 
 pub mod address;
 pub use address::Address;
 
+pub mod ari;
+
+pub mod bridge;
+
+pub mod builder;
+
+pub mod cap_list;
+
+pub mod dpc;
+
+pub mod encode;
+
+pub mod hexdump;
+
+pub mod identifiers;
+pub use identifiers::{Identifiers, VirtualFunction};
+
+pub mod l1ss;
+pub use l1ss::L1ssInfo;
+
+pub mod link;
+pub use link::LinkInfo;
+
+pub mod margining;
+
+pub mod msi;
+
+pub mod power;
+pub use power::{PowerControl, PowerInfo};
+
+pub mod ptm;
+
+pub mod rebar;
+
+pub mod registers;
+
+pub mod reset;
+
+pub mod sizing;
+
+pub mod slot;
+
+pub mod sr_iov;
+
+pub mod svm;
+
+pub mod virtualization;
+
 use pcics::{
-    capabilities::Capabilities,
-    extended_capabilities::ExtendedCapabilities,
+    capabilities::{
+        pci_express::{DeviceType, Link},
+        Capabilities, CapabilityKind,
+    },
+    extended_capabilities::{
+        single_root_io_virtualization::SingleRootIoVirtualization, ExtendedCapabilities,
+    },
     header::{BaseAddress, BaseAddressType, Bridge, Cardbus, Header, HeaderType, Normal},
 };
 
@@ -25,6 +81,8 @@ pub const ECS_OFFSET: usize = 0x100;
 
 const DDR_LENGTH: usize = ECS_OFFSET - DDR_OFFSET;
 const ECS_LENGTH: usize = 4096 - ECS_OFFSET;
+/// Raw capability length if the next capability is not above it
+const CAPABILITY_BYTES_MAX: usize = 64;
 
 /// Device
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -48,6 +106,22 @@ pub struct Device {
     pub driver_in_use: Option<String>,
     /// Device handling capable kernel modules
     pub kernel_modules: Option<Vec<String>>,
+    /// Serial number exported by OS
+    pub serial: Option<String>,
+    /// SR-IOV Virtual Function position
+    pub virtual_function: Option<VirtualFunction>,
+    /// OS runtime power management
+    pub power_info: Option<PowerInfo>,
+    /// Device tree node path
+    pub dt_node: Option<String>,
+    /// ACPI namespace path of firmware node, e.g. `\_SB_.PCI0.RP01`
+    pub acpi_path: Option<String>,
+    /// AER is handled by platform firmware (ACPI HEST), OS AER counters stay zero
+    pub aer_firmware_first: Option<bool>,
+    /// Chassis slot designation from SMBIOS System Slots, e.g. `PCIe Slot 3`
+    pub slot_designation: Option<String>,
+    /// Firmware initialized the device as the boot VGA device
+    pub boot_vga: Option<bool>,
 }
 
 impl Device {
@@ -65,6 +139,14 @@ impl Device {
             resource: None,
             driver_in_use: None,
             kernel_modules: None,
+            serial: None,
+            virtual_function: None,
+            power_info: None,
+            dt_node: None,
+            acpi_path: None,
+            aer_firmware_first: None,
+            slot_designation: None,
+            boot_vga: None,
         }
     }
     pub fn capabilities(&self) -> Option<Capabilities> {
@@ -82,9 +164,112 @@ impl Device {
             .as_ref()
             .map(|ecs| ExtendedCapabilities::new(&ecs.0))
     }
+    /// DSN, OS serial number and SR-IOV VF position in a single struct
+    pub fn identifiers(&self) -> Identifiers {
+        Identifiers::new(self)
+    }
     pub fn irq(&self) -> usize {
         self.irq.unwrap_or(self.header.interrupt_line as usize)
     }
+    /// PCI Express Link registers, if device has a link
+    pub fn express_link(&self) -> Option<Link> {
+        self.capabilities()?
+            .flatten()
+            .find_map(|cap| match cap.kind {
+                CapabilityKind::PciExpress(pcie) => match pcie.device_type {
+                    DeviceType::Endpoint { link, .. }
+                    | DeviceType::LegacyEndpoint { link, .. }
+                    | DeviceType::RootPort { link, .. }
+                    | DeviceType::UpstreamPort { link, .. }
+                    | DeviceType::DownstreamPort { link, .. }
+                    | DeviceType::PcieToPciBridge { link, .. }
+                    | DeviceType::PciToPcieBridge { link, .. }
+                    | DeviceType::Reserved { link, .. } => Some(link),
+                    _ => None,
+                },
+                _ => None,
+            })
+    }
+    /// Link speed, width and bandwidth summary
+    pub fn link_info(&self) -> Option<LinkInfo> {
+        self.express_link().map(LinkInfo::from)
+    }
+    /// MSI-X table size
+    pub fn msi_x_vectors(&self) -> Option<u16> {
+        self.capabilities()?
+            .flatten()
+            .find_map(|cap| match cap.kind {
+                CapabilityKind::MsiX(msi_x) => Some(msi_x.message_control.table_size + 1),
+                _ => None,
+            })
+    }
+    /// SR-IOV extended capability of Physical Function
+    pub fn sr_iov(&self) -> Option<SingleRootIoVirtualization> {
+        self.sr_iov_offset().map(|(_, sr_iov)| sr_iov)
+    }
+    /// Raw bytes of capability or extended capability at configuration space `offset` up to
+    /// the next capability in the list, 64 bytes if the next one is not above it
+    pub fn capability_bytes(&self, offset: usize) -> Option<&[u8]> {
+        let (region, start, next) = if offset < ECS_OFFSET {
+            let ddr = &self.device_dependent_region.as_ref()?.0[..];
+            let start = offset.checked_sub(DDR_OFFSET)?;
+            let next = (*ddr.get(start + 1)? & !0b11) as usize;
+            (ddr, start, next.checked_sub(DDR_OFFSET))
+        } else {
+            let ecs = &self.extended_configuration_space.as_ref()?.0[..];
+            let start = offset - ECS_OFFSET;
+            let header: [u8; 4] = ecs.get(start..start + 4)?.try_into().ok()?;
+            let next = (u32::from_le_bytes(header) >> 20) as usize & !0b11;
+            (ecs, start, next.checked_sub(ECS_OFFSET))
+        };
+        let end = next
+            .filter(|&next| next > start)
+            .unwrap_or(start + CAPABILITY_BYTES_MAX)
+            .min(region.len());
+        region.get(start..end)
+    }
+    /// Device dependent region bytes, configuration space offsets `0x40..0x100`
+    pub fn device_dependent_region(&self) -> Option<&[u8]> {
+        self.device_dependent_region.as_ref().map(|ddr| &ddr.0[..])
+    }
+    /// Read configuration space length, see [ConfigurationSpace::length]
+    pub fn configuration_space_length(&self) -> usize {
+        read_length(
+            self.device_dependent_region.is_some(),
+            self.extended_configuration_space.is_some(),
+        )
+    }
+    /// Extended configuration space bytes, configuration space offsets `0x100..0x1000`
+    pub fn extended_configuration_space(&self) -> Option<&[u8]> {
+        self.extended_configuration_space
+            .as_ref()
+            .map(|ecs| &ecs.0[..])
+    }
+    /// Bytes of configuration space `range` above header, which is kept decoded only. `None`
+    /// if the range is not read
+    pub fn config_bytes(&self, range: Range<usize>) -> Option<Vec<u8>> {
+        if range.start < DDR_OFFSET || range.is_empty() {
+            return None;
+        }
+        let mut bytes = Vec::with_capacity(range.len());
+        if range.start < ECS_OFFSET {
+            let ddr = self.device_dependent_region()?;
+            let end = range.end.min(ECS_OFFSET);
+            bytes.extend_from_slice(ddr.get(range.start - DDR_OFFSET..end - DDR_OFFSET)?);
+        }
+        if range.end > ECS_OFFSET {
+            let ecs = self.extended_configuration_space()?;
+            let start = range.start.max(ECS_OFFSET);
+            bytes.extend_from_slice(ecs.get(start - ECS_OFFSET..range.end - ECS_OFFSET)?);
+        }
+        Some(bytes)
+    }
+    /// Four bytes of device dependent region at configuration space `offset`
+    pub(crate) fn ddr_bytes(&self, offset: usize) -> Option<[u8; 4]> {
+        let ddr = self.device_dependent_region.as_ref()?;
+        let offset = offset.checked_sub(DDR_OFFSET)?;
+        ddr.get(offset..offset + 4)?.try_into().ok()
+    }
     pub fn has_mem_bar(&self) -> bool {
         let is_mem_bar = |ba: BaseAddress| {
             let is_non_zero_size = self
@@ -172,6 +357,14 @@ pub struct ConfigurationSpace {
 
 impl ConfigurationSpace {
     pub const SIZE: usize = 4096;
+    /// Read length: 64 bytes of header only (e.g. unprivileged sysfs read), 256 bytes (e.g.
+    /// `lspci -x` dump, no extended configuration space) or full 4096 bytes
+    pub fn length(&self) -> usize {
+        read_length(
+            self.device_dependent_region.is_some(),
+            self.extended_configuration_space.is_some(),
+        )
+    }
     pub fn device(self, address: Address) -> Device {
         Device {
             address,
@@ -186,6 +379,14 @@ impl ConfigurationSpace {
             resource: None,
             driver_in_use: None,
             kernel_modules: None,
+            serial: None,
+            virtual_function: None,
+            power_info: None,
+            dt_node: None,
+            acpi_path: None,
+            aer_firmware_first: None,
+            slot_designation: None,
+            boot_vga: None,
         }
     }
 }
@@ -212,11 +413,21 @@ impl TryFrom<&[u8]> for ConfigurationSpace {
     }
 }
 
+fn read_length(has_ddr: bool, has_ecs: bool) -> usize {
+    match (has_ddr, has_ecs) {
+        (_, true) => ConfigurationSpace::SIZE,
+        (true, false) => ECS_OFFSET,
+        (false, false) => DDR_OFFSET,
+    }
+}
+
 /// Sysfs `/sys/bus/pci/devices/*/resource` files support
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Resource {
     pub entries: [ResourceEntry; 6],
     pub rom_entry: ResourceEntry,
+    /// SR-IOV VF BARs (Linux IOV resources), each entry spans TotalVFs VFs
+    pub iov_entries: [ResourceEntry; 6],
 }
 
 impl FromStr for Resource {
@@ -229,7 +440,18 @@ impl FromStr for Resource {
             *re = line.parse()?;
         }
         let rom_entry = lines.next().unwrap_or("0x0").parse()?;
-        Ok(Self { entries, rom_entry })
+        // IOV resources precede 4 bridge windows only in kernels with SR-IOV support
+        let mut iov_entries = [ResourceEntry::default(); 6];
+        if lines.clone().count() >= iov_entries.len() + 4 {
+            for (re, line) in iov_entries.iter_mut().zip(lines) {
+                *re = line.parse()?;
+            }
+        }
+        Ok(Self {
+            entries,
+            rom_entry,
+            iov_entries,
+        })
     }
 }
 
@@ -258,6 +480,21 @@ impl ResourceEntry {
     }
 }
 
+/// Size with the largest binary suffix dividing it evenly
+pub struct Size(pub u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const SUFFIXES: [&str; 6] = ["", "K", "M", "G", "T", "P"];
+        let (mut value, mut n) = (self.0, 0);
+        while value >= 1024 && value.is_multiple_of(1024) && n + 1 < SUFFIXES.len() {
+            value /= 1024;
+            n += 1;
+        }
+        write!(f, "{}{}", value, SUFFIXES[n])
+    }
+}
+
 impl FromStr for ResourceEntry {
     type Err = ParseIntError;
 
@@ -286,10 +523,65 @@ mod tests {
         assert!(a < b);
     }
 
+    #[test]
+    fn capability_bytes() {
+        let mut cs = [0; 4096];
+        // PCI Power Management followed by MSI
+        cs[0x40..0x42].copy_from_slice(&[0x01, 0x50]);
+        cs[0x50..0x52].copy_from_slice(&[0x05, 0x00]);
+        // AER followed by looped list
+        cs[0x100..0x104].copy_from_slice(&[0x01, 0x00, 0x01, 0x10]);
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = Device::new(Default::default(), cs);
+        assert_eq!(Some(0x10), device.capability_bytes(0x40).map(<[u8]>::len));
+        assert_eq!(Some(64), device.capability_bytes(0x50).map(<[u8]>::len));
+        assert_eq!(
+            Some(&[0x05, 0x00][..]),
+            device.capability_bytes(0x50).map(|b| &b[..2])
+        );
+        assert_eq!(Some(64), device.capability_bytes(0x100).map(<[u8]>::len));
+        assert_eq!(Some(4), device.capability_bytes(0xffc).map(<[u8]>::len));
+        assert_eq!(None, device.capability_bytes(0x20));
+    }
+
+    #[test]
+    fn configuration_space_length() {
+        let length = |size: usize| {
+            let cs: ConfigurationSpace = vec![0; size].as_slice().try_into().unwrap();
+            (
+                cs.length(),
+                Device::new(Default::default(), cs).configuration_space_length(),
+            )
+        };
+        assert_eq!((64, 64), length(64));
+        assert_eq!((256, 256), length(256));
+        // Partially read extended configuration space is dropped
+        assert_eq!((256, 256), length(0x200));
+        assert_eq!((4096, 4096), length(4096));
+    }
+
     #[test]
     fn empty_capabilities() {
         let cs: ConfigurationSpace = [0; 64].as_slice().try_into().unwrap();
         let device = Device::new(Default::default(), cs);
         assert_eq!(None, device.capabilities());
     }
+
+    #[test]
+    fn resource_iov_entries() {
+        let line = |start: u64, size: u64| {
+            let end = (start + size).saturating_sub(1);
+            alloc::format!("0x{:016x} 0x{:016x} 0x{:016x}\n", start, end, 0)
+        };
+        // BARs, ROM, 6 IOV resources and 4 bridge windows
+        let mut sriov = (0..17).map(|_| line(0, 0)).collect::<Vec<_>>();
+        sriov[0] = line(0xb4418000, 0x4000);
+        sriov[7] = line(0xb4500000, 0x40000);
+        let resource: Resource = sriov.concat().parse().unwrap();
+        assert_eq!(0x4000, resource.entries[0].size());
+        assert_eq!(0x40000, resource.iov_entries[0].size());
+        // Kernel without SR-IOV support: 4 bridge windows follow ROM
+        let resource: Resource = sriov[..11].concat().parse().unwrap();
+        assert_eq!([ResourceEntry::default(); 6], resource.iov_entries);
+    }
 }
@@ -4,7 +4,8 @@
 In order to access the configuration address space of a device, the devices
 must be addressable.
 */
-use std::{
+use alloc::string::String;
+use core::{
     fmt::{self, Display},
     num::ParseIntError,
     str::FromStr,
@@ -12,20 +13,22 @@ use std::{
 
 use thiserror::Error;
 
-/// Device address
+/// Device address, ordered by domain, bus, device and function
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub struct Address {
     /// *domain*/*segment* is primarily a *platform* level construct. Logically, *domain* is the most
     /// significant selector (most significant address bits selector) in the
     /// *Domain*:Bus:Device:Function:Offset addressing scheme of the PCI Family Configuration Space
-    /// addressing mechanism.
-    pub domain: u16,
+    /// addressing mechanism. Linux uses 32-bit domains, e.g. `10000` for Intel VMD.
+    pub domain: u32,
     /// The PCI specification permits a single system to host up to 256 buses
     pub bus: u8,
     /// Each bus hosts up to 32 devices
     pub device: u8,
     /// Each device can be a multifunction board (such as an audio device withan accompanying
     /// CD-ROM drive) with a maximum of eight functions. 
+    /// ARI device functions 8-255 are stored split the same way as devfn, they are not accepted
+    /// by [FromStr] as `bb:dd.f` form does not tell ARI apart, use [Self::from_devfn]
     pub function: u8,
 }
 
@@ -50,6 +53,73 @@ pub enum ParseAddressError {
     DeviceNumber(u8),
     #[error("invalid function number {0} (only 8 available)")]
     FunctionNumber(u8),
+    #[error("bus number in path hop {0:?}, only <device>.<function> below bridge")]
+    PathHop(String),
+}
+
+impl Address {
+    pub const fn new(domain: u32, bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            domain,
+            bus,
+            device,
+            function,
+        }
+    }
+    /// Same bus, device and function in another domain
+    pub const fn with_domain(self, domain: u32) -> Self {
+        Self { domain, ..self }
+    }
+    /// Address from devfn byte, also an 8-bit function number of ARI device
+    pub const fn from_devfn(domain: u32, bus: u8, devfn: u8) -> Self {
+        Self {
+            domain,
+            bus,
+            device: devfn >> 3,
+            function: devfn & 7,
+        }
+    }
+    /// Device and function numbers as single byte (devfn)
+    pub const fn devfn(&self) -> u8 {
+        self.device << 3 | self.function
+    }
+    /// Bus and devfn as used in PCIe TLP headers
+    pub const fn routing_id(&self) -> u16 {
+        (self.bus as u16) << 8 | self.devfn() as u16
+    }
+    /// Function number, 8-bit if ARI Forwarding is enabled at the upstream port
+    pub const fn function_number(&self, ari: bool) -> u8 {
+        if ari {
+            self.devfn()
+        } else {
+            self.function
+        }
+    }
+    /// Function 0 must be implemented by every device and carries multi-function bit
+    pub const fn is_function_zero(&self) -> bool {
+        self.function == 0
+    }
+    /// All 8 possible functions of the device, starting with function 0
+    pub fn functions(&self) -> impl Iterator<Item = Address> {
+        let Self {
+            domain,
+            bus,
+            device,
+            ..
+        } = *self;
+        (0..8).map(move |function| Self {
+            domain,
+            bus,
+            device,
+            function,
+        })
+    }
+}
+
+/// Hexadecimal domain number with optional `0x` prefix, e.g. `0002` or `10000`
+pub fn parse_domain(s: &str) -> Result<u32, ParseAddressError> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    u32::from_str_radix(s, 16).map_err(ParseAddressError::Domain)
 }
 
 impl Display for Address {
@@ -91,8 +161,7 @@ impl FromStr for Address {
         let (domain, s) =
             if let Some((domain, s)) = s.split_once(':') {
                 // Domain
-                let domain = u16::from_str_radix(domain, 16)
-                    .map_err(ParseAddressError::Domain)?;
+                let domain = parse_domain(domain)?;
                 (domain, s)
             } else {
                 (0, s)
@@ -104,21 +173,74 @@ impl FromStr for Address {
     }
 }
 
-// /// Multiple addresses matching helper
-// pub struct SpecifiedAddress {
-//     domain: Option<u16>,
-//     bus: u8,
-//     device: Option<u8>,
-//     function: Option<u8>,
-// }
+/// Addresses matching lspci `-s` slot filter, `None` component matches any value
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SlotFilter {
+    pub domain: Option<u32>,
+    pub bus: Option<u8>,
+    pub device: Option<u8>,
+    pub function: Option<u8>,
+}
 
-// impl FromStr for SpecifiedAddress {
-//     type Err = ParseIntError;
+impl SlotFilter {
+    pub fn matches(&self, address: &Address) -> bool {
+        self.domain.is_none_or(|domain| domain == address.domain)
+            && self.bus.is_none_or(|bus| bus == address.bus)
+            && self.device.is_none_or(|device| device == address.device)
+            && self
+                .function
+                .is_none_or(|function| function == address.function)
+    }
+}
 
-//     fn from_str(s: &str) -> Result<Self, Self::Err> {
-//         todo!()
-//     }
-// }
+/// Empty or `*` component is any value
+fn parse_component<T>(
+    s: &str,
+    parse: impl FnOnce(&str) -> Result<T, ParseAddressError>,
+) -> Result<Option<T>, ParseAddressError> {
+    if s.is_empty() || s == "*" {
+        Ok(None)
+    } else {
+        parse(s).map(Some)
+    }
+}
+
+/// `[[[[<domain>]:]<bus>]:][<device>][.[<func>]]`, e.g. `0:` is bus 0, `0` is device 0 on any
+/// bus, `.4` is function 4 of any device
+impl FromStr for SlotFilter {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Domain and bus are counted from the end
+        let (s, device_function) = s.rsplit_once(':').unwrap_or(("", s));
+        let (domain, bus) = s.rsplit_once(':').unwrap_or(("", s));
+        let (device, function) = device_function
+            .split_once('.')
+            .unwrap_or((device_function, ""));
+        let domain = parse_component(domain, parse_domain)?;
+        let bus = parse_component(bus, |s| {
+            u8::from_str_radix(s, 16).map_err(ParseAddressError::Bus)
+        })?;
+        let device = parse_component(device, |s| {
+            match u8::from_str_radix(s, 16).map_err(ParseAddressError::Device)? {
+                device if device > 31 => Err(ParseAddressError::DeviceNumber(device)),
+                device => Ok(device),
+            }
+        })?;
+        let function = parse_component(function, |s| {
+            match u8::from_str_radix(s, 16).map_err(ParseAddressError::Function)? {
+                function if function > 7 => Err(ParseAddressError::FunctionNumber(function)),
+                function => Ok(function),
+            }
+        })?;
+        Ok(Self {
+            domain,
+            bus,
+            device,
+            function,
+        })
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -136,8 +258,12 @@ mod tests {
             (Err(ParseAddressError::Function(u8::from_str_radix("x", 16).unwrap_err())), "00:00.x"),
             (Err(ParseAddressError::Device(u8::from_str_radix("x", 16).unwrap_err())), "00:xx.0"),
             (Err(ParseAddressError::Bus(u8::from_str_radix("x", 16).unwrap_err())), "xx:00.0"),
-            (Err(ParseAddressError::Domain(u16::from_str_radix("x", 16).unwrap_err())), "xxxx:00:00.0"),
+            (Ok(Address { domain: 0x0002, bus: 0x00, device: 0x00, function: 0x00 }), "0002:00:00.0"),
+            (Ok(Address { domain: 0x10000, bus: 0xe1, device: 0x00, function: 0x00 }), "10000:e1:00.0"),
+            (Err(ParseAddressError::Domain(u32::from_str_radix("x", 16).unwrap_err())), "xxxx:00:00.0"),
+            (Err(ParseAddressError::Domain(u32::from_str_radix("1ffffffff", 16).unwrap_err())), "1ffffffff:00:00.0"),
             (Err(ParseAddressError::FunctionNumber(0xAA)), "00:00.AA"),
+            (Err(ParseAddressError::FunctionNumber(8)), "01:00.8"),
             (Err(ParseAddressError::DeviceNumber(0xAA)), "00:AA.0"),
         ];
         for (n, (sample, s)) in data.iter().enumerate() {
@@ -145,4 +271,111 @@ mod tests {
             assert_eq!(sample, &result, "#{}", n);
         }
     }
+
+    #[test]
+    fn domains() {
+        let address = Address::new(0, 0xe1, 0, 0).with_domain(0x10000);
+        assert_eq!("10000:e1:00.0", address.to_string());
+        assert_eq!("0002:00:1f.3", format!("{:#}", Address::new(2, 0, 0x1f, 3)));
+        assert_eq!("00:1f.3", format!("{:#}", Address::new(0, 0, 0x1f, 3)));
+        assert_eq!(Ok(0x10000), parse_domain("0x10000"));
+        assert_eq!(Ok(2), parse_domain("0002"));
+    }
+
+    #[test]
+    fn functions() {
+        let address = Address::new(0, 0x3b, 0x02, 0x05);
+        assert!(!address.is_function_zero());
+        let result = address
+            .functions()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!("0000:3b:02.0", result[0]);
+        assert_eq!("0000:3b:02.7", result[7]);
+        assert_eq!(8, result.len());
+        assert!(address.functions().next().unwrap().is_function_zero());
+
+        assert_eq!(0x15, address.devfn());
+        assert_eq!(0x3b15, address.routing_id());
+        assert_eq!(5, address.function_number(false));
+        assert_eq!(0x15, address.function_number(true));
+        assert_eq!(address, Address::from_devfn(0, 0x3b, 0x15));
+    }
+
+    #[test]
+    fn slot_filter() {
+        let any = SlotFilter::default();
+        let data = [
+            (
+                Ok(SlotFilter {
+                    bus: Some(0),
+                    ..any
+                }),
+                "0:",
+            ),
+            (
+                Ok(SlotFilter {
+                    device: Some(0),
+                    ..any
+                }),
+                "0",
+            ),
+            (
+                Ok(SlotFilter {
+                    device: Some(0),
+                    function: Some(3),
+                    ..any
+                }),
+                "0.3",
+            ),
+            (
+                Ok(SlotFilter {
+                    function: Some(4),
+                    ..any
+                }),
+                ".4",
+            ),
+            (
+                Ok(SlotFilter {
+                    domain: Some(2),
+                    bus: Some(0x3b),
+                    ..any
+                }),
+                "2:3b:*.*",
+            ),
+            (Ok(any), ""),
+            (Err(ParseAddressError::DeviceNumber(0x20)), "00:20"),
+            (Err(ParseAddressError::FunctionNumber(8)), "00:1f.8"),
+            (Err(ParseAddressError::FunctionNumber(8)), ".8"),
+            (Err(ParseAddressError::FunctionNumber(9)), "01:00.9"),
+        ];
+        for (n, (sample, s)) in data.iter().enumerate() {
+            assert_eq!(sample, &s.parse(), "#{}", n);
+        }
+        let filter: SlotFilter = "1f.".parse().unwrap();
+        assert!(filter.matches(&"0002:00:1f.3".parse().unwrap()));
+        assert!(!filter.matches(&"00:1c.0".parse().unwrap()));
+    }
+
+    #[test]
+    fn ordering() {
+        let mut result = [
+            "10000:00:00.0",
+            "0002:00:00.0",
+            "01:00.0",
+            "00:1f.3",
+            "00:02.0",
+        ]
+        .map(|s| s.parse::<Address>().unwrap());
+        result.sort();
+        let result = result.map(|a| a.to_string());
+        let sample = [
+            "0000:00:02.0",
+            "0000:00:1f.3",
+            "0000:01:00.0",
+            "0002:00:00.0",
+            "10000:00:00.0",
+        ];
+        assert_eq!(sample, result);
+    }
 }
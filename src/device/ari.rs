@@ -0,0 +1,119 @@
+/*!
+# Alternative Routing-ID Interpretation
+
+Below a port with ARI Forwarding enabled the device number is always 0 and the 8-bit function
+number takes the whole devfn byte: function 8 is `01:01.0` in lspci and Linux notation, the
+only one accepted when parsing, `01:00.8` is rejected. Functions are not necessarily contiguous,
+function 0 starts a chain of ARI capability Next Function Numbers ended by 0.
+
+```rust
+# use pcitool::device::{ari, builder::{DeviceBuilder, ExtendedCapability}, Address};
+// Function with ARI capability, next function is 4
+let function = |devfn: u8, next: u8| {
+    let ari = ExtendedCapability::Raw { id: 0x000e, version: 1, data: vec![0, next, 0, 0] };
+    let builder = DeviceBuilder::new(0x8086, 0x1572).address(Address::from_devfn(0, 1, devfn));
+    builder.extended_capability(ari).build().unwrap()
+};
+let functions = [function(0, 4), function(4, 0)];
+let chain = ari::chain(&functions[0], |address| {
+    functions.iter().find(|device| device.address == *address)
+});
+let result = chain.iter().map(|device| format!("{:#}", device.address)).collect::<Vec<_>>();
+assert_eq!(vec!["01:00.0", "01:00.4"], result);
+```
+*/
+
+use core::borrow::Borrow;
+
+use alloc::vec::Vec;
+
+use pcics::{capabilities::CapabilityKind, extended_capabilities::ExtendedCapabilityKind};
+
+use super::{Address, Device};
+
+impl Device {
+    /// ARI capability Next Function Number, `None` without ARI capability
+    pub fn ari_next_function(&self) -> Option<u8> {
+        self.extended_capabilities()?
+            .flatten()
+            .find_map(|ecap| match ecap.kind {
+                ExtendedCapabilityKind::AlternativeRoutingIdInterpretation(ari) => {
+                    Some(ari.ari_capability.next_function_number)
+                }
+                _ => None,
+            })
+    }
+    /// Downstream port forwards configuration requests to functions 8-255 of device 0
+    pub fn ari_forwarding(&self) -> bool {
+        self.capabilities()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|cap| match cap.kind {
+                CapabilityKind::PciExpress(pcie) => pcie
+                    .device_2
+                    .is_some_and(|device_2| device_2.control.ari_forwarding_enable),
+                _ => false,
+            })
+    }
+}
+
+/// Functions chained by Next Function Number from `first`, `lookup` finds function by address.
+/// Chain ends at Next Function Number 0, missing function or function seen before
+pub fn chain<D: Borrow<Device>>(first: D, mut lookup: impl FnMut(&Address) -> Option<D>) -> Vec<D> {
+    let mut chain: Vec<D> = Vec::new();
+    let mut next = Some(first);
+    while let Some(function) = next.take() {
+        let device = function.borrow();
+        let address = &device.address;
+        if chain.iter().any(|d| d.borrow().address == *address) {
+            break;
+        }
+        next = device
+            .ari_next_function()
+            .filter(|&n| n != 0)
+            .map(|n| Address::from_devfn(address.domain, address.bus, n))
+            .and_then(|address| lookup(&address));
+        chain.push(function);
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{DeviceBuilder, ExtendedCapability};
+    use pretty_assertions::assert_eq;
+
+    fn function(address: &str, next: Option<u8>) -> Device {
+        let mut builder = DeviceBuilder::new(0x8086, 0x1572).address(address.parse().unwrap());
+        if let Some(next) = next {
+            builder = builder.extended_capability(ExtendedCapability::Raw {
+                id: 0x000e,
+                version: 1,
+                data: vec![0, next, 0, 0],
+            });
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn next_function_chain() {
+        let functions = [
+            function("01:00.0", Some(1)),
+            function("01:00.1", Some(8)),
+            // ARI function 8
+            function("01:01.0", Some(0x21)),
+            function("01:04.1", Some(1)),
+        ];
+        let lookup = |address: &Address| functions.iter().find(|d| d.address == *address);
+        let result = chain(&functions[0], lookup)
+            .iter()
+            .map(|device| device.address.function_number(true))
+            .collect::<Vec<_>>();
+        // Loop back to function 1 is cut
+        assert_eq!(vec![0, 1, 8, 0x21], result);
+        assert_eq!(None, function("01:00.0", None).ari_next_function());
+        assert!(!functions[0].ari_forwarding());
+    }
+}
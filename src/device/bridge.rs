@@ -0,0 +1,198 @@
+/*!
+# PCI-to-PCI bridge windows
+
+I/O, memory and prefetchable memory ranges forwarded by a bridge to its secondary bus, decoded
+from Base/Limit registers. Window is enabled if base is not above limit, I/O and prefetchable
+windows are optional. Addresses are bus addresses, limits are inclusive.
+
+```rust
+# use pcitool::device::bridge::{Space, Span, Windows};
+# use pcics::header::{Header, HeaderType};
+let mut data = [0u8; 64];
+data[0x0e] = 0x01; // PCI-to-PCI bridge
+data[0x1c..0x1e].copy_from_slice(&[0xf1, 0x01]); // I/O 32-bit, disabled
+data[0x20..0x24].copy_from_slice(&[0x00, 0xb4, 0x10, 0xb4]);
+let header = Header::try_from(data.as_slice()).unwrap();
+let HeaderType::Bridge(bridge) = header.header_type else { unreachable!() };
+let windows = bridge.windows();
+assert_eq!(Space::Io, windows[0].space);
+assert!(!windows[0].enabled);
+assert_eq!(Space::Memory, windows[1].space);
+assert_eq!(Span { start: 0xb400_0000, end: 0xb41f_ffff }, windows[1].span);
+assert_eq!(0x20_0000, windows[1].span.size());
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::header::{Bridge, BridgeIoAddressRange, BridgePrefetchableMemory, HeaderType};
+
+use super::Device;
+
+/// Address space of bridge window or BAR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Space {
+    Io,
+    Memory,
+    Prefetchable,
+}
+
+/// Inclusive address range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Bridge forwarding window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub space: Space,
+    /// Decoded addresses width: 16 or 32 bits for I/O, 32 or 64 bits for memory
+    pub width: u8,
+    /// Base and limit extended by window granularity, start is above end if disabled
+    pub span: Span,
+    /// Base is not above limit, range is forwarded
+    pub enabled: bool,
+}
+
+/// Typed windows of bridge header
+pub trait Windows {
+    /// Implemented windows, enabled and disabled, in I/O, memory, prefetchable order
+    fn windows(&self) -> Vec<Window>;
+}
+
+impl Span {
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+    /// Number of addresses, zero if start is above end
+    pub fn size(&self) -> u64 {
+        if self.start > self.end {
+            0
+        } else {
+            (self.end - self.start).saturating_add(1)
+        }
+    }
+}
+
+impl Window {
+    fn new(space: Space, width: u8, base: u64, limit: u64, granularity: u64) -> Self {
+        Self {
+            space,
+            width,
+            span: Span {
+                start: base,
+                end: limit + granularity,
+            },
+            enabled: base <= limit,
+        }
+    }
+}
+
+impl Windows for Bridge {
+    fn windows(&self) -> Vec<Window> {
+        let io = match self.io_address_range {
+            BridgeIoAddressRange::IoAddr16 { base, limit } => Some((16, base as u64, limit as u64)),
+            BridgeIoAddressRange::IoAddr32 { base, limit } => Some((32, base as u64, limit as u64)),
+            _ => None,
+        };
+        let memory = Some((
+            32,
+            ((self.memory_base & !0xf) as u64) << 16,
+            ((self.memory_limit & !0xf) as u64) << 16,
+        ));
+        let prefetchable = match self.prefetchable_memory {
+            BridgePrefetchableMemory::MemAddr32 { base, limit } => {
+                Some((32, base as u64, limit as u64))
+            }
+            BridgePrefetchableMemory::MemAddr64 { base, limit } => Some((64, base, limit)),
+            _ => None,
+        };
+        [
+            (Space::Io, io, 0xfff),
+            (Space::Memory, memory, 0xfffff),
+            (Space::Prefetchable, prefetchable, 0xfffff),
+        ]
+        .into_iter()
+        .filter_map(|(space, range, granularity)| {
+            let (width, base, limit) = range?;
+            Some(Window::new(space, width, base, limit, granularity))
+        })
+        .collect()
+    }
+}
+
+impl Device {
+    /// PCI-to-PCI bridge header, secondary status and windows are there
+    pub fn bridge(&self) -> Option<&Bridge> {
+        match &self.header.header_type {
+            HeaderType::Bridge(bridge) => Some(bridge),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Space {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Io => "I/O",
+            Self::Memory => "memory",
+            Self::Prefetchable => "prefetchable memory",
+        })
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}-{:x}", self.start, self.end)
+    }
+}
+
+/// `<space> <start>-<end> [<width>-bit]`, disabled windows are marked
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} [{}-bit]", self.space, self.span, self.width)?;
+        if !self.enabled {
+            write!(f, " [disabled]")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use alloc::string::ToString;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn windows() {
+        let dump = Dump::fixture();
+        let device = dump.device("00:1d.4".parse().unwrap()).unwrap();
+        let bridge = device.bridge().unwrap();
+        let result = bridge
+            .windows()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "I/O f000-fff [16-bit] [disabled]",
+            "memory b4200000-b42fffff [32-bit]",
+            "prefetchable memory fff00000-fffff [64-bit] [disabled]",
+        ];
+        assert_eq!(sample, result);
+        // Secondary status: <MAbort+
+        assert!(bridge.secondary_status.received_master_abort);
+        assert_eq!(
+            None,
+            dump.device("00:1f.3".parse().unwrap()).unwrap().bridge()
+        );
+    }
+}
@@ -0,0 +1,529 @@
+/*!
+# Synthetic devices
+
+[DeviceBuilder] lays out a configuration space from identifiers, BARs and capabilities with
+sensible defaults, for emulators and decoder test fixtures. Capabilities are chained from `0x40`
+and extended capabilities from `0x100` in the order they are added. The result is available both
+as raw bytes and as [Device].
+
+```rust
+# use pcitool::device::builder::{Bar, Capability, DeviceBuilder, ExtendedCapability};
+let device = DeviceBuilder::new(0x8086, 0x1533)
+    .class(0x02, 0x00, 0x00)
+    .bar(0, Bar::Memory32 { address: 0xa120_0000, prefetchable: false })
+    .capability(Capability::PowerManagement)
+    .capability(Capability::PciExpress { port_type: 0, speed: 1, width: 1 })
+    .extended_capability(ExtendedCapability::DeviceSerialNumber(0x0011_22ff_ff33_4455))
+    .build()
+    .unwrap();
+assert_eq!((0x8086, 0x1533), (device.header.vendor_id, device.header.device_id));
+assert_eq!(2, device.capabilities().unwrap().count());
+assert_eq!(Some(0x40), device.capability_offset(0x01));
+```
+*/
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use super::{Address, ConfigurationSpace, Device, DDR_OFFSET, ECS_OFFSET};
+
+const CAPABILITIES_POINTER: usize = 0x34;
+/// Status register Capabilities List bit
+const STATUS_CAPABILITIES_LIST: u16 = 1 << 4;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    #[error("capabilities do not fit into device dependent region")]
+    Capabilities,
+    #[error("extended capabilities do not fit into extended configuration space")]
+    ExtendedCapabilities,
+    #[error("BAR {0} is not in the header")]
+    Bar(usize),
+}
+
+/// Base Address Register value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Io(u32),
+    Memory32 {
+        address: u32,
+        prefetchable: bool,
+    },
+    /// Takes two BAR slots
+    Memory64 {
+        address: u64,
+        prefetchable: bool,
+    },
+}
+
+/// Capability with sensible defaults, register values not listed are zero
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Capability {
+    /// Version 3, no D1/D2, in D0 with No Soft Reset
+    PowerManagement,
+    /// MSI capable of `2^multiple_message` vectors, disabled
+    Msi {
+        multiple_message: u8,
+        is_64bit: bool,
+    },
+    /// MSI-X table and PBA locations, disabled
+    MsiX {
+        table_size: u16,
+        table_bar: u8,
+        table_offset: u32,
+        pba_bar: u8,
+        pba_offset: u32,
+    },
+    /// Version 2 with `port_type` (0 Endpoint, 4 Root Port, 5 Upstream Port, 6 Downstream Port,
+    /// ...), link `speed` (1 is 2.5GT/s, 2 is 5GT/s, ...) and `width` are both supported and
+    /// current ones
+    PciExpress { port_type: u8, speed: u8, width: u8 },
+    /// Capability ID and bytes following the Next Capability Pointer
+    Raw { id: u8, data: Vec<u8> },
+}
+
+impl Capability {
+    fn id(&self) -> u8 {
+        match self {
+            Self::PowerManagement => 0x01,
+            Self::Msi { .. } => 0x05,
+            Self::MsiX { .. } => 0x11,
+            Self::PciExpress { .. } => 0x10,
+            Self::Raw { id, .. } => *id,
+        }
+    }
+    /// Bytes following the Next Capability Pointer
+    fn body(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        match *self {
+            Self::PowerManagement => {
+                body.extend_from_slice(&0x0003u16.to_le_bytes());
+                body.extend_from_slice(&0x0008u16.to_le_bytes());
+                body.extend_from_slice(&[0; 2]);
+            }
+            Self::Msi {
+                multiple_message,
+                is_64bit,
+            } => {
+                let control = ((multiple_message as u16 & 0b111) << 1) | (is_64bit as u16) << 7;
+                body.extend_from_slice(&control.to_le_bytes());
+                body.resize(if is_64bit { 0x0c } else { 0x08 }, 0);
+            }
+            Self::MsiX {
+                table_size,
+                table_bar,
+                table_offset,
+                pba_bar,
+                pba_offset,
+            } => {
+                let control = table_size.saturating_sub(1) & 0x7ff;
+                body.extend_from_slice(&control.to_le_bytes());
+                body.extend_from_slice(&(table_offset & !0b111 | table_bar as u32).to_le_bytes());
+                body.extend_from_slice(&(pba_offset & !0b111 | pba_bar as u32).to_le_bytes());
+            }
+            Self::PciExpress {
+                port_type,
+                speed,
+                width,
+            } => {
+                body.resize(0x3a, 0);
+                let caps = 0x0002 | (port_type as u16 & 0xf) << 4;
+                let link = (speed as u16 & 0xf) | (width as u16 & 0x3f) << 4;
+                // Max Read Request Size 512 bytes
+                let devctl = 0x2000u16;
+                let speeds = ((1u32 << speed.min(7)) - 1) << 1;
+                for (offset, bytes) in [
+                    (0x00, &caps.to_le_bytes()[..]),
+                    (0x06, &devctl.to_le_bytes()),
+                    (0x0a, &(link as u32).to_le_bytes()),
+                    (0x10, &link.to_le_bytes()),
+                    (0x2a, &speeds.to_le_bytes()),
+                    (0x2e, &(speed as u16 & 0xf).to_le_bytes()),
+                ] {
+                    body[offset..offset + bytes.len()].copy_from_slice(bytes);
+                }
+            }
+            Self::Raw { ref data, .. } => body.extend_from_slice(data),
+        }
+        body
+    }
+}
+
+/// Extended capability with sensible defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtendedCapability {
+    /// Version 2 with spec default severity and masks, no errors logged
+    AdvancedErrorReporting,
+    DeviceSerialNumber(u64),
+    /// Capability ID, version and bytes following the header
+    Raw {
+        id: u16,
+        version: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl ExtendedCapability {
+    fn header(&self) -> (u16, u8) {
+        match self {
+            Self::AdvancedErrorReporting => (0x0001, 2),
+            Self::DeviceSerialNumber(_) => (0x0003, 1),
+            Self::Raw { id, version, .. } => (*id, *version),
+        }
+    }
+    /// Bytes following the header
+    fn body(&self) -> Vec<u8> {
+        match self {
+            Self::AdvancedErrorReporting => {
+                let mut body = Vec::with_capacity(0x44);
+                // Uncorrectable status and mask
+                body.extend_from_slice(&[0; 8]);
+                body.extend_from_slice(&0x0006_2030u32.to_le_bytes());
+                // Correctable status, Advisory Non-Fatal Error masked
+                body.extend_from_slice(&[0; 4]);
+                body.extend_from_slice(&0x0000_2000u32.to_le_bytes());
+                body.resize(0x44, 0);
+                body
+            }
+            Self::DeviceSerialNumber(serial) => serial.to_le_bytes().to_vec(),
+            Self::Raw { data, .. } => data.clone(),
+        }
+    }
+}
+
+/// Synthetic device configuration space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceBuilder {
+    address: Address,
+    vendor_id: u16,
+    device_id: u16,
+    command: u16,
+    revision_id: u8,
+    class_code: [u8; 3],
+    multifunction: bool,
+    /// Primary, secondary and subordinate bus numbers of PCI-to-PCI bridge
+    bridge: Option<[u8; 3]>,
+    subsystem: (u16, u16),
+    interrupt: (u8, u8),
+    /// BAR index and value
+    bars: Vec<(usize, Bar)>,
+    capabilities: Vec<Capability>,
+    extended_capabilities: Vec<ExtendedCapability>,
+}
+
+impl DeviceBuilder {
+    /// Normal (type 0) header at `00:00.0`
+    pub fn new(vendor_id: u16, device_id: u16) -> Self {
+        Self {
+            address: Address::default(),
+            vendor_id,
+            device_id,
+            command: 0,
+            revision_id: 0,
+            class_code: [0; 3],
+            multifunction: false,
+            bridge: None,
+            subsystem: (0, 0),
+            interrupt: (0, 0),
+            bars: Vec::new(),
+            capabilities: Vec::new(),
+            extended_capabilities: Vec::new(),
+        }
+    }
+    pub fn address(self, address: Address) -> Self {
+        Self { address, ..self }
+    }
+    pub fn command(self, command: u16) -> Self {
+        Self { command, ..self }
+    }
+    pub fn revision(self, revision_id: u8) -> Self {
+        Self {
+            revision_id,
+            ..self
+        }
+    }
+    pub fn class(self, base: u8, sub: u8, interface: u8) -> Self {
+        Self {
+            class_code: [interface, sub, base],
+            ..self
+        }
+    }
+    pub fn multifunction(self, multifunction: bool) -> Self {
+        Self {
+            multifunction,
+            ..self
+        }
+    }
+    /// PCI-to-PCI bridge (type 1) header with bus numbers, only BARs 0 and 1 are available
+    pub fn bridge(self, primary: u8, secondary: u8, subordinate: u8) -> Self {
+        Self {
+            bridge: Some([primary, secondary, subordinate]),
+            ..self
+        }
+    }
+    /// Subsystem vendor and device, normal header only
+    pub fn subsystem(self, vendor_id: u16, device_id: u16) -> Self {
+        Self {
+            subsystem: (vendor_id, device_id),
+            ..self
+        }
+    }
+    /// Interrupt Line and Interrupt Pin (1 is INTA#)
+    pub fn interrupt(self, line: u8, pin: u8) -> Self {
+        Self {
+            interrupt: (line, pin),
+            ..self
+        }
+    }
+    pub fn bar(mut self, index: usize, bar: Bar) -> Self {
+        self.bars.retain(|(n, _)| *n != index);
+        self.bars.push((index, bar));
+        self
+    }
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.capabilities.push(capability);
+        self
+    }
+    pub fn extended_capability(mut self, capability: ExtendedCapability) -> Self {
+        self.extended_capabilities.push(capability);
+        self
+    }
+    /// Full 4096 bytes of configuration space
+    pub fn bytes(&self) -> Result<Vec<u8>, BuilderError> {
+        let mut cs = Vec::from([0u8; ConfigurationSpace::SIZE]);
+        let mut put =
+            |offset: usize, bytes: &[u8]| cs[offset..offset + bytes.len()].copy_from_slice(bytes);
+        put(0x00, &self.vendor_id.to_le_bytes());
+        put(0x02, &self.device_id.to_le_bytes());
+        put(0x04, &self.command.to_le_bytes());
+        if !self.capabilities.is_empty() {
+            put(0x06, &STATUS_CAPABILITIES_LIST.to_le_bytes());
+        }
+        put(0x08, &[self.revision_id]);
+        put(0x09, &self.class_code);
+        let header_type = if self.bridge.is_some() { 0x01 } else { 0x00 };
+        put(0x0e, &[header_type | (self.multifunction as u8) << 7]);
+        let bars_count = if let Some(bus_numbers) = self.bridge {
+            put(0x18, &bus_numbers);
+            2
+        } else {
+            put(0x2c, &self.subsystem.0.to_le_bytes());
+            put(0x2e, &self.subsystem.1.to_le_bytes());
+            6
+        };
+        put(0x3c, &[self.interrupt.0, self.interrupt.1]);
+        for &(index, bar) in &self.bars {
+            let offset = 0x10 + index * 4;
+            match bar {
+                Bar::Io(address) if index < bars_count => {
+                    put(offset, &(address & !0b11 | 1).to_le_bytes())
+                }
+                Bar::Memory32 {
+                    address,
+                    prefetchable,
+                } if index < bars_count => {
+                    let value = address & !0xf | (prefetchable as u32) << 3;
+                    put(offset, &value.to_le_bytes());
+                }
+                Bar::Memory64 {
+                    address,
+                    prefetchable,
+                } if index + 1 < bars_count => {
+                    let value = address & !0xf | 0b100 | (prefetchable as u64) << 3;
+                    put(offset, &value.to_le_bytes());
+                }
+                _ => return Err(BuilderError::Bar(index)),
+            }
+        }
+
+        let mut offset = DDR_OFFSET;
+        let mut pointer = CAPABILITIES_POINTER;
+        for capability in &self.capabilities {
+            let body = capability.body();
+            let end = offset + 2 + body.len();
+            if end > ECS_OFFSET {
+                return Err(BuilderError::Capabilities);
+            }
+            put(pointer, &[offset as u8]);
+            put(offset, &[capability.id(), 0]);
+            put(offset + 2, &body);
+            pointer = offset + 1;
+            // Dword aligned
+            offset = (end + 3) & !3;
+        }
+
+        let mut offset = ECS_OFFSET;
+        let mut previous: Option<(usize, u32)> = None;
+        for capability in &self.extended_capabilities {
+            let body = capability.body();
+            let end = offset + 4 + body.len();
+            if end > ConfigurationSpace::SIZE {
+                return Err(BuilderError::ExtendedCapabilities);
+            }
+            if let Some((previous, header)) = previous {
+                put(previous, &(header | (offset as u32) << 20).to_le_bytes());
+            }
+            let (id, version) = capability.header();
+            let header = id as u32 | (version as u32 & 0xf) << 16;
+            put(offset, &header.to_le_bytes());
+            put(offset + 4, &body);
+            previous = Some((offset, header));
+            offset = (end + 3) & !3;
+        }
+        Ok(cs)
+    }
+    pub fn build(&self) -> Result<Device, BuilderError> {
+        let bytes = self.bytes()?;
+        let cs = ConfigurationSpace::try_from(bytes.as_slice())
+            .unwrap_or_else(|_| unreachable!("full configuration space"));
+        Ok(cs.device(self.address.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcics::{
+        capabilities::{
+            pci_express::{DeviceType, LinkSpeed, LinkWidth},
+            CapabilityKind,
+        },
+        extended_capabilities::ExtendedCapabilityKind,
+        header::{BaseAddressType, HeaderType},
+    };
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn decoded() {
+        let device = DeviceBuilder::new(0x10ec, 0x8168)
+            .address("03:00.0".parse().unwrap())
+            .class(0x02, 0x00, 0x00)
+            .revision(0x15)
+            .subsystem(0x1043, 0x16a1)
+            .interrupt(0xff, 1)
+            .bar(0, Bar::Io(0x3000))
+            .bar(
+                2,
+                Bar::Memory64 {
+                    address: 0x4_a200_4000,
+                    prefetchable: false,
+                },
+            )
+            .capability(Capability::PowerManagement)
+            .capability(Capability::Msi {
+                multiple_message: 0,
+                is_64bit: true,
+            })
+            .capability(Capability::PciExpress {
+                port_type: 0,
+                speed: 1,
+                width: 1,
+            })
+            .capability(Capability::MsiX {
+                table_size: 4,
+                table_bar: 4,
+                table_offset: 0,
+                pba_bar: 4,
+                pba_offset: 0x800,
+            })
+            .extended_capability(ExtendedCapability::AdvancedErrorReporting)
+            .extended_capability(ExtendedCapability::DeviceSerialNumber(
+                0x0102_0304_0506_0708,
+            ))
+            .build()
+            .unwrap();
+        assert_eq!("03:00.0", format!("{:#}", device.address));
+        assert_eq!(
+            (0x02, 0x15),
+            (device.header.class_code.base, device.header.revision_id)
+        );
+        let HeaderType::Normal(normal) = &device.header.header_type else {
+            panic!("normal header expected")
+        };
+        assert_eq!(
+            (0x1043, 0x16a1),
+            (normal.sub_vendor_id, normal.sub_device_id)
+        );
+        let bars = normal
+            .base_addresses
+            .clone()
+            .map(|ba| (ba.region, ba.base_address_type))
+            .collect::<Vec<_>>();
+        let sample = vec![
+            (
+                0,
+                BaseAddressType::IoSpace {
+                    base_address: 0x3000,
+                },
+            ),
+            (
+                2,
+                BaseAddressType::MemorySpace64 {
+                    prefetchable: false,
+                    base_address: 0x4_a200_4000,
+                },
+            ),
+        ];
+        assert_eq!(sample, bars);
+
+        let caps = device
+            .capabilities()
+            .unwrap()
+            .map(|cap| cap.unwrap())
+            .map(|cap| (cap.pointer, cap.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![0x40, 0x48, 0x58, 0x94],
+            caps.iter().map(|(p, _)| *p).collect::<Vec<_>>()
+        );
+        let CapabilityKind::PciExpress(pcie) = &caps[2].1 else {
+            panic!("PCI Express capability expected")
+        };
+        assert!(matches!(pcie.device_type, DeviceType::Endpoint { .. }));
+        let link = device.link_info().unwrap();
+        assert_eq!(
+            (LinkSpeed::Rate2GTps, LinkWidth::X1),
+            (link.max_speed, link.max_width)
+        );
+        let ecaps = device
+            .extended_capabilities()
+            .unwrap()
+            .map(|ecap| ecap.unwrap())
+            .map(|ecap| (ecap.offset, ecap.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(0x148, ecaps[1].0);
+        assert!(matches!(
+            ecaps[1].1,
+            ExtendedCapabilityKind::DeviceSerialNumber(ref dsn)
+                if dsn.lower_dword == 0x0506_0708
+        ));
+    }
+
+    #[test]
+    fn bridge() {
+        let device = DeviceBuilder::new(0x8086, 0x9dbc)
+            .class(0x06, 0x04, 0x00)
+            .bridge(0, 1, 2)
+            .build()
+            .unwrap();
+        let HeaderType::Bridge(bridge) = &device.header.header_type else {
+            panic!("bridge header expected")
+        };
+        let buses = (bridge.primary_bus_number, bridge.secondary_bus_number);
+        assert_eq!((0, 1, 2), (buses.0, buses.1, bridge.subordinate_bus_number));
+        assert!(!device.header.status.capabilities_list);
+        let bar = Bar::Memory32 {
+            address: 0,
+            prefetchable: false,
+        };
+        let result = DeviceBuilder::new(0, 0).bridge(0, 1, 1).bar(2, bar).build();
+        assert_eq!(Err(BuilderError::Bar(2)), result);
+        let raw = Capability::Raw {
+            id: 0x09,
+            data: vec![0; 0xc0],
+        };
+        let result = DeviceBuilder::new(0, 0).capability(raw).build();
+        assert_eq!(Err(BuilderError::Capabilities), result);
+    }
+}
@@ -0,0 +1,319 @@
+/*!
+# Raw capabilities lists
+
+Capability and extended capability lists walked by headers only: offset, ID and version of
+every structure, whether or not its body decodes. Useful for scripting and for devices with
+broken or unknown capabilities. Pointers outside of configuration space, an empty extended
+capability header and loops (bounded like lspci does) end the walk. Every instance of a
+structure is returned, broken firmware may chain the same capability twice, see [duplicates].
+
+```rust
+# use pcitool::device::{cap_list::RawCapability, Address, ConfigurationSpace, Device};
+let mut cs = [0u8; 0x1000];
+// Capabilities List bit and pointer
+cs[0x06] = 0x10;
+cs[0x34] = 0x40;
+// Power Management without body, next is MSI
+cs[0x40..0x42].copy_from_slice(&[0x01, 0x50]);
+cs[0x50] = 0x05;
+// AER version 2, last one
+cs[0x100..0x104].copy_from_slice(&0x0002_0001u32.to_le_bytes());
+let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+let device = Device::new(Address::default(), cs);
+let caps = device.raw_capabilities().collect::<Vec<_>>();
+assert_eq!(
+    vec![
+        RawCapability { offset: 0x40, id: 0x01, version: None },
+        RawCapability { offset: 0x50, id: 0x05, version: None },
+        RawCapability { offset: 0x100, id: 0x01, version: Some(2) },
+    ],
+    caps
+);
+assert_eq!(Some("ECAP_AER"), caps[2].name());
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use super::{
+    registers::{CAPABILITIES, EXTENDED_CAPABILITIES},
+    Device, DDR_OFFSET, ECS_OFFSET,
+};
+
+/// Capabilities fit into device dependent region dwords
+const CAPABILITIES_TTL: usize = (ECS_OFFSET - DDR_OFFSET) / 4;
+/// Extended capabilities fit into extended configuration space dwords
+const EXTENDED_CAPABILITIES_TTL: usize = (0x1000 - ECS_OFFSET) / 4;
+
+/// Null, HyperTransport and Vendor Specific capabilities
+const REPEATABLE_CAPABILITIES: &[u16] = &[0x00, 0x08, 0x09];
+/// Null, Vendor Specific, DVSEC and DOE mailboxes extended capabilities
+const REPEATABLE_EXTENDED_CAPABILITIES: &[u16] = &[0x0000, 0x000b, 0x0023, 0x002e];
+
+/// Capability or extended capability header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawCapability {
+    /// Header offset in configuration space
+    pub offset: usize,
+    /// 8-bit Capability ID or 16-bit Extended Capability ID
+    pub id: u16,
+    /// Capability Version of extended capability
+    pub version: Option<u8>,
+}
+
+impl RawCapability {
+    pub fn is_extended(&self) -> bool {
+        self.offset >= ECS_OFFSET
+    }
+    /// Specification allows several instances of the structure in a function
+    pub fn is_repeatable(&self) -> bool {
+        if self.is_extended() {
+            REPEATABLE_EXTENDED_CAPABILITIES.contains(&self.id)
+        } else {
+            REPEATABLE_CAPABILITIES.contains(&self.id)
+        }
+    }
+    /// `setpci` name, e.g. `CAP_PM` or `ECAP_AER`
+    pub fn name(&self) -> Option<&'static str> {
+        if self.is_extended() {
+            EXTENDED_CAPABILITIES
+                .iter()
+                .find_map(|(name, id)| (*id == self.id).then_some(*name))
+        } else {
+            CAPABILITIES
+                .iter()
+                .find_map(|(name, id)| (*id as u16 == self.id).then_some(*name))
+        }
+    }
+}
+
+/// Tab separated offset, ID, version and name, missing ones shown as `-`
+impl fmt::Display for RawCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_extended() {
+            write!(f, "{:03x}\t{:04x}", self.offset, self.id)?;
+        } else {
+            write!(f, "{:03x}\t{:02x}", self.offset, self.id)?;
+        }
+        match self.version {
+            Some(version) => write!(f, "\t{}", version)?,
+            None => write!(f, "\t-")?,
+        }
+        write!(f, "\t{}", self.name().unwrap_or("-"))
+    }
+}
+
+/// Iterator over capabilities then extended capabilities headers
+#[derive(Debug, Clone)]
+pub struct RawCapabilities<'a> {
+    ddr: &'a [u8],
+    ecs: &'a [u8],
+    /// Next header offset in configuration space, 0 ends the current list
+    next: usize,
+    extended: bool,
+    ttl: usize,
+}
+
+impl<'a> RawCapabilities<'a> {
+    pub fn new(device: &'a Device) -> Self {
+        let ddr = device.device_dependent_region().unwrap_or_default();
+        let next = if device.header.status.capabilities_list {
+            device.header.capabilities_pointer as usize
+        } else {
+            0
+        };
+        Self {
+            ddr,
+            ecs: device.extended_configuration_space().unwrap_or_default(),
+            next,
+            extended: false,
+            ttl: CAPABILITIES_TTL,
+        }
+    }
+    fn capability(&mut self) -> Option<RawCapability> {
+        let offset = self.next;
+        let start = offset.checked_sub(DDR_OFFSET)?;
+        let [id, next] = self.ddr.get(start..start + 2)? else {
+            return None;
+        };
+        self.next = (next & !0b11) as usize;
+        Some(RawCapability {
+            offset,
+            id: *id as u16,
+            version: None,
+        })
+    }
+    fn extended_capability(&mut self) -> Option<RawCapability> {
+        let offset = self.next;
+        let start = offset.checked_sub(ECS_OFFSET)?;
+        let header: [u8; 4] = self.ecs.get(start..start + 4)?.try_into().ok()?;
+        let header = u32::from_le_bytes(header);
+        if header == 0 || header == u32::MAX {
+            return None;
+        }
+        self.next = (header >> 20) as usize & !0b11;
+        Some(RawCapability {
+            offset,
+            id: header as u16,
+            version: Some((header >> 16) as u8 & 0xf),
+        })
+    }
+}
+
+impl Iterator for RawCapabilities<'_> {
+    type Item = RawCapability;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.extended {
+            if self.next != 0 && self.ttl > 0 {
+                self.ttl -= 1;
+                if let Some(cap) = self.capability() {
+                    return Some(cap);
+                }
+            }
+            self.extended = true;
+            self.next = if self.ecs.is_empty() { 0 } else { ECS_OFFSET };
+            self.ttl = EXTENDED_CAPABILITIES_TTL;
+        }
+        if self.next == 0 || self.ttl == 0 {
+            return None;
+        }
+        self.ttl -= 1;
+        let cap = self.extended_capability();
+        if cap.is_none() {
+            self.next = 0;
+        }
+        cap
+    }
+}
+
+/// Repeated instances of capabilities allowed once, each with the offset of the first instance
+pub fn duplicates(caps: impl IntoIterator<Item = RawCapability>) -> Vec<(RawCapability, usize)> {
+    let mut seen: Vec<RawCapability> = Vec::new();
+    let mut result = Vec::new();
+    for cap in caps {
+        let first = seen
+            .iter()
+            .find(|first| first.id == cap.id && first.is_extended() == cap.is_extended());
+        match first {
+            Some(first) if !cap.is_repeatable() => result.push((cap, first.offset)),
+            Some(_) => (),
+            None => seen.push(cap),
+        }
+    }
+    result
+}
+
+impl Device {
+    /// Capabilities and extended capabilities headers without decoding their bodies
+    pub fn raw_capabilities(&self) -> RawCapabilities<'_> {
+        RawCapabilities::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{dump::Dump, AccessMethod},
+        device::{Address, ConfigurationSpace},
+    };
+    use alloc::string::ToString;
+    use alloc::vec;
+    use pcics::capabilities::CapabilityKind;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn dump_lists() {
+        let dump = Dump::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let raw = device.raw_capabilities().collect::<Vec<_>>();
+        let caps = device
+            .capabilities()
+            .unwrap()
+            .flatten()
+            .map(|cap| cap.pointer as usize);
+        let ecaps = device
+            .extended_capabilities()
+            .unwrap()
+            .flatten()
+            .map(|ecap| ecap.offset as usize);
+        let offsets = caps.chain(ecaps).collect::<Vec<_>>();
+        assert_eq!(
+            offsets,
+            raw.iter().map(|cap| cap.offset).collect::<Vec<_>>()
+        );
+        let pm = raw.iter().find(|cap| cap.offset == 0x80).unwrap();
+        assert_eq!("080\t01\t-\tCAP_PM", pm.to_string());
+    }
+
+    #[test]
+    fn broken_lists() {
+        let mut cs = [0u8; 0x1000];
+        cs[0x06] = 0x10;
+        cs[0x34] = 0x40;
+        // Capability pointing to itself, unknown extended capability pointing back to 0x100
+        cs[0x40..0x42].copy_from_slice(&[0x09, 0x40]);
+        cs[0x100..0x104].copy_from_slice(&0x1001_00ffu32.to_le_bytes());
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = Device::new(Address::default(), cs);
+        let raw = device.raw_capabilities().collect::<Vec<_>>();
+        assert_eq!(CAPABILITIES_TTL + EXTENDED_CAPABILITIES_TTL, raw.len());
+        assert_eq!("100\t00ff\t1\t-", raw.last().unwrap().to_string());
+
+        // Capabilities List bit clear
+        let mut cs = [0u8; 0x100];
+        cs[0x34] = 0x40;
+        cs[0x40] = 0x01;
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = Device::new(Address::default(), cs);
+        assert_eq!(0, device.raw_capabilities().count());
+    }
+
+    #[test]
+    fn duplicated_capabilities() {
+        // Firmware chained second Power Management and AER after the last ones
+        let dump = Dump::init(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/broken/duplicate-caps.xxxx.txt"
+        ))
+        .unwrap();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let pm = device
+            .capabilities()
+            .unwrap()
+            .flatten()
+            .filter(|cap| matches!(cap.kind, CapabilityKind::PowerManagementInterface(_)))
+            .map(|cap| cap.pointer)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0x80, 0xc0], pm);
+        let aer = device
+            .extended_capabilities()
+            .unwrap()
+            .flatten()
+            .filter(|ecap| ecap.id() == 0x0001)
+            .map(|ecap| ecap.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![0x100, 0x500], aer);
+        let result = duplicates(device.raw_capabilities())
+            .into_iter()
+            .map(|(cap, first)| (cap.name(), cap.offset, first))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Some("CAP_PM"), 0xc0, 0x80),
+                (Some("ECAP_AER"), 0x500, 0x100)
+            ],
+            result
+        );
+
+        let vendor = |offset| RawCapability {
+            offset,
+            id: 0x0b,
+            version: Some(1),
+        };
+        assert!(duplicates([vendor(0x100), vendor(0x200)]).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+/*!
+# Downstream Port Containment
+
+DPC status of a downstream port and release of triggered containment. Trigger Status and
+Interrupt Status are RW1C: writing ones clears them and link is allowed to retrain.
+
+```rust
+# use pcitool::device::builder::{DeviceBuilder, ExtendedCapability};
+let data = vec![
+    0x00, 0x00, 0x01, 0x00, // Capability and Control
+    0x03, 0x00, 0x00, 0x03, // Status: triggered by ERR_NONFATAL, source 03:00.0
+];
+let dpc = ExtendedCapability::Raw { id: 0x001d, version: 1, data };
+let device = DeviceBuilder::new(0x8086, 0xa110).extended_capability(dpc).build().unwrap();
+let info = device.dpc().unwrap();
+assert_eq!("triggered: ERR_NONFATAL received, source 03:00.0", info.to_string());
+assert_eq!(Ok((0x108, 0x0009)), device.dpc_release());
+```
+*/
+
+use core::fmt;
+
+use pcics::extended_capabilities::{
+    downstream_port_containment::DpcTriggerReason, ExtendedCapabilityKind,
+};
+use thiserror::Error;
+
+use super::Device;
+
+/// DPC Status register offset relative to capability
+const STATUS: usize = 0x08;
+/// Trigger Status and Interrupt Status RW1C bits
+const RELEASE: u16 = 0b1001;
+
+/// DPC Status with register location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DpcInfo {
+    pub triggered: bool,
+    pub reason: DpcTriggerReason,
+    pub interrupt: bool,
+    /// Root Port is busy with internal activity, containment should not be released
+    pub rp_busy: bool,
+    /// Requester ID of the message that triggered DPC
+    pub source_id: u16,
+    /// DPC Status register offset in configuration space
+    pub status_offset: usize,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DpcError {
+    #[error("device has no Downstream Port Containment capability")]
+    NoCapability,
+    #[error("DPC is not triggered")]
+    NotTriggered,
+    #[error("Root Port is busy, containment can not be released yet")]
+    RpBusy,
+}
+
+impl Device {
+    /// Downstream Port Containment status
+    pub fn dpc(&self) -> Option<DpcInfo> {
+        self.extended_capabilities()?.flatten().find_map(|ecap| {
+            let ExtendedCapabilityKind::DownstreamPortContainment(dpc) = ecap.kind else {
+                return None;
+            };
+            let status = dpc.dpc_status;
+            Some(DpcInfo {
+                triggered: status.dpc_trigger_status,
+                reason: status.dpc_trigger_reason,
+                interrupt: status.dpc_interrupt_status,
+                rp_busy: status.dpc_rp_busy,
+                source_id: dpc.dpc_error_source_id,
+                status_offset: ecap.offset as usize + STATUS,
+            })
+        })
+    }
+    /// Offset and value of DPC Status register write releasing triggered containment
+    pub fn dpc_release(&self) -> Result<(usize, u16), DpcError> {
+        let info = self.dpc().ok_or(DpcError::NoCapability)?;
+        if !info.triggered {
+            return Err(DpcError::NotTriggered);
+        }
+        if info.rp_busy {
+            return Err(DpcError::RpBusy);
+        }
+        Ok((info.status_offset, RELEASE))
+    }
+}
+
+/// `triggered: <reason>, source <bus>:<device>.<function>` or `not triggered`
+impl fmt::Display for DpcInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.triggered {
+            return write!(f, "not triggered");
+        }
+        let reason = match self.reason {
+            DpcTriggerReason::UnmaskedUncorrectableError => "unmasked uncorrectable error",
+            DpcTriggerReason::ReceivingAnErrNonFatal => "ERR_NONFATAL received",
+            DpcTriggerReason::ReceivingAnErrFatal => "ERR_FATAL received",
+            DpcTriggerReason::RpPioError => "RP PIO error",
+            DpcTriggerReason::DpcSoftwareTriggerBit => "software trigger",
+            DpcTriggerReason::Reserved(_) => "reserved reason",
+        };
+        let id = self.source_id;
+        write!(
+            f,
+            "triggered: {}, source {:02x}:{:02x}.{}",
+            reason,
+            id >> 8,
+            (id >> 3) & 0x1f,
+            id & 0x7
+        )?;
+        if self.interrupt {
+            write!(f, ", interrupt pending")?;
+        }
+        if self.rp_busy {
+            write!(f, ", RP busy")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{DeviceBuilder, ExtendedCapability};
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    /// Port with DPC capability and DPC Status register
+    fn device(status: u8) -> Device {
+        // Capability and Control, Status, Error Source ID 00:02.0
+        let data = vec![0x00, 0x00, 0x01, 0x00, status, 0x00, 0x10, 0x00];
+        DeviceBuilder::new(0x8086, 0xa110)
+            .extended_capability(ExtendedCapability::Raw {
+                id: 0x001d,
+                version: 1,
+                data,
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn release() {
+        // ERR_FATAL, interrupt, RP busy
+        let info = device(0x1d).dpc().unwrap();
+        assert_eq!(
+            "triggered: ERR_FATAL received, source 00:02.0, interrupt pending, RP busy",
+            info.to_string()
+        );
+        assert_eq!(Err(DpcError::RpBusy), device(0x1d).dpc_release());
+        assert_eq!("not triggered", device(0x00).dpc().unwrap().to_string());
+        assert_eq!(Err(DpcError::NotTriggered), device(0x00).dpc_release());
+        let device = DeviceBuilder::new(0x8086, 0xa110).build().unwrap();
+        assert_eq!(Err(DpcError::NoCapability), device.dpc_release());
+    }
+}
@@ -0,0 +1,522 @@
+/*!
+# Encoding to configuration space bytes
+
+Decoded registers are encoded back to their configuration space layout, so changes made through
+the typed structures can be written to a device or saved as a dump. Header is the only part of
+[ConfigurationSpace] kept decoded, so [ConfigurationSpace] and [Device] encode the header and copy
+the rest of the space as is. Capabilities are not encoded as a whole list, only these structures
+have their own encoding:
+- [PowerManagementInterface], [MessageSignaledInterrups] and [MsiX]: registers following the Next
+  Capability Pointer
+- PCI Express [DeviceControl] and [LinkControl] registers
+
+Changes to other capabilities have to be written to the raw bytes.
+
+Decoding drops some reserved bits, they are encoded as zeros: low bits of Capabilities Pointer,
+reserved header bytes and undefined bits of Bridge Control. Header of reserved type keeps only
+the common part.
+
+```rust
+# use pcitool::device::{encode::ToBytes, Address, ConfigurationSpace};
+let mut bytes = [0u8; 256];
+bytes[..4].copy_from_slice(&[0x86, 0x80, 0xc8, 0x9d]);
+let cs: ConfigurationSpace = bytes.as_slice().try_into().unwrap();
+let mut device = cs.device(Address::default());
+device.header.command.bus_master = true;
+assert_eq!([0x04, 0x00], device.header.command.to_bytes()[..]);
+assert_eq!(0x04, device.to_bytes()[4]);
+```
+*/
+
+use alloc::vec::Vec;
+
+use pcics::{
+    capabilities::{
+        message_signaled_interrups::{MessageAddress, MessageSignaledInterrups},
+        msi_x::{Bir, MsiX},
+        pci_express::{DeviceControl, LinkControl, ReadCompletionBoundary},
+        PowerManagementInterface,
+    },
+    header::{
+        BridgeControl, BridgeIoAddressRange, BridgePrefetchableMemory, CardbusBridgeControl,
+        ClassCode, Command, Header, HeaderType, IoAccessAddressRange, Status,
+    },
+};
+
+use super::{ConfigurationSpace, Device};
+
+/// Serialization to configuration space bytes
+pub trait ToBytes {
+    /// Little-endian register bytes
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+/// Fields of given bit widths packed starting from the least significant bit
+fn lsb(fields: &[(u32, u32)]) -> u32 {
+    let mut result = 0;
+    let mut shift = 0;
+    for &(value, width) in fields {
+        result |= (value & ((1 << width) - 1)) << shift;
+        shift += width;
+    }
+    result
+}
+
+impl ToBytes for Command {
+    fn to_bytes(&self) -> Vec<u8> {
+        let word = lsb(&[
+            (self.io_space as u32, 1),
+            (self.memory_space as u32, 1),
+            (self.bus_master as u32, 1),
+            (self.special_cycles as u32, 1),
+            (self.memory_write_and_invalidate_enable as u32, 1),
+            (self.vga_palette_snoop as u32, 1),
+            (self.parity_error_response as u32, 1),
+            (self.stepping as u32, 1),
+            (self.serr_enable as u32, 1),
+            (self.fast_back_to_back_enable as u32, 1),
+            (self.interrupt_disable as u32, 1),
+            (self.reserved as u32, 5),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+impl<const T: char> ToBytes for Status<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let word = lsb(&[
+            (self.reserved as u32, 3),
+            (self.interrupt_status as u32, 1),
+            (self.capabilities_list as u32, 1),
+            (self.is_66mhz_capable as u32, 1),
+            (self.user_definable_features as u32, 1),
+            (self.fast_back_to_back_capable as u32, 1),
+            (self.master_data_parity_error as u32, 1),
+            (self.devsel_timing as u32, 2),
+            (self.signaled_target_abort as u32, 1),
+            (self.received_target_abort as u32, 1),
+            (self.received_master_abort as u32, 1),
+            (self.system_error as u32, 1),
+            (self.detected_parity_error as u32, 1),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+impl ToBytes for ClassCode {
+    fn to_bytes(&self) -> Vec<u8> {
+        [self.interface, self.sub, self.base].to_vec()
+    }
+}
+
+impl ToBytes for BridgeControl {
+    fn to_bytes(&self) -> Vec<u8> {
+        let word = lsb(&[
+            (self.parity_error_response_enable as u32, 1),
+            (self.serr_enable as u32, 1),
+            (self.isa_enable as u32, 1),
+            (self.vga_enable as u32, 1),
+            (self.vga_16_enable as u32, 1),
+            (self.master_abort_mode as u32, 1),
+            (self.secondary_bus_reset as u32, 1),
+            (self.fast_back_to_back_enable as u32, 1),
+            (self.primary_discard_timer as u32, 1),
+            (self.secondary_discard_timer as u32, 1),
+            (self.discard_timer_status as u32, 1),
+            (self.discard_timer_serr_enable as u32, 1),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+impl ToBytes for CardbusBridgeControl {
+    fn to_bytes(&self) -> Vec<u8> {
+        let word = lsb(&[
+            (self.parity_error_response_enable as u32, 1),
+            (self.serr_enable as u32, 1),
+            (self.isa_enable as u32, 1),
+            (self.vga_enable as u32, 1),
+            (0, 1),
+            (self.master_abort_mode as u32, 1),
+            (self.cardbus_reset as u32, 1),
+            (self.ireq_int_enable as u32, 1),
+            (self.memory_0_prefetch_enable as u32, 1),
+            (self.memory_1_prefetch_enable as u32, 1),
+            (self.write_posting_enable as u32, 1),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+/// I/O Base, I/O Limit and their Upper 16 Bits registers
+fn bridge_io_registers(range: &BridgeIoAddressRange) -> (u8, u8, u16, u16) {
+    match *range {
+        BridgeIoAddressRange::NotImplemented => (0, 0, 0, 0),
+        BridgeIoAddressRange::IoAddr16 { base, limit } => {
+            ((base >> 8) as u8, (limit >> 8) as u8, 0, 0)
+        }
+        BridgeIoAddressRange::IoAddr32 { base, limit } => (
+            (base >> 8) as u8 & !0xf | 1,
+            (limit >> 8) as u8 & !0xf | 1,
+            (base >> 16) as u16,
+            (limit >> 16) as u16,
+        ),
+        BridgeIoAddressRange::Malformed { base, limit }
+        | BridgeIoAddressRange::Reserved { base, limit } => (base, limit, 0, 0),
+    }
+}
+
+/// Prefetchable Memory Base, Limit and their Upper 32 Bits registers
+fn bridge_prefetchable_registers(memory: &BridgePrefetchableMemory) -> (u16, u16, u32, u32) {
+    match *memory {
+        BridgePrefetchableMemory::NotImplemented => (0, 0, 0, 0),
+        BridgePrefetchableMemory::MemAddr32 { base, limit } => {
+            ((base >> 16) as u16, (limit >> 16) as u16, 0, 0)
+        }
+        BridgePrefetchableMemory::MemAddr64 { base, limit } => (
+            (base >> 16) as u16 & !0xf | 1,
+            (limit >> 16) as u16 & !0xf | 1,
+            (base >> 32) as u32,
+            (limit >> 32) as u32,
+        ),
+        BridgePrefetchableMemory::Malformed { base, limit }
+        | BridgePrefetchableMemory::Reserved { base, limit } => (base, limit, 0, 0),
+    }
+}
+
+/// CardBus I/O Base and I/O Limit registers
+fn cardbus_io_registers(range: &IoAccessAddressRange) -> (u32, u32) {
+    match *range {
+        IoAccessAddressRange::Addr16Bit { base, limit } => (base as u32, limit as u32),
+        IoAccessAddressRange::Addr32Bit { base, limit } => (base & !0b11 | 1, limit),
+        IoAccessAddressRange::Unknown {
+            io_address_capability,
+            base_lower,
+            base_upper,
+            limit_lower,
+            limit_upper,
+        } => (
+            (base_upper as u32) << 16 | base_lower as u32 | io_address_capability as u32,
+            (limit_upper as u32) << 16 | limit_lower as u32,
+        ),
+    }
+}
+
+impl ToBytes for Header {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Header::TOTAL_SIZE);
+        bytes.extend_from_slice(&self.vendor_id.to_le_bytes());
+        bytes.extend_from_slice(&self.device_id.to_le_bytes());
+        bytes.extend(self.command.to_bytes());
+        bytes.extend(self.status.to_bytes());
+        bytes.push(self.revision_id);
+        bytes.extend(self.class_code.to_bytes());
+        bytes.push(self.cache_line_size);
+        bytes.push(self.latency_timer);
+        bytes.push(u8::from(&self.header_type) | (self.is_multi_function as u8) << 7);
+        bytes.push(self.bist.clone().into());
+        let interrupt = [self.interrupt_line, self.interrupt_pin.into()];
+        match &self.header_type {
+            HeaderType::Normal(normal) => {
+                for dword in normal.base_addresses.orig() {
+                    bytes.extend_from_slice(&dword.to_le_bytes());
+                }
+                bytes.extend_from_slice(&normal.cardbus_cis_pointer.to_le_bytes());
+                bytes.extend_from_slice(&normal.sub_vendor_id.to_le_bytes());
+                bytes.extend_from_slice(&normal.sub_device_id.to_le_bytes());
+                bytes.extend_from_slice(&u32::from(normal.expansion_rom.clone()).to_le_bytes());
+                bytes.push(self.capabilities_pointer);
+                bytes.extend_from_slice(&[0; 7]);
+                bytes.extend_from_slice(&interrupt);
+                bytes.extend_from_slice(&[normal.min_grant, normal.max_latency]);
+            }
+            HeaderType::Bridge(bridge) => {
+                for dword in bridge.base_addresses.orig() {
+                    bytes.extend_from_slice(&dword.to_le_bytes());
+                }
+                bytes.extend_from_slice(&[
+                    bridge.primary_bus_number,
+                    bridge.secondary_bus_number,
+                    bridge.subordinate_bus_number,
+                    bridge.secondary_latency_timer,
+                ]);
+                let (io_base, io_limit, io_base_upper, io_limit_upper) =
+                    bridge_io_registers(&bridge.io_address_range);
+                bytes.extend_from_slice(&[io_base, io_limit]);
+                bytes.extend(bridge.secondary_status.to_bytes());
+                bytes.extend_from_slice(&bridge.memory_base.to_le_bytes());
+                bytes.extend_from_slice(&bridge.memory_limit.to_le_bytes());
+                let (base, limit, base_upper, limit_upper) =
+                    bridge_prefetchable_registers(&bridge.prefetchable_memory);
+                bytes.extend_from_slice(&base.to_le_bytes());
+                bytes.extend_from_slice(&limit.to_le_bytes());
+                bytes.extend_from_slice(&base_upper.to_le_bytes());
+                bytes.extend_from_slice(&limit_upper.to_le_bytes());
+                bytes.extend_from_slice(&io_base_upper.to_le_bytes());
+                bytes.extend_from_slice(&io_limit_upper.to_le_bytes());
+                bytes.push(self.capabilities_pointer);
+                bytes.extend_from_slice(&[0; 3]);
+                bytes.extend_from_slice(&u32::from(bridge.expansion_rom.clone()).to_le_bytes());
+                bytes.extend_from_slice(&interrupt);
+                bytes.extend(bridge.bridge_control.to_bytes());
+            }
+            HeaderType::Cardbus(cardbus) => {
+                for dword in cardbus.base_addresses.orig() {
+                    bytes.extend_from_slice(&dword.to_le_bytes());
+                }
+                bytes.extend_from_slice(&[self.capabilities_pointer, 0]);
+                bytes.extend(cardbus.secondary_status.to_bytes());
+                bytes.extend_from_slice(&[
+                    cardbus.pci_bus_number,
+                    cardbus.cardbus_bus_number,
+                    cardbus.subordinate_bus_number,
+                    cardbus.cardbus_latency_timer,
+                ]);
+                for dword in [
+                    cardbus.memory_base_address_0,
+                    cardbus.memory_limit_address_0,
+                    cardbus.memory_base_address_1,
+                    cardbus.memory_limit_address_1,
+                ] {
+                    bytes.extend_from_slice(&dword.to_le_bytes());
+                }
+                for range in [
+                    &cardbus.io_access_address_range_0,
+                    &cardbus.io_access_address_range_1,
+                ] {
+                    let (base, limit) = cardbus_io_registers(range);
+                    bytes.extend_from_slice(&base.to_le_bytes());
+                    bytes.extend_from_slice(&limit.to_le_bytes());
+                }
+                bytes.extend_from_slice(&interrupt);
+                bytes.extend(cardbus.bridge_control.to_bytes());
+            }
+            HeaderType::Reserved(_) => (),
+        }
+        bytes.resize(Header::TOTAL_SIZE, 0);
+        bytes
+    }
+}
+
+impl ToBytes for ConfigurationSpace {
+    /// [ConfigurationSpace::length] bytes
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes();
+        if let Some(ddr) = &self.device_dependent_region {
+            bytes.extend_from_slice(&ddr.0);
+        }
+        if let Some(ecs) = &self.extended_configuration_space {
+            bytes.extend_from_slice(&ecs.0);
+        }
+        bytes
+    }
+}
+
+impl ToBytes for Device {
+    /// [Device::configuration_space_length] bytes
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes();
+        bytes.extend(self.device_dependent_region().into_iter().flatten());
+        bytes.extend(self.extended_configuration_space().into_iter().flatten());
+        bytes
+    }
+}
+
+impl ToBytes for PowerManagementInterface {
+    /// Registers following the Next Capability Pointer: PMC, PMCSR, bridge extensions and data
+    fn to_bytes(&self) -> Vec<u8> {
+        let caps = &self.capabilities;
+        let pme = &caps.pme_support;
+        let pmc = lsb(&[
+            (caps.version as u32, 3),
+            (caps.pme_clock as u32, 1),
+            (caps.immediate_readiness_on_return_to_d0 as u32, 1),
+            (caps.device_specific_initialization as u32, 1),
+            (caps.aux_current as u32, 3),
+            (caps.d1_support as u32, 1),
+            (caps.d2_support as u32, 1),
+            (pme.d0 as u32, 1),
+            (pme.d1 as u32, 1),
+            (pme.d2 as u32, 1),
+            (pme.d3_hot as u32, 1),
+            (pme.d3_cold as u32, 1),
+        ]);
+        let control = &self.control;
+        let pmcsr = lsb(&[
+            (control.power_state as u32, 2),
+            (0, 1),
+            (control.no_soft_reset as u32, 1),
+            (0, 4),
+            (control.pme_enabled as u32, 1),
+            (u8::from(control.data_select) as u32, 4),
+            (control.data_scale as u32, 2),
+            (control.pme_status as u32, 1),
+        ]);
+        let bridge = lsb(&[
+            (self.bridge.reserved as u32, 6),
+            (self.bridge.b2_b3 as u32, 1),
+            (self.bridge.bpcc_enabled as u32, 1),
+        ]);
+        let mut bytes = (pmc as u16).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&(pmcsr as u16).to_le_bytes());
+        bytes.extend_from_slice(&[bridge as u8, self.data]);
+        bytes
+    }
+}
+
+impl ToBytes for MessageSignaledInterrups {
+    /// Registers following the Next Capability Pointer, size depends on Message Control
+    fn to_bytes(&self) -> Vec<u8> {
+        let mc = &self.message_control;
+        let word = lsb(&[
+            (mc.msi_enable as u32, 1),
+            (mc.multiple_message_capable.0 as u32, 3),
+            (mc.multiple_message_enable.0 as u32, 3),
+            (mc.a_64_bit_address_capable as u32, 1),
+            (mc.per_vector_masking_capable as u32, 1),
+            (mc.extended_message_data_capable as u32, 1),
+            (mc.extended_message_data_enable as u32, 1),
+        ]);
+        let mut bytes = (word as u16).to_le_bytes().to_vec();
+        match self.message_address {
+            MessageAddress::Dword(addr) => bytes.extend_from_slice(&addr.to_le_bytes()),
+            MessageAddress::Qword(addr) => bytes.extend_from_slice(&addr.to_le_bytes()),
+        }
+        bytes.extend_from_slice(&self.message_data.to_le_bytes());
+        bytes.extend_from_slice(&self.extended_message_data.to_le_bytes());
+        for bits in [self.mask_bits, self.pending_bits].into_iter().flatten() {
+            bytes.extend_from_slice(&bits.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+/// BAR Indicator register in low bits of QWORD-aligned offset
+fn msi_x_offset(bir: &Bir, offset: u32) -> u32 {
+    let bir = match *bir {
+        Bir::Bar10h => 0,
+        Bir::Bar14h => 1,
+        Bir::Bar18h => 2,
+        Bir::Bar1Ch => 3,
+        Bir::Bar20h => 4,
+        Bir::Bar24h => 5,
+        Bir::Reserved(v) => v,
+    };
+    offset & !0b111 | bir as u32 & 0b111
+}
+
+impl ToBytes for MsiX {
+    /// Message Control, Table Offset/Table BIR and PBA Offset/PBA BIR registers
+    fn to_bytes(&self) -> Vec<u8> {
+        let mc = &self.message_control;
+        let word = lsb(&[
+            (mc.table_size as u32, 11),
+            (0, 3),
+            (mc.function_mask as u32, 1),
+            (mc.msi_x_enable as u32, 1),
+        ]);
+        let mut bytes = (word as u16).to_le_bytes().to_vec();
+        let table = msi_x_offset(&self.table.bir, self.table.offset);
+        bytes.extend_from_slice(&table.to_le_bytes());
+        let pba = msi_x_offset(&self.pending_bit_array.bir, self.pending_bit_array.offset);
+        bytes.extend_from_slice(&pba.to_le_bytes());
+        bytes
+    }
+}
+
+impl ToBytes for DeviceControl {
+    fn to_bytes(&self) -> Vec<u8> {
+        let word = lsb(&[
+            (self.correctable_error_reporting_enable as u32, 1),
+            (self.non_fatal_error_reporting_enable as u32, 1),
+            (self.fatal_error_reporting_enable as u32, 1),
+            (self.unsupported_request_reporting_enable as u32, 1),
+            (self.enable_relaxed_ordering as u32, 1),
+            (self.max_payload_size as u32, 3),
+            (self.extended_tag_field_enable as u32, 1),
+            (self.phantom_functions_enable as u32, 1),
+            (self.aux_power_pm_enable as u32, 1),
+            (self.enable_no_snoop as u32, 1),
+            (self.max_read_request_size as u32, 3),
+            (self.bcre_or_flreset as u32, 1),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+impl ToBytes for LinkControl {
+    fn to_bytes(&self) -> Vec<u8> {
+        let rcb = matches!(self.read_completion_boundary, ReadCompletionBoundary::B128);
+        let word = lsb(&[
+            (self.active_state_power_management_control.clone() as u32, 2),
+            (0, 1),
+            (rcb as u32, 1),
+            (self.link_disable as u32, 1),
+            (self.retrain_link as u32, 1),
+            (self.common_clock_configuration as u32, 1),
+            (self.extended_synch as u32, 1),
+            (self.enable_clock_power_management as u32, 1),
+            (self.hardware_autonomous_width_disable as u32, 1),
+            (self.link_bandwidth_management_interrupt_enable as u32, 1),
+            (self.link_autonomous_bandwidth_interrupt_enable as u32, 1),
+        ]);
+        (word as u16).to_le_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pcics::capabilities::CapabilityKind;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn capabilities() {
+        let dump = Dump::fixture();
+        let mut checked = 0;
+        for device in dump.iter().map(Result::unwrap) {
+            let raw = device.to_bytes();
+            let word = |offset: usize| raw[offset..offset + 2].to_vec();
+            for cap in device.capabilities().unwrap().map(Result::unwrap) {
+                let offset = cap.pointer as usize;
+                match cap.kind {
+                    CapabilityKind::PowerManagementInterface(pm) => {
+                        assert_eq!(raw[offset + 2..offset + 8], pm.to_bytes()[..]);
+                        checked += 1;
+                    }
+                    CapabilityKind::MessageSignaledInterrups(msi) => {
+                        let encoded = msi.to_bytes();
+                        assert_eq!(raw[offset + 2..offset + 2 + encoded.len()], encoded[..]);
+                        checked += 1;
+                    }
+                    CapabilityKind::MsiX(msi_x) => {
+                        assert_eq!(raw[offset + 2..offset + 12], msi_x.to_bytes()[..]);
+                        checked += 1;
+                    }
+                    CapabilityKind::PciExpress(pcie) => {
+                        assert_eq!(word(offset + 0x08), pcie.device.control.to_bytes());
+                        if let Some(link) = device.express_link() {
+                            assert_eq!(word(offset + 0x10), link.control.to_bytes());
+                        }
+                        checked += 1;
+                    }
+                    _ => (),
+                }
+            }
+        }
+        assert_eq!(47, checked);
+    }
+
+    #[test]
+    fn toggled_bits() {
+        let dump = Dump::fixture();
+        let mut device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        device.header.command.bus_master = false;
+        device.header.command.interrupt_disable = false;
+        let cs: ConfigurationSpace = device.to_bytes().as_slice().try_into().unwrap();
+        assert_eq!(device.header, cs.header);
+        assert_eq!([0x02, 0x00], cs.header.command.to_bytes()[..]);
+        assert_eq!(device.to_bytes(), cs.to_bytes());
+    }
+}
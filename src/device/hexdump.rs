@@ -0,0 +1,125 @@
+/*!
+# Raw configuration space
+
+Offset-addressed hexdump of configuration space bytes, for regions this crate does not decode.
+Lines are 16 bytes long and prefixed with configuration space offset, as lspci `-xxx` and
+`-xxxx` print them.
+
+```rust
+# use pcitool::device::hexdump::{self, Hexdump};
+let range = hexdump::parse_range("0x100..0x108").unwrap();
+assert_eq!(0x100..0x108, range);
+let bytes = [0x01, 0x00, 0x01, 0x15, 0x00, 0x00, 0x00, 0x00];
+assert_eq!(
+    "100: 01 00 01 15 00 00 00 00\n",
+    Hexdump::new(range.start, &bytes).to_string()
+);
+```
+*/
+
+use core::{fmt, ops::Range};
+
+use thiserror::Error;
+
+use super::ConfigurationSpace;
+
+/// Bytes with configuration space offset of the first one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hexdump<'a> {
+    pub offset: usize,
+    pub bytes: &'a [u8],
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseRangeError {
+    #[error("range should be <start>..<end> with hexadecimal offsets")]
+    Format,
+    #[error("range {start:#x}..{end:#x} is empty or beyond configuration space")]
+    Bounds { start: usize, end: usize },
+}
+
+impl<'a> Hexdump<'a> {
+    pub fn new(offset: usize, bytes: &'a [u8]) -> Self {
+        Self { offset, bytes }
+    }
+}
+
+/// Offset has 2 digits within 256 bytes of configuration space and 3 above
+impl<'a> fmt::Display for Hexdump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = if self.offset + self.bytes.len() > 0x100 {
+            3
+        } else {
+            2
+        };
+        for (n, chunk) in self.bytes.chunks(16).enumerate() {
+            write!(f, "{:0width$x}:", self.offset + n * 16, width = width)?;
+            for byte in chunk {
+                write!(f, " {:02x}", byte)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// `<start>..<end>` hexadecimal offsets with optional `0x` prefix, end is exclusive
+pub fn parse_range(s: &str) -> Result<Range<usize>, ParseRangeError> {
+    let (start, end) = s.trim().split_once("..").ok_or(ParseRangeError::Format)?;
+    let hex = |s: &str| {
+        let s = s.trim();
+        let s = s.strip_prefix("0x").unwrap_or(s);
+        usize::from_str_radix(s, 16).map_err(|_| ParseRangeError::Format)
+    };
+    let (start, end) = (hex(start)?, hex(end)?);
+    if start >= end || end > ConfigurationSpace::SIZE {
+        return Err(ParseRangeError::Bounds { start, end });
+    }
+    Ok(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::Device;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn range() {
+        assert_eq!(Ok(0x40..0x100), parse_range("40..100"));
+        assert_eq!(Ok(0xffc..0x1000), parse_range("0xffc..0x1000"));
+        assert_eq!(Err(ParseRangeError::Format), parse_range("0x40"));
+        assert_eq!(
+            Err(ParseRangeError::Bounds {
+                start: 0x100,
+                end: 0x1001
+            }),
+            parse_range("100..1001")
+        );
+    }
+
+    #[test]
+    fn device_bytes() {
+        let mut cs = [0u8; 4096];
+        cs[0xf8..0x108].copy_from_slice(&[
+            0xde, 0xad, 0xbe, 0xef, 0x00, 0x00, 0x00, 0x01, // Device dependent region end
+            0x01, 0x00, 0x01, 0x15, 0x00, 0x00, 0x00, 0x00, // Extended capability
+        ]);
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = Device::new(Default::default(), cs);
+        assert_eq!(
+            Some(0xc0),
+            device.device_dependent_region().map(<[u8]>::len)
+        );
+        assert_eq!(
+            Some(0xf00),
+            device.extended_configuration_space().map(<[u8]>::len)
+        );
+        assert_eq!(None, device.config_bytes(0x3c..0x44));
+        let bytes = device.config_bytes(0xf8..0x108).unwrap();
+        let sample = "\
+            0f8: de ad be ef 00 00 00 01 01 00 01 15 00 00 00 00\n\
+        ";
+        assert_eq!(sample, Hexdump::new(0xf8, &bytes).to_string());
+    }
+}
@@ -0,0 +1,201 @@
+/*!
+# Device identifiers
+
+Serial numbers and other identifiers useful for asset tracking, gathered from
+configuration space and OS.
+*/
+
+use core::fmt;
+
+use alloc::{format, string::String};
+
+use pcics::{
+    capabilities::{BridgeSubsystemVendorId, CapabilityKind},
+    extended_capabilities::{device_serial_number::DeviceSerialNumber, ExtendedCapabilityKind},
+    header::{Cardbus, HeaderType, Normal},
+};
+
+use super::{Address, Device};
+
+/// Aggregated device identifiers
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Identifiers {
+    /// PCI Express Device Serial Number (IEEE EUI-64)
+    pub dsn: Option<u64>,
+    /// Serial number exported by OS (sysfs `serial` attribute)
+    pub serial: Option<String>,
+    /// SR-IOV Virtual Function position
+    pub virtual_function: Option<VirtualFunction>,
+}
+
+impl Identifiers {
+    pub fn new(device: &Device) -> Self {
+        let dsn = device.extended_capabilities().and_then(|ecaps| {
+            ecaps.flatten().find_map(|ecap| match ecap.kind {
+                ExtendedCapabilityKind::DeviceSerialNumber(DeviceSerialNumber {
+                    lower_dword,
+                    upper_dword,
+                }) => Some((upper_dword as u64) << 32 | lower_dword as u64),
+                _ => None,
+            })
+        });
+        Self {
+            dsn,
+            serial: device.serial.clone(),
+            virtual_function: device.virtual_function.clone(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Device {
+    /// Vendor ID and Device ID read as all ones: device has fallen off the bus, is powered off
+    /// or its link is down, the rest of configuration space is meaningless
+    pub fn is_not_responding(&self) -> bool {
+        (self.header.vendor_id, self.header.device_id) == (0xffff, 0xffff)
+    }
+    /// Subsystem vendor and device IDs as Linux reads them: header registers of normal and
+    /// CardBus functions, Subsystem ID capability of bridges, zeros if not present
+    pub fn subsystem_ids(&self) -> (u16, u16) {
+        match &self.header.header_type {
+            HeaderType::Normal(Normal {
+                sub_vendor_id,
+                sub_device_id,
+                ..
+            }) => (*sub_vendor_id, *sub_device_id),
+            HeaderType::Cardbus(Cardbus {
+                subsystem_vendor_id,
+                subsystem_device_id,
+                ..
+            }) => (
+                subsystem_vendor_id.unwrap_or_default(),
+                subsystem_device_id.unwrap_or_default(),
+            ),
+            _ => self
+                .capabilities()
+                .and_then(|mut caps| {
+                    caps.find_map(|cap| match cap.ok()?.kind {
+                        CapabilityKind::BridgeSubsystemVendorId(BridgeSubsystemVendorId {
+                            subsystem_vendor_id,
+                            subsystem_id,
+                            ..
+                        }) => Some((subsystem_vendor_id, subsystem_id)),
+                        _ => None,
+                    })
+                })
+                .unwrap_or_default(),
+        }
+    }
+    /// Linux `modalias` attribute built from configuration space, to match `modules.alias`
+    /// without sysfs, e.g. `pci:v00008086d00009DC8sv00001043sd000016A1bc04sc03i80`
+    pub fn modalias(&self) -> String {
+        let (sub_vendor_id, sub_device_id) = self.subsystem_ids();
+        let cc = &self.header.class_code;
+        format!(
+            "pci:v{:08X}d{:08X}sv{:08X}sd{:08X}bc{:02X}sc{:02X}i{:02X}",
+            self.header.vendor_id,
+            self.header.device_id,
+            sub_vendor_id,
+            sub_device_id,
+            cc.base,
+            cc.sub,
+            cc.interface
+        )
+    }
+}
+
+/// Columns separated by tabs: DSN, serial, VF. Missing values shown as `-`
+impl fmt::Display for Identifiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(dsn) = self.dsn {
+            write!(f, "{}", Eui64(dsn))?;
+        } else {
+            write!(f, "-")?;
+        }
+        write!(f, "\t{}", self.serial.as_deref().unwrap_or("-"))?;
+        if let Some(vf) = &self.virtual_function {
+            write!(f, "\t{}", vf)
+        } else {
+            write!(f, "\t-")
+        }
+    }
+}
+
+/// IEEE EUI-64 formatted as dash separated bytes, e.g. `00-00-00-01-00-4c-e0-00`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eui64(pub u64);
+
+impl fmt::Display for Eui64 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [b0, b1, b2, b3, b4, b5, b6, b7] = self.0.to_be_bytes();
+        write!(
+            f,
+            "{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x}-{:02x}",
+            b0, b1, b2, b3, b4, b5, b6, b7
+        )
+    }
+}
+
+/// SR-IOV Virtual Function location relative to its Physical Function
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VirtualFunction {
+    /// Physical Function address
+    pub physfn: Address,
+    /// Zero-based VF index (sysfs `virtfn<N>`)
+    pub index: u16,
+}
+
+/// Formatted as `<PF address>#<VF index>`
+impl fmt::Display for VirtualFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}", self.physfn, self.index)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn device_serial_number() {
+        let dump = Dump::fixture();
+        let device = dump.device("03:00.0".parse().unwrap()).unwrap();
+        let result = Identifiers::new(&device);
+        assert_eq!(Some(0x00000001004ce000), result.dsn);
+        assert_eq!("00-00-00-01-00-4c-e0-00\t-\t-", result.to_string());
+    }
+
+    #[test]
+    fn modalias() {
+        let dump = Dump::fixture();
+        let device = dump.device("00:1f.3".parse().unwrap()).unwrap();
+        assert_eq!(
+            "pci:v00008086d00009DC8sv00001043sd000016A1bc04sc03i80",
+            device.modalias()
+        );
+        // Root port with Subsystem ID capability
+        let device = dump.device("00:1d.4".parse().unwrap()).unwrap();
+        assert_eq!(
+            "pci:v00008086d00009DB4sv00001043sd000016A1bc06sc04i00",
+            device.modalias()
+        );
+    }
+
+    #[test]
+    fn virtual_function() {
+        let identifiers = Identifiers {
+            serial: Some("SN123".into()),
+            virtual_function: Some(VirtualFunction {
+                physfn: "0000:3b:00.0".parse().unwrap(),
+                index: 3,
+            }),
+            ..Default::default()
+        };
+        assert!(!identifiers.is_empty());
+        assert_eq!("-\tSN123\t0000:3b:00.0#3", identifiers.to_string());
+    }
+}
@@ -0,0 +1,246 @@
+/*!
+# L1 PM Substates timing
+
+L1 PM Substates registers converted to microseconds and the values software should program for
+a link, as Linux ASPM driver calculates them: T_COMMON_MODE and T_POWER_ON are the largest of
+both ports capabilities, LTR_L1.2_THRESHOLD covers T_COMMON_MODE, T_POWER_ON and 6us of
+T(L1.2 entry) and T(POWER_OFF).
+
+```rust
+# use pcitool::device::l1ss::{L1ssInfo, L1ssLink};
+# use pcics::extended_capabilities::L1PmSubstates;
+// L1SubCap: PortCommonModeRestoreTime=255us PortTPowerOnTime=10us
+// L1SubCtl1: T_CommonMode=0us LTR1.2_Threshold=51200ns, L1SubCtl2: T_PwrOn=44us
+let data = [0x1f, 0xff, 0x28, 0x00, 0x03, 0x00, 0x32, 0x40, 0xb0, 0x00, 0x00, 0x00];
+let l1ss: L1PmSubstates = data.as_slice().try_into().unwrap();
+let info = L1ssInfo::new(&l1ss);
+assert_eq!(Some(44), info.t_power_on_us);
+assert_eq!(Some(51.2), info.ltr_l1_2_threshold_us());
+let link = L1ssLink::new(&info, &info).unwrap();
+assert_eq!((255, 10, 271), (link.t_common_mode_us, link.t_power_on_us, link.ltr_l1_2_threshold_us));
+```
+*/
+
+use core::fmt;
+
+use pcics::extended_capabilities::{
+    l1_pm_substates::{L1PmSubstatesControl1, PortTPowerOn},
+    ExtendedCapabilityKind, L1PmSubstates,
+};
+
+use super::Device;
+
+/// T(L1.2 entry) 4us and T(POWER_OFF) 2us, included in LTR_L1.2_THRESHOLD
+const L1_2_ENTRY_POWER_OFF_US: u32 = 6;
+
+/// Supported or enabled substates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct L1ssStates {
+    pub pci_pm_l1_1: bool,
+    pub pci_pm_l1_2: bool,
+    pub aspm_l1_1: bool,
+    pub aspm_l1_2: bool,
+}
+
+/// L1 PM Substates capability and control with times in microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L1ssInfo {
+    pub supported: L1ssStates,
+    pub enabled: L1ssStates,
+    /// Port Common_Mode_Restore_Time
+    pub port_common_mode_restore_us: u32,
+    /// Port T_POWER_ON, None for reserved scale
+    pub port_t_power_on_us: Option<u32>,
+    /// Programmed T_COMMON_MODE, used by downstream port only
+    pub t_common_mode_us: u32,
+    /// Programmed T_POWER_ON, None for reserved scale
+    pub t_power_on_us: Option<u32>,
+    /// Programmed LTR_L1.2_THRESHOLD in ns, None for reserved scale
+    pub ltr_l1_2_threshold_ns: Option<u64>,
+}
+
+/// Values to program on both ends of a link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L1ssLink {
+    pub t_common_mode_us: u32,
+    pub t_power_on_us: u32,
+    pub ltr_l1_2_threshold_us: u32,
+}
+
+impl L1ssStates {
+    pub fn l1_2(&self) -> bool {
+        self.pci_pm_l1_2 || self.aspm_l1_2
+    }
+}
+
+impl L1ssInfo {
+    pub fn new(l1ss: &L1PmSubstates) -> Self {
+        let L1PmSubstates {
+            l1_pm_substates_capabilities: caps,
+            l1_pm_substates_control_1: ctl1,
+            l1_pm_substates_control_2: ctl2,
+        } = l1ss;
+        Self {
+            supported: L1ssStates {
+                pci_pm_l1_1: caps.pci_pm_l1_1_supported,
+                pci_pm_l1_2: caps.pci_pm_l1_2_supported,
+                aspm_l1_1: caps.aspm_l1_1_supported,
+                aspm_l1_2: caps.aspm_l1_2_supported,
+            },
+            enabled: L1ssStates {
+                pci_pm_l1_1: ctl1.pci_pm_l1_1_enable,
+                pci_pm_l1_2: ctl1.pci_pm_l1_2_enable,
+                aspm_l1_1: ctl1.aspm_l1_1_enable,
+                aspm_l1_2: ctl1.aspm_l1_2_enable,
+            },
+            port_common_mode_restore_us: caps.port_common_mode_restore_time.into(),
+            port_t_power_on_us: t_power_on_us(&caps.port_t_power_on),
+            t_common_mode_us: ctl1.common_mode_restore_time.into(),
+            t_power_on_us: t_power_on_us(&ctl2.t_power_on),
+            ltr_l1_2_threshold_ns: ltr_l1_2_threshold_ns(ctl1),
+        }
+    }
+    pub fn ltr_l1_2_threshold_us(&self) -> Option<f64> {
+        self.ltr_l1_2_threshold_ns.map(|ns| ns as f64 / 1000.0)
+    }
+}
+
+impl From<L1PmSubstates> for L1ssInfo {
+    fn from(l1ss: L1PmSubstates) -> Self {
+        Self::new(&l1ss)
+    }
+}
+
+impl L1ssLink {
+    /// None if L1.2 is not supported by both ports or T_POWER_ON scale is reserved
+    pub fn new(upstream: &L1ssInfo, downstream: &L1ssInfo) -> Option<Self> {
+        if !(upstream.supported.l1_2() && downstream.supported.l1_2()) {
+            return None;
+        }
+        let t_common_mode_us = upstream
+            .port_common_mode_restore_us
+            .max(downstream.port_common_mode_restore_us);
+        let t_power_on_us = upstream
+            .port_t_power_on_us?
+            .max(downstream.port_t_power_on_us?);
+        Some(Self {
+            t_common_mode_us,
+            t_power_on_us,
+            ltr_l1_2_threshold_us: L1_2_ENTRY_POWER_OFF_US + t_common_mode_us + t_power_on_us,
+        })
+    }
+    /// Programmed values are at least recommended ones. T_COMMON_MODE is checked on downstream
+    /// port only
+    pub fn is_programmed(&self, upstream: &L1ssInfo, downstream: &L1ssInfo) -> bool {
+        let threshold_ns = u64::from(self.ltr_l1_2_threshold_us) * 1000;
+        upstream.t_common_mode_us >= self.t_common_mode_us
+            && [upstream, downstream].iter().all(|info| {
+                info.t_power_on_us.is_some_and(|t| t >= self.t_power_on_us)
+                    && info
+                        .ltr_l1_2_threshold_ns
+                        .is_some_and(|t| t >= threshold_ns)
+            })
+    }
+}
+
+impl Device {
+    /// L1 PM Substates timing
+    pub fn l1ss(&self) -> Option<L1ssInfo> {
+        self.extended_capabilities()?
+            .flatten()
+            .find_map(|ecap| match ecap.kind {
+                ExtendedCapabilityKind::L1PmSubstates(l1ss) => Some(L1ssInfo::from(l1ss)),
+                _ => None,
+            })
+    }
+}
+
+fn t_power_on_us(t: &PortTPowerOn) -> Option<u32> {
+    t.value().map(|us| us as u32)
+}
+
+/// Scale above 5 is reserved
+fn ltr_l1_2_threshold_ns(ctl1: &L1PmSubstatesControl1) -> Option<u64> {
+    let threshold = &ctl1.ltr_l1_2_threshold;
+    (threshold.scale <= 5).then(|| threshold.value() as u64)
+}
+
+/// Times of lspci -vvvv L1SubTiming line
+impl fmt::Display for L1ssInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut us = |name, value: Option<f64>| match value {
+            Some(value) => write!(f, " {}={}us", name, value),
+            None => write!(f, " {}=<error>", name),
+        };
+        us("T_CommonMode", Some(self.t_common_mode_us.into()))?;
+        us("T_PwrOn", self.t_power_on_us.map(f64::from))?;
+        us("LTR1.2_Threshold", self.ltr_l1_2_threshold_us())?;
+        us("PortTPowerOn", self.port_t_power_on_us.map(f64::from))
+    }
+}
+
+impl fmt::Display for L1ssLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "T_CommonMode={}us T_PwrOn={}us LTR1.2_Threshold={}us",
+            self.t_common_mode_us, self.t_power_on_us, self.ltr_l1_2_threshold_us
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn info(bytes: [u8; 12]) -> L1ssInfo {
+        let l1ss: L1PmSubstates = bytes.as_slice().try_into().unwrap();
+        L1ssInfo::new(&l1ss)
+    }
+
+    #[test]
+    fn link() {
+        // Root port: PortCommonModeRestoreTime=40us PortTPowerOnTime=44us, programmed by Linux
+        let root_port = info([
+            0x1f, 0x28, 0xb0, 0x00, 0x0f, 0x28, 0x5a, 0x40, 0xb0, 0x00, 0x00, 0x00,
+        ]);
+        // NVMe: PortCommonModeRestoreTime=10us PortTPowerOnTime=10us
+        let endpoint = info([
+            0x1f, 0x0a, 0x28, 0x00, 0x0f, 0x00, 0x5a, 0x40, 0xb0, 0x00, 0x00, 0x00,
+        ]);
+        let sample = L1ssInfo {
+            supported: L1ssStates {
+                pci_pm_l1_1: true,
+                pci_pm_l1_2: true,
+                aspm_l1_1: true,
+                aspm_l1_2: true,
+            },
+            enabled: L1ssStates {
+                pci_pm_l1_1: true,
+                pci_pm_l1_2: true,
+                aspm_l1_1: true,
+                aspm_l1_2: true,
+            },
+            port_common_mode_restore_us: 40,
+            port_t_power_on_us: Some(44),
+            t_common_mode_us: 40,
+            t_power_on_us: Some(44),
+            ltr_l1_2_threshold_ns: Some(92160),
+        };
+        assert_eq!(sample, root_port);
+        assert_eq!(
+            " T_CommonMode=40us T_PwrOn=44us LTR1.2_Threshold=92.16us PortTPowerOn=44us",
+            root_port.to_string()
+        );
+        let link = L1ssLink::new(&root_port, &endpoint).unwrap();
+        let sample = L1ssLink {
+            t_common_mode_us: 40,
+            t_power_on_us: 44,
+            ltr_l1_2_threshold_us: 90,
+        };
+        assert_eq!(sample, link);
+        assert!(link.is_programmed(&root_port, &endpoint));
+        assert!(!link.is_programmed(&endpoint, &root_port));
+    }
+}
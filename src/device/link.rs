@@ -0,0 +1,255 @@
+/*!
+# PCI Express link
+
+Negotiated and maximum link speed and width with usable bandwidth, which accounts for
+encoding overhead: 8b/10b for 2.5 and 5 GT/s, 128b/130b for 8 to 32 GT/s and 242B/256B FLIT
+for 64 GT/s. Protocol (TLP/DLLP) overhead is not taken into account.
+
+[LinkSample] holds Link Status and Device Status error bits, comparing samples taken over time
+shows retraining and errors of flapping links.
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::capabilities::{
+    pci_express::{Link, LinkSpeed, LinkWidth},
+    CapabilityKind,
+};
+
+use super::Device;
+
+/// Link state summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkInfo {
+    pub speed: LinkSpeed,
+    pub width: LinkWidth,
+    pub max_speed: LinkSpeed,
+    pub max_width: LinkWidth,
+}
+
+impl LinkInfo {
+    pub fn new(link: &Link) -> Self {
+        Self {
+            speed: link.status.current_link_speed,
+            width: link.status.negotiated_link_width.clone(),
+            max_speed: link.capabilities.max_link_speed,
+            max_width: link.capabilities.maximum_link_width.clone(),
+        }
+    }
+    /// Usable bandwidth in Gb/s per direction
+    pub fn bandwidth_gbps(&self) -> f64 {
+        bandwidth_gbps(self.speed, &self.width)
+    }
+    /// Bandwidth with maximum speed and width
+    pub fn max_bandwidth_gbps(&self) -> f64 {
+        bandwidth_gbps(self.max_speed, &self.max_width)
+    }
+    /// Link trained below its capabilities. Link down (x0) is not downgraded
+    pub fn is_downgraded(&self) -> bool {
+        let bandwidth = self.bandwidth_gbps();
+        bandwidth > 0.0 && bandwidth < self.max_bandwidth_gbps()
+    }
+}
+
+/// Link state and error bits at one moment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSample {
+    pub speed: LinkSpeed,
+    pub width: LinkWidth,
+    /// LnkSta and DevSta bits named as in lspci
+    pub flags: [(&'static str, bool); 9],
+}
+
+/// Difference between two samples
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkChange {
+    Speed(LinkSpeed, LinkSpeed),
+    Width(LinkWidth, LinkWidth),
+    /// Bit name and new value
+    Flag(&'static str, bool),
+}
+
+impl LinkSample {
+    /// Changes from `self` to `new` sample
+    pub fn changes(&self, new: &Self) -> Vec<LinkChange> {
+        let mut changes = Vec::new();
+        if self.speed != new.speed {
+            changes.push(LinkChange::Speed(self.speed, new.speed));
+        }
+        if self.width != new.width {
+            changes.push(LinkChange::Width(self.width.clone(), new.width.clone()));
+        }
+        for ((name, old), (_, new)) in self.flags.iter().zip(&new.flags) {
+            if old != new {
+                changes.push(LinkChange::Flag(name, *new));
+            }
+        }
+        changes
+    }
+}
+
+impl Device {
+    /// PCI Express link state with Link Status and Device Status error bits
+    pub fn link_sample(&self) -> Option<LinkSample> {
+        let link = self.express_link()?;
+        let device_status = self
+            .capabilities()?
+            .flatten()
+            .find_map(|cap| match cap.kind {
+                CapabilityKind::PciExpress(pcie) => Some(pcie.device.status),
+                _ => None,
+            })?;
+        let status = link.status;
+        Some(LinkSample {
+            speed: status.current_link_speed,
+            width: status.negotiated_link_width,
+            flags: [
+                ("TrErr", status.link_training_error),
+                ("Train", status.link_training),
+                ("DLActive", status.data_link_layer_link_active),
+                ("BWMgmt", status.link_bandwidth_management_status),
+                ("ABWMgmt", status.link_autonomous_bandwidth_status),
+                ("CorrErr", device_status.correctable_error_detected),
+                ("NonFatalErr", device_status.non_fatal_error_detected),
+                ("FatalErr", device_status.fatal_error_detected),
+                ("UnsupReq", device_status.unsupported_request_detected),
+            ],
+        })
+    }
+}
+
+impl From<Link> for LinkInfo {
+    fn from(link: Link) -> Self {
+        Self::new(&link)
+    }
+}
+
+/// Raw transfer rate per lane in GT/s, 0 for reserved encodings
+pub fn speed_gts(speed: LinkSpeed) -> f64 {
+    match speed {
+        LinkSpeed::Rate2GTps => 2.5,
+        LinkSpeed::Rate5GTps => 5.0,
+        LinkSpeed::Rate8GTps => 8.0,
+        LinkSpeed::Rate16GTps => 16.0,
+        LinkSpeed::Rate32GTps => 32.0,
+        LinkSpeed::Rate64GTps => 64.0,
+        LinkSpeed::RateRsvdp | LinkSpeed::Reserved(_) => 0.0,
+    }
+}
+
+/// Share of transferred bits carrying data
+pub fn encoding_efficiency(speed: LinkSpeed) -> f64 {
+    match speed {
+        LinkSpeed::Rate2GTps | LinkSpeed::Rate5GTps => 8.0 / 10.0,
+        LinkSpeed::Rate64GTps => 242.0 / 256.0,
+        _ => 128.0 / 130.0,
+    }
+}
+
+/// Number of lanes, 0 for reserved encodings
+pub fn lanes(width: &LinkWidth) -> u8 {
+    match width {
+        LinkWidth::Reserved(_) => 0,
+        width => width.clone().into(),
+    }
+}
+
+/// Usable bandwidth in Gb/s per direction
+pub fn bandwidth_gbps(speed: LinkSpeed, width: &LinkWidth) -> f64 {
+    speed_gts(speed) * encoding_efficiency(speed) * lanes(width) as f64
+}
+
+/// `<speed>GT/s x<width> (<bandwidth> Gb/s)`
+impl fmt::Display for LinkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}GT/s x{} ({:.1} Gb/s)",
+            speed_gts(self.speed),
+            lanes(&self.width),
+            self.bandwidth_gbps()
+        )
+    }
+}
+
+/// `<speed>GT/s x<width>` followed by set bits
+impl fmt::Display for LinkSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}GT/s x{}", speed_gts(self.speed), lanes(&self.width))?;
+        for (name, _) in self.flags.iter().filter(|(_, value)| *value) {
+            write!(f, " {}+", name)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LinkChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Speed(old, new) => {
+                write!(
+                    f,
+                    "speed {}GT/s -> {}GT/s",
+                    speed_gts(*old),
+                    speed_gts(*new)
+                )
+            }
+            Self::Width(old, new) => write!(f, "width x{} -> x{}", lanes(old), lanes(new)),
+            Self::Flag(name, value) => write!(f, "{}{}", name, if *value { '+' } else { '-' }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use alloc::string::ToString;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn bandwidth() {
+        assert_eq!(2.0, bandwidth_gbps(LinkSpeed::Rate2GTps, &LinkWidth::X1));
+        assert_eq!(32.0, bandwidth_gbps(LinkSpeed::Rate5GTps, &LinkWidth::X8));
+        let gen3x4 = bandwidth_gbps(LinkSpeed::Rate8GTps, &LinkWidth::X4);
+        assert!((gen3x4 - 31.51).abs() < 0.01, "{}", gen3x4);
+        assert_eq!(0.0, bandwidth_gbps(LinkSpeed::Reserved(7), &LinkWidth::X4));
+    }
+
+    #[test]
+    fn downgraded() {
+        let info = LinkInfo {
+            speed: LinkSpeed::Rate2GTps,
+            width: LinkWidth::X4,
+            max_speed: LinkSpeed::Rate8GTps,
+            max_width: LinkWidth::X4,
+        };
+        assert!(info.is_downgraded());
+        assert_eq!("2.5GT/s x4 (8.0 Gb/s)", info.to_string());
+    }
+
+    #[test]
+    fn sample_changes() {
+        let dump = Dump::fixture();
+        let old = dump
+            .device("00:1d.4".parse().unwrap())
+            .unwrap()
+            .link_sample()
+            .unwrap();
+        assert_eq!("8GT/s x4 DLActive+ BWMgmt+", old.to_string());
+        assert!(old.changes(&old).is_empty());
+        let mut new = LinkSample {
+            speed: LinkSpeed::Rate2GTps,
+            ..old.clone()
+        };
+        new.flags[3].1 = false;
+        let result = old
+            .changes(&new)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["speed 8GT/s -> 2.5GT/s", "BWMgmt-"], result);
+    }
+}
@@ -0,0 +1,672 @@
+/*!
+# Lane Margining at the Receiver
+
+Ports with 16.0 GT/s and higher links move the sampling point of a receiver away from its
+normal setting on request and count errors there. Commands are written to Margining Lane
+Control of the downstream port and addressed to a receiver: 1 is the downstream port receiver,
+2-5 are retimer receivers and 6 is the upstream port receiver. Responses are read from
+Margining Lane Status.
+
+Eye width is the sum of timing steps passed to the left and to the right, eye height of voltage
+steps passed up and down, scaled by receiver reported maximum offsets. Margining is driven
+through [LaneRegisters] (feature `margining`), `Device::margin_lane` runs it through `Access`.
+Moving the sampling point on a link in use may cause errors, margining results depend on the
+traffic during the dwell time.
+
+```rust
+# use pcitool::device::margining::{LaneEye, MarginCommand, ReceiverParameters};
+// Step Margin to timing offset to the left of 3 steps addressed to Downstream Port receiver
+assert_eq!(0x4319, MarginCommand::StepTiming { left: true, steps: 3 }.control(1));
+let parameters = ReceiverParameters {
+    independent_left_right_timing: true,
+    num_timing_steps: 16,
+    max_timing_offset: 40,
+    ..Default::default()
+};
+let eye = LaneEye { lane: 0, parameters, left: 6, right: 8, up: 0, down: 0 };
+assert_eq!(35.0, eye.width());
+assert_eq!(None, eye.height());
+```
+*/
+
+use core::fmt;
+
+#[cfg(feature = "margining")]
+use core::time::Duration;
+
+#[cfg(feature = "margining")]
+use pcics::capabilities::{
+    pci_express::{DeviceType, LinkSpeed},
+    CapabilityKind, PciExpress,
+};
+use pcics::extended_capabilities::ExtendedCapabilityKind;
+#[cfg(feature = "margining")]
+use thiserror::Error;
+
+use super::Device;
+
+/// Margining Port Capabilities register offset relative to capability
+const PORT_CAPABILITIES: usize = 0x04;
+/// Margining Port Status register offset relative to capability
+const PORT_STATUS: usize = 0x06;
+/// Margining Lane Control register of lane 0 relative to capability
+const LANE_CONTROL: usize = 0x08;
+/// Margining Lane Status follows Lane Control of the same lane
+const LANE_STATUS: usize = 0x0a;
+
+/// Downstream Port receiver (Receiver A)
+pub const RECEIVER_DOWNSTREAM_PORT: u8 = 1;
+/// Upstream Port receiver (Receiver F)
+pub const RECEIVER_UPSTREAM_PORT: u8 = 6;
+
+/// Margining Port Capabilities and Status with register location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarginingPort {
+    /// Margining is ready only after device driver sets Margining Software Ready
+    pub uses_driver_software: bool,
+    pub ready: bool,
+    pub software_ready: bool,
+    /// Capability offset in configuration space
+    pub offset: usize,
+}
+
+impl MarginingPort {
+    /// Margin commands are accepted
+    pub fn is_ready(&self) -> bool {
+        if self.uses_driver_software {
+            self.software_ready
+        } else {
+            self.ready
+        }
+    }
+    /// Margining Lane Control register offset of `lane`
+    pub fn lane_control_offset(&self, lane: usize) -> usize {
+        self.offset + LANE_CONTROL + lane * 4
+    }
+    /// Margining Lane Status register offset of `lane`
+    pub fn lane_status_offset(&self, lane: usize) -> usize {
+        self.offset + LANE_STATUS + lane * 4
+    }
+}
+
+impl Device {
+    /// Lane Margining at the Receiver port registers
+    pub fn margining_port(&self) -> Option<MarginingPort> {
+        let offset = self.extended_capabilities()?.flatten().find_map(|ecap| {
+            matches!(
+                ecap.kind,
+                ExtendedCapabilityKind::LaneMarginingAtTheReceiver(_)
+            )
+            .then_some(ecap.offset as usize)
+        })?;
+        let bytes = self.capability_bytes(offset)?;
+        let word = |n: usize| {
+            let bytes = bytes.get(n..n + 2)?;
+            Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+        };
+        let capabilities = word(PORT_CAPABILITIES)?;
+        let status = word(PORT_STATUS)?;
+        Some(MarginingPort {
+            uses_driver_software: capabilities & 0b1 != 0,
+            ready: status & 0b01 != 0,
+            software_ready: status & 0b10 != 0,
+            offset,
+        })
+    }
+}
+
+/// Report command payloads
+pub const REPORT_CAPABILITIES: u8 = 0x88;
+pub const REPORT_NUM_VOLTAGE_STEPS: u8 = 0x89;
+pub const REPORT_NUM_TIMING_STEPS: u8 = 0x8a;
+pub const REPORT_MAX_TIMING_OFFSET: u8 = 0x8b;
+pub const REPORT_MAX_VOLTAGE_OFFSET: u8 = 0x8c;
+
+/// Margin command of Margining Lane Control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginCommand {
+    /// Idle command, receiver number is ignored
+    NoCommand,
+    /// Access Receiver Margin Control Capabilities and limits, payload selects the value
+    Report(u8),
+    /// Error count at which receiver stops margining and goes back to normal settings
+    SetErrorCountLimit(u8),
+    GoToNormalSettings,
+    ClearErrorLog,
+    /// Move sampling point in time, `left` is ignored without independent left/right timing
+    StepTiming {
+        left: bool,
+        steps: u8,
+    },
+    /// Move sampling point in voltage, `down` is ignored without independent up/down voltage
+    StepVoltage {
+        down: bool,
+        steps: u8,
+    },
+}
+
+impl MarginCommand {
+    /// Margin Type and Margin Payload
+    fn type_and_payload(self) -> (u8, u8) {
+        match self {
+            Self::NoCommand => (0b111, 0x9c),
+            Self::Report(payload) => (0b001, payload),
+            Self::SetErrorCountLimit(limit) => (0b010, 0xc0 | (limit & 0x3f)),
+            Self::GoToNormalSettings => (0b010, 0x0f),
+            Self::ClearErrorLog => (0b010, 0x55),
+            Self::StepTiming { left, steps } => (0b011, (left as u8) << 6 | (steps & 0x3f)),
+            Self::StepVoltage { down, steps } => (0b100, (down as u8) << 7 | (steps & 0x7f)),
+        }
+    }
+    /// Margining Lane Control value addressed to `receiver`, usage model is Lane Margining
+    pub fn control(self, receiver: u8) -> u16 {
+        let (margin_type, payload) = self.type_and_payload();
+        let receiver = if self == Self::NoCommand { 0 } else { receiver };
+        (payload as u16) << 8 | (margin_type as u16) << 3 | (receiver & 0b111) as u16
+    }
+    /// Margining Lane Status is the response to command written as `control`
+    pub fn is_response(control: u16, status: u16) -> bool {
+        // Receiver Number, Margin Type and Usage Model
+        control & 0x7f == status & 0x7f
+    }
+}
+
+impl fmt::Display for MarginCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCommand => write!(f, "No Command"),
+            Self::Report(payload) => write!(f, "Report {:02x}h", payload),
+            Self::SetErrorCountLimit(limit) => write!(f, "Set Error Count Limit {}", limit),
+            Self::GoToNormalSettings => write!(f, "Go to Normal Settings"),
+            Self::ClearErrorLog => write!(f, "Clear Error Log"),
+            Self::StepTiming { left, steps } => {
+                let direction = if *left { "left" } else { "right" };
+                write!(f, "Step Margin to timing offset {} {}", direction, steps)
+            }
+            Self::StepVoltage { down, steps } => {
+                let direction = if *down { "down" } else { "up" };
+                write!(f, "Step Margin to voltage offset {} {}", direction, steps)
+            }
+        }
+    }
+}
+
+/// Margin Payload of Step Margin response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    /// Error Count Limit exceeded, receiver is back at normal settings
+    TooManyErrors,
+    SetUpInProgress,
+    MarginingInProgress {
+        error_count: u8,
+    },
+    /// Step is out of receiver range
+    Nak,
+}
+
+impl From<u8> for StepStatus {
+    fn from(payload: u8) -> Self {
+        match payload >> 6 {
+            0b00 => Self::TooManyErrors,
+            0b01 => Self::SetUpInProgress,
+            0b10 => Self::MarginingInProgress {
+                error_count: payload & 0x3f,
+            },
+            _ => Self::Nak,
+        }
+    }
+}
+
+/// Receiver Margin Control Capabilities and limits reported by receiver
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReceiverParameters {
+    pub voltage_supported: bool,
+    pub independent_up_down_voltage: bool,
+    pub independent_left_right_timing: bool,
+    pub num_voltage_steps: u8,
+    pub num_timing_steps: u8,
+    /// Offset at Number of timing steps in percents of UI
+    pub max_timing_offset: u8,
+    /// Offset at Number of voltage steps in 10 mV units
+    pub max_voltage_offset: u8,
+}
+
+/// Steps passed in each direction, without independent directions both sides are the same
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaneEye {
+    pub lane: usize,
+    pub parameters: ReceiverParameters,
+    pub left: u8,
+    pub right: u8,
+    pub up: u8,
+    pub down: u8,
+}
+
+impl LaneEye {
+    /// Eye width in percents of UI
+    pub fn width(&self) -> f32 {
+        let ReceiverParameters {
+            num_timing_steps,
+            max_timing_offset,
+            ..
+        } = self.parameters;
+        if num_timing_steps == 0 {
+            return 0.0;
+        }
+        let steps = self.left as f32 + self.right as f32;
+        steps * max_timing_offset as f32 / num_timing_steps as f32
+    }
+    /// Eye height in mV, `None` if receiver does not support voltage margining
+    pub fn height(&self) -> Option<f32> {
+        let ReceiverParameters {
+            voltage_supported,
+            num_voltage_steps,
+            max_voltage_offset,
+            ..
+        } = self.parameters;
+        if !voltage_supported || num_voltage_steps == 0 {
+            return None;
+        }
+        let steps = self.up as f32 + self.down as f32;
+        Some(steps * max_voltage_offset as f32 * 10.0 / num_voltage_steps as f32)
+    }
+}
+
+impl fmt::Display for LaneEye {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "lane {}: width {:.1}% UI (left {} right {} steps)",
+            self.lane,
+            self.width(),
+            self.left,
+            self.right
+        )?;
+        if let Some(height) = self.height() {
+            write!(
+                f,
+                ", height {:.0} mV (up {} down {} steps)",
+                height, self.up, self.down
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "margining")]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MarginingError {
+    #[error("device has no Lane Margining at the Receiver capability")]
+    NoCapability,
+    #[error("device is not a downstream port with 16.0 GT/s or faster link")]
+    NotDownstreamPort,
+    #[error("margining is not ready")]
+    NotReady,
+    #[error("lane {lane} is out of x{width} link")]
+    Lane { lane: usize, width: usize },
+    #[error("receiver {receiver} does not respond to {command} on lane {lane}")]
+    Timeout {
+        lane: usize,
+        receiver: u8,
+        command: MarginCommand,
+    },
+    #[error("receiver {receiver} rejected {command} on lane {lane}")]
+    Nak {
+        lane: usize,
+        receiver: u8,
+        command: MarginCommand,
+    },
+}
+
+#[cfg(feature = "margining")]
+impl Device {
+    /// Margining registers of downstream port with `lane` in 16.0 GT/s or faster link
+    pub fn margining_lane_port(&self, lane: usize) -> Result<MarginingPort, MarginingError> {
+        let port = self.margining_port().ok_or(MarginingError::NoCapability)?;
+        let is_downstream_port = self
+            .capabilities()
+            .into_iter()
+            .flatten()
+            .flatten()
+            .any(|cap| {
+                matches!(
+                    cap.kind,
+                    CapabilityKind::PciExpress(PciExpress {
+                        device_type: DeviceType::RootPort { .. }
+                            | DeviceType::DownstreamPort { .. },
+                        ..
+                    })
+                )
+            });
+        let link = self.express_link().filter(|link| {
+            matches!(
+                link.status.current_link_speed,
+                LinkSpeed::Rate16GTps | LinkSpeed::Rate32GTps | LinkSpeed::Rate64GTps
+            )
+        });
+        let Some(link) = link.filter(|_| is_downstream_port) else {
+            return Err(MarginingError::NotDownstreamPort);
+        };
+        let width = u8::from(link.status.negotiated_link_width) as usize;
+        if lane >= width {
+            return Err(MarginingError::Lane { lane, width });
+        }
+        if !port.is_ready() {
+            return Err(MarginingError::NotReady);
+        }
+        Ok(port)
+    }
+}
+
+/// Margining Lane Control and Status registers of a port
+#[cfg(feature = "margining")]
+pub trait LaneRegisters {
+    type Error: From<MarginingError>;
+    fn write_control(&mut self, lane: usize, control: u16) -> Result<(), Self::Error>;
+    fn read_status(&mut self, lane: usize) -> Result<u16, Self::Error>;
+    /// Pause between status polls and while margining
+    fn wait(&mut self, duration: Duration);
+}
+
+/// Receiver, error limit and time spent at each step
+#[cfg(feature = "margining")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarginSettings {
+    pub receiver: u8,
+    /// Errors allowed at a passing step
+    pub error_limit: u8,
+    pub dwell: Duration,
+}
+
+#[cfg(feature = "margining")]
+impl Default for MarginSettings {
+    fn default() -> Self {
+        Self {
+            receiver: RECEIVER_DOWNSTREAM_PORT,
+            error_limit: 4,
+            dwell: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Receivers respond within 10 ms
+#[cfg(feature = "margining")]
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+#[cfg(feature = "margining")]
+const POLL_LIMIT: usize = 100;
+
+#[cfg(feature = "margining")]
+struct Margining<'a, R> {
+    registers: &'a mut R,
+    lane: usize,
+    settings: &'a MarginSettings,
+}
+
+#[cfg(feature = "margining")]
+impl<R: LaneRegisters> Margining<'_, R> {
+    /// Write command and poll for response, returns response Margin Payload
+    fn command(&mut self, command: MarginCommand) -> Result<u8, R::Error> {
+        let control = command.control(self.settings.receiver);
+        self.registers.write_control(self.lane, control)?;
+        for _ in 0..POLL_LIMIT {
+            let status = self.registers.read_status(self.lane)?;
+            if MarginCommand::is_response(control, status) {
+                return Ok((status >> 8) as u8);
+            }
+            self.registers.wait(POLL_INTERVAL);
+        }
+        Err(self.error(command, true).into())
+    }
+    /// Command preceded by No Command, so repeated commands get fresh responses
+    fn exchange(&mut self, command: MarginCommand) -> Result<u8, R::Error> {
+        self.command(MarginCommand::NoCommand)?;
+        self.command(command)
+    }
+    /// Set, clear or go to normal settings command is acknowledged by the same payload
+    fn set(&mut self, command: MarginCommand) -> Result<(), R::Error> {
+        let (_, payload) = command.type_and_payload();
+        if self.exchange(command)? == payload {
+            Ok(())
+        } else {
+            Err(self.error(command, false).into())
+        }
+    }
+    fn error(&self, command: MarginCommand, timeout: bool) -> MarginingError {
+        let (lane, receiver) = (self.lane, self.settings.receiver);
+        if timeout {
+            MarginingError::Timeout {
+                lane,
+                receiver,
+                command,
+            }
+        } else {
+            MarginingError::Nak {
+                lane,
+                receiver,
+                command,
+            }
+        }
+    }
+    fn parameters(&mut self) -> Result<ReceiverParameters, R::Error> {
+        let capabilities = self.exchange(MarginCommand::Report(REPORT_CAPABILITIES))?;
+        let mut report = |payload| {
+            self.exchange(MarginCommand::Report(payload))
+                .map(|value| value & 0x7f)
+        };
+        Ok(ReceiverParameters {
+            voltage_supported: capabilities & 0b001 != 0,
+            independent_up_down_voltage: capabilities & 0b010 != 0,
+            independent_left_right_timing: capabilities & 0b100 != 0,
+            num_voltage_steps: report(REPORT_NUM_VOLTAGE_STEPS)?,
+            num_timing_steps: report(REPORT_NUM_TIMING_STEPS)? & 0x3f,
+            max_timing_offset: report(REPORT_MAX_TIMING_OFFSET)?,
+            max_voltage_offset: report(REPORT_MAX_VOLTAGE_OFFSET)?,
+        })
+    }
+    /// Step passes if error count stays within limit for the dwell time
+    fn step(&mut self, command: MarginCommand) -> Result<bool, R::Error> {
+        let mut payload = self.exchange(command)?;
+        let mut dwelled = false;
+        for _ in 0..POLL_LIMIT {
+            match StepStatus::from(payload) {
+                StepStatus::TooManyErrors => return Ok(false),
+                StepStatus::Nak => return Err(self.error(command, false).into()),
+                StepStatus::MarginingInProgress { error_count } if dwelled => {
+                    return Ok(error_count <= self.settings.error_limit);
+                }
+                StepStatus::MarginingInProgress { .. } => {
+                    self.registers.wait(self.settings.dwell);
+                    dwelled = true;
+                }
+                StepStatus::SetUpInProgress => self.registers.wait(POLL_INTERVAL),
+            }
+            payload = (self.registers.read_status(self.lane)? >> 8) as u8;
+        }
+        Err(self.error(command, true).into())
+    }
+    /// Last passing step moving away from normal settings
+    fn steps_passed(
+        &mut self,
+        max_steps: u8,
+        command: impl Fn(u8) -> MarginCommand,
+    ) -> Result<u8, R::Error> {
+        let mut passed = 0;
+        for steps in 1..=max_steps {
+            self.set(MarginCommand::ClearErrorLog)?;
+            if !self.step(command(steps))? {
+                break;
+            }
+            passed = steps;
+        }
+        self.set(MarginCommand::GoToNormalSettings)?;
+        self.set(MarginCommand::ClearErrorLog)?;
+        Ok(passed)
+    }
+    fn eye(&mut self) -> Result<LaneEye, R::Error> {
+        let parameters = self.parameters()?;
+        self.set(MarginCommand::SetErrorCountLimit(self.settings.error_limit))?;
+        let timing = |left| move |steps| MarginCommand::StepTiming { left, steps };
+        let right = self.steps_passed(parameters.num_timing_steps, timing(false))?;
+        let left = if parameters.independent_left_right_timing {
+            self.steps_passed(parameters.num_timing_steps, timing(true))?
+        } else {
+            right
+        };
+        let voltage = |down| move |steps| MarginCommand::StepVoltage { down, steps };
+        let (up, down) = if !parameters.voltage_supported {
+            (0, 0)
+        } else if parameters.independent_up_down_voltage {
+            (
+                self.steps_passed(parameters.num_voltage_steps, voltage(false))?,
+                self.steps_passed(parameters.num_voltage_steps, voltage(true))?,
+            )
+        } else {
+            let steps = self.steps_passed(parameters.num_voltage_steps, voltage(false))?;
+            (steps, steps)
+        };
+        self.command(MarginCommand::NoCommand)?;
+        Ok(LaneEye {
+            lane: self.lane,
+            parameters,
+            left,
+            right,
+            up,
+            down,
+        })
+    }
+}
+
+/// Margin `lane` of the receiver selected by `settings` to both sides in time and voltage
+#[cfg(feature = "margining")]
+pub fn margin<R: LaneRegisters>(
+    registers: &mut R,
+    lane: usize,
+    settings: &MarginSettings,
+) -> Result<LaneEye, R::Error> {
+    Margining {
+        registers,
+        lane,
+        settings,
+    }
+    .eye()
+}
+
+#[cfg(all(test, feature = "margining"))]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Receiver with 16 timing steps of 3% UI and 32 voltage steps of 10 mV
+    struct Receiver {
+        status: u16,
+        /// Steps passing in each direction: right, left, up, down
+        eye: [u8; 4],
+        commands: usize,
+    }
+
+    impl LaneRegisters for Receiver {
+        type Error = MarginingError;
+        fn write_control(&mut self, _lane: usize, control: u16) -> Result<(), MarginingError> {
+            let (margin_type, payload) = ((control >> 3) & 0b111, (control >> 8) as u8);
+            let response = match (margin_type, payload) {
+                (0b001, 0x88) => 0b111,
+                (0b001, 0x89) => 32,
+                (0b001, 0x8a) => 16,
+                (0b001, 0x8b) => 48,
+                (0b001, 0x8c) => 32,
+                (0b011, payload) => {
+                    let limit = self.eye[(payload >> 6) as usize];
+                    if payload & 0x3f > limit {
+                        0x00
+                    } else {
+                        0x81
+                    }
+                }
+                (0b100, payload) => {
+                    let limit = self.eye[2 + (payload >> 7) as usize];
+                    if payload & 0x7f > limit {
+                        0x00
+                    } else {
+                        0x80
+                    }
+                }
+                (_, payload) => payload,
+            };
+            self.status = (response as u16) << 8 | control & 0x7f;
+            self.commands += 1;
+            Ok(())
+        }
+        fn read_status(&mut self, _lane: usize) -> Result<u16, MarginingError> {
+            Ok(self.status)
+        }
+        fn wait(&mut self, _duration: Duration) {}
+    }
+
+    #[test]
+    fn margin_eye() {
+        let mut receiver = Receiver {
+            status: 0,
+            eye: [7, 5, 10, 12],
+            commands: 0,
+        };
+        let settings = MarginSettings::default();
+        let eye = margin(&mut receiver, 2, &settings).unwrap();
+        assert_eq!((5, 7, 10, 12), (eye.left, eye.right, eye.up, eye.down));
+        assert_eq!(36.0, eye.width());
+        assert_eq!(Some(220.0), eye.height());
+        assert_eq!(
+            "lane 2: width 36.0% UI (left 5 right 7 steps), height 220 mV (up 10 down 12 steps)",
+            eye.to_string()
+        );
+        assert!(receiver.commands > 0);
+    }
+
+    #[test]
+    fn commands() {
+        assert_eq!(0x9c38, MarginCommand::NoCommand.control(6));
+        assert_eq!(0x880e, MarginCommand::Report(0x88).control(6));
+        assert_eq!(0xc411, MarginCommand::SetErrorCountLimit(4).control(1));
+        assert_eq!(
+            0x8321,
+            MarginCommand::StepVoltage {
+                down: true,
+                steps: 3
+            }
+            .control(1)
+        );
+        assert!(MarginCommand::is_response(0x8321, 0x8521));
+        assert!(!MarginCommand::is_response(0x8321, 0x8519));
+        let status = |payload| StepStatus::from(payload);
+        assert_eq!(
+            StepStatus::MarginingInProgress { error_count: 3 },
+            status(0x83)
+        );
+        assert_eq!(StepStatus::Nak, status(0xc0));
+
+        let mut receiver = Receiver {
+            status: 0,
+            eye: [7, 5, 10, 12],
+            commands: 0,
+        };
+        // Retimer receiver without responses
+        struct Silent;
+        impl LaneRegisters for Silent {
+            type Error = MarginingError;
+            fn write_control(&mut self, _: usize, _: u16) -> Result<(), MarginingError> {
+                Ok(())
+            }
+            fn read_status(&mut self, _: usize) -> Result<u16, MarginingError> {
+                Ok(0)
+            }
+            fn wait(&mut self, _: Duration) {}
+        }
+        let result = margin(&mut Silent, 0, &Default::default());
+        let timeout = MarginingError::Timeout {
+            lane: 0,
+            receiver: 1,
+            command: MarginCommand::NoCommand,
+        };
+        assert_eq!(Err(timeout), result);
+        assert!(margin(&mut receiver, 0, &Default::default()).is_ok());
+    }
+}
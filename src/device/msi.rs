@@ -0,0 +1,337 @@
+/*!
+# MSI and MSI-X control
+
+Enable bits of MSI and MSI-X Message Control, MSI-X Function Mask and MSI per-vector Mask Bits.
+MSI-X per-vector masks are in the MSI-X table in BAR memory, they are not changed here.
+Other Message Control bits are kept.
+
+```rust
+# use pcitool::device::msi::MsiSetting;
+let setting: MsiSetting = "mask=3".parse().unwrap();
+assert_eq!(MsiSetting::Mask { vector: 3, masked: true }, setting);
+```
+*/
+
+use core::{fmt, str::FromStr};
+
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use super::{registers::Width, Device};
+
+const CAP_MSI: u8 = 0x05;
+const CAP_MSI_X: u8 = 0x11;
+/// Message Control register offset relative to capability
+const MSGCTL: usize = 0x02;
+const MSI_ENABLE: u16 = 1 << 0;
+const MSI_MMC_SHIFT: u16 = 1;
+const MSI_MME_SHIFT: u16 = 4;
+const MSI_64BIT: u16 = 1 << 7;
+const MSI_PER_VECTOR_MASKING: u16 = 1 << 8;
+/// Mask Bits offset relative to capability with 32-bit and 64-bit Message Address
+const MSI_MASK_32: usize = 0x0c;
+const MSI_MASK_64: usize = 0x10;
+const MSIX_TABLE_SIZE: u16 = 0x07ff;
+const MSIX_FUNCTION_MASK: u16 = 1 << 14;
+const MSIX_ENABLE: u16 = 1 << 15;
+
+/// MSI Message Control and Mask Bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsiControl {
+    pub enabled: bool,
+    pub vectors_capable: u8,
+    pub vectors_enabled: u8,
+    /// Mask Bits, `None` without per-vector masking
+    pub mask: Option<u32>,
+    /// Message Control register offset in configuration space
+    pub control_offset: usize,
+}
+
+/// MSI-X Message Control
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsiXControl {
+    pub enabled: bool,
+    pub function_mask: bool,
+    pub table_size: u16,
+    /// Message Control register offset in configuration space
+    pub control_offset: usize,
+}
+
+/// Single MSI or MSI-X change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsiSetting {
+    /// MSI Enable
+    Msi(bool),
+    /// MSI-X Enable
+    MsiX(bool),
+    /// MSI-X Function Mask
+    FunctionMask(bool),
+    /// MSI per-vector Mask Bit
+    Mask { vector: u8, masked: bool },
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MsiError {
+    #[error(
+        "setting should be msi=on|off, msix=on|off, function-mask=on|off, mask=<n> or unmask=<n>"
+    )]
+    Parse,
+    #[error("device has no MSI capability")]
+    NoMsi,
+    #[error("device has no MSI-X capability")]
+    NoMsiX,
+    #[error("MSI capability has no per-vector masking")]
+    NoMaskBits,
+    #[error("MSI vector {vector} is out of {capable} capable vectors")]
+    Vector { vector: u8, capable: u8 },
+}
+
+/// Register write: configuration space offset, value and width
+pub type MsiWrite = (usize, u32, Width);
+
+/// `msi=on|off`, `msix=on|off`, `function-mask=on|off`, `mask=<n>` or `unmask=<n>`
+impl FromStr for MsiSetting {
+    type Err = MsiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let on = |value| match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(MsiError::Parse),
+        };
+        let vector = |value: &str| value.parse::<u8>().map_err(|_| MsiError::Parse);
+        match s.trim().split_once('=').ok_or(MsiError::Parse)? {
+            ("msi", value) => on(value).map(Self::Msi),
+            ("msix", value) => on(value).map(Self::MsiX),
+            ("function-mask", value) => on(value).map(Self::FunctionMask),
+            ("mask", value) => vector(value).map(|vector| Self::Mask {
+                vector,
+                masked: true,
+            }),
+            ("unmask", value) => vector(value).map(|vector| Self::Mask {
+                vector,
+                masked: false,
+            }),
+            _ => Err(MsiError::Parse),
+        }
+    }
+}
+
+impl Device {
+    /// MSI state, `None` if device has no MSI capability
+    pub fn msi_control(&self) -> Option<MsiControl> {
+        let cap = self.capability_offset(CAP_MSI)?;
+        let [_, _, lo, hi] = self.ddr_bytes(cap)?;
+        let control = u16::from_le_bytes([lo, hi]);
+        let mask = if control & MSI_PER_VECTOR_MASKING != 0 {
+            Some(u32::from_le_bytes(self.ddr_bytes(cap + msi_mask(control))?))
+        } else {
+            None
+        };
+        Some(MsiControl {
+            enabled: control & MSI_ENABLE != 0,
+            vectors_capable: vectors(control >> MSI_MMC_SHIFT),
+            vectors_enabled: vectors(control >> MSI_MME_SHIFT),
+            mask,
+            control_offset: cap + MSGCTL,
+        })
+    }
+    /// MSI-X state, `None` if device has no MSI-X capability
+    pub fn msi_x_control(&self) -> Option<MsiXControl> {
+        let cap = self.capability_offset(CAP_MSI_X)?;
+        let [_, _, lo, hi] = self.ddr_bytes(cap)?;
+        let control = u16::from_le_bytes([lo, hi]);
+        Some(MsiXControl {
+            enabled: control & MSIX_ENABLE != 0,
+            function_mask: control & MSIX_FUNCTION_MASK != 0,
+            table_size: (control & MSIX_TABLE_SIZE) + 1,
+            control_offset: cap + MSGCTL,
+        })
+    }
+    /// Register writes with `settings` applied, only changed registers are written
+    pub fn msi_writes(&self, settings: &[MsiSetting]) -> Result<Vec<MsiWrite>, MsiError> {
+        let msi = self.capability_offset(CAP_MSI).and_then(|cap| {
+            let [_, _, lo, hi] = self.ddr_bytes(cap)?;
+            Some((cap, u16::from_le_bytes([lo, hi])))
+        });
+        let msi_x = self.capability_offset(CAP_MSI_X).and_then(|cap| {
+            let [_, _, lo, hi] = self.ddr_bytes(cap)?;
+            Some((cap, u16::from_le_bytes([lo, hi])))
+        });
+        let mut msi_control = msi.map(|(_, control)| control);
+        let mut msi_x_control = msi_x.map(|(_, control)| control);
+        let mut mask = None;
+        for setting in settings {
+            match *setting {
+                MsiSetting::Msi(enable) => {
+                    let control = msi_control.as_mut().ok_or(MsiError::NoMsi)?;
+                    set(control, MSI_ENABLE, enable);
+                }
+                MsiSetting::MsiX(enable) => {
+                    let control = msi_x_control.as_mut().ok_or(MsiError::NoMsiX)?;
+                    set(control, MSIX_ENABLE, enable);
+                }
+                MsiSetting::FunctionMask(masked) => {
+                    let control = msi_x_control.as_mut().ok_or(MsiError::NoMsiX)?;
+                    set(control, MSIX_FUNCTION_MASK, masked);
+                }
+                MsiSetting::Mask { vector, masked } => {
+                    let (cap, control) = msi.ok_or(MsiError::NoMsi)?;
+                    if control & MSI_PER_VECTOR_MASKING == 0 {
+                        return Err(MsiError::NoMaskBits);
+                    }
+                    let capable = vectors(control >> MSI_MMC_SHIFT);
+                    if vector >= capable {
+                        return Err(MsiError::Vector { vector, capable });
+                    }
+                    let offset = cap + msi_mask(control);
+                    let (_, _, bits) = mask.get_or_insert_with(|| {
+                        let bits = self.ddr_bytes(offset).map(u32::from_le_bytes);
+                        let bits = bits.unwrap_or_default();
+                        (offset, bits, bits)
+                    });
+                    *bits = if masked {
+                        *bits | 1 << vector
+                    } else {
+                        *bits & !(1 << vector)
+                    };
+                }
+            }
+        }
+        let mut writes = Vec::new();
+        if let (Some((cap, old)), Some(new)) = (msi, msi_control) {
+            if old != new {
+                writes.push((cap + MSGCTL, new as u32, Width::Word));
+            }
+        }
+        if let Some((offset, old, new)) = mask {
+            if old != new {
+                writes.push((offset, new, Width::Long));
+            }
+        }
+        if let (Some((cap, old)), Some(new)) = (msi_x, msi_x_control) {
+            if old != new {
+                writes.push((cap + MSGCTL, new as u32, Width::Word));
+            }
+        }
+        Ok(writes)
+    }
+}
+
+fn msi_mask(control: u16) -> usize {
+    if control & MSI_64BIT != 0 {
+        MSI_MASK_64
+    } else {
+        MSI_MASK_32
+    }
+}
+
+/// Multiple Message Capable/Enable encoding, 0b110 and 0b111 are reserved
+fn vectors(encoded: u16) -> u8 {
+    1 << (encoded & 0b111).min(5)
+}
+
+fn set(control: &mut u16, bit: u16, on: bool) {
+    *control = if on { *control | bit } else { *control & !bit };
+}
+
+impl fmt::Display for MsiControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MSI: Enable{} Count={}/{}",
+            if self.enabled { '+' } else { '-' },
+            self.vectors_enabled,
+            self.vectors_capable
+        )?;
+        if let Some(mask) = self.mask {
+            write!(f, " Masking={:08x}", mask)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MsiXControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MSI-X: Enable{} Count={} Masked{}",
+            if self.enabled { '+' } else { '-' },
+            self.table_size,
+            if self.function_mask { '+' } else { '-' },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{Capability, DeviceBuilder};
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    /// MSI at 0x40, MSI-X at 0x58
+    fn device() -> Device {
+        let msi = Capability::Raw {
+            id: 0x05,
+            data: vec![
+                0x87, 0x01, // 64-bit, per-vector masking, 8 capable, 1 enabled, enabled
+                0x00, 0x00, 0xe0, 0xfe, 0x00, 0x00, 0x00, 0x00, // Message Address
+                0x00, 0x40, 0x00, 0x00, // Message Data
+                0x00, 0x00, 0x00, 0x00, // Mask Bits
+                0x00, 0x00, 0x00, 0x00, // Pending Bits
+            ],
+        };
+        // 16 vectors, disabled
+        let msi_x = Capability::MsiX {
+            table_size: 16,
+            table_bar: 0,
+            table_offset: 0x2000,
+            pba_bar: 0,
+            pba_offset: 0x3000,
+        };
+        DeviceBuilder::new(0x8086, 0x1533)
+            .capability(msi)
+            .capability(msi_x)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn state() {
+        let device = device();
+        let msi = device.msi_control().unwrap();
+        assert_eq!("MSI: Enable+ Count=1/8 Masking=00000000", msi.to_string());
+        let msi_x = device.msi_x_control().unwrap();
+        assert_eq!("MSI-X: Enable- Count=16 Masked-", msi_x.to_string());
+    }
+
+    #[test]
+    fn writes() {
+        let device = device();
+        let settings = [
+            "msi=off",
+            "mask=0",
+            "mask=7",
+            "unmask=7",
+            "function-mask=on",
+        ]
+        .map(|s| s.parse().unwrap());
+        let sample = vec![
+            (0x42, 0x0186, Width::Word),
+            (0x50, 0x0000_0001, Width::Long),
+            (0x5a, 0x400f, Width::Word),
+        ];
+        assert_eq!(Ok(sample), device.msi_writes(&settings));
+        assert_eq!(Ok(vec![]), device.msi_writes(&[MsiSetting::Msi(true)]));
+        assert_eq!(
+            Err(MsiError::Vector {
+                vector: 8,
+                capable: 8
+            }),
+            device.msi_writes(&["mask=8".parse().unwrap()])
+        );
+        assert_eq!(Err(MsiError::Parse), "msi=yes".parse::<MsiSetting>());
+    }
+}
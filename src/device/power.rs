@@ -0,0 +1,137 @@
+/*!
+# Runtime power management
+
+Device power state as managed by OS, complements Power Management capability
+registers. On Linux gathered from sysfs `power/runtime_status`, `power/control`
+and `d3cold_allowed` attributes. Current D-state is read from Power Management capability.
+*/
+
+use core::{fmt, str::FromStr};
+
+use alloc::string::String;
+
+use pcics::capabilities::{power_management_interface::PowerState, CapabilityKind};
+use thiserror::Error;
+
+use super::Device;
+
+/// OS runtime power management data
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PowerInfo {
+    /// Runtime PM status: active, suspended, suspending, resuming, error or unsupported
+    pub runtime_status: Option<String>,
+    /// Runtime PM control
+    pub control: Option<PowerControl>,
+    /// Device is allowed to enter D3cold
+    pub d3cold_allowed: Option<bool>,
+}
+
+impl PowerInfo {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Runtime PM control (sysfs `power/control`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerControl {
+    /// Device may be suspended at run time
+    Auto,
+    /// Device is kept at full power
+    On,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("power control should be one of: auto, on")]
+pub struct ParsePowerControlError;
+
+impl FromStr for PowerControl {
+    type Err = ParsePowerControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "auto" => Ok(Self::Auto),
+            "on" => Ok(Self::On),
+            _ => Err(ParsePowerControlError),
+        }
+    }
+}
+
+impl fmt::Display for PowerControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::On => write!(f, "on"),
+        }
+    }
+}
+
+/// `Status <status>, Control <control>, D3cold<flag>`, missing values are skipped
+impl fmt::Display for PowerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        if let Some(status) = &self.runtime_status {
+            write!(f, "Status {}", status)?;
+            sep = ", ";
+        }
+        if let Some(control) = self.control {
+            write!(f, "{}Control {}", sep, control)?;
+            sep = ", ";
+        }
+        if let Some(allowed) = self.d3cold_allowed {
+            write!(f, "{}D3cold{}", sep, if allowed { '+' } else { '-' })?;
+        }
+        Ok(())
+    }
+}
+
+impl Device {
+    /// Current power state from Power Management capability Control/Status register
+    pub fn power_state(&self) -> Option<PowerState> {
+        self.capabilities()?
+            .flatten()
+            .find_map(|cap| match cap.kind {
+                CapabilityKind::PowerManagementInterface(pmi) => Some(pmi.control.power_state),
+                _ => None,
+            })
+    }
+}
+
+/// D-state name as lspci prints it: `D0`, `D1`, `D2` or `D3hot`
+pub fn power_state_name(state: PowerState) -> &'static str {
+    match state {
+        PowerState::D0 => "D0",
+        PowerState::D1 => "D1",
+        PowerState::D2 => "D2",
+        PowerState::D3Hot => "D3hot",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_control() {
+        assert_eq!(Ok(PowerControl::Auto), "auto\n".parse());
+        assert_eq!(Ok(PowerControl::On), "on".parse());
+        assert_eq!(Err(ParsePowerControlError), "off".parse::<PowerControl>());
+    }
+
+    #[test]
+    fn display() {
+        let info = PowerInfo {
+            runtime_status: Some("suspended".into()),
+            control: Some(PowerControl::Auto),
+            d3cold_allowed: Some(true),
+        };
+        assert_eq!("Status suspended, Control auto, D3cold+", info.to_string());
+        let info = PowerInfo {
+            control: Some(PowerControl::On),
+            ..Default::default()
+        };
+        assert_eq!("Control on", info.to_string());
+    }
+}
@@ -0,0 +1,283 @@
+/*!
+# Precision Time Measurement
+
+PTM state of a function and of the path from PTM Root down to an endpoint. PTM works only if
+every function on the path has PTM enabled and the nearest upstream PTM Root is selected.
+Effective Granularity of a requester is the largest Local Clock Granularity between PTM Root
+and the requester; functions without local clock report 0.
+
+```rust
+# use pcitool::device::{builder::{DeviceBuilder, ExtendedCapability}, ptm::Granularity};
+let data = vec![
+    0x01, 0x00, 0x00, 0x00, // Requester capable
+    0x01, 0x04, 0x00, 0x00, // Enabled, effective granularity 4ns
+];
+let ptm = ExtendedCapability::Raw { id: 0x001f, version: 1, data };
+let device = DeviceBuilder::new(0x8086, 0x15f3).extended_capability(ptm).build().unwrap();
+let ptm = device.ptm().unwrap();
+assert!(ptm.requester && ptm.enabled);
+assert_eq!(Granularity(4), ptm.effective_granularity);
+assert_eq!("4ns", ptm.effective_granularity.to_string());
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::extended_capabilities::ExtendedCapabilityKind;
+
+use super::{Address, Device};
+
+/// PTM Capability and Control registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PtmInfo {
+    pub requester: bool,
+    pub responder: bool,
+    pub root: bool,
+    pub local_granularity: Granularity,
+    pub enabled: bool,
+    pub root_selected: bool,
+    pub effective_granularity: Granularity,
+}
+
+/// Clock granularity in ns: 0 is not implemented or unknown, 255 is greater than 254ns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Granularity(pub u8);
+
+/// PTM of every function from the topmost port down to the endpoint
+#[derive(Debug, Clone)]
+pub struct PtmPath<'a> {
+    pub hops: Vec<(&'a Device, Option<PtmInfo>)>,
+}
+
+/// Reason PTM does not work on the path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PtmProblem {
+    NoCapability(Address),
+    Disabled(Address),
+    NoRoot,
+    /// Programmed Effective Granularity differs from the path one
+    Granularity {
+        address: Address,
+        programmed: Granularity,
+        expected: Granularity,
+    },
+}
+
+impl Device {
+    /// Precision Time Measurement capability and control
+    pub fn ptm(&self) -> Option<PtmInfo> {
+        self.extended_capabilities()?.flatten().find_map(|ecap| {
+            let ExtendedCapabilityKind::PrecisionTimeMeasurement(ptm) = ecap.kind else {
+                return None;
+            };
+            let (cap, ctl) = (ptm.ptm_capability, ptm.ptm_control);
+            Some(PtmInfo {
+                requester: cap.ptm_requester_capable,
+                responder: cap.ptm_responder_capable,
+                root: cap.ptm_root_capable,
+                local_granularity: Granularity(cap.local_clock_granularity),
+                enabled: ctl.ptm_enable,
+                root_selected: ctl.root_select,
+                effective_granularity: Granularity(ctl.effective_granularity),
+            })
+        })
+    }
+}
+
+impl<'a> PtmPath<'a> {
+    /// `path` from the topmost bridge down to the endpoint, as
+    /// [Topology::path](crate::topology::Topology::path) returns
+    pub fn new(path: impl IntoIterator<Item = &'a Device>) -> Self {
+        let hops = path
+            .into_iter()
+            .map(|device| (device, device.ptm()))
+            .collect();
+        Self { hops }
+    }
+    /// Index of the nearest upstream enabled PTM Root
+    fn root_index(&self) -> Option<usize> {
+        let upstream = self.hops.len().checked_sub(1)?;
+        self.hops[..upstream]
+            .iter()
+            .rposition(|(_, ptm)| ptm.is_some_and(|ptm| ptm.enabled && ptm.root_selected))
+    }
+    pub fn root(&self) -> Option<&'a Device> {
+        self.root_index().map(|index| self.hops[index].0)
+    }
+    /// Largest Local Clock Granularity from PTM Root to the endpoint upstream port
+    pub fn effective_granularity(&self) -> Granularity {
+        let (Some(root), Some(upstream)) = (self.root_index(), self.hops.len().checked_sub(1))
+        else {
+            return Granularity::default();
+        };
+        self.hops[root..upstream]
+            .iter()
+            .filter_map(|(_, ptm)| ptm.map(|ptm| ptm.local_granularity))
+            .max()
+            .unwrap_or_default()
+    }
+    /// First problem from the endpoint up to PTM Root
+    pub fn problem(&self) -> Option<PtmProblem> {
+        let root = self.root_index();
+        let start = root.unwrap_or_default();
+        for (device, ptm) in self.hops[start..].iter().rev() {
+            let address = device.address.clone();
+            match ptm {
+                None => return Some(PtmProblem::NoCapability(address)),
+                Some(ptm) if !ptm.enabled => return Some(PtmProblem::Disabled(address)),
+                _ => (),
+            }
+        }
+        if root.is_none() {
+            return Some(PtmProblem::NoRoot);
+        }
+        let (device, ptm) = self.hops.last()?;
+        let programmed = ptm.as_ref()?.effective_granularity;
+        let expected = self.effective_granularity();
+        (programmed != expected).then(|| PtmProblem::Granularity {
+            address: device.address.clone(),
+            programmed,
+            expected,
+        })
+    }
+}
+
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            0 => write!(f, "unknown"),
+            255 => write!(f, ">254ns"),
+            n => write!(f, "{}ns", n),
+        }
+    }
+}
+
+/// lspci-like flags
+impl fmt::Display for PtmInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |b: bool| if b { '+' } else { '-' };
+        write!(
+            f,
+            "Requester{} Responder{} Root{} Granularity {}, Enabled{} RootSelected{} Effective {}",
+            flag(self.requester),
+            flag(self.responder),
+            flag(self.root),
+            self.local_granularity,
+            flag(self.enabled),
+            flag(self.root_selected),
+            self.effective_granularity,
+        )
+    }
+}
+
+impl fmt::Display for PtmProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCapability(address) => write!(f, "{}: no PTM capability", address),
+            Self::Disabled(address) => write!(f, "{}: PTM is not enabled", address),
+            Self::NoRoot => write!(f, "no upstream port is selected as PTM Root"),
+            Self::Granularity {
+                address,
+                programmed,
+                expected,
+            } => write!(
+                f,
+                "{}: Effective Granularity is {}, path granularity is {}",
+                address, programmed, expected
+            ),
+        }
+    }
+}
+
+/// Hop per line and summary
+impl<'a> fmt::Display for PtmPath<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (device, ptm) in &self.hops {
+            match ptm {
+                Some(ptm) => writeln!(f, "{}: {}", device.address, ptm)?,
+                None => writeln!(f, "{}: no PTM capability", device.address)?,
+            }
+        }
+        match (self.problem(), self.root()) {
+            (None, Some(root)) => writeln!(
+                f,
+                "PTM enabled from root {}, effective granularity {}",
+                root.address,
+                self.effective_granularity()
+            ),
+            (problem, _) => writeln!(
+                f,
+                "PTM does not work: {}",
+                problem.unwrap_or(PtmProblem::NoRoot)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{DeviceBuilder, ExtendedCapability};
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    /// Capability register bits 0-2 and granularity, control enable and root select bits
+    fn device(
+        address: &str,
+        capability: u8,
+        granularity: u8,
+        control: u8,
+        effective: u8,
+    ) -> Device {
+        let data = vec![capability, granularity, 0, 0, control, effective, 0, 0];
+        DeviceBuilder::new(0x8086, 0x15f3)
+            .address(address.parse().unwrap())
+            .extended_capability(ExtendedCapability::Raw {
+                id: 0x001f,
+                version: 1,
+                data,
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn path() {
+        let root_port = device("00:1c.0", 0b110, 4, 0b11, 4);
+        let switch = device("01:00.0", 0b011, 8, 0b01, 4);
+        let endpoint = device("02:00.0", 0b001, 0, 0b01, 8);
+        let path = PtmPath::new([&root_port, &switch, &endpoint]);
+        assert_eq!(
+            Some("00:1c.0".parse().unwrap()),
+            path.root().map(|d| d.address.clone())
+        );
+        assert_eq!(Granularity(8), path.effective_granularity());
+        assert_eq!(None, path.problem());
+        let sample = "\
+            0000:00:1c.0: Requester- Responder+ Root+ Granularity 4ns, Enabled+ RootSelected+ Effective 4ns\n\
+            0000:01:00.0: Requester+ Responder+ Root- Granularity 8ns, Enabled+ RootSelected- Effective 4ns\n\
+            0000:02:00.0: Requester+ Responder- Root- Granularity unknown, Enabled+ RootSelected- Effective 8ns\n\
+            PTM enabled from root 0000:00:1c.0, effective granularity 8ns\n\
+        ";
+        assert_eq!(sample, path.to_string());
+
+        let disabled = device("02:00.0", 0b001, 0, 0b00, 0);
+        let path = PtmPath::new([&root_port, &switch, &disabled]);
+        assert_eq!(
+            Some(PtmProblem::Disabled("02:00.0".parse().unwrap())),
+            path.problem()
+        );
+        let stale = device("02:00.0", 0b001, 0, 0b01, 4);
+        let path = PtmPath::new([&root_port, &switch, &stale]);
+        assert_eq!(
+            "0000:02:00.0: Effective Granularity is 4ns, path granularity is 8ns",
+            path.problem().unwrap().to_string()
+        );
+        let no_root = device("00:1c.0", 0b110, 4, 0b01, 0);
+        let path = PtmPath::new([&no_root, &switch, &endpoint]);
+        assert_eq!(None, path.root());
+        assert_eq!(Some(PtmProblem::NoRoot), path.problem());
+    }
+}
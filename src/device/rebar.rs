@@ -0,0 +1,230 @@
+/*!
+# Resizable BAR
+
+BAR size selection through Resizable BAR extended capability. Sizes are powers of two
+from 1MB (encoded as 0) to 8EB (encoded as 43).
+
+```rust
+# use pcitool::device::rebar::BarSize;
+let size: BarSize = "8G".parse().unwrap();
+assert_eq!(13, size.0);
+assert_eq!("8GB", size.to_string());
+```
+*/
+
+use core::{fmt, str::FromStr};
+
+use alloc::vec::Vec;
+
+use pcics::extended_capabilities::{
+    resizable_bar::ResizableBarEntry, ExtendedCapability, ExtendedCapabilityKind,
+};
+use thiserror::Error;
+
+use super::{Device, ExtendedConfigurationSpace};
+
+/// Encoded BAR size: 2^(20 + n) bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BarSize(pub u8);
+
+impl BarSize {
+    pub const MAX: u8 = ResizableBarEntry::BAR_SIZES.len() as u8 - 1;
+    pub fn bytes(&self) -> u64 {
+        1 << (20 + self.0 as u64)
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseBarSizeError {
+    #[error("size should be a number with optional K, M, G, T, P or E suffix")]
+    Format,
+    #[error("size should be a power of two from 1M to 8E")]
+    Value,
+}
+
+/// Accepts `<n>[K|M|G|T|P|E][B]` (case insensitive), plain number is in bytes
+impl FromStr for BarSize {
+    type Err = ParseBarSizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s
+            .strip_suffix('B')
+            .or_else(|| s.strip_suffix('b'))
+            .unwrap_or(s);
+        let (digits, shift) = match s.char_indices().last() {
+            Some((n, c)) if c.is_ascii_alphabetic() => {
+                let shift = match c.to_ascii_uppercase() {
+                    'K' => 10,
+                    'M' => 20,
+                    'G' => 30,
+                    'T' => 40,
+                    'P' => 50,
+                    'E' => 60,
+                    _ => return Err(ParseBarSizeError::Format),
+                };
+                (&s[..n], shift)
+            }
+            _ => (s, 0),
+        };
+        let value: u64 = digits.parse().map_err(|_| ParseBarSizeError::Format)?;
+        if !value.is_power_of_two() {
+            return Err(ParseBarSizeError::Value);
+        }
+        let power = value.trailing_zeros() + shift;
+        match power.checked_sub(20) {
+            Some(n) if n <= Self::MAX as u32 => Ok(Self(n as u8)),
+            _ => Err(ParseBarSizeError::Value),
+        }
+    }
+}
+
+impl fmt::Display for BarSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match ResizableBarEntry::BAR_SIZES.get(self.0 as usize) {
+            Some(s) => f.write_str(s),
+            None => write!(f, "<unknown>"),
+        }
+    }
+}
+
+/// Resizable BAR entry with its location in configuration space
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResizableBarInfo {
+    pub bar: u8,
+    pub current: BarSize,
+    pub supported: Vec<BarSize>,
+    /// Resizable BAR Control register offset
+    pub control_offset: usize,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResizableBarSetError {
+    #[error("device has no Resizable BAR capability")]
+    NoCapability,
+    #[error("BAR {0} is not resizable")]
+    NoBar(u8),
+    #[error("BAR {bar} does not support size {size}")]
+    Unsupported { bar: u8, size: BarSize },
+    #[error("Resizable BAR Control register is unreadable")]
+    Unreadable,
+}
+
+impl Device {
+    /// Resizable BARs, empty if there is no capability
+    pub fn resizable_bars(&self) -> Vec<ResizableBarInfo> {
+        let Some(ecaps) = self.extended_capabilities() else {
+            return Vec::new();
+        };
+        ecaps
+            .flatten()
+            .filter_map(|ExtendedCapability { kind, offset, .. }| match kind {
+                ExtendedCapabilityKind::ResizableBar(rebar) => Some((offset, rebar)),
+                _ => None,
+            })
+            .flat_map(|(offset, rebar)| {
+                rebar.enumerate().map(move |(n, entry)| {
+                    let supported = (0..=BarSize::MAX)
+                        .filter(|&s| entry.is_function_supports_power_of_two(s as usize + 20))
+                        .map(BarSize)
+                        .collect();
+                    ResizableBarInfo {
+                        bar: entry.control.bar_index,
+                        current: BarSize(entry.control.bar_size),
+                        supported,
+                        control_offset: offset as usize
+                            + ExtendedCapability::HEADER_SIZE
+                            + n * 8
+                            + 4,
+                    }
+                })
+            })
+            .collect()
+    }
+    /// Offset and new value of Resizable BAR Control register to select `size` for `bar`
+    pub fn resizable_bar_control(
+        &self,
+        bar: u8,
+        size: BarSize,
+    ) -> Result<(usize, u32), ResizableBarSetError> {
+        let bars = self.resizable_bars();
+        if bars.is_empty() {
+            return Err(ResizableBarSetError::NoCapability);
+        }
+        let info = bars
+            .into_iter()
+            .find(|info| info.bar == bar)
+            .ok_or(ResizableBarSetError::NoBar(bar))?;
+        if !info.supported.contains(&size) {
+            return Err(ResizableBarSetError::Unsupported { bar, size });
+        }
+        let offset = info.control_offset - ExtendedConfigurationSpace::OFFSET;
+        let bytes = self
+            .extended_configuration_space
+            .as_ref()
+            .and_then(|ecs| ecs.0.get(offset..offset + 4))
+            .ok_or(ResizableBarSetError::Unreadable)?;
+        let control = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let control = (control & !0x3f00) | ((size.0 as u32) << 8);
+        Ok((info.control_offset, control))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{DeviceBuilder, ExtendedCapability};
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_bar_size() {
+        assert_eq!(Ok(BarSize(0)), "1M".parse());
+        assert_eq!(Ok(BarSize(8)), "256MB".parse());
+        assert_eq!(Ok(BarSize(13)), "8g".parse());
+        assert_eq!(Ok(BarSize(43)), "8E".parse());
+        assert_eq!(Ok(BarSize(1)), "2097152".parse());
+        assert_eq!(Err(ParseBarSizeError::Value), "3G".parse::<BarSize>());
+        assert_eq!(Err(ParseBarSizeError::Value), "512K".parse::<BarSize>());
+        assert_eq!(Err(ParseBarSizeError::Value), "16E".parse::<BarSize>());
+        assert_eq!(Err(ParseBarSizeError::Format), "big".parse::<BarSize>());
+    }
+
+    #[test]
+    fn set_size() {
+        let data = vec![
+            0x00, 0x30, 0x02, 0x00, // 256MB, 512MB, 8GB
+            0x20, 0x08, 0x00, 0x00, // BAR 0, 1 entry, 256MB
+        ];
+        let device = DeviceBuilder::new(0x1002, 0x73bf)
+            .extended_capability(ExtendedCapability::Raw {
+                id: 0x0015,
+                version: 1,
+                data,
+            })
+            .build()
+            .unwrap();
+        let sample = vec![ResizableBarInfo {
+            bar: 0,
+            current: BarSize(8),
+            supported: vec![BarSize(8), BarSize(9), BarSize(13)],
+            control_offset: 0x108,
+        }];
+        assert_eq!(sample, device.resizable_bars());
+        assert_eq!(
+            Ok((0x108, 0x0000_0d20)),
+            device.resizable_bar_control(0, BarSize(13))
+        );
+        assert_eq!(
+            Err(ResizableBarSetError::Unsupported {
+                bar: 0,
+                size: BarSize(10)
+            }),
+            device.resizable_bar_control(0, BarSize(10))
+        );
+        assert_eq!(
+            Err(ResizableBarSetError::NoBar(2)),
+            device.resizable_bar_control(2, BarSize(8))
+        );
+    }
+}
@@ -0,0 +1,579 @@
+/*!
+# Configuration registers
+
+Symbolic register names compatible with `setpci`. Register is addressed with
+`<name>|<offset>[+<offset>][.<width>]`: named header register (`COMMAND`), hex offset (`04.w`)
+or named capability with offset relative to its header (`CAP_PM+4.b`, `ECAP_AER+0x10.l`). Width is `b`, `w` or `l`
+and may be omitted for named registers. Names are case insensitive, offsets are hex.
+
+PCI Express registers also have names relative to PCI Express capability (`LNKSTA` is
+`CAP_EXP+0x12.w`).
+
+```rust
+# use pcitool::device::registers::{Base, RegisterAddress, Width};
+let reg: RegisterAddress = "CAP_PM+4.w".parse().unwrap();
+assert_eq!(Base::Capability(0x01), reg.base);
+assert_eq!((4, Width::Word), (reg.offset, reg.width));
+assert_eq!("COMMAND", "04.w".parse::<RegisterAddress>().unwrap().to_string());
+```
+*/
+
+use core::{fmt, str::FromStr};
+
+use alloc::string::{String, ToString};
+
+use thiserror::Error;
+
+use super::{Device, DeviceDependentRegion};
+
+/// Register access width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Width {
+    Byte,
+    Word,
+    Long,
+}
+
+impl Width {
+    /// Width in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::Word => 2,
+            Self::Long => 4,
+        }
+    }
+    /// All bits of register set
+    pub fn mask(&self) -> u32 {
+        match self {
+            Self::Byte => 0xff,
+            Self::Word => 0xffff,
+            Self::Long => 0xffff_ffff,
+        }
+    }
+    fn suffix(&self) -> char {
+        match self {
+            Self::Byte => 'b',
+            Self::Word => 'w',
+            Self::Long => 'l',
+        }
+    }
+}
+
+/// Named register of configuration space header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register {
+    pub name: &'static str,
+    pub offset: u16,
+    pub width: Width,
+}
+
+const fn reg(name: &'static str, offset: u16, width: Width) -> Register {
+    Register {
+        name,
+        offset,
+        width,
+    }
+}
+
+/// Header registers known to `setpci`, bridge and CardBus registers overlap normal ones
+pub const REGISTERS: &[Register] = {
+    use Width::*;
+    &[
+        reg("VENDOR_ID", 0x00, Word),
+        reg("DEVICE_ID", 0x02, Word),
+        reg("COMMAND", 0x04, Word),
+        reg("STATUS", 0x06, Word),
+        reg("REVISION", 0x08, Byte),
+        reg("CLASS_PROG", 0x09, Byte),
+        reg("CLASS_DEVICE", 0x0a, Word),
+        reg("CACHE_LINE_SIZE", 0x0c, Byte),
+        reg("LATENCY_TIMER", 0x0d, Byte),
+        reg("HEADER_TYPE", 0x0e, Byte),
+        reg("BIST", 0x0f, Byte),
+        reg("BASE_ADDRESS_0", 0x10, Long),
+        reg("BASE_ADDRESS_1", 0x14, Long),
+        reg("BASE_ADDRESS_2", 0x18, Long),
+        reg("BASE_ADDRESS_3", 0x1c, Long),
+        reg("BASE_ADDRESS_4", 0x20, Long),
+        reg("BASE_ADDRESS_5", 0x24, Long),
+        reg("CARDBUS_CIS", 0x28, Long),
+        reg("SUBSYSTEM_VENDOR_ID", 0x2c, Word),
+        reg("SUBSYSTEM_ID", 0x2e, Word),
+        reg("ROM_ADDRESS", 0x30, Long),
+        reg("CAPABILITIES", 0x34, Byte),
+        reg("INTERRUPT_LINE", 0x3c, Byte),
+        reg("INTERRUPT_PIN", 0x3d, Byte),
+        reg("MIN_GNT", 0x3e, Byte),
+        reg("MAX_LAT", 0x3f, Byte),
+        // PCI-to-PCI bridge
+        reg("PRIMARY_BUS", 0x18, Byte),
+        reg("SECONDARY_BUS", 0x19, Byte),
+        reg("SUBORDINATE_BUS", 0x1a, Byte),
+        reg("SEC_LATENCY_TIMER", 0x1b, Byte),
+        reg("IO_BASE", 0x1c, Byte),
+        reg("IO_LIMIT", 0x1d, Byte),
+        reg("SEC_STATUS", 0x1e, Word),
+        reg("MEMORY_BASE", 0x20, Word),
+        reg("MEMORY_LIMIT", 0x22, Word),
+        reg("PREF_MEMORY_BASE", 0x24, Word),
+        reg("PREF_MEMORY_LIMIT", 0x26, Word),
+        reg("PREF_BASE_UPPER32", 0x28, Long),
+        reg("PREF_LIMIT_UPPER32", 0x2c, Long),
+        reg("IO_BASE_UPPER16", 0x30, Word),
+        reg("IO_LIMIT_UPPER16", 0x32, Word),
+        reg("BRIDGE_ROM_ADDRESS", 0x38, Long),
+        reg("BRIDGE_CONTROL", 0x3e, Word),
+        // CardBus bridge
+        reg("CB_CARDBUS_BASE", 0x10, Long),
+        reg("CB_CAPABILITIES", 0x14, Word),
+        reg("CB_SEC_STATUS", 0x16, Word),
+        reg("CB_BUS_NUMBER", 0x18, Byte),
+        reg("CB_CARDBUS_NUMBER", 0x19, Byte),
+        reg("CB_SUBORDINATE_BUS", 0x1a, Byte),
+        reg("CB_CARDBUS_LATENCY", 0x1b, Byte),
+        reg("CB_MEMORY_BASE_0", 0x1c, Long),
+        reg("CB_MEMORY_LIMIT_0", 0x20, Long),
+        reg("CB_MEMORY_BASE_1", 0x24, Long),
+        reg("CB_MEMORY_LIMIT_1", 0x28, Long),
+        reg("CB_IO_BASE_0", 0x2c, Word),
+        reg("CB_IO_BASE_0_HI", 0x2e, Word),
+        reg("CB_IO_LIMIT_0", 0x30, Word),
+        reg("CB_IO_LIMIT_0_HI", 0x32, Word),
+        reg("CB_IO_BASE_1", 0x34, Word),
+        reg("CB_IO_BASE_1_HI", 0x36, Word),
+        reg("CB_IO_LIMIT_1", 0x38, Word),
+        reg("CB_IO_LIMIT_1_HI", 0x3a, Word),
+        reg("CB_BRIDGE_CONTROL", 0x3e, Word),
+        reg("CB_SUBSYSTEM_VENDOR_ID", 0x40, Word),
+        reg("CB_SUBSYSTEM_ID", 0x42, Word),
+        reg("CB_LEGACY_MODE_BASE", 0x44, Long),
+    ]
+};
+
+/// PCI Express capability registers, offsets are relative to capability header
+pub const EXPRESS_REGISTERS: &[Register] = {
+    use Width::*;
+    &[
+        reg("EXP_FLAGS", 0x02, Word),
+        reg("DEVCAP", 0x04, Long),
+        reg("DEVCTL", 0x08, Word),
+        reg("DEVSTA", 0x0a, Word),
+        reg("LNKCAP", 0x0c, Long),
+        reg("LNKCTL", 0x10, Word),
+        reg("LNKSTA", 0x12, Word),
+        reg("SLTCAP", 0x14, Long),
+        reg("SLTCTL", 0x18, Word),
+        reg("SLTSTA", 0x1a, Word),
+        reg("RTCTL", 0x1c, Word),
+        reg("RTCAP", 0x1e, Word),
+        reg("RTSTA", 0x20, Long),
+        reg("DEVCAP2", 0x24, Long),
+        reg("DEVCTL2", 0x28, Word),
+        reg("DEVSTA2", 0x2a, Word),
+        reg("LNKCAP2", 0x2c, Long),
+        reg("LNKCTL2", 0x30, Word),
+        reg("LNKSTA2", 0x32, Word),
+        reg("SLTCAP2", 0x34, Long),
+        reg("SLTCTL2", 0x38, Word),
+        reg("SLTSTA2", 0x3a, Word),
+    ]
+};
+
+/// PCI Express capability ID
+const CAP_EXP: u8 = 0x10;
+
+/// Capability names known to `setpci` and their IDs
+pub const CAPABILITIES: &[(&str, u8)] = &[
+    ("CAP_PM", 0x01),
+    ("CAP_AGP", 0x02),
+    ("CAP_VPD", 0x03),
+    ("CAP_SLOTID", 0x04),
+    ("CAP_MSI", 0x05),
+    ("CAP_CHSWP", 0x06),
+    ("CAP_PCIX", 0x07),
+    ("CAP_HT", 0x08),
+    ("CAP_VNDR", 0x09),
+    ("CAP_DBG", 0x0a),
+    ("CAP_CCRC", 0x0b),
+    ("CAP_HOTPLUG", 0x0c),
+    ("CAP_SSVID", 0x0d),
+    ("CAP_AGP3", 0x0e),
+    ("CAP_SECURE", 0x0f),
+    ("CAP_EXP", CAP_EXP),
+    ("CAP_MSIX", 0x11),
+    ("CAP_SATA", 0x12),
+    ("CAP_AF", 0x13),
+    ("CAP_EA", 0x14),
+];
+
+/// Extended capability names known to `setpci` and their IDs
+pub const EXTENDED_CAPABILITIES: &[(&str, u16)] = &[
+    ("ECAP_AER", 0x01),
+    ("ECAP_VC", 0x02),
+    ("ECAP_DSN", 0x03),
+    ("ECAP_PB", 0x04),
+    ("ECAP_RCLINK", 0x05),
+    ("ECAP_RCILINK", 0x06),
+    ("ECAP_RCEC", 0x07),
+    ("ECAP_MFVC", 0x08),
+    ("ECAP_VC2", 0x09),
+    ("ECAP_RBCB", 0x0a),
+    ("ECAP_VNDR", 0x0b),
+    ("ECAP_ACS", 0x0d),
+    ("ECAP_ARI", 0x0e),
+    ("ECAP_ATS", 0x0f),
+    ("ECAP_SRIOV", 0x10),
+    ("ECAP_MRIOV", 0x11),
+    ("ECAP_MCAST", 0x12),
+    ("ECAP_PRI", 0x13),
+    ("ECAP_REBAR", 0x15),
+    ("ECAP_DPA", 0x16),
+    ("ECAP_TPH", 0x17),
+    ("ECAP_LTR", 0x18),
+    ("ECAP_SECPCI", 0x19),
+    ("ECAP_PMUX", 0x1a),
+    ("ECAP_PASID", 0x1b),
+    ("ECAP_LNR", 0x1c),
+    ("ECAP_DPC", 0x1d),
+    ("ECAP_L1PM", 0x1e),
+    ("ECAP_PTM", 0x1f),
+    ("ECAP_M_PCIE", 0x20),
+    ("ECAP_FRS", 0x21),
+    ("ECAP_RTR", 0x22),
+    ("ECAP_DVSEC", 0x23),
+    ("ECAP_VF_REBAR", 0x24),
+    ("ECAP_DLNK", 0x25),
+    ("ECAP_16GT", 0x26),
+    ("ECAP_LMR", 0x27),
+    ("ECAP_HIER_ID", 0x28),
+    ("ECAP_NPEM", 0x29),
+    ("ECAP_32GT", 0x2a),
+    ("ECAP_DOE", 0x2e),
+    ("ECAP_IDE", 0x30),
+];
+
+/// Header register by name
+pub fn register(name: &str) -> Option<&'static Register> {
+    REGISTERS
+        .iter()
+        .find(|reg| reg.name.eq_ignore_ascii_case(name))
+}
+
+/// PCI Express capability register by name
+pub fn express_register(name: &str) -> Option<&'static Register> {
+    EXPRESS_REGISTERS
+        .iter()
+        .find(|reg| reg.name.eq_ignore_ascii_case(name))
+}
+
+/// Capability ID by name
+pub fn capability_id(name: &str) -> Option<u8> {
+    CAPABILITIES
+        .iter()
+        .find_map(|(n, id)| n.eq_ignore_ascii_case(name).then_some(*id))
+}
+
+/// Extended capability ID by name
+pub fn extended_capability_id(name: &str) -> Option<u16> {
+    EXTENDED_CAPABILITIES
+        .iter()
+        .find_map(|(n, id)| n.eq_ignore_ascii_case(name).then_some(*id))
+}
+
+/// Register address base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    /// Start of configuration space
+    Absolute,
+    /// Capability header with given ID
+    Capability(u8),
+    /// Extended capability header with given ID
+    ExtendedCapability(u16),
+}
+
+/// Parsed register address, offset is relative to base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterAddress {
+    pub base: Base,
+    pub offset: u16,
+    pub width: Width,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseRegisterError {
+    #[error("unknown register or capability '{0}'")]
+    UnknownName(String),
+    #[error("invalid offset '{0}'")]
+    Offset(String),
+    #[error("invalid width '{0}', should be b, w or l")]
+    Width(String),
+    #[error("register width is missing, add .b, .w or .l")]
+    MissingWidth,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    #[error("device has no capability {0:#04x}")]
+    NoCapability(u8),
+    #[error("device has no extended capability {0:#06x}")]
+    NoExtendedCapability(u16),
+    #[error("register {offset:#x} is outside of configuration space")]
+    OutOfRange { offset: usize },
+    #[error("register {offset:#x} is not aligned to its width")]
+    Unaligned { offset: usize },
+}
+
+fn parse_offset(s: &str) -> Result<u16, ParseRegisterError> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| ParseRegisterError::Offset(s.to_string()))
+}
+
+impl FromStr for Width {
+    type Err = ParseRegisterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "b" | "B" => Ok(Self::Byte),
+            "w" | "W" => Ok(Self::Word),
+            "l" | "L" => Ok(Self::Long),
+            _ => Err(ParseRegisterError::Width(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for RegisterAddress {
+    type Err = ParseRegisterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, width) = match s.rsplit_once('.') {
+            Some((s, width)) => (s, Some(width.parse::<Width>()?)),
+            None => (s, None),
+        };
+        let (name, relative) = match s.split_once('+') {
+            Some((name, offset)) => (name, parse_offset(offset)?),
+            None => (s, 0),
+        };
+        let (base, offset, default_width) = if let Some(reg) = register(name) {
+            (Base::Absolute, reg.offset, Some(reg.width))
+        } else if let Some(reg) = express_register(name) {
+            (Base::Capability(CAP_EXP), reg.offset, Some(reg.width))
+        } else if let Some(id) = capability_id(name) {
+            (Base::Capability(id), 0, None)
+        } else if let Some(id) = extended_capability_id(name) {
+            (Base::ExtendedCapability(id), 0, None)
+        } else if let Ok(offset) = parse_offset(name) {
+            (Base::Absolute, offset, None)
+        } else {
+            return Err(ParseRegisterError::UnknownName(name.to_string()));
+        };
+        let offset = offset
+            .checked_add(relative)
+            .ok_or_else(|| ParseRegisterError::Offset(s.to_string()))?;
+        let width = width
+            .or(default_width)
+            .ok_or(ParseRegisterError::MissingWidth)?;
+        Ok(Self {
+            base,
+            offset,
+            width,
+        })
+    }
+}
+
+impl fmt::Display for Width {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.suffix())
+    }
+}
+
+/// Canonical `setpci` form: header register name if there is one
+impl fmt::Display for RegisterAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            base,
+            offset,
+            width,
+        } = *self;
+        match base {
+            Base::Absolute => {
+                return match REGISTERS
+                    .iter()
+                    .find(|reg| reg.offset == offset && reg.width == width)
+                {
+                    Some(reg) => write!(f, "{}", reg.name),
+                    None => write!(f, "{:02x}.{}", offset, width),
+                };
+            }
+            Base::Capability(id) => match CAPABILITIES.iter().find(|(_, cap_id)| *cap_id == id) {
+                Some((name, _)) => write!(f, "{}", name)?,
+                None => write!(f, "CAP_{:02x}", id)?,
+            },
+            Base::ExtendedCapability(id) => {
+                match EXTENDED_CAPABILITIES
+                    .iter()
+                    .find(|(_, cap_id)| *cap_id == id)
+                {
+                    Some((name, _)) => write!(f, "{}", name)?,
+                    None => write!(f, "ECAP_{:04x}", id)?,
+                }
+            }
+        }
+        if offset > 0 {
+            write!(f, "+{:x}", offset)?;
+        }
+        write!(f, ".{}", width)
+    }
+}
+
+impl Device {
+    /// Capability header offset in configuration space
+    pub fn capability_offset(&self, id: u8) -> Option<usize> {
+        let ddr = self.device_dependent_region.as_ref()?;
+        self.capabilities()?.flatten().find_map(|cap| {
+            let pointer = cap.pointer as usize;
+            let cap_id = ddr.get(pointer.checked_sub(DeviceDependentRegion::OFFSET)?)?;
+            (*cap_id == id).then_some(pointer)
+        })
+    }
+    /// Extended capability header offset in configuration space
+    pub fn extended_capability_offset(&self, id: u16) -> Option<usize> {
+        self.extended_capabilities()?
+            .flatten()
+            .find_map(|ecap| (ecap.id() == id).then_some(ecap.offset as usize))
+    }
+    /// Register offset in configuration space resolved through capabilities lists
+    pub fn register_offset(&self, reg: &RegisterAddress) -> Result<usize, RegisterError> {
+        let base = match reg.base {
+            Base::Absolute => 0,
+            Base::Capability(id) => self
+                .capability_offset(id)
+                .ok_or(RegisterError::NoCapability(id))?,
+            Base::ExtendedCapability(id) => self
+                .extended_capability_offset(id)
+                .ok_or(RegisterError::NoExtendedCapability(id))?,
+        };
+        let offset = base + reg.offset as usize;
+        let size = reg.width.size();
+        let limit = if self.extended_configuration_space.is_some() {
+            super::ConfigurationSpace::SIZE
+        } else {
+            super::ECS_OFFSET
+        };
+        if offset + size > limit {
+            Err(RegisterError::OutOfRange { offset })
+        } else if !offset.is_multiple_of(size) {
+            Err(RegisterError::Unaligned { offset })
+        } else {
+            Ok(offset)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_register_address() {
+        let reg = |s: &str| s.parse::<RegisterAddress>();
+        assert_eq!(
+            Ok(RegisterAddress {
+                base: Base::Absolute,
+                offset: 0x1b,
+                width: Width::Byte
+            }),
+            reg("sec_latency_timer")
+        );
+        assert_eq!(
+            Ok(RegisterAddress {
+                base: Base::Absolute,
+                offset: 0x04,
+                width: Width::Byte
+            }),
+            reg("COMMAND.b")
+        );
+        assert_eq!(
+            Ok(RegisterAddress {
+                base: Base::Absolute,
+                offset: 0x16,
+                width: Width::Word
+            }),
+            reg("COMMAND+0x12")
+        );
+        assert_eq!(
+            Ok(RegisterAddress {
+                base: Base::Capability(0x10),
+                offset: 0x12,
+                width: Width::Word
+            }),
+            reg("CAP_EXP+12.w")
+        );
+        assert_eq!(
+            Ok(RegisterAddress {
+                base: Base::ExtendedCapability(0x01),
+                offset: 0x10,
+                width: Width::Long
+            }),
+            reg("ECAP_AER+0x10.l")
+        );
+        assert_eq!(reg("CAP_EXP+0x12.w"), reg("lnksta"));
+        assert_eq!(Err(ParseRegisterError::MissingWidth), reg("CAP_PM+4"));
+        assert_eq!(Err(ParseRegisterError::MissingWidth), reg("3c"));
+        assert_eq!(
+            Err(ParseRegisterError::UnknownName("CAP_XYZ".into())),
+            reg("CAP_XYZ+4.b")
+        );
+        assert_eq!(Err(ParseRegisterError::Width("q".into())), reg("COMMAND.q"));
+        let names = [
+            "BASE_ADDRESS_2",
+            "PRIMARY_BUS",
+            "CAP_PM+4.b",
+            "CAP_EXP.l",
+            "ecap_l1pm+8.l",
+            "48.w",
+        ]
+        .map(|s| reg(s).unwrap().to_string());
+        assert_eq!(
+            [
+                "BASE_ADDRESS_2",
+                "PRIMARY_BUS",
+                "CAP_PM+4.b",
+                "CAP_EXP.l",
+                "ECAP_L1PM+8.l",
+                "48.w"
+            ],
+            names
+        );
+    }
+
+    #[test]
+    fn resolve_offset() {
+        let dump = Dump::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let offset = |s: &str| device.register_offset(&s.parse().unwrap());
+        assert_eq!(Ok(0x04), offset("COMMAND"));
+        assert_eq!(Ok(0x84), offset("CAP_PM+4.w"));
+        assert_eq!(Ok(0x52), offset("CAP_EXP+0x12.w"));
+        assert_eq!(Ok(0x110), offset("ECAP_AER+0x10.l"));
+        assert_eq!(Ok(0x404), offset("ECAP_L1PM+4.l"));
+        assert_eq!(Err(RegisterError::NoCapability(0x09)), offset("CAP_VNDR.b"));
+        assert_eq!(
+            Err(RegisterError::NoExtendedCapability(0x10)),
+            offset("ECAP_SRIOV.w")
+        );
+        assert_eq!(
+            Err(RegisterError::Unaligned { offset: 0x85 }),
+            offset("CAP_PM+5.w")
+        );
+        assert_eq!(
+            Err(RegisterError::OutOfRange { offset: 0x1000 }),
+            offset("1000.b")
+        );
+    }
+}
@@ -0,0 +1,235 @@
+/*!
+# Function reset
+
+Configuration space sequences for resets without OS help:
+- Function Level Reset: _Initiate Function Level Reset_ bit of PCI Express Device Control,
+  device is not accessed for 100ms afterwards
+- Secondary Bus Reset: bridge _Secondary Bus Reset_ bit is held for 2ms (Trst is at least 1ms),
+  devices below are not accessed for 100ms after release
+- Power management reset: D3hot to D0 transition of device without _No_Soft_Reset_, 10ms
+  recovery time after each state change
+
+Configuration space of reset functions is not restored. [ResetStep] lists are built from device
+state, `Device::reset` (feature `std`) runs them through `Access`.
+
+```rust
+# use pcitool::device::{reset::{ResetKind, ResetStep}, ConfigurationSpace, Device};
+let mut cs = [0u8; 64];
+cs[0x0e] = 0x01; // PCI-to-PCI bridge
+let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+let bridge = Device::new(Default::default(), cs);
+let steps = bridge.reset_steps(ResetKind::SecondaryBus).unwrap();
+assert_eq!(ResetStep::Update { offset: 0x3e, mask: 0x40, value: 0x40 }, steps[0]);
+```
+*/
+
+use core::{fmt, str::FromStr, time::Duration};
+
+use alloc::{string::String, vec, vec::Vec};
+
+use pcics::header::HeaderType;
+use thiserror::Error;
+
+use super::Device;
+
+/// Reset method
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetKind {
+    /// Function Level Reset
+    Flr,
+    /// Secondary Bus Reset of bridge, resets every device below
+    SecondaryBus,
+    /// D3hot to D0 transition
+    Pm,
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ResetError {
+    #[error("device has no PCI Express capability")]
+    NoExpress,
+    #[error("device does not support Function Level Reset")]
+    FlrUnsupported,
+    #[error("device is not a PCI-to-PCI bridge")]
+    NotBridge,
+    #[error("device has no Power Management capability")]
+    NoPowerManagement,
+    #[error("device does not reset on D3hot to D0 transition (No_Soft_Reset is set)")]
+    NoSoftReset,
+    #[error("unknown reset kind '{0}', available: flr, bus, pm")]
+    Kind(String),
+}
+
+impl FromStr for ResetKind {
+    type Err = ResetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "flr" => Ok(Self::Flr),
+            "bus" | "secondary-bus" => Ok(Self::SecondaryBus),
+            "pm" => Ok(Self::Pm),
+            _ => Err(ResetError::Kind(s.into())),
+        }
+    }
+}
+
+impl fmt::Display for ResetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flr => write!(f, "flr"),
+            Self::SecondaryBus => write!(f, "bus"),
+            Self::Pm => write!(f, "pm"),
+        }
+    }
+}
+
+/// Single step of reset sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetStep {
+    /// Read 16-bit register, replace `mask` bits with `value` and write back
+    Update {
+        offset: usize,
+        mask: u16,
+        value: u16,
+    },
+    /// Do not access device
+    Delay(Duration),
+}
+
+/// Initiate Function Level Reset bit of Device Control
+const DEVCTL_BCR_FLR: u16 = 1 << 15;
+/// Function Level Reset Capability bit of Device Capabilities
+const DEVCAP_FLR: u32 = 1 << 28;
+/// Bridge Control register offset
+const BRIDGE_CONTROL: usize = 0x3e;
+const BRIDGE_CTL_BUS_RESET: u16 = 1 << 6;
+/// PME_Status is RW1C, it is written as 0 to be preserved
+const PMCSR_PME_STATUS: u16 = 1 << 15;
+const PMCSR_STATE: u16 = 0b11;
+const PMCSR_NO_SOFT_RESET: u16 = 1 << 3;
+
+impl Device {
+    /// Reset sequence for `kind`, checked against device capabilities
+    pub fn reset_steps(&self, kind: ResetKind) -> Result<Vec<ResetStep>, ResetError> {
+        let steps = match kind {
+            ResetKind::Flr => {
+                let cap = self.capability_offset(0x10).ok_or(ResetError::NoExpress)?;
+                let devcap = self.ddr_bytes(cap + 4).map(u32::from_le_bytes);
+                if devcap.unwrap_or_default() & DEVCAP_FLR == 0 {
+                    return Err(ResetError::FlrUnsupported);
+                }
+                vec![
+                    ResetStep::Update {
+                        offset: cap + 8,
+                        mask: DEVCTL_BCR_FLR,
+                        value: DEVCTL_BCR_FLR,
+                    },
+                    ResetStep::Delay(Duration::from_millis(100)),
+                ]
+            }
+            ResetKind::SecondaryBus => {
+                if !matches!(self.header.header_type, HeaderType::Bridge(_)) {
+                    return Err(ResetError::NotBridge);
+                }
+                vec![
+                    ResetStep::Update {
+                        offset: BRIDGE_CONTROL,
+                        mask: BRIDGE_CTL_BUS_RESET,
+                        value: BRIDGE_CTL_BUS_RESET,
+                    },
+                    ResetStep::Delay(Duration::from_millis(2)),
+                    ResetStep::Update {
+                        offset: BRIDGE_CONTROL,
+                        mask: BRIDGE_CTL_BUS_RESET,
+                        value: 0,
+                    },
+                    ResetStep::Delay(Duration::from_millis(100)),
+                ]
+            }
+            ResetKind::Pm => {
+                let cap = self
+                    .capability_offset(0x01)
+                    .ok_or(ResetError::NoPowerManagement)?;
+                let pmcsr = self
+                    .ddr_bytes(cap + 4)
+                    .map(|[lo, hi, ..]| u16::from_le_bytes([lo, hi]));
+                if pmcsr.unwrap_or_default() & PMCSR_NO_SOFT_RESET != 0 {
+                    return Err(ResetError::NoSoftReset);
+                }
+                let state = |value| ResetStep::Update {
+                    offset: cap + 4,
+                    mask: PMCSR_PME_STATUS | PMCSR_STATE,
+                    value,
+                };
+                let delay = ResetStep::Delay(Duration::from_millis(10));
+                vec![state(3), delay, state(0), delay]
+            }
+        };
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{Capability, DeviceBuilder};
+    use pretty_assertions::assert_eq;
+
+    /// Endpoint with Power Management at 0x40 and PCI Express at 0x48
+    fn device(no_soft_reset: bool, flr: bool) -> Device {
+        let pm = Capability::Raw {
+            id: 0x01,
+            data: vec![0x03, 0x00, (no_soft_reset as u8) << 3, 0x00, 0x00, 0x00],
+        };
+        let mut data = vec![0u8; 0x3a];
+        data[0x00..0x02].copy_from_slice(&[0x02, 0x00]);
+        data[0x02..0x06].copy_from_slice(&[0x00, 0x80, 0x00, (flr as u8) << 4]);
+        DeviceBuilder::new(0x8086, 0x1533)
+            .capability(pm)
+            .capability(Capability::Raw { id: 0x10, data })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn flr_and_pm() {
+        let result = device(true, true).reset_steps(ResetKind::Flr).unwrap();
+        let sample = vec![
+            ResetStep::Update {
+                offset: 0x50,
+                mask: 0x8000,
+                value: 0x8000,
+            },
+            ResetStep::Delay(Duration::from_millis(100)),
+        ];
+        assert_eq!(sample, result);
+        assert_eq!(
+            Err(ResetError::NoSoftReset),
+            device(true, true).reset_steps(ResetKind::Pm)
+        );
+        assert_eq!(
+            Err(ResetError::NotBridge),
+            device(true, true).reset_steps(ResetKind::SecondaryBus)
+        );
+
+        let result = device(false, false).reset_steps(ResetKind::Pm).unwrap();
+        assert_eq!(
+            ResetStep::Update {
+                offset: 0x44,
+                mask: 0x8003,
+                value: 3
+            },
+            result[0]
+        );
+        assert_eq!(4, result.len());
+        assert_eq!(
+            Err(ResetError::FlrUnsupported),
+            device(false, false).reset_steps(ResetKind::Flr)
+        );
+        let device = DeviceBuilder::new(0x8086, 0x1533).build().unwrap();
+        assert_eq!(
+            Err(ResetError::NoExpress),
+            device.reset_steps(ResetKind::Flr)
+        );
+        assert_eq!(Ok(ResetKind::SecondaryBus), "bus".parse());
+    }
+}
@@ -0,0 +1,178 @@
+/*!
+# BAR sizing
+
+Base Address Register size is found by writing all-ones to the register and reading it back:
+address bits below the size are hardwired to zero. Sizing disables decoding of the device, so
+it is meant for direct access backends, where OS does not report resources.
+
+```rust
+# use pcitool::device::sizing;
+// 16K 32-bit non-prefetchable memory at b4418000, expansion ROM 64K disabled
+let resource = sizing::resource(
+    &[0xb4418000, 0x00000000],
+    &[0xffffc000, 0x00000000],
+    Some((0x00000000, 0xffff0000)),
+);
+assert_eq!(0x4000, resource.entries[0].size());
+assert_eq!(0xb4418000, resource.entries[0].start);
+assert_eq!(0, resource.entries[1].size());
+assert_eq!(0x10000, resource.rom_entry.size());
+```
+*/
+
+use core::ops::Range;
+
+use pcics::header::HeaderType;
+
+use super::{Device, Resource, ResourceEntry};
+
+/// Command register offset
+pub const COMMAND: usize = 0x04;
+/// PCI_COMMAND_IO | PCI_COMMAND_MEMORY
+pub const COMMAND_DECODE: u16 = 0x03;
+/// Written to BAR registers
+pub const BAR_SIZING_VALUE: u32 = u32::MAX;
+/// Written to expansion ROM register, ROM decoding stays disabled
+pub const ROM_SIZING_VALUE: u32 = !ROM_ADDRESS_ENABLE;
+/// SR-IOV Control register offset in SR-IOV capability
+pub const SRIOV_CONTROL: usize = 0x08;
+/// VF Memory Space Enable bit of SR-IOV Control
+pub const SRIOV_CONTROL_VF_MSE: u16 = 0x08;
+/// VF BAR0 offset in SR-IOV capability
+pub const SRIOV_VF_BARS: usize = 0x24;
+
+const BAR_SPACE_IO: u32 = 0x01;
+const BAR_MEM_TYPE_MASK: u32 = 0x06;
+const BAR_MEM_TYPE_64: u32 = 0x04;
+const BAR_IO_MASK: u32 = !0x03;
+const BAR_MEM_MASK: u32 = !0x0f;
+/// x86 I/O space is 64K (IO_SPACE_LIMIT)
+const IO_SPACE_LIMIT: u32 = 0xffff;
+const ROM_ADDRESS_ENABLE: u32 = 0x01;
+const ROM_ADDRESS_MASK: u32 = !0x7ff;
+
+impl Device {
+    /// BAR registers offsets and expansion ROM register offset
+    pub fn sizing_registers(&self) -> (Range<usize>, Option<usize>) {
+        match self.header.header_type {
+            HeaderType::Normal(_) => (0x10..0x28, Some(0x30)),
+            HeaderType::Bridge(_) => (0x10..0x18, Some(0x38)),
+            HeaderType::Cardbus(_) => (0x10..0x14, None),
+            _ => (0..0, None),
+        }
+    }
+}
+
+/// Resource from BAR values before (`original`) and after writing all-ones (`sized`) and the
+/// same pair of expansion ROM register. Upper half of 64-bit BAR has empty entry, as in sysfs.
+pub fn resource(original: &[u32], sized: &[u32], rom: Option<(u32, u32)>) -> Resource {
+    let mut entries = [ResourceEntry::default(); 6];
+    let len = original.len().min(sized.len()).min(entries.len());
+    let mut n = 0;
+    while n < len {
+        let (index, value, mask) = (n, original[n], sized[n]);
+        n += 1;
+        if matches!(mask, 0 | u32::MAX) {
+            continue;
+        }
+        entries[index] = if value & BAR_SPACE_IO != 0 {
+            let size = lowest_bit((mask & BAR_IO_MASK & IO_SPACE_LIMIT) as u64);
+            entry((value & BAR_IO_MASK) as u64, size, value & !BAR_IO_MASK)
+        } else if value & BAR_MEM_TYPE_MASK == BAR_MEM_TYPE_64 && n < len {
+            let upper = (original[n] as u64, sized[n] as u64);
+            n += 1;
+            let size = lowest_bit(upper.1 << 32 | (mask & BAR_MEM_MASK) as u64);
+            let address = upper.0 << 32 | (value & BAR_MEM_MASK) as u64;
+            entry(address, size, value & !BAR_MEM_MASK)
+        } else {
+            let size = lowest_bit((mask & BAR_MEM_MASK) as u64);
+            entry((value & BAR_MEM_MASK) as u64, size, value & !BAR_MEM_MASK)
+        };
+    }
+    let rom_entry = rom
+        .filter(|&(_, mask)| mask != u32::MAX)
+        .map(|(value, mask)| {
+            let size = lowest_bit((mask & ROM_ADDRESS_MASK) as u64);
+            entry((value & ROM_ADDRESS_MASK) as u64, size, 0)
+        })
+        .unwrap_or_default();
+    Resource {
+        entries,
+        rom_entry,
+        iov_entries: Default::default(),
+    }
+}
+
+/// VF BAR entries from VF BAR registers pairs like [resource], each spans `total_vfs` VFs as
+/// Linux IOV resources do
+pub fn iov_entries(original: &[u32], sized: &[u32], total_vfs: u16) -> [ResourceEntry; 6] {
+    resource(original, sized, None).entries.map(|re| {
+        let size = re.size() * total_vfs as u64;
+        if size == 0 {
+            return ResourceEntry::default();
+        }
+        ResourceEntry {
+            end: re.start + size - 1,
+            ..re
+        }
+    })
+}
+
+fn lowest_bit(value: u64) -> u64 {
+    value & value.wrapping_neg()
+}
+
+/// Unassigned region has no flags, so lspci view does not take flags for an address
+fn entry(start: u64, size: u64, flags: u32) -> ResourceEntry {
+    if size == 0 {
+        return ResourceEntry::default();
+    }
+    ResourceEntry {
+        start,
+        end: start + size - 1,
+        flags: if start != 0 { flags as u64 } else { 0 },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn bars() {
+        // 256M 64-bit prefetchable memory at 6_0000_0000, 64 I/O ports at 3000, 1M unassigned
+        // 32-bit memory and not implemented BARs
+        let original = [
+            0x0000000c, 0x00000006, 0x00003001, 0x00000000, 0x00000000, 0x00000000,
+        ];
+        let sized = [
+            0xf000000c, 0xffffffff, 0xffffffc1, 0xfff00000, 0x00000000, 0x00000000,
+        ];
+        let result = resource(&original, &sized, None);
+        let sample = [
+            (0x6_0000_0000, 0x6_0fff_ffff, 0x0c),
+            (0, 0, 0),
+            (0x3000, 0x303f, 0x01),
+            (0, 0xfffff, 0),
+            (0, 0, 0),
+            (0, 0, 0),
+        ]
+        .map(|(start, end, flags)| ResourceEntry { start, end, flags });
+        assert_eq!(sample, result.entries);
+        assert_eq!(ResourceEntry::default(), result.rom_entry);
+    }
+
+    #[test]
+    fn vf_bars() {
+        // 16K 64-bit non-prefetchable memory per VF at 8_0000_0000, 8 VFs
+        let original = [0x00000004, 0x00000008, 0, 0, 0, 0];
+        let sized = [0xffffc004, 0xffffffff, 0, 0, 0, 0];
+        let result = iov_entries(&original, &sized, 8);
+        assert_eq!(0x8_0000_0000, result[0].start);
+        assert_eq!(8 * 0x4000, result[0].size());
+        assert_eq!([ResourceEntry::default(); 5], result[1..]);
+        let no_vfs = iov_entries(&original, &sized, 0);
+        assert_eq!([ResourceEntry::default(); 6], no_vfs);
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+# Hot-plug slot control
+
+PCI Express Slot Control register of a downstream port: power controller and attention/power
+indicators. Changes are checked against Slot Capabilities, other Slot Control bits (hot-plug
+interrupt enables) are kept.
+
+```rust
+# use pcitool::device::slot::{Indicator, SlotSetting};
+let setting: SlotSetting = "attention=blink".parse().unwrap();
+assert_eq!(SlotSetting::Attention(Indicator::Blink), setting);
+```
+*/
+
+use core::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use super::Device;
+
+/// Slot Capabilities register offset relative to PCI Express capability
+const SLTCAP: usize = 0x14;
+/// Slot Control register offset relative to PCI Express capability
+const SLTCTL: usize = 0x18;
+/// Slot Implemented bit of PCI Express Capabilities register
+const SLOT_IMPLEMENTED: u32 = 1 << 24;
+const SLTCAP_POWER_CONTROLLER: u32 = 1 << 1;
+const SLTCAP_ATTENTION_INDICATOR: u32 = 1 << 3;
+const SLTCAP_POWER_INDICATOR: u32 = 1 << 4;
+const SLTCTL_ATTENTION_INDICATOR_SHIFT: u16 = 6;
+const SLTCTL_POWER_INDICATOR_SHIFT: u16 = 8;
+/// Power Controller Control: 0 is on, 1 is off
+const SLTCTL_POWER_OFF: u16 = 1 << 10;
+/// Presence Detect State bit of Slot Status
+const SLTSTA_PRESENCE: u16 = 1 << 6;
+
+/// Attention or power indicator state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indicator {
+    On,
+    Blink,
+    Off,
+}
+
+impl Indicator {
+    fn bits(self) -> u16 {
+        match self {
+            Self::On => 0b01,
+            Self::Blink => 0b10,
+            Self::Off => 0b11,
+        }
+    }
+    fn from_bits(bits: u16) -> Option<Self> {
+        match bits & 0b11 {
+            0b01 => Some(Self::On),
+            0b10 => Some(Self::Blink),
+            0b11 => Some(Self::Off),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Indicator {
+    type Err = SlotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => Ok(Self::On),
+            "blink" => Ok(Self::Blink),
+            "off" => Ok(Self::Off),
+            _ => Err(SlotError::Parse),
+        }
+    }
+}
+
+/// Single Slot Control change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotSetting {
+    /// Slot power on (`true`) or off
+    Power(bool),
+    Attention(Indicator),
+    PowerIndicator(Indicator),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SlotError {
+    #[error(
+        "setting should be power=on|off, attention=on|blink|off or power-indicator=on|blink|off"
+    )]
+    Parse,
+    #[error("device has no PCI Express slot")]
+    NoSlot,
+    #[error("slot has no power controller")]
+    NoPowerController,
+    #[error("slot has no attention indicator")]
+    NoAttentionIndicator,
+    #[error("slot has no power indicator")]
+    NoPowerIndicator,
+}
+
+/// `power=on|off`, `attention=on|blink|off` or `power-indicator=on|blink|off`
+impl FromStr for SlotSetting {
+    type Err = SlotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once('=').ok_or(SlotError::Parse)? {
+            ("power", "on") => Ok(Self::Power(true)),
+            ("power", "off") => Ok(Self::Power(false)),
+            ("attention", value) => value.parse().map(Self::Attention),
+            ("power-indicator", value) => value.parse().map(Self::PowerIndicator),
+            _ => Err(SlotError::Parse),
+        }
+    }
+}
+
+/// Slot state from Slot Control and Slot Status, `None` if not implemented by slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotState {
+    pub power: Option<bool>,
+    pub attention: Option<Indicator>,
+    pub power_indicator: Option<Indicator>,
+    /// Adapter is present in slot
+    pub presence: bool,
+    /// Slot Control register offset in configuration space
+    pub control_offset: usize,
+}
+
+impl Device {
+    /// Hot-plug slot state, `None` if device has no slot
+    pub fn slot_state(&self) -> Option<SlotState> {
+        let cap = self.capability_offset(0x10)?;
+        let header = u32::from_le_bytes(self.ddr_bytes(cap)?);
+        if header & SLOT_IMPLEMENTED == 0 {
+            return None;
+        }
+        let sltcap = u32::from_le_bytes(self.ddr_bytes(cap + SLTCAP)?);
+        let [ctl_lo, ctl_hi, sta_lo, sta_hi] = self.ddr_bytes(cap + SLTCTL)?;
+        let control = u16::from_le_bytes([ctl_lo, ctl_hi]);
+        let status = u16::from_le_bytes([sta_lo, sta_hi]);
+        Some(SlotState {
+            power: (sltcap & SLTCAP_POWER_CONTROLLER != 0)
+                .then_some(control & SLTCTL_POWER_OFF == 0),
+            attention: (sltcap & SLTCAP_ATTENTION_INDICATOR != 0)
+                .then(|| Indicator::from_bits(control >> SLTCTL_ATTENTION_INDICATOR_SHIFT))
+                .flatten(),
+            power_indicator: (sltcap & SLTCAP_POWER_INDICATOR != 0)
+                .then(|| Indicator::from_bits(control >> SLTCTL_POWER_INDICATOR_SHIFT))
+                .flatten(),
+            presence: status & SLTSTA_PRESENCE != 0,
+            control_offset: cap + SLTCTL,
+        })
+    }
+    /// Offset and new value of Slot Control register with `settings` applied
+    pub fn slot_control(&self, settings: &[SlotSetting]) -> Result<(usize, u16), SlotError> {
+        let cap = self.capability_offset(0x10).ok_or(SlotError::NoSlot)?;
+        let header = self.ddr_bytes(cap).map(u32::from_le_bytes);
+        if header.unwrap_or_default() & SLOT_IMPLEMENTED == 0 {
+            return Err(SlotError::NoSlot);
+        }
+        let sltcap = self.ddr_bytes(cap + SLTCAP).map(u32::from_le_bytes);
+        let sltcap = sltcap.ok_or(SlotError::NoSlot)?;
+        let [lo, hi, ..] = self.ddr_bytes(cap + SLTCTL).ok_or(SlotError::NoSlot)?;
+        let mut control = u16::from_le_bytes([lo, hi]);
+        for setting in settings {
+            match *setting {
+                SlotSetting::Power(on) => {
+                    if sltcap & SLTCAP_POWER_CONTROLLER == 0 {
+                        return Err(SlotError::NoPowerController);
+                    }
+                    control &= !SLTCTL_POWER_OFF;
+                    if !on {
+                        control |= SLTCTL_POWER_OFF;
+                    }
+                }
+                SlotSetting::Attention(indicator) => {
+                    if sltcap & SLTCAP_ATTENTION_INDICATOR == 0 {
+                        return Err(SlotError::NoAttentionIndicator);
+                    }
+                    control &= !(0b11 << SLTCTL_ATTENTION_INDICATOR_SHIFT);
+                    control |= indicator.bits() << SLTCTL_ATTENTION_INDICATOR_SHIFT;
+                }
+                SlotSetting::PowerIndicator(indicator) => {
+                    if sltcap & SLTCAP_POWER_INDICATOR == 0 {
+                        return Err(SlotError::NoPowerIndicator);
+                    }
+                    control &= !(0b11 << SLTCTL_POWER_INDICATOR_SHIFT);
+                    control |= indicator.bits() << SLTCTL_POWER_INDICATOR_SHIFT;
+                }
+            }
+        }
+        Ok((cap + SLTCTL, control))
+    }
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::On => write!(f, "on"),
+            Self::Blink => write!(f, "blink"),
+            Self::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// `Power on, Attention off, Power indicator on, Presence+`, missing controls are skipped
+impl fmt::Display for SlotState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(power) = self.power {
+            write!(f, "Power {}, ", if power { "on" } else { "off" })?;
+        }
+        if let Some(indicator) = self.attention {
+            write!(f, "Attention {}, ", indicator)?;
+        }
+        if let Some(indicator) = self.power_indicator {
+            write!(f, "Power indicator {}, ", indicator)?;
+        }
+        write!(f, "Presence{}", if self.presence { '+' } else { '-' })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::builder::{Capability, DeviceBuilder};
+    use alloc::{string::ToString, vec};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn control() {
+        // PCI Express capability at 0x40
+        let mut data = vec![0u8; 0x3a];
+        // Downstream port with slot, power controller and power indicator
+        data[0x00..0x02].copy_from_slice(&[0x62, 0x01]);
+        data[0x12..0x16].copy_from_slice(&[0x12, 0x00, 0x00, 0x00]);
+        // Slot Control: hot-plug interrupts enabled, power indicator on; Presence Detect
+        data[0x16..0x1a].copy_from_slice(&[0x28, 0x01, 0x40, 0x00]);
+        let device = DeviceBuilder::new(0x8086, 0xa190)
+            .bridge(0, 1, 1)
+            .capability(Capability::Raw { id: 0x10, data })
+            .build()
+            .unwrap();
+
+        let state = device.slot_state().unwrap();
+        assert_eq!("Power on, Power indicator on, Presence+", state.to_string());
+        let settings = ["power=off", "power-indicator=off"].map(|s| s.parse().unwrap());
+        assert_eq!(Ok((0x58, 0x0728)), device.slot_control(&settings));
+        assert_eq!(
+            Err(SlotError::NoAttentionIndicator),
+            device.slot_control(&[SlotSetting::Attention(Indicator::On)])
+        );
+        assert_eq!(Err(SlotError::Parse), "power=blink".parse::<SlotSetting>());
+    }
+}
@@ -0,0 +1,280 @@
+/*!
+# SR-IOV Virtual Functions
+
+Virtual Function `n` (counting from 0) takes Routing ID of its Physical Function plus First VF
+Offset plus `n` VF Strides, VFs may spill over to the following buses. Offset and stride depend
+on NumVFs and ARI Capable Hierarchy, so once NumVFs is written VF addresses are known before
+VFs are enabled. VF BAR registers hold the region of the first VF, the next VFs follow it at VF
+BAR size steps. The size comes from OS (Linux IOV resources span TotalVFs VFs) or from
+[sizing](Device::size_bars).
+
+```rust
+# use pcitool::device::builder::{DeviceBuilder, ExtendedCapability};
+let mut data = vec![0u8; 0x3c];
+data[0x0a..0x0c].copy_from_slice(&8u16.to_le_bytes()); // TotalVFs
+data[0x0c..0x0e].copy_from_slice(&4u16.to_le_bytes()); // NumVFs
+data[0x10..0x14].copy_from_slice(&[0x80, 0x00, 0x02, 0x00]); // Offset 128, stride 2
+let device = DeviceBuilder::new(0x8086, 0x1572)
+    .address("01:00.0".parse().unwrap())
+    .extended_capability(ExtendedCapability::Raw { id: 0x0010, version: 1, data })
+    .build()
+    .unwrap();
+let vfs = device.vf_layout().unwrap();
+let addresses = vfs.addresses().map(|a| format!("{:#}", a)).collect::<Vec<_>>();
+assert_eq!(vec!["01:10.0", "01:10.2", "01:10.4", "01:10.6"], addresses);
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::extended_capabilities::{
+    single_root_io_virtualization::SingleRootIoVirtualization, ExtendedCapabilityKind,
+};
+
+use super::{Address, Device, Size};
+
+const BAR_SPACE_IO: u32 = 0x01;
+const BAR_MEM_TYPE_MASK: u32 = 0x06;
+const BAR_MEM_TYPE_64: u32 = 0x04;
+const BAR_MEM_PREFETCH: u32 = 0x08;
+const BAR_MEM_MASK: u32 = !0x0f;
+
+/// VF Routing IDs and VF BARs of a Physical Function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VfLayout {
+    /// Physical Function
+    pub pf: Address,
+    pub first_vf_offset: u16,
+    pub vf_stride: u16,
+    /// NumVFs, TotalVFs while NumVFs is not set
+    pub count: u16,
+    /// NumVFs is not set, offset and stride may change once it is
+    pub predicted: bool,
+    pub bars: Vec<VfBar>,
+}
+
+/// VF BAR, the first VF region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VfBar {
+    /// VF BAR register number
+    pub index: usize,
+    pub base: u64,
+    pub is_64bit: bool,
+    pub prefetchable: bool,
+    /// Region size of every VF, `None` if neither OS reported it nor BARs were sized
+    pub size: Option<u64>,
+}
+
+impl Device {
+    /// SR-IOV extended capability with its configuration space offset
+    pub fn sr_iov_offset(&self) -> Option<(usize, SingleRootIoVirtualization)> {
+        self.extended_capabilities()?
+            .flatten()
+            .find_map(|ecap| match ecap.kind {
+                ExtendedCapabilityKind::SingleRootIoVirtualization(sr_iov) => {
+                    Some((ecap.offset as usize, sr_iov))
+                }
+                _ => None,
+            })
+    }
+    /// VF addresses and VF BARs, sizes are taken from [Resource](super::Resource) IOV entries
+    pub fn vf_layout(&self) -> Option<VfLayout> {
+        let sr_iov = self.sr_iov()?;
+        let total_vfs = sr_iov.total_vfs as u64;
+        let iov_entries = self.resource.as_ref().map(|resource| resource.iov_entries);
+        let per_vf = |index: usize| {
+            let size = iov_entries?[index].size();
+            (size != 0 && total_vfs != 0).then(|| size / total_vfs)
+        };
+        let raw = sr_iov.base_addresses.orig();
+        let mut bars = Vec::new();
+        let mut index = 0;
+        while index < raw.len() {
+            let (value, size) = (raw[index], per_vf(index));
+            let is_64bit = value & BAR_MEM_TYPE_MASK == BAR_MEM_TYPE_64;
+            let upper = is_64bit.then(|| raw.get(index + 1).copied()).flatten();
+            let implemented = value != 0 || size.is_some();
+            if implemented && value & BAR_SPACE_IO == 0 {
+                let upper = upper.unwrap_or_default() as u64;
+                bars.push(VfBar {
+                    index,
+                    base: upper << 32 | (value & BAR_MEM_MASK) as u64,
+                    is_64bit,
+                    prefetchable: value & BAR_MEM_PREFETCH != 0,
+                    size,
+                });
+            }
+            index += if is_64bit { 2 } else { 1 };
+        }
+        Some(VfLayout {
+            pf: self.address.clone(),
+            first_vf_offset: sr_iov.first_vf_offset,
+            vf_stride: sr_iov.vf_stride,
+            count: if sr_iov.num_vfs != 0 {
+                sr_iov.num_vfs
+            } else {
+                sr_iov.total_vfs
+            },
+            predicted: sr_iov.num_vfs == 0,
+            bars,
+        })
+    }
+}
+
+impl VfLayout {
+    /// Address of VF `n`, `None` past the last VF or bus ff
+    pub fn address(&self, n: u16) -> Option<Address> {
+        if n >= self.count {
+            return None;
+        }
+        let rid = self.pf.routing_id() as u32
+            + self.first_vf_offset as u32
+            + n as u32 * self.vf_stride as u32;
+        let rid = u16::try_from(rid).ok()?;
+        let bus = (rid >> 8) as u8;
+        Some(Address::from_devfn(self.pf.domain, bus, rid as u8))
+    }
+    /// Addresses of all VFs up to bus ff
+    pub fn addresses(&self) -> impl Iterator<Item = Address> + '_ {
+        (0..self.count).map_while(|n| self.address(n))
+    }
+}
+
+impl VfBar {
+    /// Region start of VF `n`, `None` if size is unknown or the region is past 64-bit space
+    pub fn address(&self, n: u16) -> Option<u64> {
+        self.size?
+            .checked_mul(n as u64)
+            .and_then(|offset| self.base.checked_add(offset))
+    }
+}
+
+/// VF addresses range and VF BARs lines
+impl fmt::Display for VfLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (first, last) = (self.address(0), self.addresses().last());
+        match (first, last) {
+            (Some(first), Some(last)) => write!(
+                f,
+                "\t\tVFs: {} - {} ({} VFs, stride {})",
+                first, last, self.count, self.vf_stride
+            )?,
+            _ => write!(f, "\t\tVFs: none")?,
+        }
+        if self.predicted && self.count != 0 {
+            write!(f, ", NumVFs not set")?;
+        }
+        writeln!(f)?;
+        for bar in &self.bars {
+            write!(
+                f,
+                "\t\tVF Region {}: Memory at {:08x} ({}-bit, {}prefetchable)",
+                bar.index,
+                bar.base,
+                if bar.is_64bit { 64 } else { 32 },
+                if bar.prefetchable { "" } else { "non-" },
+            )?;
+            match bar.size {
+                Some(size) => writeln!(
+                    f,
+                    " [size={} per VF, {} for {} VFs]",
+                    Size(size),
+                    Size(size.saturating_mul(self.count as u64)),
+                    self.count
+                )?,
+                None => writeln!(f, " [size=unknown]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{
+        builder::{DeviceBuilder, ExtendedCapability},
+        Resource, ResourceEntry,
+    };
+    use alloc::{string::ToString, vec};
+    use pretty_assertions::assert_eq;
+
+    /// PF at `address` with SR-IOV capability: TotalVFs, NumVFs, offset, stride and VF BARs
+    fn device(address: &str, counts: (u16, u16), routing: (u16, u16), bars: [u32; 6]) -> Device {
+        let mut data = vec![0u8; 0x3c];
+        data[0x0a..0x0c].copy_from_slice(&counts.0.to_le_bytes());
+        data[0x0c..0x0e].copy_from_slice(&counts.1.to_le_bytes());
+        data[0x10..0x12].copy_from_slice(&routing.0.to_le_bytes());
+        data[0x12..0x14].copy_from_slice(&routing.1.to_le_bytes());
+        for (n, bar) in bars.iter().enumerate() {
+            let offset = 0x20 + n * 4;
+            data[offset..offset + 4].copy_from_slice(&bar.to_le_bytes());
+        }
+        DeviceBuilder::new(0x8086, 0x1572)
+            .address(address.parse().unwrap())
+            .extended_capability(ExtendedCapability::Raw {
+                id: 0x0010,
+                version: 1,
+                data,
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn vf_layout() {
+        // 64-bit prefetchable VF BAR 0 and 32-bit VF BAR 3 at 0, 8 of 64 VFs
+        let bars = [0x0000000c, 0x00000060, 0, 0, 0, 0];
+        let mut pf = device("3b:00.0", (64, 8), (0x80, 1), bars);
+        let vfs = pf.vf_layout().unwrap();
+        assert_eq!(Some("3b:10.7".parse().unwrap()), vfs.address(7));
+        assert_eq!(None, vfs.address(8));
+        assert_eq!(None, vfs.bars[0].address(1));
+        let sample = "\
+            \t\tVFs: 0000:3b:10.0 - 0000:3b:10.7 (8 VFs, stride 1)\n\
+            \t\tVF Region 0: Memory at 6000000000 (64-bit, prefetchable) [size=unknown]\n\
+        ";
+        assert_eq!(sample, vfs.to_string());
+
+        // Linux IOV resources: 64 * 16K and 64 * 4K
+        let mut iov_entries = [ResourceEntry::default(); 6];
+        iov_entries[0] = ResourceEntry {
+            start: 0x60_0000_0000,
+            end: 0x60_000f_ffff,
+            flags: 0x14220c,
+        };
+        iov_entries[3] = ResourceEntry {
+            start: 0xb000_0000,
+            end: 0xb003_ffff,
+            flags: 0x40200,
+        };
+        pf.resource = Some(Resource {
+            iov_entries,
+            ..Default::default()
+        });
+        let vfs = pf.vf_layout().unwrap();
+        assert_eq!(Some(0x60_0000_4000), vfs.bars[0].address(1));
+        let bar = VfBar {
+            base: u64::MAX - 0xfff,
+            size: Some(0x1000),
+            ..vfs.bars[0]
+        };
+        assert_eq!(Some(u64::MAX - 0xfff), bar.address(0));
+        assert_eq!(None, bar.address(1));
+        let sample = "\
+            \t\tVFs: 0000:3b:10.0 - 0000:3b:10.7 (8 VFs, stride 1)\n\
+            \t\tVF Region 0: Memory at 6000000000 (64-bit, prefetchable) [size=16K per VF, 128K for 8 VFs]\n\
+            \t\tVF Region 3: Memory at 00000000 (32-bit, non-prefetchable) [size=4K per VF, 32K for 8 VFs]\n\
+        ";
+        assert_eq!(sample, vfs.to_string());
+
+        // NumVFs not set yet, VFs past bus ff are dropped
+        let pf = device("fe:00.0", (512, 0), (0x100, 1), [0; 6]);
+        let vfs = pf.vf_layout().unwrap();
+        assert_eq!(256, vfs.addresses().count());
+        let sample = "\t\tVFs: 0000:ff:00.0 - 0000:ff:1f.7 (512 VFs, stride 1), NumVFs not set\n";
+        assert_eq!(sample, vfs.to_string());
+    }
+}
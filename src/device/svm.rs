@@ -0,0 +1,367 @@
+/*!
+# Shared Virtual Memory readiness
+
+Shared Virtual Memory (SVM, SVA in Linux) lets a device use process page tables through the
+IOMMU. It needs PASID to tag requests with an address space, ATS to translate addresses and PRI
+to fault pages in, all on the same function, and a device behind an IOMMU. PASID is carried in
+an End-End TLP Prefix, so every function on the path from Root Port down to the device must
+support End-End TLP Prefixes. Root Complex integrated endpoints do not send requests over a
+link and have no such requirement.
+
+```rust
+# use pcitool::device::builder::{DeviceBuilder, ExtendedCapability};
+// PASID extended capability: Max PASID Width 20, enabled
+let data = vec![0x00, 0x14, 0x01, 0x00];
+let pasid = ExtendedCapability::Raw { id: 0x001b, version: 1, data };
+let device = DeviceBuilder::new(0x8086, 0x0b25).extended_capability(pasid).build().unwrap();
+let pasid = device.pasid().unwrap();
+assert!(pasid.enabled);
+assert_eq!(1 << 20, pasid.max_pasids());
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::{
+    capabilities::{pci_express::DeviceType, CapabilityKind},
+    extended_capabilities::ExtendedCapabilityKind,
+};
+
+use super::{Address, Device};
+use crate::topology::Topology;
+
+/// PASID Capability and Control registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasidInfo {
+    pub execute: bool,
+    pub privileged: bool,
+    pub max_width: u8,
+    pub enabled: bool,
+}
+
+/// ATS Capability and Control registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtsInfo {
+    pub invalidate_queue_depth: u8,
+    pub page_aligned_request: bool,
+    pub global_invalidate: bool,
+    pub enabled: bool,
+    pub smallest_translation_unit: u8,
+}
+
+/// Page Request Interface registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriInfo {
+    pub capacity: u32,
+    pub allocation: u32,
+    /// PRG Response PASID Required
+    pub pasid_required: bool,
+    pub enabled: bool,
+    pub stopped: bool,
+}
+
+/// SVM capabilities of a device and End-End TLP Prefix support on its path
+#[derive(Debug, Clone)]
+pub struct SvmCheck<'a> {
+    pub device: &'a Device,
+    pub pasid: Option<PasidInfo>,
+    pub ats: Option<AtsInfo>,
+    pub pri: Option<PriInfo>,
+    /// Functions from the topmost port down to the device with End-End TLP Prefix Supported,
+    /// empty for Root Complex integrated endpoint
+    pub prefix_path: Vec<(&'a Device, bool)>,
+}
+
+/// Reason SVM can not work
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvmProblem {
+    NoPasid,
+    NoAts,
+    NoPri,
+    /// OS did not put the device into an IOMMU group
+    NoIommu,
+    /// Function on the path does not support End-End TLP Prefix
+    NoTlpPrefix(Address),
+}
+
+impl Device {
+    /// Process Address Space ID capability and control
+    pub fn pasid(&self) -> Option<PasidInfo> {
+        self.extended_capabilities()?.flatten().find_map(|ecap| {
+            let ExtendedCapabilityKind::ProcessAddressSpaceId(pasid) = ecap.kind else {
+                return None;
+            };
+            let (cap, ctl) = (pasid.pacid_capability, pasid.pacid_control);
+            Some(PasidInfo {
+                execute: cap.execute_permission_supported,
+                privileged: cap.privileged_mode_supported,
+                max_width: cap.max_pasid_width,
+                enabled: ctl.pasid_enable,
+            })
+        })
+    }
+    /// Address Translation Services capability and control
+    pub fn ats(&self) -> Option<AtsInfo> {
+        self.extended_capabilities()?.flatten().find_map(|ecap| {
+            let ExtendedCapabilityKind::AddressTranslationServices(ats) = ecap.kind else {
+                return None;
+            };
+            let (cap, ctl) = (ats.ats_capability, ats.ats_control);
+            Some(AtsInfo {
+                invalidate_queue_depth: cap.invalidate_queue_depth,
+                page_aligned_request: cap.page_aligned_request,
+                global_invalidate: cap.global_invalidate_supported,
+                enabled: ctl.enable,
+                smallest_translation_unit: ctl.smallest_translation_unit,
+            })
+        })
+    }
+    /// Page Request Interface control, status and page request credits
+    pub fn pri(&self) -> Option<PriInfo> {
+        self.extended_capabilities()?.flatten().find_map(|ecap| {
+            let ExtendedCapabilityKind::PageRequestInterface(pri) = ecap.kind else {
+                return None;
+            };
+            Some(PriInfo {
+                capacity: pri.outstanding_page_request_capacity,
+                allocation: pri.outstanding_page_request_allocation,
+                pasid_required: pri.page_request_status.prg_response_pasid_required,
+                enabled: pri.page_request_control.enable,
+                stopped: pri.page_request_status.stopped,
+            })
+        })
+    }
+    /// End-End TLP Prefix Supported, `None` for Root Complex integrated functions
+    fn end_end_tlp_prefix(&self) -> Option<bool> {
+        let pcie = self.capabilities().and_then(|mut caps| {
+            caps.find_map(|cap| match cap.ok()?.kind {
+                CapabilityKind::PciExpress(pcie) => Some(pcie),
+                _ => None,
+            })
+        });
+        match pcie {
+            Some(pcie) => match pcie.device_type {
+                DeviceType::RootComplexIntegratedEndpoint
+                | DeviceType::RootComplexEventCollector { .. } => None,
+                _ => {
+                    let dev2 = pcie.device_2;
+                    Some(dev2.is_some_and(|dev2| dev2.capabilities.end_end_tlp_prefix_supported))
+                }
+            },
+            // Conventional PCI does not carry TLP Prefixes
+            None => Some(false),
+        }
+    }
+}
+
+impl PasidInfo {
+    pub fn max_pasids(&self) -> u32 {
+        1u32.checked_shl(self.max_width as u32).unwrap_or(u32::MAX)
+    }
+}
+
+impl<'a> SvmCheck<'a> {
+    pub fn new(topology: &Topology<'a>, device: &'a Device) -> Self {
+        let prefix_path = match device.end_end_tlp_prefix() {
+            Some(_) => topology
+                .path(device)
+                .into_iter()
+                .filter_map(|device| Some((device, device.end_end_tlp_prefix()?)))
+                .collect(),
+            None => Vec::new(),
+        };
+        Self {
+            device,
+            pasid: device.pasid(),
+            ats: device.ats(),
+            pri: device.pri(),
+            prefix_path,
+        }
+    }
+    /// Every problem, from the device capabilities to the path
+    pub fn problems(&self) -> Vec<SvmProblem> {
+        let mut problems = Vec::new();
+        if self.pasid.is_none() {
+            problems.push(SvmProblem::NoPasid);
+        }
+        if self.ats.is_none() {
+            problems.push(SvmProblem::NoAts);
+        }
+        if self.pri.is_none() {
+            problems.push(SvmProblem::NoPri);
+        }
+        if self.device.iommu_group.is_none() {
+            problems.push(SvmProblem::NoIommu);
+        }
+        problems.extend(
+            self.prefix_path
+                .iter()
+                .filter(|(_, supported)| !supported)
+                .map(|(device, _)| SvmProblem::NoTlpPrefix(device.address.clone())),
+        );
+        problems
+    }
+    pub fn is_ready(&self) -> bool {
+        self.problems().is_empty()
+    }
+}
+
+/// lspci-like flags
+impl fmt::Display for PasidInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |b: bool| if b { '+' } else { '-' };
+        write!(
+            f,
+            "Exec{} Priv{} Max PASID Width {}, Enabled{}",
+            flag(self.execute),
+            flag(self.privileged),
+            self.max_width,
+            flag(self.enabled),
+        )
+    }
+}
+
+impl fmt::Display for AtsInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |b: bool| if b { '+' } else { '-' };
+        write!(
+            f,
+            "Invalidate Queue Depth {} PageAligned{} GlobalInvalidate{}, Enabled{} STU {}",
+            self.invalidate_queue_depth,
+            flag(self.page_aligned_request),
+            flag(self.global_invalidate),
+            flag(self.enabled),
+            self.smallest_translation_unit,
+        )
+    }
+}
+
+impl fmt::Display for PriInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |b: bool| if b { '+' } else { '-' };
+        write!(
+            f,
+            "Capacity {} Allocation {} PASIDRequired{}, Enabled{} Stopped{}",
+            self.capacity,
+            self.allocation,
+            flag(self.pasid_required),
+            flag(self.enabled),
+            flag(self.stopped),
+        )
+    }
+}
+
+impl fmt::Display for SvmProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoPasid => write!(f, "no PASID capability"),
+            Self::NoAts => write!(f, "no ATS capability"),
+            Self::NoPri => write!(f, "no PRI capability"),
+            Self::NoIommu => write!(f, "not in an IOMMU group"),
+            Self::NoTlpPrefix(address) => {
+                write!(f, "{}: End-End TLP Prefix not supported", address)
+            }
+        }
+    }
+}
+
+/// Capability per line, path End-End TLP Prefix support and summary
+impl<'a> fmt::Display for SvmCheck<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.device.address)?;
+        match &self.pasid {
+            Some(pasid) => writeln!(f, "\tPASID: {}", pasid)?,
+            None => writeln!(f, "\tPASID: none")?,
+        }
+        match &self.ats {
+            Some(ats) => writeln!(f, "\tATS: {}", ats)?,
+            None => writeln!(f, "\tATS: none")?,
+        }
+        match &self.pri {
+            Some(pri) => writeln!(f, "\tPRI: {}", pri)?,
+            None => writeln!(f, "\tPRI: none")?,
+        }
+        match &self.device.iommu_group {
+            Some(group) => writeln!(f, "\tIOMMU group: {}", group.trim())?,
+            None => writeln!(f, "\tIOMMU group: none")?,
+        }
+        if self.prefix_path.is_empty() {
+            let note = "not required, Root Complex integrated";
+            writeln!(f, "\tEnd-End TLP Prefix: {}", note)?;
+        } else {
+            write!(f, "\tEnd-End TLP Prefix:")?;
+            for (device, supported) in &self.prefix_path {
+                let flag = if *supported { '+' } else { '-' };
+                write!(f, " {}{}", device.address, flag)?;
+            }
+            writeln!(f)?;
+        }
+        let problems = self.problems();
+        match (problems.as_slice(), &self.pasid) {
+            ([], Some(pasid)) => writeln!(f, "SVM ready, up to {} PASIDs", pasid.max_pasids()),
+            _ => {
+                write!(f, "SVM does not work:")?;
+                for (n, problem) in problems.iter().enumerate() {
+                    let sep = if n == 0 { " " } else { "; " };
+                    write!(f, "{}{}", sep, problem)?;
+                }
+                writeln!(f)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use alloc::string::ToString;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn svm_check() {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let find = |devices: &[Device], address: &str| {
+            let address = address.parse().unwrap();
+            devices.iter().position(|d| d.address == address).unwrap()
+        };
+        let igpu = find(&devices, "00:02.0");
+        devices[igpu].iommu_group = Some("1\n".into());
+
+        let topology = Topology::new(&devices);
+        let check = SvmCheck::new(&topology, &devices[igpu]);
+        let sample = "\
+            0000:00:02.0\n\
+            \tPASID: Exec- Priv- Max PASID Width 20, Enabled-\n\
+            \tATS: Invalidate Queue Depth 0 PageAligned+ GlobalInvalidate+, Enabled- STU 0\n\
+            \tPRI: Capacity 32768 Allocation 0 PASIDRequired+, Enabled- Stopped+\n\
+            \tIOMMU group: 1\n\
+            \tEnd-End TLP Prefix: not required, Root Complex integrated\n\
+            SVM ready, up to 1048576 PASIDs\n\
+        ";
+        assert_eq!(sample, check.to_string());
+        assert!(check.is_ready());
+
+        let dgpu = find(&devices, "02:00.0");
+        let check = SvmCheck::new(&topology, &devices[dgpu]);
+        let sample = "\
+            0000:02:00.0\n\
+            \tPASID: none\n\
+            \tATS: none\n\
+            \tPRI: none\n\
+            \tIOMMU group: none\n\
+            \tEnd-End TLP Prefix: 0000:00:1c.4- 0000:02:00.0-\n\
+            SVM does not work: no PASID capability; no ATS capability; no PRI capability; \
+            not in an IOMMU group; 0000:00:1c.4: End-End TLP Prefix not supported; \
+            0000:02:00.0: End-End TLP Prefix not supported\n\
+        ";
+        assert_eq!(sample, check.to_string());
+        assert_eq!(
+            Some(&SvmProblem::NoTlpPrefix("00:1c.4".parse().unwrap())),
+            check.problems().get(4)
+        );
+    }
+}
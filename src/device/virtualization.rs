@@ -0,0 +1,147 @@
+/*!
+# Virtualization
+
+Heuristic classification of devices inside a guest: paravirtual devices, devices emulated by
+hypervisor, SR-IOV Virtual Functions and physical (passed through or bare-metal) devices.
+Emulated devices keep IDs of real hardware (Intel chipset, e1000), but hypervisors put their
+own vendor into subsystem IDs.
+
+```rust
+# use pcitool::device::{builder::DeviceBuilder, virtualization::Virtualization};
+// virtio network device, transitional
+let device = DeviceBuilder::new(0x1af4, 0x1000).build().unwrap();
+assert_eq!(Virtualization::Paravirtual, device.virtualization());
+assert_eq!("paravirtual", device.virtualization().to_string());
+```
+*/
+
+use core::fmt;
+
+use pcics::header::{Cardbus, HeaderType, Normal};
+
+use super::Device;
+
+/// Red Hat, Inc. (virtio)
+const VENDOR_VIRTIO: u16 = 0x1af4;
+/// Red Hat, Inc. (QEMU devices)
+const VENDOR_QEMU: u16 = 0x1b36;
+/// QEMU/Bochs VGA
+const VENDOR_BOCHS: u16 = 0x1234;
+const VENDOR_VMWARE: u16 = 0x15ad;
+/// InnoTek (VirtualBox)
+const VENDOR_VIRTUALBOX: u16 = 0x80ee;
+/// Microsoft Corporation (Hyper-V)
+const VENDOR_MICROSOFT: u16 = 0x1414;
+/// XenSource, Inc.
+const VENDOR_XEN: u16 = 0x5853;
+
+/// VMware paravirtual devices: VMCI, VMXNET, VMXNET3, PVSCSI
+const VMWARE_PARAVIRTUAL: [u16; 4] = [0x0740, 0x0720, 0x07b0, 0x07c0];
+
+/// Device origin as seen by a guest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Virtualization {
+    /// virtio, VMware VMXNET/PVSCSI/VMCI, Xen platform device
+    Paravirtual,
+    /// Hardware model of hypervisor: QEMU, VMware, VirtualBox or Hyper-V vendor or subsystem
+    Emulated,
+    /// SR-IOV Virtual Function, known only if OS reports Physical Function
+    VirtualFunction,
+    /// Real hardware: passed through to a guest or bare-metal host device
+    Physical,
+}
+
+impl Device {
+    /// Classification by vendor, device and subsystem IDs and SR-IOV VF position
+    pub fn virtualization(&self) -> Virtualization {
+        let (vendor_id, device_id) = (self.header.vendor_id, self.header.device_id);
+        let sub_vendor_id = match &self.header.header_type {
+            HeaderType::Normal(Normal { sub_vendor_id, .. }) => Some(*sub_vendor_id),
+            HeaderType::Cardbus(Cardbus {
+                subsystem_vendor_id,
+                ..
+            }) => *subsystem_vendor_id,
+            _ => None,
+        };
+        let is_hypervisor_vendor = |vendor_id| {
+            matches!(
+                vendor_id,
+                VENDOR_VIRTIO
+                    | VENDOR_QEMU
+                    | VENDOR_BOCHS
+                    | VENDOR_VMWARE
+                    | VENDOR_VIRTUALBOX
+                    | VENDOR_MICROSOFT
+                    | VENDOR_XEN
+            )
+        };
+        match vendor_id {
+            // Transitional 0x1000-0x103f and modern 0x1040-0x107f virtio devices
+            VENDOR_VIRTIO if (0x1000..=0x107f).contains(&device_id) => Virtualization::Paravirtual,
+            VENDOR_VMWARE if VMWARE_PARAVIRTUAL.contains(&device_id) => Virtualization::Paravirtual,
+            VENDOR_XEN => Virtualization::Paravirtual,
+            _ if self.virtual_function.is_some() => Virtualization::VirtualFunction,
+            id if is_hypervisor_vendor(id) => Virtualization::Emulated,
+            _ if sub_vendor_id.is_some_and(is_hypervisor_vendor) => Virtualization::Emulated,
+            _ => Virtualization::Physical,
+        }
+    }
+}
+
+impl fmt::Display for Virtualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::Paravirtual => "paravirtual",
+            Self::Emulated => "emulated",
+            Self::VirtualFunction => "vf",
+            Self::Physical => "physical",
+        };
+        f.write_str(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{builder::DeviceBuilder, VirtualFunction};
+    use pretty_assertions::assert_eq;
+
+    fn device(vendor_id: u16, device_id: u16, sub_vendor_id: u16) -> Device {
+        DeviceBuilder::new(vendor_id, device_id)
+            .subsystem(sub_vendor_id, 0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn classification() {
+        let result = [
+            device(0x1af4, 0x1041, 0x1af4),
+            device(0x15ad, 0x07b0, 0x15ad),
+            device(0x15ad, 0x0405, 0x15ad),
+            // QEMU Q35 ICH9 SATA controller
+            device(0x8086, 0x2922, 0x1af4),
+            device(0x1b36, 0x000c, 0x1af4),
+            device(0x1234, 0x1111, 0x1af4),
+            device(0x8086, 0x9dc8, 0x1043),
+        ]
+        .map(|device| device.virtualization());
+        let sample = [
+            Virtualization::Paravirtual,
+            Virtualization::Paravirtual,
+            Virtualization::Emulated,
+            Virtualization::Emulated,
+            Virtualization::Emulated,
+            Virtualization::Emulated,
+            Virtualization::Physical,
+        ];
+        assert_eq!(sample, result);
+
+        let mut vf = device(0x8086, 0x154c, 0x8086);
+        vf.virtual_function = Some(VirtualFunction {
+            physfn: "00:03.0".parse().unwrap(),
+            index: 0,
+        });
+        assert_eq!(Virtualization::VirtualFunction, vf.virtualization());
+    }
+}
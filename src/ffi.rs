@@ -0,0 +1,351 @@
+/*!
+# C interface
+
+Thin `extern "C"` layer over [Access] for C/C++ tools migrating from libpci. Declarations are
+in `include/pcitool.h`. Build a shared library (`target/release/libpcitool.so`) with:
+
+```sh
+cargo build --lib --release --features ffi
+```
+
+All returned pointers are owned by the caller and must be released with the matching `*_free`
+function. Passing NULL to any function is allowed and handled as an error. An access handle
+may be used by several threads at once, an iterator by one thread at a time.
+*/
+
+use std::{
+    ffi::{c_char, c_int, CStr},
+    ptr,
+};
+
+use pcics::header::HeaderType;
+
+use crate::{
+    access::{dump::Dump, Access},
+    device::{Device, DDR_OFFSET},
+};
+
+/// Domain (segment) number
+pub const PCI_FIELD_DOMAIN: u32 = 0;
+/// Bus number
+pub const PCI_FIELD_BUS: u32 = 1;
+/// Device number
+pub const PCI_FIELD_DEVICE: u32 = 2;
+/// Function number
+pub const PCI_FIELD_FUNCTION: u32 = 3;
+pub const PCI_FIELD_VENDOR_ID: u32 = 4;
+pub const PCI_FIELD_DEVICE_ID: u32 = 5;
+/// Class code as 24-bit value: base class, sub class, programming interface
+pub const PCI_FIELD_CLASS: u32 = 6;
+pub const PCI_FIELD_REVISION_ID: u32 = 7;
+/// Header type without multi-function bit
+pub const PCI_FIELD_HEADER_TYPE: u32 = 8;
+/// Subsystem vendor ID (header type 0 only)
+pub const PCI_FIELD_SUBSYS_VENDOR_ID: u32 = 9;
+/// Subsystem ID (header type 0 only)
+pub const PCI_FIELD_SUBSYS_ID: u32 = 10;
+/// IRQ as reported by OS or interrupt line register
+pub const PCI_FIELD_IRQ: u32 = 11;
+pub const PCI_FIELD_NUMA_NODE: u32 = 12;
+
+/// Field is available
+pub const PCI_OK: c_int = 0;
+/// Invalid argument (NULL pointer, unknown field)
+pub const PCI_EINVAL: c_int = -1;
+/// Field is not available for this device
+pub const PCI_ENODATA: c_int = -2;
+
+/// Opaque access handle
+pub struct PciAccess(Access);
+
+/// Opaque devices iterator
+pub struct PciIter(std::vec::IntoIter<Device>);
+
+/// Opaque device handle
+pub struct PciDevice(Device);
+
+/// Initialize first available access method
+#[no_mangle]
+pub extern "C" fn pci_access_init() -> *mut PciAccess {
+    Access::init()
+        .map(|access| Box::into_raw(Box::new(PciAccess(access))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Initialize dump access method from `lspci -x` output file
+///
+/// # Safety
+///
+/// `path` should be a valid NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn pci_access_init_dump(path: *const c_char) -> *mut PciAccess {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+    Dump::init(path)
+        .map(|dump| Box::into_raw(Box::new(PciAccess(dump.into()))))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// # Safety
+///
+/// `access` should be returned by `pci_access_init*` and not freed before
+#[no_mangle]
+pub unsafe extern "C" fn pci_access_free(access: *mut PciAccess) {
+    if !access.is_null() {
+        drop(Box::from_raw(access));
+    }
+}
+
+/// Start devices enumeration. Iterator does not borrow the access handle.
+///
+/// # Safety
+///
+/// `access` should be a valid pointer returned by `pci_access_init*`
+#[no_mangle]
+pub unsafe extern "C" fn pci_iter_new(access: *const PciAccess) -> *mut PciIter {
+    match access.as_ref() {
+        Some(PciAccess(access)) => {
+            let mut devices = access.iter().filter_map(Result::ok).collect::<Vec<_>>();
+            devices.sort();
+            Box::into_raw(Box::new(PciIter(devices.into_iter())))
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+/// Next successfully parsed device or NULL at the end of enumeration
+///
+/// # Safety
+///
+/// `iter` should be a valid pointer returned by `pci_iter_new`
+#[no_mangle]
+pub unsafe extern "C" fn pci_iter_next(iter: *mut PciIter) -> *mut PciDevice {
+    match iter.as_mut() {
+        Some(PciIter(iter)) => iter
+            .next()
+            .map(|device| Box::into_raw(Box::new(PciDevice(device))))
+            .unwrap_or(ptr::null_mut()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `iter` should be returned by `pci_iter_new` and not freed before
+#[no_mangle]
+pub unsafe extern "C" fn pci_iter_free(iter: *mut PciIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// # Safety
+///
+/// `device` should be returned by `pci_iter_next` and not freed before
+#[no_mangle]
+pub unsafe extern "C" fn pci_device_free(device: *mut PciDevice) {
+    if !device.is_null() {
+        drop(Box::from_raw(device));
+    }
+}
+
+/// Read one of `PCI_FIELD_*` values into `value`
+///
+/// # Safety
+///
+/// `device` should be a valid pointer returned by `pci_iter_next`, `value` should point to
+/// writable u32
+#[no_mangle]
+pub unsafe extern "C" fn pci_device_field(
+    device: *const PciDevice,
+    field: u32,
+    value: *mut u32,
+) -> c_int {
+    let (Some(PciDevice(device)), false) = (device.as_ref(), value.is_null()) else {
+        return PCI_EINVAL;
+    };
+    match device_field(device, field) {
+        Ok(Some(v)) => {
+            *value = v;
+            PCI_OK
+        }
+        Ok(None) => PCI_ENODATA,
+        Err(()) => PCI_EINVAL,
+    }
+}
+
+/// Write NUL-terminated device address (`0000:00:1f.3`) into `buf`. Returns address string
+/// length without NUL.
+///
+/// # Safety
+///
+/// `device` should be a valid pointer returned by `pci_iter_next`, `buf` should point to at
+/// least `len` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn pci_device_address(
+    device: *const PciDevice,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    let (Some(PciDevice(device)), false) = (device.as_ref(), buf.is_null()) else {
+        return PCI_EINVAL;
+    };
+    let s = device.address.to_string();
+    if s.len() >= len {
+        return PCI_EINVAL;
+    }
+    ptr::copy_nonoverlapping(s.as_ptr() as *const c_char, buf, s.len());
+    *buf.add(s.len()) = 0;
+    s.len() as c_int
+}
+
+/// Copy capability IDs in the order of the capability list into `ids`. Returns number of
+/// capabilities, which may be greater than `len`.
+///
+/// # Safety
+///
+/// `device` should be a valid pointer returned by `pci_iter_next`, `ids` should point to at
+/// least `len` writable bytes or be NULL if `len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn pci_device_capabilities(
+    device: *const PciDevice,
+    ids: *mut u8,
+    len: usize,
+) -> c_int {
+    let Some(PciDevice(device)) = device.as_ref() else {
+        return PCI_EINVAL;
+    };
+    if ids.is_null() && len > 0 {
+        return PCI_EINVAL;
+    }
+    let (Some(caps), Some(ddr)) = (device.capabilities(), &device.device_dependent_region) else {
+        return PCI_ENODATA;
+    };
+    let mut n = 0;
+    // Pointers into the header are malformed, skip them
+    let offsets = caps
+        .flatten()
+        .filter_map(|cap| (cap.pointer as usize).checked_sub(DDR_OFFSET));
+    for offset in offsets {
+        let id = ddr.get(offset).copied().unwrap_or_default();
+        if n < len {
+            *ids.add(n) = id;
+        }
+        n += 1;
+    }
+    n as c_int
+}
+
+fn device_field(device: &Device, field: u32) -> Result<Option<u32>, ()> {
+    let header = &device.header;
+    let value = match field {
+        PCI_FIELD_DOMAIN => Some(device.address.domain),
+        PCI_FIELD_BUS => Some(device.address.bus as u32),
+        PCI_FIELD_DEVICE => Some(device.address.device as u32),
+        PCI_FIELD_FUNCTION => Some(device.address.function as u32),
+        PCI_FIELD_VENDOR_ID => Some(header.vendor_id as u32),
+        PCI_FIELD_DEVICE_ID => Some(header.device_id as u32),
+        PCI_FIELD_CLASS => {
+            let cc = &header.class_code;
+            Some((cc.base as u32) << 16 | (cc.sub as u32) << 8 | cc.interface as u32)
+        }
+        PCI_FIELD_REVISION_ID => Some(header.revision_id as u32),
+        PCI_FIELD_HEADER_TYPE => Some(u8::from(&header.header_type) as u32 & 0x7f),
+        PCI_FIELD_SUBSYS_VENDOR_ID => match header.header_type {
+            HeaderType::Normal(ref normal) => Some(normal.sub_vendor_id as u32),
+            _ => None,
+        },
+        PCI_FIELD_SUBSYS_ID => match header.header_type {
+            HeaderType::Normal(ref normal) => Some(normal.sub_device_id as u32),
+            _ => None,
+        },
+        PCI_FIELD_IRQ => Some(device.irq() as u32),
+        PCI_FIELD_NUMA_NODE => device.numa_node.map(Into::into),
+        _ => return Err(()),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::ffi::CString;
+
+    fn field(device: *const PciDevice, field: u32) -> (c_int, u32) {
+        let mut value = 0;
+        let rc = unsafe { pci_device_field(device, field, &mut value) };
+        (rc, value)
+    }
+
+    #[test]
+    fn dump_enumeration() {
+        let path = CString::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/out.vxxx.txt"
+        ))
+        .unwrap();
+        unsafe {
+            let access = pci_access_init_dump(path.as_ptr());
+            assert!(!access.is_null());
+            let iter = pci_iter_new(access);
+            let device = pci_iter_next(iter);
+            assert!(!device.is_null());
+
+            let mut buf = [0 as c_char; 16];
+            assert_eq!(12, pci_device_address(device, buf.as_mut_ptr(), buf.len()));
+            let address = CStr::from_ptr(buf.as_ptr()).to_str().unwrap();
+            assert_eq!("0000:00:1f.3", address);
+
+            assert_eq!((PCI_OK, 0x8086), field(device, PCI_FIELD_VENDOR_ID));
+            assert_eq!((PCI_OK, 0x9dc8), field(device, PCI_FIELD_DEVICE_ID));
+            assert_eq!((PCI_OK, 0x040380), field(device, PCI_FIELD_CLASS));
+            assert_eq!((PCI_OK, 0x1f), field(device, PCI_FIELD_DEVICE));
+            assert_eq!((PCI_ENODATA, 0), field(device, PCI_FIELD_NUMA_NODE));
+            assert_eq!((PCI_EINVAL, 0), field(device, 0xffff));
+
+            let n = pci_device_capabilities(device, ptr::null_mut(), 0);
+            assert!(n > 0);
+            let mut ids = vec![0u8; n as usize];
+            pci_device_capabilities(device, ids.as_mut_ptr(), ids.len());
+            assert!(ids.contains(&0x01), "{:x?}", ids);
+
+            pci_device_free(device);
+            assert!(pci_iter_next(iter).is_null());
+            pci_iter_free(iter);
+            pci_access_free(access);
+        }
+    }
+
+    #[test]
+    fn capability_pointer_into_header() {
+        use crate::device::ConfigurationSpace;
+        let mut cs = [0u8; 256];
+        cs[0x06] = 0x10; // Capabilities List
+        cs[0x34] = 0x40;
+        // Power Management, then pointer to Cardbus CIS Pointer register
+        cs[0x40..0x42].copy_from_slice(&[0x01, 0x28]);
+        cs[0x28..0x2a].copy_from_slice(&[0x05, 0x00]);
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = PciDevice(Device::new(Default::default(), cs));
+        let mut ids = [0u8; 4];
+        let n = unsafe { pci_device_capabilities(&device, ids.as_mut_ptr(), ids.len()) };
+        assert_eq!((1, 0x01), (n, ids[0]));
+    }
+
+    #[test]
+    fn null_arguments() {
+        unsafe {
+            assert!(pci_access_init_dump(ptr::null()).is_null());
+            assert!(pci_iter_new(ptr::null()).is_null());
+            assert!(pci_iter_next(ptr::null_mut()).is_null());
+            assert_eq!((PCI_EINVAL, 0), field(ptr::null(), PCI_FIELD_BUS));
+            pci_access_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,360 @@
+/*!
+# Inventory drift
+
+Devices state saved before a change (kernel update, firmware upgrade) compared with the
+current one: added and removed devices, driver changes, PCI Express links trained to lower
+speed or width and BARs moved or resized. Devices are matched by address, a different
+vendor/device ID at the same address is a removal and an addition.
+
+```rust
+# use pcitool::inventory::{diff, Change, DeviceSnapshot, LinkSnapshot};
+let old = DeviceSnapshot {
+    address: "0000:01:00.0".into(),
+    vendor_id: 0x10de,
+    device_id: 0x1d12,
+    driver: Some("nvidia".into()),
+    link: Some(LinkSnapshot { speed_gts: 8.0, width: 4 }),
+    bars: Vec::new(),
+};
+let new = DeviceSnapshot {
+    driver: Some("nouveau".into()),
+    link: Some(LinkSnapshot { speed_gts: 2.5, width: 4 }),
+    ..old.clone()
+};
+let changes = diff(&[old], &[new]);
+assert_eq!("0000:01:00.0: driver nvidia -> nouveau", changes[0].to_string());
+assert_eq!("0000:01:00.0: link degraded 8GT/s x4 -> 2.5GT/s x4", changes[1].to_string());
+```
+*/
+
+use core::fmt;
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use pcics::header::{Bridge, HeaderType, Normal};
+
+use crate::device::{link, Device};
+
+pub mod stats;
+
+/// PCI_BASE_ADDRESS_SPACE_IO
+const BAR_IO: u32 = 0x01;
+/// PCI_BASE_ADDRESS_MEM_TYPE_64
+const BAR_MEM_64: u32 = 0x04;
+
+/// Device state compared by [diff]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    /// Full address with domain
+    pub address: String,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub driver: Option<String>,
+    pub link: Option<LinkSnapshot>,
+    pub bars: Vec<BarSnapshot>,
+}
+
+/// Current PCI Express link speed and width
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkSnapshot {
+    pub speed_gts: f64,
+    pub width: u8,
+}
+
+/// Assigned BAR, size is 0 if OS resources are not known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BarSnapshot {
+    pub index: u8,
+    pub address: u64,
+    pub size: u64,
+}
+
+/// Single difference between snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(DeviceSnapshot),
+    Removed(DeviceSnapshot),
+    Driver {
+        address: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// Link trained to lower speed or width
+    LinkDegraded {
+        address: String,
+        old: LinkSnapshot,
+        new: LinkSnapshot,
+    },
+    /// BAR moved, resized, assigned or released
+    BarReassigned {
+        address: String,
+        index: u8,
+        old: Option<BarSnapshot>,
+        new: Option<BarSnapshot>,
+    },
+}
+
+impl DeviceSnapshot {
+    pub fn new(device: &Device) -> Self {
+        let link = device.link_info().map(|info| LinkSnapshot {
+            speed_gts: link::speed_gts(info.speed),
+            width: link::lanes(&info.width),
+        });
+        Self {
+            address: device.address.to_string(),
+            vendor_id: device.header.vendor_id,
+            device_id: device.header.device_id,
+            driver: device.driver_in_use.clone(),
+            link,
+            bars: bars(device),
+        }
+    }
+}
+
+/// Differences ordered by address, then by kind
+pub fn diff(old: &[DeviceSnapshot], new: &[DeviceSnapshot]) -> Vec<Change> {
+    let same_function = |a: &DeviceSnapshot, b: &DeviceSnapshot| {
+        a.address == b.address && a.vendor_id == b.vendor_id && a.device_id == b.device_id
+    };
+    let mut changes = Vec::new();
+    for old in old {
+        let Some(new) = new.iter().find(|new| same_function(old, new)) else {
+            changes.push(Change::Removed(old.clone()));
+            continue;
+        };
+        let address = &old.address;
+        if old.driver != new.driver {
+            changes.push(Change::Driver {
+                address: address.clone(),
+                old: old.driver.clone(),
+                new: new.driver.clone(),
+            });
+        }
+        if let (Some(old), Some(new)) = (old.link, new.link) {
+            if new.speed_gts < old.speed_gts || new.width < old.width {
+                changes.push(Change::LinkDegraded {
+                    address: address.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+        let mut indexes = old
+            .bars
+            .iter()
+            .chain(&new.bars)
+            .map(|bar| bar.index)
+            .collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes.dedup();
+        for index in indexes {
+            let find = |bars: &[BarSnapshot]| bars.iter().find(|bar| bar.index == index).copied();
+            let (old, new) = (find(&old.bars), find(&new.bars));
+            if old != new {
+                changes.push(Change::BarReassigned {
+                    address: address.clone(),
+                    index,
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+    for new in new {
+        if !old.iter().any(|old| same_function(old, new)) {
+            changes.push(Change::Added(new.clone()));
+        }
+    }
+    changes.sort_by(|a, b| a.address().cmp(b.address()));
+    changes
+}
+
+impl Change {
+    pub fn address(&self) -> &str {
+        match self {
+            Self::Added(device) | Self::Removed(device) => &device.address,
+            Self::Driver { address, .. }
+            | Self::LinkDegraded { address, .. }
+            | Self::BarReassigned { address, .. } => address,
+        }
+    }
+}
+
+/// BARs from OS resources, raw BAR registers if resources are not known
+fn bars(device: &Device) -> Vec<BarSnapshot> {
+    if let Some(resource) = &device.resource {
+        return resource
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.size() > 0)
+            .map(|(index, entry)| BarSnapshot {
+                index: index as u8,
+                address: entry.start,
+                size: entry.size(),
+            })
+            .collect();
+    }
+    let registers = match &device.header.header_type {
+        HeaderType::Normal(Normal { base_addresses, .. }) => base_addresses.orig().to_vec(),
+        HeaderType::Bridge(Bridge { base_addresses, .. }) => base_addresses.orig().to_vec(),
+        _ => Vec::new(),
+    };
+    let mut result = Vec::new();
+    let mut registers = registers.into_iter().enumerate();
+    while let Some((index, register)) = registers.next() {
+        let address = if register & BAR_IO != 0 {
+            (register & !0x3) as u64
+        } else if register & BAR_MEM_64 != 0 {
+            let upper = registers.next().map(|(_, upper)| upper).unwrap_or_default();
+            (upper as u64) << 32 | (register & !0xf) as u64
+        } else {
+            (register & !0xf) as u64
+        };
+        if address != 0 && register != u32::MAX {
+            result.push(BarSnapshot {
+                index: index as u8,
+                address,
+                size: 0,
+            });
+        }
+    }
+    result
+}
+
+impl fmt::Display for LinkSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}GT/s x{}", self.speed_gts, self.width)
+    }
+}
+
+/// `<address> [<size>]`, size is skipped if not known
+impl fmt::Display for BarSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}", self.address)?;
+        if self.size > 0 {
+            write!(f, " [size={:#x}]", self.size)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".into());
+        let bar = |bar: &Option<BarSnapshot>| match bar {
+            Some(bar) => bar.to_string(),
+            None => "unassigned".into(),
+        };
+        write!(f, "{}: ", self.address())?;
+        match self {
+            Self::Added(device) => {
+                write!(f, "added {:04x}:{:04x}", device.vendor_id, device.device_id)
+            }
+            Self::Removed(device) => {
+                write!(
+                    f,
+                    "removed {:04x}:{:04x}",
+                    device.vendor_id, device.device_id
+                )
+            }
+            Self::Driver { old, new, .. } => {
+                write!(f, "driver {} -> {}", or_none(old), or_none(new))
+            }
+            Self::LinkDegraded { old, new, .. } => write!(f, "link degraded {} -> {}", old, new),
+            Self::BarReassigned {
+                index, old, new, ..
+            } => {
+                write!(f, "BAR {} {} -> {}", index, bar(old), bar(new))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{ConfigurationSpace, Resource};
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    fn snapshot(address: &str, device_id: u16) -> DeviceSnapshot {
+        DeviceSnapshot {
+            address: address.into(),
+            vendor_id: 0x8086,
+            device_id,
+            driver: None,
+            link: None,
+            bars: vec![BarSnapshot {
+                index: 0,
+                address: 0xb4418000,
+                size: 0x4000,
+            }],
+        }
+    }
+
+    #[test]
+    fn drift() {
+        let old = vec![
+            snapshot("0000:00:1f.3", 0x9dc8),
+            snapshot("0000:00:1f.4", 0x9da3),
+            snapshot("0000:02:00.0", 0x1234),
+        ];
+        let mut moved = snapshot("0000:00:1f.3", 0x9dc8);
+        moved.bars[0].address = 0xc0000000;
+        let mut unbound = snapshot("0000:00:1f.4", 0x9da3);
+        unbound.bars.clear();
+        unbound.driver = Some("i801_smbus".into());
+        let new = vec![moved, unbound, snapshot("0000:02:00.0", 0x5678)];
+        let result = diff(&old, &new)
+            .iter()
+            .map(|change| change.to_string())
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "0000:00:1f.3: BAR 0 b4418000 [size=0x4000] -> c0000000 [size=0x4000]",
+            "0000:00:1f.4: driver none -> i801_smbus",
+            "0000:00:1f.4: BAR 0 b4418000 [size=0x4000] -> unassigned",
+            "0000:02:00.0: removed 8086:1234",
+            "0000:02:00.0: added 8086:5678",
+        ];
+        assert_eq!(sample, result);
+        assert_eq!(Vec::<Change>::new(), diff(&old, &old));
+    }
+
+    #[test]
+    fn device_bars() {
+        let mut cs = [0u8; 64];
+        cs[0x10..0x18].copy_from_slice(&[0x04, 0x80, 0x41, 0xb4, 0x01, 0x00, 0x00, 0x00]);
+        cs[0x18..0x1c].copy_from_slice(&[0x01, 0x30, 0x00, 0x00]);
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let mut device = Device::new("00:1f.3".parse().unwrap(), cs);
+        let sample = vec![
+            BarSnapshot {
+                index: 0,
+                address: 0x1_b441_8000,
+                size: 0,
+            },
+            BarSnapshot {
+                index: 2,
+                address: 0x3000,
+                size: 0,
+            },
+        ];
+        assert_eq!(sample, DeviceSnapshot::new(&device).bars);
+        device.resource = Some(Resource::default());
+        device.resource.as_mut().unwrap().entries[0].start = 0xb4418000;
+        device.resource.as_mut().unwrap().entries[0].end = 0xb441bfff;
+        let sample = vec![BarSnapshot {
+            index: 0,
+            address: 0xb4418000,
+            size: 0x4000,
+        }];
+        assert_eq!(sample, DeviceSnapshot::new(&device).bars);
+    }
+}
@@ -0,0 +1,102 @@
+/*!
+# Devices statistics
+
+Counts of devices per class and vendor, header types and SR-IOV usage for quick
+characterization of a machine. Classes and vendors are ordered by count, most common first.
+
+```rust
+# use pcitool::{access::{dump::Dump, AccessMethod}, inventory::stats::Stats};
+let dump = Dump::init(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+)).unwrap();
+let devices: Vec<_> = dump.iter().filter_map(Result::ok).collect();
+let stats = Stats::new(&devices);
+assert_eq!(devices.len(), stats.total);
+assert_eq!((0x8086, 22), stats.vendors[0]);
+```
+*/
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use pcics::header::HeaderType;
+
+use crate::device::Device;
+
+/// Devices counts
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub total: usize,
+    /// Header type 0
+    pub endpoints: usize,
+    /// PCI-to-PCI bridges, header type 1
+    pub bridges: usize,
+    /// CardBus bridges, header type 2
+    pub cardbus_bridges: usize,
+    /// Base class and sub class with devices count
+    pub classes: Vec<((u8, u8), usize)>,
+    /// Vendor ID with devices count
+    pub vendors: Vec<(u16, usize)>,
+    /// Physical functions with SR-IOV capability
+    pub sr_iov_capable: usize,
+    /// Virtual functions enabled on all physical functions
+    pub virtual_functions: usize,
+}
+
+impl Stats {
+    pub fn new<'a>(devices: impl IntoIterator<Item = &'a Device>) -> Self {
+        let mut stats = Self::default();
+        let mut classes = BTreeMap::new();
+        let mut vendors = BTreeMap::new();
+        for device in devices {
+            let header = &device.header;
+            stats.total += 1;
+            match header.header_type {
+                HeaderType::Normal(_) => stats.endpoints += 1,
+                HeaderType::Bridge(_) => stats.bridges += 1,
+                HeaderType::Cardbus(_) => stats.cardbus_bridges += 1,
+                _ => (),
+            }
+            let class = (header.class_code.base, header.class_code.sub);
+            *classes.entry(class).or_insert(0) += 1;
+            *vendors.entry(header.vendor_id).or_insert(0) += 1;
+            if let Some(sr_iov) = device.sr_iov() {
+                stats.sr_iov_capable += 1;
+                stats.virtual_functions += sr_iov.num_vfs as usize;
+            }
+        }
+        stats.classes = by_count(classes);
+        stats.vendors = by_count(vendors);
+        stats
+    }
+}
+
+/// Most common first, equal counts in key order
+fn by_count<K: Ord>(counts: BTreeMap<K, usize>) -> Vec<(K, usize)> {
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|(_, a), (_, b)| b.cmp(a));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn counts() {
+        let dump = Dump::fixture();
+        let devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let stats = Stats::new(&devices);
+        assert_eq!(25, stats.total);
+        assert_eq!(4, stats.bridges);
+        assert_eq!(21, stats.endpoints);
+        assert_eq!(((0x0c, 0x80), 5), stats.classes[0]);
+        assert_eq!(((0x06, 0x04), 4), stats.classes[1]);
+        let sample = vec![(0x8086, 22), (0x10de, 1), (0x10ec, 1), (0x1179, 1)];
+        assert_eq!(sample, stats.vendors);
+        assert_eq!(0, stats.sr_iov_capable);
+        assert_eq!(0, stats.virtual_functions);
+    }
+}
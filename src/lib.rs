@@ -12,11 +12,33 @@ let devices = access.iter();
 // More than one device are in the system
 assert!(devices.count() > 1);
 
+```
+
+## `no_std` support
+
+Configuration space parsing, the device data model and bus topology ([device], [misc],
+[topology], [inventory]) depend only on `core` and `alloc`. Disable default `std` feature to use them in firmware or hypervisors:
+
+```toml
+pcitool = { version = "0.1", default-features = false }
 ```
 */
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod access;
 pub mod device;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod inventory;
 pub mod misc;
+#[cfg(feature = "std")]
 pub mod names;
+#[cfg(feature = "std")]
+pub mod testing;
+pub mod topology;
+#[cfg(feature = "std")]
 pub mod view;
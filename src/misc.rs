@@ -1 +1,6 @@
+pub mod hest;
+pub mod irq;
 pub mod pnp;
+pub mod rom;
+pub mod smbios;
+pub mod virtio;
@@ -0,0 +1,245 @@
+/*!
+# ACPI Hardware Error Source Table
+
+PCI Express AER error sources of HEST tell whether platform firmware handles AER instead of the
+OS (firmware-first). Error source flagged `GLOBAL` applies to every device of its type, others
+to a single function. Then OS AER counters stay zero and errors are reported by firmware, e.g.
+through GHES records. Matching follows Linux `aer_hest_parse`: the last matching source wins.
+
+```rust
+# use pcitool::misc::hest::{AerSourceKind, Hest};
+let mut table = vec![0u8; 40];
+table[..4].copy_from_slice(b"HEST");
+// AER Endpoint error source with FIRMWARE_FIRST and GLOBAL flags
+let mut source = vec![0u8; 44];
+source[0] = 7;
+source[6] = 0b11;
+table.extend(source);
+let length = table.len() as u32;
+table[4..8].copy_from_slice(&length.to_le_bytes());
+table[36] = 1;
+let hest = Hest::parse(&table).unwrap();
+assert_eq!(AerSourceKind::Endpoint, hest.aer_sources[0].kind);
+assert!(hest.is_firmware_first());
+```
+*/
+
+use alloc::vec::Vec;
+
+use pcics::{
+    capabilities::{pci_express::DeviceType, CapabilityKind},
+    extended_capabilities::ExtendedCapabilityKind,
+};
+use thiserror::Error;
+
+use crate::device::Device;
+
+/// ACPI table header and Error Source Count
+const ENTRIES_OFFSET: usize = 40;
+const FLAG_FIRMWARE_FIRST: u8 = 0b01;
+const FLAG_GLOBAL: u8 = 0b10;
+/// IA-32 Architecture Machine Check Bank structure
+const MACHINE_CHECK_BANK_SIZE: usize = 28;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum HestError {
+    #[error("HEST table has wrong signature or length")]
+    Table,
+    #[error("unknown error source type {kind} at {offset:#x}")]
+    SourceType { kind: u16, offset: usize },
+    #[error("error source at {0:#x} exceeds table length")]
+    SourceLength(usize),
+}
+
+/// AER error source structure type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AerSourceKind {
+    RootPort,
+    Endpoint,
+    Bridge,
+}
+
+/// PCI Express AER error source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AerSource {
+    pub kind: AerSourceKind,
+    /// Errors are handled by firmware
+    pub firmware_first: bool,
+    /// Applies to every device of the kind, bus, device and function are ignored
+    pub global: bool,
+    pub segment: u16,
+    pub bus: u8,
+    pub device: u16,
+    pub function: u16,
+}
+
+impl AerSource {
+    pub fn matches(&self, device: &Device) -> bool {
+        if !self.global {
+            let address = &device.address;
+            return address.domain == self.segment as u32
+                && address.bus == self.bus
+                && address.device as u16 == self.device
+                && address.function as u16 == self.function;
+        }
+        match self.kind {
+            AerSourceKind::RootPort | AerSourceKind::Endpoint => {
+                let device_type = device.capabilities().and_then(|mut caps| {
+                    caps.find_map(|cap| match cap.ok()?.kind {
+                        CapabilityKind::PciExpress(pcie) => Some(pcie.device_type),
+                        _ => None,
+                    })
+                });
+                matches!(
+                    (self.kind, device_type),
+                    (AerSourceKind::RootPort, Some(DeviceType::RootPort { .. }))
+                        | (AerSourceKind::Endpoint, Some(DeviceType::Endpoint { .. }))
+                )
+            }
+            AerSourceKind::Bridge => device.header.class_code.base == 0x06,
+        }
+    }
+}
+
+/// Error sources of HEST, only AER sources are kept
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hest {
+    pub aer_sources: Vec<AerSource>,
+}
+
+impl Hest {
+    pub const PATH: &'static str = "/sys/firmware/acpi/tables/HEST";
+    pub fn parse(bytes: &[u8]) -> Result<Self, HestError> {
+        let length = bytes
+            .get(4..8)
+            .filter(|_| bytes.starts_with(b"HEST"))
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .filter(|&length| length >= ENTRIES_OFFSET && length <= bytes.len())
+            .ok_or(HestError::Table)?;
+        let bytes = &bytes[..length];
+        let count = u32::from_le_bytes([bytes[36], bytes[37], bytes[38], bytes[39]]);
+        let mut aer_sources = Vec::new();
+        let mut offset = ENTRIES_OFFSET;
+        for _ in 0..count {
+            let entry = &bytes[offset.min(length)..];
+            let byte = |n: usize| entry.get(n).copied().unwrap_or_default();
+            let word = |n: usize| u16::from_le_bytes([byte(n), byte(n + 1)]);
+            let kind = word(0);
+            let size = match kind {
+                0 => 40 + byte(32) as usize * MACHINE_CHECK_BANK_SIZE,
+                1 | 11 => 48 + byte(44) as usize * MACHINE_CHECK_BANK_SIZE,
+                2 => 20,
+                6 => 48,
+                7 => 44,
+                8 => 56,
+                9 => 64,
+                10 => 92,
+                _ => return Err(HestError::SourceType { kind, offset }),
+            };
+            if entry.len() < size {
+                return Err(HestError::SourceLength(offset));
+            }
+            let kind = match kind {
+                6 => Some(AerSourceKind::RootPort),
+                7 => Some(AerSourceKind::Endpoint),
+                8 => Some(AerSourceKind::Bridge),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                let flags = byte(6);
+                let bus = u32::from_le_bytes([byte(16), byte(17), byte(18), byte(19)]);
+                aer_sources.push(AerSource {
+                    kind,
+                    firmware_first: flags & FLAG_FIRMWARE_FIRST != 0,
+                    global: flags & FLAG_GLOBAL != 0,
+                    // ACPI_HEST_SEGMENT and ACPI_HEST_BUS
+                    segment: (bus >> 8) as u16,
+                    bus: bus as u8,
+                    device: word(20),
+                    function: word(22),
+                });
+            }
+            offset += size;
+        }
+        Ok(Self { aer_sources })
+    }
+    /// Any AER error source is handled by firmware
+    pub fn is_firmware_first(&self) -> bool {
+        self.aer_sources.iter().any(|source| source.firmware_first)
+    }
+    /// AER of `device` is handled by firmware, `None` for devices without AER capability
+    pub fn device_firmware_first(&self, device: &Device) -> Option<bool> {
+        let has_aer = device
+            .extended_capabilities()?
+            .flatten()
+            .any(|ecap| matches!(ecap.kind, ExtendedCapabilityKind::AdvancedErrorReporting(_)));
+        has_aer.then(|| {
+            self.aer_sources
+                .iter()
+                .rev()
+                .find(|source| source.matches(device))
+                .is_some_and(|source| source.firmware_first)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    fn table(sources: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = [b"HEST".as_slice(), &[0; 36]].concat();
+        bytes[36] = sources.len() as u8;
+        for source in sources {
+            bytes.extend_from_slice(source);
+        }
+        let length = bytes.len() as u32;
+        bytes[4..8].copy_from_slice(&length.to_le_bytes());
+        bytes
+    }
+
+    fn aer_source(kind: u8, flags: u8, address: [u8; 3]) -> Vec<u8> {
+        let size = [48, 44, 56][kind as usize - 6];
+        let mut source = vec![0; size];
+        source[0] = kind;
+        source[6] = flags;
+        (source[16], source[20], source[22]) = (address[0], address[1], address[2]);
+        source
+    }
+
+    #[test]
+    fn firmware_first() {
+        let dump = Dump::fixture();
+        let device = |address: &str| dump.device(address.parse().unwrap()).unwrap();
+        // GHES with single machine check bank sized IA-32 Corrected Machine Check before AER
+        let mut cmc = vec![0; 48 + 28];
+        (cmc[0], cmc[44]) = (1, 1);
+        let ghes = [&[9u8][..], &[0; 63]].concat();
+        let root_ports = aer_source(6, 0b11, [0; 3]);
+        // Root port 00:1d.0 is handled by OS
+        let root_port = aer_source(6, 0b00, [0, 0x1d, 0]);
+        let bytes = table(&[&cmc, &ghes, &root_ports, &root_port]);
+        let hest = Hest::parse(&bytes).unwrap();
+        assert_eq!(2, hest.aer_sources.len());
+        assert!(hest.is_firmware_first());
+        assert_eq!(Some(true), hest.device_firmware_first(&device("00:1c.4")));
+        assert_eq!(Some(false), hest.device_firmware_first(&device("00:1d.0")));
+        // No Endpoint error source
+        assert_eq!(Some(false), hest.device_firmware_first(&device("04:00.0")));
+        // Host bridge has no AER capability
+        assert_eq!(None, hest.device_firmware_first(&device("00:00.0")));
+
+        let mut bytes = table(&[&aer_source(7, 0b11, [0; 3])]);
+        bytes[ENTRIES_OFFSET] = 5;
+        assert_eq!(
+            Err(HestError::SourceType {
+                kind: 5,
+                offset: ENTRIES_OFFSET
+            }),
+            Hest::parse(&bytes)
+        );
+        assert_eq!(Err(HestError::Table), Hest::parse(b"APIC"));
+    }
+}
@@ -0,0 +1,235 @@
+/*!
+# Interrupt vectors
+
+MSI and MSI-X vectors of a device with their interrupt counts and CPU affinity. Linux lists
+vectors in `msi_irqs` device directory, counts are in `/proc/interrupts`, requested and
+effective affinity in `/proc/irq/<n>/smp_affinity_list` and `effective_affinity_list`.
+
+```rust
+# use pcitool::misc::irq::{self, CpuList};
+let interrupts = "\
+           CPU0       CPU1
+ 125:          3       1234  IR-PCI-MSIX-0000:02:00.0    0-edge      nvme0q0
+";
+let lines = irq::parse_interrupts(interrupts);
+assert_eq!(1237, lines[0].total());
+assert_eq!("nvme0q0", lines[0].actions());
+let cpus: CpuList = "0-3,8".parse().unwrap();
+assert_eq!(vec![0, 1, 2, 3, 8], cpus.0);
+assert_eq!("0-3,8", cpus.to_string());
+```
+*/
+
+use core::{fmt, str::FromStr};
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use thiserror::Error;
+
+/// Line of `/proc/interrupts` with numeric IRQ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterruptLine {
+    pub irq: u32,
+    /// Per CPU counts
+    pub counts: Vec<u64>,
+    /// Chip name, hardware IRQ with trigger type and actions
+    pub description: String,
+}
+
+/// CPU numbers in `0-3,8` format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CpuList(pub Vec<u32>);
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("CPU list should be comma separated numbers or ranges")]
+pub struct ParseCpuListError;
+
+/// MSI or MSI-X vector with counts and affinity
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IrqVector {
+    pub irq: u32,
+    /// `msi` or `msix` as listed in `msi_irqs`
+    pub kind: String,
+    pub interrupts: Option<InterruptLine>,
+    /// Requested affinity
+    pub affinity: Option<CpuList>,
+    /// CPUs interrupt is actually delivered to
+    pub effective_affinity: Option<CpuList>,
+}
+
+impl InterruptLine {
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+    /// Handlers names following hardware IRQ and flow handler, e.g. `0-edge`
+    pub fn actions(&self) -> &str {
+        let is_hwirq = |token: &str| {
+            token
+                .split_once('-')
+                .is_some_and(|(hwirq, _)| hwirq.bytes().all(|b| b.is_ascii_digit()))
+        };
+        let mut rest = self.description.as_str();
+        while let Some((token, tail)) = rest.trim_start().split_once(char::is_whitespace) {
+            if is_hwirq(token) {
+                return tail.trim();
+            }
+            rest = tail;
+        }
+        ""
+    }
+}
+
+/// Lines with numeric IRQ, architecture specific lines (NMI, LOC, ...) are skipped
+pub fn parse_interrupts(s: &str) -> Vec<InterruptLine> {
+    let mut lines = s.lines();
+    let cpus = lines
+        .next()
+        .map(|header| header.split_whitespace().count())
+        .unwrap_or_default();
+    lines
+        .filter_map(|line| {
+            let (irq, rest) = line.trim_start().split_once(':')?;
+            let irq = irq.parse().ok()?;
+            let mut tokens = rest.split_whitespace().peekable();
+            let mut counts = Vec::with_capacity(cpus);
+            while counts.len() < cpus {
+                match tokens.peek().and_then(|token| token.parse().ok()) {
+                    Some(count) => counts.push(count),
+                    None => break,
+                }
+                tokens.next();
+            }
+            let description = tokens.collect::<Vec<_>>().join(" ");
+            Some(InterruptLine {
+                irq,
+                counts,
+                description,
+            })
+        })
+        .collect()
+}
+
+impl FromStr for CpuList {
+    type Err = ParseCpuListError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cpus = Vec::new();
+        for range in s.trim().split(',').filter(|range| !range.is_empty()) {
+            let parse = |s: &str| s.parse::<u32>().map_err(|_| ParseCpuListError);
+            match range.split_once('-') {
+                Some((start, end)) => cpus.extend(parse(start)?..=parse(end)?),
+                None => cpus.push(parse(range)?),
+            }
+        }
+        Ok(Self(cpus))
+    }
+}
+
+/// Consecutive CPUs are collapsed to ranges
+impl fmt::Display for CpuList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut cpus = self.0.iter().copied().peekable();
+        let mut first = true;
+        while let Some(start) = cpus.next() {
+            let mut end = start;
+            while cpus.peek() == Some(&(end + 1)) {
+                end += 1;
+                cpus.next();
+            }
+            if !first {
+                write!(f, ",")?;
+            }
+            first = false;
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `IRQ <n> <kind> CPUs <effective> (affinity <requested>) count <total> <actions>`
+impl fmt::Display for IrqVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |cpus: &Option<CpuList>| {
+            cpus.as_ref()
+                .map(|cpus| cpus.to_string())
+                .unwrap_or_else(|| "?".into())
+        };
+        write!(
+            f,
+            "IRQ {} {} CPUs {} (affinity {})",
+            self.irq,
+            self.kind,
+            list(&self.effective_affinity),
+            list(&self.affinity)
+        )?;
+        if let Some(line) = &self.interrupts {
+            write!(f, " count {}", line.total())?;
+            let actions = line.actions();
+            if !actions.is_empty() {
+                write!(f, " {}", actions)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn interrupts() {
+        let data = "\
+            \x20          CPU0       CPU1\n\
+            \x20 0:         36          0  IR-IO-APIC    2-edge      timer\n\
+            \x20 9:          0          4  IR-IO-APIC    9-fasteoi   acpi\n\
+            \x20124:          0         21  IR-PCI-MSI-0000:00:17.0    0-edge      ahci[0000:00:17.0]\n\
+            NMI:          1          1   Non-maskable interrupts\n\
+            ERR:          0\n\
+        ";
+        let result = parse_interrupts(data);
+        assert_eq!(3, result.len());
+        assert_eq!(vec![36, 0], result[0].counts);
+        assert_eq!("timer", result[0].actions());
+        assert_eq!("IR-IO-APIC 9-fasteoi acpi", result[1].description);
+        assert_eq!("acpi", result[1].actions());
+        assert_eq!(124, result[2].irq);
+        assert_eq!(21, result[2].total());
+        assert_eq!("ahci[0000:00:17.0]", result[2].actions());
+    }
+
+    #[test]
+    fn cpu_list() {
+        assert_eq!(Ok(CpuList(vec![])), "\n".parse());
+        assert_eq!(Ok(CpuList(vec![0, 2, 3, 4, 7])), "0,2-4,7".parse());
+        assert_eq!(Err(ParseCpuListError), "0-x".parse::<CpuList>());
+        assert_eq!("0,2-4,7", CpuList(vec![0, 2, 3, 4, 7]).to_string());
+    }
+
+    #[test]
+    fn vector() {
+        let vector = IrqVector {
+            irq: 124,
+            kind: "msi".into(),
+            interrupts: parse_interrupts(
+                "CPU0 CPU1\n124: 0 21 IR-PCI-MSI-0000:00:17.0 0-edge ahci[0000:00:17.0]\n",
+            )
+            .pop(),
+            affinity: "0-1".parse().ok(),
+            effective_affinity: "1".parse().ok(),
+        };
+        assert_eq!(
+            "IRQ 124 msi CPUs 1 (affinity 0-1) count 21 ahci[0000:00:17.0]",
+            vector.to_string()
+        );
+    }
+}
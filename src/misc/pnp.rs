@@ -43,8 +43,8 @@ impl<'a> Iterator for PlugAndPlayResource<'a> {
             let (data, tail) = tail.split_at(large_item_len as usize);
             let item = match large_item_name {
                 0x01 => LargeItem::MemoryRangeDescriptor,
-                0x02 => LargeItem::IdentifierStringAnsi(std::str::from_utf8(data).ok()?),
-                0x03 => LargeItem::IdentifierStringUnicode(std::str::from_utf8(data).ok()?),
+                0x02 => LargeItem::IdentifierStringAnsi(core::str::from_utf8(data).ok()?),
+                0x03 => LargeItem::IdentifierStringUnicode(core::str::from_utf8(data).ok()?),
                 0x04 => LargeItem::VendorDefined,
                 0x05 => LargeItem::MemoryRangeDescriptor32bit,
                 0x06 => LargeItem::FixedLocationMemoryRangeDescriptor32bit,
@@ -226,14 +226,14 @@ impl<'a> Iterator for VitalProductDataRo<'a> {
         let (data, tail) = tail.split_at(len);
         self.data = tail;
         let result = match (k0, k1) {
-            ('P', 'N') => VpdRoResource::PartNumber(std::str::from_utf8(data).ok()?),
-            ('E', 'C') => VpdRoResource::EngineeringChange(std::str::from_utf8(data).ok()?),
-            ('F', 'G') => VpdRoResource::FabricGeography(std::str::from_utf8(data).ok()?),
-            ('L', 'C') => VpdRoResource::Location(std::str::from_utf8(data).ok()?),
-            ('M', 'N') => VpdRoResource::ManufactureId(std::str::from_utf8(data).ok()?),
-            ('P', 'G') => VpdRoResource::PciGeography(std::str::from_utf8(data).ok()?),
-            ('S', 'N') => VpdRoResource::SerialNumber(std::str::from_utf8(data).ok()?),
-            ('V', x) => VpdRoResource::VendorSpecific(x, std::str::from_utf8(data).ok()?),
+            ('P', 'N') => VpdRoResource::PartNumber(core::str::from_utf8(data).ok()?),
+            ('E', 'C') => VpdRoResource::EngineeringChange(core::str::from_utf8(data).ok()?),
+            ('F', 'G') => VpdRoResource::FabricGeography(core::str::from_utf8(data).ok()?),
+            ('L', 'C') => VpdRoResource::Location(core::str::from_utf8(data).ok()?),
+            ('M', 'N') => VpdRoResource::ManufactureId(core::str::from_utf8(data).ok()?),
+            ('P', 'G') => VpdRoResource::PciGeography(core::str::from_utf8(data).ok()?),
+            ('S', 'N') => VpdRoResource::SerialNumber(core::str::from_utf8(data).ok()?),
+            ('V', x) => VpdRoResource::VendorSpecific(x, core::str::from_utf8(data).ok()?),
             ('C', 'P') => {
                 let Seq {
                     head: Le((cap_id, bar_index, bar_offset)),
@@ -324,8 +324,8 @@ impl<'a> Iterator for VitalProductDataRw<'a> {
         let (data, tail) = tail.split_at(len);
         self.data = tail;
         let result = match (k0, k1) {
-            ('V', x) => VpdRwResource::VendorSpecific(x, std::str::from_utf8(data).ok()?),
-            ('Y', 'A') => VpdRwResource::AssetTagIdentifier(std::str::from_utf8(data).ok()?),
+            ('V', x) => VpdRwResource::VendorSpecific(x, core::str::from_utf8(data).ok()?),
+            ('Y', 'A') => VpdRwResource::AssetTagIdentifier(core::str::from_utf8(data).ok()?),
             ('Y', x) => VpdRwResource::SystemSpecific(x, data),
             _ => VpdRwResource::RemainingRwArea(data),
         };
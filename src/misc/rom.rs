@@ -0,0 +1,348 @@
+/*!
+# Expansion ROM
+
+Expansion ROM consists of one or more images. Every image starts with ROM header
+(signature `55 AA`) that points to PCI Data Structure (`PCIR`) with image length, code type
+and "last image" indicator. EFI images have extended ROM header with subsystem, machine type
+and compression.
+
+```rust
+# use pcitool::misc::rom::{CodeType, RomImages};
+let mut rom = [0u8; 512];
+rom[..2].copy_from_slice(&[0x55, 0xaa]);
+rom[0x18] = 0x1c;
+rom[0x1c..0x34].copy_from_slice(&[
+    b'P', b'C', b'I', b'R', 0x86, 0x80, 0x34, 0x12, 0x00, 0x00, 0x18, 0x00,
+    0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00,
+]);
+let image = RomImages::new(&rom).next().unwrap().unwrap();
+assert_eq!((0x8086, 0x1234), (image.pcir.vendor_id, image.pcir.device_id));
+assert_eq!(CodeType::PcAt, image.pcir.code_type);
+assert!(image.pcir.last);
+```
+*/
+
+use core::fmt;
+
+use thiserror::Error;
+
+/// ROM header signature
+pub const SIGNATURE: [u8; 2] = [0x55, 0xaa];
+/// Image length unit
+pub const BLOCK_SIZE: usize = 512;
+/// EFI image signature
+pub const EFI_SIGNATURE: u32 = 0x0ef1;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    #[error("no ROM signature at {offset:#x}")]
+    Signature { offset: usize },
+    #[error("no PCI Data Structure signature at {offset:#x}")]
+    PcirSignature { offset: usize },
+    #[error("image at {offset:#x} is truncated")]
+    Truncated { offset: usize },
+}
+
+/// Iterator over images of expansion ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomImages<'a> {
+    data: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> RomImages<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Iterator for RomImages<'a> {
+    type Item = Result<RomImage<'a>, RomError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset >= self.data.len() {
+            return None;
+        }
+        let result = RomImage::new(self.data, self.offset);
+        match &result {
+            Ok(image) if !image.pcir.last && image.pcir.image_length > 0 => {
+                self.offset += image.pcir.image_length;
+            }
+            _ => self.done = true,
+        }
+        Some(result)
+    }
+}
+
+/// Single ROM image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomImage<'a> {
+    /// Image offset in ROM
+    pub offset: usize,
+    /// Image data, may be shorter than image length if ROM is truncated
+    pub data: &'a [u8],
+    pub pcir: PciDataStructure,
+    pub efi: Option<EfiHeader>,
+}
+
+impl<'a> RomImage<'a> {
+    fn new(rom: &'a [u8], offset: usize) -> Result<Self, RomError> {
+        let data = &rom[offset..];
+        if data.get(..2) != Some(&SIGNATURE[..]) {
+            return Err(RomError::Signature { offset });
+        }
+        let pcir_offset = u16_at(data, 0x18).ok_or(RomError::Truncated { offset })? as usize;
+        let pcir = data
+            .get(pcir_offset..)
+            .ok_or(RomError::Truncated { offset })
+            .and_then(|pcir| {
+                PciDataStructure::new(pcir).ok_or(RomError::PcirSignature {
+                    offset: offset + pcir_offset,
+                })
+            })?;
+        let efi = (pcir.code_type == CodeType::Efi)
+            .then(|| EfiHeader::new(data))
+            .flatten();
+        let end = pcir.image_length.min(data.len());
+        Ok(Self {
+            offset,
+            data: &data[..end],
+            pcir,
+            efi,
+        })
+    }
+}
+
+/// PCI Data Structure (`PCIR`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PciDataStructure {
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub revision: u8,
+    /// Base class, sub-class and programming interface
+    pub class_code: [u8; 3],
+    /// Image length in bytes
+    pub image_length: usize,
+    pub code_revision: u16,
+    pub code_type: CodeType,
+    /// Last image in ROM
+    pub last: bool,
+}
+
+impl PciDataStructure {
+    pub const SIGNATURE: &'static [u8; 4] = b"PCIR";
+    fn new(data: &[u8]) -> Option<Self> {
+        if data.get(..4)? != Self::SIGNATURE {
+            return None;
+        }
+        Some(Self {
+            vendor_id: u16_at(data, 0x04)?,
+            device_id: u16_at(data, 0x06)?,
+            revision: *data.get(0x0c)?,
+            class_code: [*data.get(0x0f)?, *data.get(0x0e)?, *data.get(0x0d)?],
+            image_length: u16_at(data, 0x10)? as usize * BLOCK_SIZE,
+            code_revision: u16_at(data, 0x12)?,
+            code_type: CodeType::from(*data.get(0x14)?),
+            last: data.get(0x15)? & 0x80 != 0,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeType {
+    /// Intel x86, PC-AT compatible (legacy BIOS)
+    PcAt,
+    OpenFirmware,
+    HpPaRisc,
+    /// Extensible Firmware Interface (UEFI driver)
+    Efi,
+    Reserved(u8),
+}
+
+impl From<u8> for CodeType {
+    fn from(byte: u8) -> Self {
+        match byte {
+            0x00 => Self::PcAt,
+            0x01 => Self::OpenFirmware,
+            0x02 => Self::HpPaRisc,
+            0x03 => Self::Efi,
+            v => Self::Reserved(v),
+        }
+    }
+}
+
+/// EFI extended ROM header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EfiHeader {
+    pub subsystem: u16,
+    pub machine_type: u16,
+    pub compressed: bool,
+}
+
+impl EfiHeader {
+    fn new(data: &[u8]) -> Option<Self> {
+        let signature = u16_at(data, 0x04)? as u32 | (u16_at(data, 0x06)? as u32) << 16;
+        if signature != EFI_SIGNATURE {
+            return None;
+        }
+        Some(Self {
+            subsystem: u16_at(data, 0x08)?,
+            machine_type: u16_at(data, 0x0a)?,
+            compressed: u16_at(data, 0x0c)? == 1,
+        })
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+impl fmt::Display for CodeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PcAt => write!(f, "x86 PC-AT"),
+            Self::OpenFirmware => write!(f, "Open Firmware"),
+            Self::HpPaRisc => write!(f, "HP PA RISC"),
+            Self::Efi => write!(f, "EFI"),
+            Self::Reserved(v) => write!(f, "Reserved ({:#04x})", v),
+        }
+    }
+}
+
+impl fmt::Display for EfiHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subsystem = match self.subsystem {
+            10 => "application",
+            11 => "boot service driver",
+            12 => "runtime driver",
+            _ => "unknown subsystem",
+        };
+        let machine = match self.machine_type {
+            0x014c => "IA32",
+            0x0200 => "Itanium",
+            0x0ebc => "EBC",
+            0x8664 => "x64",
+            0xaa64 => "AArch64",
+            0x5064 => "RISC-V 64",
+            _ => "unknown machine",
+        };
+        write!(f, "{}, {}", machine, subsystem)?;
+        if self.compressed {
+            write!(f, ", compressed")?;
+        }
+        Ok(())
+    }
+}
+
+/// `offset <offset>, <length> bytes, <code type>, <vendor>:<device> class ...`
+impl fmt::Display for RomImage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let PciDataStructure {
+            vendor_id,
+            device_id,
+            class_code: [base, sub, interface],
+            image_length,
+            code_revision,
+            code_type,
+            last,
+            ..
+        } = self.pcir;
+        write!(
+            f,
+            "offset {:#07x}, {} bytes, {}",
+            self.offset, image_length, code_type
+        )?;
+        if let Some(efi) = &self.efi {
+            write!(f, " ({})", efi)?;
+        }
+        write!(
+            f,
+            ", {:04x}:{:04x} class {:02x}{:02x}{:02x} code revision {:#06x}",
+            vendor_id, device_id, base, sub, interface, code_revision
+        )?;
+        if last {
+            write!(f, ", last")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::{string::ToString, vec, vec::Vec};
+    use pretty_assertions::assert_eq;
+
+    fn image(code_type: u8, blocks: u16, last: bool) -> Vec<u8> {
+        let mut data = vec![0u8; blocks as usize * BLOCK_SIZE];
+        data[..2].copy_from_slice(&SIGNATURE);
+        if code_type == 3 {
+            data[0x04..0x0e].copy_from_slice(&[
+                0xf1, 0x0e, 0x00, 0x00, // EFI signature
+                0x0b, 0x00, // Boot service driver
+                0x64, 0x86, // x64
+                0x01, 0x00, // Compressed
+            ]);
+        }
+        data[0x18] = 0x40;
+        data[0x40..0x58].copy_from_slice(&[
+            b'P',
+            b'C',
+            b'I',
+            b'R',
+            0xde,
+            0x10,
+            0x12,
+            0x1d,
+            0x00,
+            0x00,
+            0x18,
+            0x00, //
+            0x03,
+            0x00,
+            0x00,
+            0x03, // revision, class code 030000
+            blocks as u8,
+            (blocks >> 8) as u8,
+            0x01,
+            0x00, // length, code revision
+            code_type,
+            if last { 0x80 } else { 0x00 },
+            0x00,
+            0x00,
+        ]);
+        data
+    }
+
+    #[test]
+    fn legacy_and_efi_images() {
+        let rom = [image(0, 2, false), image(3, 1, true), vec![0xff; 512]].concat();
+        let result = RomImages::new(&rom)
+            .map(|image| image.unwrap().to_string())
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "offset 0x00000, 1024 bytes, x86 PC-AT, 10de:1d12 class 030000 code revision 0x0001",
+            "offset 0x00400, 512 bytes, EFI (x64, boot service driver, compressed), \
+            10de:1d12 class 030000 code revision 0x0001, last",
+        ];
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn broken_rom() {
+        let rom = [image(0, 1, false), vec![0xff; 512]].concat();
+        let result = RomImages::new(&rom).collect::<Vec<_>>();
+        assert_eq!(2, result.len());
+        assert_eq!(Err(RomError::Signature { offset: 512 }), result[1]);
+        let mut rom = image(0, 1, true);
+        rom[0x40] = b'X';
+        let result = RomImages::new(&rom).collect::<Vec<_>>();
+        assert_eq!(vec![Err(RomError::PcirSignature { offset: 0x40 })], result);
+    }
+}
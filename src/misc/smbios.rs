@@ -0,0 +1,169 @@
+/*!
+# SMBIOS System Slots
+
+SMBIOS type 9 structures describe chassis expansion slots: designation printed on the board or
+chassis, e.g. `PCIe Slot 3`, slot type, usage and since SMBIOS 2.6 segment, bus, device and
+function of the device in the slot. Linux exports every structure as
+`/sys/firmware/dmi/entries/9-<n>/raw`, readable by root only.
+
+```rust
+# use pcitool::misc::smbios::{SystemSlot, SystemSlots};
+let mut raw = vec![0u8; 0x11];
+raw[0] = 9;
+raw[1] = 0x11;
+raw[4] = 1;
+// Bus 3b, device 0, function 0
+raw[0x0f] = 0x3b;
+raw.extend_from_slice(b"PCIe Slot 3\0\0");
+let slot = SystemSlot::parse(&raw).unwrap();
+assert_eq!("PCIe Slot 3", slot.designation);
+let slots = SystemSlots(vec![slot]);
+assert_eq!(Some("PCIe Slot 3"), slots.designation(&"3b:00.1".parse().unwrap()));
+```
+*/
+
+use alloc::{string::String, vec::Vec};
+
+use thiserror::Error;
+
+use crate::device::Address;
+
+/// System Slots structure type
+const TYPE: u8 = 9;
+/// Formatted area length of SMBIOS 2.1 structure
+const LENGTH_2_1: usize = 0x0d;
+/// Formatted area length with Segment Group, Bus and Device/Function Numbers (SMBIOS 2.6)
+const LENGTH_2_6: usize = 0x11;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SmbiosError {
+    #[error("structure type {0} is not System Slots")]
+    Type(u8),
+    #[error("formatted area length {0:#x} is too short")]
+    Length(usize),
+}
+
+/// Expansion slot of the chassis
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemSlot {
+    /// Reference designation, empty if firmware has no string
+    pub designation: String,
+    /// Slot Type, e.g. `0xa5` for PCI Express
+    pub slot_type: u8,
+    /// Current Usage: 3 available, 4 in use
+    pub current_usage: u8,
+    /// Device in the slot, `None` before SMBIOS 2.6 or if not applicable
+    pub address: Option<Address>,
+}
+
+impl SystemSlot {
+    /// Parse raw structure: formatted area followed by strings
+    pub fn parse(bytes: &[u8]) -> Result<Self, SmbiosError> {
+        let byte = |n: usize| bytes.get(n).copied().unwrap_or_default();
+        if byte(0) != TYPE {
+            return Err(SmbiosError::Type(byte(0)));
+        }
+        let length = byte(1) as usize;
+        if length < LENGTH_2_1 || bytes.len() < length {
+            return Err(SmbiosError::Length(length));
+        }
+        // Strings are numbered from 1, 0 means no string
+        let designation = match byte(4) {
+            0 => Default::default(),
+            n => bytes[length..]
+                .split(|&b| b == 0)
+                .nth(n as usize - 1)
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .unwrap_or_default(),
+        };
+        let segment = u16::from_le_bytes([byte(0x0d), byte(0x0e)]);
+        let (bus, devfn) = (byte(0x0f), byte(0x10));
+        // All ones if not applicable
+        let address = (length >= LENGTH_2_6 && (segment, bus, devfn) != (0xffff, 0xff, 0xff))
+            .then(|| Address::new(segment as u32, bus, devfn >> 3, devfn & 0b111));
+        Ok(Self {
+            designation,
+            slot_type: byte(5),
+            current_usage: byte(7),
+            address,
+        })
+    }
+}
+
+/// System Slots of SMBIOS table
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SystemSlots(pub Vec<SystemSlot>);
+
+impl SystemSlots {
+    pub const PATH: &'static str = "/sys/firmware/dmi/entries";
+    /// Designation of the slot holding `address`, functions of a multi-function card share it
+    pub fn designation(&self, address: &Address) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|slot| {
+                slot.address.as_ref().is_some_and(|slot| {
+                    (slot.domain, slot.bus, slot.device)
+                        == (address.domain, address.bus, address.device)
+                })
+            })
+            .map(|slot| slot.designation.as_str())
+            .filter(|designation| !designation.is_empty())
+    }
+}
+
+impl FromIterator<SystemSlot> for SystemSlots {
+    fn from_iter<I: IntoIterator<Item = SystemSlot>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use pretty_assertions::assert_eq;
+
+    fn raw(length: u8, segment: u16, bus: u8, devfn: u8, strings: &[u8]) -> Vec<u8> {
+        let mut raw = vec![0; length as usize];
+        (raw[0], raw[1], raw[4], raw[5], raw[7]) = (TYPE, length, 2, 0xa5, 4);
+        if length as usize >= LENGTH_2_6 {
+            raw[0x0d..0x0f].copy_from_slice(&segment.to_le_bytes());
+            (raw[0x0f], raw[0x10]) = (bus, devfn);
+        }
+        raw.extend_from_slice(strings);
+        raw
+    }
+
+    #[test]
+    fn system_slots() {
+        let slot = SystemSlot::parse(&raw(0x11, 1, 0x17, 0x08, b"J1\0SLOT2\0\0")).unwrap();
+        let sample = SystemSlot {
+            designation: "SLOT2".into(),
+            slot_type: 0xa5,
+            current_usage: 4,
+            address: Some(Address::new(1, 0x17, 1, 0)),
+        };
+        assert_eq!(sample, slot);
+        // Empty slot and SMBIOS 2.1 structure
+        let empty = SystemSlot::parse(&raw(0x11, 0xffff, 0xff, 0xff, b"J1\0SLOT3\0\0")).unwrap();
+        assert_eq!(None, empty.address);
+        let old = SystemSlot::parse(&raw(0x0d, 0, 0, 0, b"J1\0SLOT4\0\0")).unwrap();
+        assert_eq!(
+            ("SLOT4", None),
+            (old.designation.as_str(), old.address.clone())
+        );
+
+        let slots = [slot, empty, old].into_iter().collect::<SystemSlots>();
+        assert_eq!(
+            Some("SLOT2"),
+            slots.designation(&"0001:17:01.3".parse().unwrap())
+        );
+        assert_eq!(None, slots.designation(&"17:01.0".parse().unwrap()));
+
+        assert_eq!(Err(SmbiosError::Type(41)), SystemSlot::parse(&[41, 0x11]));
+        assert_eq!(
+            Err(SmbiosError::Length(0x11)),
+            SystemSlot::parse(&[9, 0x11, 0])
+        );
+    }
+}
@@ -0,0 +1,295 @@
+/*!
+# Virtio PCI capabilities
+
+Virtio devices (vendor `1af4`, devices `1000`-`107f`) describe location of their configuration
+structures with Vendor Specific capabilities: structure type, BAR, offset within BAR and
+length. Shared memory regions have 64-bit offset and length. [VirtioInfo] collects them with
+device type and MSI-X vectors count for a single device.
+
+```rust
+# use pcitool::misc::virtio::{CfgType, VirtioCapability};
+let data = [
+    0x09, 0x7c, 0x10, // Header and length
+    0x01, 0x04, 0x00, 0x00, 0x00, // Common configuration in BAR 4
+    0x00, 0x00, 0x00, 0x00, // Offset
+    0x00, 0x10, 0x00, 0x00, // Length
+];
+let cap = VirtioCapability::new(&data).unwrap();
+assert_eq!(CfgType::Common, cap.cfg_type);
+assert_eq!((4, 0, 0x1000), (cap.bar, cap.offset, cap.length));
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+use pcics::{
+    capabilities::CapabilityKind,
+    header::{HeaderType, Normal},
+};
+
+use crate::device::{Device, DDR_OFFSET};
+
+/// Virtio vendor ID
+pub const VENDOR_ID: u16 = 0x1af4;
+
+/// Virtio PCI devices, transitional (`1000`-`103f`) and modern (`1040`-`107f`)
+pub fn is_virtio(vendor_id: u16, device_id: u16) -> bool {
+    vendor_id == VENDOR_ID && (0x1000..=0x107f).contains(&device_id)
+}
+
+/// Device type name by virtio device ID
+pub fn device_type_name(id: u16) -> Option<&'static str> {
+    let name = match id {
+        1 => "network",
+        2 => "block",
+        3 => "console",
+        4 => "entropy",
+        5 => "memory balloon",
+        8 => "SCSI host",
+        9 => "9P transport",
+        16 => "GPU",
+        18 => "input",
+        19 => "socket",
+        20 => "crypto",
+        23 => "IOMMU",
+        24 => "memory",
+        25 => "sound",
+        26 => "file system",
+        27 => "PMEM",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Configuration structure type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgType {
+    Common,
+    Notify,
+    Isr,
+    Device,
+    /// PCI configuration access
+    Pci,
+    SharedMemory,
+    Vendor,
+    Reserved(u8),
+}
+
+impl From<u8> for CfgType {
+    fn from(byte: u8) -> Self {
+        match byte {
+            1 => Self::Common,
+            2 => Self::Notify,
+            3 => Self::Isr,
+            4 => Self::Device,
+            5 => Self::Pci,
+            8 => Self::SharedMemory,
+            9 => Self::Vendor,
+            v => Self::Reserved(v),
+        }
+    }
+}
+
+/// Decoded `virtio_pci_cap` with extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtioCapability {
+    pub cfg_type: CfgType,
+    pub bar: u8,
+    /// Shared memory region ID
+    pub id: u8,
+    pub offset: u64,
+    pub length: u64,
+    /// Notify structure only
+    pub notify_off_multiplier: Option<u32>,
+}
+
+impl VirtioCapability {
+    /// Minimal capability length
+    pub const SIZE: usize = 16;
+    /// Decode capability starting with its header (ID, next pointer and length)
+    pub fn new(data: &[u8]) -> Option<Self> {
+        let len = *data.get(2)? as usize;
+        let data = data.get(..len.max(Self::SIZE))?;
+        if len < Self::SIZE {
+            return None;
+        }
+        let cfg_type = CfgType::from(data[3]);
+        let (mut offset, mut length) = (u32_at(data, 8)? as u64, u32_at(data, 12)? as u64);
+        let mut notify_off_multiplier = None;
+        match cfg_type {
+            CfgType::Notify => notify_off_multiplier = u32_at(data, 16),
+            CfgType::SharedMemory => {
+                offset |= (u32_at(data, 16)? as u64) << 32;
+                length |= (u32_at(data, 20)? as u64) << 32;
+            }
+            _ => (),
+        }
+        Some(Self {
+            cfg_type,
+            bar: data[4],
+            id: data[5],
+            offset,
+            length,
+            notify_off_multiplier,
+        })
+    }
+}
+
+/// Virtio device summary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtioInfo {
+    /// Virtio device ID, see [device_type_name]
+    pub device_type: u16,
+    /// Configuration structures, empty for legacy only devices
+    pub capabilities: Vec<VirtioCapability>,
+    /// MSI-X table size and enable bit
+    pub msi_x: Option<(u16, bool)>,
+}
+
+impl VirtioInfo {
+    /// Summary of virtio device, `None` for other devices
+    pub fn new(device: &Device) -> Option<Self> {
+        let header = &device.header;
+        if !is_virtio(header.vendor_id, header.device_id) {
+            return None;
+        }
+        // Transitional devices report device type in subsystem ID
+        let device_type = match (header.device_id, &header.header_type) {
+            (id, _) if id >= 0x1040 => id - 0x1040,
+            (_, HeaderType::Normal(Normal { sub_device_id, .. })) => *sub_device_id,
+            _ => 0,
+        };
+        let mut capabilities = Vec::new();
+        let mut msi_x = None;
+        let ddr = device.device_dependent_region.as_ref();
+        for cap in device.capabilities().into_iter().flatten().flatten() {
+            match cap.kind {
+                CapabilityKind::VendorSpecific(_) => {
+                    let offset = (cap.pointer as usize).saturating_sub(DDR_OFFSET);
+                    if let Some(cap) = ddr
+                        .and_then(|ddr| ddr.get(offset..))
+                        .and_then(VirtioCapability::new)
+                    {
+                        capabilities.push(cap);
+                    }
+                }
+                CapabilityKind::MsiX(msi_x_cap) => {
+                    let ctrl = msi_x_cap.message_control;
+                    msi_x = Some((ctrl.table_size + 1, ctrl.msi_x_enable));
+                }
+                _ => (),
+            }
+        }
+        Some(Self {
+            device_type,
+            capabilities,
+            msi_x,
+        })
+    }
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+impl fmt::Display for CfgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Common => write!(f, "CommonCfg"),
+            Self::Notify => write!(f, "Notify"),
+            Self::Isr => write!(f, "ISR"),
+            Self::Device => write!(f, "DeviceCfg"),
+            Self::Pci => write!(f, "PCICfg"),
+            Self::SharedMemory => write!(f, "SharedMemory"),
+            Self::Vendor => write!(f, "VendorCfg"),
+            Self::Reserved(v) => write!(f, "<unknown {}>", v),
+        }
+    }
+}
+
+/// `BAR <n> offset <hex> length <hex>`
+impl fmt::Display for VirtioCapability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BAR {} offset {:#x} length {:#x}",
+            self.bar, self.offset, self.length
+        )?;
+        if let CfgType::SharedMemory = self.cfg_type {
+            write!(f, " id {}", self.id)?;
+        }
+        if let Some(multiplier) = self.notify_off_multiplier {
+            write!(f, " multiplier {}", multiplier)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::ConfigurationSpace;
+    use alloc::{format, vec};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn notify_and_shared_memory() {
+        let notify = [
+            0x09, 0x00, 0x14, 0x02, 0x04, 0x00, 0x00, 0x00, // Header, Notify in BAR 4
+            0x00, 0x30, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, // Offset, length
+            0x04, 0x00, 0x00, 0x00, // Multiplier
+        ];
+        let result = VirtioCapability::new(&notify).unwrap();
+        assert_eq!(CfgType::Notify, result.cfg_type);
+        assert_eq!(Some(4), result.notify_off_multiplier);
+        let shm = [
+            0x09, 0x00, 0x18, 0x08, 0x02, 0x01, 0x00, 0x00, // Header, shm 1 in BAR 2
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, // Offset, length
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // High dwords
+        ];
+        let sample = VirtioCapability {
+            cfg_type: CfgType::SharedMemory,
+            bar: 2,
+            id: 1,
+            offset: 0x1_0000_0000,
+            length: 0x8000_0000,
+            notify_off_multiplier: None,
+        };
+        assert_eq!(Some(sample), VirtioCapability::new(&shm));
+        assert_eq!(None, VirtioCapability::new(&shm[..0x10]));
+    }
+
+    #[test]
+    fn info() {
+        let mut cs = [0u8; 256];
+        cs[..4].copy_from_slice(&[0xf4, 0x1a, 0x41, 0x10]); // Network device
+        cs[0x06] = 0x10; // Capabilities list
+        cs[0x34] = 0x40;
+        cs[0x40..0x70].copy_from_slice(&[
+            0x11, 0x4c, 0x02, 0x80, // MSI-X, 3 vectors, enabled
+            0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, // Table and PBA
+            0x09, 0x5c, 0x10, 0x01, 0x04, 0x00, 0x00, 0x00, // Common configuration
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, //
+            0x09, 0x00, 0x14, 0x02, 0x04, 0x00, 0x00, 0x00, // Notifications
+            0x00, 0x30, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, //
+            0x04, 0x00, 0x00, 0x00, //
+        ]);
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        let device = Device::new(Default::default(), cs);
+        let info = VirtioInfo::new(&device).unwrap();
+        assert_eq!(Some("network"), device_type_name(info.device_type));
+        assert_eq!(Some((3, true)), info.msi_x);
+        let result = info
+            .capabilities
+            .iter()
+            .map(|cap| format!("{}: {}", cap.cfg_type, cap))
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "CommonCfg: BAR 4 offset 0x0 length 0x1000",
+            "Notify: BAR 4 offset 0x3000 length 0x1000 multiplier 4",
+        ];
+        assert_eq!(sample, result);
+    }
+}
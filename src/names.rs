@@ -1,9 +1,20 @@
-use std::{collections::HashMap, fs, io, num::ParseIntError, path::Path, str::FromStr};
+use std::{fs, io, num::ParseIntError, path::Path, str::FromStr};
 
 mod pciids;
+pub use pciids::Header as PciIdsHeader;
 use pciids::PciIds;
 
 mod hwdb;
+
+mod table;
+pub use table::{NameTable, NameTableBuilder};
+
+pub mod index;
+use index::Index;
+
+#[cfg(feature = "network")]
+pub mod net;
+
 use thiserror::Error;
 
 #[derive(Debug, Default)]
@@ -13,8 +24,31 @@ pub struct Names {
 }
 
 impl Names {
+    /// pci.ids read by [Names::init] without udev hwdb
+    pub const PCI_IDS_PATH: &'static str = PciIds::PATH;
+    /// udev hwdb vendor and model database preferred by [Names::init]
+    pub const HWDB_PATH: &'static str = hwdb::VendorModel::PATH;
+    /// Precompiled index at [Index::default_path], udev hwdb or pci.ids
     pub fn init() -> io::Result<Self> {
-        Self::init_hwdb().or_else(|_| Self::init_pciids(pciids::PciIds::PATH))
+        Index::default_path()
+            .filter(|path| path.exists())
+            .map_or_else(|| Err(io::ErrorKind::NotFound.into()), Self::init_index)
+            .or_else(|_| Self::init_hwdb())
+            .or_else(|_| Self::init_pciids(pciids::PciIds::PATH))
+    }
+    /// Map precompiled index, recompile it first if its source pci.ids changed
+    pub fn init_index(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut index = Index::open(path)?;
+        // Stale names are still better than none if the index is not writable
+        if index.is_stale() && index::compile(index.source(), path).is_ok() {
+            index = Index::open(path)?;
+        }
+        let (vendor_device_subsystem, class_code) = index.tables();
+        Ok(Self {
+            vendor_device_subsystem,
+            class_code,
+        })
     }
     pub fn init_hwdb() -> io::Result<Self> {
         let vds = fs::read_to_string(hwdb::VendorModel::PATH)?;
@@ -26,7 +60,11 @@ impl Names {
             class_code,
         })
     }
+    /// Parse pci.ids, precompiled index is mapped instead
     pub fn init_pciids(path: impl AsRef<Path>) -> io::Result<Self> {
+        if Index::is_index(path.as_ref()) {
+            return Self::init_index(path);
+        }
         fs::read_to_string(path.as_ref()).map(|s| {
             let (vendor_device_subsystem, class_code) = PciIds::new(s.lines()).collect();
             Self {
@@ -45,20 +83,20 @@ impl Names {
 
 /// Struct to store pciids devices DB
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct VendorDeviceSubsystem(pub HashMap<VdsKey, String>);
+pub struct VendorDeviceSubsystem(pub NameTable<VdsKey>);
 
 impl VendorDeviceSubsystem {
-    pub fn lookup<V, D, S>(&self, vendor_id: V, device_id: D, subsystem_id: S) -> Option<String>
+    pub fn lookup<V, D, S>(&self, vendor_id: V, device_id: D, subsystem_id: S) -> Option<&str>
     where
         V: Into<Option<u16>>,
         D: Into<Option<u16>>,
         S: Into<Option<(u16, u16)>>,
     {
         let data = &self.0;
-        let name = match (vendor_id.into(), device_id.into(), subsystem_id.into()) {
+        match (vendor_id.into(), device_id.into(), subsystem_id.into()) {
             // Lookup "generic" subsystem
             (None, None, Some((sv, sd))) => data.iter().find_map(|(k, v)| {
-                if let &VdsKey::Subsystem(_, _, sv_, sd_) = k {
+                if let VdsKey::Subsystem(_, _, sv_, sd_) = k {
                     if sv == sv_ && sd == sd_ {
                         Some(v)
                     } else {
@@ -72,12 +110,11 @@ impl VendorDeviceSubsystem {
             (Some(v), Some(d), None) => data.get(&VdsKey::Device(v, d)),
             (Some(v), _, _) => data.get(&VdsKey::Vendor(v)),
             _ => None,
-        };
-        name.cloned()
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum VdsKey {
     Vendor(u16),
     Device(u16, u16),
@@ -114,27 +151,26 @@ impl From<Modalias> for VdsKey {
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct ClassCode(pub HashMap<CcKey, String>);
+pub struct ClassCode(pub NameTable<CcKey>);
 
 impl ClassCode {
-    pub fn lookup<S, P>(&self, class_id: u8, subclass_id: S, prog_if_id: P) -> Option<String>
+    pub fn lookup<S, P>(&self, class_id: u8, subclass_id: S, prog_if_id: P) -> Option<&str>
     where
         S: Into<Option<u8>>,
         P: Into<Option<u8>>,
     {
-        (match (subclass_id.into(), prog_if_id.into()) {
+        match (subclass_id.into(), prog_if_id.into()) {
             (Some(subclass_id), Some(prog_if_id)) => {
                 self.0
                     .get(&CcKey::ProgIf(class_id, subclass_id, prog_if_id))
             }
             (Some(subclass_id), None) => self.0.get(&CcKey::Subclass(class_id, subclass_id)),
             _ => self.0.get(&CcKey::Class(class_id)),
-        })
-        .cloned()
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum CcKey {
     Class(u8),
     Subclass(u8, u8),
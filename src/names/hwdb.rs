@@ -163,9 +163,8 @@ impl FromIterator<ClassesEntry> for super::ClassCode {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
 
-    use crate::names::{ClassCode, VendorDeviceSubsystem};
+    use crate::names::{ClassCode, NameTable, VendorDeviceSubsystem};
 
     use super::*;
     use pretty_assertions::assert_eq;
@@ -278,7 +277,7 @@ mod tests {
             (VdsKey::Vendor(0x1002), "SuperVendor".into()),
         ]
         .into_iter()
-        .collect::<HashMap<_, _>>();
+        .collect::<NameTable<_>>();
         assert_eq!(VendorDeviceSubsystem(vds), result);
     }
 
@@ -405,7 +404,7 @@ mod tests {
             (CcKey::ProgIf(0x08, 0x00, 0x00), "8259".into()),
         ]
         .into_iter()
-        .collect::<HashMap<_, _>>();
+        .collect::<NameTable<_>>();
         assert_eq!(ClassCode(cc), result);
     }
 }
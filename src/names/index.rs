@@ -0,0 +1,512 @@
+/*!
+# Precompiled pci.ids index
+
+Binary form of pci.ids that is memory-mapped instead of parsed: vendors, devices and subsystems
+and class codes are open addressing hash tables of packed IDs and name spans into a shared
+string arena. Opening costs a header check, lookups are O(1) and pages are shared between
+processes. Index records path, modification time and size of its source, [Names::init_index]
+regenerates a stale index.
+
+Layout, little-endian:
+
+| Offset | Size | Field                                                  |
+|--------|------|--------------------------------------------------------|
+| 0      | 8    | magic `PCIIDX\0\x01`                                   |
+| 8      | 12   | source modification time: seconds, nanoseconds         |
+| 20     | 4    | source path length                                     |
+| 24     | 8    | source size                                            |
+| 32     | 12   | vendor-device-subsystem slots, class slots, arena size |
+| 48     |      | source path, slots of both tables, arena               |
+
+Slot is kind tag (0 for empty slot), packed IDs, name start and length: 20 bytes.
+
+```rust
+# use pcitool::names::{index, Names};
+let dir = tempfile::tempdir().unwrap();
+let path = dir.path().join("pci.ids.idx");
+index::compile(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids"), &path).unwrap();
+let vds = Names::init_index(&path).unwrap().vendor_device_subsystem();
+assert_eq!(Some("Intel Corporation"), vds.lookup(0x8086, None, None));
+```
+
+[Names::init_index]: super::Names::init_index
+*/
+
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    ops::Range,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    process, ptr, slice, str,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::UNIX_EPOCH,
+};
+
+use super::{CcKey, ClassCode, NameTable, Names, VdsKey, VendorDeviceSubsystem};
+
+pub const MAGIC: &[u8; 8] = b"PCIIDX\0\x01";
+const HEADER_LEN: usize = 48;
+const SLOT_LEN: usize = 20;
+
+/// Key packed into slot: nonzero kind tag and IDs
+pub trait IndexKey: Sized {
+    fn pack(&self) -> (u32, u64);
+    fn unpack(tag: u32, ids: u64) -> Option<Self>;
+}
+
+impl IndexKey for VdsKey {
+    fn pack(&self) -> (u32, u64) {
+        match *self {
+            Self::Vendor(v) => (1, v as u64),
+            Self::Device(v, d) => (2, (v as u64) << 16 | d as u64),
+            Self::Subsystem(v, d, sv, sd) => (
+                3,
+                (v as u64) << 48 | (d as u64) << 32 | (sv as u64) << 16 | sd as u64,
+            ),
+        }
+    }
+    fn unpack(tag: u32, ids: u64) -> Option<Self> {
+        let id = |n: u32| (ids >> (16 * n)) as u16;
+        match tag {
+            1 => Some(Self::Vendor(id(0))),
+            2 => Some(Self::Device(id(1), id(0))),
+            3 => Some(Self::Subsystem(id(3), id(2), id(1), id(0))),
+            _ => None,
+        }
+    }
+}
+
+impl IndexKey for CcKey {
+    fn pack(&self) -> (u32, u64) {
+        match *self {
+            Self::Class(c) => (1, c as u64),
+            Self::Subclass(c, s) => (2, (c as u64) << 8 | s as u64),
+            Self::ProgIf(c, s, p) => (3, (c as u64) << 16 | (s as u64) << 8 | p as u64),
+        }
+    }
+    fn unpack(tag: u32, ids: u64) -> Option<Self> {
+        let id = |n: u32| (ids >> (8 * n)) as u8;
+        match tag {
+            1 => Some(Self::Class(id(0))),
+            2 => Some(Self::Subclass(id(1), id(0))),
+            3 => Some(Self::ProgIf(id(2), id(1), id(0))),
+            _ => None,
+        }
+    }
+}
+
+/// splitmix64 finalizer
+fn hash(tag: u32, ids: u64) -> u64 {
+    let mut x = ids ^ (tag as u64).rotate_right(2);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Read-only file mapping, unmapped on drop
+struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+// Mapping is never written
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    fn new(file: &File, len: usize) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+    fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
+
+/// Source file modification time and size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    secs: u64,
+    nanos: u32,
+    len: u64,
+}
+
+impl Stamp {
+    fn new(metadata: &fs::Metadata) -> io::Result<Self> {
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(Self {
+            secs: mtime.as_secs(),
+            nanos: mtime.subsec_nanos(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Memory-mapped index file
+pub struct Index {
+    map: Mmap,
+    source: PathBuf,
+    stamp: Stamp,
+    vds: Range<usize>,
+    cc: Range<usize>,
+    arena: Range<usize>,
+}
+
+impl Index {
+    /// Index of the default pci.ids, `$XDG_CACHE_HOME/pcitool/pci.ids.idx` or
+    /// `~/.cache/pcitool/pci.ids.idx`
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|cache| cache.join("pcitool").join("pci.ids.idx"))
+    }
+    /// File starts with index magic
+    pub fn is_index(path: impl AsRef<Path>) -> bool {
+        let mut magic = [0u8; 8];
+        File::open(path)
+            .and_then(|mut file| file.read_exact(&mut magic))
+            .is_ok_and(|_| &magic == MAGIC)
+    }
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_LEN {
+            return Err(invalid("pci.ids index is truncated"));
+        }
+        let map = Mmap::new(&file, len)?;
+        let bytes = map.bytes();
+        if &bytes[..8] != MAGIC {
+            return Err(invalid("not a pci.ids index"));
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let stamp = Stamp {
+            secs: u64_at(8),
+            nanos: u32_at(16) as u32,
+            len: u64_at(24),
+        };
+        let source_end = HEADER_LEN + u32_at(20);
+        let vds = source_end..source_end + u32_at(32) * SLOT_LEN;
+        let cc = vds.end..vds.end + u32_at(36) * SLOT_LEN;
+        let arena = cc.end..cc.end + u32_at(40);
+        if arena.end != len {
+            return Err(invalid("pci.ids index size mismatch"));
+        }
+        let source = str::from_utf8(&bytes[HEADER_LEN..source_end])
+            .map_err(|_| invalid("pci.ids index source path is not UTF-8"))?
+            .into();
+        Ok(Self {
+            map,
+            source,
+            stamp,
+            vds,
+            cc,
+            arena,
+        })
+    }
+    /// pci.ids the index was compiled from
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+    /// Source exists and its modification time or size differ from recorded ones
+    pub fn is_stale(&self) -> bool {
+        fs::metadata(&self.source)
+            .and_then(|metadata| Stamp::new(&metadata))
+            .is_ok_and(|stamp| stamp != self.stamp)
+    }
+    /// Vendor-device-subsystem and class code tables sharing the mapping
+    pub fn tables(self) -> (VendorDeviceSubsystem, ClassCode) {
+        let index = Arc::new(self);
+        let vds = Table::new(index.clone(), index.vds.clone());
+        let cc = Table::new(index.clone(), index.cc.clone());
+        (
+            VendorDeviceSubsystem(NameTable::mapped(vds)),
+            ClassCode(NameTable::mapped(cc)),
+        )
+    }
+}
+
+impl fmt::Debug for Index {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Index")
+            .field("source", &self.source)
+            .field("len", &self.map.len)
+            .finish()
+    }
+}
+
+/// Hash table of an index
+#[derive(Clone)]
+pub(super) struct Table {
+    index: Arc<Index>,
+    slots: Range<usize>,
+    len: usize,
+}
+
+impl Table {
+    fn new(index: Arc<Index>, slots: Range<usize>) -> Self {
+        let mut table = Self {
+            index,
+            slots,
+            len: 0,
+        };
+        table.len = (0..table.capacity())
+            .filter(|&n| table.slot(n).0 != 0)
+            .count();
+        table
+    }
+    fn capacity(&self) -> usize {
+        self.slots.len() / SLOT_LEN
+    }
+    /// Tag, IDs, name start and length
+    fn slot(&self, n: usize) -> (u32, u64, usize, usize) {
+        let start = self.slots.start + n * SLOT_LEN;
+        let bytes = &self.index.map.bytes()[start..start + SLOT_LEN];
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let ids = u64::from_le_bytes(bytes[4..12].try_into().unwrap());
+        (u32_at(0), ids, u32_at(12) as usize, u32_at(16) as usize)
+    }
+    fn name(&self, start: usize, len: usize) -> Option<&str> {
+        let arena = &self.index.map.bytes()[self.index.arena.clone()];
+        str::from_utf8(arena.get(start..start + len)?).ok()
+    }
+    pub fn get(&self, tag: u32, ids: u64) -> Option<&str> {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return None;
+        }
+        let mut n = hash(tag, ids) as usize & (capacity - 1);
+        for _ in 0..capacity {
+            match self.slot(n) {
+                (0, ..) => return None,
+                (t, i, start, len) if (t, i) == (tag, ids) => return self.name(start, len),
+                _ => n = (n + 1) & (capacity - 1),
+            }
+        }
+        None
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// Entries in slot order
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u64, &str)> {
+        (0..self.capacity()).filter_map(|n| match self.slot(n) {
+            (0, ..) => None,
+            (tag, ids, start, len) => Some((tag, ids, self.name(start, len)?)),
+        })
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Table").field("len", &self.len).finish()
+    }
+}
+
+/// Slots of a hash table with at most 3/4 of them used, names are appended to `arena`
+fn encode_table<'a, K: IndexKey + Ord + Clone>(
+    table: &'a NameTable<K>,
+    arena: &mut Vec<u8>,
+    interned: &mut HashMap<&'a str, (u32, u32)>,
+) -> (usize, Vec<u8>) {
+    let capacity = if table.is_empty() {
+        0
+    } else {
+        (table.len() * 4 / 3 + 1).next_power_of_two()
+    };
+    let mut slots = vec![0u8; capacity * SLOT_LEN];
+    for (key, name) in table.iter() {
+        let (start, len) = *interned.entry(name).or_insert_with(|| {
+            let span = (arena.len() as u32, name.len() as u32);
+            arena.extend_from_slice(name.as_bytes());
+            span
+        });
+        let (tag, ids) = key.pack();
+        let mut n = hash(tag, ids) as usize & (capacity - 1);
+        while slots[n * SLOT_LEN..][..4] != [0; 4] {
+            n = (n + 1) & (capacity - 1);
+        }
+        let slot = &mut slots[n * SLOT_LEN..][..SLOT_LEN];
+        slot[..4].copy_from_slice(&tag.to_le_bytes());
+        slot[4..12].copy_from_slice(&ids.to_le_bytes());
+        slot[12..16].copy_from_slice(&start.to_le_bytes());
+        slot[16..20].copy_from_slice(&len.to_le_bytes());
+    }
+    (capacity, slots)
+}
+
+/// Index file content for `names` parsed from `source`
+fn encode(source: &Path, stamp: Stamp, names: &Names) -> Vec<u8> {
+    let mut arena = Vec::new();
+    let mut interned = HashMap::new();
+    let vds = &names.vendor_device_subsystem.0;
+    let (vds_slots, vds) = encode_table(vds, &mut arena, &mut interned);
+    let (cc_slots, cc) = encode_table(&names.class_code.0, &mut arena, &mut interned);
+    let source = source.to_string_lossy();
+    let len = HEADER_LEN + source.len() + vds.len() + cc.len() + arena.len();
+    let mut bytes = Vec::with_capacity(len);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&stamp.secs.to_le_bytes());
+    bytes.extend_from_slice(&stamp.nanos.to_le_bytes());
+    bytes.extend_from_slice(&(source.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&stamp.len.to_le_bytes());
+    for n in [vds_slots, cc_slots, arena.len(), 0] {
+        bytes.extend_from_slice(&(n as u32).to_le_bytes());
+    }
+    bytes.extend_from_slice(source.as_bytes());
+    bytes.extend_from_slice(&vds);
+    bytes.extend_from_slice(&cc);
+    bytes.extend_from_slice(&arena);
+    bytes
+}
+
+/// New file next to `output` named after it, process ID and a counter
+fn create_tmp(output: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    loop {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let tmp = output.with_file_name(format!(".{}.{}.{}.tmp", name, process::id(), n));
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Ok(file) => return Ok((tmp, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Parse pci.ids `source` and write its index to `output`, returns parsed names.
+///
+/// Index is written to a unique temporary file renamed over `output`, so concurrent runs do not
+/// overwrite each other and processes that mapped the old index keep reading it.
+pub fn compile(source: impl AsRef<Path>, output: impl AsRef<Path>) -> io::Result<Names> {
+    let source = fs::canonicalize(source.as_ref())?;
+    if Index::is_index(&source) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "source is an index already",
+        ));
+    }
+    let output = output.as_ref();
+    let stamp = Stamp::new(&fs::metadata(&source)?)?;
+    let names = Names::init_pciids(&source)?;
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let (tmp, mut file) = create_tmp(output)?;
+    let result = file
+        .write_all(&encode(&source, stamp, &names))
+        .and_then(|()| fs::rename(&tmp, output));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map(|()| names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const PCI_IDS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids");
+
+    #[test]
+    fn keys() {
+        let vds = [
+            VdsKey::Vendor(0x8086),
+            VdsKey::Device(0x8086, 0x9dc8),
+            VdsKey::Subsystem(0x8086, 0x9dc8, 0x1043, 0x16a1),
+        ];
+        for key in vds {
+            let (tag, ids) = key.pack();
+            assert_eq!(Some(key.clone()), VdsKey::unpack(tag, ids));
+        }
+        let cc = [
+            CcKey::Class(3),
+            CcKey::Subclass(3, 2),
+            CcKey::ProgIf(1, 6, 1),
+        ];
+        for key in cc {
+            let (tag, ids) = key.pack();
+            assert_eq!(Some(key.clone()), CcKey::unpack(tag, ids));
+        }
+        assert_eq!(None, CcKey::unpack(0, 0));
+    }
+
+    #[test]
+    fn compile_and_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("pci.ids");
+        fs::copy(PCI_IDS, &source).unwrap();
+        let path = dir.path().join("cache/pci.ids.idx");
+        let parsed = compile(&source, &path).unwrap();
+        assert!(Index::is_index(&path));
+        assert!(!Index::is_index(&source));
+        let files = fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(1, files, "temporary file is left");
+
+        let index = Index::open(&path).unwrap();
+        assert_eq!(fs::canonicalize(&source).unwrap(), index.source());
+        assert!(!index.is_stale());
+        let (vds, cc) = index.tables();
+        assert_eq!(parsed.vendor_device_subsystem, vds);
+        assert_eq!(parsed.class_code, cc);
+        assert_eq!(Some("Intel Corporation"), vds.lookup(0x8086, None, None));
+        assert_eq!(Some("VGA controller"), cc.lookup(0x03, 0x00, 0x00));
+        assert_eq!(None, vds.lookup(0x8086, 0xffff, None));
+
+        fs::write(&source, "8086  Intel\n").unwrap();
+        assert!(Index::open(&path).unwrap().is_stale());
+        let names = Names::init_index(&path).unwrap();
+        assert!(!Index::open(&path).unwrap().is_stale());
+        let vds = names.vendor_device_subsystem();
+        assert_eq!(
+            (1, Some("Intel")),
+            (vds.0.len(), vds.lookup(0x8086, None, None))
+        );
+        assert!(compile(&path, dir.path().join("again.idx")).is_err());
+        fs::write(&path, b"PCIIDX\0\x01").unwrap();
+        assert!(Index::open(&path).is_err());
+    }
+}
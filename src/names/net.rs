@@ -0,0 +1,499 @@
+/*!
+# Central PCI ID database queries
+
+Resolving of unknown ID's via DNS TXT records (`lspci -q`/`-Q`). The central
+database at `pci.id.ucw.cz` answers queries like `8086.1234.pci.id.ucw.cz` with
+`i=<name>` TXT records. Answers (including negative ones) are stored in the same
+cache file lspci uses (`~/.pciids-cache`).
+*/
+
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use pcics::header::HeaderType;
+
+use super::{CcKey, Names, VdsKey};
+use crate::device::Device;
+
+/// Default `net.domain`
+pub const DOMAIN: &str = "pci.id.ucw.cz";
+
+/// Names database entry key
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Vds(VdsKey),
+    Cc(CcKey),
+}
+
+impl Key {
+    /// Category number used by pciutils (`ID_VENDOR` .. `ID_PROGIF`)
+    pub fn category(&self) -> u8 {
+        match self {
+            Self::Vds(VdsKey::Vendor(..)) => 1,
+            Self::Vds(VdsKey::Device(..)) => 2,
+            Self::Vds(VdsKey::Subsystem(..)) => 3,
+            Self::Cc(CcKey::Class(..)) => 5,
+            Self::Cc(CcKey::Subclass(..)) => 6,
+            Self::Cc(CcKey::ProgIf(..)) => 7,
+        }
+    }
+    /// Four id's as stored in cache file, unused are 0
+    pub fn ids(&self) -> [u16; 4] {
+        match *self {
+            Self::Vds(VdsKey::Vendor(v)) => [v, 0, 0, 0],
+            Self::Vds(VdsKey::Device(v, d)) => [v, d, 0, 0],
+            Self::Vds(VdsKey::Subsystem(v, d, sv, sd)) => [v, d, sv, sd],
+            Self::Cc(CcKey::Class(c)) => [c as u16, 0, 0, 0],
+            Self::Cc(CcKey::Subclass(c, s)) => [c as u16, s as u16, 0, 0],
+            Self::Cc(CcKey::ProgIf(c, s, p)) => [c as u16, s as u16, p as u16, 0],
+        }
+    }
+    pub fn from_ids(category: u8, ids: [u16; 4]) -> Option<Self> {
+        let [id1, id2, id3, id4] = ids;
+        let byte = |id: u16| u8::try_from(id).ok();
+        let key = match category {
+            1 => Self::Vds(VdsKey::Vendor(id1)),
+            2 => Self::Vds(VdsKey::Device(id1, id2)),
+            3 => Self::Vds(VdsKey::Subsystem(id1, id2, id3, id4)),
+            5 => Self::Cc(CcKey::Class(byte(id1)?)),
+            6 => Self::Cc(CcKey::Subclass(byte(id1)?, byte(id2)?)),
+            7 => Self::Cc(CcKey::ProgIf(byte(id1)?, byte(id2)?, byte(id3)?)),
+            _ => return None,
+        };
+        Some(key)
+    }
+    /// DNS name relative to database domain (most specific id goes first)
+    pub fn dns_name(&self, domain: &str) -> String {
+        let name = match *self {
+            Self::Vds(VdsKey::Vendor(v)) => format!("{:04x}", v),
+            Self::Vds(VdsKey::Device(v, d)) => format!("{:04x}.{:04x}", d, v),
+            Self::Vds(VdsKey::Subsystem(v, d, sv, sd)) => {
+                format!("{:04x}.{:04x}.{:04x}.{:04x}", sd, sv, d, v)
+            }
+            Self::Cc(CcKey::Class(c)) => format!("{:02x}.c", c),
+            Self::Cc(CcKey::Subclass(c, s)) => format!("{:02x}.{:02x}.c", s, c),
+            Self::Cc(CcKey::ProgIf(c, s, p)) => format!("{:02x}.{:02x}.{:02x}.c", p, s, c),
+        };
+        format!("{}.{}", name, domain)
+    }
+    /// All keys needed to display device names
+    pub fn device_keys(device: &Device) -> Vec<Self> {
+        let header = &device.header;
+        let (v, d) = (header.vendor_id, header.device_id);
+        let cc = &header.class_code;
+        let mut keys = vec![
+            Self::Vds(VdsKey::Vendor(v)),
+            Self::Vds(VdsKey::Device(v, d)),
+            Self::Cc(CcKey::Class(cc.base)),
+            Self::Cc(CcKey::Subclass(cc.base, cc.sub)),
+            Self::Cc(CcKey::ProgIf(cc.base, cc.sub, cc.interface)),
+        ];
+        if let HeaderType::Normal(ref normal) = header.header_type {
+            let (sv, sd) = (normal.sub_vendor_id, normal.sub_device_id);
+            if sv != 0 && sv != 0xffff {
+                keys.push(Self::Vds(VdsKey::Vendor(sv)));
+                keys.push(Self::Vds(VdsKey::Subsystem(v, d, sv, sd)));
+            }
+        }
+        keys
+    }
+}
+
+/// Local cache of network query results
+///
+/// Empty name means the database does not know the ID.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Cache {
+    entries: HashMap<Key, String>,
+    dirty: bool,
+}
+
+impl Cache {
+    pub const VERSION: &'static str = "#PCI-CACHE-1.0";
+    pub const NAME: &'static str = ".pciids-cache";
+
+    /// `~/.pciids-cache`
+    pub fn default_path() -> Option<PathBuf> {
+        std::env::var_os("HOME").map(|home| Path::new(&home).join(Self::NAME))
+    }
+    /// Missing file is the same as empty cache
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(s.parse().unwrap_or_default()),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+    /// Write cache only if it was changed since load
+    pub fn save(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        if self.dirty {
+            fs::write(path, self.to_string())?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+    pub fn get(&self, key: &Key) -> Option<&str> {
+        self.entries.get(key).map(String::as_str)
+    }
+    pub fn insert(&mut self, key: Key, name: impl Into<String>) {
+        self.entries.insert(key, name.into());
+        self.dirty = true;
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Unknown cache version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheVersionError;
+
+impl std::str::FromStr for Cache {
+    type Err = CacheVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(Self::VERSION) {
+            return Err(CacheVersionError);
+        }
+        let entries = lines
+            .filter_map(|line| {
+                let mut fields = line.splitn(6, ' ');
+                let category = fields.next()?.parse().ok()?;
+                let mut ids = [0u16; 4];
+                for id in ids.iter_mut() {
+                    *id = u16::from_str_radix(fields.next()?, 16).ok()?;
+                }
+                let name = fields.next().unwrap_or_default();
+                Some((Key::from_ids(category, ids)?, name.to_string()))
+            })
+            .collect();
+        Ok(Self {
+            entries,
+            dirty: false,
+        })
+    }
+}
+
+impl fmt::Display for Cache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", Self::VERSION)?;
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(key, _)| (key.category(), key.ids()));
+        for (key, name) in entries {
+            let [id1, id2, id3, id4] = key.ids();
+            writeln!(
+                f,
+                "{} {:x} {:x} {:x} {:x} {}",
+                key.category(),
+                id1,
+                id2,
+                id3,
+                id4,
+                name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Minimal stub resolver for TXT records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolver {
+    pub nameserver: SocketAddr,
+    pub timeout: Duration,
+}
+
+impl Resolver {
+    pub const RESOLV_CONF: &'static str = "/etc/resolv.conf";
+    const TYPE_TXT: u16 = 16;
+    const CLASS_IN: u16 = 1;
+    const RCODE_NXDOMAIN: u8 = 3;
+
+    /// First nameserver from /etc/resolv.conf or localhost
+    pub fn system() -> Self {
+        let nameserver = fs::read_to_string(Self::RESOLV_CONF)
+            .ok()
+            .and_then(|s| {
+                s.lines().find_map(|line| {
+                    line.strip_prefix("nameserver")?
+                        .trim()
+                        .parse::<IpAddr>()
+                        .ok()
+                })
+            })
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        Self {
+            nameserver: SocketAddr::new(nameserver, 53),
+            timeout: Duration::from_secs(5),
+        }
+    }
+    /// Query TXT records. Returns `None` if the name does not exist.
+    pub fn txt(&self, name: &str) -> io::Result<Option<Vec<Vec<u8>>>> {
+        let id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u16)
+            .unwrap_or_default()
+            ^ std::process::id() as u16;
+        let local: SocketAddr = if self.nameserver.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+        socket.connect(self.nameserver)?;
+        socket.send(&Self::query(id, name))?;
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = socket.recv(&mut buf)?;
+            match Self::answer(id, &buf[..len]) {
+                Some(result) => return Ok(result),
+                // Not our answer, wait for next datagram
+                None => continue,
+            }
+        }
+    }
+    fn query(id: u16, name: &str) -> Vec<u8> {
+        let mut msg = Vec::with_capacity(name.len() + 18);
+        msg.extend_from_slice(&id.to_be_bytes());
+        // Recursion desired, one question
+        msg.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.').filter(|l| !l.is_empty()) {
+            msg.push(label.len() as u8);
+            msg.extend_from_slice(label.as_bytes());
+        }
+        msg.push(0);
+        msg.extend_from_slice(&Self::TYPE_TXT.to_be_bytes());
+        msg.extend_from_slice(&Self::CLASS_IN.to_be_bytes());
+        msg
+    }
+    /// `None` on malformed or foreign message, otherwise TXT character-strings
+    fn answer(id: u16, msg: &[u8]) -> Option<Option<Vec<Vec<u8>>>> {
+        let u16_at = |pos: usize| -> Option<u16> {
+            Some(u16::from_be_bytes([*msg.get(pos)?, *msg.get(pos + 1)?]))
+        };
+        // Response flag set
+        if u16_at(0)? != id || msg.get(2)? & 0x80 == 0 {
+            return None;
+        }
+        if msg.get(3)? & 0x0f == Self::RCODE_NXDOMAIN {
+            return Some(None);
+        }
+        let qdcount = u16_at(4)?;
+        let ancount = u16_at(6)?;
+        let mut pos = 12;
+        for _ in 0..qdcount {
+            pos = Self::skip_name(msg, pos)? + 4;
+        }
+        let mut result = Vec::new();
+        for _ in 0..ancount {
+            pos = Self::skip_name(msg, pos)?;
+            let rtype = u16_at(pos)?;
+            let rdlength = u16_at(pos + 8)? as usize;
+            let rdata = msg.get(pos + 10..pos + 10 + rdlength)?;
+            pos += 10 + rdlength;
+            if rtype != Self::TYPE_TXT {
+                continue;
+            }
+            let mut rdata = rdata;
+            while let Some((&len, rest)) = rdata.split_first() {
+                let s = rest.get(..len as usize)?;
+                result.push(s.to_vec());
+                rdata = &rest[len as usize..];
+            }
+        }
+        Some(Some(result))
+    }
+    fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+        loop {
+            let len = *msg.get(pos)?;
+            match len {
+                0 => return Some(pos + 1),
+                // Compression pointer
+                len if len & 0xc0 == 0xc0 => return Some(pos + 2),
+                len => pos += 1 + len as usize,
+            }
+        }
+    }
+}
+
+/// Network lookup state shared between queries
+#[derive(Debug, Clone)]
+pub struct NetQuery {
+    pub domain: String,
+    pub cache: Cache,
+    /// Ignore cached entries (`-qq`)
+    pub refresh: bool,
+    pub resolver: Resolver,
+    /// Set after first resolver failure to avoid waiting for timeout on every ID
+    failed: bool,
+}
+
+impl NetQuery {
+    pub fn new(domain: impl Into<String>, cache: Cache, refresh: bool) -> Self {
+        Self {
+            domain: domain.into(),
+            cache,
+            refresh,
+            resolver: Resolver::system(),
+            failed: false,
+        }
+    }
+    /// Look up name in cache or in the central database
+    pub fn lookup(&mut self, key: &Key) -> Option<String> {
+        if !self.refresh {
+            if let Some(name) = self.cache.get(key) {
+                return Some(name).filter(|s| !s.is_empty()).map(Into::into);
+            }
+        }
+        if self.failed {
+            return None;
+        }
+        let name = match self.resolver.txt(&key.dns_name(&self.domain)) {
+            Ok(Some(txt)) => txt.iter().find_map(|s| {
+                s.strip_prefix(b"i=")
+                    .map(|s| String::from_utf8_lossy(s).into_owned())
+            }),
+            Ok(None) => None,
+            Err(_) => {
+                self.failed = true;
+                return None;
+            }
+        };
+        self.cache
+            .insert(key.clone(), name.clone().unwrap_or_default());
+        name
+    }
+}
+
+impl Names {
+    /// Resolve ID's missing in local database via network
+    pub fn query_network<I>(&mut self, keys: I, query: &mut NetQuery)
+    where
+        I: IntoIterator<Item = Key>,
+    {
+        for key in keys {
+            let known = match &key {
+                Key::Vds(k) => self.vendor_device_subsystem.0.contains_key(k),
+                Key::Cc(k) => self.class_code.0.contains_key(k),
+            };
+            if known {
+                continue;
+            }
+            if let Some(name) = query.lookup(&key) {
+                match key {
+                    Key::Vds(k) => self.vendor_device_subsystem.0.insert(k, name),
+                    Key::Cc(k) => self.class_code.0.insert(k, name),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn dns_names() {
+        let keys = [
+            Key::Vds(VdsKey::Vendor(0x8086)),
+            Key::Vds(VdsKey::Device(0x8086, 0x9dc8)),
+            Key::Vds(VdsKey::Subsystem(0x8086, 0x9dc8, 0x17aa, 0x2292)),
+            Key::Cc(CcKey::Class(0x04)),
+            Key::Cc(CcKey::Subclass(0x04, 0x03)),
+            Key::Cc(CcKey::ProgIf(0x0c, 0x03, 0x30)),
+        ];
+        let result = keys.map(|k| k.dns_name(DOMAIN));
+        let sample = [
+            "8086.pci.id.ucw.cz",
+            "9dc8.8086.pci.id.ucw.cz",
+            "2292.17aa.9dc8.8086.pci.id.ucw.cz",
+            "04.c.pci.id.ucw.cz",
+            "03.04.c.pci.id.ucw.cz",
+            "30.03.0c.c.pci.id.ucw.cz",
+        ];
+        assert_eq!(sample.map(String::from), result);
+    }
+
+    #[test]
+    fn cache_file() {
+        let data = "#PCI-CACHE-1.0\n\
+            1 8086 0 0 0 Intel Corporation\n\
+            2 8086 9dc8 0 0 \n\
+            3 8086 9dc8 17aa 2292 ThinkPad X1\n\
+            7 c 3 30 0 XHCI\n";
+        let mut cache: Cache = data.parse().unwrap();
+        assert_eq!(4, cache.len());
+        assert_eq!(
+            Some("Intel Corporation"),
+            cache.get(&Key::Vds(VdsKey::Vendor(0x8086)))
+        );
+        assert_eq!(
+            Some(""),
+            cache.get(&Key::Vds(VdsKey::Device(0x8086, 0x9dc8)))
+        );
+        assert_eq!(
+            Some("XHCI"),
+            cache.get(&Key::Cc(CcKey::ProgIf(0x0c, 0x03, 0x30)))
+        );
+        assert_eq!(data, cache.to_string());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(Cache::NAME);
+        cache.insert(Key::Cc(CcKey::Class(0x04)), "Multimedia controller");
+        cache.save(&path).unwrap();
+        assert_eq!(cache, Cache::load(&path).unwrap());
+        assert_eq!(Err(CacheVersionError), "#PCI-CACHE-0.1\n".parse::<Cache>());
+    }
+
+    #[test]
+    fn dns_messages() {
+        let query = Resolver::query(0x1234, "8086.pci.id.ucw.cz");
+        let mut answer = query.clone();
+        // QR, RD, RA; one answer
+        answer[2..8].copy_from_slice(&[0x81, 0x80, 0, 1, 0, 1]);
+        // Name pointer to question, TXT, IN, TTL, RDLENGTH
+        answer.extend_from_slice(&[0xc0, 0x0c, 0, 16, 0, 1, 0, 0, 0x0e, 0x10, 0, 20]);
+        answer.push(19);
+        answer.extend_from_slice(b"i=Intel Corporation");
+        assert_eq!(
+            Some(Some(vec![b"i=Intel Corporation".to_vec()])),
+            Resolver::answer(0x1234, &answer)
+        );
+        // Foreign id
+        assert_eq!(None, Resolver::answer(0x4321, &answer));
+        // NXDOMAIN
+        answer[3] = 0x83;
+        assert_eq!(Some(None), Resolver::answer(0x1234, &answer));
+    }
+
+    #[test]
+    fn cached_lookup() {
+        let mut cache = Cache::default();
+        cache.insert(Key::Vds(VdsKey::Vendor(0x1af4)), "Red Hat, Inc.");
+        cache.insert(Key::Vds(VdsKey::Device(0x1af4, 0x1000)), "");
+        let mut query = NetQuery::new(DOMAIN, cache, false);
+        let mut names = Names::default();
+        names.query_network(
+            [
+                Key::Vds(VdsKey::Vendor(0x1af4)),
+                Key::Vds(VdsKey::Device(0x1af4, 0x1000)),
+            ],
+            &mut query,
+        );
+        let vds = names.vendor_device_subsystem();
+        assert_eq!(Some("Red Hat, Inc."), vds.lookup(0x1af4, None, None));
+        assert_eq!(None, vds.lookup(0x1af4, 0x1000, None));
+    }
+}
@@ -1,7 +1,6 @@
 //! PCI IDs Database parser
 
 use std::{
-    collections::HashMap,
     iter::FromIterator,
     num::ParseIntError,
     str::{self, FromStr},
@@ -9,7 +8,7 @@ use std::{
 
 use thiserror::Error;
 
-use crate::names::{CcKey, ClassCode, VdsKey, VendorDeviceSubsystem};
+use crate::names::{CcKey, ClassCode, NameTableBuilder, VdsKey, VendorDeviceSubsystem};
 
 /// Structured entry of pci.ids file
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
@@ -158,6 +157,35 @@ impl FromStr for ProgIf {
     }
 }
 
+/// `Version:` and `Date:` of the database header comment
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Header<'a> {
+    pub version: Option<&'a str>,
+    pub date: Option<&'a str>,
+}
+
+impl<'a> Header<'a> {
+    /// Parse leading comment lines, the first entry stops parsing
+    pub fn parse(lines: str::Lines<'a>) -> Self {
+        let mut header = Self::default();
+        for line in lines {
+            let Some(comment) = line.strip_prefix('#') else {
+                if line.is_empty() {
+                    continue;
+                }
+                break;
+            };
+            let comment = comment.trim();
+            if let Some(version) = comment.strip_prefix("Version:") {
+                header.version = Some(version.trim());
+            } else if let Some(date) = comment.strip_prefix("Date:") {
+                header.date = Some(date.trim());
+            }
+        }
+        header
+    }
+}
+
 /// An iterator through pci.ids database file
 #[derive(Debug, Clone)]
 pub struct PciIds<'a> {
@@ -246,13 +274,13 @@ impl Iterator for PciIds<'_> {
 
 impl FromIterator<PciIdsEntry> for (VendorDeviceSubsystem, ClassCode) {
     fn from_iter<I: IntoIterator<Item = PciIdsEntry>>(iter: I) -> Self {
-        let mut vds: HashMap<VdsKey, String> = HashMap::default();
-        let mut cc: HashMap<CcKey, String> = HashMap::default();
+        let mut vds = NameTableBuilder::new();
+        let mut cc = NameTableBuilder::new();
         for entry in iter {
             match entry {
-                PciIdsEntry::Vendor(vendor) => vds.insert(VdsKey::Vendor(vendor.id), vendor.name),
+                PciIdsEntry::Vendor(vendor) => vds.insert(VdsKey::Vendor(vendor.id), &vendor.name),
                 PciIdsEntry::Device(vendor, device) => {
-                    vds.insert(VdsKey::Device(vendor.id, device.id), device.name)
+                    vds.insert(VdsKey::Device(vendor.id, device.id), &device.name)
                 }
                 PciIdsEntry::Subsystem(vendor, device, subsystem) => {
                     let key = VdsKey::Subsystem(
@@ -261,19 +289,19 @@ impl FromIterator<PciIdsEntry> for (VendorDeviceSubsystem, ClassCode) {
                         subsystem.vendor_id,
                         subsystem.device_id,
                     );
-                    vds.insert(key, subsystem.name)
+                    vds.insert(key, &subsystem.name)
                 }
-                PciIdsEntry::Class(class) => cc.insert(CcKey::Class(class.id), class.name),
+                PciIdsEntry::Class(class) => cc.insert(CcKey::Class(class.id), &class.name),
                 PciIdsEntry::Subclass(class, subclass) => {
-                    cc.insert(CcKey::Subclass(class.id, subclass.id), subclass.name)
+                    cc.insert(CcKey::Subclass(class.id, subclass.id), &subclass.name)
                 }
                 PciIdsEntry::ProgIf(class, subclass, prog_if) => cc.insert(
                     CcKey::ProgIf(class.id, subclass.id, prog_if.id),
-                    prog_if.name,
+                    &prog_if.name,
                 ),
             };
         }
-        (VendorDeviceSubsystem(vds), ClassCode(cc))
+        (VendorDeviceSubsystem(vds.build()), ClassCode(cc.build()))
     }
 }
 
@@ -283,6 +311,20 @@ mod tests {
     use pretty_assertions::assert_eq;
     use std::prelude::v1::*;
 
+    #[test]
+    fn header() {
+        let data = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids"));
+        let header = Header {
+            version: Some("2021.05.16"),
+            date: Some("2021-05-16 03:15:02"),
+        };
+        assert_eq!(header, Header::parse(data.lines()));
+        assert_eq!(
+            Header::default(),
+            Header::parse("0001  SafeNet\n#\tDate: 0".lines())
+        );
+    }
+
     #[test]
     fn parse_vendor_valid() {
         let result = "aa55  Ncomputing X300 PCI-Engine"
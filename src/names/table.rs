@@ -0,0 +1,273 @@
+//! Compact names storage
+//!
+//! Names are interned into a single string arena, equal names (there are many among subsystems)
+//! are stored once. Keys are kept sorted with arena spans and looked up by binary search. The
+//! table is shared behind [Arc], so copies handed to views and threads are cheap. Tables of a
+//! [precompiled index](super::index) read names from the mapped file until modified.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::BuildHasher,
+    iter::FromIterator,
+    sync::Arc,
+};
+
+use super::index::{IndexKey, Table};
+
+/// Name position in arena
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Inner<K> {
+    /// Sorted by key, keys are unique
+    index: Vec<(K, Span)>,
+    arena: String,
+}
+
+impl<K> Inner<K> {
+    fn name(&self, span: Span) -> &str {
+        let start = span.start as usize;
+        &self.arena[start..start + span.len as usize]
+    }
+    fn push(&mut self, name: &str) -> Span {
+        let span = Span {
+            start: self.arena.len() as u32,
+            len: name.len() as u32,
+        };
+        self.arena.push_str(name);
+        span
+    }
+}
+
+/// Index table with key packing of the key type
+#[derive(Clone)]
+struct Mapped<K> {
+    table: Table,
+    pack: fn(&K) -> (u32, u64),
+    unpack: fn(u32, u64) -> Option<K>,
+}
+
+#[derive(Clone)]
+enum Storage<K> {
+    Owned(Arc<Inner<K>>),
+    Mapped(Mapped<K>),
+}
+
+/// Read-mostly map of keys to names
+#[derive(Clone)]
+pub struct NameTable<K> {
+    storage: Storage<K>,
+}
+
+impl<K: Ord + Clone + IndexKey> NameTable<K> {
+    pub(super) fn mapped(table: Table) -> Self {
+        Self {
+            storage: Storage::Mapped(Mapped {
+                table,
+                pack: K::pack,
+                unpack: K::unpack,
+            }),
+        }
+    }
+}
+
+impl<K: Ord + Clone> NameTable<K> {
+    pub fn get(&self, key: &K) -> Option<&str> {
+        match &self.storage {
+            Storage::Owned(inner) => {
+                let index = &inner.index;
+                let n = index.binary_search_by(|(k, _)| k.cmp(key)).ok()?;
+                Some(inner.name(index[n].1))
+            }
+            Storage::Mapped(mapped) => {
+                let (tag, ids) = (mapped.pack)(key);
+                mapped.table.get(tag, ids)
+            }
+        }
+    }
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+    /// Add or replace name, the table is copied if it is shared or mapped
+    pub fn insert(&mut self, key: K, name: String) {
+        if let Storage::Mapped(_) = self.storage {
+            let mut builder = NameTableBuilder::new();
+            for (key, name) in self.iter() {
+                builder.insert(key, name);
+            }
+            *self = builder.build();
+        }
+        let Storage::Owned(inner) = &mut self.storage else {
+            unreachable!()
+        };
+        let inner = Arc::make_mut(inner);
+        let span = inner.push(&name);
+        match inner.index.binary_search_by(|(k, _)| k.cmp(&key)) {
+            Ok(n) => inner.index[n].1 = span,
+            Err(n) => inner.index.insert(n, (key, span)),
+        }
+    }
+    /// Entries in key order, mapped table entries are in index slot order
+    pub fn iter(&self) -> impl Iterator<Item = (K, &str)> {
+        let (owned, mapped) = match &self.storage {
+            Storage::Owned(inner) => {
+                let iter = inner
+                    .index
+                    .iter()
+                    .map(|(k, span)| (k.clone(), inner.name(*span)));
+                (Some(iter), None)
+            }
+            Storage::Mapped(Mapped { table, unpack, .. }) => {
+                let iter = table
+                    .iter()
+                    .filter_map(|(tag, ids, name)| Some((unpack(tag, ids)?, name)));
+                (None, Some(iter))
+            }
+        };
+        owned
+            .into_iter()
+            .flatten()
+            .chain(mapped.into_iter().flatten())
+    }
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Owned(inner) => inner.index.len(),
+            Storage::Mapped(mapped) => mapped.table.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Arena size in bytes, 0 for a mapped table
+    pub fn arena_len(&self) -> usize {
+        match &self.storage {
+            Storage::Owned(inner) => inner.arena.len(),
+            Storage::Mapped(_) => 0,
+        }
+    }
+}
+
+impl<K> Default for NameTable<K> {
+    fn default() -> Self {
+        Self {
+            storage: Storage::Owned(Arc::new(Inner {
+                index: Vec::new(),
+                arena: String::new(),
+            })),
+        }
+    }
+}
+
+/// Table filled entry by entry, names are interned as they come
+#[derive(Debug)]
+pub struct NameTableBuilder<K> {
+    inner: Inner<K>,
+    /// Name hash to its first span, colliding names are just not shared
+    interned: HashMap<u64, Span>,
+    hasher: RandomState,
+}
+
+impl<K: Ord + Clone> NameTableBuilder<K> {
+    pub fn new() -> Self {
+        Self {
+            inner: Inner {
+                index: Vec::new(),
+                arena: String::new(),
+            },
+            interned: HashMap::new(),
+            hasher: RandomState::new(),
+        }
+    }
+    pub fn insert(&mut self, key: K, name: &str) {
+        let hash = self.hasher.hash_one(name);
+        let span = match self.interned.get(&hash) {
+            Some(&span) if self.inner.name(span) == name => span,
+            Some(_) => self.inner.push(name),
+            None => {
+                let span = self.inner.push(name);
+                self.interned.insert(hash, span);
+                span
+            }
+        };
+        self.inner.index.push((key, span));
+    }
+    /// Later entries replace earlier ones with the same key
+    pub fn build(self) -> NameTable<K> {
+        let mut inner = self.inner;
+        // Stable sort of reversed entries keeps the last one of equal keys first
+        inner.index.reverse();
+        inner.index.sort_by(|(a, _), (b, _)| a.cmp(b));
+        inner.index.dedup_by(|(a, _), (b, _)| a == b);
+        inner.index.shrink_to_fit();
+        inner.arena.shrink_to_fit();
+        NameTable {
+            storage: Storage::Owned(Arc::new(inner)),
+        }
+    }
+}
+
+impl<K: Ord + Clone> Default for NameTableBuilder<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Later entries replace earlier ones with the same key, like [HashMap] collecting
+impl<K: Ord + Clone> FromIterator<(K, String)> for NameTable<K> {
+    fn from_iter<I: IntoIterator<Item = (K, String)>>(iter: I) -> Self {
+        let mut builder = NameTableBuilder::new();
+        for (key, name) in iter {
+            builder.insert(key, &name);
+        }
+        builder.build()
+    }
+}
+
+/// Keys are unique, so equal tables have the same length and names of all keys
+impl<K: Ord + Clone> PartialEq for NameTable<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(key, name)| other.get(&key) == Some(name))
+    }
+}
+
+impl<K: Ord + Clone> Eq for NameTable<K> {}
+
+impl<K: Ord + Clone + fmt::Debug> fmt::Debug for NameTable<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn interned() {
+        let mut table = [(3, "Device"), (1, "Vendor"), (2, "Device"), (1, "Renamed")]
+            .into_iter()
+            .map(|(k, v)| (k, v.to_string()))
+            .collect::<NameTable<u8>>();
+        assert_eq!(3, table.len());
+        assert_eq!("VendorDeviceRenamed".len(), table.arena_len());
+        assert_eq!(Some("Renamed"), table.get(&1));
+        assert_eq!(Some("Device"), table.get(&3));
+        assert_eq!(None, table.get(&4));
+
+        let shared = table.clone();
+        table.insert(4, "Network".into());
+        table.insert(2, "Bridge".into());
+        let entries = table.iter().collect::<Vec<_>>();
+        assert_eq!(
+            vec![(1, "Renamed"), (2, "Bridge"), (3, "Device"), (4, "Network")],
+            entries
+        );
+        assert_eq!((3, Some("Device")), (shared.len(), shared.get(&2)));
+    }
+}
@@ -0,0 +1,245 @@
+/*!
+# Golden-file fixtures
+
+Regression fixtures for lspci compatible views without writing Rust tests. A fixture is a
+directory with device files laid out like sysfs device directory and expected outputs:
+- `config`: configuration space blob (64, 256 or 4096 bytes)
+- `label`, `numa_node`, `irq`, `resource`: optional sysfs attributes
+- `driver`: optional bound driver name (text file or symlink as in sysfs)
+- `slot`: optional physical slot name
+- `out.<flags>.txt`: expected `lspci -<flags>` output of the device, `out.txt` for no flags
+
+Flags are `v` and `n` repeated, outputs with other flags (hex dumps) are skipped. Device address
+is taken from the first line of expected output.
+
+```no_run
+# use pcitool::{names::Names, testing};
+let names = Names::init_pciids("tests/data/pci.ids").unwrap();
+let mismatches = testing::check_dir("tests/data/fixtures", &names).unwrap();
+assert!(mismatches.is_empty(), "{}", mismatches[0]);
+```
+*/
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    device::{Address, ConfigurationSpace, Device},
+    names::Names,
+    view::lspci::basic::{View, ViewArgs},
+};
+
+#[derive(Error, Debug)]
+pub enum FixtureError {
+    #[error("{path}: {source}")]
+    File { path: PathBuf, source: io::Error },
+    #[error("{0}: invalid configuration space")]
+    ConfigurationSpace(PathBuf),
+    #[error("{0}: no device address in first line")]
+    Address(PathBuf),
+}
+
+/// Expected output with view arguments parsed from the file name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Case {
+    pub path: PathBuf,
+    pub verbose: usize,
+    pub as_numbers: usize,
+    pub expected: String,
+}
+
+/// Single device fixture directory
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub path: PathBuf,
+    pub device: Device,
+    pub cases: Vec<Case>,
+}
+
+/// Expected and produced outputs differ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    pub expected: String,
+    pub result: String,
+}
+
+impl Fixture {
+    /// Read device files and expected outputs of fixture directory
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FixtureError> {
+        let path = path.as_ref().to_path_buf();
+        let mut cases = Vec::new();
+        let entries = fs::read_dir(&path).map_err(|source| FixtureError::File {
+            path: path.clone(),
+            source,
+        })?;
+        for entry in entries.flatten() {
+            let file_path = entry.path();
+            let Some((verbose, as_numbers)) = file_path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .and_then(parse_flags)
+            else {
+                continue;
+            };
+            let expected = read(&file_path)?;
+            cases.push(Case {
+                path: file_path,
+                verbose,
+                as_numbers,
+                expected,
+            });
+        }
+        cases.sort_by(|a, b| a.path.cmp(&b.path));
+        let address = match cases.first() {
+            Some(case) => case
+                .expected
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| FixtureError::Address(case.path.clone()))?,
+            None => Address::default(),
+        };
+        let config_path = path.join("config");
+        let bytes = fs::read(&config_path).map_err(|source| FixtureError::File {
+            path: config_path.clone(),
+            source,
+        })?;
+        let cs: ConfigurationSpace = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| FixtureError::ConfigurationSpace(config_path))?;
+        let mut device = Device::new(address, cs);
+        let attr = |name| fs::read_to_string(path.join(name)).ok();
+        device.label = attr("label");
+        device.phy_slot = attr("slot").map(|s| s.trim().to_string());
+        device.numa_node = attr("numa_node").and_then(|s| s.trim().parse().ok());
+        device.irq = attr("irq").and_then(|s| s.trim().parse().ok());
+        device.resource = attr("resource").and_then(|s| s.parse().ok());
+        device.driver_in_use = fs::read_link(path.join("driver"))
+            .ok()
+            .and_then(|path| path.file_name()?.to_str().map(|s| s.to_string()))
+            .or_else(|| attr("driver").map(|s| s.trim().to_string()));
+        Ok(Self {
+            path,
+            device,
+            cases,
+        })
+    }
+    /// Compare every expected output with device view
+    pub fn check(&self, names: &Names) -> Vec<Mismatch> {
+        let vds = &names.vendor_device_subsystem();
+        let cc = &names.class_code();
+        let access = Default::default();
+        self.cases
+            .iter()
+            .filter_map(|case| {
+                let args = &ViewArgs {
+                    verbose: case.verbose,
+                    kernel: false,
+                    always_domain_number: false,
+                    as_numbers: case.as_numbers,
+                    bus_centric: false,
+                    show_power: false,
+                    name_width: Default::default(),
+                    vds,
+                    cc,
+                    access: &access,
+                };
+                let result = View {
+                    data: self.device.clone(),
+                    args,
+                }
+                .to_string();
+                (result != case.expected).then(|| Mismatch {
+                    path: case.path.clone(),
+                    expected: case.expected.clone(),
+                    result,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Check every fixture (subdirectory with `config` file) of `path`
+pub fn check_dir(path: impl AsRef<Path>, names: &Names) -> Result<Vec<Mismatch>, FixtureError> {
+    let path = path.as_ref();
+    let entries = fs::read_dir(path).map_err(|source| FixtureError::File {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let mut dirs = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("config").is_file())
+        .collect::<Vec<_>>();
+    dirs.sort();
+    let mut mismatches = Vec::new();
+    for dir in dirs {
+        mismatches.extend(Fixture::load(dir)?.check(names));
+    }
+    Ok(mismatches)
+}
+
+/// `out.vvnn.txt` -> verbose 2, as_numbers 2
+fn parse_flags(file_name: &str) -> Option<(usize, usize)> {
+    let flags = match file_name {
+        "out.txt" => "",
+        _ => file_name.strip_prefix("out.")?.strip_suffix(".txt")?,
+    };
+    if !flags.chars().all(|c| matches!(c, 'v' | 'n')) {
+        return None;
+    }
+    let count = |flag| flags.chars().filter(|&c| c == flag).count();
+    Some((count('v'), count('n')))
+}
+
+fn read(path: &Path) -> Result<String, FixtureError> {
+    fs::read_to_string(path).map_err(|source| FixtureError::File {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// File path and first different line
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut expected = self.expected.lines();
+        let mut result = self.result.lines();
+        let mut n = 1;
+        loop {
+            match (expected.next(), result.next()) {
+                (Some(a), Some(b)) if a == b => n += 1,
+                (None, None) => return write!(f, "{}: line endings differ", self.path.display()),
+                (a, b) => {
+                    return write!(
+                        f,
+                        "{}:{}: expected {:?}, got {:?}",
+                        self.path.display(),
+                        n,
+                        a.unwrap_or_default(),
+                        b.unwrap_or_default()
+                    )
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn flags() {
+        assert_eq!(Some((0, 0)), parse_flags("out.txt"));
+        assert_eq!(Some((3, 2)), parse_flags("out.vvvnn.txt"));
+        assert_eq!(None, parse_flags("out.vvxxx.txt"));
+        assert_eq!(None, parse_flags("caps.lspci.vvv.txt"));
+    }
+}
@@ -0,0 +1,169 @@
+/*!
+# Bus topology
+
+Devices hierarchy reconstructed from PCI-to-PCI bridges bus numbers: device parent is the
+bridge with secondary bus number equal to the device bus in the same domain.
+
+```rust
+# use pcitool::{access::Access, topology::Topology};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+for device in topology.roots() {
+    println!("{}", device.address);
+}
+```
+*/
+
+use alloc::vec::Vec;
+
+use pcics::header::{Bridge, HeaderType};
+
+use crate::device::{ari, Address, Device};
+
+pub mod lint;
+pub mod naming;
+pub mod p2p;
+pub mod select;
+pub mod usage;
+pub mod vga;
+
+/// Devices hierarchy over borrowed devices list
+#[derive(Debug, Clone, Copy)]
+pub struct Topology<'a> {
+    devices: &'a [Device],
+}
+
+impl<'a> Topology<'a> {
+    pub fn new(devices: &'a [Device]) -> Self {
+        Self { devices }
+    }
+    pub fn devices(&self) -> &'a [Device] {
+        self.devices
+    }
+    /// Upstream bridge, bridges with secondary bus 0 are not configured by firmware yet
+    pub fn parent(&self, device: &Device) -> Option<&'a Device> {
+        let address = &device.address;
+        self.devices.iter().find(|bridge| {
+            bridge.address != *address
+                && bridge.address.domain == address.domain
+                && matches!(
+                    bridge.header.header_type,
+                    HeaderType::Bridge(Bridge { secondary_bus_number, .. })
+                        if secondary_bus_number != 0 && secondary_bus_number == address.bus
+                )
+        })
+    }
+    /// Devices directly behind the bridge
+    pub fn children(&self, bridge: &'a Device) -> impl Iterator<Item = &'a Device> + '_ {
+        self.devices.iter().filter(move |device| {
+            self.parent(device)
+                .filter(|parent| parent.address == bridge.address)
+                .is_some()
+        })
+    }
+    /// Devices without upstream bridge
+    pub fn roots(&self) -> impl Iterator<Item = &'a Device> + '_ {
+        self.devices
+            .iter()
+            .filter(move |device| self.parent(device).is_none())
+    }
+    /// Bridges from the topmost one down to the device itself
+    pub fn path(&self, device: &'a Device) -> Vec<&'a Device> {
+        let mut path = Vec::new();
+        let mut current = Some(device);
+        while let Some(device) = current {
+            // Misconfigured bus numbers may form a loop
+            if path.iter().any(|d: &&Device| d.address == device.address) {
+                break;
+            }
+            path.push(device);
+            current = self.parent(device);
+        }
+        path.reverse();
+        path
+    }
+    /// 8-bit ARI function number if upstream port forwards ARI, 3-bit function number otherwise
+    pub fn function_number(&self, device: &Device) -> u8 {
+        let ari = self.parent(device).is_some_and(Device::ari_forwarding);
+        device.address.function_number(ari)
+    }
+    /// ARI functions chained from function 0 on the device bus, empty without ARI capability
+    pub fn ari_chain(&self, device: &Device) -> Vec<&'a Device> {
+        let Address { domain, bus, .. } = device.address;
+        let find = |address: &Address| self.devices.iter().find(|d| d.address == *address);
+        find(&Address::from_devfn(domain, bus, 0))
+            .filter(|first| first.ari_next_function().is_some())
+            .map(|first| ari::chain(first, find))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    fn devices() -> Vec<Device> {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices
+    }
+
+    fn addresses<'a>(devices: impl IntoIterator<Item = &'a Device>) -> Vec<String> {
+        devices
+            .into_iter()
+            .map(|d| format!("{:#}", d.address))
+            .collect()
+    }
+
+    #[test]
+    fn hierarchy() {
+        let devices = devices();
+        let topology = Topology::new(&devices);
+        let nvme = devices
+            .iter()
+            .find(|d| d.address == "04:00.0".parse().unwrap())
+            .unwrap();
+        assert_eq!(vec!["00:1d.4", "04:00.0"], addresses(topology.path(nvme)));
+        let bridge = topology.parent(nvme).unwrap();
+        assert_eq!(vec!["04:00.0"], addresses(topology.children(bridge)));
+        assert_eq!(22, topology.roots().count());
+        assert_eq!(0, topology.function_number(nvme));
+        assert!(topology.ari_chain(nvme).is_empty());
+    }
+
+    #[test]
+    fn ari_functions() {
+        let function = |devfn: u8, next: u8| {
+            let mut cs = [0u8; 0x1000];
+            cs[0x100..0x104].copy_from_slice(&0x0001_000eu32.to_le_bytes());
+            cs[0x105] = next;
+            let cs: crate::device::ConfigurationSpace = cs.as_slice().try_into().unwrap();
+            Device::new(Address::from_devfn(0, 1, devfn), cs)
+        };
+        let devices = [function(0, 0x0a), function(0x0a, 0x81), function(0x81, 0)];
+        let topology = Topology::new(&devices);
+        let sample = vec!["01:00.0", "01:01.2", "01:10.1"];
+        assert_eq!(sample, addresses(topology.ari_chain(&devices[2])));
+        // No upstream port with ARI Forwarding in the list
+        assert_eq!(1, topology.function_number(&devices[2]));
+    }
+
+    #[test]
+    fn unconfigured_bridge() {
+        let device = |dev: u8, header_type: u8| {
+            let mut cs = [0u8; 0x100];
+            cs[0x0e] = header_type;
+            let cs: crate::device::ConfigurationSpace = cs.as_slice().try_into().unwrap();
+            Device::new(Address::from_devfn(0, 0, dev << 3), cs)
+        };
+        // Bridge with all bus numbers zeroed and a device on bus 0
+        let devices = [device(0x1c, 1), device(0x1f, 0)];
+        let topology = Topology::new(&devices);
+        assert!(topology.parent(&devices[1]).is_none());
+        assert_eq!(2, topology.roots().count());
+    }
+}
@@ -0,0 +1,517 @@
+/*!
+# Topology consistency
+
+Bring-up sanity checks of bus numbers and address windows programmed by firmware or OS:
+bridges bus ranges and windows must nest in the upstream bridge and not overlap with sibling
+bridges, BARs must be inside upstream bridge windows and decoding must be enabled on the path.
+Capabilities allowed once per function must not be chained twice.
+
+Windows and BARs are taken from configuration space registers (bus addresses), BAR sizes are
+known only if OS reports resources.
+
+```rust
+# use pcitool::{access::Access, topology::{lint, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+for warning in lint::check(&Topology::new(&devices)) {
+    println!("{}", warning);
+}
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::header::{Bridge, HeaderType};
+
+use super::Topology;
+pub use crate::device::bridge::{Space, Span};
+use crate::device::{
+    bridge::Windows,
+    cap_list::{self, RawCapability},
+    Address, Device,
+};
+
+/// PCI_BASE_ADDRESS_SPACE_IO
+const BAR_IO: u32 = 0x01;
+/// PCI_BASE_ADDRESS_MEM_TYPE_64
+const BAR_MEM_64: u32 = 0x04;
+/// PCI_BASE_ADDRESS_MEM_PREFETCH
+const BAR_MEM_PREFETCH: u32 = 0x08;
+
+/// Assigned BAR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bar {
+    pub index: usize,
+    pub space: Space,
+    /// Single address if size is not known
+    pub span: Span,
+}
+
+/// Inconsistency with the fix hint in [Display](fmt::Display)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Secondary bus is not above primary or above subordinate
+    BusNumbers {
+        bridge: Address,
+        primary: u8,
+        secondary: u8,
+        subordinate: u8,
+    },
+    /// Secondary-subordinate range is not inside upstream bridge range
+    BusOutsideParent {
+        bridge: Address,
+        buses: (u8, u8),
+        parent: Address,
+        parent_buses: (u8, u8),
+    },
+    /// Sibling bridges claim the same bus numbers
+    BusOverlap {
+        bridges: (Address, Address),
+        buses: ((u8, u8), (u8, u8)),
+    },
+    /// Sibling bridges forward the same addresses
+    WindowOverlap {
+        space: Space,
+        bridges: (Address, Address),
+        spans: (Span, Span),
+    },
+    /// Window is not forwarded by upstream bridge
+    WindowOutsideParent {
+        space: Space,
+        bridge: Address,
+        span: Span,
+        parent: Address,
+    },
+    /// Upstream bridge does not decode BAR space
+    DecodingDisabled {
+        device: Address,
+        space: Space,
+        bridge: Address,
+    },
+    /// BAR is not inside upstream bridge windows
+    BarOutsideWindow {
+        device: Address,
+        bar: Bar,
+        bridge: Address,
+    },
+    /// Capability allowed once is present again, first instance is at `first` offset
+    DuplicateCapability {
+        device: Address,
+        capability: RawCapability,
+        first: usize,
+    },
+}
+
+/// Enabled (base not above limit) I/O, memory and prefetchable memory windows of bridge
+pub fn windows(device: &Device) -> Vec<(Space, Span)> {
+    device.bridge().map_or_else(Vec::new, |bridge| {
+        bridge
+            .windows()
+            .into_iter()
+            .filter(|window| window.enabled)
+            .map(|window| (window.space, window.span))
+            .collect()
+    })
+}
+
+/// Assigned BARs of normal and bridge headers
+pub fn bars(device: &Device) -> Vec<Bar> {
+    let registers = match &device.header.header_type {
+        HeaderType::Normal(header) => header.base_addresses.orig().to_vec(),
+        HeaderType::Bridge(header) => header.base_addresses.orig().to_vec(),
+        _ => Vec::new(),
+    };
+    let mut result = Vec::new();
+    let mut registers = registers.into_iter().enumerate();
+    while let Some((index, register)) = registers.next() {
+        if register == u32::MAX {
+            continue;
+        }
+        let (space, address) = if register & BAR_IO != 0 {
+            (Space::Io, (register & !0x3) as u64)
+        } else {
+            let upper = if register & BAR_MEM_64 != 0 {
+                registers.next().map(|(_, upper)| upper).unwrap_or_default()
+            } else {
+                0
+            };
+            let space = if register & BAR_MEM_PREFETCH != 0 {
+                Space::Prefetchable
+            } else {
+                Space::Memory
+            };
+            (space, (upper as u64) << 32 | (register & !0xf) as u64)
+        };
+        if address == 0 {
+            continue;
+        }
+        let size = device
+            .resource
+            .as_ref()
+            .and_then(|resource| resource.entries.get(index))
+            .map(|entry| entry.size())
+            .filter(|&size| size > 0)
+            .unwrap_or(1);
+        result.push(Bar {
+            index,
+            space,
+            span: Span {
+                start: address,
+                end: address + (size - 1),
+            },
+        });
+    }
+    result
+}
+
+/// All warnings in devices order
+pub fn check(topology: &Topology) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let devices = topology.devices();
+    for device in devices {
+        if let Some(buses) = bus_numbers(device) {
+            check_bridge(topology, device, buses, &mut warnings);
+        }
+        if let Some(parent) = topology.parent(device) {
+            check_bars(device, parent, &mut warnings);
+        }
+        for (capability, first) in cap_list::duplicates(device.raw_capabilities()) {
+            warnings.push(Warning::DuplicateCapability {
+                device: device.address.clone(),
+                capability,
+                first,
+            });
+        }
+    }
+    warnings
+}
+
+fn check_bridge(
+    topology: &Topology,
+    bridge: &Device,
+    (primary, secondary, subordinate): (u8, u8, u8),
+    warnings: &mut Vec<Warning>,
+) {
+    let address = &bridge.address;
+    if secondary <= primary || secondary > subordinate {
+        warnings.push(Warning::BusNumbers {
+            bridge: address.clone(),
+            primary,
+            secondary,
+            subordinate,
+        });
+    }
+    let windows = windows(bridge);
+    if let Some(parent) = topology.parent(bridge) {
+        if let Some((_, parent_secondary, parent_subordinate)) = bus_numbers(parent) {
+            if secondary < parent_secondary || subordinate > parent_subordinate {
+                warnings.push(Warning::BusOutsideParent {
+                    bridge: address.clone(),
+                    buses: (secondary, subordinate),
+                    parent: parent.address.clone(),
+                    parent_buses: (parent_secondary, parent_subordinate),
+                });
+            }
+        }
+        let parent_windows = self::windows(parent);
+        for &(space, span) in &windows {
+            if !forwarded(&parent_windows, space, &span) {
+                warnings.push(Warning::WindowOutsideParent {
+                    space,
+                    bridge: address.clone(),
+                    span,
+                    parent: parent.address.clone(),
+                });
+            }
+        }
+    }
+    // Each pair once: siblings ordered after the bridge
+    let siblings = topology.devices().iter().filter(|sibling| {
+        sibling.address > *address
+            && sibling.address.domain == address.domain
+            && sibling.address.bus == address.bus
+    });
+    for sibling in siblings {
+        let Some((_, sibling_secondary, sibling_subordinate)) = bus_numbers(sibling) else {
+            continue;
+        };
+        let bridges = (address.clone(), sibling.address.clone());
+        if secondary <= sibling_subordinate && sibling_secondary <= subordinate {
+            warnings.push(Warning::BusOverlap {
+                bridges: bridges.clone(),
+                buses: (
+                    (secondary, subordinate),
+                    (sibling_secondary, sibling_subordinate),
+                ),
+            });
+        }
+        for (space, span) in &windows {
+            for (sibling_space, sibling_span) in self::windows(sibling) {
+                if *space == sibling_space && span.overlaps(&sibling_span) {
+                    warnings.push(Warning::WindowOverlap {
+                        space: *space,
+                        bridges: bridges.clone(),
+                        spans: (*span, sibling_span),
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn check_bars(device: &Device, bridge: &Device, warnings: &mut Vec<Warning>) {
+    let windows = windows(bridge);
+    let command = &bridge.header.command;
+    let bars = bars(device);
+    for space in [Space::Io, Space::Memory] {
+        let decoded = match space {
+            Space::Io => command.io_space,
+            _ => command.memory_space,
+        };
+        let used = bars
+            .iter()
+            .any(|bar| (bar.space == Space::Io) == (space == Space::Io));
+        if used && !decoded {
+            warnings.push(Warning::DecodingDisabled {
+                device: device.address.clone(),
+                space,
+                bridge: bridge.address.clone(),
+            });
+        }
+    }
+    for bar in bars {
+        if !forwarded(&windows, bar.space, &bar.span) {
+            warnings.push(Warning::BarOutsideWindow {
+                device: device.address.clone(),
+                bar,
+                bridge: bridge.address.clone(),
+            });
+        }
+    }
+}
+
+/// Prefetchable range may be forwarded by non-prefetchable memory window
+fn forwarded(windows: &[(Space, Span)], space: Space, span: &Span) -> bool {
+    windows.iter().any(|(window_space, window)| {
+        let same_space = *window_space == space
+            || (space == Space::Prefetchable && *window_space == Space::Memory);
+        same_space && window.contains(span)
+    })
+}
+
+/// Primary, secondary and subordinate bus numbers of configured bridge
+fn bus_numbers(device: &Device) -> Option<(u8, u8, u8)> {
+    match device.header.header_type {
+        HeaderType::Bridge(Bridge {
+            primary_bus_number,
+            secondary_bus_number,
+            subordinate_bus_number,
+            ..
+        }) if (secondary_bus_number, subordinate_bus_number) != (0, 0) => Some((
+            primary_bus_number,
+            secondary_bus_number,
+            subordinate_bus_number,
+        )),
+        _ => None,
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BusNumbers {
+                bridge,
+                primary,
+                secondary,
+                subordinate,
+            } => write!(
+                f,
+                "{}: bus numbers primary={:02x} secondary={:02x} subordinate={:02x} are \
+                inconsistent, secondary must be above primary and not above subordinate",
+                bridge, primary, secondary, subordinate
+            ),
+            Self::BusOutsideParent {
+                bridge,
+                buses,
+                parent,
+                parent_buses,
+            } => write!(
+                f,
+                "{}: buses {:02x}-{:02x} are outside of {} buses {:02x}-{:02x}, devices below \
+                are unreachable",
+                bridge, buses.0, buses.1, parent, parent_buses.0, parent_buses.1
+            ),
+            Self::BusOverlap { bridges, buses } => write!(
+                f,
+                "{} and {}: buses {:02x}-{:02x} and {:02x}-{:02x} overlap, assign disjoint \
+                secondary-subordinate ranges",
+                bridges.0, bridges.1, buses.0 .0, buses.0 .1, buses.1 .0, buses.1 .1
+            ),
+            Self::WindowOverlap {
+                space,
+                bridges,
+                spans,
+            } => write!(
+                f,
+                "{} and {}: {} windows {} and {} overlap",
+                bridges.0, bridges.1, space, spans.0, spans.1
+            ),
+            Self::WindowOutsideParent {
+                space,
+                bridge,
+                span,
+                parent,
+            } => write!(
+                f,
+                "{}: {} window {} is not forwarded by {}, extend upstream window",
+                bridge, space, span, parent
+            ),
+            Self::DecodingDisabled {
+                device,
+                space,
+                bridge,
+            } => write!(
+                f,
+                "{}: {} BARs behind {} with {} decoding disabled in Command register",
+                device, space, bridge, space
+            ),
+            Self::BarOutsideWindow {
+                device,
+                bar,
+                bridge,
+            } => write!(
+                f,
+                "{}: BAR {} {} {} is outside of {} windows, device is unreachable",
+                device, bar.index, bar.space, bar.span, bridge
+            ),
+            Self::DuplicateCapability {
+                device,
+                capability,
+                first,
+            } => {
+                write!(f, "{}: ", device)?;
+                match (capability.name(), capability.is_extended()) {
+                    (Some(name), _) => write!(f, "{}", name)?,
+                    (None, false) => write!(f, "CAP_{:02x}", capability.id)?,
+                    (None, true) => write!(f, "ECAP_{:04x}", capability.id)?,
+                }
+                write!(
+                    f,
+                    " at {:#x} duplicates the one at {:#x}, firmware chains the capability \
+                    twice and drivers use the first one",
+                    capability.offset, first
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::ConfigurationSpace;
+    use alloc::string::{String, ToString};
+    use pretty_assertions::assert_eq;
+
+    /// Memory decoding bridge, windows are bases and limits in 1M units
+    fn bridge(address: &str, buses: [u8; 3], memory: (u16, u16), command: u8) -> Device {
+        let mut cs = [0u8; 64];
+        cs[0x04] = command;
+        cs[0x0e] = 0x01;
+        cs[0x18..0x1b].copy_from_slice(&buses);
+        // I/O and prefetchable windows disabled
+        cs[0x1c] = 0xf0;
+        cs[0x20..0x22].copy_from_slice(&(memory.0 << 4).to_le_bytes());
+        cs[0x22..0x24].copy_from_slice(&(memory.1 << 4).to_le_bytes());
+        cs[0x24..0x26].copy_from_slice(&0xfff0u16.to_le_bytes());
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        Device::new(address.parse().unwrap(), cs)
+    }
+
+    fn endpoint(address: &str, bar: u32) -> Device {
+        let mut cs = [0u8; 64];
+        cs[0x10..0x14].copy_from_slice(&bar.to_le_bytes());
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        Device::new(address.parse().unwrap(), cs)
+    }
+
+    #[test]
+    fn consistent() {
+        let devices = [
+            bridge("00:1c.0", [0, 1, 2], (0xb40, 0xb4f), 0x06),
+            bridge("01:00.0", [1, 2, 2], (0xb40, 0xb40), 0x06),
+            endpoint("02:00.0", 0xb4000000),
+        ];
+        assert_eq!(Vec::<Warning>::new(), check(&Topology::new(&devices)));
+    }
+
+    #[test]
+    fn warnings() {
+        let devices = [
+            bridge("00:1c.0", [0, 1, 2], (0xb40, 0xb4f), 0x00),
+            bridge("00:1d.0", [0, 2, 3], (0xb4f, 0xb5f), 0x06),
+            endpoint("01:00.0", 0xb4100000),
+            bridge("02:00.0", [2, 5, 4], (0xb60, 0xb60), 0x06),
+        ];
+        let result = check(&Topology::new(&devices))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let sample = [
+            "0000:00:1c.0 and 0000:00:1d.0: buses 01-02 and 02-03 overlap, assign disjoint secondary-subordinate ranges",
+            "0000:00:1c.0 and 0000:00:1d.0: memory windows b4000000-b4ffffff and b4f00000-b5ffffff overlap",
+            "0000:01:00.0: memory BARs behind 0000:00:1c.0 with memory decoding disabled in Command register",
+            "0000:02:00.0: bus numbers primary=02 secondary=05 subordinate=04 are inconsistent, secondary must be above primary and not above subordinate",
+            "0000:02:00.0: buses 05-04 are outside of 0000:00:1d.0 buses 02-03, devices below are unreachable",
+            "0000:02:00.0: memory window b6000000-b60fffff is not forwarded by 0000:00:1d.0, extend upstream window",
+        ]
+        .map(String::from);
+        assert_eq!(sample.to_vec(), result);
+    }
+
+    #[test]
+    fn bar_outside_window() {
+        let devices = [
+            bridge("00:1c.0", [0, 1, 1], (0xb40, 0xb40), 0x06),
+            endpoint("01:00.0", 0xb4100000),
+        ];
+        let result = check(&Topology::new(&devices));
+        let sample = Warning::BarOutsideWindow {
+            device: "01:00.0".parse().unwrap(),
+            bar: Bar {
+                index: 0,
+                space: Space::Memory,
+                span: Span {
+                    start: 0xb4100000,
+                    end: 0xb4100000,
+                },
+            },
+            bridge: "00:1c.0".parse().unwrap(),
+        };
+        assert_eq!(vec![sample], result);
+    }
+
+    #[test]
+    fn duplicate_capabilities() {
+        use crate::access::{dump::Dump, AccessMethod};
+        let dump = Dump::init(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/broken/duplicate-caps.xxxx.txt"
+        ))
+        .unwrap();
+        let devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let result = check(&Topology::new(&devices))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let sample = [
+            "0000:04:00.0: CAP_PM at 0xc0 duplicates the one at 0x80, firmware chains the \
+            capability twice and drivers use the first one",
+            "0000:04:00.0: ECAP_AER at 0x500 duplicates the one at 0x100, firmware chains the \
+            capability twice and drivers use the first one",
+        ];
+        assert_eq!(sample.to_vec(), result);
+    }
+}
@@ -0,0 +1,130 @@
+/*!
+# Persistent device names
+
+Identifier of a device that survives reboots and bus renumbering, for inventory databases.
+Location is the root device address followed by `/<device>.<function>` hops on secondary
+buses, the same syntax as [path selectors](super::select), so bus numbers assigned by firmware
+below the root bus do not leak into it. SMBIOS slot designation of the device or the nearest
+upstream bridge having one and PCI Express Device Serial Number are appended when present.
+
+```rust
+# use pcitool::{access::Access, topology::{naming::PersistentId, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+for device in &devices {
+    println!("{}", PersistentId::new(&topology, device));
+}
+```
+*/
+
+use core::fmt;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use super::Topology;
+use crate::device::{identifiers::Eui64, Device, Identifiers};
+
+/// Stable device identifier components
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PersistentId {
+    /// Root device address and hops below it, e.g. `0000:00:1c.0/00.0`
+    pub path: String,
+    /// SMBIOS slot designation, e.g. `PCIe Slot 3`
+    pub slot: Option<String>,
+    /// PCI Express Device Serial Number
+    pub dsn: Option<u64>,
+}
+
+impl PersistentId {
+    pub fn new<'a>(topology: &Topology<'a>, device: &'a Device) -> Self {
+        let path = topology.path(device);
+        let mut hops = path.iter();
+        let mut location = hops
+            .next()
+            .map(|root| root.address.to_string())
+            .unwrap_or_default();
+        for hop in hops {
+            location.push_str(&format!(
+                "/{:02x}.{:x}",
+                hop.address.device, hop.address.function
+            ));
+        }
+        // Functions behind a switch on an add-in card share the designation of its slot
+        let slot = path
+            .iter()
+            .rev()
+            .find_map(|device| device.slot_designation.clone());
+        Self {
+            path: location,
+            slot,
+            dsn: Identifiers::new(device).dsn,
+        }
+    }
+}
+
+/// Components joined by `+`: path, `slot-` with designation whitespace replaced by `_`, `dsn-`
+impl fmt::Display for PersistentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(slot) = &self.slot {
+            let slot = slot
+                .chars()
+                .map(|c| {
+                    if c.is_whitespace() || c == '+' {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect::<String>();
+            write!(f, "+slot-{}", slot)?;
+        }
+        if let Some(dsn) = self.dsn {
+            write!(f, "+dsn-{}", Eui64(dsn))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use alloc::{string::ToString, vec::Vec};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn persistent_id() {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let find = |devices: &[Device], address: &str| {
+            let address = address.parse().unwrap();
+            devices.iter().position(|d| d.address == address).unwrap()
+        };
+        let bridge = find(&devices, "00:1d.4");
+        devices[bridge].slot_designation = Some("M.2 Slot+1".into());
+        let topology = Topology::new(&devices);
+
+        let nvme = &devices[find(&devices, "04:00.0")];
+        let id = PersistentId::new(&topology, nvme);
+        assert_eq!(("0000:00:1d.4/00.0", None), (id.path.as_str(), id.dsn));
+        assert_eq!("0000:00:1d.4/00.0+slot-M.2_Slot_1", id.to_string());
+
+        let root = &devices[find(&devices, "00:1f.3")];
+        assert_eq!(
+            "0000:00:1f.3",
+            PersistentId::new(&topology, root).to_string()
+        );
+
+        let dsn = &devices[find(&devices, "03:00.0")];
+        let id = PersistentId::new(&topology, dsn);
+        assert_eq!(Some(0x00000001004ce000), id.dsn);
+        assert!(id
+            .to_string()
+            .ends_with("/00.0+dsn-00-00-00-01-00-4c-e0-00"));
+    }
+}
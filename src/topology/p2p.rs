@@ -0,0 +1,246 @@
+/*!
+# Peer-to-peer DMA path
+
+Path of peer-to-peer transactions between two devices: up from each device to the deepest
+common upstream bridge and down to the peer. ACS P2P Request/Completion Redirect and Egress
+Control enabled on any port below the common bridge send transactions up to the root complex,
+so they reach the peer only if the root complex routes them back (and IOMMU allows it). Without
+common bridge transactions always cross the root complex.
+
+```rust
+# use pcitool::{access::Access, topology::{p2p::P2pPath, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+if let [a, b, ..] = devices.as_slice() {
+    let path = P2pPath::new(&topology, a, b);
+    println!("{}", path.verdict());
+}
+```
+*/
+
+use core::fmt;
+
+use alloc::{string::String, vec::Vec};
+
+use pcics::extended_capabilities::ExtendedCapabilityKind;
+
+use super::Topology;
+use crate::device::Device;
+
+/// ACS controls redirecting peer-to-peer transactions upstream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AcsRedirect {
+    /// P2P Request Redirect Enable
+    pub request: bool,
+    /// P2P Completion Redirect Enable
+    pub completion: bool,
+    /// P2P Egress Control Enable
+    pub egress: bool,
+}
+
+/// Port with redirecting ACS controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Redirect<'a> {
+    pub device: &'a Device,
+    pub acs: AcsRedirect,
+}
+
+/// Whether peer-to-peer transactions go directly between devices
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// Routed by the common upstream bridge
+    Direct,
+    /// No common upstream bridge, depends on host bridge peer-to-peer support
+    RootComplex,
+    /// ACS redirects transactions to the root complex
+    Redirected,
+}
+
+/// Devices between two peers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct P2pPath<'a> {
+    /// Deepest bridge upstream of both devices
+    pub common: Option<&'a Device>,
+    /// From the first device up to, not including, the common bridge
+    pub a: Vec<&'a Device>,
+    /// From the second device up to, not including, the common bridge
+    pub b: Vec<&'a Device>,
+}
+
+impl AcsRedirect {
+    pub fn is_enabled(&self) -> bool {
+        self.request || self.completion || self.egress
+    }
+}
+
+impl Device {
+    /// ACS redirect controls, `None` if device has no ACS capability
+    pub fn acs_redirect(&self) -> Option<AcsRedirect> {
+        self.extended_capabilities()?
+            .flatten()
+            .find_map(|ecap| match ecap.kind {
+                ExtendedCapabilityKind::AccessControlServices(acs) => Some(AcsRedirect {
+                    request: acs.acs_control.acs_p2p_request_redirect_enable,
+                    completion: acs.acs_control.acs_p2p_completion_redirect_enable,
+                    egress: acs.acs_control.acs_p2p_egress_control_enable,
+                }),
+                _ => None,
+            })
+    }
+}
+
+impl<'a> P2pPath<'a> {
+    pub fn new(topology: &Topology<'a>, a: &'a Device, b: &'a Device) -> Self {
+        let path_a = topology.path(a);
+        let path_b = topology.path(b);
+        let shared = path_a
+            .iter()
+            .zip(&path_b)
+            .take_while(|(a, b)| a.address == b.address)
+            .count();
+        let below = |path: &[&'a Device]| path[shared..].iter().rev().copied().collect();
+        Self {
+            common: shared.checked_sub(1).map(|n| path_a[n]),
+            a: below(&path_a),
+            b: below(&path_b),
+        }
+    }
+    /// Devices below the common bridge with redirecting ACS controls
+    pub fn redirects(&self) -> Vec<Redirect<'a>> {
+        self.a
+            .iter()
+            .chain(&self.b)
+            .filter_map(|&device| {
+                let acs = device.acs_redirect().filter(AcsRedirect::is_enabled)?;
+                Some(Redirect { device, acs })
+            })
+            .collect()
+    }
+    pub fn verdict(&self) -> Verdict {
+        if !self.redirects().is_empty() {
+            Verdict::Redirected
+        } else if self.common.is_none() {
+            Verdict::RootComplex
+        } else {
+            Verdict::Direct
+        }
+    }
+    /// Linux `pci=disable_acs_redir=` parameter for redirecting ports
+    pub fn override_parameter(&self) -> Option<String> {
+        let redirects = self.redirects();
+        if redirects.is_empty() {
+            return None;
+        }
+        let addresses = redirects
+            .iter()
+            .map(|r| alloc::format!("{}", r.device.address))
+            .collect::<Vec<_>>();
+        Some(alloc::format!(
+            "pci=disable_acs_redir={}",
+            addresses.join(";")
+        ))
+    }
+}
+
+/// Enabled controls named as in lspci `ACSCtl`
+impl fmt::Display for AcsRedirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (self.request, "ReqRedir"),
+            (self.completion, "CmpltRedir"),
+            (self.egress, "EgressCtrl"),
+        ];
+        let mut sep = "";
+        for (_, name) in names.iter().filter(|(enabled, _)| *enabled) {
+            write!(f, "{}{}+", sep, name)?;
+            sep = " ";
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Direct => write!(f, "direct, routed by common upstream bridge"),
+            Self::RootComplex => write!(
+                f,
+                "through root complex, depends on host bridge peer-to-peer support"
+            ),
+            Self::Redirected => write!(f, "blocked, ACS redirects transactions to root complex"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use crate::device::ExtendedConfigurationSpace;
+    use pretty_assertions::assert_eq;
+
+    fn devices() -> Vec<Device> {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices
+    }
+
+    fn find<'a>(devices: &'a [Device], address: &str) -> &'a Device {
+        let address = address.parse().unwrap();
+        devices.iter().find(|d| d.address == address).unwrap()
+    }
+
+    #[test]
+    fn root_complex() {
+        let devices = devices();
+        let topology = Topology::new(&devices);
+        let path = P2pPath::new(
+            &topology,
+            find(&devices, "02:00.0"),
+            find(&devices, "04:00.0"),
+        );
+        assert_eq!(None, path.common);
+        assert_eq!(2, path.a.len());
+        assert_eq!(Verdict::RootComplex, path.verdict());
+        assert_eq!(None, path.override_parameter());
+        let path = P2pPath::new(
+            &topology,
+            find(&devices, "04:00.0"),
+            find(&devices, "00:1d.4"),
+        );
+        assert_eq!(
+            Some("00:1d.4".parse().unwrap()),
+            path.common.map(|d| d.address.clone())
+        );
+        assert_eq!(Verdict::Direct, path.verdict());
+    }
+
+    #[test]
+    fn redirected() {
+        let mut devices = devices();
+        let port = devices
+            .iter_mut()
+            .find(|d| d.address == "00:1d.4".parse().unwrap())
+            .unwrap();
+        let offset = port.extended_capability_offset(0x000d).unwrap();
+        let ecs = port.extended_configuration_space.as_mut().unwrap();
+        // ACS Control: P2P Request Redirect Enable, P2P Completion Redirect Enable
+        ecs.0[offset - ExtendedConfigurationSpace::OFFSET + 6] |= 0b1100;
+        let topology = Topology::new(&devices);
+        let path = P2pPath::new(
+            &topology,
+            find(&devices, "02:00.0"),
+            find(&devices, "04:00.0"),
+        );
+        let redirects = path.redirects();
+        assert_eq!(1, redirects.len());
+        assert_eq!("ReqRedir+ CmpltRedir+", redirects[0].acs.to_string());
+        assert_eq!(Verdict::Redirected, path.verdict());
+        assert_eq!(
+            Some("pci=disable_acs_redir=0000:00:1d.4".into()),
+            path.override_parameter()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+/*!
+# Bridge path selection
+
+lspci `-s` slot filter extended with a path below bridges: `/`-separated `<device>.<function>`
+hops on secondary buses of the bridges matched before. Trailing `/` selects every device below
+the last bridge, e.g. `00:1c.0/` is everything behind root port `00:1c.0` and `00:1c.0/00.0/`
+is everything behind the switch upstream port plugged into it. Hops do not depend on bus
+numbers assigned by firmware.
+
+```rust
+# use pcitool::{access::Access, topology::{select::Selector, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+let selector: Selector = "00:1c.0/".parse().unwrap();
+for device in devices.iter().filter(|device| selector.matches(&topology, device)) {
+    println!("{}", device.address);
+}
+```
+*/
+
+use core::str::FromStr;
+
+use alloc::vec::Vec;
+
+use super::Topology;
+use crate::device::{
+    address::{ParseAddressError, SlotFilter},
+    Device,
+};
+
+/// Slot filter with optional path below matched bridges
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Selector {
+    pub slot: SlotFilter,
+    /// Device and function filters on consecutive secondary buses
+    pub hops: Vec<SlotFilter>,
+    /// Select all devices below the last hop instead of the last hop itself
+    pub subtree: bool,
+}
+
+impl Selector {
+    pub fn matches<'a>(&self, topology: &Topology<'a>, device: &'a Device) -> bool {
+        if self.hops.is_empty() && !self.subtree {
+            return self.slot.matches(&device.address);
+        }
+        let path = topology.path(device);
+        // Device itself is the last hop or lies below it
+        let depth = self.hops.len() + 1;
+        path.iter().enumerate().any(|(n, bridge)| {
+            let below = path.len() - n;
+            let depth_matches = if self.subtree {
+                below > depth
+            } else {
+                below == depth
+            };
+            depth_matches
+                && self.slot.matches(&bridge.address)
+                && self
+                    .hops
+                    .iter()
+                    .zip(&path[n + 1..])
+                    .all(|(hop, device)| hop.matches(&device.address))
+        })
+    }
+}
+
+/// `<slot filter>[/<device>.<function>]...[/]`
+impl FromStr for Selector {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        let slot = parts.next().unwrap_or_default().parse()?;
+        let mut hops = parts.collect::<Vec<_>>();
+        let subtree = hops.last() == Some(&"");
+        if subtree {
+            hops.pop();
+        }
+        let hops = hops
+            .into_iter()
+            .map(|hop| {
+                // Bus numbers below bridge are implied by the path
+                if hop.contains(':') {
+                    Err(ParseAddressError::PathHop(hop.into()))
+                } else {
+                    hop.parse()
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            slot,
+            hops,
+            subtree,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    fn select(s: &str) -> Vec<String> {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        let topology = Topology::new(&devices);
+        let selector: Selector = s.parse().unwrap();
+        devices
+            .iter()
+            .filter(|device| selector.matches(&topology, device))
+            .map(|device| format!("{:#}", device.address))
+            .collect()
+    }
+
+    #[test]
+    fn bridge_path() {
+        assert_eq!(vec!["00:1d.4"], select("1d.4"));
+        assert_eq!(vec!["04:00.0"], select("00:1d.4/"));
+        assert_eq!(vec!["04:00.0"], select("00:1d.4/00.0"));
+        assert_eq!(Vec::<String>::new(), select("00:1d.4/00.1"));
+        assert_eq!(Vec::<String>::new(), select("00:1d.4/00.0/"));
+        assert_eq!(vec!["02:00.0"], select("00:1c.*/"));
+        assert_eq!(vec!["02:00.0", "03:00.0", "04:00.0"], select("*/"));
+        assert_eq!(
+            Err(ParseAddressError::PathHop("04:00.0".into())),
+            "00:1d.4/04:00.0".parse::<Selector>()
+        );
+    }
+}
@@ -0,0 +1,213 @@
+/*!
+# Bridge windows usage
+
+Space of bridge windows claimed by devices on the secondary bus: BARs of children and windows
+of child bridges. Prefetchable BARs and windows outside the prefetchable window are forwarded by
+the non-prefetchable memory window. The rest of a window is free for hot-plugged devices or
+BARs resizing.
+
+BAR sizes are known only if OS reports resources, otherwise BAR claims a single address.
+
+```rust
+# use pcitool::{access::Access, topology::{usage, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+for bridge in devices.iter().filter(|device| device.bridge().is_some()) {
+    for window in usage::usage(&topology, bridge) {
+        println!("{} {}: {} free", bridge.address, window.window, window.free_size());
+    }
+}
+```
+*/
+
+use alloc::vec::Vec;
+
+use super::{lint, Topology};
+use crate::device::{
+    bridge::{Space, Span, Window, Windows},
+    Device,
+};
+
+/// What claims window space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim {
+    /// BAR index
+    Bar(usize),
+    /// Window of child bridge
+    Window(Space),
+}
+
+/// Window space claimed by device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation<'a> {
+    pub device: &'a Device,
+    pub claim: Claim,
+    pub span: Span,
+}
+
+/// Enabled bridge window with claims sorted by address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowUsage<'a> {
+    pub window: Window,
+    pub allocations: Vec<Allocation<'a>>,
+}
+
+impl<'a> WindowUsage<'a> {
+    /// Claimed addresses, overlapping claims are counted once
+    pub fn allocated_size(&self) -> u64 {
+        self.window.span.size() - self.free_size()
+    }
+    pub fn free_size(&self) -> u64 {
+        self.free().iter().map(Span::size).sum()
+    }
+    /// Unclaimed ranges of the window
+    pub fn free(&self) -> Vec<Span> {
+        let window = self.window.span;
+        let mut free = Vec::new();
+        let mut next = Some(window.start);
+        for span in self.allocations.iter().map(|a| a.span) {
+            let Some(start) = next else { break };
+            if span.start > start {
+                free.push(Span {
+                    start,
+                    end: (span.start - 1).min(window.end),
+                });
+            }
+            if span.end >= start {
+                next = span.end.checked_add(1);
+            }
+        }
+        if let Some(start) = next.filter(|&start| start <= window.end) {
+            free.push(Span {
+                start,
+                end: window.end,
+            });
+        }
+        free
+    }
+}
+
+/// Claims of devices on the secondary bus of each enabled bridge window
+pub fn usage<'a>(topology: &Topology<'a>, bridge: &'a Device) -> Vec<WindowUsage<'a>> {
+    let Some(header) = bridge.bridge() else {
+        return Vec::new();
+    };
+    let claims = topology
+        .children(bridge)
+        .flat_map(|device| {
+            let bars = lint::bars(device)
+                .into_iter()
+                .map(move |bar| (device, Claim::Bar(bar.index), bar.space, bar.span));
+            let windows = lint::windows(device)
+                .into_iter()
+                .map(move |(space, span)| (device, Claim::Window(space), space, span));
+            bars.chain(windows)
+        })
+        .collect::<Vec<_>>();
+    let windows = header.windows();
+    // Prefetchable claims outside prefetchable window are forwarded as non-prefetchable
+    let effective = |space: Space, span: &Span| {
+        let prefetchable = windows
+            .iter()
+            .any(|w| w.enabled && w.space == Space::Prefetchable && w.span.contains(span));
+        if space == Space::Prefetchable && !prefetchable {
+            Space::Memory
+        } else {
+            space
+        }
+    };
+    windows
+        .iter()
+        .filter(|window| window.enabled)
+        .map(|window| {
+            let mut allocations = claims
+                .iter()
+                .filter(|(_, _, space, span)| {
+                    effective(*space, span) == window.space && window.span.overlaps(span)
+                })
+                .map(|&(device, claim, _, span)| Allocation {
+                    device,
+                    claim,
+                    span,
+                })
+                .collect::<Vec<_>>();
+            allocations.sort_by_key(|a| (a.span.start, a.span.end));
+            WindowUsage {
+                window: *window,
+                allocations,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{ConfigurationSpace, Resource, ResourceEntry};
+    use pretty_assertions::assert_eq;
+
+    fn device(address: &str, data: &[(usize, &[u8])]) -> Device {
+        let mut cs = [0u8; 64];
+        for (offset, bytes) in data {
+            cs[*offset..*offset + bytes.len()].copy_from_slice(bytes);
+        }
+        let cs: ConfigurationSpace = cs.as_slice().try_into().unwrap();
+        Device::new(address.parse().unwrap(), cs)
+    }
+
+    #[test]
+    fn free_space() {
+        // Memory window b4000000-b41fffff
+        let bridge = device(
+            "00:1c.0",
+            &[
+                (0x0e, &[0x01]),
+                (0x18, &[0, 1, 1]),
+                (0x20, &[0x00, 0xb4, 0x10, 0xb4]),
+            ],
+        );
+        // 16K BAR 0 at b4100000
+        let mut endpoint = device("01:00.0", &[(0x10, &[0x00, 0x00, 0x10, 0xb4])]);
+        endpoint.resource = Some(Resource {
+            entries: [
+                (0xb4100000, 0xb4103fff, 0x200),
+                (0, 0, 0),
+                (0, 0, 0),
+                (0, 0, 0),
+                (0, 0, 0),
+                (0, 0, 0),
+            ]
+            .map(|(start, end, flags)| ResourceEntry { start, end, flags }),
+            rom_entry: ResourceEntry {
+                start: 0,
+                end: 0,
+                flags: 0,
+            },
+            iov_entries: Default::default(),
+        });
+        let devices = [bridge, endpoint];
+        let topology = Topology::new(&devices);
+        let result = usage(&topology, &devices[0]);
+        assert_eq!(1, result.len());
+        let memory = &result[0];
+        let claims = memory
+            .allocations
+            .iter()
+            .map(|a| a.claim)
+            .collect::<Vec<_>>();
+        assert_eq!(vec![Claim::Bar(0)], claims);
+        assert_eq!(0x4000, memory.allocated_size());
+        let sample = vec![
+            Span {
+                start: 0xb4000000,
+                end: 0xb40fffff,
+            },
+            Span {
+                start: 0xb4104000,
+                end: 0xb41fffff,
+            },
+        ];
+        assert_eq!(sample, memory.free());
+    }
+}
@@ -0,0 +1,259 @@
+/*!
+# Legacy VGA routing
+
+Legacy VGA memory (`a0000h-bffffh`) and I/O ranges are not covered by bridge windows, each
+bridge forwards them downstream only with VGA Enable set in Bridge Control, VGA 16-bit Decode
+selects whether I/O aliases are decoded with 10 or 16 address bits. Firmware sets these bits
+along the path to the boot VGA device, which OS reports as `boot_vga`. [BootVga] finds the
+primary display device from it or, without OS information, from routing and decoding state.
+
+```rust
+# use pcitool::{access::Access, topology::{vga::VgaRoute, Topology}};
+let access = Access::init().unwrap();
+let devices: Vec<_> = access.iter().filter_map(Result::ok).collect();
+let topology = Topology::new(&devices);
+for route in VgaRoute::all(&topology) {
+    println!("{} routed: {}", route.device.address, route.is_routed());
+}
+```
+*/
+
+use core::fmt;
+
+use alloc::vec::Vec;
+
+use pcics::header::{Bridge, HeaderType};
+
+use super::Topology;
+use crate::device::Device;
+
+/// Bridge with its legacy VGA forwarding controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VgaBridge<'a> {
+    pub device: &'a Device,
+    /// VGA Enable
+    pub vga_enable: bool,
+    /// VGA 16-bit Decode
+    pub vga_16_enable: bool,
+}
+
+/// How [BootVga] device was chosen, from the most to the least reliable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootVgaSource {
+    /// OS reported `boot_vga`
+    Os,
+    /// VGA compatible controller with legacy VGA routed and I/O and memory decoding enabled
+    Routed,
+    /// Display controller with memory decoding enabled
+    Decoding,
+}
+
+/// Primary display device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootVga<'a> {
+    pub device: &'a Device,
+    pub source: BootVgaSource,
+}
+
+/// Bridges between VGA compatible controller and its root bus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VgaRoute<'a> {
+    pub device: &'a Device,
+    /// From the topmost bridge down to the device parent, empty on a root bus
+    pub bridges: Vec<VgaBridge<'a>>,
+}
+
+impl<'a> VgaBridge<'a> {
+    /// `None` if device is not a PCI-to-PCI bridge
+    pub fn new(device: &'a Device) -> Option<Self> {
+        match &device.header.header_type {
+            HeaderType::Bridge(Bridge { bridge_control, .. }) => Some(Self {
+                device,
+                vga_enable: bridge_control.vga_enable,
+                vga_16_enable: bridge_control.vga_16_enable,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Address with `VGA+`/`VGA-` and `VGA16+`/`VGA16-` like lspci bridge control flags
+impl fmt::Display for VgaBridge<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |enabled| if enabled { '+' } else { '-' };
+        write!(
+            f,
+            "{} [VGA{} VGA16{}]",
+            self.device.address,
+            flag(self.vga_enable),
+            flag(self.vga_16_enable)
+        )
+    }
+}
+
+impl<'a> VgaRoute<'a> {
+    pub fn new(topology: &Topology<'a>, device: &'a Device) -> Self {
+        let mut path = topology.path(device);
+        path.pop();
+        Self {
+            device,
+            bridges: path.into_iter().filter_map(VgaBridge::new).collect(),
+        }
+    }
+    /// Routes of every VGA compatible controller
+    pub fn all(topology: &Topology<'a>) -> Vec<Self> {
+        topology
+            .devices()
+            .iter()
+            .filter(|device| device.is_vga())
+            .map(|device| Self::new(topology, device))
+            .collect()
+    }
+    /// Every bridge on the path forwards legacy VGA ranges
+    pub fn is_routed(&self) -> bool {
+        self.blocked_at().is_none()
+    }
+    /// Topmost bridge not forwarding legacy VGA ranges
+    pub fn blocked_at(&self) -> Option<&VgaBridge<'a>> {
+        self.bridges.iter().find(|bridge| !bridge.vga_enable)
+    }
+    /// Firmware initialized the device as the boot VGA device
+    pub fn is_boot(&self) -> bool {
+        self.device.boot_vga == Some(true)
+    }
+}
+
+impl<'a> BootVga<'a> {
+    /// Device marked as boot VGA by OS, otherwise the first one matching heuristics similar to
+    /// Linux VGA arbiter default device selection
+    pub fn find(topology: &Topology<'a>) -> Option<Self> {
+        let devices = topology.devices();
+        let found = |device, source| Some(Self { device, source });
+        if let Some(device) = devices.iter().find(|device| device.boot_vga == Some(true)) {
+            return found(device, BootVgaSource::Os);
+        }
+        let routed = VgaRoute::all(topology).into_iter().find(|route| {
+            let command = &route.device.header.command;
+            route.is_routed() && command.io_space && command.memory_space
+        });
+        if let Some(route) = routed {
+            return found(route.device, BootVgaSource::Routed);
+        }
+        devices
+            .iter()
+            .find(|device| {
+                device.header.class_code.base == 0x03 && device.header.command.memory_space
+            })
+            .and_then(|device| found(device, BootVgaSource::Decoding))
+    }
+}
+
+impl fmt::Display for BootVgaSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Os => write!(f, "boot_vga"),
+            Self::Routed => write!(f, "legacy VGA routed and decoded"),
+            Self::Decoding => write!(f, "display controller decoding memory"),
+        }
+    }
+}
+
+impl Device {
+    /// Display controller, VGA compatible subclass
+    pub fn is_vga(&self) -> bool {
+        let cc = &self.header.class_code;
+        (cc.base, cc.sub) == (0x03, 0x00)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use alloc::{string::ToString, vec};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn vga_routes() {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let find = |devices: &[Device], address: &str| {
+            let address = address.parse().unwrap();
+            devices.iter().position(|d| d.address == address).unwrap()
+        };
+        // Pretend NVMe SSD behind root port 00:1d.4 is a discrete GPU
+        let igpu = find(&devices, "00:02.0");
+        devices[igpu].boot_vga = Some(true);
+        let dgpu = find(&devices, "04:00.0");
+        devices[dgpu].header.class_code.base = 0x03;
+        devices[dgpu].header.class_code.sub = 0x00;
+
+        let topology = Topology::new(&devices);
+        let routes = VgaRoute::all(&topology);
+        let result = routes
+            .iter()
+            .map(|route| {
+                let bridges = route
+                    .bridges
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                (route.device.address.to_string(), route.is_boot(), bridges)
+            })
+            .collect::<Vec<_>>();
+        let sample = vec![
+            ("0000:00:02.0".to_string(), true, vec![]),
+            (
+                "0000:04:00.0".to_string(),
+                false,
+                vec!["0000:00:1d.4 [VGA- VGA16+]".to_string()],
+            ),
+        ];
+        assert_eq!(sample, result);
+        assert!(routes[0].is_routed());
+        assert_eq!(
+            Some(&devices[find(&devices, "00:1d.4")].address),
+            routes[1].blocked_at().map(|bridge| &bridge.device.address)
+        );
+
+        let root_port = find(&devices, "00:1d.4");
+        if let HeaderType::Bridge(bridge) = &mut devices[root_port].header.header_type {
+            bridge.bridge_control.vga_enable = true;
+        }
+        let topology = Topology::new(&devices);
+        assert!(VgaRoute::new(&topology, &devices[dgpu]).is_routed());
+    }
+
+    #[test]
+    fn boot_vga() {
+        let dump = Dump::init(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/machine/ec8a5fc/out.xxxx.txt"
+        ))
+        .unwrap();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        let source = |devices: &[Device]| {
+            let boot_vga = BootVga::find(&Topology::new(devices))?;
+            Some((boot_vga.device.address.to_string(), boot_vga.source))
+        };
+        let bmc = Some(("0000:03:00.0".to_string(), BootVgaSource::Routed));
+        assert_eq!(bmc, source(&devices));
+
+        // Legacy VGA routing disabled, e.g. by OS arbiter
+        let bridge = devices
+            .iter()
+            .position(|d| d.address.to_string() == "0000:02:00.0")
+            .unwrap();
+        if let HeaderType::Bridge(bridge) = &mut devices[bridge].header.header_type {
+            bridge.bridge_control.vga_enable = false;
+        }
+        let bmc = Some(("0000:03:00.0".to_string(), BootVgaSource::Decoding));
+        assert_eq!(bmc, source(&devices));
+
+        let last = devices.len() - 1;
+        devices[last].boot_vga = Some(true);
+        let os = Some((devices[last].address.to_string(), BootVgaSource::Os));
+        assert_eq!(os, source(&devices));
+        assert_eq!(None, source(&[]));
+    }
+}
@@ -1,6 +1,12 @@
 use std::fmt;
 
+pub mod color;
+#[cfg(feature = "serde")]
+pub mod json;
 pub mod lspci;
+pub mod render;
+pub mod table;
+pub mod template;
 
 /// Struct that has arbitrary [fmt::Display] implementations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -0,0 +1,157 @@
+/*!
+# Colorized output
+
+Decorator over plain text views. Inner view is rendered as usual and then
+decorated line by line with ANSI escape sequences, so plain output is never
+affected:
+- terse device line: class and vendor/device names
+- status registers: error flags set and degraded links
+*/
+
+use std::fmt;
+
+use crate::device::Address;
+
+/// ANSI SGR sequences
+pub mod ansi {
+    pub const RESET: &str = "\x1b[0m";
+    pub const CLASS: &str = "\x1b[33m";
+    pub const NAME: &str = "\x1b[32m";
+    pub const ERROR: &str = "\x1b[1;31m";
+}
+
+/// Flags that signal a problem when set (`Flag+`)
+const ERROR_FLAGS: [&str; 13] = [
+    "ParErr",
+    ">TAbort",
+    "<TAbort",
+    "<MAbort",
+    ">SERR",
+    "<SERR",
+    "<PERR",
+    "CorrErr",
+    "NonFatalErr",
+    "FatalErr",
+    "UnsupReq",
+    "DiscTmrStat",
+    "MasterAbort",
+];
+
+/// Any [fmt::Display] colorized with ANSI escape sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colored<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Colored<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.0.to_string();
+        for line in s.split_inclusive('\n') {
+            let (line, eol) = match line.strip_suffix('\n') {
+                Some(line) => (line, "\n"),
+                None => (line, ""),
+            };
+            if line.starts_with('\t') {
+                status_line(f, line)?;
+            } else {
+                device_line(f, line)?;
+            }
+            f.write_str(eol)?;
+        }
+        Ok(())
+    }
+}
+
+/// `<address> <class>: <names> (rev xx) (prog-if xx ...)`
+fn device_line(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    let parsed = line.split_once(' ').and_then(|(address, rest)| {
+        address.parse::<Address>().ok()?;
+        let (class, names) = rest.split_once(": ")?;
+        Some((address, class, names))
+    });
+    let Some((address, class, names)) = parsed else {
+        return f.write_str(line);
+    };
+    let (names, tail) = [" (rev ", " (prog-if "]
+        .iter()
+        .filter_map(|pat| names.find(pat))
+        .min()
+        .map(|n| names.split_at(n))
+        .unwrap_or((names, ""));
+    write!(
+        f,
+        "{} {}{}{}: {}{}{}{}",
+        address,
+        ansi::CLASS,
+        class,
+        ansi::RESET,
+        ansi::NAME,
+        names,
+        ansi::RESET,
+        tail
+    )
+}
+
+/// Highlight error flags in `*Sta*`/`Status` lines and degraded links
+fn status_line(f: &mut fmt::Formatter<'_>, line: &str) -> fmt::Result {
+    let label = line.trim_start().split(':').next().unwrap_or_default();
+    if !label.contains("Sta") {
+        return f.write_str(line);
+    }
+    // AER status registers consist of error bits only
+    let all_errors = matches!(label, "UESta" | "CESta");
+    let mut rest = line;
+    while !rest.is_empty() {
+        let end = match rest.find(|c: char| c.is_whitespace() || c == ',') {
+            // Separator itself
+            Some(0) => rest.chars().next().map_or(1, char::len_utf8),
+            Some(n) => n,
+            None => rest.len(),
+        };
+        let (token, tail) = rest.split_at(end);
+        let is_error = match token.strip_suffix('+') {
+            Some(flag) => all_errors || ERROR_FLAGS.contains(&flag),
+            None => token == "(downgraded)",
+        };
+        if is_error {
+            write!(f, "{}{}{}", ansi::ERROR, token, ansi::RESET)?;
+        } else {
+            f.write_str(token)?;
+        }
+        rest = tail;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn device_line() {
+        let s = "00:1f.3 Audio device: Intel Corporation Device 9dc8 (rev 30) (prog-if 80)\n";
+        let result = Colored(s).to_string();
+        let sample = "00:1f.3 \x1b[33mAudio device\x1b[0m: \
+            \x1b[32mIntel Corporation Device 9dc8\x1b[0m (rev 30) (prog-if 80)\n";
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn status_flags() {
+        let s = "\tStatus: Cap+ 66MHz- >TAbort- <MAbort+ >SERR- INTx-\n\
+            \t\tLnkSta:\tSpeed 2.5GT/s (downgraded), Width x1\n\
+            \t\tUESta:\tDLP- SDES+ TLP-\n\
+            \t\tLnkCtl:\tASPM L1 Enabled; CorrErr+\n";
+        let result = Colored(s).to_string();
+        let sample = "\tStatus: Cap+ 66MHz- >TAbort- \x1b[1;31m<MAbort+\x1b[0m >SERR- INTx-\n\
+            \t\tLnkSta:\tSpeed 2.5GT/s \x1b[1;31m(downgraded)\x1b[0m, Width x1\n\
+            \t\tUESta:\tDLP- \x1b[1;31mSDES+\x1b[0m TLP-\n\
+            \t\tLnkCtl:\tASPM L1 Enabled; CorrErr+\n";
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn untouched() {
+        let s = "Unable to read 00:00.0\n\tKernel driver in use: snd_hda_intel";
+        assert_eq!(s, Colored(s).to_string());
+    }
+}
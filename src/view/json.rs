@@ -0,0 +1,169 @@
+/*!
+# Structured data model
+
+Serializable device records for JSON, YAML and other serde formats. Identifiers are hex strings
+as in lspci output, missing values are omitted.
+*/
+
+use serde::Serialize;
+
+use crate::{
+    device::{link, Device, LinkInfo},
+    names::{ClassCode, VendorDeviceSubsystem},
+};
+
+/// Device summary
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    pub address: String,
+    pub status: Status,
+    pub class: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+    pub vendor_id: String,
+    pub device_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    pub revision: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numa_node: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iommu_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<LinkRecord>,
+    /// AER is handled by platform firmware, omitted without AER capability or readable HEST
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aer_firmware_first: Option<bool>,
+    /// SMBIOS System Slots designation of the chassis slot holding the device
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slot_designation: Option<String>,
+    /// Firmware initialized the device as the boot VGA device, VGA controllers only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub boot_vga: Option<bool>,
+}
+
+/// Devices listed under a common key
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GroupRecord {
+    /// Class code, driver or IOMMU group, `null` for devices without the value
+    pub key: Option<String>,
+    pub heading: String,
+    pub devices: Vec<Record>,
+}
+
+/// Device health as seen from configuration space
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Ok,
+    /// Configuration space reads as all ones, other fields are not meaningful
+    NotResponding,
+}
+
+/// PCI Express link with bandwidth in Gb/s per direction
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkRecord {
+    pub speed_gts: f64,
+    pub width: u8,
+    pub max_speed_gts: f64,
+    pub max_width: u8,
+    pub bandwidth_gbps: f64,
+    pub max_bandwidth_gbps: f64,
+    pub downgraded: bool,
+}
+
+impl Record {
+    pub fn new(device: &Device, vds: &VendorDeviceSubsystem, cc: &ClassCode) -> Self {
+        let header = &device.header;
+        let (vendor_id, device_id) = (header.vendor_id, header.device_id);
+        let class_code = &header.class_code;
+        Self {
+            address: device.address.to_string(),
+            status: if device.is_not_responding() {
+                Status::NotResponding
+            } else {
+                Status::Ok
+            },
+            class: format!("{:02x}{:02x}", class_code.base, class_code.sub),
+            class_name: cc
+                .lookup(class_code.base, class_code.sub, None)
+                .map(String::from),
+            vendor_id: format!("{:04x}", vendor_id),
+            device_id: format!("{:04x}", device_id),
+            vendor: vds.lookup(vendor_id, None, None).map(String::from),
+            device: vds.lookup(vendor_id, device_id, None).map(String::from),
+            revision: format!("{:02x}", header.revision_id),
+            driver: device.driver_in_use.clone(),
+            numa_node: device.numa_node,
+            iommu_group: device.iommu_group.clone(),
+            link: device.link_info().as_ref().map(LinkRecord::from),
+            aer_firmware_first: device.aer_firmware_first,
+            slot_designation: device.slot_designation.clone(),
+            boot_vga: device.boot_vga,
+        }
+    }
+}
+
+impl From<&LinkInfo> for LinkRecord {
+    fn from(info: &LinkInfo) -> Self {
+        // Two decimal places are more than enough for Gb/s
+        let round = |v: f64| (v * 100.0).round() / 100.0;
+        Self {
+            speed_gts: link::speed_gts(info.speed),
+            width: link::lanes(&info.width),
+            max_speed_gts: link::speed_gts(info.max_speed),
+            max_width: link::lanes(&info.max_width),
+            bandwidth_gbps: round(info.bandwidth_gbps()),
+            max_bandwidth_gbps: round(info.max_bandwidth_gbps()),
+            downgraded: info.is_downgraded(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn link_record() {
+        let dump = Dump::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let record = Record::new(&device, &Default::default(), &Default::default());
+        let sample = LinkRecord {
+            speed_gts: 8.0,
+            width: 4,
+            max_speed_gts: 8.0,
+            max_width: 4,
+            bandwidth_gbps: 31.51,
+            max_bandwidth_gbps: 31.51,
+            downgraded: false,
+        };
+        assert_eq!(Some(sample), record.link);
+        assert_eq!("0108", record.class);
+        assert_eq!(None, record.driver);
+        assert_eq!(Status::Ok, record.status);
+    }
+
+    #[test]
+    fn not_responding() {
+        let data = "\
+            00:1f.4 SMBus: Device ffff:ffff (rev ff)\n\
+            00: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            10: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            20: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            30: ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff\n\
+            \n\
+        ";
+        let device = Dump::new(data).device("00:1f.4".parse().unwrap()).unwrap();
+        let record = Record::new(&device, &Default::default(), &Default::default());
+        assert_eq!(Status::NotResponding, record.status);
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!("not-responding", json["status"]);
+    }
+}
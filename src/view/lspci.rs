@@ -1 +1,66 @@
+use std::io;
+
+use crate::device::Device;
+
 pub mod basic;
+pub mod machine;
+
+/// Write lspci listing of devices to `w` device by device, whole output is never built in memory
+pub fn render_to(
+    w: &mut impl io::Write,
+    devices: impl IntoIterator<Item = Device>,
+    args: &basic::ViewArgs,
+) -> io::Result<()> {
+    for data in devices {
+        write!(w, "{}", basic::View { data, args })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        device::ConfigurationSpace,
+        names::Names,
+        view::lspci::basic::{View, ViewArgs},
+    };
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn render_listing() {
+        let data = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/config"
+        ));
+        let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+        let devices =
+            ["00:1f.3", "01:00.0"].map(|address| Device::new(address.parse().unwrap(), cs.clone()));
+        let names = Names::default();
+        let args = &ViewArgs {
+            verbose: 1,
+            kernel: false,
+            always_domain_number: false,
+            as_numbers: 2,
+            bus_centric: false,
+            show_power: false,
+            name_width: Default::default(),
+            vds: &names.vendor_device_subsystem(),
+            cc: &names.class_code(),
+            access: &Default::default(),
+        };
+        let sample = devices
+            .iter()
+            .map(|data| {
+                View {
+                    data: data.clone(),
+                    args,
+                }
+                .to_string()
+            })
+            .collect::<String>();
+        let mut result = Vec::new();
+        render_to(&mut result, devices, args).unwrap();
+        assert_str_eq!(sample, String::from_utf8(result).unwrap());
+    }
+}
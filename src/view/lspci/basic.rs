@@ -1,14 +1,18 @@
 use core::fmt;
 
 use pcics::{
-    capabilities::CapabilityKind,
+    capabilities::{power_management_interface::PowerState, CapabilityKind},
     header::{
         self, Bridge, BridgeIoAddressRange, BridgePrefetchableMemory, Cardbus, ClassCode, Command,
         Header, HeaderType, InterruptPin, IoAccessAddressRange, Normal,
     },
 };
 
-use crate::{access::Access, device::Device, names};
+use crate::{
+    access::Access,
+    device::{power::power_state_name, Device, ECS_OFFSET},
+    names,
+};
 
 mod caps;
 mod ecaps;
@@ -55,16 +59,66 @@ pub struct ViewArgs<'a> {
     pub kernel: bool,
     pub always_domain_number: bool,
     pub as_numbers: usize,
+    /// Show IRQ and addresses as seen by the bus instead of OS-translated values
+    pub bus_centric: bool,
+    /// Annotate terse line with current D-state of devices not in D0
+    pub show_power: bool,
+    pub name_width: NameWidth,
     pub vds: &'a names::VendorDeviceSubsystem,
     pub cc: &'a names::ClassCode,
     pub access: &'a Access,
 }
 
+/// Truncation of class, vendor, device and subsystem names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameWidth {
+    /// Cut to lspci buffers: 128 bytes for class and device names, 256 for subsystem
+    #[default]
+    Lspci,
+    /// Cut to the number of bytes, ellipsis included
+    Bytes(usize),
+    /// Not truncated
+    Full,
+}
+
+impl NameWidth {
+    /// Buffer size (terminating `\0` included) for the name with `lspci` buffer size
+    pub(super) fn max_len(self, lspci: usize) -> usize {
+        match self {
+            Self::Lspci => lspci,
+            // Room for the ellipsis
+            Self::Bytes(n) => n.max(3).saturating_add(1),
+            Self::Full => usize::MAX,
+        }
+    }
+}
+
 impl<'a> fmt::Display for View<Device, &'a ViewArgs<'a>> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let &ViewArgs {
-            verbose, kernel, ..
+            verbose,
+            kernel,
+            bus_centric,
+            ..
         } = self.args;
+        // Bus-centric view ignores IRQ and resources reported by OS, so values are taken
+        // from configuration space registers
+        if bus_centric && (self.data.irq.is_some() || self.data.resource.is_some()) {
+            let data = Device {
+                irq: None,
+                resource: None,
+                ..self.data.clone()
+            };
+            return View {
+                data,
+                args: self.args,
+            }
+            .fmt(f);
+        }
+        // Dumps are decoded as is, like lspci -F does
+        if self.data.is_not_responding() && !matches!(self.args.access, Access::Dump(_)) {
+            return self.fmt_not_responding(f);
+        }
         if verbose > 0 {
             self.fmt_terse(f)?;
             self.fmt_verbose(f)?;
@@ -82,6 +136,27 @@ impl<'a> fmt::Display for View<Device, &'a ViewArgs<'a>> {
 }
 
 impl<'a> View<Device, &'a ViewArgs<'a>> {
+    /// Configuration space reads as all ones, decoding it would show garbage
+    fn fmt_not_responding(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let &ViewArgs {
+            verbose,
+            kernel,
+            always_domain_number,
+            ..
+        } = self.args;
+        if always_domain_number {
+            writeln!(f, "{:} <device not responding>", self.data.address)?;
+        } else {
+            writeln!(f, "{:#} <device not responding>", self.data.address)?;
+        }
+        if verbose > 0 || kernel {
+            self.fmt_kernel(f)?;
+        }
+        if verbose > 0 {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
     fn fmt_terse(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Device {
             ref address,
@@ -104,6 +179,8 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
             cc,
             always_domain_number,
             kernel,
+            show_power,
+            name_width,
             ..
         } = self.args;
         // Device address
@@ -114,23 +191,27 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
         }
 
         // PCI_LOOKUP_CLASS
+        let base_name = cc.lookup(class_code.base, None, None);
+        let sub_name = cc.lookup(class_code.base, class_code.sub, None);
         let class_name = fmt_class_name(
             as_numbers,
             class_code.base,
             class_code.sub,
-            cc.lookup(class_code.base, None, None).as_deref(),
-            cc.lookup(class_code.base, class_code.sub, None).as_deref(),
-            128,
+            base_name,
+            sub_name,
+            name_width.max_len(128),
         );
 
         // PCI_LOOKUP_VENDOR | PCI_LOOKUP_DEVICE
+        let vendor_name = vds.lookup(vendor_id, None, None);
+        let device_name = vds.lookup(vendor_id, device_id, None);
         let device_name = fmt_device_name(
             as_numbers,
             vendor_id,
             device_id,
-            vds.lookup(vendor_id, None, None).as_deref(),
-            vds.lookup(vendor_id, device_id, None).as_deref(),
-            128,
+            vendor_name,
+            device_name,
+            name_width.max_len(128),
         );
         write!(f, " {}: {}", class_name, device_name)?;
 
@@ -141,55 +222,20 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
 
         // PCI_LOOKUP_PROGIF | PCI_LOOKUP_NO_NUMBERS
         if verbose > 0 {
-            let prg_if_name = cc
-                .lookup(class_code.base, class_code.sub, class_code.interface)
-                /* IDE controllers have complex prog-if semantics */
-                .or_else(|| {
-                    if class_code.base == 0x01
-                        && class_code.sub == 0x01
-                        && class_code.interface & 0x70 == 0
-                    {
-                        Some(format!(
-                            "{}{}{}{}{}",
-                            if class_code.interface & 0x80 != 0 {
-                                " Master"
-                            } else {
-                                ""
-                            },
-                            if class_code.interface & 0x08 != 0 {
-                                " SecP"
-                            } else {
-                                ""
-                            },
-                            if class_code.interface & 0x04 != 0 {
-                                " SecO"
-                            } else {
-                                ""
-                            },
-                            if class_code.interface & 0x02 != 0 {
-                                " PriP"
-                            } else {
-                                ""
-                            },
-                            if class_code.interface & 0x01 != 0 {
-                                " PriO"
-                            } else {
-                                ""
-                            },
-                        ))
-                    } else {
-                        None
-                    }
-                });
-            if let Some(x) = prg_if_name {
-                write!(
-                    f,
-                    " (prog-if {:02x} [{}])",
-                    class_code.interface,
-                    x.trim_start()
-                )?;
-            } else if class_code.interface > 0 {
-                write!(f, " (prog-if {:02x})", class_code.interface)?;
+            let interface = class_code.interface;
+            if let Some(name) = cc.lookup(class_code.base, class_code.sub, interface) {
+                write!(f, " (prog-if {:02x} [{}])", interface, name.trim_start())?;
+            } else if class_code.base == 0x01 && class_code.sub == 0x01 && interface & 0x70 == 0 {
+                write!(f, " (prog-if {:02x} [{}])", interface, IdeProgIf(interface))?;
+            } else if interface > 0 {
+                write!(f, " (prog-if {:02x})", interface)?;
+            }
+        }
+        // Not shown by lspci: BARs of device in D3hot read as all ones
+        if show_power {
+            match self.data.power_state() {
+                Some(PowerState::D0) | None => (),
+                Some(state) => write!(f, " ({})", power_state_name(state))?,
             }
         }
         writeln!(f)?;
@@ -200,40 +246,31 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
                 write!(f, "\tDeviceName: {}", label)?;
             }
             // Subdevice
-            if let &HeaderType::Normal(header::Normal {
-                sub_vendor_id: sub_vendor_id @ 0x0001..=0xFFFE,
-                sub_device_id,
-                ..
-            }) = header_type
-            {
+            let subsystem = match header_type {
+                &HeaderType::Normal(header::Normal {
+                    sub_vendor_id,
+                    sub_device_id,
+                    ..
+                }) => Some((sub_vendor_id, sub_device_id)),
+                // Subsystem ID capability, get_subid() of lspci newer than 3.7.0
+                HeaderType::Bridge(_) if cfg!(feature = "ls_bridge_subsystem") => {
+                    Some(self.data.subsystem_ids())
+                }
+                _ => None,
+            };
+            if let Some((sub_vendor_id @ 0x0001..=0xFFFE, sub_device_id)) = subsystem {
                 let sub_vendor_name = vds.lookup(sub_vendor_id, None, None);
-                // Per-device lookup
-                let mut sub_device_name = if vendor_id > 0 && device_id > 0 {
-                    vds.lookup(vendor_id, device_id, (sub_vendor_id, sub_device_id))
-                } else {
-                    None
-                };
-                // neither pci.ids nor hwdb has generic subsystems
-                // // Generic lookup
-                // if sub_device_name.is_none() {
-                //     sub_device_name = vds.lookup(None, None, (sub_vendor_id, sub_device_id))
-                // }
-                // Check for subsystem == device
-                if sub_device_name.is_none()
-                    && vendor_id == sub_vendor_id
-                    && device_id == sub_device_id
-                {
-                    sub_device_name = vds.lookup(vendor_id, device_id, None)
-                };
+                let sub_device_name =
+                    subsystem_name(vds, vendor_id, device_id, sub_vendor_id, sub_device_id);
 
                 // PCI_LOOKUP_SUBSYSTEM | PCI_LOOKUP_VENDOR | PCI_LOOKUP_DEVICE
                 let subsys_name = fmt_device_name(
                     as_numbers,
                     sub_vendor_id,
                     sub_device_id,
-                    sub_vendor_name.as_deref(),
-                    sub_device_name.as_deref(),
-                    256,
+                    sub_vendor_name,
+                    sub_device_name,
+                    name_width.max_len(256),
                 );
                 writeln!(f, "\tSubsystem: {}", subsys_name)?;
             };
@@ -257,12 +294,26 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
             numa_node,
             ref phy_slot,
             ref iommu_group,
+            ref dt_node,
+            ref acpi_path,
+            ref slot_designation,
             ..
         } = self.data;
         let &ViewArgs { verbose, .. } = self.args;
         if let Some(phy_slot) = phy_slot {
             writeln!(f, "\tPhysical Slot: {}", phy_slot)?;
         }
+        // Not shown by lspci
+        if let (Some(slot_designation), 4..) = (slot_designation, verbose) {
+            writeln!(f, "\tSlot designation: {}", slot_designation)?;
+        }
+        if let Some(dt_node) = dt_node {
+            writeln!(f, "\tDevice tree node: {}", dt_node)?;
+        }
+        // Not shown by lspci
+        if let (Some(acpi_path), 4..) = (acpi_path, verbose) {
+            writeln!(f, "\tACPI path: {}", acpi_path)?;
+        }
         let (min_gnt, max_lat) = {
             let &ClassCode { base, sub, .. } = class_code;
             match header_type {
@@ -399,14 +450,12 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
     fn fmt_kernel(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let Device {
             ref driver_in_use,
-            #[cfg(feature = "pciutils_make_opt_libkmod")]
             ref kernel_modules,
             ..
         } = self.data;
         if let Some(driver_in_use) = driver_in_use {
             writeln!(f, "\tKernel driver in use: {}", driver_in_use)?;
         }
-        #[cfg(feature = "pciutils_make_opt_libkmod")]
         if let Some(kernel_modules) = kernel_modules {
             if !kernel_modules.is_empty() {
                 writeln!(f, "\tKernel modules: {}", kernel_modules.join(", "))?;
@@ -768,9 +817,10 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
                     if let Some((_, val)) = bars.next() {
                         hw_upper = *val;
                     } else {
-                        eprintln!(
+                        tracing::warn!(
                             "pcilib: {}: Invalid 64-bit address seen for BAR {}.",
-                            address, n
+                            address,
+                            n
                         );
                         broken = true;
                     }
@@ -952,7 +1002,11 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
                         }
                         write!(f, "{}", View { data, args })?;
                     }
-                    Err(data) => write!(f, "{}", Simple(data))?,
+                    Err(data) => {
+                        let offset = caps::error_offset(&data);
+                        write!(f, "{}", Simple(data))?;
+                        self.fmt_raw_capability(f, offset)?;
+                    }
                 }
             }
             maybe_pci_express
@@ -969,10 +1023,38 @@ impl<'a> View<Device, &'a ViewArgs<'a>> {
             };
             for ecap in ecaps {
                 match ecap {
-                    Ok(data) => write!(f, "{}", View { data, args }),
-                    Err(data) => write!(f, "{}", Verbose { data, verbose }),
-                }?;
+                    Ok(data) => write!(f, "{}", View { data, args })?,
+                    Err(data) => {
+                        let offset = ecaps::error_offset(&data);
+                        write!(f, "{}", Verbose { data, verbose })?;
+                        self.fmt_raw_capability(f, offset)?;
+                    }
+                }
+            }
+        } else if verbose > 3
+            && maybe_pci_express.is_some()
+            && device.configuration_space_length() == ECS_OFFSET
+        {
+            // Short dump or read, lspci shows nothing
+            writeln!(f, "\tCapabilities: <extended config space unavailable>")?;
+        }
+        Ok(())
+    }
+    /// Hexdump of capability failed to parse for decoding bug reports. Shown at `-vvvv` as
+    /// `-vvv` output is the same as lspci one
+    fn fmt_raw_capability(&self, f: &mut fmt::Formatter<'_>, offset: Option<usize>) -> fmt::Result {
+        let Some(offset) = offset.filter(|_| self.args.verbose > 3) else {
+            return Ok(());
+        };
+        let Some(bytes) = self.data.capability_bytes(offset) else {
+            return Ok(());
+        };
+        for (n, chunk) in bytes.chunks(16).enumerate() {
+            write!(f, "\t\t{:03x}:", offset + n * 16)?;
+            for byte in chunk {
+                write!(f, " {:02x}", byte)?;
             }
+            writeln!(f)?;
         }
         Ok(())
     }
@@ -1016,73 +1098,273 @@ fn fmt_range(
 }
 
 // Wrap string with ellipsis
-fn truncate(s: &str, max_len: usize) -> String {
+// PCI_LOOKUP_SUBSYSTEM | PCI_LOOKUP_DEVICE
+pub(super) fn subsystem_name(
+    vds: &names::VendorDeviceSubsystem,
+    vendor_id: u16,
+    device_id: u16,
+    sub_vendor_id: u16,
+    sub_device_id: u16,
+) -> Option<&str> {
+    // Per-device lookup
+    let mut sub_device_name = if vendor_id > 0 && device_id > 0 {
+        vds.lookup(vendor_id, device_id, (sub_vendor_id, sub_device_id))
+    } else {
+        None
+    };
+    // neither pci.ids nor hwdb has generic subsystems
+    // // Generic lookup
+    // if sub_device_name.is_none() {
+    //     sub_device_name = vds.lookup(None, None, (sub_vendor_id, sub_device_id))
+    // }
+    // Check for subsystem == device
+    if sub_device_name.is_none() && vendor_id == sub_vendor_id && device_id == sub_device_id {
+        sub_device_name = vds.lookup(vendor_id, device_id, None)
+    };
+    sub_device_name
+}
+
+/// Longest name buffer of pci_lookup_name() callers
+const NAME_BUF_LEN: usize = 256;
+
+/// Formatted string cut to `max_len` bytes (C buffer size) with ellipsis, without heap
+/// allocation if `max_len` is at most 256.
+pub(super) struct Truncated<T> {
+    pub data: T,
+    pub max_len: usize,
+}
+
+impl<T: fmt::Display> fmt::Display for Truncated<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Wider than lspci buffers, allocation is not an issue
+        if self.max_len > NAME_BUF_LEN {
+            return write_truncated(f, &self.data.to_string(), false, self.max_len);
+        }
+        let mut prefix = Prefix {
+            buf: [0; NAME_BUF_LEN],
+            len: 0,
+            overflow: false,
+        };
+        fmt::write(&mut prefix, format_args!("{}", self.data))?;
+        write_truncated(f, prefix.as_str(), prefix.overflow, self.max_len)
+    }
+}
+
+/// Longest prefix of `s` not longer than `len` bytes, multi-byte chars are never split
+fn floor_char_boundary(s: &str, len: usize) -> &str {
+    let mut end = len.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// `s` cut to `max_len` bytes buffer with ellipsis, `overflow` if `s` is already cut
+fn write_truncated(
+    f: &mut fmt::Formatter<'_>,
+    s: &str,
+    overflow: bool,
+    max_len: usize,
+) -> fmt::Result {
     // sizeof(buf[128]) minus '\0'
-    let max_len = max_len - 1;
-    let len = s.len();
-    if len >= max_len && len >= 4 {
-        format!("{}...", &s[..max_len - 3])
+    let max_len = max_len.saturating_sub(1);
+    if (overflow || s.len() >= max_len) && s.len() >= 4 {
+        f.write_str(floor_char_boundary(s, max_len.saturating_sub(3)))?;
+        f.write_str("...")
     } else {
-        s.to_string()
+        f.write_str(s)
+    }
+}
+
+/// First [NAME_BUF_LEN] bytes of formatted string, cut at char boundary
+struct Prefix {
+    buf: [u8; NAME_BUF_LEN],
+    len: usize,
+    overflow: bool,
+}
+
+impl Prefix {
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Prefix {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let head = floor_char_boundary(s, NAME_BUF_LEN - self.len);
+        self.buf[self.len..self.len + head.len()].copy_from_slice(head.as_bytes());
+        self.len += head.len();
+        self.overflow |= head.len() < s.len();
+        Ok(())
     }
 }
 
-fn fmt_class_name(
+pub(super) fn fmt_class_name<'a>(
     as_numbers: usize,
     base_id: u8,
     sub_id: u8,
-    base_name: Option<&str>,
-    sub_name: Option<&str>,
+    base_name: Option<&'a str>,
+    sub_name: Option<&'a str>,
     max_len: usize,
-) -> String {
-    let maybe_long_str = match (as_numbers, base_name, sub_name) {
-        (0, _, Some(sub)) => sub.to_string(),
-        (0, Some(base), _) => {
-            format!("{} [{:02x}{:02x}]", base, base_id, sub_id)
-        }
-        (0, _, _) => format!("Class {:02x}{:02x}", base_id, sub_id),
-        // Args: -n
-        (1, _, _) => format!("{:02x}{:02x}", base_id, sub_id),
-        // Args: -nn+
-        (_, _, Some(sub)) => format!("{} [{:02x}{:02x}]", sub, base_id, sub_id),
-        (_, Some(base), _) => {
-            format!("{} [{:02x}{:02x}]", base, base_id, sub_id)
-        }
-        _ => format!("Class [{:02x}{:02x}]", base_id, sub_id),
+) -> Truncated<ClassName<'a>> {
+    let data = ClassName {
+        as_numbers,
+        base_id,
+        sub_id,
+        base_name,
+        sub_name,
     };
-    truncate(&maybe_long_str, max_len)
+    Truncated { data, max_len }
+}
+
+pub(super) struct ClassName<'a> {
+    as_numbers: usize,
+    base_id: u8,
+    sub_id: u8,
+    base_name: Option<&'a str>,
+    sub_name: Option<&'a str>,
+}
+
+impl<'a> fmt::Display for ClassName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            base_id, sub_id, ..
+        } = *self;
+        match (self.as_numbers, self.base_name, self.sub_name) {
+            (0, _, Some(sub)) => f.write_str(sub),
+            (0, Some(base), _) => write!(f, "{} [{:02x}{:02x}]", base, base_id, sub_id),
+            (0, _, _) => write!(f, "Class {:02x}{:02x}", base_id, sub_id),
+            // Args: -n
+            (1, _, _) => write!(f, "{:02x}{:02x}", base_id, sub_id),
+            // Args: -nn+
+            (_, _, Some(sub)) => write!(f, "{} [{:02x}{:02x}]", sub, base_id, sub_id),
+            (_, Some(base), _) => write!(f, "{} [{:02x}{:02x}]", base, base_id, sub_id),
+            _ => write!(f, "Class [{:02x}{:02x}]", base_id, sub_id),
+        }
+    }
 }
 
-fn fmt_device_name(
+fn fmt_device_name<'a>(
     as_numbers: usize,
     vendor_id: u16,
     device_id: u16,
-    vendor_name: Option<&str>,
-    device_name: Option<&str>,
+    vendor_name: Option<&'a str>,
+    device_name: Option<&'a str>,
     max_len: usize,
-) -> String {
-    let maybe_long_str = match (as_numbers, vendor_name, device_name) {
-        (0, Some(v), Some(d)) => format!("{} {}", v, d),
-        (0, Some(v), _) => format!("{} Device {:04x}", v, device_id),
-        (0, _, _) => format!("Device {:04x}:{:04x}", vendor_id, device_id),
-        (1, _, _) => format!("{:04x}:{:04x}", vendor_id, device_id),
-        (_, Some(v), Some(d)) => {
-            format!("{} {} [{:04x}:{:04x}]", v, d, vendor_id, device_id)
-        }
-        (_, Some(v), _) => format!("{} Device [{:04x}:{:04x}]", v, vendor_id, device_id),
-        _ => format!("Device [{:04x}:{:04x}]", vendor_id, device_id),
+) -> impl fmt::Display + 'a {
+    let data = DeviceName {
+        as_numbers,
+        vendor_id,
+        device_id,
+        vendor_name,
+        device_name,
     };
-    truncate(&maybe_long_str, max_len)
+    Truncated { data, max_len }
+}
+
+struct DeviceName<'a> {
+    as_numbers: usize,
+    vendor_id: u16,
+    device_id: u16,
+    vendor_name: Option<&'a str>,
+    device_name: Option<&'a str>,
+}
+
+impl<'a> fmt::Display for DeviceName<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            vendor_id,
+            device_id,
+            ..
+        } = *self;
+        match (self.as_numbers, self.vendor_name, self.device_name) {
+            (0, Some(v), Some(d)) => write!(f, "{} {}", v, d),
+            (0, Some(v), _) => write!(f, "{} Device {:04x}", v, device_id),
+            (0, _, _) => write!(f, "Device {:04x}:{:04x}", vendor_id, device_id),
+            (1, _, _) => write!(f, "{:04x}:{:04x}", vendor_id, device_id),
+            (_, Some(v), Some(d)) => {
+                write!(f, "{} {} [{:04x}:{:04x}]", v, d, vendor_id, device_id)
+            }
+            (_, Some(v), _) => write!(f, "{} Device [{:04x}:{:04x}]", v, vendor_id, device_id),
+            _ => write!(f, "Device [{:04x}:{:04x}]", vendor_id, device_id),
+        }
+    }
+}
+
+/// IDE controllers have complex prog-if semantics
+struct IdeProgIf(u8);
+
+impl fmt::Display for IdeProgIf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (0x80, "Master"),
+            (0x08, "SecP"),
+            (0x04, "SecO"),
+            (0x02, "PriP"),
+            (0x01, "PriO"),
+        ];
+        let mut names = flags
+            .iter()
+            .filter(|(mask, _)| self.0 & mask != 0)
+            .map(|(_, name)| name);
+        if let Some(name) = names.next() {
+            f.write_str(name)?;
+        }
+        for name in names {
+            write!(f, " {}", name)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::access::dump::Dump;
     use crate::device::{address::Address, ConfigurationSpace, Device, Resource, ResourceEntry};
-    use crate::names::Names;
+    use crate::names::{ClassCode, Names, VendorDeviceSubsystem};
     use lazy_static::lazy_static;
     use pretty_assertions::assert_str_eq;
 
+    /// Access and names the view arguments borrow
+    struct Fixture {
+        access: Access,
+        vds: VendorDeviceSubsystem,
+        cc: ClassCode,
+    }
+
+    impl Fixture {
+        fn new(access: Access, names: Names) -> Self {
+            Self {
+                access,
+                vds: names.vendor_device_subsystem(),
+                cc: names.class_code(),
+            }
+        }
+        /// Laptop dump without names
+        fn laptop() -> Self {
+            Self::new(Dump::fixture().into(), Names::default())
+        }
+        /// Terse view, tests set options they check
+        fn args(&self) -> ViewArgs<'_> {
+            ViewArgs {
+                verbose: 0,
+                kernel: false,
+                always_domain_number: false,
+                as_numbers: 0,
+                bus_centric: false,
+                show_power: false,
+                name_width: Default::default(),
+                vds: &self.vds,
+                cc: &self.cc,
+                access: &self.access,
+            }
+        }
+        fn device(&self, address: &str) -> Device {
+            self.access.device(address.parse().unwrap()).unwrap()
+        }
+    }
+
     lazy_static! {
         static ref I9DC8: Device = {
             let data = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/device/8086:9dc8/config"));
@@ -1100,6 +1382,7 @@ mod tests {
                     (0x0000000000000000, 0x0000000000000000, 0x0000000000000000),
                 ].map(|(start, end, flags)| ResourceEntry { start, end, flags }),
                 rom_entry: ResourceEntry { start: 0, end: 0, flags: 0 },
+                iov_entries: Default::default(),
             });
             device.irq = Some(145);
             device
@@ -1108,18 +1391,8 @@ mod tests {
 
     #[test]
     fn display_device_basic() {
-        let names = Names::init().unwrap_or_default();
-        let vds = &names.vendor_device_subsystem();
-        let cc = &names.class_code();
-        let args = &ViewArgs {
-            verbose: 0,
-            kernel: false,
-            always_domain_number: false,
-            as_numbers: 0,
-            vds,
-            cc,
-            access: &Default::default(),
-        };
+        let fixture = Fixture::new(Default::default(), Names::init().unwrap_or_default());
+        let args = &fixture.args();
         assert_str_eq!(
             "00:1f.3 Audio device: Intel Corporation Cannon Point-LP High Definition Audio Controller (rev 30)\n",
             View { data: I9DC8.clone(), args }.to_string(),
@@ -1134,17 +1407,8 @@ mod tests {
                     #[test]
                     fn $id() {
                         let names = Names::init().unwrap_or_default();
-                        let vds = &names.vendor_device_subsystem();
-                        let cc = &names.class_code();
-                        let args = &ViewArgs {
-                            verbose: 0,
-                            kernel: false,
-                            always_domain_number: false,
-                            as_numbers: $val,
-                            vds,
-                            cc,
-                            access: &Default::default(),
-                        };
+                        let fixture = Fixture::new(Default::default(), names);
+                        let args = &ViewArgs { as_numbers: $val, ..fixture.args() };
                         let result = View { data: I9DC8.clone(), args }.to_string();
                         assert_str_eq!($sample, result);
                     }
@@ -1163,17 +1427,8 @@ mod tests {
                     #[test]
                     fn $id() {
                         let names = Names::init().unwrap_or_default();
-                        let vds = &names.vendor_device_subsystem();
-                        let cc = &names.class_code();
-                        let args = &ViewArgs {
-                            verbose: $val,
-                            kernel: false,
-                            always_domain_number: false,
-                            as_numbers: 0,
-                            vds,
-                            cc,
-                            access: &Default::default(),
-                        };
+                        let fixture = Fixture::new(Default::default(), names);
+                        let args = &ViewArgs { verbose: $val, ..fixture.args() };
                         let result = View { data: I9DC8.clone(), args }.to_string();
                         let sample =
                             include_str!(concat!(env!("CARGO_MANIFEST_DIR"),
@@ -1200,17 +1455,10 @@ mod tests {
         let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
         let address: Address = "7f:16.0".parse().unwrap();
         let device = Device::new(address, cs);
-        let names = Names::init().unwrap_or_default();
-        let vds = &names.vendor_device_subsystem();
-        let cc = &names.class_code();
+        let fixture = Fixture::new(Default::default(), Names::init().unwrap_or_default());
         let args = &ViewArgs {
-            verbose: 0,
-            kernel: false,
-            always_domain_number: false,
             as_numbers: 2,
-            vds,
-            cc,
-            access: &Default::default(),
+            ..fixture.args()
         };
         let result = View { data: device, args }.to_string();
         let sample = "7f:16.0 System peripheral [0880]: Intel Corporation Xeon E7 v3/Xeon E5 v3/Core i7 Integrated Memory Controller 1 Target Address, Thermal & RAS Registers [8086... (rev 02)\n";
@@ -1228,16 +1476,10 @@ mod tests {
         let device = Device::new(address, cs);
         let names = Names::init_pciids(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids"))
             .unwrap_or_default();
-        let vds = &names.vendor_device_subsystem();
-        let cc = &names.class_code();
+        let fixture = Fixture::new(Default::default(), names);
         let args = &ViewArgs {
             verbose: 2,
-            kernel: false,
-            always_domain_number: false,
-            as_numbers: 0,
-            vds,
-            cc,
-            access: &Default::default(),
+            ..fixture.args()
         };
         let result = View { data: device, args }.to_string();
         let sample = include_str!(concat!(
@@ -1246,4 +1488,255 @@ mod tests {
         ));
         assert_str_eq!(sample, result);
     }
+
+    #[test]
+    fn firmware_node_lines() {
+        let data = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:2f83/config"
+        ));
+        let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+        let mut device = Device::new("7f:08.3".parse().unwrap(), cs);
+        device.dt_node = Some("/sys/firmware/devicetree/base/pcie@10000000".into());
+        device.acpi_path = Some("\\_SB_.UNC0".into());
+        device.slot_designation = Some("PCIe Slot 3".into());
+        let fixture = Fixture::new(Default::default(), Names::default());
+        let args = &ViewArgs {
+            verbose: 1,
+            ..fixture.args()
+        };
+        let view = View {
+            data: device.clone(),
+            args,
+        }
+        .to_string();
+        let result = view.lines().skip(2).take(2).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "\tDevice tree node: /sys/firmware/devicetree/base/pcie@10000000",
+                "\tFlags: fast devsel"
+            ],
+            result
+        );
+        let args = &ViewArgs {
+            verbose: 3,
+            ..*args
+        };
+        let view = View {
+            data: device.clone(),
+            args,
+        }
+        .to_string();
+        assert!(!view.contains("ACPI path") && !view.contains("Slot designation"));
+        let args = &ViewArgs {
+            verbose: 4,
+            ..*args
+        };
+        let view = View { data: device, args }.to_string();
+        let result = view.lines().skip(2).take(3).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "\tSlot designation: PCIe Slot 3",
+                "\tDevice tree node: /sys/firmware/devicetree/base/pcie@10000000",
+                "\tACPI path: \\_SB_.UNC0"
+            ],
+            result
+        );
+    }
+
+    #[test]
+    fn raw_capability_on_error() {
+        let mut data = [0u8; 256];
+        // Capabilities list at 0x40: Vendor Specific with invalid length
+        data[0x06] = 0x10;
+        data[0x34] = 0x40;
+        data[0x40..0x43].copy_from_slice(&[0x09, 0x00, 0xff]);
+        let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+        let device = Device::new(Default::default(), cs);
+        let fixture = Fixture::new(Default::default(), Names::default());
+        let args = &ViewArgs {
+            verbose: 4,
+            ..fixture.args()
+        };
+        let view = View { data: device, args }.to_string();
+        let result = view
+            .lines()
+            .skip_while(|line| !line.starts_with("\tCapabilities"))
+            .take(3)
+            .collect::<Vec<_>>();
+        let sample = vec![
+            "\tCapabilities: [40] Vendor Specific Information: Len=ff <?>",
+            "\t\t040: 09 00 ff 00 00 00 00 00 00 00 00 00 00 00 00 00",
+            "\t\t050: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00",
+        ];
+        assert_eq!(sample, result);
+    }
+
+    #[test]
+    fn show_power() {
+        let fixture = Fixture::laptop();
+        let args = &ViewArgs {
+            as_numbers: 1,
+            show_power: true,
+            ..fixture.args()
+        };
+        let terse = |addr: &str| {
+            let data = fixture.device(addr);
+            View { data, args }.to_string()
+        };
+        assert_str_eq!(
+            "00:15.0 0c80: 8086:9de8 (rev 30) (D3hot)\n",
+            terse("00:15.0")
+        );
+        assert_str_eq!("00:1f.3 0403: 8086:9dc8 (rev 30)\n", terse("00:1f.3"));
+    }
+
+    #[test]
+    fn bridge_subsystem() {
+        let fixture = Fixture::laptop();
+        let args = &ViewArgs {
+            kernel: true,
+            as_numbers: 1,
+            ..fixture.args()
+        };
+        let data = fixture.device("00:1c.0");
+        assert_eq!((0x1043, 0x16a1), data.subsystem_ids());
+        let mut sample = String::from("00:1c.0 0604: 8086:9db8 (rev f0)\n");
+        if cfg!(feature = "ls_bridge_subsystem") {
+            sample.push_str("\tSubsystem: 1043:16a1\n");
+        }
+        assert_str_eq!(sample, View { data, args }.to_string());
+    }
+
+    #[test]
+    fn lane_equalization() {
+        let fixture = Fixture::laptop();
+        let args = &ViewArgs {
+            verbose: 4,
+            as_numbers: 1,
+            ..fixture.args()
+        };
+        let lanes = |addr: &str| {
+            let data = fixture.device(addr);
+            View { data, args }
+                .to_string()
+                .lines()
+                .filter(|line| line.starts_with("\t\tLaneEquCtl"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        // Root port x1
+        let sample = "\t\tLaneEquCtl[0]: DsTxPreset=P7 DsRxHint=reserved UsTxPreset=P5 \
+                      UsRxHint=reserved";
+        assert_eq!(vec![sample], lanes("00:1d.0"));
+        // Endpoint x4
+        let result = lanes("04:00.0");
+        assert_eq!(4, result.len());
+        assert_str_eq!(
+            "\t\tLaneEquCtl[3]: DsTxPreset=P0 DsRxHint=-6dB UsTxPreset=P5 UsRxHint=reserved",
+            result[3]
+        );
+    }
+
+    #[test]
+    fn aer_firmware_first() {
+        let fixture = Fixture::laptop();
+        let args = &ViewArgs {
+            verbose: 4,
+            as_numbers: 1,
+            ..fixture.args()
+        };
+        let mut data = fixture.device("04:00.0");
+        let is_noted = |data: &Device| {
+            View {
+                data: data.clone(),
+                args,
+            }
+            .to_string()
+            .contains("\t\tFirmware-first: errors are handled by platform firmware")
+        };
+        assert!(!is_noted(&data));
+        data.aer_firmware_first = Some(true);
+        assert!(is_noted(&data));
+    }
+
+    #[test]
+    fn short_dump() {
+        let fixture = Fixture::laptop();
+        let args = |verbose| ViewArgs {
+            verbose,
+            as_numbers: 1,
+            ..fixture.args()
+        };
+        let unavailable = |address: &str, verbose| {
+            let mut data = fixture.device(address);
+            // As captured by lspci -x
+            data.extended_configuration_space = None;
+            View {
+                data,
+                args: &args(verbose),
+            }
+            .to_string()
+            .contains("\tCapabilities: <extended config space unavailable>\n")
+        };
+        assert!(unavailable("04:00.0", 4));
+        assert!(!unavailable("04:00.0", 3));
+        // Conventional PCI device
+        assert!(!unavailable("00:1f.3", 4));
+    }
+
+    #[test]
+    fn not_responding() {
+        let data = [0xffu8; 64];
+        let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+        let mut device = Device::new("00:1c.0".parse().unwrap(), cs);
+        device.driver_in_use = Some("pcieport".into());
+        let fixture = Fixture::new(Default::default(), Names::default());
+        let args = &fixture.args();
+        let view = View {
+            data: device.clone(),
+            args,
+        };
+        assert_str_eq!("00:1c.0 <device not responding>\n", view.to_string());
+        let args = &ViewArgs {
+            verbose: 2,
+            ..*args
+        };
+        let view = View { data: device, args };
+        let sample = "00:1c.0 <device not responding>\n\tKernel driver in use: pcieport\n\n";
+        assert_str_eq!(sample, view.to_string());
+    }
+
+    #[test]
+    fn name_width() {
+        let truncate = |data: &str, max_len| Truncated { data, max_len }.to_string();
+        let name = "x".repeat(300);
+        let result = truncate(&name, NameWidth::Lspci.max_len(128));
+        assert_eq!((127, true), (result.len(), result.ends_with("...")));
+        assert_str_eq!(
+            "xxxxxxx...",
+            truncate(&name, NameWidth::Bytes(10).max_len(128))
+        );
+        let result = truncate(&name, NameWidth::Bytes(280).max_len(128));
+        assert_eq!((280, true), (result.len(), result.ends_with("...")));
+        assert_str_eq!(name, truncate(&name, NameWidth::Full.max_len(128)));
+    }
+
+    #[test]
+    fn name_width_non_ascii() {
+        // pci.ids: 1002 0b00  Radeon HD 5870 Eyefinity⁶ Edition, '⁶' is 3 bytes long
+        let truncate = |data: &str, max_len| Truncated { data, max_len }.to_string();
+        let name = "Radeon HD 5870 Eyefinity⁶ Edition";
+        let superscript = name.find('⁶').unwrap();
+        for max_len in superscript..superscript + 8 {
+            let result = truncate(name, max_len);
+            assert!(result.len() < max_len, "{}: {:?}", max_len, result);
+        }
+        assert_str_eq!("Radeon HD 5870 Eyefinity...", truncate(name, 29));
+        assert_str_eq!("Radeon HD 5870 Eyefinity⁶...", truncate(name, 31));
+        let name = "–".repeat(100);
+        assert_str_eq!(format!("{}...", "–".repeat(84)), truncate(&name, 256));
+        assert_str_eq!(format!("{}...", "–".repeat(99)), truncate(&name, 301));
+        assert_str_eq!(name, truncate(&name, NameWidth::Full.max_len(128)));
+    }
 }
@@ -1,13 +1,9 @@
 use core::fmt;
 
 use pcics::capabilities::{
-    message_signaled_interrups::MessageAddress,
-    msi_x::Bir,
-    sata::BarLocation,
-    slot_identification::ExpansionSlot,
-    vendor_specific::{VendorSpecific, VendorSpecificError, Virtio},
-    AdvancedFeatures, Capability, CapabilityError, CapabilityKind, DebugPort,
-    MessageSignaledInterrups, MsiX, Sata, SlotIdentification,
+    message_signaled_interrups::MessageAddress, msi_x::Bir, sata::BarLocation,
+    slot_identification::ExpansionSlot, AdvancedFeatures, Capability, CapabilityError,
+    CapabilityKind, DebugPort, MessageSignaledInterrups, MsiX, Sata, SlotIdentification,
 };
 
 use super::{Flag, Simple, Verbose, View};
@@ -53,6 +49,7 @@ impl<'a> fmt::Display for View<Capability<'a>, &'a ViewArgs<'a>> {
                     pmi,
                     raw_data,
                     verbose,
+                    power_info: device.power_info.as_ref(),
                 };
                 write!(f, "{}", view)
             }
@@ -71,11 +68,24 @@ impl<'a> fmt::Display for View<Capability<'a>, &'a ViewArgs<'a>> {
             CapabilityKind::MessageSignaledInterrups(data) => {
                 write!(f, "{}", Verbose { data, verbose })
             }
-            CapabilityKind::CompactPciHotSwap(_) => writeln!(f, "CompactPCI hot-swap <?>"),
+            CapabilityKind::CompactPciHotSwap(_) => {
+                writeln!(f, "CompactPCI hot-swap <?>")?;
+                match raw_data.get(2) {
+                    Some(&csr) if verbose > 3 => write!(f, "{}", HotSwapStatus(csr)),
+                    _ => Ok(()),
+                }
+            }
             CapabilityKind::PciX(data) => write!(f, "{}", Verbose { data, verbose }),
             CapabilityKind::PciXBridge(data) => write!(f, "{}", Verbose { data, verbose }),
             CapabilityKind::Hypertransport(data) => write!(f, "{}", Verbose { data, verbose }),
-            CapabilityKind::VendorSpecific(data) => write!(f, "{}", Verbose { data, verbose }),
+            CapabilityKind::VendorSpecific(data) => {
+                let view = vendor_specific::View {
+                    data,
+                    raw_data,
+                    verbose,
+                };
+                write!(f, "{}", view)
+            }
             CapabilityKind::DebugPort(dp) => write!(f, "{}", Simple(dp)),
             CapabilityKind::CompactPciResourceControl(_) => {
                 writeln!(f, "CompactPCI central resource control <?>")
@@ -105,8 +115,8 @@ impl<'a> fmt::Display for View<Capability<'a>, &'a ViewArgs<'a>> {
             CapabilityKind::Sata(data) => write!(f, "{}", Verbose { data, verbose }),
             CapabilityKind::AdvancedFeatures(data) => write!(f, "{}", Verbose { data, verbose }),
             CapabilityKind::EnhancedAllocation(data) => write!(f, "{}", Verbose { data, verbose }),
-            CapabilityKind::FlatteningPortalBridge(fpb) => {
-                writeln!(f, "Capability ID 0x15 [{:04x}]", fpb.reserved)
+            CapabilityKind::FlatteningPortalBridge(data) => {
+                write!(f, "{}", Verbose { data, verbose })
             }
             CapabilityKind::Reserved(cid) => writeln!(f, "{:#02x}", cid),
         }
@@ -119,11 +129,27 @@ impl fmt::Display for Simple<CapabilityError> {
             CapabilityError::VendorSpecific { source: data, ptr } => {
                 write!(f, "\tCapabilities: [{:02x}] {}", ptr, Simple(data))
             }
-            err => write!(f, "\tCapabilities: {}", err),
+            err => writeln!(f, "\tCapabilities: {}", err),
         }
     }
 }
 
+/// Configuration space offset of capability failed to parse
+pub(super) fn error_offset(error: &CapabilityError) -> Option<usize> {
+    match error {
+        CapabilityError::Pointer => None,
+        CapabilityError::Header { ptr }
+        | CapabilityError::Data { ptr, .. }
+        | CapabilityError::PciExpress { ptr, .. }
+        | CapabilityError::Hypertransport { ptr, .. }
+        | CapabilityError::VendorSpecific { ptr, .. }
+        | CapabilityError::MessageSignaledInterrups { ptr, .. }
+        | CapabilityError::PciX { ptr, .. }
+        | CapabilityError::PciXBridge { ptr, .. }
+        | CapabilityError::EnhancedAllocation { ptr, .. } => Some(*ptr as usize),
+    }
+}
+
 // 01h PCI Power Management Interface
 mod pmi;
 
@@ -200,69 +226,26 @@ mod pci_x;
 mod hypertransport;
 
 // 09h Vendor Specific
-impl<'a> fmt::Display for Verbose<&'a VendorSpecific<'a>> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let &Verbose { data, verbose } = self;
-        write!(f, "Vendor Specific Information: ")?;
-        match data {
-            VendorSpecific::Virtio(virtio) => {
-                let (bar, offset, size, multiplier) = match virtio {
-                    Virtio::CommonCfg { bar, offset, size } => {
-                        writeln!(f, "VirtIO: CommonCfg",)?;
-                        (bar, offset, size, &None)
-                    }
-                    Virtio::Notify {
-                        bar,
-                        offset,
-                        size,
-                        multiplier,
-                    } => {
-                        writeln!(f, "VirtIO: Notify",)?;
-                        (bar, offset, size, multiplier)
-                    }
-                    Virtio::Isr { bar, offset, size } => {
-                        writeln!(f, "VirtIO: ISR",)?;
-                        (bar, offset, size, &None)
-                    }
-                    Virtio::DeviceCfg { bar, offset, size } => {
-                        writeln!(f, "VirtIO: DeviceCfg",)?;
-                        (bar, offset, size, &None)
-                    }
-                    Virtio::Unknown { bar, offset, size } => {
-                        writeln!(f, "VirtIO: <unknown>",)?;
-                        (bar, offset, size, &None)
-                    }
-                };
-                if verbose < 2 {
-                    return Ok(());
-                }
-                write!(f, "\t\tBAR={} offset={:08x} size={:08x}", bar, offset, size)?;
-                if let Some(multiplier) = multiplier {
-                    write!(f, " multiplier={:08x}", multiplier)?;
-                }
-                writeln!(f)
-            }
-            VendorSpecific::Unspecified(slice) => writeln!(f, "Len={:02x} <?>", slice.len() + 3),
-        }
-    }
-}
+mod vendor_specific;
+
+// 06h CompactPCI Hot Swap
+/// Hot-Swap Control and Status register, shown with `-vvvv` as lspci does not decode it
+struct HotSwapStatus(u8);
 
-impl<'a> fmt::Display for Simple<&'a VendorSpecificError> {
+impl fmt::Display for HotSwapStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {
-            VendorSpecificError::LengthUnreadable => {
-                writeln!(f, "Vendor Specific Information: Len=00 <?>")
-            }
-            VendorSpecificError::Length { val } => {
-                writeln!(f, "Vendor Specific Information: Len={:02x} <?>", val)
-            }
-            VendorSpecificError::Data { size } => {
-                writeln!(f, "Vendor Specific Information: Len={:02x} <?>", size + 2)
-            }
-            VendorSpecificError::Virtio => {
-                writeln!(f, "Vendor Specific Information: VirtIO: <unknown>")
-            }
-        }
+        let csr = self.0;
+        writeln!(
+            f,
+            "\t\tHSCSR: INS{} EXT{} PI={} LOO{} PIE{} EIM{} DHA{}",
+            Flag(csr & 0x80 != 0),
+            Flag(csr & 0x40 != 0),
+            (csr >> 4) & 0b11,
+            Flag(csr & 0x08 != 0),
+            Flag(csr & 0x04 != 0),
+            Flag(csr & 0x02 != 0),
+            Flag(csr & 0x01 != 0),
+        )
     }
 }
 
@@ -375,6 +358,9 @@ impl<'a> fmt::Display for Verbose<&'a AdvancedFeatures> {
 // 14h Enhanced Allocation
 mod ea;
 
+// 15h Flattening Portal Bridge
+mod fpb;
+
 impl DisplayMultiView<()> for Bir {}
 impl fmt::Display for MultiView<&Bir, ()> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -399,7 +385,7 @@ mod tests {
     use crate::device::DDR_OFFSET;
     use crate::device::ECS_OFFSET;
     use crate::names::Names;
-    use pcics::capabilities::VendorSpecific;
+    use pcics::capabilities::{FlatteningPortalBridge, VendorSpecific};
     use pcics::Capabilities;
     use pcics::Header;
     use pretty_assertions::assert_eq;
@@ -432,6 +418,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn flattening_portal_bridge() {
+        let data: [u8; 34] = [
+            0x00, 0x00, // Reserved
+            0x55, 0x55, 0x55, 0x55, // FPB Capabilities
+            0x55, 0x55, 0x55, 0x55, // FPB RID Vector Control 1
+            0x55, 0x55, 0x55, 0x55, // FPB RID Vector Control 2
+            0x55, 0x55, 0x55, 0x55, // FPB MEM Low Vector Control
+            0x55, 0x55, 0x55, 0x55, // FPB MEM High Vector Control 1
+            0x55, 0x55, 0x55, 0x55, // FPB MEM High Vector Control 2
+            0x55, 0x55, 0x55, 0x55, // FPB Vector Access Control
+            0x67, 0x45, 0x23, 0x01, // FPB Vector Access Data
+        ];
+        let data = &FlatteningPortalBridge::from(data);
+        assert_eq!(
+            "Capability ID 0x15 [0000]\n",
+            Verbose { data, verbose: 3 }.to_string()
+        );
+        assert_eq!(
+            "Capability ID 0x15 [0000]\n\
+            \t\tFPBCap: RID+ MEMLow- MEMHigh+ NumSecDev=10\n\
+            \t\tFPBVectorSize: RID=8Kb MEMLow=<reserved 5> MEMHigh=8Kb\n\
+            \t\tFPBRIDCtl: Enable+ Granularity=256RIDs Start=55:00.0 SecStart=55:0a.0\n\
+            \t\tFPBMEMLowCtl: Enable+ Granularity=<reserved 5> Start=54000000\n\
+            \t\tFPBMEMHighCtl: Enable+ Granularity=8GB Start=5555555400000000\n\
+            \t\tFPBVectorAccess: Select=MEMLow Offset=55 Data=01234567\n",
+            Verbose { data, verbose: 4 }.to_string()
+        );
+    }
+
+    #[test]
+    fn legacy_capabilities() {
+        let mut data = [0u8; 0x100];
+        // Capabilities List, capabilities pointer
+        data[0x06] = 0x10;
+        data[0x34] = 0x40;
+        // Pending insertion, LED on, Device Hiding Arm
+        data[0x40..0x43].copy_from_slice(&[0x06, 0x58, 0x89]);
+        // EHCI debug port
+        data[0x58..0x5c].copy_from_slice(&[0x0a, 0x00, 0xa0, 0x20]);
+        let device = {
+            let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+            &Device::new(Default::default(), cs)
+        };
+        let view = |verbose| {
+            let args = &ViewArgs {
+                device,
+                vds: &Default::default(),
+                verbose,
+                as_numbers: 0,
+                access: &Default::default(),
+            };
+            device
+                .capabilities()
+                .unwrap()
+                .flatten()
+                .map(|data| View { data, args }.to_string())
+                .collect::<String>()
+        };
+        let sample = "\
+            \tCapabilities: [40] CompactPCI hot-swap <?>\n\
+            \tCapabilities: [58] Debug port: BAR=1 offset=00a0\n\
+        ";
+        assert_eq!(sample, view(3));
+        let sample = "\
+            \tCapabilities: [40] CompactPCI hot-swap <?>\n\
+            \t\tHSCSR: INS+ EXT- PI=0 LOO+ PIE- EIM- DHA+\n\
+            \tCapabilities: [58] Debug port: BAR=1 offset=00a0\n\
+        ";
+        assert_eq!(sample, view(4));
+    }
+
     #[test]
     fn capabilities() {
         // Capabilities: [50] Power Management version 3
@@ -0,0 +1,194 @@
+use core::fmt;
+
+use pcics::capabilities::flattening_portal_bridge::{
+    FlatteningPortalBridge, FpbCapabilities, FpbMemHighVectorControl, FpbMemHighVectorGranularity,
+    FpbMemHighVectorSizeSupported, FpbMemLowVectorControl, FpbMemLowVectorGranularity,
+    FpbMemLowVectorSizeSupported, FpbRidVectorControl, FpbRidVectorGranularity,
+    FpbRidVectorSizeSupported, FpbVectorAccessControl, FpbVectorSelect,
+};
+
+use super::{Flag, Verbose};
+
+/// lspci does not decode FPB, capability and vector control registers are shown with `-vvvv`
+impl fmt::Display for Verbose<&FlatteningPortalBridge> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let FlatteningPortalBridge {
+            reserved,
+            fpb_capabilities:
+                FpbCapabilities {
+                    fpb_rid_decode_mechanism_supported,
+                    fpb_mem_low_decode_mechanism_supported,
+                    fpb_mem_high_decode_mechanism_supported,
+                    fpb_num_sec_dev,
+                    fpb_rid_vector_size_supported,
+                    fpb_mem_low_vector_size_supported,
+                    fpb_mem_high_vector_size_supported,
+                },
+            fpb_rid_vector_control:
+                FpbRidVectorControl {
+                    fpb_rid_decode_mechanism_enable,
+                    fpb_rid_vector_granularity,
+                    fpb_rid_vector_start,
+                    rid_secondary_start,
+                },
+            fpb_mem_low_vector_control:
+                FpbMemLowVectorControl {
+                    fpb_mem_low_decode_mechanism_enable,
+                    fpb_mem_low_vector_granularity,
+                    fpb_mem_low_vector_start,
+                },
+            fpb_mem_high_vector_control:
+                FpbMemHighVectorControl {
+                    fpb_mem_high_decode_mechanism_enable,
+                    fpb_mem_high_vector_granularity,
+                    fpb_mem_high_vector_start,
+                },
+            fpb_vector_access_control:
+                FpbVectorAccessControl {
+                    fpb_vector_access_offset,
+                    fpb_vector_select,
+                },
+            fpb_vector_access_data,
+        } = self.data;
+        writeln!(f, "Capability ID 0x15 [{:04x}]", reserved)?;
+        if self.verbose < 4 {
+            return Ok(());
+        }
+        let rid_size = match fpb_rid_vector_size_supported {
+            FpbRidVectorSizeSupported::Size256bits => Size::Bits(256),
+            FpbRidVectorSizeSupported::Size1Kbits => Size::Bits(1 << 10),
+            FpbRidVectorSizeSupported::Size8kbits => Size::Bits(8 << 10),
+            FpbRidVectorSizeSupported::Reserved(v) => Size::Reserved(*v),
+        };
+        let mem_low_size = match fpb_mem_low_vector_size_supported {
+            FpbMemLowVectorSizeSupported::Size256bits => Size::Bits(256),
+            FpbMemLowVectorSizeSupported::Size512bits => Size::Bits(512),
+            FpbMemLowVectorSizeSupported::Size1Kbits => Size::Bits(1 << 10),
+            FpbMemLowVectorSizeSupported::Size2Kbits => Size::Bits(2 << 10),
+            FpbMemLowVectorSizeSupported::Size4Kbits => Size::Bits(4 << 10),
+            FpbMemLowVectorSizeSupported::Reserved(v) => Size::Reserved(*v),
+        };
+        let mem_high_size = match fpb_mem_high_vector_size_supported {
+            FpbMemHighVectorSizeSupported::Size256bits => Size::Bits(256),
+            FpbMemHighVectorSizeSupported::Size512bits => Size::Bits(512),
+            FpbMemHighVectorSizeSupported::Size1Kbits => Size::Bits(1 << 10),
+            FpbMemHighVectorSizeSupported::Size2Kbits => Size::Bits(2 << 10),
+            FpbMemHighVectorSizeSupported::Size4Kbits => Size::Bits(4 << 10),
+            FpbMemHighVectorSizeSupported::Size8Kbits => Size::Bits(8 << 10),
+            FpbMemHighVectorSizeSupported::Reserved(v) => Size::Reserved(*v),
+        };
+        writeln!(
+            f,
+            "\t\tFPBCap: RID{} MEMLow{} MEMHigh{} NumSecDev={}",
+            Flag(*fpb_rid_decode_mechanism_supported),
+            Flag(*fpb_mem_low_decode_mechanism_supported),
+            Flag(*fpb_mem_high_decode_mechanism_supported),
+            fpb_num_sec_dev,
+        )?;
+        writeln!(
+            f,
+            "\t\tFPBVectorSize: RID={} MEMLow={} MEMHigh={}",
+            rid_size, mem_low_size, mem_high_size,
+        )?;
+        let rid_granularity = match fpb_rid_vector_granularity {
+            FpbRidVectorGranularity::Granularity8RIDs => Granularity::Units(8, "RIDs"),
+            FpbRidVectorGranularity::Granularity64RIDs => Granularity::Units(64, "RIDs"),
+            FpbRidVectorGranularity::Granularity256RIDs => Granularity::Units(256, "RIDs"),
+            FpbRidVectorGranularity::Reserved(v) => Granularity::Reserved(*v),
+        };
+        writeln!(
+            f,
+            "\t\tFPBRIDCtl: Enable{} Granularity={} Start={} SecStart={}",
+            Flag(*fpb_rid_decode_mechanism_enable),
+            rid_granularity,
+            Rid(*fpb_rid_vector_start),
+            Rid(rid_secondary_start << 3),
+        )?;
+        let mem_low_granularity = match fpb_mem_low_vector_granularity {
+            FpbMemLowVectorGranularity::Granularity1MB => Granularity::Units(1, "MB"),
+            FpbMemLowVectorGranularity::Granularity2MB => Granularity::Units(2, "MB"),
+            FpbMemLowVectorGranularity::Granularity4MB => Granularity::Units(4, "MB"),
+            FpbMemLowVectorGranularity::Granularity8MB => Granularity::Units(8, "MB"),
+            FpbMemLowVectorGranularity::Granularity16MB => Granularity::Units(16, "MB"),
+            FpbMemLowVectorGranularity::Reserved(v) => Granularity::Reserved(*v),
+        };
+        writeln!(
+            f,
+            "\t\tFPBMEMLowCtl: Enable{} Granularity={} Start={:08x}",
+            Flag(*fpb_mem_low_decode_mechanism_enable),
+            mem_low_granularity,
+            fpb_mem_low_vector_start,
+        )?;
+        let mem_high_granularity = match fpb_mem_high_vector_granularity {
+            FpbMemHighVectorGranularity::Granularity256MB => Granularity::Units(256, "MB"),
+            FpbMemHighVectorGranularity::Granularity512MB => Granularity::Units(512, "MB"),
+            FpbMemHighVectorGranularity::Granularity1GB => Granularity::Units(1, "GB"),
+            FpbMemHighVectorGranularity::Granularity2GB => Granularity::Units(2, "GB"),
+            FpbMemHighVectorGranularity::Granularity4GB => Granularity::Units(4, "GB"),
+            FpbMemHighVectorGranularity::Granularity8GB => Granularity::Units(8, "GB"),
+            FpbMemHighVectorGranularity::Granularity16GB => Granularity::Units(16, "GB"),
+            FpbMemHighVectorGranularity::Granularity32GB => Granularity::Units(32, "GB"),
+            FpbMemHighVectorGranularity::Reserved(v) => Granularity::Reserved(*v),
+        };
+        writeln!(
+            f,
+            "\t\tFPBMEMHighCtl: Enable{} Granularity={} Start={:016x}",
+            Flag(*fpb_mem_high_decode_mechanism_enable),
+            mem_high_granularity,
+            fpb_mem_high_vector_start,
+        )?;
+        let select = match fpb_vector_select {
+            FpbVectorSelect::Rid => "RID",
+            FpbVectorSelect::MemLow => "MEMLow",
+            FpbVectorSelect::MemHigh => "MEMHigh",
+            FpbVectorSelect::Reserved => "<reserved>",
+        };
+        writeln!(
+            f,
+            "\t\tFPBVectorAccess: Select={} Offset={:02x} Data={:08x}",
+            select, fpb_vector_access_offset, fpb_vector_access_data,
+        )
+    }
+}
+
+/// Supported vector size
+enum Size {
+    Bits(u16),
+    Reserved(u8),
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Bits(bits) if bits >= 1 << 10 => write!(f, "{}Kb", bits >> 10),
+            Self::Bits(bits) => write!(f, "{}b", bits),
+            Self::Reserved(v) => write!(f, "<reserved {}>", v),
+        }
+    }
+}
+
+/// Vector bit granularity
+enum Granularity {
+    Units(u16, &'static str),
+    Reserved(u8),
+}
+
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Units(n, units) => write!(f, "{}{}", n, units),
+            Self::Reserved(v) => write!(f, "<reserved {}>", v),
+        }
+    }
+}
+
+/// Routing ID as bus:device.function
+struct Rid(u16);
+
+impl fmt::Display for Rid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rid = self.0;
+        let (bus, device, function) = (rid >> 8, (rid >> 3) & 0x1f, rid & 0x7);
+        write!(f, "{:02x}:{:02x}.{}", bus, device, function)
+    }
+}
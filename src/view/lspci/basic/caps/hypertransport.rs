@@ -502,3 +502,57 @@ impl fmt::Display for Simple<LinkFrequency> {
         write!(f, "{}", s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_str_eq;
+
+    use super::*;
+
+    fn view(data: &[u8], verbose: usize) -> String {
+        let ht: Hypertransport = data.try_into().unwrap();
+        Verbose { data: &ht, verbose }.to_string()
+    }
+
+    #[test]
+    fn host_or_secondary_interface() {
+        let data = [
+            0x01, 0x21, // +02h
+            0x20, 0xa0, 0x11, 0x11, // +04h
+            0x60, 0x0c, 0xf5, 0xff, // +08h
+            0x13, 0x00, 0x00, 0x00, // +0Ch
+            0xee, 0x02, 0x84, 0x80, // +10h
+            0x00, 0x00, 0x01, 0x00, // +14h
+        ];
+        assert_str_eq!(
+            "HyperTransport: Host or Secondary Interface\n",
+            view(&data, 1)
+        );
+        // ls-caps.c keeps Link Frequency Capability in u8, upper bits are always shown unset
+        let sample = "\
+            HyperTransport: Host or Secondary Interface\n\
+            \t\tCommand: WarmRst+ DblEnd- DevNum=0 ChainSide- HostHide+ Slave- <EOCErr- DUL-\n\
+            \t\tLink Control: CFlE- CST- CFE- <LkFail- Init+ EOC- TXO- <CRCErr=0 IsocEn- LSEn+ ExtCTL- 64b+\n\
+            \t\tLink Config: MLWI=16bit DwFcIn- MLWO=16bit DwFcOut- LWI=16bit DwFcInEn- LWO=16bit DwFcOutEn-\n\
+            \t\tRevision ID: 3.00\n\
+            \t\tLink Frequency: [c]\n\
+            \t\tLink Error: <Prot- <Ovfl- <EOC- CTLTm-\n\
+            \t\tLink Frequency Capability: 200MHz+ 300MHz- 400MHz+ 500MHz- 600MHz+ 800MHz+ 1.0GHz+ 1.2GHz+ 1.4GHz- 1.6GHz- Vend-\n\
+            \t\tFeature Capability: IsocFC+ LDTSTOP+ CRCTM- ECTLT- 64bA+ UIDRD- ExtRS- UCnfE-\n\
+        ";
+        assert_str_eq!(sample, view(&data, 2));
+    }
+
+    #[test]
+    fn msi_mapping() {
+        let fixed = [0x03, 0xa8, 0x00, 0x00, 0x00, 0x00, 0x34, 0x17, 0xda, 0x11];
+        let sample = "HyperTransport: MSI Mapping Enable+ Fixed+\n";
+        assert_str_eq!(sample, view(&fixed, 2));
+        let programmable = [0x01, 0xa8, 0x00, 0x00, 0xe0, 0xfe, 0x00, 0x00, 0x00, 0x00];
+        let sample = "\
+            HyperTransport: MSI Mapping Enable+ Fixed-\n\
+            \t\tMapping Address Base: 00000000fee00000\n\
+        ";
+        assert_str_eq!(sample, view(&programmable, 2));
+    }
+}
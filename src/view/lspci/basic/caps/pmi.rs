@@ -3,11 +3,14 @@ use core::fmt;
 use pcics::capabilities::power_management_interface::{AuxCurrent, PowerManagementInterface};
 
 use super::Flag;
+use crate::device::PowerInfo;
 
 pub(super) struct View<'a> {
     pub(super) pmi: &'a PowerManagementInterface,
     pub(super) raw_data: &'a [u8],
     pub(super) verbose: usize,
+    /// Runtime PM data reported by OS
+    pub(super) power_info: Option<&'a PowerInfo>,
 }
 
 impl<'a> fmt::Display for View<'a> {
@@ -22,6 +25,7 @@ impl<'a> fmt::Display for View<'a> {
                 },
             verbose,
             raw_data,
+            power_info,
         } = self;
         writeln!(f, "Power Management version {}", caps.version)?;
         if verbose < 2 {
@@ -70,6 +74,10 @@ impl<'a> fmt::Display for View<'a> {
             };
             writeln!(f, "\t\tBridge: PM{} B3{}", Flag(pm), Flag(b3))?;
         }
+        // Not shown by lspci
+        if let (Some(info), 4..) = (power_info, verbose) {
+            writeln!(f, "\t\tRuntime PM: {}", info)?;
+        }
         Ok(())
     }
 }
@@ -89,6 +97,7 @@ mod tests {
             pmi: &pmi,
             verbose: 1,
             raw_data: &data,
+            power_info: None,
         };
         let v1_sample = "\
             Power Management version 2\n\
@@ -99,6 +108,7 @@ mod tests {
             pmi: &pmi,
             verbose: 2,
             raw_data: &data,
+            power_info: None,
         };
         let v2_sample = "\
             Power Management version 2\n\
@@ -112,6 +122,7 @@ mod tests {
             pmi: &pmi,
             verbose: 3,
             raw_data: &data,
+            power_info: None,
         };
         let v3_sample = "\
             Power Management version 2\n\
@@ -121,4 +132,34 @@ mod tests {
         ";
         assert_eq!(v3_sample, v3_result.to_string(), "-vvv");
     }
+
+    #[test]
+    fn runtime_power_management() {
+        use crate::device::PowerControl;
+        let data = [0x01, 0x00, 0x03, 0x00, 0x00, 0x00];
+        let pmi: PowerManagementInterface = data.as_slice().try_into().unwrap();
+        let info = PowerInfo {
+            runtime_status: Some("suspended".into()),
+            control: Some(PowerControl::Auto),
+            d3cold_allowed: Some(false),
+        };
+        let mut view = View {
+            pmi: &pmi,
+            verbose: 3,
+            raw_data: &data,
+            power_info: Some(&info),
+        };
+        let sample = "\
+            Power Management version 1\n\
+            \t\tFlags: PMEClk- DSI- D1- D2- AuxCurrent=0mA PME(D0-,D1-,D2-,D3hot-,D3cold-)\n\
+            \t\tStatus: D3 NoSoftRst- PME-Enable- DSel=0 DScale=0 PME-\n\
+        ";
+        assert_eq!(sample, view.to_string(), "-vvv");
+        view.verbose = 4;
+        let sample = format!(
+            "{}\t\tRuntime PM: Status suspended, Control auto, D3cold-\n",
+            sample
+        );
+        assert_eq!(sample, view.to_string(), "-vvvv");
+    }
 }
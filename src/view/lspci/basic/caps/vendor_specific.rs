@@ -0,0 +1,165 @@
+use core::fmt;
+
+use pcics::capabilities::vendor_specific::{VendorSpecific, VendorSpecificError, Virtio};
+
+use super::{Simple, Verbose};
+use crate::misc::virtio::VirtioCapability;
+
+pub(super) struct View<'a> {
+    pub(super) data: &'a VendorSpecific<'a>,
+    /// Capability bytes starting with header
+    pub(super) raw_data: &'a [u8],
+    pub(super) verbose: usize,
+}
+
+impl<'a> fmt::Display for View<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let &View {
+            data,
+            raw_data,
+            verbose,
+        } = self;
+        write!(f, "{}", Verbose { data, verbose })?;
+        if verbose < 4 {
+            return Ok(());
+        }
+        if let VendorSpecific::Virtio(_) = data {
+            if let Some(cap) = VirtioCapability::new(raw_data) {
+                write!(
+                    f,
+                    "\t\tType={} BAR={} ID={} offset={:x} length={:x}",
+                    cap.cfg_type, cap.bar, cap.id, cap.offset, cap.length
+                )?;
+                if let Some(multiplier) = cap.notify_off_multiplier {
+                    write!(f, " multiplier={:x}", multiplier)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        let len = raw_data.get(2).copied().unwrap_or_default() as usize;
+        writeln!(f, "\t\tLen={:02x}", len)?;
+        // Body follows ID, next pointer and length bytes
+        let body = raw_data.get(3..len.min(raw_data.len())).unwrap_or_default();
+        for (n, chunk) in body.chunks(16).enumerate() {
+            write!(f, "\t\t{:02x}:", 3 + n * 16)?;
+            for byte in chunk {
+                write!(f, " {:02x}", byte)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Verbose<&'a VendorSpecific<'a>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let &Verbose { data, verbose } = self;
+        write!(f, "Vendor Specific Information: ")?;
+        match data {
+            VendorSpecific::Virtio(virtio) => {
+                let (bar, offset, size, multiplier) = match virtio {
+                    Virtio::CommonCfg { bar, offset, size } => {
+                        writeln!(f, "VirtIO: CommonCfg",)?;
+                        (bar, offset, size, &None)
+                    }
+                    Virtio::Notify {
+                        bar,
+                        offset,
+                        size,
+                        multiplier,
+                    } => {
+                        writeln!(f, "VirtIO: Notify",)?;
+                        (bar, offset, size, multiplier)
+                    }
+                    Virtio::Isr { bar, offset, size } => {
+                        writeln!(f, "VirtIO: ISR",)?;
+                        (bar, offset, size, &None)
+                    }
+                    Virtio::DeviceCfg { bar, offset, size } => {
+                        writeln!(f, "VirtIO: DeviceCfg",)?;
+                        (bar, offset, size, &None)
+                    }
+                    Virtio::Unknown { bar, offset, size } => {
+                        writeln!(f, "VirtIO: <unknown>",)?;
+                        (bar, offset, size, &None)
+                    }
+                };
+                if verbose < 2 {
+                    return Ok(());
+                }
+                write!(f, "\t\tBAR={} offset={:08x} size={:08x}", bar, offset, size)?;
+                if let Some(multiplier) = multiplier {
+                    write!(f, " multiplier={:08x}", multiplier)?;
+                }
+                writeln!(f)
+            }
+            VendorSpecific::Unspecified(slice) => writeln!(f, "Len={:02x} <?>", slice.len() + 3),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Simple<&'a VendorSpecificError> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            VendorSpecificError::LengthUnreadable => {
+                writeln!(f, "Vendor Specific Information: Len=00 <?>")
+            }
+            VendorSpecificError::Length { val } => {
+                writeln!(f, "Vendor Specific Information: Len={:02x} <?>", val)
+            }
+            VendorSpecificError::Data { size } => {
+                writeln!(f, "Vendor Specific Information: Len={:02x} <?>", size + 2)
+            }
+            VendorSpecificError::Virtio => {
+                writeln!(f, "Vendor Specific Information: VirtIO: <unknown>")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pcics::Header;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn hexdump() {
+        let mut header = Header::from([0; 0x40]);
+        header.vendor_id = 0x1af4;
+        header.device_id = 0x1045;
+        let raw_data = [
+            0x09, 0xa4, 0x14, 0x02, 0x04, 0x00, 0x00, 0x00, // Header, Notify in BAR 4
+            0x00, 0x30, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, // Offset, size
+            0x04, 0x00, 0x00, 0x00, // Multiplier
+            0xff, 0xff, // Next capability
+        ];
+        let data = &VendorSpecific::try_new(&raw_data[2..], &header).unwrap();
+        let view = View {
+            data,
+            raw_data: &raw_data,
+            verbose: 4,
+        };
+        let sample = "\
+            Vendor Specific Information: VirtIO: Notify\n\
+            \t\tBAR=4 offset=00003000 size=00001000 multiplier=00000004\n\
+            \t\tType=Notify BAR=4 ID=0 offset=3000 length=1000 multiplier=4\n\
+            \t\tLen=14\n\
+            \t\t03: 02 04 00 00 00 00 30 00 00 00 10 00 00 04 00 00\n\
+            \t\t13: 00\n\
+        ";
+        assert_eq!(sample, view.to_string());
+
+        header.vendor_id = 0x8086;
+        let data = &VendorSpecific::try_new(&raw_data[2..], &header).unwrap();
+        let view = View {
+            data,
+            raw_data: &raw_data,
+            verbose: 3,
+        };
+        assert_eq!(
+            "Vendor Specific Information: Len=14 <?>\n",
+            view.to_string()
+        );
+    }
+}
@@ -3,8 +3,9 @@ use core::fmt;
 use pcics::{
     capabilities::PciExpress,
     extended_capabilities::{
-        multifunction_virtual_channel::MultifunctionVirtualChannelError, tph_requester::StTable,
-        AccessControlServices, AddressTranslationServices, AlternativeRoutingIdInterpretation,
+        multifunction_virtual_channel::MultifunctionVirtualChannelError,
+        secondary_pci_express::ECL_OFFSET, tph_requester::StTable, AccessControlServices,
+        AddressTranslationServices, AlternativeRoutingIdInterpretation,
         ConfigurationAccessCorrelation, DataLinkFeature, DeviceSerialNumber,
         DownstreamPortContainment, DynamicPowerAllocation, ExtendedCapability,
         ExtendedCapabilityError, ExtendedCapabilityKind, FrsQueuing, HierarchyId, L1PmSubstates,
@@ -19,7 +20,7 @@ use pcics::{
 };
 
 use crate::{
-    device::Device,
+    device::{Device, L1ssInfo},
     view::{DisplayMultiView, MultiView},
 };
 
@@ -62,6 +63,7 @@ impl<'a> fmt::Display for View<ExtendedCapability<'a>, &'a ViewArgs<'a>> {
                 let args = &aer::ViewArgs {
                     verbose,
                     is_type_root,
+                    firmware_first: device.aer_firmware_first == Some(true),
                 };
                 write!(f, "{}", View { data, args })
             }
@@ -115,7 +117,11 @@ impl<'a> fmt::Display for View<ExtendedCapability<'a>, &'a ViewArgs<'a>> {
             }
             // 0010h
             ExtendedCapabilityKind::SingleRootIoVirtualization(c) => {
-                write!(f, "{}", Verbose { data: c, verbose })
+                write!(f, "{}", Verbose { data: c, verbose })?;
+                match device.vf_layout() {
+                    Some(vfs) if verbose > 3 => write!(f, "{}", vfs),
+                    _ => Ok(()),
+                }
             }
             // 0011h
             ExtendedCapabilityKind::MultiRootIoVirtualization(c) => {
@@ -163,7 +169,14 @@ impl<'a> fmt::Display for View<ExtendedCapability<'a>, &'a ViewArgs<'a>> {
             }
             // 0019h
             ExtendedCapabilityKind::SecondaryPciExpress(c) => {
-                write!(f, "{}", Verbose { data: c, verbose })
+                write!(f, "{}", Verbose { data: c, verbose })?;
+                if verbose > 3 {
+                    let controls = lane_equalization_controls(device, offset);
+                    for (lane, control) in controls.into_iter().enumerate() {
+                        writeln!(f, "\t\tLaneEquCtl[{}]: {}", lane, LaneEqualization(control))?;
+                    }
+                }
+                Ok(())
             }
             // 01Ah
             ExtendedCapabilityKind::ProtocolMultiplexing(c) => {
@@ -309,7 +322,31 @@ impl fmt::Display for Verbose<ExtendedCapabilityError> {
             ),
         };
         let ver = if verbose > 1 { " v0" } else { "" };
-        write!(f, "\tCapabilities: [{:03x}{}] {}", offset, ver, msg)
+        write!(f, "\tCapabilities: [{:03x}{}] {}", offset, ver, msg)?;
+        if !msg.ends_with('\n') {
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Configuration space offset of extended capability failed to parse
+pub(super) fn error_offset(error: &ExtendedCapabilityError) -> Option<usize> {
+    match error {
+        ExtendedCapabilityError::Offset => None,
+        ExtendedCapabilityError::Header { offset }
+        | ExtendedCapabilityError::EmptyHeader { offset }
+        | ExtendedCapabilityError::Data { offset, .. }
+        | ExtendedCapabilityError::RootComplexLinkDeclaration { offset, .. }
+        | ExtendedCapabilityError::MultifunctionVirtualChannel { offset, .. }
+        | ExtendedCapabilityError::SingleRootIoVirtualization { offset, .. }
+        | ExtendedCapabilityError::AdvancedErrorReporting { offset, .. }
+        | ExtendedCapabilityError::DownstreamPortContainment { offset, .. }
+        | ExtendedCapabilityError::ResizableBar { offset, .. }
+        | ExtendedCapabilityError::DynamicPowerAllocation { offset, .. }
+        | ExtendedCapabilityError::ProtocolMultiplexing { offset, .. }
+        | ExtendedCapabilityError::DesignatedVendorSpecificExtendedCapability { offset, .. }
+        | ExtendedCapabilityError::VfResizableBar { offset, .. } => Some(*offset as usize),
     }
 }
 
@@ -661,6 +698,52 @@ impl<'a> fmt::Display for Verbose<&'a SecondaryPciExpress<'a>> {
     }
 }
 
+/// Lane Equalization Control registers, one per lane of Maximum Link Width
+fn lane_equalization_controls(device: &Device, offset: u16) -> Vec<u16> {
+    let lanes = device
+        .express_link()
+        .map(|link| u8::from(link.capabilities.maximum_link_width) as usize)
+        .unwrap_or_default();
+    // extended_configuration_space at 0x100
+    let start = offset as usize + ECL_OFFSET - 0x100;
+    device
+        .extended_configuration_space
+        .as_ref()
+        .and_then(|ecs| ecs.0.get(start..start + lanes * 2))
+        .map(|bytes| {
+            bytes
+                .chunks_exact(2)
+                .map(|word| u16::from_le_bytes([word[0], word[1]]))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 8.0 GT/s Transmitter Presets and Receiver Preset Hints of lane
+struct LaneEqualization(u16);
+
+impl fmt::Display for LaneEqualization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tx_preset = |n: u16| match n & 0xf {
+            n @ 0..=10 => format!("P{}", n),
+            _ => "reserved".into(),
+        };
+        let rx_hint = |n: u16| match n >> 4 & 0x7 {
+            7 => "reserved".into(),
+            n => format!("-{}dB", n + 6),
+        };
+        let (downstream, upstream) = (self.0 & 0xff, self.0 >> 8);
+        write!(
+            f,
+            "DsTxPreset={} DsRxHint={} UsTxPreset={} UsRxHint={}",
+            tx_preset(downstream),
+            rx_hint(downstream),
+            tx_preset(upstream),
+            rx_hint(upstream),
+        )
+    }
+}
+
 // 001Ah Protocol Multiplexing (PMUX)
 impl<'a> fmt::Display for Simple<&'a ProtocolMultiplexing<'a>> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -832,7 +915,11 @@ impl<'a> fmt::Display for Verbose<&'a L1PmSubstates> {
                 write!(f, " T_PwrOn=<error>")?;
             };
         }
-        writeln!(f)
+        writeln!(f)?;
+        if verbose > 3 && is_l1_2_supported {
+            writeln!(f, "\t\tL1SubTiming:{}", L1ssInfo::new(self.data))?;
+        }
+        Ok(())
     }
 }
 
@@ -7,6 +7,8 @@ use super::{Flag, View};
 pub(super) struct ViewArgs {
     pub(super) verbose: usize,
     pub(super) is_type_root: bool,
+    /// AER is handled by platform firmware
+    pub(super) firmware_first: bool,
 }
 
 impl<'a> fmt::Display for View<&'a AdvancedErrorReporting, &'a ViewArgs> {
@@ -27,6 +29,7 @@ impl<'a> fmt::Display for View<&'a AdvancedErrorReporting, &'a ViewArgs> {
         let &ViewArgs {
             verbose,
             is_type_root,
+            firmware_first,
         } = self.args;
         writeln!(f, "Advanced Error Reporting")?;
         if verbose < 2 {
@@ -170,6 +173,13 @@ impl<'a> fmt::Display for View<&'a AdvancedErrorReporting, &'a ViewArgs> {
                 esi.err_fatal_or_nonfatal_source_identification
             )?;
         }
+        if verbose > 3 && firmware_first {
+            writeln!(
+                f,
+                "\t\tFirmware-first: errors are handled by platform firmware (ACPI HEST), \
+                status above is not updated by OS"
+            )?;
+        }
         Ok(())
     }
 }
@@ -0,0 +1,354 @@
+/*!
+# Machine readable output
+
+`lspci -m` (single line per device, shell quoted names) and `lspci -vmm` (tag-value records
+separated with blank line). `-vm` differs from `-vmm` only in the first tag name: `Device`
+instead of `Slot`.
+
+lspci does not show capabilities in machine readable mode at any verbosity level, so neither
+does this view.
+*/
+
+use core::fmt;
+
+use pcics::header::{Cardbus, Header, HeaderType, Normal};
+
+use crate::{device::Device, names};
+
+use super::basic::{fmt_class_name, subsystem_name, ClassName, Truncated, ViewArgs};
+
+/// Machine readable device view, `machine` is `-m` occurrences
+pub struct View<'a> {
+    pub data: &'a Device,
+    pub args: &'a ViewArgs<'a>,
+    pub machine: usize,
+}
+
+impl<'a> fmt::Display for View<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.args.verbose > 0 {
+            self.fmt_verbose(f)
+        } else {
+            self.fmt_simple(f)
+        }
+    }
+}
+
+impl<'a> View<'a> {
+    fn fmt_simple(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Names::new(self.data, self.args);
+        let Header {
+            revision_id,
+            ref class_code,
+            ..
+        } = self.data.header;
+        self.fmt_slot(f)?;
+        write!(f, " {}", Escaped(&names.class))?;
+        write!(f, " {}", Escaped(&names.vendor))?;
+        write!(f, " {}", Escaped(&names.device))?;
+        if revision_id != 0 {
+            write!(f, " -r{:02x}", revision_id)?;
+        }
+        if class_code.interface != 0 {
+            write!(f, " -p{:02x}", class_code.interface)?;
+        }
+        if let Some((sub_vendor, sub_device)) = &names.subsystem {
+            write!(f, " {} {}", Escaped(sub_vendor), Escaped(sub_device))?;
+        } else {
+            write!(f, " \"\" \"\"")?;
+        }
+        writeln!(f)
+    }
+    fn fmt_verbose(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = Names::new(self.data, self.args);
+        let Device {
+            header:
+                Header {
+                    revision_id,
+                    ref class_code,
+                    ..
+                },
+            ref phy_slot,
+            numa_node,
+            ref iommu_group,
+            ..
+        } = *self.data;
+        write!(f, "{}:\t", if self.machine > 1 { "Slot" } else { "Device" })?;
+        self.fmt_slot(f)?;
+        writeln!(f)?;
+        writeln!(f, "Class:\t{}", names.class)?;
+        writeln!(f, "Vendor:\t{}", names.vendor)?;
+        writeln!(f, "Device:\t{}", names.device)?;
+        if let Some((sub_vendor, sub_device)) = &names.subsystem {
+            writeln!(f, "SVendor:\t{}", sub_vendor)?;
+            writeln!(f, "SDevice:\t{}", sub_device)?;
+        }
+        if let Some(phy_slot) = phy_slot {
+            writeln!(f, "PhySlot:\t{}", phy_slot)?;
+        }
+        if revision_id != 0 {
+            writeln!(f, "Rev:\t{:02x}", revision_id)?;
+        }
+        if class_code.interface != 0 {
+            writeln!(f, "ProgIf:\t{:02x}", class_code.interface)?;
+        }
+        if self.args.kernel {
+            self.fmt_kernel(f)?;
+        }
+        if let Some(numa_node) = numa_node {
+            writeln!(f, "NUMANode:\t{}", numa_node)?;
+        }
+        if let Some(iommu_group) = iommu_group {
+            writeln!(f, "IOMMUGroup:\t{}", iommu_group)?;
+        }
+        writeln!(f)
+    }
+    fn fmt_slot(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.args.always_domain_number {
+            write!(f, "{:}", self.data.address)
+        } else {
+            write!(f, "{:#}", self.data.address)
+        }
+    }
+    fn fmt_kernel(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Device {
+            ref driver_in_use,
+            ref kernel_modules,
+            ..
+        } = self.data;
+        if let Some(driver_in_use) = driver_in_use {
+            writeln!(f, "Driver:\t{}", driver_in_use)?;
+        }
+        for module in kernel_modules.iter().flatten() {
+            writeln!(f, "Module:\t{}", module)?;
+        }
+        Ok(())
+    }
+}
+
+/// Names resolved with `-n` semantics of pci_lookup_name
+struct Names<'a> {
+    class: Truncated<ClassName<'a>>,
+    vendor: Truncated<Name<'a>>,
+    device: Truncated<Name<'a>>,
+    subsystem: Option<(Truncated<Name<'a>>, Truncated<Name<'a>>)>,
+}
+
+impl<'a> Names<'a> {
+    fn new(device: &Device, args: &ViewArgs<'a>) -> Self {
+        let &ViewArgs {
+            as_numbers,
+            vds,
+            cc,
+            name_width,
+            ..
+        } = args;
+        let Header {
+            vendor_id,
+            device_id,
+            ref class_code,
+            ..
+        } = device.header;
+        // PCI_LOOKUP_CLASS
+        let class = fmt_class_name(
+            as_numbers,
+            class_code.base,
+            class_code.sub,
+            cc.lookup(class_code.base, None, None),
+            cc.lookup(class_code.base, class_code.sub, None),
+            name_width.max_len(256),
+        );
+        // PCI_LOOKUP_VENDOR
+        let vendor_name = vds.lookup(vendor_id, None, None);
+        let max_len = name_width.max_len(256);
+        let vendor = fmt_name(as_numbers, vendor_name, "Vendor", vendor_id, max_len);
+        // PCI_LOOKUP_DEVICE
+        let device_name = vds.lookup(vendor_id, device_id, None);
+        let device_str = fmt_name(as_numbers, device_name, "Device", device_id, max_len);
+        let subsystem = subsystem_ids(device)
+            .filter(|&(sv, sd)| !matches!(sv, 0 | 0xffff) || !matches!(sd, 0 | 0xffff))
+            .map(|(sv, sd)| {
+                let ids = (vendor_id, device_id, sv, sd);
+                subsystem_names(vds, as_numbers, ids, max_len)
+            });
+        Self {
+            class,
+            vendor,
+            device: device_str,
+            subsystem,
+        }
+    }
+}
+
+// PCI_LOOKUP_SUBSYSTEM | PCI_LOOKUP_VENDOR and PCI_LOOKUP_SUBSYSTEM | PCI_LOOKUP_DEVICE
+fn subsystem_names(
+    vds: &names::VendorDeviceSubsystem,
+    as_numbers: usize,
+    (vendor_id, device_id, sub_vendor_id, sub_device_id): (u16, u16, u16, u16),
+    max_len: usize,
+) -> (Truncated<Name<'_>>, Truncated<Name<'_>>) {
+    let sub_vendor_name = vds.lookup(sub_vendor_id, None, None);
+    let sub_device_name = subsystem_name(vds, vendor_id, device_id, sub_vendor_id, sub_device_id);
+    (
+        fmt_name(
+            as_numbers,
+            sub_vendor_name,
+            "Vendor",
+            sub_vendor_id,
+            max_len,
+        ),
+        fmt_name(
+            as_numbers,
+            sub_device_name,
+            "Device",
+            sub_device_id,
+            max_len,
+        ),
+    )
+}
+
+/// Subsystem IDs of normal and CardBus headers, bridges have none (as in lspci get_subid())
+fn subsystem_ids(device: &Device) -> Option<(u16, u16)> {
+    match &device.header.header_type {
+        HeaderType::Normal(Normal {
+            sub_vendor_id,
+            sub_device_id,
+            ..
+        }) => Some((*sub_vendor_id, *sub_device_id)),
+        HeaderType::Cardbus(Cardbus {
+            subsystem_vendor_id: Some(sv),
+            subsystem_device_id: Some(sd),
+            ..
+        }) => Some((*sv, *sd)),
+        _ => None,
+    }
+}
+
+fn fmt_name<'a>(
+    as_numbers: usize,
+    name: Option<&'a str>,
+    unknown: &'static str,
+    id: u16,
+    max_len: usize,
+) -> Truncated<Name<'a>> {
+    let data = Name {
+        as_numbers,
+        name,
+        unknown,
+        id,
+    };
+    Truncated { data, max_len }
+}
+
+// format_name() of names.c
+struct Name<'a> {
+    as_numbers: usize,
+    name: Option<&'a str>,
+    unknown: &'static str,
+    id: u16,
+}
+
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { unknown, id, .. } = *self;
+        match (self.as_numbers, self.name) {
+            (0, Some(name)) => f.write_str(name),
+            (0, None) => write!(f, "{} {:04x}", unknown, id),
+            (1, _) => write!(f, "{:04x}", id),
+            (_, Some(name)) => write!(f, "{} [{:04x}]", name, id),
+            (_, None) => write!(f, "{} [{:04x}]", unknown, id),
+        }
+    }
+}
+
+/// Double quoted with `"` and `\` escaped, print_shell_escaped() of lspci
+struct Escaped<T>(T);
+
+impl<T: fmt::Display> fmt::Display for Escaped<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        fmt::write(&mut ShellEscape(f), format_args!("{}", self.0))?;
+        f.write_str("\"")
+    }
+}
+
+struct ShellEscape<'a, 'b>(&'a mut fmt::Formatter<'b>);
+
+impl<'a, 'b> fmt::Write for ShellEscape<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if matches!(c, '"' | '\\') {
+                self.0.write_str("\\")?;
+            }
+            fmt::Write::write_char(self.0, c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{ConfigurationSpace, Device};
+    use crate::names::Names as PciIds;
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn simple_and_verbose() {
+        let data = include_bytes!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/config"
+        ));
+        let cs: ConfigurationSpace = data.as_slice().try_into().unwrap();
+        let mut device = Device::new("00:1f.3".parse().unwrap(), cs);
+        device.driver_in_use = Some("snd_hda_intel".into());
+        let names = PciIds::default();
+        let vds = &names.vendor_device_subsystem();
+        let cc = &names.class_code();
+        let args = ViewArgs {
+            verbose: 0,
+            kernel: true,
+            always_domain_number: false,
+            as_numbers: 0,
+            bus_centric: false,
+            show_power: false,
+            name_width: Default::default(),
+            vds,
+            cc,
+            access: &Default::default(),
+        };
+        let view = View {
+            data: &device,
+            args: &args,
+            machine: 1,
+        };
+        assert_str_eq!(
+            "00:1f.3 \"Class 0403\" \"Vendor 8086\" \"Device 9dc8\" -r30 -p80 \"Vendor 1043\" \"Device 16a1\"\n",
+            view.to_string()
+        );
+        let args = ViewArgs {
+            verbose: 1,
+            as_numbers: 2,
+            ..args
+        };
+        let view = View {
+            data: &device,
+            args: &args,
+            machine: 2,
+        };
+        let sample = "\
+            Slot:\t00:1f.3\n\
+            Class:\tClass [0403]\n\
+            Vendor:\tVendor [8086]\n\
+            Device:\tDevice [9dc8]\n\
+            SVendor:\tVendor [1043]\n\
+            SDevice:\tDevice [16a1]\n\
+            Rev:\t30\n\
+            ProgIf:\t80\n\
+            Driver:\tsnd_hda_intel\n\
+            \n\
+        ";
+        assert_str_eq!(sample, view.to_string());
+        assert_str_eq!("\"a\\\"b\\\\\"", Escaped("a\"b\\").to_string());
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+# Rendering without view plumbing
+
+Text of a single device or a listing in one of lspci styles, for applications embedding
+lspci-identical output. Names are shown as in lspci without `-n`, addresses and IRQs as seen by
+OS.
+
+```rust
+# use pcitool::{access::{dump::Dump, AccessMethod}, names::Names, view::render::{self, Style}};
+let dump = Dump::init(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+)).unwrap();
+let device = dump.device("00:1f.3".parse().unwrap()).unwrap();
+let names = Names::default();
+let text = render::device_to_string(&device, Style::Lspci { verbose: 0 }, &names);
+assert!(text.starts_with("00:1f.3 "));
+```
+*/
+
+use crate::{access::Access, device::Device, names::Names};
+
+use super::lspci::{
+    basic::{self, ViewArgs},
+    machine,
+};
+
+/// Output style, `verbose` is `-v` occurrences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// `lspci`, `lspci -v`, `lspci -vv`, ...
+    Lspci { verbose: usize },
+    /// `lspci -m` if not verbose, `lspci -vmm` otherwise
+    Machine { verbose: usize },
+}
+
+/// Device text in `style`, ends with new line
+pub fn device_to_string(device: &Device, style: Style, names: &Names) -> String {
+    devices_to_string([device], style, names)
+}
+
+/// Listing of `devices` in `style`, devices are not sorted
+pub fn devices_to_string<'a>(
+    devices: impl IntoIterator<Item = &'a Device>,
+    style: Style,
+    names: &Names,
+) -> String {
+    let (Style::Lspci { verbose } | Style::Machine { verbose }) = style;
+    let args = &ViewArgs {
+        verbose,
+        kernel: false,
+        always_domain_number: false,
+        as_numbers: 0,
+        bus_centric: false,
+        show_power: false,
+        name_width: Default::default(),
+        vds: &names.vendor_device_subsystem(),
+        cc: &names.class_code(),
+        access: &Access::default(),
+    };
+    devices
+        .into_iter()
+        .map(|data| match style {
+            Style::Lspci { .. } => basic::View {
+                data: data.clone(),
+                args,
+            }
+            .to_string(),
+            Style::Machine { .. } => machine::View {
+                data,
+                args,
+                machine: if verbose > 0 { 2 } else { 1 },
+            }
+            .to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_str_eq;
+
+    #[test]
+    fn device_styles() {
+        let dump = Dump::fixture();
+        let device = dump.device("00:1f.3".parse().unwrap()).unwrap();
+        let names = Names::default();
+        let vds = &names.vendor_device_subsystem();
+        let cc = &names.class_code();
+        let access = Access::default();
+        let args = ViewArgs {
+            verbose: 2,
+            kernel: false,
+            always_domain_number: false,
+            as_numbers: 0,
+            bus_centric: false,
+            show_power: false,
+            name_width: Default::default(),
+            vds,
+            cc,
+            access: &access,
+        };
+        let sample = basic::View {
+            data: device.clone(),
+            args: &args,
+        }
+        .to_string();
+        assert_str_eq!(
+            sample,
+            device_to_string(&device, Style::Lspci { verbose: 2 }, &names)
+        );
+        let args = ViewArgs { verbose: 0, ..args };
+        let sample = machine::View {
+            data: &device,
+            args: &args,
+            machine: 1,
+        }
+        .to_string();
+        assert_str_eq!(
+            sample,
+            device_to_string(&device, Style::Machine { verbose: 0 }, &names)
+        );
+    }
+}
@@ -0,0 +1,336 @@
+/*!
+# Table view
+
+Devices in aligned columns, one device per line. Unlike lspci free-form text
+this is meant for quick interactive inspection.
+*/
+
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    device::{Device, Size},
+    names::{ClassCode, VendorDeviceSubsystem},
+    view::DisplayMultiView,
+};
+
+/// Table column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Address,
+    Class,
+    Vendor,
+    Device,
+    Driver,
+    Irq,
+    Numa,
+    /// PCI Express link speed and width
+    Link,
+    /// MSI-X vectors count
+    MsiX,
+    /// SR-IOV enabled and total Virtual Functions
+    Vfs,
+    /// Maximum PCI Express link speed and width
+    MaxLink,
+    /// Memory BAR sizes from OS resources
+    Bars,
+    /// Resizable BAR current and largest supported sizes
+    Rebar,
+    /// Paravirtual, emulated, VF or physical device
+    Virt,
+}
+
+impl Column {
+    pub const ALL: [Column; 14] = [
+        Self::Address,
+        Self::Class,
+        Self::Vendor,
+        Self::Device,
+        Self::Driver,
+        Self::Irq,
+        Self::Numa,
+        Self::Link,
+        Self::MsiX,
+        Self::Vfs,
+        Self::MaxLink,
+        Self::Bars,
+        Self::Rebar,
+        Self::Virt,
+    ];
+    /// Columns shown when none selected
+    pub const DEFAULT: [Column; 8] = [
+        Self::Address,
+        Self::Class,
+        Self::Vendor,
+        Self::Device,
+        Self::Driver,
+        Self::Irq,
+        Self::Numa,
+        Self::Link,
+    ];
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Address => "ADDRESS",
+            Self::Class => "CLASS",
+            Self::Vendor => "VENDOR",
+            Self::Device => "DEVICE",
+            Self::Driver => "DRIVER",
+            Self::Irq => "IRQ",
+            Self::Numa => "NUMA",
+            Self::Link => "LINK",
+            Self::MsiX => "MSI-X",
+            Self::Vfs => "VFS",
+            Self::MaxLink => "MAX-LINK",
+            Self::Bars => "BARS",
+            Self::Rebar => "REBAR",
+            Self::Virt => "VIRT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error(
+    "unknown column {0:?}, available: address, class, vendor, device, driver, irq, numa, link, msi-x, vfs, max-link, bars, rebar, virt"
+)]
+pub struct ParseColumnError(String);
+
+impl FromStr for Column {
+    type Err = ParseColumnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|column| column.header().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ParseColumnError(s.into()))
+    }
+}
+
+/// Devices table
+#[derive(Debug, Clone, Copy)]
+pub struct Table<'a> {
+    pub devices: &'a [Device],
+    pub columns: &'a [Column],
+    pub vds: &'a VendorDeviceSubsystem,
+    pub cc: &'a ClassCode,
+}
+
+impl Column {
+    /// Cell value, `None` if device does not have it
+    pub fn value(
+        self,
+        device: &Device,
+        vds: &VendorDeviceSubsystem,
+        cc: &ClassCode,
+    ) -> Option<String> {
+        let header = &device.header;
+        let (vendor_id, device_id) = (header.vendor_id, header.device_id);
+        let class_code = &header.class_code;
+        match self {
+            Self::Address => Some(device.address.to_string()),
+            Self::Class => Some(
+                cc.lookup(class_code.base, class_code.sub, None)
+                    .or_else(|| cc.lookup(class_code.base, None, None))
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{:02x}{:02x}", class_code.base, class_code.sub)),
+            ),
+            Self::Vendor => Some(
+                vds.lookup(vendor_id, None, None)
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{:04x}", vendor_id)),
+            ),
+            Self::Device => Some(
+                vds.lookup(vendor_id, device_id, None)
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{:04x}", device_id)),
+            ),
+            Self::Driver => device.driver_in_use.clone(),
+            Self::Irq => Some(device.irq())
+                .filter(|&irq| irq != 0)
+                .map(|irq| irq.to_string()),
+            Self::Numa => device.numa_node.map(|node| node.to_string()),
+            Self::Link => device.express_link().map(|link| {
+                format!(
+                    "{} {}",
+                    link.status.current_link_speed.display(()),
+                    link.status.negotiated_link_width.display(())
+                )
+            }),
+            Self::MsiX => device.msi_x_vectors().map(|count| count.to_string()),
+            Self::Vfs => device
+                .sr_iov()
+                .map(|sr_iov| format!("{}/{}", sr_iov.num_vfs, sr_iov.total_vfs)),
+            Self::MaxLink => device.express_link().map(|link| {
+                format!(
+                    "{} {}",
+                    link.capabilities.max_link_speed.display(()),
+                    link.capabilities.maximum_link_width.display(())
+                )
+            }),
+            Self::Bars => device.resource.as_ref().and_then(|resource| {
+                let bars = resource
+                    .entries
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, entry)| entry.flags & IORESOURCE_MEM != 0 && entry.size() > 0)
+                    .map(|(n, entry)| format!("{}:{}", n, Size(entry.size())))
+                    .collect::<Vec<_>>();
+                (!bars.is_empty()).then(|| bars.join(" "))
+            }),
+            Self::Rebar => {
+                let bars = device
+                    .resizable_bars()
+                    .iter()
+                    .map(|info| {
+                        let max = info.supported.iter().max().unwrap_or(&info.current);
+                        format!("{}:{}/{}", info.bar, info.current, max)
+                    })
+                    .collect::<Vec<_>>();
+                (!bars.is_empty()).then(|| bars.join(" "))
+            }
+            Self::Virt => Some(device.virtualization().to_string()),
+        }
+    }
+}
+
+impl Table<'_> {
+    /// Missing values shown as `-`
+    fn cell(&self, device: &Device, column: Column) -> String {
+        column
+            .value(device, self.vds, self.cc)
+            .unwrap_or_else(|| "-".into())
+    }
+}
+
+/// Linux `IORESOURCE_MEM` resource flag
+const IORESOURCE_MEM: u64 = 0x200;
+
+impl fmt::Display for Table<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = self
+            .columns
+            .iter()
+            .map(|c| c.header().to_string())
+            .collect();
+        let rows = self
+            .devices
+            .iter()
+            .map(|device| {
+                self.columns
+                    .iter()
+                    .map(|&column| self.cell(device, column))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let rows = [header].into_iter().chain(rows).collect::<Vec<Vec<_>>>();
+        let mut widths = vec![0; self.columns.len()];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+        for row in &rows {
+            let last = row.len().saturating_sub(1);
+            for (n, (cell, width)) in row.iter().zip(&widths).enumerate() {
+                if n == last {
+                    writeln!(f, "{}", cell)?;
+                } else {
+                    write!(f, "{:width$}  ", cell, width = width)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_column() {
+        assert_eq!(Ok(Column::Numa), "numa".parse());
+        assert_eq!(Ok(Column::Irq), "IRQ".parse());
+        assert_eq!(Err(ParseColumnError("bus".into())), "bus".parse::<Column>());
+    }
+
+    #[test]
+    fn display_table() {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices
+            .retain(|d| ["0000:00:1f.3", "0000:03:00.0"].contains(&d.address.to_string().as_str()));
+        let mut vds = VendorDeviceSubsystem::default();
+        vds.0.insert(
+            crate::names::VdsKey::Vendor(0x8086),
+            "Intel Corporation".into(),
+        );
+        let table = Table {
+            devices: &devices,
+            columns: &[
+                Column::Address,
+                Column::Vendor,
+                Column::Device,
+                Column::Link,
+            ],
+            vds: &vds,
+            cc: &Default::default(),
+        };
+        let sample = "\
+            ADDRESS       VENDOR             DEVICE  LINK\n\
+            0000:00:1f.3  Intel Corporation  9dc8    -\n\
+            0000:03:00.0  10ec               522a    2.5GT/s x1\n\
+        ";
+        assert_eq!(sample, table.to_string());
+    }
+
+    #[test]
+    fn interrupts_and_virtual_functions() {
+        let dump = Dump::fixture();
+        let device = dump.device("04:00.0".parse().unwrap()).unwrap();
+        let table = Table {
+            devices: &[device],
+            columns: &[Column::Address, Column::MsiX, Column::Vfs, Column::Virt],
+            vds: &Default::default(),
+            cc: &Default::default(),
+        };
+        let sample = "\
+            ADDRESS       MSI-X  VFS  VIRT\n\
+            0000:04:00.0  32     -    physical\n\
+        ";
+        assert_eq!(sample, table.to_string());
+        assert_eq!(Ok(Column::MsiX), "msi-x".parse());
+    }
+
+    #[test]
+    fn memory_bars() {
+        let dump = Dump::fixture();
+        let mut device = dump.device("02:00.0".parse().unwrap()).unwrap();
+        device.resource = Some(
+            "0x00000000ec000000 0x00000000ecffffff 0x0000000000040200\n\
+            0x00000000c0000000 0x00000000cfffffff 0x000000000014220c\n\
+            0x0000000000000000 0x0000000000000000 0x0000000000000000\n\
+            0x0000000000000000 0x0000000000000000 0x0000000000000000\n\
+            0x0000000000000000 0x0000000000000000 0x0000000000000000\n\
+            0x000000000000e000 0x000000000000e07f 0x0000000000040101\n\
+            0x0000000000000000 0x0000000000000000 0x0000000000000000\n"
+                .parse()
+                .unwrap(),
+        );
+        let table = Table {
+            devices: &[device],
+            columns: &[Column::MaxLink, Column::Bars, Column::Rebar],
+            vds: &Default::default(),
+            cc: &Default::default(),
+        };
+        let sample = "\
+            MAX-LINK  BARS          REBAR\n\
+            8GT/s x4  0:16M 1:256M  -\n\
+        ";
+        assert_eq!(sample, table.to_string());
+        assert_eq!("1536K", Size(0x18_0000).to_string());
+    }
+}
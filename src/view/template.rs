@@ -0,0 +1,258 @@
+/*!
+# Format templates
+
+One line per device from a template with `{field}` placeholders, `{{` and `}}` are literal
+braces. Missing values are shown as `-`, like in [table](super::table) view.
+
+```rust
+# use pcitool::{access::{dump::Dump, AccessMethod}, view::template::Template};
+let dump = Dump::init(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+)).unwrap();
+let device = dump.device("03:00.0".parse().unwrap()).unwrap();
+let template: Template = "{address} {vendor_id}:{device_id} {link_speed}".parse().unwrap();
+let line = template.render(&device, &Default::default(), &Default::default());
+assert_eq!("0000:03:00.0 10ec:522a 2.5GT/s", line);
+```
+*/
+
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    device::Device,
+    names::{ClassCode, VendorDeviceSubsystem},
+    view::{table::Column, DisplayMultiView},
+};
+
+/// Template placeholder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Table column value
+    Column(Column),
+    VendorId,
+    DeviceId,
+    /// Base class and sub class
+    ClassId,
+    Revision,
+    /// Kernel modules capable of handling device, comma separated
+    Modules,
+    /// Current PCI Express link speed
+    LinkSpeed,
+    /// Negotiated PCI Express link width
+    LinkWidth,
+    MaxLinkSpeed,
+    MaxLinkWidth,
+}
+
+impl Field {
+    pub const ALL: [(&'static str, Field); 22] = [
+        ("address", Self::Column(Column::Address)),
+        ("vendor_id", Self::VendorId),
+        ("device_id", Self::DeviceId),
+        ("class_id", Self::ClassId),
+        ("revision", Self::Revision),
+        ("vendor_name", Self::Column(Column::Vendor)),
+        ("device_name", Self::Column(Column::Device)),
+        ("class_name", Self::Column(Column::Class)),
+        ("driver", Self::Column(Column::Driver)),
+        ("modules", Self::Modules),
+        ("irq", Self::Column(Column::Irq)),
+        ("numa", Self::Column(Column::Numa)),
+        ("link", Self::Column(Column::Link)),
+        ("link_speed", Self::LinkSpeed),
+        ("link_width", Self::LinkWidth),
+        ("max_link", Self::Column(Column::MaxLink)),
+        ("max_link_speed", Self::MaxLinkSpeed),
+        ("max_link_width", Self::MaxLinkWidth),
+        ("msi_x", Self::Column(Column::MsiX)),
+        ("vfs", Self::Column(Column::Vfs)),
+        ("bars", Self::Column(Column::Bars)),
+        ("virt", Self::Column(Column::Virt)),
+    ];
+    pub fn value(
+        self,
+        device: &Device,
+        vds: &VendorDeviceSubsystem,
+        cc: &ClassCode,
+    ) -> Option<String> {
+        let header = &device.header;
+        match self {
+            Self::Column(column) => column.value(device, vds, cc),
+            Self::VendorId => Some(format!("{:04x}", header.vendor_id)),
+            Self::DeviceId => Some(format!("{:04x}", header.device_id)),
+            Self::ClassId => Some(format!(
+                "{:02x}{:02x}",
+                header.class_code.base, header.class_code.sub
+            )),
+            Self::Revision => Some(format!("{:02x}", header.revision_id)),
+            Self::Modules => device
+                .kernel_modules
+                .as_ref()
+                .filter(|modules| !modules.is_empty())
+                .map(|modules| modules.join(",")),
+            Self::LinkSpeed => device
+                .express_link()
+                .map(|link| link.status.current_link_speed.display(()).to_string()),
+            Self::LinkWidth => device
+                .express_link()
+                .map(|link| link.status.negotiated_link_width.display(()).to_string()),
+            Self::MaxLinkSpeed => device
+                .express_link()
+                .map(|link| link.capabilities.max_link_speed.display(()).to_string()),
+            Self::MaxLinkWidth => device
+                .express_link()
+                .map(|link| link.capabilities.maximum_link_width.display(()).to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseTemplateError {
+    #[error(
+        "unknown field {{{0}}}, available: {available}",
+        available = Field::ALL.map(|(name, _)| name).join(", ")
+    )]
+    UnknownField(String),
+    #[error("unclosed {{ at {0}")]
+    Unclosed(usize),
+    #[error("unmatched }} at {0}, use }}}} for literal brace")]
+    Unmatched(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+/// Parsed format template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Segment>);
+
+impl FromStr for Template {
+    type Err = ParseTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = s.char_indices().peekable();
+        while let Some((n, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let end = s[n..].find('}').ok_or(ParseTemplateError::Unclosed(n))?;
+                    let name = &s[n + 1..n + end];
+                    let (_, field) = Field::ALL
+                        .into_iter()
+                        .find(|(field, _)| *field == name)
+                        .ok_or_else(|| ParseTemplateError::UnknownField(name.into()))?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                    while chars.next_if(|&(i, _)| i <= n + end).is_some() {}
+                }
+                '}' => return Err(ParseTemplateError::Unmatched(n)),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl Template {
+    /// Device line without trailing new line
+    pub fn render(&self, device: &Device, vds: &VendorDeviceSubsystem, cc: &ClassCode) -> String {
+        self.0
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.clone(),
+                Segment::Field(field) => field.value(device, vds, cc).unwrap_or_else(|| "-".into()),
+            })
+            .collect()
+    }
+}
+
+/// Devices rendered with template, one per line
+#[derive(Debug, Clone, Copy)]
+pub struct Formatted<'a> {
+    pub devices: &'a [Device],
+    pub template: &'a Template,
+    pub vds: &'a VendorDeviceSubsystem,
+    pub cc: &'a ClassCode,
+}
+
+impl fmt::Display for Formatted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for device in self.devices {
+            writeln!(f, "{}", self.template.render(device, self.vds, self.cc))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::access::{dump::Dump, AccessMethod};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_template() {
+        let result = "{{{address}}} {driver}".parse::<Template>().unwrap();
+        let sample = Template(vec![
+            Segment::Text("{".into()),
+            Segment::Field(Field::Column(Column::Address)),
+            Segment::Text("} ".into()),
+            Segment::Field(Field::Column(Column::Driver)),
+        ]);
+        assert_eq!(sample, result);
+        assert_eq!(
+            Err(ParseTemplateError::UnknownField("bus".into())),
+            "{address} {bus}".parse::<Template>()
+        );
+        assert_eq!(
+            Err(ParseTemplateError::Unclosed(3)),
+            "{{ {address".parse::<Template>()
+        );
+        assert_eq!(
+            Err(ParseTemplateError::Unmatched(9)),
+            "{address}}".parse::<Template>()
+        );
+    }
+
+    #[test]
+    fn formatted() {
+        let dump = Dump::fixture();
+        let mut devices = dump.iter().filter_map(Result::ok).collect::<Vec<_>>();
+        devices.sort();
+        devices
+            .retain(|d| ["0000:00:1f.3", "0000:02:00.0"].contains(&d.address.to_string().as_str()));
+        let mut vds = VendorDeviceSubsystem::default();
+        vds.0.insert(
+            crate::names::VdsKey::Vendor(0x8086),
+            "Intel Corporation".into(),
+        );
+        let template = "{address}\t{vendor_name}\t{class_id}\t{link_width}/{max_link_width}"
+            .parse()
+            .unwrap();
+        let formatted = Formatted {
+            devices: &devices,
+            template: &template,
+            vds: &vds,
+            cc: &Default::default(),
+        };
+        let sample = "\
+            0000:00:1f.3\tIntel Corporation\t0403\t-/-\n\
+            0000:02:00.0\t10de\t0302\tx4/x4\n\
+        ";
+        assert_eq!(sample, formatted.to_string());
+    }
+}
@@ -8,6 +8,7 @@
 use pretty_assertions::assert_str_eq;
 
 use std::ffi::OsStr;
+use std::fs::File;
 use std::process::Command;
 use std::process::Stdio;
 
@@ -122,4 +123,34 @@ pub(crate) fn compare_exe_outputs(lspci_path: impl AsRef<OsStr>, args_str: &str,
     }
 
     assert_str_eq!(lspci_out, pci_ls_out, "STDOUT");
-}
\ No newline at end of file
+}
+
+/// Same as [compare_exe_outputs] with `input` file on stdin, lspci reads it as `-F /dev/stdin`
+/// while `pci list` gets `-F -`
+#[allow(dead_code)]
+pub(crate) fn compare_stdin_outputs(lspci_path: impl AsRef<OsStr>, args_str: &str, input: &str) {
+    let args: Vec<&str> = args_str.split_whitespace().collect();
+    let stdin = || Stdio::from(File::open(input).expect("failed to open input"));
+    let lspci = Command::new(lspci_path)
+        .args(["-F", "/dev/stdin"])
+        .args(&args)
+        .stdin(stdin())
+        .output()
+        .expect("failed to execute lspci");
+    let pci_ls = Command::new(PCI_BIN_PATH)
+        .args(["list", "-F", "-"])
+        .args(&args)
+        .stdin(stdin())
+        .output()
+        .expect("failed to execute `pci list`");
+    assert_str_eq!(
+        String::from_utf8_lossy(&lspci.stderr),
+        String::from_utf8_lossy(&pci_ls.stderr),
+        "STDERR"
+    );
+    assert_str_eq!(
+        String::from_utf8_lossy(&lspci.stdout),
+        String::from_utf8_lossy(&pci_ls.stdout),
+        "STDOUT"
+    );
+}
@@ -1,7 +1,7 @@
 #![cfg(target_os = "linux")]
 
 mod common;
-use common::{compare_exe_outputs, LSPCI_MUSL_PATH};
+use common::{compare_exe_outputs, compare_stdin_outputs, LSPCI_MUSL_PATH};
 
 const PCI_IDS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids");
 
@@ -34,6 +34,14 @@ user_dump_multiple_args! {
     args_xxx:       "xxx", "",
     args_xxx_n:     "xxx", "-n",
     args_xxx_nn:    "xxx", "-nn",
+    args_xxxx:       "xxxx", "",
+    args_xxxx_n:     "xxxx", "-n",
+    args_xxxx_nn:    "xxxx", "-nn",
+}
+
+// lspci 3.7.0 shows no subsystem of bridges
+#[cfg(not(feature = "ls_bridge_subsystem"))]
+user_dump_multiple_args! {
     args_xxx_v:     "xxx", "-v",
     args_xxx_vv:    "xxx", "-vv",
     args_xxx_vvv:   "xxx", "-vvv",
@@ -43,9 +51,6 @@ user_dump_multiple_args! {
     args_xxx_nnv:   "xxx", "-nnv",
     args_xxx_nnvv:  "xxx", "-nnvv",
     args_xxx_nnvvv: "xxx", "-nnvvv",
-    args_xxxx:       "xxxx", "",
-    args_xxxx_n:     "xxxx", "-n",
-    args_xxxx_nn:    "xxxx", "-nn",
     args_xxxx_v:     "xxxx", "-v",
     // This test cover most of issues
     args_xxxx_vv:    "xxxx", "-vv",
@@ -58,6 +63,8 @@ user_dump_multiple_args! {
     args_xxxx_nnvvv: "xxxx", "-nnvvv",
 }
 
+// lspci 3.7.0 shows no subsystem of bridges
+#[cfg(not(feature = "ls_bridge_subsystem"))]
 macro_rules! machines {
     ($($fname:ident: $machine:expr,)*) => {
         $(
@@ -75,12 +82,271 @@ macro_rules! machines {
     }
 }
 
+#[cfg(not(feature = "ls_bridge_subsystem"))]
 machines! {
     machine_ec8a5fc: "ec8a5fc",
     machine_02daadc: "02daadc",
     machine_23c7a39: "23c7a39",
 }
 
+macro_rules! machine_readable {
+    ($($fname:ident: $machine:expr, $x:expr, $args:expr,)*) => {
+        $(
+            #[test]
+            fn $fname() {
+                compare_exe_outputs(LSPCI_MUSL_PATH, &format!(
+                    "-F {}/tests/data/machine/{}/out.{}.txt {} -i {}",
+                    env!("CARGO_MANIFEST_DIR"),
+                    $machine,
+                    $x,
+                    $args,
+                    PCI_IDS_PATH,
+                ), true);
+            }
+        )*
+    }
+}
+
+machine_readable! {
+    args_m_362f18e:      "362f18e", "xxx", "-m",
+    args_nm_362f18e:     "362f18e", "xxx", "-nm",
+    args_nnmm_362f18e:   "362f18e", "xxx", "-nnmm",
+    args_vm_362f18e:     "362f18e", "xxx", "-vm",
+    args_vmm_362f18e:    "362f18e", "xxx", "-vmm",
+    args_nvvmm_362f18e:  "362f18e", "xxx", "-nvvmm",
+    args_nnvmm_362f18e:  "362f18e", "xxx", "-nnvmm",
+    args_m_ec8a5fc:      "ec8a5fc", "xxx", "-m",
+    args_vmm_ec8a5fc:    "ec8a5fc", "xxx", "-vmm",
+    args_m_02daadc:      "02daadc", "xxxx", "-m",
+    args_vmm_02daadc:    "02daadc", "xxxx", "-vmm",
+    args_m_23c7a39:      "23c7a39", "xxxx", "-m",
+    args_vmm_23c7a39:    "23c7a39", "xxxx", "-vmm",
+}
+
+macro_rules! stdin_dump {
+    ($($fname:ident: $machine:expr, $x:expr, $args:expr,)*) => {
+        $(
+            #[test]
+            fn $fname() {
+                let dump = format!(
+                    "{}/tests/data/machine/{}/out.{}.txt",
+                    env!("CARGO_MANIFEST_DIR"),
+                    $machine,
+                    $x,
+                );
+                compare_stdin_outputs(
+                    LSPCI_MUSL_PATH,
+                    &format!("{} -i {}", $args, PCI_IDS_PATH),
+                    &dump,
+                );
+            }
+        )*
+    }
+}
+
+stdin_dump! {
+    stdin_x_nn_362f18e:       "362f18e", "x", "-nn",
+    stdin_xxx_vmm_362f18e:    "362f18e", "xxx", "-vmm",
+}
+
+#[cfg(not(feature = "ls_bridge_subsystem"))]
+stdin_dump! {
+    stdin_xxxx_nnvvv_23c7a39: "23c7a39", "xxxx", "-nnvvv",
+}
+
+#[test]
+fn debug_access() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["list", "--debug-access", "-O", "probe.order=void", "-F"])
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/machine/362f18e/out.x.txt"
+        ))
+        .output()
+        .unwrap();
+    let report = "access: void: found 0 devices\naccess: selected dump (-F), 25 devices\n";
+    assert_eq!(report, String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn persistent_id() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["id", "03:00.0", "-F"])
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+        ))
+        .output()
+        .unwrap();
+    let id = String::from_utf8_lossy(&output.stdout);
+    assert!(id.starts_with("0000:00:"), "{}", id);
+    assert!(
+        id.ends_with("/00.0+dsn-00-00-00-01-00-4c-e0-00\n"),
+        "{}",
+        id
+    );
+}
+
+#[test]
+fn compiled_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let index = dir.path().join("pci.ids.idx");
+    let pci_ids = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["ids", "compile", "-i", pci_ids, "-o"])
+        .arg(&index)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let list = |ids: &std::path::Path| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+            .args(["list", "-vnn", "-F"])
+            .arg(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+            ))
+            .arg("-i")
+            .arg(ids)
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let parsed = list(pci_ids.as_ref());
+    assert!(parsed.contains("Intel Corporation"));
+    assert_eq!(parsed, list(&index));
+}
+
+#[test]
+fn vga_route() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["vga-route", "-F"])
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/machine/ec8a5fc/out.xxxx.txt"
+        ))
+        .arg("-i")
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids"))
+        .output()
+        .unwrap();
+    let sample = "\
+        0000:03:00.0 Matrox Electronics Systems Ltd. Integrated Matrox G200eW3 Graphics Controller\n\
+        \tRoute: 0000:00:1c.4 [VGA+ VGA16+] -> 0000:02:00.0 [VGA+ VGA16+] -> 0000:03:00.0\n\
+        \tLegacy VGA: routed\n\
+        Boot VGA: 0000:03:00.0 (legacy VGA routed and decoded)\n\
+    ";
+    assert_eq!(sample, String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn svm_check() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["svm-check", "00:02.0", "-F"])
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+        ))
+        .output()
+        .unwrap();
+    let report = String::from_utf8_lossy(&output.stdout);
+    assert!(report.contains("\tPASID: Exec- Priv- Max PASID Width 20, Enabled-\n"));
+    // Dumps have no IOMMU groups
+    assert!(
+        report.ends_with("SVM does not work: not in an IOMMU group\n"),
+        "{}",
+        report
+    );
+    assert_eq!(Some(1), output.status.code());
+}
+
+#[cfg(feature = "margining")]
+#[test]
+fn margin_unknown_driver() {
+    // Dumps do not report drivers and the device is missing in sysfs
+    let dir = tempfile::tempdir().unwrap();
+    let margin = |allow_bound: bool| {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_pci"));
+        cmd.args(["margin", "00:1d.0", "-F"])
+            .arg(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+            ))
+            .arg("-O")
+            .arg(format!("sysfs.path={}", dir.path().display()));
+        if allow_bound {
+            cmd.arg("--allow-bound");
+        }
+        let output = cmd.output().unwrap();
+        assert_eq!(Some(1), output.status.code());
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+    assert_eq!(
+        "0000:00:1d.0: driver is unknown without sysfs, use --allow-bound to write anyway\n",
+        margin(false)
+    );
+    assert!(!margin(true).contains("--allow-bound"));
+}
+
+#[test]
+fn caps_list() {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["caps", "04:00.0", "--list", "-F"])
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/data/device/8086:9dc8/out.vvvxxxx.txt"
+        ))
+        .output()
+        .unwrap();
+    let list = String::from_utf8_lossy(&output.stdout);
+    let lines = list.lines().collect::<Vec<_>>();
+    assert_eq!(8, lines.len());
+    assert_eq!(Some(&"040\t10\t-\tCAP_EXP"), lines.first());
+    assert_eq!(Some(&"400\t001e\t1\tECAP_L1PM"), lines.last());
+}
+
+#[test]
+fn ari_chain() {
+    // Functions 0, 1 and 9 on bus 01 chained by ARI Next Function Numbers
+    let dump: String = [(0x00, 0x01), (0x01, 0x09), (0x09, 0x00)]
+        .iter()
+        .map(|&(devfn, next)| {
+            let mut cs = [0u8; 4096];
+            cs[0x06] = 0x10;
+            cs[0x34] = 0x40;
+            cs[0x40..0x44].copy_from_slice(&[0x10, 0x00, 0x02, 0x00]);
+            cs[0x100..0x108].copy_from_slice(&[0x0e, 0x00, 0x01, 0x00, 0x00, next, 0x00, 0x00]);
+            let body = cs
+                .chunks_exact(16)
+                .enumerate()
+                .map(|(n, line)| {
+                    let bytes: String = line.iter().map(|b| format!(" {:02x}", b)).collect();
+                    format!("{:x}0:{}\n", n, bytes)
+                })
+                .collect::<String>();
+            format!("01:{:02x}.{:x} _\n{}\n", devfn >> 3, devfn & 7, body)
+        })
+        .collect();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(file.path(), dump).unwrap();
+    let caps = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+            .args(["caps", "01:01.1", "-F"])
+            .arg(file.path())
+            .args(args)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    assert!(caps(&[]).contains("100\t000e\t1\tECAP_ARI\nARI chain: 0 -> 1 -> 9\n"));
+    assert!(!caps(&["--list"]).contains("ARI chain"));
+    // lspci never prints the chain
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["list", "-vvvv", "-s", "01:01.1", "-F"])
+        .arg(file.path())
+        .output()
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("chain"));
+}
+
 #[cfg(test)]
 mod fuzzing {
     use super::*;
@@ -0,0 +1,53 @@
+use std::{fs, path::Path};
+
+use pcitool::{
+    access::{dump::Dump, AccessMethod},
+    device::{encode::ToBytes, Address, ConfigurationSpace},
+};
+use pretty_assertions::assert_eq;
+
+const DATA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+
+/// Capabilities Pointer low bits are dropped by decoder
+fn assert_round_trip(name: &str, raw: &[u8], encoded: &[u8]) {
+    let mut raw = raw.to_vec();
+    raw[0x34] &= !0b11;
+    let line = |bytes: &[u8], n: usize| format!("{}: {:03x}: {:02x?}", name, n * 16, bytes);
+    let raw = raw.chunks(16).enumerate().map(|(n, b)| line(b, n));
+    let encoded = encoded.chunks(16).enumerate().map(|(n, b)| line(b, n));
+    assert_eq!(raw.collect::<Vec<_>>(), encoded.collect::<Vec<_>>());
+}
+
+/// Header is encoded, space past it is copied as is
+#[test]
+fn config_files_round_trip() {
+    for dir in ["device", "fixtures"] {
+        for entry in fs::read_dir(Path::new(DATA_PATH).join(dir)).unwrap() {
+            let path = entry.unwrap().path().join("config");
+            let Ok(raw) = fs::read(&path) else { continue };
+            let cs: ConfigurationSpace = raw.as_slice().try_into().unwrap();
+            let encoded = cs.to_bytes();
+            assert_eq!(raw.len(), encoded.len());
+            assert_round_trip(&path.display().to_string(), &raw, &encoded);
+            let device = cs.device(Address::default());
+            assert_eq!(encoded, device.to_bytes());
+        }
+    }
+}
+
+#[test]
+fn dumps_round_trip() {
+    let mut dumps = vec![Path::new(DATA_PATH).join("device/8086:9dc8/out.vvvxxxx.txt")];
+    for entry in fs::read_dir(Path::new(DATA_PATH).join("machine")).unwrap() {
+        dumps.push(entry.unwrap().path().join("out.xxxx.txt"));
+    }
+    for path in dumps {
+        let dump = Dump::init(&path).unwrap();
+        for device in dump.iter().map(Result::unwrap) {
+            let encoded = device.to_bytes();
+            let raw = dump.read(device.address.clone(), 0, encoded.len()).unwrap();
+            let name = format!("{} {}", path.display(), device.address);
+            assert_round_trip(&name, &raw, &encoded);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! C program built against `include/pcitool.h` links with the cdylib and lists a dump
+
+// Shared library name and LD_LIBRARY_PATH lookup
+#![cfg(target_os = "linux")]
+
+use std::{env, path::Path, process::Command};
+
+#[test]
+fn c_program_links() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ffi");
+    // cargo test builds only the rlib, separate target directory keeps the build lock free
+    let output = Command::new(env!("CARGO"))
+        .args(["build", "--lib", "--features", "ffi", "--manifest-path"])
+        .arg(root.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&tmp)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "cdylib build failed: {}", stderr);
+    let lib_dir = tmp.join("debug");
+
+    let exe = tmp.join("list");
+    let output = Command::new(env::var("CC").unwrap_or_else(|_| "cc".into()))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg(root.join("tests/ffi/list.c"))
+        .arg("-o")
+        .arg(&exe)
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-lpcitool")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "C program build failed: {}",
+        stderr
+    );
+
+    let output = Command::new(&exe)
+        .arg(root.join("tests/data/device/8086:9dc8/out.vxxx.txt"))
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        "0000:00:1f.3 8086:9dc8 01 09 05\n",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
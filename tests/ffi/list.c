@@ -0,0 +1,38 @@
+/* Lists devices of a dump through the C interface: address, ids and capabilities */
+
+#include <stdio.h>
+
+#include "pcitool.h"
+
+int main(int argc, char **argv)
+{
+	pci_access *access;
+	pci_iter *iter;
+	pci_device *device;
+
+	if (argc != 2)
+		return 2;
+	access = pci_access_init_dump(argv[1]);
+	if (!access)
+		return 1;
+	iter = pci_iter_new(access);
+	while ((device = pci_iter_next(iter))) {
+		char address[16];
+		uint32_t vendor, id;
+		uint8_t caps[48];
+		int i, n;
+
+		pci_device_address(device, address, sizeof(address));
+		pci_device_field(device, PCI_FIELD_VENDOR_ID, &vendor);
+		pci_device_field(device, PCI_FIELD_DEVICE_ID, &id);
+		printf("%s %04x:%04x", address, vendor, id);
+		n = pci_device_capabilities(device, caps, sizeof(caps));
+		for (i = 0; i < n; i++)
+			printf(" %02x", caps[i]);
+		printf("\n");
+		pci_device_free(device);
+	}
+	pci_iter_free(iter);
+	pci_access_free(access);
+	return 0;
+}
@@ -0,0 +1,14 @@
+use pcitool::{names::Names, testing};
+
+#[test]
+fn device_fixtures() {
+    let names =
+        Names::init_pciids(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/pci.ids")).unwrap();
+    let mismatches = testing::check_dir(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/fixtures"),
+        &names,
+    )
+    .unwrap();
+    let report = mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+    assert!(report.is_empty(), "{:#?}", report);
+}
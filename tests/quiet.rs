@@ -0,0 +1,71 @@
+//! Library must not write to stdout or stderr, diagnostics go through `tracing`
+
+use std::{fs, path::Path, process::Command};
+
+/// Verbose, machine readable and structured views
+const ARGS: [&[&str]; 3] = [
+    &["list", "-vvvvkxxxx"],
+    &["list", "-vmm"],
+    &["list", "--json"],
+];
+
+/// Every dump fixture decoded at the highest verbosity leaves stderr empty
+#[test]
+fn library_output() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let mut found = Vec::new();
+    for dir in ["device", "machine"] {
+        for entry in fs::read_dir(data.join(dir)).unwrap() {
+            let dir = entry.unwrap().path();
+            for entry in fs::read_dir(&dir).unwrap() {
+                let path = entry.unwrap().path();
+                let is_dump = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("out.") && name.ends_with(".txt"));
+                if !is_dump {
+                    continue;
+                }
+                for args in ARGS {
+                    let output = Command::new(env!("CARGO_BIN_EXE_pci"))
+                        .args(args)
+                        .arg("-F")
+                        .arg(&path)
+                        .output()
+                        .unwrap();
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    if !stderr.is_empty() {
+                        found.push(format!("{} {:?}: {}", path.display(), args, stderr));
+                    }
+                }
+            }
+        }
+    }
+    assert!(found.is_empty(), "{:#?}", found);
+}
+
+/// Sysfs listing with kernel modules looked up in modules.alias leaves stderr empty
+#[test]
+fn sysfs_modules_alias() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/device/8086:9dc8");
+    let dir = tempfile::tempdir().unwrap();
+    let device = dir.path().join("devices/0000:00:1f.3");
+    fs::create_dir_all(&device).unwrap();
+    for file in ["config", "modalias"] {
+        fs::copy(data.join(file), device.join(file)).unwrap();
+    }
+    let modules_alias = dir.path().join("modules.alias");
+    let alias = "alias pci:v00008086d00009DC8sv*sd*bc04sc03i80* snd_hda_intel\n";
+    fs::write(&modules_alias, alias).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_pci"))
+        .args(["list", "-vvvk", "-A", "linux-sysfs", "-O"])
+        .arg(format!("sysfs.path={}", dir.path().display()))
+        .arg("-p")
+        .arg(&modules_alias)
+        .output()
+        .unwrap();
+    assert_eq!("", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let modules = "\tKernel modules: snd_hda_intel\n";
+    assert!(stdout.contains(modules), "{}", stdout);
+}